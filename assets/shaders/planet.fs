@@ -0,0 +1,704 @@
+#version 330
+// planet.fs
+// Fragment stage of the GPU backend: GLSL ports of the lighting in triangle.rs/shadow.rs
+// and of the procedural shaders in shader_system.rs. The noise is the same family
+// (simplex, fBm, Worley) but not the same permutation, so patterns match in look, not
+// pixel for pixel
+
+in vec3 modelPosition;
+in vec3 worldPosition;
+in vec3 worldNormal;
+
+out vec4 finalColor;
+
+// Must match MAX_LIGHTS and MAX_OCCLUDERS in gpu.rs and MAX_ERUPTIONS in volcano.rs
+#define MAX_LIGHTS 4
+#define MAX_OCCLUDERS 16
+#define MAX_ERUPTIONS 8
+
+uniform float time;
+/// 0 star, 1 rocky, 2 gas giant, 3 lava, 4 ice, 5 cloud, 6 rings
+uniform int shaderType;
+uniform int emissive;
+/// Per-body offset into the noise domain, standing in for the seed
+uniform vec3 seedOffset;
+/// Blinn-Phong material of the body
+uniform vec3 materialDiffuse;
+uniform float materialSpecular;
+uniform float materialShininess;
+uniform int materialTwoSided;
+uniform vec3 cameraPosition;
+/// Unit direction from the body toward its main light (light.rs main_light_direction)
+uniform vec3 lightDirection;
+/// Atmosphere rim (atmosphere.rs); strength 0 for bodies without air
+uniform vec3 atmosphereDay;
+uniform vec3 atmosphereSunset;
+uniform float atmosphereFalloff;
+uniform float atmosphereStrength;
+/// Aurora (aurora.rs); strength 0 for bodies without one
+uniform vec3 auroraColor;
+uniform vec3 auroraFringe;
+uniform float auroraLatitude;
+uniform float auroraWidth;
+uniform float auroraStrength;
+uniform float auroraSpeed;
+/// Translucent bodies (fragment.rs BlendMode) cover by opacity times the shader's coverage
+uniform int translucent;
+uniform float opacity;
+
+uniform int lightCount;
+uniform vec3 lightPosition[MAX_LIGHTS];
+uniform vec3 lightColor[MAX_LIGHTS];
+uniform float lightIntensity[MAX_LIGHTS];
+uniform float lightRadius[MAX_LIGHTS];
+uniform float lightAmbient[MAX_LIGHTS];
+uniform float lightFalloff[MAX_LIGHTS];
+
+/// Great storm of the body (storm.rs); radius 0 for bodies without one
+uniform float vortexLatitude;
+uniform float vortexLongitude;
+uniform float vortexRadius;
+uniform float vortexStretch;
+uniform float vortexSpin;
+uniform vec3 vortexColor;
+/// Eruptions of the body (volcano.rs): vent direction in model space and heat
+uniform int eruptionCount;
+uniform vec4 eruptions[MAX_ERUPTIONS];
+
+uniform int occluderCount;
+/// Center in xyz, radius in w
+uniform vec4 occluders[MAX_OCCLUDERS];
+
+// ---------------------------------------------------------------------------------------
+// Simplex noise in 3D and 4D by Ian McEwan and Stefan Gustavson (webgl-noise, MIT license)
+
+vec3 mod289(vec3 x) { return x - floor(x * (1.0 / 289.0)) * 289.0; }
+vec4 mod289(vec4 x) { return x - floor(x * (1.0 / 289.0)) * 289.0; }
+float mod289(float x) { return x - floor(x * (1.0 / 289.0)) * 289.0; }
+vec4 permute(vec4 x) { return mod289(((x * 34.0) + 1.0) * x); }
+float permute(float x) { return mod289(((x * 34.0) + 1.0) * x); }
+vec4 taylorInvSqrt(vec4 r) { return 1.79284291400159 - 0.85373472095314 * r; }
+float taylorInvSqrt(float r) { return 1.79284291400159 - 0.85373472095314 * r; }
+
+float snoise(vec3 v) {
+    const vec2 C = vec2(1.0 / 6.0, 1.0 / 3.0);
+    const vec4 D = vec4(0.0, 0.5, 1.0, 2.0);
+
+    vec3 i = floor(v + dot(v, C.yyy));
+    vec3 x0 = v - i + dot(i, C.xxx);
+
+    vec3 g = step(x0.yzx, x0.xyz);
+    vec3 l = 1.0 - g;
+    vec3 i1 = min(g.xyz, l.zxy);
+    vec3 i2 = max(g.xyz, l.zxy);
+
+    vec3 x1 = x0 - i1 + C.xxx;
+    vec3 x2 = x0 - i2 + C.yyy;
+    vec3 x3 = x0 - D.yyy;
+
+    i = mod289(i);
+    vec4 p = permute(permute(permute(
+                i.z + vec4(0.0, i1.z, i2.z, 1.0))
+              + i.y + vec4(0.0, i1.y, i2.y, 1.0))
+              + i.x + vec4(0.0, i1.x, i2.x, 1.0));
+
+    float n_ = 0.142857142857;
+    vec3 ns = n_ * D.wyz - D.xzx;
+
+    vec4 j = p - 49.0 * floor(p * ns.z * ns.z);
+    vec4 x_ = floor(j * ns.z);
+    vec4 y_ = floor(j - 7.0 * x_);
+
+    vec4 x = x_ * ns.x + ns.yyyy;
+    vec4 y = y_ * ns.x + ns.yyyy;
+    vec4 h = 1.0 - abs(x) - abs(y);
+
+    vec4 b0 = vec4(x.xy, y.xy);
+    vec4 b1 = vec4(x.zw, y.zw);
+    vec4 s0 = floor(b0) * 2.0 + 1.0;
+    vec4 s1 = floor(b1) * 2.0 + 1.0;
+    vec4 sh = -step(h, vec4(0.0));
+
+    vec4 a0 = b0.xzyw + s0.xzyw * sh.xxyy;
+    vec4 a1 = b1.xzyw + s1.xzyw * sh.zzww;
+
+    vec3 p0 = vec3(a0.xy, h.x);
+    vec3 p1 = vec3(a0.zw, h.y);
+    vec3 p2 = vec3(a1.xy, h.z);
+    vec3 p3 = vec3(a1.zw, h.w);
+
+    vec4 norm = taylorInvSqrt(vec4(dot(p0, p0), dot(p1, p1), dot(p2, p2), dot(p3, p3)));
+    p0 *= norm.x;
+    p1 *= norm.y;
+    p2 *= norm.z;
+    p3 *= norm.w;
+
+    vec4 m = max(0.6 - vec4(dot(x0, x0), dot(x1, x1), dot(x2, x2), dot(x3, x3)), 0.0);
+    m = m * m;
+    return 42.0 * dot(m * m, vec4(dot(p0, x0), dot(p1, x1), dot(p2, x2), dot(p3, x3)));
+}
+
+vec4 grad4(float j, vec4 ip) {
+    const vec4 ones = vec4(1.0, 1.0, 1.0, -1.0);
+    vec4 p, s;
+
+    p.xyz = floor(fract(vec3(j) * ip.xyz) * 7.0) * ip.z - 1.0;
+    p.w = 1.5 - dot(abs(p.xyz), ones.xyz);
+    s = vec4(lessThan(p, vec4(0.0)));
+    p.xyz = p.xyz + (s.xyz * 2.0 - 1.0) * s.www;
+    return p;
+}
+
+float snoise(vec4 v) {
+    const vec4 C = vec4(0.138196601125011, 0.276393202250021, 0.414589803375032, -0.447213595499958);
+    const float F4 = 0.309016994374947451;
+
+    vec4 i = floor(v + dot(v, vec4(F4)));
+    vec4 x0 = v - i + dot(i, C.xxxx);
+
+    vec4 i0;
+    vec3 isX = step(x0.yzw, x0.xxx);
+    vec3 isYZ = step(x0.zww, x0.yyz);
+    i0.x = isX.x + isX.y + isX.z;
+    i0.yzw = 1.0 - isX;
+    i0.y += isYZ.x + isYZ.y;
+    i0.zw += 1.0 - isYZ.xy;
+    i0.z += isYZ.z;
+    i0.w += 1.0 - isYZ.z;
+
+    vec4 i3 = clamp(i0, 0.0, 1.0);
+    vec4 i2 = clamp(i0 - 1.0, 0.0, 1.0);
+    vec4 i1 = clamp(i0 - 2.0, 0.0, 1.0);
+
+    vec4 x1 = x0 - i1 + C.xxxx;
+    vec4 x2 = x0 - i2 + C.yyyy;
+    vec4 x3 = x0 - i3 + C.zzzz;
+    vec4 x4 = x0 + C.wwww;
+
+    i = mod289(i);
+    float j0 = permute(permute(permute(permute(i.w) + i.z) + i.y) + i.x);
+    vec4 j1 = permute(permute(permute(permute(
+                 i.w + vec4(i1.w, i2.w, i3.w, 1.0))
+               + i.z + vec4(i1.z, i2.z, i3.z, 1.0))
+               + i.y + vec4(i1.y, i2.y, i3.y, 1.0))
+               + i.x + vec4(i1.x, i2.x, i3.x, 1.0));
+
+    vec4 ip = vec4(1.0 / 294.0, 1.0 / 49.0, 1.0 / 7.0, 0.0);
+    vec4 p0 = grad4(j0, ip);
+    vec4 p1 = grad4(j1.x, ip);
+    vec4 p2 = grad4(j1.y, ip);
+    vec4 p3 = grad4(j1.z, ip);
+    vec4 p4 = grad4(j1.w, ip);
+
+    vec4 norm = taylorInvSqrt(vec4(dot(p0, p0), dot(p1, p1), dot(p2, p2), dot(p3, p3)));
+    p0 *= norm.x;
+    p1 *= norm.y;
+    p2 *= norm.z;
+    p3 *= norm.w;
+    p4 *= taylorInvSqrt(dot(p4, p4));
+
+    vec3 m0 = max(0.6 - vec3(dot(x0, x0), dot(x1, x1), dot(x2, x2)), 0.0);
+    vec2 m1 = max(0.6 - vec2(dot(x3, x3), dot(x4, x4)), 0.0);
+    m0 = m0 * m0;
+    m1 = m1 * m1;
+    return 49.0 * (dot(m0 * m0, vec3(dot(p0, x0), dot(p1, x1), dot(p2, x2)))
+                 + dot(m1 * m1, vec2(dot(p3, x3), dot(p4, x4))));
+}
+
+// ---------------------------------------------------------------------------------------
+// Seeded wrappers mirroring noise.rs
+
+float simplex(vec3 p) { return snoise(p + seedOffset); }
+float simplex4(vec3 p, float w) { return snoise(vec4(p + seedOffset, w)); }
+
+/// Unscaled fBm over simplex noise (`fbm_simplex`)
+float fbmSimplex(vec3 p, int octaves, float lacunarity, float gain) {
+    float value = 0.0;
+    float amplitude = 1.0;
+    float frequency = 1.0;
+    for (int i = 0; i < octaves; i++) {
+        value += amplitude * simplex(p * frequency);
+        frequency *= lacunarity;
+        amplitude *= gain;
+    }
+    return value;
+}
+
+/// `fbm`: at most 4 octaves, halved
+float fbm(vec3 p, int octaves, float lacunarity, float gain) {
+    return fbmSimplex(p, clamp(octaves, 1, 4), lacunarity, gain) * 0.5;
+}
+
+float fbm4(vec3 p, float w, int octaves, float lacunarity, float gain) {
+    float value = 0.0;
+    float amplitude = 1.0;
+    float frequency = 1.0;
+    for (int i = 0; i < clamp(octaves, 1, 4); i++) {
+        value += amplitude * simplex4(p * frequency, w * frequency);
+        frequency *= lacunarity;
+        amplitude *= gain;
+    }
+    return value * 0.5;
+}
+
+float turbulence4(vec3 p, float w, int octaves) {
+    return abs(fbm4(p, w, octaves, 2.0, 0.5));
+}
+
+float ridged(vec3 p, int octaves) {
+    float value = 0.0;
+    float amplitude = 1.0;
+    float frequency = 1.0;
+    for (int i = 0; i < octaves; i++) {
+        value += (1.0 - abs(simplex(p * frequency))) * amplitude;
+        frequency *= 2.0;
+        amplitude *= 0.5;
+    }
+    return value;
+}
+
+vec3 hash3(vec3 cell) {
+    cell += seedOffset;
+    vec3 q = vec3(dot(cell, vec3(127.1, 311.7, 74.7)),
+                  dot(cell, vec3(269.5, 183.3, 246.1)),
+                  dot(cell, vec3(113.5, 271.9, 124.6)));
+    return fract(sin(q) * 43758.5453123);
+}
+
+/// Worley F1 and F2 over the 3x3x3 neighborhood
+vec2 worley(vec3 p, float scale) {
+    vec3 scaled = p * scale;
+    vec3 cell = floor(scaled);
+    float f1 = 1e9;
+    float f2 = 1e9;
+    for (int z = -1; z <= 1; z++) {
+        for (int y = -1; y <= 1; y++) {
+            for (int x = -1; x <= 1; x++) {
+                vec3 neighbor = cell + vec3(x, y, z);
+                vec3 offset = scaled - (neighbor + hash3(neighbor));
+                float d = dot(offset, offset);
+                if (d < f1) {
+                    f2 = f1;
+                    f1 = d;
+                } else if (d < f2) {
+                    f2 = d;
+                }
+            }
+        }
+    }
+    return sqrt(vec2(f1, f2));
+}
+
+float voronoi(vec3 p, float scale) { return worley(p, scale).x; }
+
+vec3 domainWarp4(vec3 p, float w, int octaves, float strength) {
+    vec3 offset = vec3(fbm4(p, w, octaves, 2.0, 0.5),
+                       fbm4(p + vec3(5.2, 1.3, -2.8), w, octaves, 2.0, 0.5),
+                       fbm4(p + vec3(-7.4, 3.9, 6.1), w, octaves, 2.0, 0.5));
+    return p + offset * strength;
+}
+
+float warpNoise4(vec3 p, float w, float amount) {
+    return simplex4(domainWarp4(p, w, 3, amount), w) * 0.7;
+}
+
+vec3 curlPotential(vec3 q, float w) {
+    return vec3(simplex4(q, w),
+                simplex4(q + vec3(31.4, -17.9, 5.3), w),
+                simplex4(q + vec3(-11.7, 23.1, -41.2), w));
+}
+
+vec3 curlNoise(vec3 p, float w) {
+    const float EPS = 1e-3;
+    vec3 base = curlPotential(p, w);
+    vec3 dx = (curlPotential(p + vec3(EPS, 0.0, 0.0), w) - base) / EPS;
+    vec3 dy = (curlPotential(p + vec3(0.0, EPS, 0.0), w) - base) / EPS;
+    vec3 dz = (curlPotential(p + vec3(0.0, 0.0, EPS), w) - base) / EPS;
+    return vec3(dy.z - dz.y, dz.x - dx.z, dx.y - dy.x);
+}
+
+/// Slope of a height field on the sphere: gradient (central differences) minus its normal part
+float surfaceSlope(vec3 gradient, vec3 normal) {
+    return length(gradient - normal * dot(gradient, normal));
+}
+
+vec3 fbmGradient(vec3 p) {
+    const float EPS = 1e-3;
+    return vec3(fbm(p + vec3(EPS, 0.0, 0.0), 2, 2.0, 0.5) - fbm(p - vec3(EPS, 0.0, 0.0), 2, 2.0, 0.5),
+                fbm(p + vec3(0.0, EPS, 0.0), 2, 2.0, 0.5) - fbm(p - vec3(0.0, EPS, 0.0), 2, 2.0, 0.5),
+                fbm(p + vec3(0.0, 0.0, EPS), 2, 2.0, 0.5) - fbm(p - vec3(0.0, 0.0, EPS), 2, 2.0, 0.5)) / (2.0 * EPS);
+}
+
+vec3 simplexGradient(vec3 p) {
+    const float EPS = 1e-3;
+    return vec3(simplex(p + vec3(EPS, 0.0, 0.0)) - simplex(p - vec3(EPS, 0.0, 0.0)),
+                simplex(p + vec3(0.0, EPS, 0.0)) - simplex(p - vec3(0.0, EPS, 0.0)),
+                simplex(p + vec3(0.0, 0.0, EPS)) - simplex(p - vec3(0.0, 0.0, EPS))) / (2.0 * EPS);
+}
+
+// ---------------------------------------------------------------------------------------
+// Lighting (triangle.rs, shadow.rs)
+
+float discCoverage(float lightAngle, float occluderAngle, float separation) {
+    float outer = lightAngle + occluderAngle;
+    if (separation >= outer) {
+        return 0.0;
+    }
+    float ratio = occluderAngle / lightAngle;
+    float full = occluderAngle >= lightAngle ? 1.0 : ratio * ratio;
+    float inner = abs(lightAngle - occluderAngle);
+    if (separation <= inner) {
+        return full;
+    }
+    return full * (1.0 - smoothstep(inner, outer, separation));
+}
+
+float lightVisibility(vec3 point, int light) {
+    vec3 toLight = lightPosition[light] - point;
+    float lightDistance = length(toLight);
+    if (lightDistance < 1e-6) {
+        return 1.0;
+    }
+    vec3 lightDir = toLight / lightDistance;
+    float lightAngle = asin(min(lightRadius[light] / lightDistance, 1.0));
+
+    float visibility = 1.0;
+    for (int i = 0; i < occluderCount; i++) {
+        vec3 center = occluders[i].xyz;
+        float radius = occluders[i].w;
+        if (distance(center, lightPosition[light]) < radius) {
+            continue;
+        }
+        vec3 toOccluder = center - point;
+        float along = dot(toOccluder, lightDir);
+        if (along <= 0.0 || along - radius >= lightDistance) {
+            continue;
+        }
+        float occluderDistance = length(toOccluder);
+        if (occluderDistance <= radius) {
+            continue;
+        }
+        float occluderAngle = asin(radius / occluderDistance);
+        float separation = acos(clamp(along / occluderDistance, -1.0, 1.0));
+        visibility *= 1.0 - discCoverage(lightAngle, occluderAngle, separation);
+    }
+    return max(visibility, 0.0);
+}
+
+vec3 surfaceLighting(vec3 surfaceNormal, vec3 position, out vec3 specular) {
+    specular = vec3(0.0);
+    if (emissive != 0) {
+        return vec3(1.0);
+    }
+    vec3 viewDir = normalize(cameraPosition - position);
+    vec3 shaded = vec3(0.0);
+    for (int i = 0; i < lightCount; i++) {
+        vec3 toLight = lightPosition[i] - position;
+        vec3 lightDir = normalize(toLight);
+        // Two-sided surfaces turn their normal toward the light
+        vec3 normal = (materialTwoSided != 0 && dot(surfaceNormal, lightDir) < 0.0) ? -surfaceNormal : surfaceNormal;
+        float radiance = lightIntensity[i] / (1.0 + lightFalloff[i] * dot(toLight, toLight));
+        float intensity = max(dot(normal, lightDir), 0.0) * radiance;
+        float visibility = intensity > 0.0 ? lightVisibility(position, i) : 0.0;
+        float received = lightAmbient[i] * lightIntensity[i] + intensity * visibility;
+        shaded += materialDiffuse * lightColor[i] * received;
+
+        if (materialSpecular > 0.0 && visibility > 0.0) {
+            float facing = max(dot(normal, normalize(lightDir + viewDir)), 0.0);
+            specular += lightColor[i] * pow(facing, materialShininess) * materialSpecular * radiance * visibility;
+        }
+    }
+    return shaded;
+}
+
+vec3 atmosphereGlow(vec3 normal, vec3 position) {
+    if (emissive != 0 || atmosphereStrength <= 0.0) {
+        return vec3(0.0);
+    }
+    vec3 viewDir = normalize(cameraPosition - position);
+    float rim = pow(1.0 - max(dot(normal, viewDir), 0.0), atmosphereFalloff) * atmosphereStrength;
+    vec3 glow = vec3(0.0);
+    for (int i = 0; i < lightCount; i++) {
+        vec3 toLight = lightPosition[i] - position;
+        float sun = dot(normal, normalize(toLight));
+        float lit = smoothstep(-0.25, 0.3, sun);
+        vec3 tint = mix(atmosphereSunset, atmosphereDay, smoothstep(0.0, 0.6, sun));
+        float radiance = lightIntensity[i] / (1.0 + lightFalloff[i] * dot(toLight, toLight));
+        glow += tint * lightColor[i] * lit * radiance;
+    }
+    return glow * rim;
+}
+
+vec3 auroraGlow(vec3 pos, vec3 normal) {
+    vec3 dir = normalize(pos);
+    float latitude = abs(dir.y);
+    if (emissive != 0 || auroraStrength <= 0.0 || abs(latitude - auroraLatitude) > auroraWidth * 2.5) {
+        return vec3(0.0);
+    }
+    float t = time * auroraSpeed;
+    float warp = simplex(vec3(dir.x * 3.0 + t, dir.y * 3.0, dir.z * 3.0 - t * 0.7)) * auroraWidth * 1.5;
+    float offset = (latitude - auroraLatitude - warp) / auroraWidth;
+    float curtain = exp(-offset * offset);
+    float rays = simplex(vec3(dir.x * 14.0, dir.z * 14.0, t * 2.0)) * 0.4 + 0.6;
+    float night = length(lightDirection) == 0.0 ? 1.0 : 0.25 + 0.75 * (1.0 - smoothstep(-0.2, 0.1, dot(normal, lightDirection)));
+    vec3 tint = mix(auroraColor, auroraFringe, smoothstep(-0.5, 1.5, offset));
+    return tint * (curtain * rays * night * auroraStrength);
+}
+
+// ---------------------------------------------------------------------------------------
+// Procedural shaders (shader_system.rs)
+
+vec3 mixColor(vec3 a, vec3 b, float t) { return mix(a, b, clamp(t, 0.0, 1.0)); }
+
+vec3 normalizeIntensity(vec3 litColor, vec3 baseColor, float boost) {
+    float originalIntensity = (baseColor.x + baseColor.y + baseColor.z) / 3.0 + boost;
+    float mixedIntensity = (litColor.x + litColor.y + litColor.z) / 3.0;
+    return mixedIntensity > 0.001 ? litColor * (originalIntensity / mixedIntensity) : litColor;
+}
+
+vec3 applyLighting(vec3 color, vec3 baseColor) {
+    return normalizeIntensity(color * baseColor, baseColor, 0.0);
+}
+
+vec3 starShader(vec3 pos) {
+    float t = time * 0.8;
+    float surface = turbulence4(pos * 2.5, t * 1.2, 4);
+    float flares = warpNoise4(pos * 4.0, t * 1.5, 0.8);
+    float secondaryFlares = warpNoise4(pos * 2.0, t * 0.7, 0.5);
+
+    float pulse1 = (simplex(vec3(pos.xy * 1.5, pos.z * 1.5 + t * 3.0)) * 0.5 + 0.5) * 0.8;
+    float pulse2 = (sin(t * 1.5) * 0.5 + 0.5) * 0.6;
+    float combinedPulse = (pulse1 + pulse2) * 0.5;
+
+    float spots1 = voronoi(pos + vec3(t * 0.3, t * 0.2, 0.0), 3.5);
+    float spots2 = voronoi(pos + vec3(-t * 0.4, 0.0, t * 0.25), 4.5);
+    float spotMask = smoothstep(0.15, 0.3, spots1) * smoothstep(0.2, 0.35, spots2);
+
+    vec3 coreWhite = vec3(1.0);
+    vec3 brightYellow = vec3(1.0, 0.95, 0.4);
+    vec3 deepOrange = vec3(1.0, 0.6, 0.15);
+    vec3 hotRed = vec3(1.0, 0.4, 0.2);
+    vec3 darkSpot = vec3(0.7, 0.2, 0.05);
+
+    vec3 color = mixColor(brightYellow, deepOrange, surface);
+    color = mixColor(color, hotRed, abs(flares) * 0.4);
+    color = mixColor(color, coreWhite, combinedPulse * 0.6);
+
+    float flareIntensity = smoothstep(0.3, 0.75, abs(flares));
+    float secondaryIntensity = smoothstep(0.4, 0.8, abs(secondaryFlares));
+    color = mixColor(color, coreWhite, flareIntensity * 0.5 + secondaryIntensity * 0.3);
+    color = mixColor(color, darkSpot, (1.0 - spotMask) * 0.85);
+
+    float brightness = 1.6 + combinedPulse * 0.3;
+    return color * brightness + vec3(0.25, 0.15, 0.02) * combinedPulse;
+}
+
+vec3 rockyShader(vec3 pos, vec3 baseColor, vec3 specular) {
+    float terrain = fbm(pos * 3.0, 3, 2.0, 0.5);
+    float craterMask = smoothstep(0.28, 0.48, voronoi(pos, 3.5));
+
+    vec3 darkRock = vec3(0.25, 0.15, 0.10);
+    vec3 midRock = vec3(0.55, 0.35, 0.22);
+    vec3 lightRock = vec3(0.85, 0.65, 0.45);
+
+    vec3 color = mixColor(darkRock, midRock, terrain * 0.5 + 0.5);
+    color = mixColor(color, lightRock, abs(terrain) * 0.8 + 0.2);
+    color = mixColor(color, darkRock * 0.9, 1.0 - craterMask);
+    return applyLighting(color, baseColor) + specular;
+}
+
+/// Coverage of the great storm (1 at the center, 0 past the edge) and `pos` turned about
+/// its center by the spin, so what is sampled there swirls around it
+float vortexSwirl(inout vec3 pos) {
+    if (vortexRadius <= 0.0) {
+        return 0.0;
+    }
+    vec3 center = vec3(cos(vortexLatitude) * cos(vortexLongitude), sin(vortexLatitude), cos(vortexLatitude) * sin(vortexLongitude));
+    vec3 dir = normalize(pos);
+    if (dot(dir, center) <= 0.0) {
+        return 0.0;
+    }
+    vec3 east = vec3(-sin(vortexLongitude), 0.0, cos(vortexLongitude));
+    vec3 north = cross(east, center);
+    vec2 offset = vec2(dot(dir, east) / vortexStretch, dot(dir, north));
+    float inside = 1.0 - smoothstep(0.0, vortexRadius, length(offset));
+    float angle = vortexSpin * inside * inside;
+    pos = pos * cos(angle) + cross(center, pos) * sin(angle) + center * (dot(center, pos) * (1.0 - cos(angle)));
+    return inside;
+}
+
+vec3 gasGiantShader(vec3 pos, vec3 baseColor, vec3 specular) {
+    float storm = vortexSwirl(pos);
+    float t = time * 0.05;
+    float bandPattern = sin(pos.y * 10.0 + t) * 0.5 + 0.5;
+    float turb = turbulence4(pos * vec3(2.0, 4.0, 2.0), time * 0.05, 3);
+
+    vec3 flow = curlNoise(pos * vec3(1.5, 3.0, 1.5), t);
+    vec3 advected = vec3(pos.x, pos.y * 2.0, pos.z) + flow * 0.15;
+    float swirl = simplex(advected) * 0.7;
+
+    vec3 lightBand = vec3(0.95, 0.85, 0.7);
+    vec3 midBand = vec3(0.85, 0.55, 0.35);
+    vec3 darkBand = vec3(0.55, 0.35, 0.25);
+    vec3 stormColor = vec3(1.0, 0.9, 0.85);
+
+    vec3 color = mixColor(lightBand, midBand, bandPattern);
+    color = mixColor(color, darkBand, turb * 0.35);
+    color = mixColor(color, stormColor, smoothstep(0.68, 0.82, swirl) * 0.6);
+    if (vortexRadius > 0.0) {
+        float collar = smoothstep(0.0, 0.15, storm) * (1.0 - smoothstep(0.15, 0.35, storm));
+        color = mixColor(color, lightBand, collar * 0.5);
+        color = mixColor(color, vortexColor * (0.85 + 0.3 * swirl), smoothstep(0.25, 0.6, storm) * 0.85);
+    }
+    return applyLighting(color, baseColor) + specular;
+}
+
+vec3 eruptionGlow(vec3 pos) {
+    vec3 dir = normalize(pos);
+    vec3 glow = vec3(0.0);
+    for (int i = 0; i < eruptionCount; i++) {
+        float distance = 1.0 - dot(dir, eruptions[i].xyz);
+        float heat = eruptions[i].w;
+        float vent = 1.0 - smoothstep(0.0, 0.012, distance);
+        float halo = 1.0 - smoothstep(0.0, 0.05, distance);
+        glow += vec3(1.0, 0.85, 0.4) * (vent * heat * 1.5) + vec3(1.0, 0.3, 0.0) * (halo * heat * 0.6);
+    }
+    return glow;
+}
+
+vec3 lavaShader(vec3 pos, vec3 baseColor, vec3 specular) {
+    float t = time * 0.3;
+    float lavaFlow = warpNoise4(pos * 2.0, t, 0.6);
+
+    vec3 darkCrust = vec3(0.1, 0.05, 0.0);
+    vec3 hotLava = vec3(1.0, 0.3, 0.0);
+
+    vec3 surface = mixColor(darkCrust, hotLava, lavaFlow * 0.5 + 0.5);
+
+    vec3 crustPoint = pos * 1.5;
+    float valley = 1.0 - smoothstep(-0.25, 0.05, fbm(crustPoint, 2, 2.0, 0.5));
+    float flat_ = 1.0 - smoothstep(0.3, 0.9, surfaceSlope(fbmGradient(crustPoint), normalize(pos)));
+    surface = mixColor(surface, hotLava, valley * flat_ * 0.8);
+    return applyLighting(surface, baseColor) + specular;
+}
+
+// Light of the molten cracks and eruptions, added after the lighting
+vec3 lavaEmission(vec3 pos) {
+    float cracks = ridged(pos * 5.0, 2);
+    float crackMask = smoothstep(0.62, 0.72, cracks);
+    float pulse = 0.85 + 0.15 * sin(time * 0.6);
+    vec3 brightLava = vec3(1.0, 0.8, 0.1);
+    return brightLava * (crackMask * pulse) + eruptionGlow(pos);
+}
+
+vec3 iceShader(vec3 pos, vec3 baseColor, vec3 specular) {
+    float t = time * 0.02;
+    vec2 crystals = worley(pos, 4.0);
+    float snow = fbm(pos * 8.0, 3, 2.0, 0.5);
+    float frost = simplex(vec3(pos.x * 12.0 + t, pos.y * 12.0, pos.z * 12.0));
+
+    vec3 deepIce = vec3(0.3, 0.6, 0.9);
+    vec3 surfaceIce = vec3(0.8, 0.9, 1.0);
+    vec3 brightSnow = vec3(1.0);
+
+    vec3 color = mixColor(deepIce, surfaceIce, snow * 0.45 + 0.55);
+
+    float flatness = 1.0 - smoothstep(0.6, 1.6, surfaceSlope(simplexGradient(pos * 3.0), normalize(pos)));
+    color = mixColor(color, brightSnow, flatness * 0.35);
+    float sparkleMask = 1.0 - smoothstep(0.02, 0.1, crystals.y - crystals.x);
+    color = mixColor(color, brightSnow, sparkleMask * (frost * 0.5 + 0.5) * 1.1);
+
+    return normalizeIntensity(color * (baseColor + vec3(0.18, 0.22, 0.28)), baseColor, 0.18) + specular;
+}
+
+/// Warm speckles of city light on the land of the night side
+vec3 cityLights(vec3 pos, vec3 normal, float landMask) {
+    if (length(lightDirection) == 0.0 || landMask <= 0.0) {
+        return vec3(0.0);
+    }
+    float night = 1.0 - smoothstep(-0.2, 0.05, dot(normal, lightDirection));
+    float cities = smoothstep(0.55, 0.75, simplex(pos * 60.0)) * smoothstep(0.0, 0.08, landMask);
+    return vec3(1.0, 0.75, 0.4) * (cities * night * 0.9);
+}
+
+/// Climate bands from the latitude of the model-space position: x land ice, y sea ice,
+/// z desert belt, w tropics
+vec4 latitudeClimate(vec3 pos) {
+    float latitude = abs(normalize(pos).y) + simplex(vec3(pos.x * 3.0 + 11.0, pos.y * 3.0, pos.z * 3.0)) * 0.08;
+    return vec4(
+        smoothstep(0.78, 0.84, latitude),
+        smoothstep(0.88, 0.93, latitude),
+        smoothstep(0.2, 0.3, latitude) * (1.0 - smoothstep(0.42, 0.55, latitude)),
+        1.0 - smoothstep(0.12, 0.25, latitude)
+    );
+}
+
+vec3 landBiome(vec4 climate, float vegetation) {
+    vec3 color = mixColor(vec3(0.4, 0.5, 0.3), vec3(0.2, 0.4, 0.2), vegetation * 0.5 + 0.5);
+    color = mixColor(color, vec3(0.1, 0.32, 0.12), climate.w * (vegetation * 0.3 + 0.7));
+    return mixColor(color, vec3(0.76, 0.64, 0.42), climate.z * (0.85 - vegetation * 0.15));
+}
+
+vec3 cloudPlanetShader(vec3 pos, vec3 normal, vec3 baseColor, vec3 specular) {
+    float t = time * 0.1;
+    float landMask = fbmSimplex(pos * 2.0, 4, 2.0, 0.5) + fbmSimplex(pos * 32.0, 2, 2.0, 0.5) * 0.0625;
+    float clouds = fbmSimplex(vec3(pos.x * 4.0 + t, pos.y * 4.0, pos.z * 4.0), 4, 2.0, 0.6);
+    float vegetation = simplex(pos * 10.0);
+
+    vec3 ocean = vec3(0.1, 0.3, 0.6);
+
+    vec4 climate = latitudeClimate(pos);
+    vec3 color = landMask > 0.0 ? landBiome(climate, vegetation) : ocean;
+    float ice = landMask > 0.0 ? climate.x : climate.y;
+    color = mixColor(color, vec3(0.92, 0.95, 1.0), ice);
+    float cloudMask = smoothstep(0.3, 0.5, clouds);
+    color = mixColor(color, vec3(1.0), cloudMask * 0.7);
+    float gloss = (landMask > 0.0 ? 0.15 : 1.0 - ice * 0.6) * (1.0 - cloudMask * 0.7);
+    return applyLighting(color, baseColor) + specular * gloss
+        + cityLights(pos, normal, landMask) * (1.0 - cloudMask * 0.8) * (1.0 - ice);
+}
+
+/// Coverage left by the shader, set by shaders with see-through parts (ring divisions)
+float coverage = 1.0;
+
+vec3 ringShader(vec3 pos, vec3 baseColor, vec3 specular) {
+    float radius = length(pos.xz);
+    float bands = fbm(vec3(radius * 6.0, 0.0, 0.0), 4, 2.0, 0.5);
+    float grain = simplex(vec3(radius * 40.0, 0.0, 0.0));
+
+    vec3 dust = vec3(0.55, 0.45, 0.35);
+    vec3 ice = vec3(0.9, 0.85, 0.75);
+    vec3 gap = vec3(0.08, 0.07, 0.06);
+
+    vec3 color = mixColor(dust, ice, bands * 0.5 + 0.5 + grain * 0.1);
+    float density = smoothstep(-0.45, -0.25, bands);
+    color = mixColor(gap, color, density);
+    coverage = 0.15 + 0.8 * density;
+    return applyLighting(color, baseColor) + specular * density;
+}
+
+void main() {
+    vec3 specular;
+    vec3 baseColor = surfaceLighting(normalize(worldNormal), worldPosition, specular);
+    vec3 pos = modelPosition;
+
+    vec3 color;
+    if (shaderType == 0) {
+        color = starShader(pos);
+    } else if (shaderType == 1) {
+        color = rockyShader(pos, baseColor, specular);
+    } else if (shaderType == 2) {
+        color = gasGiantShader(pos, baseColor, specular);
+    } else if (shaderType == 3) {
+        color = lavaShader(pos, baseColor, specular) + lavaEmission(pos);
+    } else if (shaderType == 4) {
+        color = iceShader(pos, baseColor, specular);
+    } else if (shaderType == 5) {
+        color = cloudPlanetShader(pos, normalize(worldNormal), baseColor, specular);
+    } else {
+        color = ringShader(pos, baseColor, specular);
+    }
+    color += atmosphereGlow(normalize(worldNormal), worldPosition);
+    color += auroraGlow(pos, normalize(worldNormal));
+    finalColor = vec4(clamp(color, 0.0, 1.0), translucent != 0 ? opacity * coverage : 1.0);
+}
@@ -0,0 +1,22 @@
+#version 330
+// planet.vs
+// Vertex stage of the GPU backend: the procedural shaders sample their noise at the
+// model-space position, lighting uses the world-space position and normal
+
+in vec3 vertexPosition;
+in vec3 vertexNormal;
+
+uniform mat4 mvp;
+uniform mat4 matModel;
+uniform mat4 matNormal;
+
+out vec3 modelPosition;
+out vec3 worldPosition;
+out vec3 worldNormal;
+
+void main() {
+    modelPosition = vertexPosition;
+    worldPosition = vec3(matModel * vec4(vertexPosition, 1.0));
+    worldNormal = normalize(vec3(matNormal * vec4(vertexNormal, 0.0)));
+    gl_Position = mvp * vec4(vertexPosition, 1.0);
+}
@@ -0,0 +1,1063 @@
+// app.rs
+// Application state: startup from the command-line options, the windowless runs
+// (export, panorama, bench, headless) and the interactive app's per-frame update and render
+
+use crate::bench::bench_scene;
+use crate::bloom::Bloom;
+use crate::camera::{Camera, CameraMode};
+use crate::camera_path::{CameraAnimation, CameraKey, Easing, Playback};
+use crate::cli::{Options, Preset};
+use crate::config::Config;
+use crate::console::Console;
+use crate::debug_ui::{DebugPanel, DebugSettings};
+use crate::export::{export_video, orbit_path, ExportSettings};
+use crate::focus::Focus;
+use crate::fxaa::Fxaa;
+use crate::gizmos::{body_labels, draw_light_gizmos, draw_orbit_paths, draw_selection_outline};
+use crate::gpu::GpuBackend;
+use crate::gravity::Motion;
+use crate::hud::{slowest_objects, Hud, HudInfo, Selection, SLOWEST_SHOWN};
+use crate::input::{Action, ClickDetector, InputFrame};
+use crate::noise::{BlueNoise, Noise};
+use crate::noise_cache::BakeGrid;
+use crate::noise_inspector::NoiseInspector;
+use crate::obj::Obj;
+use crate::panorama::render_panorama;
+use crate::picking::pick_body;
+use crate::post::{PostChain, POST_EFFECT_NAMES};
+use crate::profiler::{Profiler, Stage, StageTimings};
+use crate::renderer::{step_render_scale, ImageBuffer, Renderer};
+use crate::replay::{CameraSetup, Player, Recorder, Recording, SceneLoad};
+use crate::scene_file::{scene_script, FileWatcher};
+use crate::script::{Script, ScriptContext};
+use crate::simulation::{next_time_scale, Simulation};
+use crate::solar_system::SolarSystem;
+use crate::spacecraft::{Spacecraft, SHIP_MODEL};
+use crate::starfield::Starfield;
+use raylib::prelude::*;
+
+/// Eye separation of the stereo mode, about 1/30 of the distance to the inner planets
+const ANAGLYPH_SEPARATION: f32 = 0.6;
+/// Seconds between the keyframes added with J
+const KEYFRAME_INTERVAL: f32 = 3.0;
+/// A single step while paused advances exactly one fixed timestep
+const FIXED_STEP: f32 = 1.0 / 60.0;
+/// Ticks per second of the simulation thread
+const SIMULATION_RATE: f32 = 120.0;
+/// Simulated seconds per second scrubbed with [ and ]
+const SCRUB_RATE: f32 = 60.0;
+const LABEL_FONT_SIZE: i32 = 14;
+
+/// The scene, camera and renderer set up from the options, before any window is open
+pub struct Startup {
+    options: Options,
+    config: Config,
+    width: i32,
+    height: i32,
+    render_scale: f32,
+    camera: Camera,
+    preset: Preset,
+    system: SolarSystem,
+    scene_name: String,
+    /// Scene file given with --scene, reloaded whenever it is saved
+    scene_file: Option<FileWatcher>,
+    /// Behavior script and its file; the script is None while an error stops it
+    script: Option<(FileWatcher, Option<Script>)>,
+    player: Option<Player>,
+    renderer: Renderer,
+    blue_noise: BlueNoise,
+    post_effects: [bool; 5],
+    asset_warning: Option<String>,
+}
+
+impl Startup {
+    /// Loads the replay, scene and script the options ask for and configures the renderer
+    pub fn new(options: Options, config: Config) -> Result<Self, String> {
+        // Replays start from the recorded window size, camera and scene
+        let player = match &options.replay {
+            Some(path) => Some(Player::new(
+                Recording::load(path).map_err(|error| format!("could not load replay {path}: {error}"))?,
+            )),
+            None => None,
+        };
+
+        let (width, height) = match &player {
+            Some(player) => (player.recording().width, player.recording().height),
+            None => (options.width, options.height),
+        };
+
+        let camera = match &player {
+            Some(player) => player.recording().camera.build(),
+            None => {
+                let mut camera = Camera::new(config.camera.eye, config.camera.target, Vector3::new(0.0, 1.0, 0.0));
+                camera.rotation_speed = config.controls.rotation_speed;
+                camera.zoom_speed = config.controls.zoom_speed;
+                camera.pan_speed = config.controls.pan_speed;
+                camera.mouse_sensitivity = config.controls.mouse_sensitivity;
+                camera.scroll_sensitivity = config.controls.scroll_sensitivity;
+                camera.invert_x = config.controls.invert_x;
+                camera.invert_y = config.controls.invert_y;
+                camera.fly_speed = config.controls.fly_speed;
+                camera
+            }
+        };
+
+        // Falls back to a generated sphere so the app always starts
+        let (obj, asset_error) = Obj::load_or_sphere("./models/sphere.obj");
+        let asset_warning = asset_error.map(|error| format!("{error} (procedural sphere in use)"));
+
+        let preset = match &player {
+            Some(player) => {
+                let name = &player.recording().scene.preset;
+                Preset::from_name(name).ok_or_else(|| format!("unknown preset '{name}' in replay"))?
+            }
+            None => options.preset,
+        };
+        // A replay rebuilds the recorded system, generated ones depend on the seed
+        let seed = player.as_ref().map_or(options.seed, |player| player.recording().scene.seed);
+        let mut system = preset.build(seed);
+        if let Some(player) = &player {
+            player.recording().scene.apply(&mut system);
+        }
+        let mut scene_name = preset.name().to_string();
+
+        let scene_file = options.scene.as_ref().map(FileWatcher::new);
+        if let Some(watcher) = &scene_file {
+            system = SolarSystem::from_file(watcher.path())
+                .map_err(|error| format!("could not load scene {}: {error}", watcher.path().display()))?;
+            scene_name = watcher.path().display().to_string();
+        }
+
+        // Behavior script from --script or the scene file
+        let script_path = match (&options.script, &scene_file) {
+            (Some(path), _) => Some(path.into()),
+            (None, Some(watcher)) => scene_script(watcher.path())
+                .map_err(|error| format!("could not load scene {}: {error}", watcher.path().display()))?,
+            (None, None) => None,
+        };
+        let script = match script_path {
+            Some(path) => {
+                let watcher = FileWatcher::new(path);
+                let loaded = Script::from_file(watcher.path())
+                    .map_err(|error| format!("could not load script {}: {error}", watcher.path().display()))?;
+                Some((watcher, Some(loaded)))
+            }
+            None => None,
+        };
+
+        // Only the window is rendered at a fraction of its size; exports, images and the
+        // benchmark keep the requested resolution
+        let interactive = options.export.is_none() && options.panorama.is_none() && !options.headless && !options.bench;
+        let render_scale = if interactive { options.render_scale } else { 1.0 };
+        let mut renderer = Renderer::new(scaled(width, render_scale), scaled(height, render_scale), &obj);
+        renderer.set_fov(options.fov.to_radians());
+        renderer.framebuffer.set_background_color(Color::new(
+            (config.render.background.x.clamp(0.0, 1.0) * 255.0) as u8,
+            (config.render.background.y.clamp(0.0, 1.0) * 255.0) as u8,
+            (config.render.background.z.clamp(0.0, 1.0) * 255.0) as u8,
+            255,
+        ));
+        renderer.set_threads(options.threads);
+        // The GPU backend only exists once the window is open, so windowless runs
+        // (export, panorama, bench, headless) asking for it keep the rasterizer
+        renderer.select_backend(&options.backend);
+        if options.anaglyph {
+            renderer.anaglyph = Some(ANAGLYPH_SEPARATION);
+        }
+        let blue_noise = BlueNoise::new(64, 0);
+        if config.render.dither {
+            renderer.framebuffer.set_dither(Some(blue_noise.clone()));
+        }
+        renderer.noise_cache.enabled = config.render.noise_cache;
+        if config.render.noise_cache_volume > 1 {
+            renderer.noise_cache.set_grid(BakeGrid::Volume { size: config.render.noise_cache_volume });
+        }
+        renderer.surface_cache.enabled = config.render.bake_surfaces;
+        renderer.shadows = config.render.shadows;
+        renderer.normal_mapping = config.render.normal_mapping;
+        renderer.lod = config.render.lod;
+        renderer.asteroid_sprites = config.render.asteroid_sprites;
+        renderer.coronas = config.render.corona;
+        if config.render.antialiasing {
+            renderer.antialiasing = Some(Fxaa::default());
+        }
+        renderer.post = PostChain::new(Bloom {
+            threshold: config.render.bloom_threshold,
+            radius: config.render.bloom_radius,
+            ..Bloom::default()
+        });
+        let post_effects = [
+            config.render.bloom,
+            config.render.tone_mapping,
+            config.render.chromatic_aberration,
+            config.render.vignette,
+            config.render.film_grain,
+        ];
+        for (name, enabled) in POST_EFFECT_NAMES.iter().zip(post_effects) {
+            renderer.post.set_enabled(name, enabled);
+        }
+        if config.render.starfield {
+            renderer.starfield = Some(Starfield::new(options.seed, config.render.nebula));
+        }
+
+        Ok(Startup {
+            options,
+            config,
+            width,
+            height,
+            render_scale,
+            camera,
+            preset,
+            system,
+            scene_name,
+            scene_file,
+            script,
+            player,
+            renderer,
+            blue_noise,
+            post_effects,
+            asset_warning,
+        })
+    }
+
+    /// Size of the window to open, the recorded one when replaying
+    pub fn window_size(&self) -> (i32, i32) {
+        (self.width, self.height)
+    }
+
+    /// Runs the export, panorama, benchmark or headless render the options ask for;
+    /// None when the app should open its window instead
+    pub fn run_windowless(&mut self) -> Option<Result<(), String>> {
+        let options = &self.options;
+        let camera = &mut self.camera;
+        let renderer = &mut self.renderer;
+
+        // Export: render a fixed-timestep video along an orbiting camera path
+        if let Some(output) = &options.export {
+            let settings = ExportSettings {
+                output: output.clone(),
+                image_format: options.export_format.clone(),
+                fps: options.export_fps,
+                duration: options.duration,
+                frames: options.frames,
+            };
+            let (yaw, pitch, distance) = (camera.yaw, camera.pitch, camera.distance);
+            let tour = options.tour.then(|| CameraAnimation::tour(camera, &self.system));
+            let result = export_video(renderer, &mut self.system, camera, &settings, |camera, scene, progress| {
+                match &tour {
+                    Some(tour) => tour.apply(camera, progress * tour.duration(), scene),
+                    None => orbit_path(camera, progress, yaw, pitch, distance),
+                }
+            });
+            return Some(result.map_err(|error| format!("export failed: {error}")));
+        }
+
+        // Panorama: render every direction around the camera into one image
+        if let Some(output) = &options.panorama {
+            let heading = camera.target - camera.eye;
+            let panorama = render_panorama(renderer, &self.system, camera.eye, heading, 0.0, options.panorama_width);
+            return Some(
+                panorama
+                    .save(output)
+                    .map(|_| log::info!("Saved {output} ({}x{})", panorama.width, panorama.height))
+                    .map_err(|error| format!("could not save the panorama: {error}")),
+            );
+        }
+
+        // Benchmark: time every preset headlessly
+        if options.bench {
+            println!(
+                "Benchmark: {}x{}, {} frames per preset, {} render threads",
+                self.width,
+                self.height,
+                options.bench_frames,
+                renderer.threads()
+            );
+            for preset in Preset::ALL {
+                let scene = preset.build(options.seed);
+                let result = bench_scene(renderer, preset.name(), scene, camera, options.bench_frames);
+                println!("{}", result.report());
+            }
+            return Some(Ok(()));
+        }
+
+        // Headless: render a single frame to an image
+        if options.headless {
+            renderer.draw(&self.system, camera, 0.0);
+            return Some(
+                ImageBuffer::from_framebuffer(&renderer.framebuffer)
+                    .save(&options.output)
+                    .map(|_| log::info!("Saved {}", options.output))
+                    .map_err(|error| format!("could not write {}: {error}", options.output)),
+            );
+        }
+
+        None
+    }
+}
+
+/// The interactive app: everything that lives from one frame to the next
+pub struct App {
+    options: Options,
+    config: Config,
+    width: i32,
+    height: i32,
+    render_scale: f32,
+    camera: Camera,
+    preset: Preset,
+    scene_name: String,
+    scene_file: Option<FileWatcher>,
+    script: Option<(FileWatcher, Option<Script>)>,
+    player: Option<Player>,
+    recorder: Option<Recorder>,
+    simulation: Simulation,
+    renderer: Renderer,
+    blue_noise: BlueNoise,
+    asset_warning: Option<String>,
+    /// Input of the current frame
+    input: InputFrame,
+    /// Debug view of the raw noise functions
+    inspector: NoiseInspector,
+    hud: Hud,
+    console: Console,
+    /// Tuning panel (raygui)
+    panel: DebugPanel,
+    profiler: Profiler,
+    /// Orbit path overlay (O)
+    show_orbits: bool,
+    /// Body name labels (I)
+    show_labels: bool,
+    /// Body followed by the camera (Tab)
+    focus: Focus,
+    clicks: ClickDetector,
+    /// Body clicked last (its id, so merges don't move the selection to another body),
+    /// outlined and described in the HUD
+    selected_body: Option<u32>,
+    /// Camera animation being played (K tour, Shift+J keyframes)
+    playback: Option<Playback>,
+    /// Keyframes added with J
+    keyframes: CameraAnimation,
+    /// Spacecraft flown with M (None without its model)
+    ship: Option<Spacecraft>,
+    /// Whether the spacecraft is out flying
+    piloting: bool,
+    /// Shader animation clock, advanced at the panel's animation speed
+    shader_time: f32,
+}
+
+impl App {
+    /// Takes over the startup state once the window is open, adding the GPU backend
+    pub fn new(startup: Startup, window: &mut RaylibHandle, thread: &RaylibThread) -> Self {
+        let Startup {
+            options,
+            config,
+            width,
+            height,
+            render_scale,
+            camera,
+            preset,
+            system,
+            scene_name,
+            scene_file,
+            script,
+            player,
+            mut renderer,
+            blue_noise,
+            post_effects,
+            asset_warning,
+        } = startup;
+
+        match GpuBackend::new(window, thread, renderer.mesh()) {
+            Ok(gpu) => renderer.add_backend(Box::new(gpu)),
+            Err(error) => log::warn!("GPU backend unavailable: {error}"),
+        }
+        if options.backend == "gpu" && !renderer.select_backend("gpu") {
+            log::warn!("falling back to the {} backend", renderer.backend());
+        }
+
+        let ship = match Spacecraft::load(SHIP_MODEL) {
+            Ok(ship) => Some(ship),
+            Err(error) => {
+                log::warn!("{error}; no spacecraft to fly");
+                None
+            }
+        };
+        let panel = DebugPanel::new(DebugSettings {
+            dither: config.render.dither,
+            noise_cache: config.render.noise_cache,
+            shadows: config.render.shadows,
+            post_effects,
+            ..DebugSettings::default()
+        });
+
+        let recorder = options.record.as_ref().and_then(|path| {
+            let scene = SceneLoad::capture(preset.key(), &system);
+            match Recorder::create(path, width, height, &CameraSetup::capture(&camera), &scene) {
+                Ok(recorder) => {
+                    log::info!("Recording session to {path}");
+                    Some(recorder)
+                }
+                Err(error) => {
+                    log::warn!("could not create {path}: {error}");
+                    None
+                }
+            }
+        });
+
+        // Orbits tick on their own thread so slow frames don't slow the simulation;
+        // recording and replays keep it in lockstep with the frames to stay deterministic,
+        // and scripts need to change the system between frames
+        let simulation = if recorder.is_some() || player.is_some() || script.is_some() {
+            Simulation::inline(system)
+        } else {
+            Simulation::threaded(system, SIMULATION_RATE)
+        };
+
+        // Per-stage timings, optionally dumped to disk
+        let mut profiler = Profiler::new();
+        renderer.profile_objects = options.profile_objects;
+        if let Some(path) = &options.profile_csv
+            && let Err(error) = profiler.write_csv(path)
+        {
+            log::warn!("could not create {path}: {error}");
+        }
+        if let Some(path) = &options.profile_trace
+            && let Err(error) = profiler.write_trace(path)
+        {
+            log::warn!("could not create {path}: {error}");
+        }
+
+        // The controls are listed on screen, as bound in config.toml
+        let mut hud = Hud::new();
+        hud.set_controls(&config.bindings);
+        log::info!("Press {} for the controls", config.bindings.describe(Action::ToggleHelp));
+
+        App {
+            options,
+            config,
+            width,
+            height,
+            render_scale,
+            camera,
+            preset,
+            scene_name,
+            scene_file,
+            script,
+            player,
+            recorder,
+            simulation,
+            renderer,
+            blue_noise,
+            asset_warning,
+            input: InputFrame::default(),
+            inspector: NoiseInspector::new(),
+            hud,
+            console: Console::default(),
+            panel,
+            profiler,
+            show_orbits: false,
+            show_labels: true,
+            focus: Focus::default(),
+            clicks: ClickDetector::default(),
+            selected_body: None,
+            playback: None,
+            keyframes: CameraAnimation::new(Vec::new(), Easing::Smooth, true),
+            ship,
+            piloting: false,
+            shader_time: 0.0,
+        }
+    }
+
+    /// Reads this frame's input and advances the scene, camera and simulation by it
+    pub fn update(&mut self, window: &RaylibHandle) {
+        self.hud.record_frame(window.get_frame_time());
+        self.profiler.begin_frame();
+
+        // Follow the window's size (a minimized window reports zero); the image keeps its
+        // aspect ratio and render scale
+        if window.is_window_resized() && window.get_screen_width() > 0 && window.get_screen_height() > 0 {
+            (self.width, self.height) = (window.get_screen_width(), window.get_screen_height());
+            self.resize_render();
+            log::debug!("window resized to {}x{}", self.width, self.height);
+        }
+
+        let replayed_scene = self.read_input(window);
+        let input = self.input.clone();
+
+        let selected = if input.is_pressed(Action::PresetBasic) {
+            Some(Preset::Basic)
+        } else if input.is_pressed(Action::PresetAlien) {
+            Some(Preset::Alien)
+        } else if input.is_pressed(Action::PresetGenerated) {
+            Some(Preset::Generated)
+        } else {
+            None
+        };
+        if let Some(selected) = selected {
+            self.load_preset(selected, replayed_scene.as_ref());
+        }
+        self.reload_files(input.is_pressed(Action::ReloadScene));
+        self.toggle_options(&input);
+
+        // The noise inspector replaces the scene while active
+        if self.inspector.active {
+            if self.inspector.process_input(&input) {
+                log::info!("{}", self.inspector.describe());
+            }
+            return;
+        }
+
+        let simulated = self.advance_simulation(&input);
+        self.run_script(simulated);
+        self.update_camera(&input);
+    }
+
+    /// Draws the frame with its overlays and presents it with the HUD on top
+    pub fn render(&mut self, window: &mut RaylibHandle, thread: &RaylibThread) {
+        if self.inspector.active {
+            self.renderer.framebuffer.clear();
+            self.inspector.render(&mut self.renderer.framebuffer, &Noise::default(), self.input.time);
+            self.renderer.framebuffer.swap_buffers(window, thread);
+            return;
+        }
+
+        let renderer = &mut self.renderer;
+        let camera = &self.camera;
+        let paused = self.simulation.paused();
+        let system = self.simulation.scene();
+        // The ship is drawn with a copy of the scene it was added to; the bodies keep
+        // their indices
+        let ship_scene = self.ship.as_ref().filter(|_| self.piloting).map(|ship| ship.scene_with(system));
+        let drawn = ship_scene.as_ref().unwrap_or(system);
+
+        renderer.draw(drawn, camera, self.shader_time);
+
+        // Orbit paths under the light gizmos
+        if self.show_orbits {
+            draw_orbit_paths(
+                &mut renderer.framebuffer,
+                system,
+                &camera.get_view_matrix(),
+                &renderer.projection,
+                &renderer.viewport,
+            );
+        }
+
+        if let Some(object) = self.selected_body.and_then(|id| system.index_of(id)).map(|index| &system.objects[index]) {
+            let mesh_radius = renderer.mesh_radius();
+            draw_selection_outline(
+                &mut renderer.framebuffer,
+                object,
+                &camera.get_view_matrix(),
+                &renderer.projection,
+                &renderer.viewport,
+                mesh_radius,
+            );
+        }
+
+        // Light debug gizmos
+        if self.input.is_down(Action::ShowLights) {
+            let show_directions = self.input.is_down(Action::Modifier);
+            let lights = system.lights(renderer.mesh_radius());
+            let targets: Vec<Vector3> = system.objects
+                .iter()
+                .filter(|o| !o.material.emissive)
+                .map(|o| o.position)
+                .collect();
+            draw_light_gizmos(
+                &mut renderer.framebuffer,
+                &lights,
+                &targets,
+                &camera.get_view_matrix(),
+                &renderer.projection,
+                &renderer.viewport,
+                show_directions,
+            );
+        }
+
+        // Name labels, placed and faded from this frame's depth
+        let labels = if self.show_labels {
+            body_labels(
+                &renderer.framebuffer,
+                drawn,
+                &camera.get_view_matrix(),
+                &renderer.projection,
+                &renderer.viewport,
+                renderer.mesh_radius(),
+            )
+        } else {
+            Vec::new()
+        };
+        let label_widths: Vec<i32> = labels.iter().map(|label| window.measure_text(&label.text, LABEL_FONT_SIZE)).collect();
+        let label_scale = self.width as f32 / renderer.framebuffer.width as f32;
+
+        let info = HudInfo {
+            fps: window.get_fps(),
+            stats: &renderer.stats,
+            timings: self.profiler.average(),
+            slowest: slowest_objects(system, &renderer.stats, SLOWEST_SHOWN),
+            time_scale: self.panel.settings.time_scale,
+            paused,
+            sim_time: system.time,
+            year: system.year_length(),
+            preset: &self.scene_name,
+            seed: system.seed,
+            render_size: (renderer.framebuffer.width, renderer.framebuffer.height),
+            render_scale: self.render_scale,
+            camera_distance: camera.distance,
+            focus: self.focus.describe(system),
+            selection: self.selected_body.and_then(|id| system.index_of(id)).and_then(|index| {
+                let object = system.objects.get(index)?;
+                Some(Selection {
+                    name: system.display_name(index)?,
+                    object,
+                    distance: camera.eye.distance_to(object.position),
+                })
+            }),
+            fly_speed: (camera.mode == CameraMode::FreeFly).then_some(camera.fly_speed),
+            ship: self.ship.as_ref().filter(|_| self.piloting).map(|ship| {
+                (ship.speed(), ship.autopilot_target(system).and_then(|target| system.display_name(target)))
+            }),
+            warning: self.asset_warning.as_deref(),
+        };
+        let (width, height) = (self.width, self.height);
+        let (hud, console, panel) = (&self.hud, &self.console, &mut self.panel);
+        let mut timings = renderer.stats.timings;
+        timings.measure(Stage::Presentation, || {
+            renderer.framebuffer.present(window, thread, |d| {
+                for (label, width) in labels.iter().zip(&label_widths) {
+                    let alpha = (label.alpha * 255.0) as u8;
+                    if alpha == 0 {
+                        continue;
+                    }
+                    let (x, y) = ((label.x as f32 * label_scale) as i32 - width / 2, (label.y as f32 * label_scale) as i32 - LABEL_FONT_SIZE);
+                    d.draw_text(&label.text, x + 1, y + 1, LABEL_FONT_SIZE, Color::new(0, 0, 0, alpha));
+                    d.draw_text(&label.text, x, y, LABEL_FONT_SIZE, Color::new(230, 235, 255, alpha));
+                }
+                hud.draw(d, &info);
+                console.draw(d, width, height);
+                panel.draw(d, width, system);
+            });
+        });
+
+        // Apply the panel's settings for the next frame
+        apply_settings(renderer, &panel.settings, &self.blue_noise);
+        if renderer.profile_objects {
+            let objects: Vec<(String, StageTimings)> = renderer.stats.object_timings
+                .iter()
+                .enumerate()
+                .filter_map(|(index, timings)| Some((system.display_name(index)?, *timings)))
+                .collect();
+            if let Err(error) = self.profiler.record_objects(&objects) {
+                log::warn!("could not write profile: {error}");
+            }
+        }
+        if let Err(error) = self.profiler.end_frame(timings) {
+            log::warn!("could not write profile: {error}");
+        }
+    }
+
+    /// Flushes the profile and the session recording
+    pub fn finish(mut self) {
+        if let Err(error) = self.profiler.finish() {
+            log::warn!("could not write profile: {error}");
+        }
+        if let Some(recorder) = self.recorder.take()
+            && let Err(error) = recorder.finish()
+        {
+            log::warn!("could not write recording: {error}");
+        }
+    }
+
+    /// Takes the frame's input from the replay while it lasts, then from the window,
+    /// and records it; returns the scene the replay loaded this frame, if any
+    fn read_input(&mut self, window: &RaylibHandle) -> Option<SceneLoad> {
+        let replayed = self.player.as_mut().and_then(|player| player.next_frame().cloned());
+        if self.player.is_some() && replayed.is_none() {
+            self.player = None;
+            log::info!("Replay finished");
+        }
+        self.input = match &replayed {
+            Some(frame) => {
+                self.panel.settings = frame.settings.clone();
+                apply_settings(&mut self.renderer, &self.panel.settings, &self.blue_noise);
+                frame.input.clone()
+            }
+            None => {
+                let mut input = InputFrame::capture(window, &self.config.bindings);
+                // Dragging a panel slider must not turn the camera
+                if self.panel.contains(window.get_mouse_position()) {
+                    input.release_mouse();
+                }
+                input
+            }
+        };
+        if let Some(recorder) = &mut self.recorder
+            && let Err(error) = recorder.record_frame(&self.input, &self.panel.settings)
+        {
+            log::warn!("recording stopped: {error}");
+            self.recorder = None;
+        }
+        replayed.and_then(|frame| frame.scene)
+    }
+
+    /// Switches to a preset system, rebuilt as the replay recorded it when replaying
+    fn load_preset(&mut self, preset: Preset, replayed_scene: Option<&SceneLoad>) {
+        self.preset = preset;
+        let mut system = preset.build(replayed_scene.map_or(self.options.seed, |scene| scene.seed));
+        if let Some(scene) = replayed_scene {
+            scene.apply(&mut system);
+        }
+        if let Some(recorder) = &mut self.recorder
+            && let Err(error) = recorder.record_scene(&SceneLoad::capture(preset.key(), &system))
+        {
+            log::warn!("recording stopped: {error}");
+            self.recorder = None;
+        }
+        self.simulation.load(system);
+        self.renderer.noise_cache.clear();
+        self.renderer.surface_cache.clear();
+        self.focus.select(None, &self.camera, self.simulation.scene());
+        self.selected_body = None;
+        self.playback = None;
+        if let Some(ship) = &mut self.ship {
+            ship.autopilot = None;
+        }
+        self.scene_name = preset.name().to_string();
+        // Presets replace the scene file (and its script) until the next restart;
+        // a --script starts over on the new system
+        self.scene_file = None;
+        if self.options.script.is_none() {
+            self.script = None;
+        } else if let Some((watcher, active)) = &mut self.script {
+            match Script::from_file(watcher.path()) {
+                Ok(reloaded) => *active = Some(reloaded),
+                Err(error) => log::warn!("could not reload {}: {error}", watcher.path().display()),
+            }
+        }
+        log::info!("Loaded: {}", preset.name());
+    }
+
+    /// Hot-reloads the scene file and script when saved or on request, keeping the
+    /// orbits where they were
+    fn reload_files(&mut self, reload: bool) {
+        if let Some(watcher) = &mut self.scene_file
+            && (watcher.changed() || reload)
+        {
+            match SolarSystem::from_file(watcher.path()) {
+                Ok(mut reloaded) => {
+                    reloaded.carry_state_from(self.simulation.scene());
+                    self.simulation.load(reloaded);
+                    self.renderer.noise_cache.clear();
+                    self.renderer.surface_cache.clear();
+                    log::info!("Reloaded: {}", watcher.path().display());
+                }
+                Err(error) => log::warn!("could not reload {}: {error}", watcher.path().display()),
+            }
+
+            // The scene may have gained, changed or dropped its script
+            if self.options.script.is_none() {
+                match scene_script(watcher.path()) {
+                    Ok(Some(path)) => match Script::from_file(&path) {
+                        Ok(loaded) => self.script = Some((FileWatcher::new(path), Some(loaded))),
+                        Err(error) => log::warn!("could not load script {}: {error}", path.display()),
+                    },
+                    Ok(None) => self.script = None,
+                    Err(error) => log::warn!("could not reload {}: {error}", watcher.path().display()),
+                }
+            }
+        }
+        if let Some((watcher, active)) = &mut self.script
+            && (watcher.changed() || reload)
+        {
+            match Script::from_file(watcher.path()) {
+                Ok(reloaded) => {
+                    *active = Some(reloaded);
+                    log::info!("Reloaded: {}", watcher.path().display());
+                }
+                Err(error) => log::warn!("could not reload {}: {error}", watcher.path().display()),
+            }
+        }
+        // Scripts change the system between frames, which needs an inline simulation
+        if self.script.is_some() && self.simulation.is_threaded() {
+            let paused = self.simulation.paused();
+            self.simulation = Simulation::inline(self.simulation.scene().clone());
+            self.simulation.set_paused(paused);
+        }
+    }
+
+    /// Overlays, render options and modes switched on and off with a key
+    fn toggle_options(&mut self, input: &InputFrame) {
+        if input.is_pressed(Action::ToggleHud) {
+            self.hud.visible = !self.hud.visible;
+        }
+        if input.is_pressed(Action::ToggleHelp) {
+            self.hud.help_visible = !self.hud.help_visible;
+        }
+        if input.is_pressed(Action::ToggleNoiseCache) {
+            self.panel.settings.noise_cache = !self.panel.settings.noise_cache;
+            log::info!("Noise cache: {}", if self.panel.settings.noise_cache { "on" } else { "off" });
+        }
+        if input.is_pressed(Action::ToggleConsole) {
+            self.console.visible = !self.console.visible;
+        }
+        if input.is_pressed(Action::ToggleBackend) {
+            log::info!("Render backend: {}", self.renderer.next_backend());
+        }
+        if input.is_pressed(Action::CycleDebugView) {
+            self.renderer.debug_view = self.renderer.debug_view.next();
+            log::info!("Debug view: {}", self.renderer.debug_view.name());
+        }
+        if input.is_pressed(Action::CycleRenderScale) {
+            self.render_scale = step_render_scale(self.render_scale, input.is_down(Action::Modifier));
+            self.resize_render();
+            let framebuffer = &self.renderer.framebuffer;
+            log::info!("Render scale: {}x ({}x{})", self.render_scale, framebuffer.width, framebuffer.height);
+        }
+        if input.is_pressed(Action::ToggleAnaglyph) {
+            self.renderer.anaglyph = match self.renderer.anaglyph {
+                Some(_) => None,
+                None => Some(ANAGLYPH_SEPARATION),
+            };
+            log::info!("Anaglyph 3D: {}", if self.renderer.anaglyph.is_some() { "on" } else { "off" });
+        }
+        if input.is_pressed(Action::ToggleAntialiasing) {
+            self.renderer.antialiasing = match self.renderer.antialiasing {
+                Some(_) => None,
+                None => Some(Fxaa::default()),
+            };
+            log::info!("Antialiasing: {}", if self.renderer.antialiasing.is_some() { "on" } else { "off" });
+        }
+        if input.is_pressed(Action::TogglePanel) {
+            self.panel.visible = !self.panel.visible;
+        }
+        if input.is_pressed(Action::ToggleOrbits) {
+            self.show_orbits = !self.show_orbits;
+        }
+        if input.is_pressed(Action::ToggleLabels) {
+            self.show_labels = !self.show_labels;
+        }
+        if input.is_pressed(Action::ToggleGravity) {
+            let mut system = self.simulation.scene().clone();
+            let motion = match system.motion {
+                Motion::Kinematic => Motion::Gravity,
+                Motion::Gravity => Motion::Kinematic,
+            };
+            system.set_motion(motion);
+            self.simulation.load(system);
+            log::info!("Motion: {motion:?}");
+        }
+        if input.is_pressed(Action::TogglePause) {
+            self.simulation.set_paused(!self.simulation.paused());
+            log::info!("{}", if self.simulation.paused() { "Paused" } else { "Resumed" });
+        }
+        if input.is_pressed(Action::ToggleInspector) {
+            self.inspector.active = !self.inspector.active;
+            if self.inspector.active {
+                log::info!("{}", self.inspector.describe());
+            } else {
+                log::info!("Noise inspector closed");
+            }
+        }
+    }
+
+    /// Advances the simulation (frozen while paused unless stepping) and the shader
+    /// clock with it; returns the simulated time that passed
+    fn advance_simulation(&mut self, input: &InputFrame) -> f32 {
+        let settings = &mut self.panel.settings;
+        if input.is_pressed(Action::SpeedUp) || input.is_pressed(Action::SlowDown) {
+            settings.time_scale = next_time_scale(settings.time_scale, input.is_pressed(Action::SpeedUp));
+            log::info!("Time scale: {}x", settings.time_scale);
+        }
+        self.simulation.set_time_scale(settings.time_scale);
+
+        // Timeline: [ and ] scrub backward/forward (faster with Shift), Backspace rewinds
+        let scrub = match (input.is_down(Action::ScrubBack), input.is_down(Action::ScrubForward)) {
+            (true, false) => -1.0,
+            (false, true) => 1.0,
+            _ => 0.0,
+        };
+        if input.is_pressed(Action::Rewind) {
+            self.simulation.set_time(0.0);
+            self.shader_time = 0.0;
+        } else if scrub != 0.0 {
+            let boost = if input.is_down(Action::Modifier) { 10.0 } else { 1.0 };
+            let time = self.simulation.scene().time;
+            let scrubbed = scrub * SCRUB_RATE * boost * input.delta_time;
+            self.simulation.set_time(time + scrubbed);
+            self.shader_time += scrubbed * settings.animation_speed;
+        }
+
+        // The shaders run on the simulated clock too, so surfaces keep pace with the
+        // orbits when fast-forwarded and stop with them when paused
+        let mut simulated = 0.0;
+        if !self.simulation.paused() {
+            self.simulation.advance(input.delta_time);
+            simulated = input.delta_time * settings.time_scale;
+        } else if input.is_pressed(Action::StepFrame) {
+            self.simulation.step(FIXED_STEP);
+            simulated = FIXED_STEP * settings.time_scale;
+        }
+        self.shader_time += simulated * settings.animation_speed;
+        simulated
+    }
+
+    /// Runs the scene's behaviors; a failing script is stopped until it is fixed and saved
+    fn run_script(&mut self, dt: f32) {
+        if let Some((_, slot)) = &mut self.script
+            && let Some(active) = slot
+            && let Some(system) = self.simulation.scene_mut()
+        {
+            let mut context = ScriptContext {
+                system,
+                camera: &mut self.camera,
+                settings: &mut self.panel.settings,
+                dt,
+            };
+            if let Err(error) = active.run(&mut context) {
+                log::error!("script stopped: {error}");
+                *slot = None;
+            }
+        }
+    }
+
+    /// Moves the camera with the controls, the followed body, a camera animation or the
+    /// piloted ship; checked after the simulation so targets are where they are drawn
+    fn update_camera(&mut self, input: &InputFrame) {
+        let camera = &mut self.camera;
+        let delta_time = input.delta_time;
+
+        if input.is_pressed(Action::ToggleCameraMode) {
+            camera.toggle_mode();
+            log::info!("Camera: {:?}", camera.mode);
+        }
+        if input.is_pressed(Action::ToggleShip) {
+            match &mut self.ship {
+                Some(ship) if !self.piloting => {
+                    ship.launch(camera);
+                    self.piloting = true;
+                    self.playback = None;
+                    log::info!("Piloting the spacecraft");
+                }
+                Some(_) => {
+                    self.piloting = false;
+                    log::info!("Spacecraft parked");
+                }
+                None => log::warn!("no spacecraft to fly ({SHIP_MODEL} did not load)"),
+            }
+        }
+        // The piloted ship takes the controls, the camera chases it
+        match &mut self.ship {
+            Some(ship) if self.piloting => ship.steer(input),
+            _ if self.playback.is_none() => camera.process_input(input),
+            _ => {}
+        }
+
+        let system = self.simulation.scene();
+        let focus = &mut self.focus;
+        if input.is_pressed(Action::FocusNext) {
+            focus.cycle(!input.is_down(Action::Modifier), camera, system);
+            match focus.describe(system) {
+                Some(name) => log::info!("Following {name}"),
+                None => log::info!("Free camera"),
+            }
+        }
+        // Clicking a body selects it, a double click follows it too; clicking empty space
+        // clears the selection
+        if let Some(click) = self.clicks.update(input) {
+            let picked = pick_body(
+                system,
+                &camera.get_view_matrix(),
+                &self.renderer.projection,
+                self.renderer.mesh_radius(),
+                click.position,
+                self.width as f32,
+                self.height as f32,
+            );
+            self.selected_body = picked.map(|index| system.objects[index].id);
+            if let Some(name) = picked.and_then(|index| system.display_name(index)) {
+                if click.double {
+                    self.playback = None;
+                    focus.select(picked, camera, system);
+                    log::info!("Following {name}");
+                } else {
+                    log::info!("Selected {name}");
+                }
+            }
+        }
+        focus.update(camera, system, delta_time);
+
+        // Camera animations take over the camera until they end or are stopped
+        if input.is_pressed(Action::PlayTour) {
+            self.playback = match self.playback {
+                Some(_) => None,
+                None => {
+                    focus.select(None, camera, system);
+                    Some(Playback::new(CameraAnimation::tour(camera, system)))
+                }
+            };
+        }
+        if input.is_pressed(Action::AddKeyframe) {
+            let keyframes = &mut self.keyframes;
+            if input.is_down(Action::Modifier) {
+                if keyframes.keys().len() >= 2 {
+                    focus.select(None, camera, system);
+                    self.playback = Some(Playback::new(keyframes.clone()));
+                } else {
+                    log::warn!("add at least two keyframes with J first");
+                }
+            } else {
+                let time = if keyframes.keys().is_empty() { 0.0 } else { keyframes.duration() + KEYFRAME_INTERVAL };
+                keyframes.add(CameraKey::new(time, camera.eye, camera.target));
+                log::info!("Keyframe {} at {time:.0} s", keyframes.keys().len());
+            }
+        }
+        if let Some(active) = &mut self.playback
+            && !active.advance(camera, system, delta_time)
+        {
+            self.playback = None;
+            log::info!("Camera animation finished");
+        }
+
+        // The ship flies on the window's clock, also while the simulation is paused
+        if let Some(ship) = &mut self.ship
+            && self.piloting
+        {
+            if input.is_pressed(Action::Autopilot) {
+                match ship.cycle_autopilot(system).and_then(|target| system.display_name(target)) {
+                    Some(name) => log::info!("Autopilot to {name}"),
+                    None => log::info!("Autopilot off"),
+                }
+            }
+            if ship.update(system, delta_time)
+                && let Some(name) = ship.autopilot_target(system).and_then(|target| system.display_name(target))
+            {
+                log::info!("Arrived at {name}");
+            }
+            ship.chase(camera, delta_time);
+        }
+    }
+
+    /// Resizes the rendered image to the window at the current render scale
+    fn resize_render(&mut self) {
+        self.renderer.resize(scaled(self.width, self.render_scale), scaled(self.height, self.render_scale));
+    }
+}
+
+/// A window dimension at a render scale, at least one pixel
+fn scaled(size: i32, scale: f32) -> i32 {
+    ((size as f32 * scale).round() as i32).max(1)
+}
+
+/// Applies the debug panel's render settings to the renderer
+fn apply_settings(renderer: &mut Renderer, settings: &DebugSettings, blue_noise: &BlueNoise) {
+    if settings.dither != renderer.framebuffer.has_dither() {
+        renderer.framebuffer.set_dither(settings.dither.then(|| blue_noise.clone()));
+    }
+    renderer.noise_cache.enabled = settings.noise_cache;
+    renderer.light_intensity = settings.light_intensity;
+    renderer.shadows = settings.shadows;
+    for (name, &enabled) in POST_EFFECT_NAMES.iter().zip(&settings.post_effects) {
+        renderer.post.set_enabled(name, enabled);
+    }
+}
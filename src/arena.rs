@@ -0,0 +1,40 @@
+// arena.rs
+// Reusable scratch buffers for the per-object pipeline stages
+
+use crate::clipping::Clipper;
+use crate::fragment::{Fragment, ShadedFragment};
+use crate::vertex::Vertex;
+
+/// Buffers one object needs on its way through the pipeline. They are cleared instead of
+/// dropped between uses, so after the first frames rendering no longer allocates
+#[derive(Default)]
+pub struct FrameArena {
+    /// Output of the vertex stage
+    pub vertices: Vec<Vertex>,
+    /// Triangles that reach the rasterizer, clipped to the view frustum
+    pub triangles: Vec<[Vertex; 3]>,
+    /// Polygon buffers of the clipping stage
+    pub clipper: Clipper,
+    /// Raw fragments of the rasterizer
+    pub fragments: Vec<Fragment>,
+    /// Output of the fragment stage, ready for the depth test
+    pub shaded: Vec<ShadedFragment>,
+}
+
+impl FrameArena {
+    /// Empties every buffer, keeping its capacity
+    pub fn reset(&mut self) {
+        self.vertices.clear();
+        self.triangles.clear();
+        self.fragments.clear();
+        self.shaded.clear();
+    }
+
+    /// Bytes reserved by the buffers
+    pub fn capacity_bytes(&self) -> usize {
+        self.vertices.capacity() * size_of::<Vertex>()
+            + self.triangles.capacity() * size_of::<[Vertex; 3]>()
+            + self.fragments.capacity() * size_of::<Fragment>()
+            + self.shaded.capacity() * size_of::<ShadedFragment>()
+    }
+}
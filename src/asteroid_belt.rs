@@ -0,0 +1,217 @@
+// asteroid_belt.rs
+// Asteroid belts: hundreds of small rocks on their own orbits in an annulus around a
+// body, drawn as instances of one shared low-poly mesh, or as single-pixel sprites once
+// they are too small to tell apart from one
+
+use raylib::prelude::*;
+use std::f32::consts::PI;
+use crate::arena::FrameArena;
+use crate::backend::ViewSetup;
+use crate::fragment::Fragment;
+use crate::framebuffer::Framebuffer;
+use crate::light::main_light_direction;
+use crate::lod::screen_radius;
+use crate::matrix::{camera_position, create_model_matrix, project_to_screen};
+use crate::mesh::{generate_icosphere, SPHERE_RADIUS};
+use crate::material::Material;
+use crate::noise::Noise;
+use crate::obj::Obj;
+use crate::profiler::Stage;
+use crate::renderer::RenderStats;
+use crate::shadow::Occluder;
+use crate::shader_system::{apply_shader, ShaderType};
+use crate::solar_system::CelestialObject;
+use crate::triangle::{surface_lighting, Lighting};
+use crate::vertex::Vertex;
+use crate::{shade_object, write_fragments, Uniforms};
+
+/// Projected radius in pixels below which a rock is drawn as a single point
+pub const SPRITE_RADIUS: f32 = 1.5;
+
+/// How far rock vertices are pushed in or out of the sphere, as a fraction of its radius
+const ROCK_LUMPINESS: f32 = 0.3;
+
+/// One rock of a belt
+#[derive(Clone, Debug)]
+pub struct Asteroid {
+    pub orbit_radius: f32,
+    pub orbit_angle: f32,
+    /// Angular speed in radians per second, already slowed down with the radius
+    pub orbit_speed: f32,
+    /// Tilt of the orbit and the direction where it rises through the belt's plane
+    pub inclination: f32,
+    pub ascending_node: f32,
+    pub scale: f32,
+    pub rotation: Vector3,
+    pub rotation_speed: Vector3,
+    pub position: Vector3,
+}
+
+impl Asteroid {
+    /// Position relative to the belt's center
+    fn offset(&self) -> Vector3 {
+        let (sin, cos) = self.orbit_angle.sin_cos();
+        let height = (self.orbit_angle - self.ascending_node).sin() * self.inclination;
+        Vector3::new(cos, height, sin) * self.orbit_radius
+    }
+}
+
+/// Rocks orbiting a body between two radii; they are too light to pull on anything,
+/// collide or cast shadows
+#[derive(Clone, Debug)]
+pub struct AsteroidBelt {
+    /// Index of the body the belt surrounds
+    pub parent: usize,
+    pub inner: f32,
+    pub outer: f32,
+    /// Surface of every rock
+    pub material: Material,
+    /// Noise seed of the rocks' surface
+    pub seed: u32,
+    pub rocks: Vec<Asteroid>,
+}
+
+/// Hash of a belt seed and a rock attribute, evenly spread over 0..1
+fn random(seed: u32, index: usize) -> f32 {
+    let mut h = seed ^ (index as u32).wrapping_mul(0x9E37_79B9);
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7FEB_352D);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846C_A68B);
+    h ^= h >> 16;
+    (h & 0xFF_FFFF) as f32 / 0xFF_FFFF as f32
+}
+
+impl AsteroidBelt {
+    /// Belt of `count` rocks between `inner` and `outer` around body `parent`, laid out
+    /// from `seed` so the same seed gives the same belt; `orbit_speed` works like a
+    /// planet's (slowed down with the radius), `scale` is the size range of the rocks,
+    /// most of them near its small end, and `thickness` the largest orbit tilt
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        parent: usize,
+        inner: f32,
+        outer: f32,
+        count: usize,
+        orbit_speed: f32,
+        scale: (f32, f32),
+        thickness: f32,
+        seed: u32,
+    ) -> Self {
+        let rocks = (0..count)
+            .map(|i| {
+                let r = |attribute: usize| random(seed, i * 8 + attribute);
+                // Uniform over the annulus's area, not its radius
+                let orbit_radius = (inner * inner + (outer * outer - inner * inner) * r(0)).sqrt();
+                let spin = |attribute: usize| (r(attribute) - 0.5) * 2.0;
+                let mut rock = Asteroid {
+                    orbit_radius,
+                    orbit_angle: r(1) * 2.0 * PI,
+                    orbit_speed: orbit_speed * (0.9 + 0.2 * r(2)) / orbit_radius.max(1e-3).sqrt(),
+                    inclination: (r(3) - 0.5) * 2.0 * thickness,
+                    ascending_node: r(4) * 2.0 * PI,
+                    scale: scale.0 + (scale.1 - scale.0) * r(5).powi(3),
+                    rotation: Vector3::zero(),
+                    rotation_speed: Vector3::new(spin(6), spin(7), spin(6) * spin(7)),
+                    position: Vector3::zero(),
+                };
+                rock.position = rock.offset();
+                rock
+            })
+            .collect();
+        AsteroidBelt { parent, inner, outer, material: ShaderType::Rocky.material(), seed, rocks }
+    }
+
+    /// Moves every rock along its orbit around `center`
+    pub fn update(&mut self, delta_time: f32, center: Vector3) {
+        for rock in &mut self.rocks {
+            rock.orbit_angle += rock.orbit_speed * delta_time;
+            rock.rotation += rock.rotation_speed * delta_time;
+            rock.position = center + rock.offset();
+        }
+    }
+
+    /// Body the rocks are shaded as, with the belt's material and noise seed
+    fn surface(&self) -> CelestialObject {
+        let mut surface = CelestialObject::moon(self.parent, 0.0, 0.0, 1.0, self.material.shader);
+        surface.material = self.material;
+        surface.seed = self.seed;
+        surface
+    }
+}
+
+/// Mesh shared by every rock: an icosahedron with its corners pushed in and out, about
+/// as wide as the body sphere
+pub fn rock_mesh() -> Obj {
+    let mut rock: Obj = generate_icosphere(0);
+    for (i, vertex) in rock.vertices.iter_mut().enumerate() {
+        let bulge = 1.0 + (random(0x5EED, i) - 0.5) * 2.0 * ROCK_LUMPINESS;
+        *vertex = Vertex::new(vertex.position * bulge, vertex.normal, vertex.tex_coords);
+    }
+    rock
+}
+
+/// Draws the rocks of every belt over a view the backend already drew, depth tested
+/// against its bodies: rocks covering a few pixels as instances of `rock_mesh`, smaller
+/// ones as single shaded points (or still as meshes without `sprites`)
+pub fn draw_belts(
+    framebuffer: &mut Framebuffer,
+    setup: &ViewSetup,
+    belts: &[AsteroidBelt],
+    occluders: &[Occluder],
+    rock_mesh: &Obj,
+    sprites: bool,
+    stats: &mut RenderStats,
+) {
+    let eye = camera_position(&setup.view);
+    let mut arena = FrameArena::default();
+    for belt in belts {
+        let surface = belt.surface();
+        let noise = Noise::new(belt.seed);
+        let lighting = Lighting {
+            lights: setup.lights,
+            occluders,
+            material: surface.material,
+            eye,
+            bump: None,
+            atmosphere: None,
+            vertex_colors: false,
+        };
+
+        for rock in &belt.rocks {
+            let uniforms = Uniforms {
+                model_matrix: create_model_matrix(rock.position, rock.scale, rock.rotation),
+                view_matrix: setup.view,
+                projection_matrix: setup.projection,
+                viewport_matrix: setup.viewport,
+                camera_position: eye,
+                light_direction: main_light_direction(setup.lights, rock.position),
+                time: setup.time,
+                noise,
+                baked: None,
+                eruptions: &[],
+                vortex: None,
+            };
+
+            let radius = screen_radius(rock.position, rock.scale * SPHERE_RADIUS, &setup.view, &setup.projection, &setup.viewport);
+            if sprites && radius < SPRITE_RADIUS {
+                let Some(screen) = project_to_screen(rock.position, &setup.view, &setup.projection, &setup.viewport) else {
+                    continue;
+                };
+                // The side of the rock that faces the camera, lit like a surface point
+                let normal = (eye - rock.position).normalized();
+                let (color, specular) = surface_lighting(normal, rock.position, &lighting);
+                let fragment = Fragment::new_with_world_pos(
+                    screen.x, screen.y, color, specular, Vector3::zero(), screen.z, rock.position,
+                );
+                let color = stats.timings.measure(Stage::FragmentShading, || apply_shader(&fragment, &uniforms, &belt.material));
+                framebuffer.point(screen.x as i32, screen.y as i32, screen.z, color);
+                stats.fragments += 1;
+                continue;
+            }
+
+            shade_object(&uniforms, rock_mesh, setup.lights, occluders, &surface, &mut arena, stats);
+            stats.timings.measure(Stage::FragmentShading, || write_fragments(framebuffer, &arena.shaded));
+        }
+    }
+}
@@ -0,0 +1,65 @@
+// atmosphere.rs
+// Atmosphere layer: a fresnel rim of scattered light over any body's shader, blue where
+// the sun is high and orange along the terminator
+
+use raylib::prelude::*;
+use crate::light::Light;
+
+/// Air around a body; composes with whatever shader colors the surface
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Atmosphere {
+    /// Scattered light on the day side
+    pub day: Vector3,
+    /// Light near the terminator, where sunlight crosses more air
+    pub sunset: Vector3,
+    /// Fresnel exponent: higher values hug the limb more tightly
+    pub falloff: f32,
+    /// Brightness of the glow
+    pub strength: f32,
+}
+
+impl Default for Atmosphere {
+    /// Earth-like air
+    fn default() -> Self {
+        Atmosphere {
+            day: Vector3::new(0.35, 0.6, 1.0),
+            sunset: Vector3::new(1.0, 0.45, 0.15),
+            falloff: 3.0,
+            strength: 0.9,
+        }
+    }
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+impl Atmosphere {
+    /// Atmosphere with another day color, the sunset tint kept
+    pub fn tinted(day: Vector3) -> Self {
+        Atmosphere { day, ..Atmosphere::default() }
+    }
+
+    /// Light scattered toward the eye at a surface point with a world-space `normal`;
+    /// strongest where the view grazes the limb, nothing where it looks straight down
+    pub fn glow(&self, normal: Vector3, position: Vector3, eye: Vector3, lights: &[Light]) -> Vector3 {
+        let view = (eye - position).normalized();
+        let rim = (1.0 - normal.dot(view).max(0.0)).powf(self.falloff) * self.strength;
+        if rim < 1e-3 {
+            return Vector3::zero();
+        }
+
+        let mut glow = Vector3::zero();
+        for light in lights {
+            let to_light = light.position - position;
+            let distance = to_light.length();
+            let sun = normal.dot(to_light / distance.max(1e-6));
+            // The air stays lit a little past the terminator, reddened on the way
+            let lit = smoothstep(-0.25, 0.3, sun);
+            let tint = self.sunset.lerp(self.day, smoothstep(0.0, 0.6, sun));
+            glow += tint * light.color * (lit * light.intensity * light.attenuation(distance));
+        }
+        glow * rim
+    }
+}
@@ -0,0 +1,79 @@
+// aurora.rs
+// Aurora layer: curtains of green and purple light circling the poles, drifting with time
+// over any body's shader and brightest on its night side
+
+use raylib::prelude::*;
+use crate::fragment::Fragment;
+use crate::Uniforms;
+
+/// Polar lights of a body; composes with whatever shader colors the surface
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aurora {
+    /// Color of the lower edge of the curtains
+    pub color: Vector3,
+    /// Color the curtains fade to on their poleward side
+    pub fringe: Vector3,
+    /// Sine of the latitude the oval circles the poles at (measured from the spin axis,
+    /// so the body's `axial_tilt` leans it)
+    pub latitude: f32,
+    /// Half width of the oval in the same units
+    pub width: f32,
+    /// Brightness of the curtains
+    pub strength: f32,
+    /// How fast the curtains drift and ripple
+    pub speed: f32,
+}
+
+impl Default for Aurora {
+    /// Earth-like green aurora with a purple fringe
+    fn default() -> Self {
+        Aurora {
+            color: Vector3::new(0.2, 1.0, 0.45),
+            fringe: Vector3::new(0.6, 0.25, 0.9),
+            latitude: 0.84,
+            width: 0.06,
+            strength: 0.8,
+            speed: 0.4,
+        }
+    }
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+impl Aurora {
+    /// Aurora with another main color, the fringe kept
+    pub fn tinted(color: Vector3) -> Self {
+        Aurora { color, ..Aurora::default() }
+    }
+
+    /// Light the curtains add over a fragment of the body (model-space position, world
+    /// normal), animated by `uniforms.time`; faint on the day side when the body has a
+    /// light direction
+    pub fn glow(&self, fragment: &Fragment, uniforms: &Uniforms) -> Vector3 {
+        let dir = fragment.world_position.normalized();
+        let latitude = dir.y.abs();
+        let reach = self.width * 2.5;
+        if latitude < self.latitude - reach || latitude > self.latitude + reach {
+            return Vector3::zero();
+        }
+
+        let noise = &uniforms.noise;
+        let t = uniforms.time * self.speed;
+        // The oval wanders in latitude as the noise under it drifts around the pole
+        let warp = noise.simplex_noise(Vector3::new(dir.x * 3.0 + t, dir.y * 3.0, dir.z * 3.0 - t * 0.7)) * self.width * 1.5;
+        let offset = (latitude - self.latitude - warp) / self.width;
+        let curtain = (-offset * offset).exp();
+        // Vertical rays flickering along the curtain
+        let rays = noise.simplex_noise(Vector3::new(dir.x * 14.0, dir.z * 14.0, t * 2.0)) * 0.4 + 0.6;
+
+        let night = match uniforms.light_direction {
+            direction if direction == Vector3::zero() => 1.0,
+            direction => 0.25 + 0.75 * smoothstep(0.1, -0.2, fragment.normal.normalized().dot(direction)),
+        };
+        let tint = self.color.lerp(self.fringe, smoothstep(-0.5, 1.5, offset));
+        tint * (curtain * rays * night * self.strength)
+    }
+}
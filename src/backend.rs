@@ -0,0 +1,381 @@
+// backend.rs
+// Pluggable render backends: everything that turns the bodies of a scene into pixels of
+// the renderer's framebuffer for one view
+
+use raylib::prelude::*;
+use rayon::prelude::*;
+use rayon::ThreadPool;
+use std::sync::Mutex;
+use crate::arena::FrameArena;
+use crate::clipping::{sphere_in_frustum, sphere_occluded};
+use crate::debug_view::{diagnostic_fragments, DebugView};
+use crate::fragment::{BlendMode, ShadedFragment};
+use crate::framebuffer::Framebuffer;
+use crate::light::{main_light_direction, Light};
+use crate::lod::{screen_radius, select_level};
+use crate::mesh::SPHERE_RADIUS;
+use crate::matrix::camera_position;
+use crate::noise::Noise;
+use crate::noise_cache::NoiseCache;
+use crate::profiler::{Stage, StageTimings};
+use crate::renderer::RenderStats;
+use crate::solar_system::{CelestialObject, SolarSystem};
+use crate::surface_bake::SurfaceCache;
+use crate::obj::Obj;
+use crate::shadow::Occluder;
+use crate::triangle::{pixel_bounds, rasterize, rasterize_edges, Bump, Lighting, PixelRect};
+use crate::{blend_translucent, shade_baked, shade_fragments, transform_object, write_opaque, Uniforms};
+
+/// Names accepted by `Renderer::select_backend`, in the order T cycles through them
+pub const BACKEND_NAMES: [&str; 3] = ["rasterizer", "raytracer", "gpu"];
+
+/// Per-view state shared by every backend
+pub struct ViewSetup<'a> {
+    pub view: Matrix,
+    pub projection: Matrix,
+    pub viewport: Matrix,
+    pub time: f32,
+    /// Radius of the body mesh in model space
+    pub mesh_radius: f32,
+    /// Radius of the largest sphere inside the full body mesh and then each of
+    /// `detail_levels`, what a body surely covers; empty disables occlusion culling
+    pub inner_radii: &'a [f32],
+    /// Body mesh (rings and models bring their own)
+    pub body_mesh: &'a Obj,
+    /// Coarser body meshes for bodies that cover few pixels, finest first; empty draws
+    /// every body with `body_mesh`
+    pub detail_levels: &'a [Obj],
+    /// Star lights, already scaled by the renderer's intensity
+    pub lights: &'a [Light],
+    /// Whether bodies cast shadows (eclipses) on each other
+    pub shadows: bool,
+    /// Whether surface relief tilts the normals of rocky, icy and earth-like bodies
+    pub normal_mapping: bool,
+    /// Baked noise layers, prepared for this frame's time
+    pub noise_cache: &'a NoiseCache,
+    /// Thread pool the parallel passes run on, None for rayon's global one
+    pub pool: Option<&'a ThreadPool>,
+    /// Baked shader surfaces, prepared for this frame's time
+    pub surfaces: &'a SurfaceCache,
+    /// Diagnostic mode, see `DebugView`
+    pub debug_view: DebugView,
+    /// Whether backends time each object into `RenderStats::object_timings`
+    pub profile_objects: bool,
+}
+
+impl ViewSetup<'_> {
+    /// Runs `work` on the view's thread pool, so the parallel iterators inside it use
+    /// that pool's threads
+    pub fn install<R: Send>(&self, work: impl FnOnce() -> R + Send) -> R {
+        match self.pool {
+            Some(pool) => pool.install(work),
+            None => work(),
+        }
+    }
+
+    /// Mesh an object is drawn with: its ring or model mesh, or the sphere level of detail
+    /// that suits its size on screen
+    pub fn mesh<'b>(&'b self, object: &'b CelestialObject) -> &'b Obj {
+        if let Some(mesh) = object.mesh() {
+            return mesh;
+        }
+        match self.detail_level(object) {
+            0 => self.body_mesh,
+            level => &self.detail_levels[level - 1],
+        }
+    }
+
+    /// Normal mapping of an object, if it is enabled and the object has relief
+    pub fn bump<'b>(&self, object: &CelestialObject, uniforms: &'b Uniforms<'b>) -> Option<Bump<'b>> {
+        let relief = object.material.relief().filter(|_| self.normal_mapping)?;
+        Some(Bump { relief, uniforms })
+    }
+
+    /// Level of detail of a body: 0 for the full mesh, n for `detail_levels[n - 1]`
+    pub fn detail_level(&self, object: &CelestialObject) -> usize {
+        if self.detail_levels.is_empty() {
+            return 0;
+        }
+        let radius = screen_radius(object.position, self.mesh_radius * object.scale, &self.view, &self.projection, &self.viewport);
+        select_level(radius, self.detail_levels.len())
+    }
+
+    /// Radius of a sphere around an object that its mesh fits in
+    pub fn bounding_radius(&self, object: &CelestialObject) -> f32 {
+        match (&object.ring, &object.model) {
+            (Some(ring), _) => ring.outer * object.scale,
+            // Models are fitted to the body sphere
+            (None, Some(_)) => SPHERE_RADIUS * object.scale,
+            (None, None) => self.mesh_radius * object.scale,
+        }
+    }
+
+    /// Which objects can show in this view; the others are skipped whole. An object is
+    /// hidden when its bounding sphere is outside the frustum, or behind the sphere inside
+    /// an opaque body (except in wireframe, where bodies do not cover each other)
+    pub fn visible_objects(&self, scene: &SolarSystem) -> Vec<bool> {
+        let in_frustum: Vec<bool> = scene.objects
+            .iter()
+            .map(|object| sphere_in_frustum(object.position, self.bounding_radius(object), &self.view, &self.projection))
+            .collect();
+        let occluders: Vec<(Vector3, f32)> = scene.objects
+            .iter()
+            .zip(&in_frustum)
+            .filter(|&(object, &visible)| {
+                visible
+                    && self.debug_view != DebugView::Wireframe
+                    && object.mesh().is_none()
+                    && object.blend == BlendMode::Opaque
+            })
+            .filter_map(|(object, _)| Some((object.position, self.inner_radii.get(self.detail_level(object))? * object.scale)))
+            .collect();
+
+        let eye = camera_position(&self.view);
+        scene.objects
+            .iter()
+            .zip(in_frustum)
+            .map(|(object, visible)| {
+                let radius = self.bounding_radius(object);
+                visible && !occluders.iter().any(|&(center, occluder_radius)| sphere_occluded(eye, object.position, radius, center, occluder_radius))
+            })
+            .collect()
+    }
+
+    /// Bodies that can shadow the object at `index`: every other body, or none with
+    /// shadows disabled
+    pub fn occluders(&self, scene: &SolarSystem, index: usize) -> Vec<Occluder> {
+        if self.shadows {
+            scene.occluders(self.mesh_radius, index)
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+/// Draws a view of the scene into a cleared framebuffer
+pub trait RenderBackend {
+    /// Name shown in logs and the HUD, one of `BACKEND_NAMES` for the built-in backends
+    fn name(&self) -> &'static str;
+
+    /// Whether the backend draws without a window (headless runs, exports, tests)
+    fn is_software(&self) -> bool {
+        true
+    }
+
+    /// Draws every object of the scene, adding to `stats`
+    fn draw_view(&mut self, setup: &ViewSetup, scene: &SolarSystem, framebuffer: &mut Framebuffer, stats: &mut RenderStats);
+}
+
+/// Side of the square screen tiles rasterized as independent jobs
+const TILE_SIZE: i32 = 64;
+
+/// Triangle mesh through the vertex, raster and fragment stages, in two parallel passes:
+/// objects are transformed one job each, then their triangles are binned into screen
+/// tiles that are rasterized and shaded one job each. A tile owns its pixels, so even a
+/// single body filling the screen spreads over every core, and the fragments of each
+/// pixel stay in object and triangle order, which keeps the depth test deterministic
+#[derive(Default)]
+pub struct Rasterizer {
+    /// Vertices and triangles of each object, reused every frame
+    arenas: Vec<Mutex<FrameArena>>,
+    /// Fragments of each tile, reused every frame
+    tile_arenas: Vec<Mutex<FrameArena>>,
+    /// (object, triangle) pairs touching each tile, in drawing order
+    bins: Vec<Vec<(usize, usize)>>,
+}
+
+impl Rasterizer {
+    /// Bytes currently reserved by the object and tile arenas
+    pub fn arena_bytes(&mut self) -> usize {
+        self.arenas
+            .iter_mut()
+            .chain(self.tile_arenas.iter_mut())
+            .map(|arena| lock(arena).capacity_bytes())
+            .sum()
+    }
+}
+
+/// The arena of a job that panicked is still usable: it is reset before every use
+fn lock(arena: &mut Mutex<FrameArena>) -> &mut FrameArena {
+    arena.get_mut().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Grows a list of arenas to at least `count`
+fn reserve(arenas: &mut Vec<Mutex<FrameArena>>, count: usize) {
+    if arenas.len() < count {
+        arenas.resize_with(count, Mutex::default);
+    }
+}
+
+/// What the tile jobs need to know about one object
+struct ObjectSetup<'a> {
+    uniforms: Uniforms<'a>,
+    occluders: Vec<Occluder>,
+    /// False when the object is outside the view frustum or hidden behind another
+    visible: bool,
+}
+
+impl RenderBackend for Rasterizer {
+    fn name(&self) -> &'static str {
+        "rasterizer"
+    }
+
+    fn draw_view(&mut self, setup: &ViewSetup, scene: &SolarSystem, framebuffer: &mut Framebuffer, stats: &mut RenderStats) {
+        let count = scene.objects.len();
+        reserve(&mut self.arenas, count);
+
+        let eye = camera_position(&setup.view);
+        let visible = setup.visible_objects(scene);
+        let objects: Vec<ObjectSetup> = scene.objects
+            .iter()
+            .enumerate()
+            .map(|(index, object)| {
+                let noise = Noise::new(object.seed);
+                let baked = setup.noise_cache.lookup(&noise, &object.material, setup.time);
+                let visible = visible[index];
+                ObjectSetup {
+                    uniforms: Uniforms {
+                        model_matrix: object.model_matrix(),
+                        view_matrix: setup.view,
+                        projection_matrix: setup.projection,
+                        viewport_matrix: setup.viewport,
+                        camera_position: eye,
+                        light_direction: main_light_direction(setup.lights, object.position),
+                        time: setup.time,
+                        noise,
+                        baked,
+                        eruptions: object.eruptions(),
+                        vortex: object.vortex,
+                    },
+                    // Every other body can cast a shadow on this one
+                    occluders: if visible { setup.occluders(scene, index) } else { Vec::new() },
+                    visible,
+                }
+            })
+            .collect();
+
+        // Pass 1: vertex stage, one job per object; culled objects leave their arena empty
+        let arenas = &self.arenas;
+        let transform = |index: usize| {
+            let mut arena = arenas[index].lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            let mut stats = RenderStats::default();
+            if !objects[index].visible {
+                arena.reset();
+                stats.culled = 1;
+                return stats;
+            }
+            transform_object(&objects[index].uniforms, setup.mesh(&scene.objects[index]), &mut arena, &mut stats);
+            if setup.profile_objects {
+                let timings = stats.timings;
+                stats.add_object(index, &timings);
+            }
+            stats
+        };
+        let object_stats: Vec<RenderStats> = setup.install(|| (0..count).into_par_iter().map(transform).collect());
+        let transformed: Vec<&FrameArena> = self.arenas.iter_mut().take(count).map(|arena| &*lock(arena)).collect();
+
+        // Bin every on-screen triangle into the tiles its bounding box touches
+        let (width, height) = (framebuffer.width, framebuffer.height);
+        let (columns, rows) = ((width + TILE_SIZE - 1) / TILE_SIZE, (height + TILE_SIZE - 1) / TILE_SIZE);
+        let tiles = (columns * rows) as usize;
+        self.bins.resize_with(tiles, Vec::new);
+        self.bins.iter_mut().for_each(Vec::clear);
+        for (object, arena) in transformed.iter().enumerate() {
+            for (index, [v1, v2, v3]) in arena.triangles.iter().enumerate() {
+                let (min_x, min_y, max_x, max_y) = pixel_bounds(v1, v2, v3);
+                let (min_x, min_y) = (min_x.max(0), min_y.max(0));
+                let (max_x, max_y) = (max_x.min(width - 1), max_y.min(height - 1));
+                if min_x > max_x || min_y > max_y {
+                    continue;
+                }
+                for row in min_y / TILE_SIZE..=max_y / TILE_SIZE {
+                    for column in min_x / TILE_SIZE..=max_x / TILE_SIZE {
+                        self.bins[(row * columns + column) as usize].push((object, index));
+                    }
+                }
+            }
+        }
+
+        // Pass 2: rasterization and fragment stage, one job per tile
+        reserve(&mut self.tile_arenas, tiles);
+        let (bins, tile_arenas) = (&self.bins, &self.tile_arenas);
+        let rasterize_tile = |tile: usize| {
+            let mut arena = tile_arenas[tile].lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            arena.reset();
+            let (column, row) = (tile as i32 % columns, tile as i32 / columns);
+            let bounds = PixelRect::new(
+                column * TILE_SIZE,
+                row * TILE_SIZE,
+                ((column + 1) * TILE_SIZE).min(width),
+                ((row + 1) * TILE_SIZE).min(height),
+            );
+
+            let mut stats = RenderStats::default();
+            let FrameArena { fragments, shaded, .. } = &mut *arena;
+            for run in bins[tile].chunk_by(|a, b| a.0 == b.0) {
+                let index = run[0].0;
+                let (object, info) = (&scene.objects[index], &objects[index]);
+                let lighting = Lighting {
+                    lights: setup.lights,
+                    occluders: &info.occluders,
+                    material: object.material,
+                    eye,
+                    bump: setup.bump(object, &info.uniforms),
+                    atmosphere: object.atmosphere,
+                    vertex_colors: object.model.is_some(),
+                };
+                fragments.clear();
+                let mut timings = StageTimings::default();
+                timings.measure(Stage::Rasterization, || {
+                    for &(_, triangle) in run {
+                        let [v1, v2, v3] = &transformed[index].triangles[triangle];
+                        if setup.debug_view == DebugView::Wireframe {
+                            rasterize_edges(v1, v2, v3, bounds, fragments);
+                        } else {
+                            rasterize(v1, v2, v3, &lighting, bounds, fragments);
+                        }
+                    }
+                });
+                stats.fragments += fragments.len();
+                timings.measure(Stage::FragmentShading, || {
+                    if setup.debug_view.replaces_shaders() {
+                        diagnostic_fragments(fragments, setup.debug_view, shaded);
+                    } else if let Some(surface) = setup.surfaces.lookup(object.seed, &object.material, setup.time) {
+                        shade_baked(fragments, surface, &info.uniforms, object, shaded);
+                    } else {
+                        shade_fragments(fragments, &info.uniforms, object, shaded);
+                    }
+                });
+                stats.timings.merge(&timings);
+                if setup.profile_objects {
+                    stats.add_object(index, &timings);
+                }
+            }
+            stats
+        };
+        let tile_stats: Vec<RenderStats> = setup.install(|| (0..tiles).into_par_iter().map(rasterize_tile).collect());
+
+        let tile_arenas: Vec<&FrameArena> = self.tile_arenas.iter_mut().take(tiles).map(|arena| &*lock(arena)).collect();
+        let results = object_stats
+            .iter()
+            .map(|stats| (&[][..], stats))
+            .chain(tile_arenas.iter().zip(&tile_stats).map(|(arena, stats)| (&*arena.shaded, stats)));
+        merge(results, framebuffer, stats);
+    }
+}
+
+/// Adds the counters of every job and writes their fragments, in job order so the
+/// depth test resolves ties the same way every frame; translucent fragments of all jobs
+/// are blended together afterwards
+pub fn merge<'a>(
+    results: impl IntoIterator<Item = (&'a [ShadedFragment], &'a RenderStats)>,
+    framebuffer: &mut Framebuffer,
+    stats: &mut RenderStats,
+) {
+    let mut translucent = Vec::new();
+    for (fragments, job) in results {
+        stats.merge(job);
+        stats.timings.measure(Stage::FragmentShading, || write_opaque(framebuffer, fragments, &mut translucent));
+    }
+    stats.timings.measure(Stage::FragmentShading, || blend_translucent(framebuffer, &mut translucent));
+}
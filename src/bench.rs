@@ -0,0 +1,102 @@
+// bench.rs
+// Headless benchmark: renders a fixed number of frames per scene without presenting them
+// and summarizes frame times and per-stage costs
+
+use std::time::{Duration, Instant};
+use crate::camera::Camera;
+use crate::profiler::{millis, Stage, StageTimings};
+use crate::renderer::Renderer;
+use crate::solar_system::SolarSystem;
+
+/// Simulation step between benchmark frames
+const STEP: f32 = 1.0 / 60.0;
+
+/// Frame times and stage totals of one benchmarked scene
+#[derive(Clone, Debug)]
+pub struct BenchResult {
+    pub name: String,
+    /// Wall time of every measured frame, sorted ascending
+    pub frame_times: Vec<Duration>,
+    /// Stage times summed over all measured frames
+    pub stages: StageTimings,
+    /// Triangles and fragments of the last frame
+    pub triangles: usize,
+    pub fragments: usize,
+}
+
+impl BenchResult {
+    pub fn average(&self) -> Duration {
+        if self.frame_times.is_empty() {
+            return Duration::ZERO;
+        }
+        self.frame_times.iter().sum::<Duration>() / self.frame_times.len() as u32
+    }
+
+    /// Frame time below which `p` percent of the frames fall (nearest rank)
+    pub fn percentile(&self, p: f32) -> Duration {
+        if self.frame_times.is_empty() {
+            return Duration::ZERO;
+        }
+        let rank = (p / 100.0 * self.frame_times.len() as f32).ceil() as usize;
+        self.frame_times[rank.clamp(1, self.frame_times.len()) - 1]
+    }
+
+    /// Average time per frame spent in a stage
+    pub fn stage_average(&self, stage: Stage) -> Duration {
+        self.stages.get(stage) / self.frame_times.len().max(1) as u32
+    }
+
+    /// Human-readable summary
+    pub fn report(&self) -> String {
+        let mut report = format!(
+            "{}: {} frames, {} triangles, {} fragments\n  avg {:.2} ms  p50 {:.2} ms  p95 {:.2} ms  p99 {:.2} ms  max {:.2} ms\n ",
+            self.name,
+            self.frame_times.len(),
+            self.triangles,
+            self.fragments,
+            millis(self.average()),
+            millis(self.percentile(50.0)),
+            millis(self.percentile(95.0)),
+            millis(self.percentile(99.0)),
+            millis(self.percentile(100.0)),
+        );
+        // Presentation is never measured here
+        for stage in Stage::ALL.into_iter().filter(|&s| s != Stage::Presentation) {
+            report.push_str(&format!(" {} {:.2} ms", stage.name(), millis(self.stage_average(stage))));
+        }
+        report
+    }
+}
+
+/// Renders `frames` frames of `scene` on a fixed timestep and times them
+///
+/// One warm-up frame (which bakes the noise cache) is rendered first and not measured
+pub fn bench_scene(renderer: &mut Renderer, name: &str, mut scene: SolarSystem, camera: &Camera, frames: u32) -> BenchResult {
+    renderer.noise_cache.clear();
+    renderer.surface_cache.clear();
+    renderer.draw(&scene, camera, 0.0);
+
+    let mut result = BenchResult {
+        name: name.to_string(),
+        frame_times: Vec::with_capacity(frames as usize),
+        stages: StageTimings::default(),
+        triangles: 0,
+        fragments: 0,
+    };
+
+    for index in 1..=frames {
+        scene.update(STEP);
+        let start = Instant::now();
+        renderer.draw(&scene, camera, index as f32 * STEP);
+        result.frame_times.push(start.elapsed());
+
+        for stage in Stage::ALL {
+            result.stages.add(stage, renderer.stats.timings.get(stage));
+        }
+    }
+
+    result.frame_times.sort_unstable();
+    result.triangles = renderer.stats.triangles;
+    result.fragments = renderer.stats.fragments;
+    result
+}
@@ -0,0 +1,108 @@
+// bloom.rs
+// Bloom post-processing: the bright parts of the frame (stars) bleed light into their
+// surroundings, so emissive bodies glow instead of ending at a hard edge
+
+use raylib::prelude::*;
+use crate::post::{PostEffect, PostFrame};
+
+/// Bloom parameters
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bloom {
+    /// Luminance (0..1) above which pixels start to glow
+    pub threshold: f32,
+    /// Reach of the glow in pixels
+    pub radius: usize,
+    /// Strength of the glow added back onto the frame
+    pub intensity: f32,
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Bloom { threshold: 0.75, radius: 12, intensity: 0.8 }
+    }
+}
+
+/// Normalized Gaussian weights for offsets 0..=radius, with sigma = radius / 2
+fn gaussian_kernel(radius: usize) -> Vec<f32> {
+    let sigma = (radius as f32 / 2.0).max(0.5);
+    let weights: Vec<f32> = (0..=radius).map(|i| (-((i * i) as f32) / (2.0 * sigma * sigma)).exp()).collect();
+    let total = weights[0] + 2.0 * weights[1..].iter().sum::<f32>();
+    weights.iter().map(|w| w / total).collect()
+}
+
+/// One direction of the separable blur over a `width` x `height` image
+fn blur_pass(input: &[Vector3], width: usize, height: usize, kernel: &[f32], horizontal: bool) -> Vec<Vector3> {
+    let radius = kernel.len() as isize - 1;
+    let mut output = vec![Vector3::zero(); input.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = input[y * width + x] * kernel[0];
+            for offset in 1..=radius {
+                for side in [-offset, offset] {
+                    // Edges are clamped, so light doesn't fade out at the border
+                    let (sx, sy) = if horizontal {
+                        ((x as isize + side).clamp(0, width as isize - 1) as usize, y)
+                    } else {
+                        (x, (y as isize + side).clamp(0, height as isize - 1) as usize)
+                    };
+                    sum += input[sy * width + sx] * kernel[offset as usize];
+                }
+            }
+            output[y * width + x] = sum;
+        }
+    }
+    output
+}
+
+impl PostEffect for Bloom {
+    fn name(&self) -> &'static str {
+        "bloom"
+    }
+
+    /// Extracts the pixels brighter than the threshold at half resolution, blurs them
+    /// with a separable Gaussian and adds the result back onto the frame
+    fn apply(&self, frame: &mut PostFrame) {
+        let (width, height) = (frame.width, frame.height);
+        let (half_width, half_height) = (width.div_ceil(2), height.div_ceil(2));
+
+        // Bright pass, averaging 2x2 blocks
+        let mut bright = vec![Vector3::zero(); half_width * half_height];
+        let mut any = false;
+        for y in 0..half_height {
+            for x in 0..half_width {
+                let (x2, y2) = (x * 2, y * 2);
+                let average = (frame.color(x2, y2) + frame.color(x2 + 1, y2) + frame.color(x2, y2 + 1) + frame.color(x2 + 1, y2 + 1)) * 0.25;
+                let luma = 0.2126 * average.x + 0.7152 * average.y + 0.0722 * average.z;
+                if luma > self.threshold {
+                    // Fades in above the threshold up to full strength for white, keeping the hue
+                    let strength = ((luma - self.threshold) / (1.0 - self.threshold).max(1e-3)).min(1.0);
+                    bright[y * half_width + x] = average * strength;
+                    any = true;
+                }
+            }
+        }
+        if !any {
+            return;
+        }
+
+        let kernel = gaussian_kernel((self.radius / 2).max(1));
+        let glow = blur_pass(&bright, half_width, half_height, &kernel, true);
+        let glow = blur_pass(&glow, half_width, half_height, &kernel, false);
+
+        // Upsample bilinearly and add
+        let texel = |x: isize, y: isize| {
+            glow[y.clamp(0, half_height as isize - 1) as usize * half_width + x.clamp(0, half_width as isize - 1) as usize]
+        };
+        for y in 0..height {
+            for x in 0..width {
+                let (u, v) = ((x as f32 + 0.5) / 2.0 - 0.5, (y as f32 + 0.5) / 2.0 - 0.5);
+                let (x0, y0) = (u.floor(), v.floor());
+                let (fx, fy) = (u - x0, v - y0);
+                let (x0, y0) = (x0 as isize, y0 as isize);
+                let light = texel(x0, y0).lerp(texel(x0 + 1, y0), fx)
+                    .lerp(texel(x0, y0 + 1).lerp(texel(x0 + 1, y0 + 1), fx), fy);
+                frame.colors[y * width + x] += light * self.intensity;
+            }
+        }
+    }
+}
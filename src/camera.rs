@@ -1,11 +1,33 @@
 // camera.rs
-// Orbital camera implementation with keyboard controls
+// Orbital and free-fly camera with keyboard and mouse controls
 
 use raylib::prelude::*;
+use crate::input::{Action, InputFrame};
 use crate::matrix::create_view_matrix;
 use std::f32::consts::PI;
 
-/// Orbital camera that rotates around a target point
+/// Default radians of orbit per pixel of mouse drag
+pub const MOUSE_SENSITIVITY: f32 = 0.005;
+
+/// Default fraction of the distance covered per scroll wheel notch
+pub const SCROLL_SENSITIVITY: f32 = 0.1;
+
+/// Default free-fly movement speed in units per second
+pub const FLY_SPEED: f32 = 10.0;
+
+/// How the controls move the camera
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CameraMode {
+    /// Turns around the target, which stays put
+    #[default]
+    Orbit,
+    /// Moves the eye through the scene and turns the view around it (the target is kept
+    /// `distance` ahead of the eye)
+    FreeFly,
+}
+
+/// Camera that orbits a target point or flies freely
+#[derive(Clone, Debug)]
 pub struct Camera {
     // Camera vectors
     pub eye: Vector3,
@@ -21,6 +43,17 @@ pub struct Camera {
     pub rotation_speed: f32,
     pub zoom_speed: f32,
     pub pan_speed: f32,
+    /// Radians of orbit per pixel of mouse drag
+    pub mouse_sensitivity: f32,
+    /// Fraction of the distance covered per scroll wheel notch
+    pub scroll_sensitivity: f32,
+    /// Reverses horizontal and vertical mouse orbiting
+    pub invert_x: bool,
+    pub invert_y: bool,
+
+    pub mode: CameraMode,
+    /// Free-fly movement in units per second
+    pub fly_speed: f32,
 }
 
 impl Camera {
@@ -49,18 +82,66 @@ impl Camera {
             rotation_speed: 0.05,
             zoom_speed: 0.5,
             pan_speed: 0.1,
+            mouse_sensitivity: MOUSE_SENSITIVITY,
+            scroll_sensitivity: SCROLL_SENSITIVITY,
+            invert_x: false,
+            invert_y: false,
+            mode: CameraMode::Orbit,
+            fly_speed: FLY_SPEED,
         }
     }
 
-    /// Updates camera eye position based on spherical coordinates
+    /// Updates camera eye position based on spherical coordinates; in free-fly mode the
+    /// eye stays and the target moves instead
     fn update_eye_position(&mut self) {
         // Clamp pitch to avoid gimbal lock
         self.pitch = self.pitch.clamp(-PI / 2.0 + 0.1, PI / 2.0 - 0.1);
 
         // Convert spherical to Cartesian coordinates
-        self.eye.x = self.target.x + self.distance * self.pitch.cos() * self.yaw.cos();
-        self.eye.y = self.target.y + self.distance * self.pitch.sin();
-        self.eye.z = self.target.z + self.distance * self.pitch.cos() * self.yaw.sin();
+        let offset = Vector3::new(
+            self.pitch.cos() * self.yaw.cos(),
+            self.pitch.sin(),
+            self.pitch.cos() * self.yaw.sin(),
+        ) * self.distance;
+        match self.mode {
+            CameraMode::Orbit => self.eye = self.target + offset,
+            CameraMode::FreeFly => self.target = self.eye - offset,
+        }
+    }
+
+    /// Switches between orbiting and free flight, keeping the current view
+    pub fn toggle_mode(&mut self) {
+        self.mode = match self.mode {
+            CameraMode::Orbit => CameraMode::FreeFly,
+            CameraMode::FreeFly => CameraMode::Orbit,
+        };
+    }
+
+    /// Places the camera on its orbit around the target (used by scripted paths)
+    pub fn set_orbit(&mut self, yaw: f32, pitch: f32, distance: f32) {
+        self.yaw = yaw;
+        self.pitch = pitch;
+        self.distance = distance.max(0.5);
+        self.update_eye_position();
+    }
+
+    /// Places eye and target directly (animations), updating the orbit to match
+    pub fn set_view(&mut self, eye: Vector3, target: Vector3) {
+        let direction = eye - target;
+        let distance = direction.length();
+        if distance > 0.0 {
+            self.eye = eye;
+            self.target = target;
+            self.distance = distance;
+            self.pitch = (direction.y / distance).clamp(-1.0, 1.0).asin();
+            self.yaw = direction.z.atan2(direction.x);
+        }
+    }
+
+    /// Moves the orbit target, carrying the eye along at the same angle and distance
+    pub fn set_target(&mut self, target: Vector3) {
+        self.target = target;
+        self.update_eye_position();
     }
 
     /// Returns the view matrix for this camera
@@ -68,41 +149,61 @@ impl Camera {
         create_view_matrix(self.eye, self.target, self.up)
     }
 
-    /// Processes keyboard input for camera control
+    /// View matrices of a left and right eye `separation` apart, both looking
+    /// parallel to this camera (no toe-in, so there is no vertical parallax)
+    pub fn stereo_view_matrices(&self, separation: f32) -> (Matrix, Matrix) {
+        let forward = (self.target - self.eye).normalized();
+        let offset = forward.cross(self.up).normalized() * (separation * 0.5);
+
+        (
+            create_view_matrix(self.eye - offset, self.target - offset, self.up),
+            create_view_matrix(self.eye + offset, self.target + offset, self.up),
+        )
+    }
+
+    /// Processes the camera actions
     /// 
-    /// Controls:
-    /// - W/S: Pitch (up/down)
-    /// - A/D: Yaw (left/right)
-    /// - Up/Down arrows: Zoom in/out
-    /// - Q/E or Left/Right arrows: Pan horizontally
-    /// - R/F: Pan vertically
-    pub fn process_input(&mut self, window: &RaylibHandle) {
+    /// Actions (default keys):
+    /// - OrbitUp/OrbitDown (W/S): Pitch
+    /// - OrbitLeft/OrbitRight (A/D): Yaw
+    /// - ZoomIn/ZoomOut (Up/Down arrows): Distance
+    /// - PanLeft/PanRight (Q/E or Left/Right arrows): Pan horizontally
+    /// - PanUp/PanDown (R/F): Pan vertically
+    /// - MouseOrbit (left drag): Yaw and pitch
+    /// - MousePan (right or middle drag): Pan in the view plane
+    /// - Scroll wheel: Distance
+    pub fn process_input(&mut self, input: &InputFrame) {
+        if self.mode == CameraMode::FreeFly {
+            self.process_fly(input);
+            return;
+        }
+
         // Yaw rotation (horizontal)
-        if window.is_key_down(KeyboardKey::KEY_A) {
+        if input.is_down(Action::OrbitLeft) {
             self.yaw += self.rotation_speed;
             self.update_eye_position();
         }
-        if window.is_key_down(KeyboardKey::KEY_D) {
+        if input.is_down(Action::OrbitRight) {
             self.yaw -= self.rotation_speed;
             self.update_eye_position();
         }
 
         // Pitch rotation (vertical)
-        if window.is_key_down(KeyboardKey::KEY_W) {
+        if input.is_down(Action::OrbitUp) {
             self.pitch += self.rotation_speed;
             self.update_eye_position();
         }
-        if window.is_key_down(KeyboardKey::KEY_S) {
+        if input.is_down(Action::OrbitDown) {
             self.pitch -= self.rotation_speed;
             self.update_eye_position();
         }
 
         // Zoom (distance control)
-        if window.is_key_down(KeyboardKey::KEY_UP) {
+        if input.is_down(Action::ZoomIn) {
             self.distance = (self.distance - self.zoom_speed).max(0.5);
             self.update_eye_position();
         }
-        if window.is_key_down(KeyboardKey::KEY_DOWN) {
+        if input.is_down(Action::ZoomOut) {
             self.distance += self.zoom_speed;
             self.update_eye_position();
         }
@@ -123,25 +224,102 @@ impl Camera {
         let right = Vector3::new(forward_normalized.z, 0.0, -forward_normalized.x);
 
         // Horizontal panning
-        if window.is_key_down(KeyboardKey::KEY_Q) || window.is_key_down(KeyboardKey::KEY_LEFT) {
+        if input.is_down(Action::PanLeft) {
             self.target.x += right.x * self.pan_speed;
             self.target.z += right.z * self.pan_speed;
             self.update_eye_position();
         }
-        if window.is_key_down(KeyboardKey::KEY_E) || window.is_key_down(KeyboardKey::KEY_RIGHT) {
+        if input.is_down(Action::PanRight) {
             self.target.x -= right.x * self.pan_speed;
             self.target.z -= right.z * self.pan_speed;
             self.update_eye_position();
         }
 
         // Vertical panning
-        if window.is_key_down(KeyboardKey::KEY_R) {
+        if input.is_down(Action::PanUp) {
             self.target.y += self.pan_speed;
             self.update_eye_position();
         }
-        if window.is_key_down(KeyboardKey::KEY_F) {
+        if input.is_down(Action::PanDown) {
             self.target.y -= self.pan_speed;
             self.update_eye_position();
         }
+
+        self.process_mouse(input);
+    }
+
+    /// Free-fly controls
+    ///
+    /// Actions (default keys):
+    /// - OrbitUp/OrbitDown (W/S): Move forward/back along the view direction
+    /// - OrbitLeft/OrbitRight (A/D): Strafe
+    /// - PanUp/PanDown (R/F): Move up/down
+    /// - PanLeft/PanRight (Q/E or Left/Right arrows), ZoomIn/ZoomOut (Up/Down arrows): Look around
+    /// - MouseOrbit (left drag): Look around
+    /// - Scroll wheel: Movement speed; Modifier (Shift) moves 4x faster
+    fn process_fly(&mut self, input: &InputFrame) {
+        let sign = |invert: bool| if invert { -1.0 } else { 1.0 };
+        let axis = |positive: Action, negative: Action| {
+            input.is_down(positive) as i32 as f32 - input.is_down(negative) as i32 as f32
+        };
+
+        // Looking: dragging right turns right, dragging down looks down
+        let mut turn = Vector2::new(
+            axis(Action::PanRight, Action::PanLeft),
+            axis(Action::ZoomOut, Action::ZoomIn),
+        ) * self.rotation_speed;
+        if input.is_down(Action::MouseOrbit) {
+            turn += input.mouse_delta * self.mouse_sensitivity;
+        }
+        self.yaw += turn.x * sign(self.invert_x);
+        self.pitch += turn.y * sign(self.invert_y);
+
+        if input.wheel != 0.0 {
+            self.fly_speed = (self.fly_speed * 1.25f32.powf(input.wheel)).clamp(0.1, 1000.0);
+        }
+
+        self.update_eye_position();
+        let forward = (self.target - self.eye).normalized();
+        let right = forward.cross(self.up).normalized();
+        let movement = forward * axis(Action::OrbitUp, Action::OrbitDown)
+            + right * axis(Action::OrbitRight, Action::OrbitLeft)
+            + self.up * axis(Action::PanUp, Action::PanDown);
+        if movement != Vector3::zero() {
+            let boost = if input.is_down(Action::Modifier) { 4.0 } else { 1.0 };
+            let step = movement.normalized() * (self.fly_speed * boost * input.delta_time);
+            self.eye += step;
+            self.target += step;
+        }
+    }
+
+    /// Mouse drags and the scroll wheel
+    fn process_mouse(&mut self, input: &InputFrame) {
+        let delta = input.mouse_delta;
+        let orbiting = input.is_down(Action::MouseOrbit) && delta != Vector2::zero();
+        let panning = !orbiting && input.is_down(Action::MousePan) && delta != Vector2::zero();
+        if !orbiting && !panning && input.wheel == 0.0 {
+            return;
+        }
+
+        if orbiting {
+            // Dragging right turns the scene right, dragging down tilts it toward the viewer
+            let sign = |invert: bool| if invert { -1.0 } else { 1.0 };
+            self.yaw += delta.x * self.mouse_sensitivity * sign(self.invert_x);
+            self.pitch += delta.y * self.mouse_sensitivity * sign(self.invert_y);
+        }
+        if panning {
+            // The scene follows the cursor; farther away a pixel covers more ground
+            let forward = (self.target - self.eye).normalized();
+            let right = forward.cross(self.up).normalized();
+            let up = right.cross(forward);
+            let scale = self.distance * self.mouse_sensitivity * 0.25;
+            self.target += (right * -delta.x + up * delta.y) * scale;
+        }
+        if input.wheel != 0.0 {
+            let factor = (1.0 - self.scroll_sensitivity.clamp(0.0, 0.9)).powf(input.wheel);
+            self.distance = (self.distance * factor).max(0.5);
+        }
+
+        self.update_eye_position();
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,190 @@
+// camera_path.rs
+// Camera animation: keyframes (eye, target, time) joined by a Catmull-Rom spline and eased
+// per segment, plus a cinematic tour that flies past every planet for demo videos
+
+use raylib::prelude::*;
+use crate::camera::Camera;
+use crate::solar_system::SolarSystem;
+
+/// Seconds spent flying from one stop of the tour to the next
+const TOUR_TRAVEL: f32 = 4.0;
+
+/// Seconds spent sweeping past each body
+const TOUR_LINGER: f32 = 3.0;
+
+/// Speed profile within each segment between two keyframes
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Easing {
+    Linear,
+    /// Starts and stops gently (smoothstep)
+    #[default]
+    Smooth,
+    /// Stronger acceleration and braking (cubic in-out)
+    Cubic,
+}
+
+impl Easing {
+    /// Eased progress for a linear progress `t` in [0, 1]
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::Smooth => t * t * (3.0 - 2.0 * t),
+            Easing::Cubic => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+        }
+    }
+}
+
+/// Camera placement at a moment of the animation
+#[derive(Clone, Debug, PartialEq)]
+pub struct CameraKey {
+    /// Seconds from the start of the animation
+    pub time: f32,
+    pub eye: Vector3,
+    pub target: Vector3,
+    /// Body (index into `SolarSystem::objects`) that `eye` and `target` are relative to,
+    /// so the key moves with it; None for world positions
+    pub body: Option<usize>,
+}
+
+impl CameraKey {
+    /// A key at world positions
+    pub fn new(time: f32, eye: Vector3, target: Vector3) -> Self {
+        CameraKey { time, eye, target, body: None }
+    }
+
+    /// Eye and target in world space for the current state of the scene
+    fn resolve(&self, system: &SolarSystem) -> (Vector3, Vector3) {
+        let origin = self.body
+            .and_then(|body| system.objects.get(body))
+            .map_or(Vector3::zero(), |object| object.position);
+        (origin + self.eye, origin + self.target)
+    }
+}
+
+/// Uniform Catmull-Rom spline through p1 and p2
+fn catmull_rom(p0: Vector3, p1: Vector3, p2: Vector3, p3: Vector3, t: f32) -> Vector3 {
+    let (t2, t3) = (t * t, t * t * t);
+    (p1 * 2.0 + (p2 - p0) * t + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2 + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3) * 0.5
+}
+
+/// A camera path through keyframes sorted by time
+#[derive(Clone, Debug, Default)]
+pub struct CameraAnimation {
+    keys: Vec<CameraKey>,
+    pub easing: Easing,
+    /// Curve through the keys (Catmull-Rom) instead of straight lines between them
+    pub spline: bool,
+}
+
+impl CameraAnimation {
+    /// An animation through `keys`, which are sorted by time
+    pub fn new(mut keys: Vec<CameraKey>, easing: Easing, spline: bool) -> Self {
+        keys.sort_by(|a, b| a.time.total_cmp(&b.time));
+        CameraAnimation { keys, easing, spline }
+    }
+
+    /// Adds a keyframe, keeping the keys sorted
+    pub fn add(&mut self, key: CameraKey) {
+        let index = self.keys.partition_point(|k| k.time <= key.time);
+        self.keys.insert(index, key);
+    }
+
+    pub fn keys(&self) -> &[CameraKey] {
+        &self.keys
+    }
+
+    /// Time of the last key
+    pub fn duration(&self) -> f32 {
+        self.keys.last().map_or(0.0, |key| key.time)
+    }
+
+    /// Eye and target at `time` (clamped to the animation), None without keys
+    pub fn sample(&self, time: f32, system: &SolarSystem) -> Option<(Vector3, Vector3)> {
+        let last = self.keys.len().checked_sub(1)?;
+        let segment = self.keys.partition_point(|key| key.time <= time).clamp(1, last.max(1)) - 1;
+        if last == 0 {
+            return Some(self.keys[0].resolve(system));
+        }
+
+        let (start, end) = (&self.keys[segment], &self.keys[segment + 1]);
+        let span = end.time - start.time;
+        let t = if span > 0.0 { (time - start.time) / span } else { 1.0 };
+        let t = self.easing.apply(t);
+
+        let (eye1, target1) = start.resolve(system);
+        let (eye2, target2) = end.resolve(system);
+        if !self.spline {
+            return Some((eye1.lerp(eye2, t), target1.lerp(target2, t)));
+        }
+        // The curve's outer control points are the neighbouring keys, or the ends themselves
+        let (eye0, target0) = self.keys[segment.saturating_sub(1)].resolve(system);
+        let (eye3, target3) = self.keys[(segment + 2).min(last)].resolve(system);
+        Some((
+            catmull_rom(eye0, eye1, eye2, eye3, t),
+            catmull_rom(target0, target1, target2, target3, t),
+        ))
+    }
+
+    /// Places the camera where the animation is at `time`
+    pub fn apply(&self, camera: &mut Camera, time: f32, system: &SolarSystem) {
+        if let Some((eye, target)) = self.sample(time, system) {
+            camera.set_view(eye, target);
+        }
+    }
+
+    /// A tour that leaves from the camera's current view, sweeps past every planet and
+    /// moon in turn and returns to where it started
+    pub fn tour(camera: &Camera, system: &SolarSystem) -> Self {
+        let mut keys = vec![CameraKey::new(0.0, camera.eye, camera.target)];
+        let mut time = 0.0;
+
+        for (index, object) in system.objects.iter().enumerate() {
+            if object.material.emissive || object.ring.is_some() {
+                continue;
+            }
+            // Seen from the sunlit side, slightly above, sweeping around the body
+            let outward = Vector3::new(object.position.x, 0.0, object.position.z);
+            let outward = if outward.length() > 1e-3 { outward.normalized() } else { Vector3::new(1.0, 0.0, 0.0) };
+            let side = Vector3::new(-outward.z, 0.0, outward.x);
+            let distance = object.scale * 4.0 + 1.0;
+            let up = Vector3::new(0.0, distance * 0.35, 0.0);
+
+            for (delay, sweep) in [(TOUR_TRAVEL, -0.6), (TOUR_LINGER, 0.6)] {
+                time += delay;
+                let eye = (-outward + side * sweep).normalized() * distance + up;
+                keys.push(CameraKey { time, eye, target: Vector3::zero(), body: Some(index) });
+            }
+        }
+
+        keys.push(CameraKey::new(time + TOUR_TRAVEL, camera.eye, camera.target));
+        CameraAnimation::new(keys, Easing::Smooth, true)
+    }
+}
+
+/// An animation being played on the camera
+#[derive(Clone, Debug)]
+pub struct Playback {
+    pub animation: CameraAnimation,
+    /// Seconds since playback started
+    pub time: f32,
+}
+
+impl Playback {
+    pub fn new(animation: CameraAnimation) -> Self {
+        Playback { animation, time: 0.0 }
+    }
+
+    /// Advances by `delta_time` and moves the camera; false once the animation is over
+    pub fn advance(&mut self, camera: &mut Camera, system: &SolarSystem, delta_time: f32) -> bool {
+        self.time += delta_time;
+        self.animation.apply(camera, self.time.min(self.animation.duration()), system);
+        self.time < self.animation.duration()
+    }
+}
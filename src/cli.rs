@@ -0,0 +1,366 @@
+// cli.rs
+// Command-line options for the renderer front-end
+
+use crate::backend::BACKEND_NAMES;
+use crate::config::{Config, Quality};
+use crate::export::EXPORT_FORMATS;
+use crate::solar_system::SolarSystem;
+use crate::generator::GeneratorParams;
+
+/// Scene presets selectable at startup
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Preset {
+    Basic,
+    Alien,
+    /// Procedural system built from the seed, see `SolarSystem::generate`
+    Generated,
+}
+
+impl Preset {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "basic" => Some(Preset::Basic),
+            "alien" => Some(Preset::Alien),
+            "generated" => Some(Preset::Generated),
+            _ => None,
+        }
+    }
+
+    /// Name accepted by `--preset`
+    pub fn key(self) -> &'static str {
+        match self {
+            Preset::Basic => "basic",
+            Preset::Alien => "alien",
+            Preset::Generated => "generated",
+        }
+    }
+
+    /// Display name of the preset
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::Basic => "Basic Solar System",
+            Preset::Alien => "Alien Binary Star System",
+            Preset::Generated => "Generated System",
+        }
+    }
+
+    pub const ALL: [Preset; 3] = [Preset::Basic, Preset::Alien, Preset::Generated];
+
+    /// Builds the solar system for this preset with the given seed
+    pub fn build(self, seed: u32) -> SolarSystem {
+        let mut system = match self {
+            Preset::Basic => SolarSystem::create_basic_system(),
+            Preset::Alien => SolarSystem::create_alien_system(),
+            Preset::Generated => return SolarSystem::generate(seed, &GeneratorParams::default()),
+        };
+        system.reseed(seed);
+        system
+    }
+}
+
+/// Startup configuration: config.toml values overridden by command-line flags
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub width: i32,
+    pub height: i32,
+    pub fullscreen: bool,
+    pub target_fps: u32,
+    pub vsync: bool,
+    /// Vertical field of view in degrees
+    pub fov: f32,
+    /// Fraction of the window's resolution rendered
+    pub render_scale: f32,
+    /// Quality bundle applied over the config's render options
+    pub quality: Option<Quality>,
+    pub scene: Option<String>,
+    /// Behavior script run with the scene, overriding the scene file's own
+    pub script: Option<String>,
+    pub seed: u32,
+    pub preset: Preset,
+    pub headless: bool,
+    /// Image written by a headless run
+    pub output: String,
+    /// Video file or image-sequence directory to export to
+    pub export: Option<String>,
+    pub export_fps: u32,
+    /// Length of the exported video in seconds
+    pub duration: f32,
+    /// Exact number of exported frames, overriding the duration
+    pub frames: Option<u32>,
+    /// Image format of exported sequences, one of `EXPORT_FORMATS`
+    pub export_format: String,
+    /// Export the cinematic tour instead of an orbit around the system
+    pub tour: bool,
+    /// Equirectangular 360° image to render from the camera position
+    pub panorama: Option<String>,
+    /// Width of the panorama in pixels (height is half)
+    pub panorama_width: usize,
+    /// Per-frame stage timings written as CSV
+    pub profile_csv: Option<String>,
+    /// Per-frame stage timings written as a Chrome trace
+    pub profile_trace: Option<String>,
+    /// Time every body, shown in the HUD and added to the Chrome trace
+    pub profile_objects: bool,
+    /// Session file to record input to
+    pub record: Option<String>,
+    /// Session file to play back
+    pub replay: Option<String>,
+    /// Log level filter
+    pub log: String,
+    /// Render backend to start with, one of `BACKEND_NAMES`
+    pub backend: String,
+    /// Start in red/cyan stereo mode
+    pub anaglyph: bool,
+    /// Render threads (0 = one per core)
+    pub threads: usize,
+    /// Run the headless benchmark and exit
+    pub bench: bool,
+    /// Frames rendered per preset by the benchmark
+    pub bench_frames: u32,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            width: 1300,
+            height: 900,
+            fullscreen: false,
+            target_fps: 60,
+            vsync: false,
+            fov: 60.0,
+            render_scale: 1.0,
+            quality: None,
+            scene: None,
+            script: None,
+            seed: 0,
+            preset: Preset::Basic,
+            headless: false,
+            output: "frame.png".to_string(),
+            export: None,
+            export_fps: 30,
+            duration: 10.0,
+            frames: None,
+            export_format: "png".to_string(),
+            tour: false,
+            panorama: None,
+            panorama_width: 2048,
+            profile_csv: None,
+            profile_trace: None,
+            profile_objects: false,
+            record: None,
+            replay: None,
+            log: "info".to_string(),
+            backend: "rasterizer".to_string(),
+            anaglyph: false,
+            threads: 0,
+            bench: false,
+            bench_frames: 120,
+        }
+    }
+}
+
+pub const USAGE: &str = "\
+Usage: lab5 [OPTIONS]
+
+Options:
+  --width <PX>          Window width (default 1300)
+  --height <PX>         Window height (default 900)
+  --resolution <WxH>    Window size, e.g. 1920x1080
+  --fullscreen          Start in fullscreen
+  --fps <N>             Target frames per second (default 60, 0 = unlimited)
+  --vsync               Wait for the display's refresh between frames
+  --fov <DEGREES>       Vertical field of view (default 60)
+  --render-scale <F>    Fraction of the window resolution rendered, 0.25..2
+                        (default 1)
+  --quality <NAME>      Render settings bundle: low | medium | high | ultra
+  --scene <PATH>        Scene file to load instead of a preset; reloaded
+                        automatically when it changes
+  --script <PATH>       Behavior script to run with the scene (also reloaded
+                        when it changes)
+  --seed <N>            Seed of the scene's noise and orbits (default 0)
+  --preset <NAME>       Starting preset: basic | alien | generated (default basic)
+  --headless            Render one frame without opening a window
+  --output <PATH>       Image written by --headless (default frame.png)
+  --export <PATH>       Render a video (.mp4/.mkv/.webm/.mov via ffmpeg) or a
+                        numbered image sequence (directory) of an orbiting
+                        camera; the size comes from --resolution
+  --export-fps <N>      Frame rate of the export (default 30)
+  --duration <SECS>     Length of the export (default 10)
+  --frames <N>          Exact number of frames to export, instead of --duration
+  --export-format <EXT> Image format of sequences: png | ppm (default png)
+  --tour                Export the cinematic tour past every planet, stretched
+                        over the export's length, instead of an orbit
+  --panorama <PATH>     Render a 360° equirectangular image from the camera
+                        position (png, jpg or ppm) and exit
+  --panorama-width <PX> Width of the panorama (default 2048, height is half)
+  --profile-csv <PATH>  Write per-frame stage timings as CSV
+  --profile-trace <PATH>
+                        Write per-frame stage timings as a Chrome trace (JSON)
+  --profile-objects     Also time every body: the slowest are listed in the HUD
+                        and each gets a span in the Chrome trace
+  --record <PATH>       Record the session (input, settings, scene loads)
+  --replay <PATH>       Play back a recorded session frame by frame
+  --log <FILTER>        Log level filter, e.g. debug or warn,lab5::scene_file=debug
+  --backend <NAME>      Render backend: rasterizer | raytracer | gpu (default
+                        rasterizer, cycle with T); gpu needs a window
+  --raytrace            Same as --backend raytracer
+  --anaglyph            Red/cyan stereo 3D (toggle with V)
+  --threads <N>         Render threads (default 0 = one per core)
+  --bench               Render every preset headlessly and report frame times
+  --bench-frames <N>    Frames rendered per preset by --bench (default 120)
+  -h, --help            Show this help";
+
+impl Options {
+    /// Options taken from the config file, before any flag is applied
+    pub fn from_config(config: &Config) -> Self {
+        let preset = Preset::from_name(&config.scene.preset).unwrap_or_else(|| {
+            log::warn!("unknown preset '{}' in config, using basic", config.scene.preset);
+            Preset::Basic
+        });
+
+        Options {
+            width: config.window.width,
+            height: config.window.height,
+            fullscreen: config.window.fullscreen,
+            target_fps: config.window.target_fps,
+            vsync: config.window.vsync,
+            fov: config.render.fov,
+            render_scale: config.render.scale,
+            seed: config.scene.seed,
+            preset,
+            log: config.log.level.clone(),
+            threads: config.render.threads,
+            ..Options::default()
+        }
+    }
+
+    /// Parses options from the process arguments (without the program name),
+    /// starting from `defaults`
+    /// Returns Ok(None) when help was requested
+    pub fn parse(args: impl IntoIterator<Item = String>, defaults: Options) -> Result<Option<Self>, String> {
+        let mut options = defaults;
+        let mut args = args.into_iter();
+        let mut scale_given = false;
+
+        while let Some(arg) = args.next() {
+            // Accept both "--flag value" and "--flag=value"
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+            let mut value = || {
+                inline.clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("missing value for {flag}"))
+            };
+
+            match flag.as_str() {
+                "-h" | "--help" => return Ok(None),
+                "--width" => options.width = parse_number(&value()?, "--width")?,
+                "--height" => options.height = parse_number(&value()?, "--height")?,
+                "--resolution" => {
+                    let resolution = value()?;
+                    let (w, h) = resolution
+                        .split_once('x')
+                        .ok_or_else(|| format!("invalid resolution '{resolution}', expected WxH"))?;
+                    options.width = parse_number(w, "--resolution")?;
+                    options.height = parse_number(h, "--resolution")?;
+                }
+                "--fullscreen" => options.fullscreen = true,
+                "--fps" => options.target_fps = parse_number(&value()?, "--fps")?,
+                "--vsync" => options.vsync = true,
+                "--fov" => options.fov = parse_number(&value()?, "--fov")?,
+                "--render-scale" => {
+                    options.render_scale = parse_number(&value()?, "--render-scale")?;
+                    scale_given = true;
+                }
+                "--quality" => {
+                    let name = value()?;
+                    options.quality = Some(Quality::from_name(&name).ok_or_else(|| {
+                        format!("unknown quality '{name}' (expected {})", Quality::NAMES.join(", "))
+                    })?);
+                }
+                "--scene" => options.scene = Some(value()?),
+                "--script" => options.script = Some(value()?),
+                "--seed" => options.seed = parse_number(&value()?, "--seed")?,
+                "--preset" => {
+                    let name = value()?;
+                    options.preset = Preset::from_name(&name)
+                        .ok_or_else(|| format!("unknown preset '{name}' (expected basic, alien or generated)"))?;
+                }
+                "--headless" => options.headless = true,
+                "--output" => options.output = value()?,
+                "--export" => options.export = Some(value()?),
+                "--export-fps" => options.export_fps = parse_number(&value()?, "--export-fps")?,
+                "--panorama" => options.panorama = Some(value()?),
+                "--panorama-width" => options.panorama_width = parse_number(&value()?, "--panorama-width")?,
+                "--profile-csv" => options.profile_csv = Some(value()?),
+                "--profile-trace" => options.profile_trace = Some(value()?),
+                "--profile-objects" => options.profile_objects = true,
+                "--record" => options.record = Some(value()?),
+                "--replay" => options.replay = Some(value()?),
+                "--log" => options.log = value()?,
+                "--backend" => {
+                    let name = value()?;
+                    if !BACKEND_NAMES.contains(&name.as_str()) {
+                        return Err(format!("unknown backend '{name}' (expected {})", BACKEND_NAMES.join(", ")));
+                    }
+                    options.backend = name;
+                }
+                "--raytrace" => options.backend = "raytracer".to_string(),
+                "--anaglyph" => options.anaglyph = true,
+                "--threads" => options.threads = parse_number(&value()?, "--threads")?,
+                "--bench" => options.bench = true,
+                "--bench-frames" => options.bench_frames = parse_number(&value()?, "--bench-frames")?,
+                "--duration" => options.duration = parse_number(&value()?, "--duration")?,
+                "--frames" => options.frames = Some(parse_number(&value()?, "--frames")?),
+                "--export-format" => {
+                    let name = value()?;
+                    if !EXPORT_FORMATS.contains(&name.as_str()) {
+                        return Err(format!("unknown export format '{name}' (expected {})", EXPORT_FORMATS.join(", ")));
+                    }
+                    options.export_format = name;
+                }
+                "--tour" => options.tour = true,
+                _ => return Err(format!("unknown option '{flag}'")),
+            }
+        }
+
+        if options.width <= 0 || options.height <= 0 {
+            return Err("resolution must be positive".to_string());
+        }
+        if let Some(quality) = options.quality
+            && !scale_given
+        {
+            options.render_scale = quality.render_scale();
+        }
+        if !(0.25..=2.0).contains(&options.render_scale) {
+            return Err("--render-scale must be between 0.25 and 2".to_string());
+        }
+        if !(10.0..=150.0).contains(&options.fov) {
+            return Err("--fov must be between 10 and 150 degrees".to_string());
+        }
+        if options.export_fps == 0 || options.duration <= 0.0 || options.frames == Some(0) {
+            return Err("export frame rate, duration and frame count must be positive".to_string());
+        }
+
+        if options.panorama_width < 4 {
+            return Err("--panorama-width must be at least 4".to_string());
+        }
+        if options.bench_frames == 0 {
+            return Err("--bench-frames must be positive".to_string());
+        }
+        if options.record.is_some() && options.replay.is_some() {
+            return Err("--record and --replay cannot be combined".to_string());
+        }
+        if (options.scene.is_some() || options.script.is_some()) && (options.record.is_some() || options.replay.is_some()) {
+            return Err("sessions can only be recorded with presets, not with --scene or --script".to_string());
+        }
+
+        Ok(Some(options))
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(text: &str, flag: &str) -> Result<T, String> {
+    text.parse().map_err(|_| format!("invalid value '{text}' for {flag}"))
+}
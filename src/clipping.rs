@@ -0,0 +1,167 @@
+// clipping.rs
+// Clip-space clipping between the vertex stage and the rasterizer: triangles crossing the
+// view frustum are cut against its six planes and re-triangulated as fans
+
+use raylib::prelude::*;
+use crate::matrix::multiply_matrix_vector4;
+use crate::vertex::Vertex;
+
+/// Frustum planes in clip space; a point is inside when `distance` is non-negative
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Plane {
+    Near,
+    Far,
+    Left,
+    Right,
+    Bottom,
+    Top,
+}
+
+impl Plane {
+    const ALL: [Plane; 6] = [Plane::Near, Plane::Far, Plane::Left, Plane::Right, Plane::Bottom, Plane::Top];
+
+    #[inline]
+    fn distance(self, p: &Vector4) -> f32 {
+        match self {
+            Plane::Near => p.z + p.w,
+            Plane::Far => p.w - p.z,
+            Plane::Left => p.x + p.w,
+            Plane::Right => p.w - p.x,
+            Plane::Bottom => p.y + p.w,
+            Plane::Top => p.w - p.y,
+        }
+    }
+}
+
+/// Bit per plane the point lies outside of
+#[inline]
+fn outcode(p: &Vector4) -> u8 {
+    Plane::ALL
+        .iter()
+        .enumerate()
+        .filter(|(_, plane)| plane.distance(p) < 0.0)
+        .fold(0, |code, (bit, _)| code | (1 << bit))
+}
+
+/// Vertex between `a` (t = 0) and `b` (t = 1), every attribute interpolated linearly in
+/// clip space; the screen position is rebuilt with the perspective divide
+fn lerp_vertex(a: &Vertex, b: &Vertex, t: f32, viewport: &Matrix) -> Vertex {
+    let mix3 = |a: Vector3, b: Vector3| a + (b - a) * t;
+    let clip = Vector4::new(
+        a.clip_position.x + (b.clip_position.x - a.clip_position.x) * t,
+        a.clip_position.y + (b.clip_position.y - a.clip_position.y) * t,
+        a.clip_position.z + (b.clip_position.z - a.clip_position.z) * t,
+        a.clip_position.w + (b.clip_position.w - a.clip_position.w) * t,
+    );
+
+    let mut normal = mix3(a.transformed_normal, b.transformed_normal);
+    normal.normalize();
+
+    Vertex {
+        position: mix3(a.position, b.position),
+        normal: mix3(a.normal, b.normal),
+        tex_coords: a.tex_coords + (b.tex_coords - a.tex_coords) * t,
+        color: mix3(a.color, b.color),
+        emission: mix3(a.emission, b.emission),
+        transformed_position: to_screen(&clip, viewport),
+        world_position: mix3(a.world_position, b.world_position),
+        transformed_normal: normal,
+        clip_position: clip,
+    }
+}
+
+/// Perspective divide and viewport transform of a clip-space position inside the frustum
+#[inline]
+pub fn to_screen(clip: &Vector4, viewport: &Matrix) -> Vector3 {
+    let ndc = Vector4::new(clip.x / clip.w, clip.y / clip.w, clip.z / clip.w, 1.0);
+    let screen = multiply_matrix_vector4(viewport, &ndc);
+    Vector3::new(screen.x, screen.y, screen.z)
+}
+
+/// Whether any part of a world-space sphere can be inside the view frustum: false only
+/// when it lies entirely outside one of the six planes, so whole bodies can be skipped
+/// before their vertex stage
+pub fn sphere_in_frustum(center: Vector3, radius: f32, view: &Matrix, projection: &Matrix) -> bool {
+    // The view is rigid, so the radius is the same in view space
+    let p = multiply_matrix_vector4(view, &Vector4::new(center.x, center.y, center.z, 1.0));
+    let columns = [
+        Vector4::new(projection.m0, projection.m1, projection.m2, projection.m3),
+        Vector4::new(projection.m4, projection.m5, projection.m6, projection.m7),
+        Vector4::new(projection.m8, projection.m9, projection.m10, projection.m11),
+        Vector4::new(projection.m12, projection.m13, projection.m14, projection.m15),
+    ];
+    Plane::ALL.iter().all(|plane| {
+        // `distance` is linear in the clip position, so applying it to the projection's
+        // columns gives the plane's equation in view space
+        let [a, b, c, d] = columns.map(|column| plane.distance(&column));
+        let length = (a * a + b * b + c * c).sqrt();
+        length == 0.0 || (a * p.x + b * p.y + c * p.z + d) / length >= -radius
+    })
+}
+
+/// Whether a sphere is hidden from `eye` behind an opaque one: it lies inside the cone
+/// of the occluder's silhouette and entirely beyond the occluder's center, which every
+/// ray in that cone has hit before
+pub fn sphere_occluded(eye: Vector3, center: Vector3, radius: f32, occluder: Vector3, occluder_radius: f32) -> bool {
+    let (to_sphere, to_occluder) = (center - eye, occluder - eye);
+    let (distance, occluder_distance) = (to_sphere.length(), to_occluder.length());
+    if distance - radius < occluder_distance || occluder_distance <= occluder_radius {
+        return false;
+    }
+    let between = (to_sphere.dot(to_occluder) / (distance * occluder_distance)).clamp(-1.0, 1.0).acos();
+    between + (radius / distance).asin() <= (occluder_radius / occluder_distance).asin()
+}
+
+/// Clips triangles, reusing its polygon buffers between calls
+#[derive(Default)]
+pub struct Clipper {
+    polygon: Vec<Vertex>,
+    scratch: Vec<Vertex>,
+}
+
+impl Clipper {
+    /// Appends the parts of the triangle inside the view frustum to `out`: the triangle
+    /// itself when it is fully inside, nothing when it is fully outside one plane,
+    /// otherwise a fan over the clipped polygon
+    pub fn clip(&mut self, triangle: [&Vertex; 3], viewport: &Matrix, out: &mut Vec<[Vertex; 3]>) {
+        let codes = triangle.map(|v| outcode(&v.clip_position));
+        if codes.iter().all(|&code| code == 0) {
+            out.push(triangle.map(Vertex::clone));
+            return;
+        }
+        if codes[0] & codes[1] & codes[2] != 0 {
+            return;
+        }
+
+        // Sutherland-Hodgman, one plane at a time
+        self.polygon.clear();
+        self.polygon.extend(triangle.map(Vertex::clone));
+        for plane in Plane::ALL {
+            let bit = 1 << (plane as u8);
+            if codes.iter().all(|&code| code & bit == 0) {
+                continue;
+            }
+
+            self.scratch.clear();
+            for (i, current) in self.polygon.iter().enumerate() {
+                let next = &self.polygon[(i + 1) % self.polygon.len()];
+                let (d_current, d_next) = (plane.distance(&current.clip_position), plane.distance(&next.clip_position));
+                if d_current >= 0.0 {
+                    self.scratch.push(current.clone());
+                }
+                if (d_current >= 0.0) != (d_next >= 0.0) {
+                    let t = d_current / (d_current - d_next);
+                    self.scratch.push(lerp_vertex(current, next, t, viewport));
+                }
+            }
+            std::mem::swap(&mut self.polygon, &mut self.scratch);
+            if self.polygon.len() < 3 {
+                return;
+            }
+        }
+
+        for i in 1..self.polygon.len() - 1 {
+            out.push([self.polygon[0].clone(), self.polygon[i].clone(), self.polygon[i + 1].clone()]);
+        }
+    }
+}
@@ -0,0 +1,225 @@
+// collision.rs
+// Collisions between bodies: spheres that start to overlap are reported as events and,
+// depending on the system's response, merged into one body or shattered into debris
+
+use raylib::prelude::*;
+use std::fmt;
+use crate::mesh::SPHERE_RADIUS;
+use crate::particles::{Emitter, ParticleShape, ParticleSystem};
+use crate::shader_system::ShaderType;
+use crate::solar_system::{CelestialObject, SolarSystem};
+
+/// Fragments a body breaks into with `CollisionResponse::Debris`
+const DEBRIS_COUNT: usize = 8;
+
+/// Size of each fragment relative to the shattered body
+const DEBRIS_SCALE: f32 = 0.3;
+
+/// Sparks thrown out where two bodies merge or shatter, and how long they glow
+const SPARKS: usize = 120;
+const SPARK_LIFETIME: f32 = 1.5;
+
+/// What happens when two bodies touch
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum CollisionResponse {
+    /// The contact is only reported
+    #[default]
+    Log,
+    /// The larger body absorbs the smaller one, growing to hold both volumes
+    Merge,
+    /// The smaller body shatters into fragments that orbit the larger one
+    Debris,
+}
+
+impl CollisionResponse {
+    /// Response for its scene file name (`log`, `merge`, `debris`)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "log" => Some(CollisionResponse::Log),
+            "merge" => Some(CollisionResponse::Merge),
+            "debris" => Some(CollisionResponse::Debris),
+            _ => None,
+        }
+    }
+}
+
+/// Two bodies that came into contact
+#[derive(Clone, Debug, PartialEq)]
+pub struct Collision {
+    /// Simulated time of the contact
+    pub time: f32,
+    /// Names of the larger and the smaller body (see `SolarSystem::display_name`)
+    pub larger: String,
+    pub smaller: String,
+    /// Midpoint between both centers
+    pub position: Vector3,
+    pub response: CollisionResponse,
+}
+
+impl fmt::Display for Collision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let outcome = match self.response {
+            CollisionResponse::Log => "collided with",
+            CollisionResponse::Merge => "absorbed",
+            CollisionResponse::Debris => "shattered",
+        };
+        write!(f, "t={:.1} s: {} {outcome} {}", self.time, self.larger, self.smaller)
+    }
+}
+
+/// Radius of a body's collision sphere: the body mesh is a sphere of `SPHERE_RADIUS`
+fn radius(object: &CelestialObject) -> f32 {
+    object.scale * SPHERE_RADIUS
+}
+
+/// Whether a body takes part in collisions; rings are flat and debris passes through
+fn solid(object: &CelestialObject) -> bool {
+    object.ring.is_none() && !object.debris
+}
+
+/// Hash of a collision and a fragment index, so debris spreads the same way every run
+fn scatter(time: f32, index: usize) -> f32 {
+    let mut h = time.to_bits() ^ (index as u32).wrapping_mul(0x9E37_79B9);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2C1B_3C6D);
+    h ^= h >> 12;
+    (h & 0xFFFF) as f32 / 65535.0
+}
+
+/// Glowing sparks bursting out of an impact between bodies of about `size`
+fn sparks(position: Vector3, size: f32) -> ParticleSystem {
+    let emitter = Emitter {
+        position,
+        spread: size,
+        lifetime: SPARK_LIFETIME,
+        color_start: Vector3::new(1.0, 0.85, 0.5),
+        color_end: Vector3::new(0.6, 0.1, 0.0),
+        size: size * 0.05,
+        ..Emitter::default()
+    };
+    let mut sparks = ParticleSystem::new(emitter, SPARKS).with_shape(ParticleShape::Quad { soft: true });
+    sparks.drag = 1.5;
+    sparks.burst(SPARKS);
+    sparks
+}
+
+impl SolarSystem {
+    /// Finds bodies that started touching since the last call and applies the system's
+    /// `collisions` response to each pair; bodies that keep touching are reported once
+    pub fn resolve_collisions(&mut self) -> Vec<Collision> {
+        let mut contacts = Vec::new();
+        for a in 0..self.objects.len() {
+            for b in a + 1..self.objects.len() {
+                let (first, second) = (&self.objects[a], &self.objects[b]);
+                if solid(first) && solid(second)
+                    && first.position.distance_to(second.position) < radius(first) + radius(second)
+                {
+                    contacts.push((a, b));
+                }
+            }
+        }
+        let new: Vec<(usize, usize)> = contacts.iter().copied().filter(|pair| !self.contacts.contains(pair)).collect();
+        self.contacts = contacts;
+
+        let mut collisions = Vec::new();
+        // Merges and debris reorder the bodies, so only the first new contact is resolved;
+        // any other is found again on the next call
+        for (a, b) in new {
+            let (larger, smaller) = if self.objects[b].scale > self.objects[a].scale { (b, a) } else { (a, b) };
+            let position = (self.objects[a].position + self.objects[b].position) * 0.5;
+            collisions.push(Collision {
+                time: self.time,
+                larger: self.display_name(larger).unwrap_or_default(),
+                smaller: self.display_name(smaller).unwrap_or_default(),
+                position,
+                response: self.collisions,
+            });
+            let size = self.objects[smaller].scale;
+            match self.collisions {
+                CollisionResponse::Log => continue,
+                CollisionResponse::Merge => self.merge(larger, smaller),
+                CollisionResponse::Debris => self.shatter(larger, smaller),
+            }
+            self.effects.push(sparks(position, size));
+            self.contacts.clear();
+            break;
+        }
+        collisions
+    }
+
+    /// Grows `larger` to hold the volume of both bodies and removes `smaller`, whose
+    /// moons go on orbiting the merged body; the merged body keeps both momenta
+    fn merge(&mut self, larger: usize, smaller: usize) {
+        let (big, small) = (&self.objects[larger], &self.objects[smaller]);
+        let (big_mass, small_mass) = (big.mass(), small.mass());
+        let mass = big_mass + small_mass;
+        if mass > 0.0 {
+            self.objects[larger].velocity =
+                (self.objects[larger].velocity * big_mass + self.objects[smaller].velocity * small_mass) / mass;
+        }
+        if self.objects[larger].mass.is_some() || self.objects[smaller].mass.is_some() {
+            self.objects[larger].mass = Some(mass);
+        }
+        let (big, small) = (self.objects[larger].scale, self.objects[smaller].scale);
+        self.objects[larger].scale = (big.powi(3) + small.powi(3)).cbrt();
+        self.remove(smaller, larger);
+    }
+
+    /// Replaces `smaller` with fragments on scattered orbits around `larger`
+    fn shatter(&mut self, larger: usize, smaller: usize) {
+        let survivor = &self.objects[larger];
+        let shattered = &self.objects[smaller];
+        let fragment_scale = shattered.scale * DEBRIS_SCALE;
+        // Fragments leave from where the bodies met, on rings wide enough not to touch
+        let impact = shattered.position - survivor.position;
+        let angle = impact.z.atan2(impact.x);
+        let base = radius(survivor) + radius(shattered);
+        let time = self.time;
+        let prefix = if shattered.name.is_empty() { "debris".to_string() } else { format!("{} debris", shattered.name) };
+
+        let fragments: Vec<CelestialObject> = (0..DEBRIS_COUNT)
+            .map(|i| {
+                let orbit_radius = base + (i as f32 + 1.0) * fragment_scale * SPHERE_RADIUS * 3.0;
+                let speed = 0.2 + 0.4 * scatter(time, i);
+                let mut fragment = CelestialObject::moon(0, orbit_radius, speed, fragment_scale, ShaderType::Rocky);
+                fragment.name = format!("{prefix} {}", i + 1);
+                fragment.orbit_angle = angle + (scatter(time, i + DEBRIS_COUNT) - 0.5) * 0.6;
+                fragment.orbit.inclination = (scatter(time, i + 2 * DEBRIS_COUNT) - 0.5) * 0.8;
+                fragment.debris = true;
+                fragment
+            })
+            .collect();
+
+        let survivor = self.remove(smaller, larger);
+        for mut fragment in fragments {
+            fragment.parent_index = Some(survivor);
+            fragment.position = self.objects[survivor].position + fragment.orbit_offset();
+            self.add(fragment);
+        }
+    }
+
+    /// Removes the body at `index`, handing its moons to `heir`; returns where `heir`
+    /// ended up. The bodies after it move down one index, so anything outside the system
+    /// that follows a body holds its id (`CelestialObject::id`) rather than its index
+    fn remove(&mut self, index: usize, heir: usize) -> usize {
+        let removed = self.objects.remove(index);
+        let shift = |parent: usize| if parent > index { parent - 1 } else { parent };
+        let heir = shift(heir);
+        for (i, object) in self.objects.iter_mut().enumerate() {
+            object.parent_index = object.parent_index.and_then(|parent| {
+                if parent != index {
+                    Some(shift(parent))
+                } else if i == heir {
+                    // A moon that outgrew its planet takes over the planet's orbit center
+                    removed.parent_index.map(shift)
+                } else {
+                    Some(heir)
+                }
+            });
+        }
+        for belt in &mut self.belts {
+            belt.parent = if belt.parent == index { heir } else { shift(belt.parent) };
+        }
+        heir
+    }
+}
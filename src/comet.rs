@@ -0,0 +1,89 @@
+// comet.rs
+// Comets: small icy bodies on long, tilted orbits whose tail of particles streams away
+// from the nearest star, longer and denser the closer they pass to it
+
+use raylib::prelude::*;
+use crate::mesh::SPHERE_RADIUS;
+use crate::particles::{Emitter, ParticleShape, ParticleSystem};
+use crate::shader_system::ShaderType;
+use crate::solar_system::{CelestialObject, CelestialType, Orbit, SolarSystem};
+
+/// Particles a comet emits per second at `TAIL_REFERENCE` from its star; the rate grows
+/// with the inverse square of the distance
+const TAIL_RATE: f32 = 40.0;
+const TAIL_REFERENCE: f32 = 10.0;
+
+/// Largest multiple of `TAIL_RATE` reached close to the star
+const TAIL_BOOST: f32 = 4.0;
+
+/// Speed of the particles blown away from the star, and how long they last
+const TAIL_SPEED: f32 = 2.0;
+const TAIL_LIFETIME: f32 = 3.0;
+
+/// Particles kept per tail
+const TAIL_PARTICLES: usize = 500;
+
+/// Pale blue of the ionized gas, whiter near the nucleus
+const TAIL_HEAD: Vector3 = Vector3 { x: 0.8, y: 0.9, z: 1.0 };
+const TAIL_COLOR: Vector3 = Vector3 { x: 0.45, y: 0.65, z: 1.0 };
+
+/// How quickly the star's wind turns the particles, per second
+const TAIL_DRAG: f32 = 1.0;
+
+impl CelestialObject {
+    /// Create a new comet: an icy nucleus on an eccentric, tilted orbit (replace it with
+    /// `with_orbit`) that grows a tail
+    pub fn comet(parent_idx: usize, orbit_radius: f32, orbit_speed: f32, scale: f32) -> Self {
+        let mut comet = Self::planet(parent_idx, orbit_radius, orbit_speed, scale, ShaderType::IceWorld);
+        comet.object_type = CelestialType::Comet;
+        comet.orbit = Orbit::new(0.75, 0.3, 0.0, 0.0);
+        let emitter = Emitter {
+            lifetime: TAIL_LIFETIME,
+            color_start: TAIL_HEAD,
+            color_end: TAIL_COLOR,
+            size: scale * 0.15,
+            ..Emitter::default()
+        };
+        let mut tail = ParticleSystem::new(emitter, TAIL_PARTICLES).with_shape(ParticleShape::Quad { soft: true });
+        tail.drag = TAIL_DRAG;
+        comet.tail = Some(tail);
+        comet
+    }
+}
+
+impl SolarSystem {
+    /// Blows every comet's tail away from its nearest star and emits new particles from
+    /// the side of the nucleus facing away from it; going back in time starts the tails over
+    pub(crate) fn update_tails(&mut self, delta_time: f32) {
+        let stars: Vec<Vector3> = self.objects
+            .iter()
+            .filter(|object| object.object_type == CelestialType::Star)
+            .map(|object| object.position)
+            .collect();
+
+        for object in &mut self.objects {
+            let Some(tail) = &mut object.tail else {
+                continue;
+            };
+            if delta_time < 0.0 {
+                tail.clear();
+                continue;
+            }
+            let Some(star) = stars.iter().copied().min_by(|a, b| {
+                a.distance_to(object.position).total_cmp(&b.distance_to(object.position))
+            }) else {
+                continue;
+            };
+
+            let distance = star.distance_to(object.position).max(1e-3);
+            let away = (object.position - star) / distance;
+            let wind = away * TAIL_SPEED;
+            tail.update(delta_time, wind);
+            tail.emitter.position = object.position + away * (object.scale * SPHERE_RADIUS);
+            tail.emitter.velocity = wind * 0.3;
+            tail.emitter.spread = TAIL_SPEED * 0.15;
+            tail.emitter.rate = TAIL_RATE * (TAIL_REFERENCE / distance).powi(2).min(TAIL_BOOST);
+            tail.emit(delta_time);
+        }
+    }
+}
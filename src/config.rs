@@ -0,0 +1,402 @@
+// config.rs
+// Persistent settings loaded from config.toml, with in-code defaults as fallback
+
+use raylib::prelude::*;
+use std::fmt;
+use std::fs;
+use std::io;
+use crate::camera::{FLY_SPEED, MOUSE_SENSITIVITY, SCROLL_SENSITIVITY};
+use crate::input::{Action, Binding, InputMap};
+use crate::toml::{self, ParseError, Value};
+
+/// Window settings
+#[derive(Clone, Debug)]
+pub struct WindowConfig {
+    pub width: i32,
+    pub height: i32,
+    pub fullscreen: bool,
+    pub target_fps: u32,
+    /// Wait for the display's refresh between frames
+    pub vsync: bool,
+}
+
+/// Camera control sensitivity
+#[derive(Clone, Debug)]
+pub struct ControlsConfig {
+    pub rotation_speed: f32,
+    pub zoom_speed: f32,
+    pub pan_speed: f32,
+    /// Radians of orbit per pixel of mouse drag
+    pub mouse_sensitivity: f32,
+    /// Fraction of the distance zoomed per scroll wheel notch
+    pub scroll_sensitivity: f32,
+    /// Reverse horizontal / vertical mouse orbiting
+    pub invert_x: bool,
+    pub invert_y: bool,
+    /// Free-fly movement in units per second
+    pub fly_speed: f32,
+}
+
+/// Bundles of render settings chosen with `render.quality` or `--quality`; keys set next
+/// to `render.quality` in the file still win
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Quality {
+    Low,
+    Medium,
+    High,
+    Ultra,
+}
+
+impl Quality {
+    pub const NAMES: [&str; 4] = ["low", "medium", "high", "ultra"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "low" => Some(Quality::Low),
+            "medium" => Some(Quality::Medium),
+            "high" => Some(Quality::High),
+            "ultra" => Some(Quality::Ultra),
+            _ => None,
+        }
+    }
+
+    /// Fraction of the window's resolution rendered at this quality
+    pub fn render_scale(self) -> f32 {
+        match self {
+            Quality::Low => 0.5,
+            Quality::Medium => 0.75,
+            Quality::High | Quality::Ultra => 1.0,
+        }
+    }
+
+    /// Sets the render options this quality covers; `High` is the defaults
+    pub fn apply(self, render: &mut RenderConfig) {
+        let (low, medium, ultra) = (self == Quality::Low, self == Quality::Medium, self == Quality::Ultra);
+        render.scale = self.render_scale();
+        render.shadows = !low;
+        render.normal_mapping = !low && !medium;
+        render.bloom = !low;
+        render.corona = !low;
+        render.nebula = !low && !medium;
+        render.bake_surfaces = low;
+        // Ultra draws every body and rock in full and runs the noise live
+        render.lod = !ultra;
+        render.asteroid_sprites = !ultra;
+        render.noise_cache = !ultra;
+        render.tone_mapping = ultra;
+        render.antialiasing = ultra;
+    }
+}
+
+/// Render options
+#[derive(Clone, Debug)]
+pub struct RenderConfig {
+    /// Vertical field of view in degrees
+    pub fov: f32,
+    /// Fraction of the window's resolution the frame is rendered at, then stretched
+    pub scale: f32,
+    /// Sky color (0..1 per channel) where there is no starfield
+    pub background: Vector3,
+    /// FXAA pass smoothing the edges of the bodies
+    pub antialiasing: bool,
+    /// Bright pixels (stars) glow into their surroundings
+    pub bloom: bool,
+    /// Luminance above which pixels glow (0..1)
+    pub bloom_threshold: f32,
+    /// Reach of the glow in pixels
+    pub bloom_radius: usize,
+    /// Filmic tone curve over the frame
+    pub tone_mapping: bool,
+    /// Color fringes toward the edges of the frame
+    pub chromatic_aberration: bool,
+    /// Darkened corners
+    pub vignette: bool,
+    /// Animated film grain
+    pub film_grain: bool,
+    /// Small bodies on screen are drawn with coarser meshes
+    pub lod: bool,
+    /// Asteroids smaller than a pixel or so are drawn as points
+    pub asteroid_sprites: bool,
+    /// Animated glow around the stars
+    pub corona: bool,
+    /// Blue-noise dithering of the 8-bit output
+    pub dither: bool,
+    /// Baked low-frequency noise layers
+    pub noise_cache: bool,
+    /// Side of the 3D volume the layers are baked into, 0 for surface maps
+    pub noise_cache_volume: usize,
+    /// Planet surfaces baked into cube maps instead of shaded per fragment
+    pub bake_surfaces: bool,
+    /// Bodies cast shadows on each other (eclipses)
+    pub shadows: bool,
+    /// Surfaces lit along the slopes of their terrain noise (normal mapping)
+    pub normal_mapping: bool,
+    /// Procedural stars behind the bodies instead of a flat color
+    pub starfield: bool,
+    /// Colored nebula clouds in the starfield
+    pub nebula: bool,
+    /// Render threads (0 = one per core)
+    pub threads: usize,
+}
+
+/// Scene loaded at startup
+#[derive(Clone, Debug)]
+pub struct SceneConfig {
+    /// Preset name ("basic", "alien" or "generated")
+    pub preset: String,
+    pub seed: u32,
+}
+
+/// Initial camera placement
+#[derive(Clone, Debug)]
+pub struct CameraConfig {
+    pub eye: Vector3,
+    pub target: Vector3,
+}
+
+/// Diagnostics
+#[derive(Clone, Debug)]
+pub struct LogConfig {
+    /// Level filter, e.g. "info" or "warn,lab5::scene_file=debug"
+    pub level: String,
+}
+
+/// All persistent settings
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub window: WindowConfig,
+    pub controls: ControlsConfig,
+    pub render: RenderConfig,
+    pub scene: SceneConfig,
+    pub camera: CameraConfig,
+    pub log: LogConfig,
+    /// Inputs bound to each action; a `[bindings]` entry replaces that action's defaults
+    pub bindings: InputMap,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            window: WindowConfig {
+                width: 1300,
+                height: 900,
+                fullscreen: false,
+                target_fps: 60,
+                vsync: false,
+            },
+            controls: ControlsConfig {
+                rotation_speed: 0.05,
+                zoom_speed: 0.5,
+                pan_speed: 0.1,
+                mouse_sensitivity: MOUSE_SENSITIVITY,
+                scroll_sensitivity: SCROLL_SENSITIVITY,
+                invert_x: false,
+                invert_y: false,
+                fly_speed: FLY_SPEED,
+            },
+            render: RenderConfig {
+                fov: 60.0,
+                scale: 1.0,
+                background: Vector3::zero(),
+                antialiasing: false,
+                bloom: true,
+                bloom_threshold: 0.75,
+                bloom_radius: 12,
+                tone_mapping: false,
+                chromatic_aberration: false,
+                vignette: false,
+                film_grain: false,
+                lod: true,
+                asteroid_sprites: true,
+                corona: true,
+                dither: true,
+                noise_cache: true,
+                noise_cache_volume: 0,
+                bake_surfaces: false,
+                shadows: true,
+                normal_mapping: true,
+                starfield: true,
+                nebula: true,
+                threads: 0,
+            },
+            scene: SceneConfig {
+                preset: "basic".to_string(),
+                seed: 0,
+            },
+            camera: CameraConfig {
+                eye: Vector3::new(0.0, 15.0, 30.0),
+                target: Vector3::zero(),
+            },
+            log: LogConfig {
+                level: "info".to_string(),
+            },
+            bindings: InputMap::default(),
+        }
+    }
+}
+
+/// Error while reading or parsing a config file
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    /// Line number (1-based) and description
+    Parse(usize, String),
+    /// Key and description
+    Invalid(String, String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(error) => write!(f, "{error}"),
+            ConfigError::Parse(line, message) => write!(f, "line {line}: {message}"),
+            ConfigError::Invalid(key, message) => write!(f, "{key}: {message}"),
+        }
+    }
+}
+
+impl From<io::Error> for ConfigError {
+    fn from(error: io::Error) -> Self {
+        ConfigError::Io(error)
+    }
+}
+
+impl From<ParseError> for ConfigError {
+    fn from(error: ParseError) -> Self {
+        ConfigError::Parse(error.line, error.message)
+    }
+}
+
+impl Config {
+    /// Parses config text; missing keys keep their default value
+    pub fn parse(text: &str) -> Result<Config, ConfigError> {
+        let mut config = Config::default();
+        let document = toml::parse(text)?;
+
+        // The quality bundle first, so the keys it covers can still be set one by one
+        match document.values.get("render.quality") {
+            Some(Value::Str(name)) => Quality::from_name(name)
+                .ok_or_else(|| ConfigError::Invalid("render.quality".to_string(), format!("expected one of {}", Quality::NAMES.join(", "))))?
+                .apply(&mut config.render),
+            Some(_) => return Err(ConfigError::Invalid("render.quality".to_string(), "expected a string".to_string())),
+            None => {}
+        }
+
+        for (key, value) in document.values {
+            let invalid = |expected: &str| ConfigError::Invalid(key.clone(), format!("expected {expected}"));
+            let as_bool = || match value {
+                Value::Bool(b) => Ok(b),
+                _ => Err(invalid("true or false")),
+            };
+            let as_int = || match value {
+                Value::Int(int) => Ok(int),
+                _ => Err(invalid("an integer")),
+            };
+            let as_f32 = || value.as_f32().ok_or_else(|| invalid("a number"));
+            let as_vector = || match &value {
+                Value::Array(items) if items.len() == 3 => {
+                    let c: Option<Vec<f32>> = items.iter().map(Value::as_f32).collect();
+                    c.map(|c| Vector3::new(c[0], c[1], c[2])).ok_or_else(|| invalid("[x, y, z]"))
+                }
+                _ => Err(invalid("[x, y, z]")),
+            };
+
+            match key.as_str() {
+                "window.width" => config.window.width = as_int()? as i32,
+                "window.height" => config.window.height = as_int()? as i32,
+                "window.fullscreen" => config.window.fullscreen = as_bool()?,
+                "window.target_fps" => config.window.target_fps = as_int()?.max(0) as u32,
+                "window.vsync" => config.window.vsync = as_bool()?,
+                "controls.rotation_speed" => config.controls.rotation_speed = as_f32()?,
+                "controls.zoom_speed" => config.controls.zoom_speed = as_f32()?,
+                "controls.pan_speed" => config.controls.pan_speed = as_f32()?,
+                "controls.mouse_sensitivity" => config.controls.mouse_sensitivity = as_f32()?,
+                "controls.scroll_sensitivity" => config.controls.scroll_sensitivity = as_f32()?.clamp(0.0, 0.9),
+                "controls.invert_x" => config.controls.invert_x = as_bool()?,
+                "controls.invert_y" => config.controls.invert_y = as_bool()?,
+                "controls.fly_speed" => config.controls.fly_speed = as_f32()?.max(0.1),
+                "render.quality" => {}
+                "render.fov" => config.render.fov = as_f32()?.clamp(10.0, 150.0),
+                "render.scale" => config.render.scale = as_f32()?.clamp(0.25, 2.0),
+                "render.background" => config.render.background = as_vector()?,
+                "render.antialiasing" => config.render.antialiasing = as_bool()?,
+                "render.bloom" => config.render.bloom = as_bool()?,
+                "render.bloom_threshold" => config.render.bloom_threshold = as_f32()?.clamp(0.0, 1.0),
+                "render.bloom_radius" => config.render.bloom_radius = as_int()?.max(1) as usize,
+                "render.tone_mapping" => config.render.tone_mapping = as_bool()?,
+                "render.chromatic_aberration" => config.render.chromatic_aberration = as_bool()?,
+                "render.vignette" => config.render.vignette = as_bool()?,
+                "render.film_grain" => config.render.film_grain = as_bool()?,
+                "render.lod" => config.render.lod = as_bool()?,
+                "render.asteroid_sprites" => config.render.asteroid_sprites = as_bool()?,
+                "render.corona" => config.render.corona = as_bool()?,
+                "render.dither" => config.render.dither = as_bool()?,
+                "render.noise_cache" => config.render.noise_cache = as_bool()?,
+                "render.noise_cache_volume" => config.render.noise_cache_volume = as_int()?.max(0) as usize,
+                "render.bake_surfaces" => config.render.bake_surfaces = as_bool()?,
+                "render.shadows" => config.render.shadows = as_bool()?,
+                "render.normal_mapping" => config.render.normal_mapping = as_bool()?,
+                "render.starfield" => config.render.starfield = as_bool()?,
+                "render.nebula" => config.render.nebula = as_bool()?,
+                "render.threads" => config.render.threads = as_int()?.max(0) as usize,
+                "scene.preset" => match &value {
+                    Value::Str(name) => config.scene.preset = name.clone(),
+                    _ => return Err(invalid("a string")),
+                },
+                "scene.seed" => config.scene.seed = as_int()? as u32,
+                "camera.eye" => config.camera.eye = as_vector()?,
+                "camera.target" => config.camera.target = as_vector()?,
+                "log.level" => match &value {
+                    Value::Str(level) => config.log.level = level.clone(),
+                    _ => return Err(invalid("a string")),
+                },
+                _ if key.starts_with("bindings.") => {
+                    let name = &key["bindings.".len()..];
+                    let action = Action::from_name(name)
+                        .ok_or_else(|| ConfigError::Invalid(key.clone(), "unknown action".to_string()))?;
+                    let names = match &value {
+                        Value::Str(name) => vec![name.as_str()],
+                        Value::Array(items) => items
+                            .iter()
+                            .map(|item| match item {
+                                Value::Str(name) => Ok(name.as_str()),
+                                _ => Err(invalid("input names")),
+                            })
+                            .collect::<Result<_, _>>()?,
+                        _ => return Err(invalid("an input name or a list of them")),
+                    };
+                    config.bindings.unbind(action);
+                    for name in names {
+                        let binding = Binding::parse(name)
+                            .ok_or_else(|| ConfigError::Invalid(key.clone(), format!("unknown input '{name}'")))?;
+                        config.bindings.bind(action, binding);
+                    }
+                }
+                _ => log::warn!("unknown config key '{key}'"),
+            }
+        }
+
+        if config.window.width <= 0 || config.window.height <= 0 {
+            return Err(ConfigError::Invalid("window".to_string(), "size must be positive".to_string()));
+        }
+
+        Ok(config)
+    }
+
+    /// Reads and parses a config file
+    pub fn load(path: &str) -> Result<Config, ConfigError> {
+        Config::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Loads a config file, falling back to the defaults when it is missing or invalid
+    pub fn load_or_default(path: &str) -> Config {
+        match Config::load(path) {
+            Ok(config) => config,
+            Err(ConfigError::Io(error)) if error.kind() == io::ErrorKind::NotFound => Config::default(),
+            Err(error) => {
+                log::warn!("ignoring {path}: {error}");
+                Config::default()
+            }
+        }
+    }
+}
@@ -0,0 +1,53 @@
+// console.rs
+// On-screen console listing the most recent log messages
+
+use log::Level;
+use raylib::prelude::*;
+use crate::logger;
+
+/// Number of messages shown
+const LINES: usize = 10;
+
+/// Toggleable log overlay at the bottom of the screen
+#[derive(Default)]
+pub struct Console {
+    pub visible: bool,
+}
+
+fn level_color(level: Level) -> Color {
+    match level {
+        Level::Error => Color::new(255, 90, 90, 255),
+        Level::Warn => Color::new(255, 200, 80, 255),
+        Level::Info => Color::new(220, 230, 255, 255),
+        Level::Debug | Level::Trace => Color::new(150, 160, 180, 255),
+    }
+}
+
+impl Console {
+    /// Draws the recent messages across the bottom of a `screen_width` x `screen_height` window
+    pub fn draw(&self, d: &mut impl RaylibDraw, screen_width: i32, screen_height: i32) {
+        if !self.visible {
+            return;
+        }
+
+        const FONT_SIZE: i32 = 16;
+        const LINE_HEIGHT: i32 = 20;
+        const PADDING: i32 = 8;
+
+        let lines = logger::recent(LINES);
+        let height = LINES as i32 * LINE_HEIGHT + PADDING * 2;
+        let top = screen_height - height;
+        d.draw_rectangle(0, top, screen_width, height, Color::new(0, 0, 0, 180));
+
+        for (i, line) in lines.iter().enumerate() {
+            let text = format!("{:7.2}  {:<5} {}: {}", line.time, line.level, line.target, line.message);
+            d.draw_text(
+                &text,
+                PADDING,
+                top + PADDING + i as i32 * LINE_HEIGHT,
+                FONT_SIZE,
+                level_color(line.level),
+            );
+        }
+    }
+}
@@ -0,0 +1,82 @@
+// corona.rs
+// Coronas: an additive glow around every star, drawn as a screen-space billboard whose
+// reach pulses and breaks into streamers with animated noise, so stars don't end at the
+// sphere's silhouette
+
+use raylib::prelude::*;
+use crate::backend::ViewSetup;
+use crate::fragment::BlendMode;
+use crate::framebuffer::Framebuffer;
+use crate::lod::screen_radius;
+use crate::matrix::project_to_screen;
+use crate::noise::Noise;
+use crate::solar_system::{CelestialType, SolarSystem};
+
+/// How far the glow reaches past the silhouette, in star radii
+const CORONA_EXTENT: f32 = 0.8;
+
+/// Brightness of the glow right at the silhouette
+const CORONA_STRENGTH: f32 = 0.6;
+
+/// Streamers around the rim (noise frequency along the angle) and how much longer or
+/// shorter they make the glow
+const STREAMERS: f32 = 3.0;
+const STREAMER_REACH: f32 = 0.6;
+
+/// Speed of the streamers' drift and of the whole corona's pulse
+const STREAMER_SPEED: f32 = 0.25;
+const PULSE_SPEED: f32 = 0.6;
+
+/// Largest corona radius in pixels, so a star filling the screen doesn't shade every pixel
+const MAX_REACH: f32 = 2048.0;
+
+/// Draws the corona of every star in the scene over a view the backend already drew;
+/// it sits at the star's center depth, so the star's own disk and anything in front of
+/// it cover the glow
+pub fn draw_coronas(framebuffer: &mut Framebuffer, setup: &ViewSetup, scene: &SolarSystem) {
+    let stars = scene.objects.iter().filter(|object| object.object_type == CelestialType::Star);
+    for star in stars {
+        let radius = screen_radius(star.position, star.scale * setup.mesh_radius, &setup.view, &setup.projection, &setup.viewport);
+        // Camera inside the star, or nothing to see
+        if !radius.is_finite() || radius < 0.5 {
+            continue;
+        }
+        let Some(center) = project_to_screen(star.position, &setup.view, &setup.projection, &setup.viewport) else {
+            continue;
+        };
+
+        let noise = Noise::new(star.seed);
+        let color = star.spectral_class.color() * CORONA_STRENGTH;
+        let time = setup.time;
+        let pulse = 1.0 + 0.15 * noise.simplex_noise(Vector3::new(time * PULSE_SPEED, 0.0, 0.0));
+        let extent = CORONA_EXTENT * pulse * (1.0 + STREAMER_REACH);
+        let reach = (radius * (1.0 + extent)).min(MAX_REACH);
+
+        let (width, height) = (framebuffer.width as f32, framebuffer.height as f32);
+        let x0 = (center.x - reach).floor().max(0.0) as i32;
+        let x1 = (center.x + reach).ceil().min(width - 1.0) as i32;
+        let y0 = (center.y - reach).floor().max(0.0) as i32;
+        let y1 = (center.y + reach).ceil().min(height - 1.0) as i32;
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                let dx = x as f32 + 0.5 - center.x;
+                let dy = y as f32 + 0.5 - center.y;
+                // Distance past the silhouette, in star radii
+                let rim = (dx * dx + dy * dy).sqrt() / radius - 1.0;
+                if rim <= 0.0 || rim >= extent {
+                    continue;
+                }
+
+                // Streamers: the reach varies around the rim and drifts over time
+                let (sin, cos) = dy.atan2(dx).sin_cos();
+                let around = Vector3::new(cos, sin, 0.0) * STREAMERS;
+                let streamer = noise.simplex_noise_4d(around, time * STREAMER_SPEED);
+                let length = CORONA_EXTENT * pulse * (1.0 + STREAMER_REACH * streamer);
+                let fade = (1.0 - rim / length).max(0.0);
+                if fade > 0.0 {
+                    framebuffer.blend(x, y, center.z, color, fade * fade, BlendMode::Additive);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,158 @@
+// debug_ui.rs
+// Immediate-mode (raygui) tuning panel drawn on top of the software-rendered frame
+
+use raylib::prelude::*;
+use crate::simulation::TIME_SCALES;
+use crate::solar_system::SolarSystem;
+
+const PANEL_WIDTH: f32 = 280.0;
+const ROW_HEIGHT: f32 = 24.0;
+const MARGIN: f32 = 10.0;
+
+/// Values edited by the panel; the caller applies them to the scene every frame
+#[derive(Clone, Debug, PartialEq)]
+pub struct DebugSettings {
+    /// Simulation speed multiplier, also applied to the shader animations
+    pub time_scale: f32,
+    /// Multiplier on every star's light intensity
+    pub light_intensity: f32,
+    /// Speed of the shader animations (surface turbulence, clouds, flares)
+    pub animation_speed: f32,
+    pub dither: bool,
+    pub noise_cache: bool,
+    pub shadows: bool,
+    /// On/off per post-processing effect, in `POST_EFFECT_NAMES` order
+    pub post_effects: [bool; 5],
+}
+
+impl Default for DebugSettings {
+    fn default() -> Self {
+        DebugSettings {
+            time_scale: 1.0,
+            light_intensity: 1.0,
+            animation_speed: 1.0,
+            dither: true,
+            noise_cache: true,
+            shadows: true,
+            post_effects: [true, false, false, false, false],
+        }
+    }
+}
+
+/// Debug panel state (visibility and object list selection)
+pub struct DebugPanel {
+    pub visible: bool,
+    pub settings: DebugSettings,
+    /// Index of the selected body in the object list, -1 for none
+    pub selected: i32,
+    scroll: i32,
+    /// Where the panel was last drawn
+    bounds: Option<Rectangle>,
+}
+
+impl DebugPanel {
+    /// Creates a hidden panel editing `settings`
+    pub fn new(settings: DebugSettings) -> Self {
+        DebugPanel {
+            visible: false,
+            settings,
+            selected: -1,
+            scroll: 0,
+            bounds: None,
+        }
+    }
+
+    /// Whether a point in window pixels is over the visible panel
+    pub fn contains(&self, point: Vector2) -> bool {
+        self.visible && self.bounds.is_some_and(|bounds| bounds.check_collision_point_rec(point))
+    }
+
+    /// Draws the panel at the right edge of a `screen_width` wide window and updates the settings
+    pub fn draw(&mut self, d: &mut impl RaylibDrawGui, screen_width: i32, scene: &SolarSystem) {
+        if !self.visible {
+            return;
+        }
+
+        let x = screen_width as f32 - PANEL_WIDTH - MARGIN;
+        let inner_x = x + MARGIN;
+        let inner_width = PANEL_WIDTH - MARGIN * 2.0;
+        let mut y = MARGIN + ROW_HEIGHT + 6.0;
+
+        let object_rows = scene.objects.len().clamp(3, 8) as f32;
+        let height = ROW_HEIGHT * (15.0 + object_rows) + MARGIN * 2.0;
+        let bounds = Rectangle::new(x, MARGIN, PANEL_WIDTH, height);
+        self.bounds = Some(bounds);
+        if d.gui_window_box(bounds, "Debug (G)") {
+            self.visible = false;
+            return;
+        }
+
+        let settings = &mut self.settings;
+        // Leave room on the left for the label and on the right for the value
+        let slider = |y: f32| Rectangle::new(inner_x + 90.0, y, inner_width - 130.0, ROW_HEIGHT - 6.0);
+
+        // The time scale spans four orders of magnitude, so its slider moves the exponent;
+        // it is only written back when dragged to keep the value exact
+        let (slowest, fastest) = (TIME_SCALES[0], TIME_SCALES[TIME_SCALES.len() - 1]);
+        let exponent = settings.time_scale.clamp(slowest, fastest).log10();
+        let mut dragged = exponent;
+        let text = format!("{:.2}", settings.time_scale);
+        d.gui_slider_bar(slider(y), "Time scale", &text, &mut dragged, slowest.log10(), fastest.log10());
+        if dragged != exponent {
+            settings.time_scale = 10f32.powf(dragged);
+        }
+        y += ROW_HEIGHT;
+
+        let rows: [(&str, &mut f32, f32, f32); 2] = [
+            ("Light", &mut settings.light_intensity, 0.0, 3.0),
+            ("Animation", &mut settings.animation_speed, 0.0, 5.0),
+        ];
+        for (label, value, min, max) in rows {
+            let text = format!("{:.2}", *value);
+            d.gui_slider_bar(slider(y), label, &text, value, min, max);
+            y += ROW_HEIGHT;
+        }
+
+        y += 4.0;
+        d.gui_check_box(Rectangle::new(inner_x, y, 16.0, 16.0), "Dithering", &mut settings.dither);
+        y += ROW_HEIGHT;
+        d.gui_check_box(Rectangle::new(inner_x, y, 16.0, 16.0), "Baked noise cache", &mut settings.noise_cache);
+        y += ROW_HEIGHT;
+        d.gui_check_box(Rectangle::new(inner_x, y, 16.0, 16.0), "Shadows", &mut settings.shadows);
+        y += ROW_HEIGHT;
+        let labels = ["Bloom", "Tone mapping", "Chromatic aberration", "Vignette", "Film grain"];
+        for (label, enabled) in labels.into_iter().zip(&mut settings.post_effects) {
+            d.gui_check_box(Rectangle::new(inner_x, y, 16.0, 16.0), label, enabled);
+            y += ROW_HEIGHT;
+        }
+
+        if d.gui_button(Rectangle::new(inner_x, y, inner_width, ROW_HEIGHT - 4.0), "Reset") {
+            *settings = DebugSettings::default();
+        }
+        y += ROW_HEIGHT + 4.0;
+
+        // Object list
+        let items: Vec<String> = scene.objects
+            .iter()
+            .enumerate()
+            .map(|(i, o)| format!("{i}: {:?} ({:?})", o.object_type, o.material.shader))
+            .collect();
+        let list_height = ROW_HEIGHT * object_rows;
+        d.gui_list_view(
+            Rectangle::new(inner_x, y, inner_width, list_height),
+            &items.join(";"),
+            &mut self.scroll,
+            &mut self.selected,
+        );
+        y += list_height + 4.0;
+
+        let details = match scene.objects.get(self.selected.max(0) as usize) {
+            Some(object) if self.selected >= 0 => format!(
+                "pos ({:.1}, {:.1}, {:.1})  scale {:.2}",
+                object.position.x, object.position.y, object.position.z, object.scale
+            ),
+            _ => "Select a body".to_string(),
+        };
+        d.gui_label(Rectangle::new(inner_x, y, inner_width, ROW_HEIGHT), &details);
+    }
+}
@@ -0,0 +1,123 @@
+// debug_view.rs
+// Diagnostic render modes cycled with F3: the depth buffer and the number of fragments
+// per pixel as heatmaps, world-space normals as colors, and triangle edges only
+
+use raylib::prelude::*;
+use crate::fragment::{BlendMode, Fragment, ShadedFragment};
+use crate::framebuffer::Framebuffer;
+
+/// Fragments per pixel shown at the hot end of the overdraw heatmap
+const OVERDRAW_SCALE: f32 = 8.0;
+
+/// What the software backends draw
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DebugView {
+    /// The normal image
+    #[default]
+    Shaded,
+    /// Depth buffer as a heatmap, near hot and far cold
+    Depth,
+    /// Shading normals (relief included) in world space, xyz as rgb
+    Normals,
+    /// Triangle edges only, about a pixel wide and depth tested against each other
+    Wireframe,
+    /// Fragments submitted per pixel, depth test passed or not
+    Overdraw,
+}
+
+impl DebugView {
+    pub const ALL: [DebugView; 5] = [DebugView::Shaded, DebugView::Depth, DebugView::Normals, DebugView::Wireframe, DebugView::Overdraw];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DebugView::Shaded => "shaded",
+            DebugView::Depth => "depth",
+            DebugView::Normals => "normals",
+            DebugView::Wireframe => "wireframe",
+            DebugView::Overdraw => "overdraw",
+        }
+    }
+
+    /// View after this one in `ALL`, back to `Shaded` after the last
+    pub fn next(self) -> Self {
+        let index = DebugView::ALL.iter().position(|&view| view == self).unwrap_or(0);
+        DebugView::ALL[(index + 1) % DebugView::ALL.len()]
+    }
+
+    /// Whether the fragments are colored by `fragment_color` instead of the shaders
+    pub fn replaces_shaders(self) -> bool {
+        matches!(self, DebugView::Normals | DebugView::Wireframe)
+    }
+
+    /// Flat color of a fragment in the views that replace the shaders
+    pub fn fragment_color(self, fragment: &Fragment) -> Option<Vector3> {
+        match self {
+            DebugView::Normals => Some(normal_color(fragment.normal)),
+            DebugView::Wireframe => Some(fragment.color),
+            _ => None,
+        }
+    }
+
+    /// Replaces the finished frame with the heatmap of the views that read the buffers
+    /// (depth, overdraw); the others are left as drawn
+    pub fn apply(self, framebuffer: &mut Framebuffer) {
+        let values: Vec<Option<f32>> = match self {
+            DebugView::Depth => {
+                let depth = framebuffer.depth_buffer();
+                let finite = depth.iter().copied().filter(|d| d.is_finite());
+                let near = finite.clone().fold(f32::INFINITY, f32::min);
+                let far = finite.fold(f32::NEG_INFINITY, f32::max);
+                let range = (far - near).max(1e-6);
+                depth.iter().map(|&d| d.is_finite().then(|| 1.0 - (d - near) / range)).collect()
+            }
+            DebugView::Overdraw => framebuffer
+                .overdraw()
+                .iter()
+                .map(|&count| (count > 0).then(|| (count as f32 / OVERDRAW_SCALE).min(1.0)))
+                .collect(),
+            _ => return,
+        };
+
+        let width = framebuffer.width as usize;
+        for (index, value) in values.into_iter().enumerate() {
+            let color = value.map_or(Vector3::zero(), heatmap);
+            let quantize = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+            let pixel = Color::new(quantize(color.x), quantize(color.y), quantize(color.z), 255);
+            framebuffer.set_pixel((index % width) as i32, (index / width) as i32, pixel);
+        }
+    }
+}
+
+/// A unit normal mapped into 0..1 per channel
+pub fn normal_color(normal: Vector3) -> Vector3 {
+    normal * 0.5 + Vector3::new(0.5, 0.5, 0.5)
+}
+
+/// Blue through cyan, green and yellow to red as `t` goes from 0 to 1
+pub fn heatmap(t: f32) -> Vector3 {
+    const STOPS: [Vector3; 5] = [
+        Vector3 { x: 0.0, y: 0.0, z: 1.0 },
+        Vector3 { x: 0.0, y: 1.0, z: 1.0 },
+        Vector3 { x: 0.0, y: 1.0, z: 0.0 },
+        Vector3 { x: 1.0, y: 1.0, z: 0.0 },
+        Vector3 { x: 1.0, y: 0.0, z: 0.0 },
+    ];
+    let scaled = t.clamp(0.0, 1.0) * (STOPS.len() - 1) as f32;
+    let index = (scaled as usize).min(STOPS.len() - 2);
+    STOPS[index].lerp(STOPS[index + 1], scaled - index as f32)
+}
+
+/// Fragment stage of the views that replace the shaders: opaque fragments of their flat
+/// color, appended to `shaded`
+pub fn diagnostic_fragments(fragments: &[Fragment], view: DebugView, shaded: &mut Vec<ShadedFragment>) {
+    shaded.extend(fragments.iter().filter_map(|fragment| {
+        Some(ShadedFragment {
+            x: fragment.position.x as i32,
+            y: fragment.position.y as i32,
+            depth: fragment.depth,
+            color: view.fragment_color(fragment)?,
+            alpha: 1.0,
+            blend: BlendMode::Opaque,
+        })
+    }));
+}
@@ -0,0 +1,176 @@
+// export.rs
+// Offline video export: fixed-timestep simulation rendered headlessly and piped to ffmpeg
+// or written as a numbered image sequence
+
+use std::f32::consts::PI;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use crate::camera::Camera;
+use crate::renderer::{ImageBuffer, Renderer};
+use crate::solar_system::SolarSystem;
+
+/// Image formats an exported sequence can be written in
+pub const EXPORT_FORMATS: [&str; 2] = ["png", "ppm"];
+
+/// Video export parameters
+#[derive(Clone, Debug)]
+pub struct ExportSettings {
+    /// Video file (.mp4, .mkv, .webm, .mov) or a directory for an image sequence
+    pub output: String,
+    /// Extension of the sequence images: png, or ppm for lossless frames without raylib
+    pub image_format: String,
+    /// Frames per second of the output; the simulation advances 1/fps per frame
+    pub fps: u32,
+    /// Length of the video in seconds
+    pub duration: f32,
+    /// Exact number of frames to render, overriding `duration`
+    pub frames: Option<u32>,
+}
+
+impl ExportSettings {
+    /// Total number of frames to render
+    pub fn frame_count(&self) -> u32 {
+        if let Some(frames) = self.frames {
+            return frames.max(1);
+        }
+        (self.duration * self.fps as f32).round().max(1.0) as u32
+    }
+}
+
+/// ffmpeg process fed raw frames through its stdin
+struct FfmpegPipe {
+    child: Child,
+    /// Taken, which closes it, once the stream ends or writing to it fails
+    stdin: Option<BufWriter<ChildStdin>>,
+}
+
+impl FfmpegPipe {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        let stdin = self.stdin.as_mut().expect("ffmpeg stdin is open until the pipe is closed");
+        stdin.write_all(bytes).or_else(|e| self.close(Some(e)))
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        let flushed = self.stdin.as_mut().map_or(Ok(()), |stdin| stdin.flush());
+        self.close(flushed.err())
+    }
+
+    /// Closes stdin, which tells ffmpeg the stream has ended, and waits for it to exit.
+    /// Fails with `error` (what went wrong on our side) or with ffmpeg's own failure,
+    /// either way naming its exit status
+    fn close(&mut self, error: Option<io::Error>) -> io::Result<()> {
+        drop(self.stdin.take());
+        match (error, self.child.wait()) {
+            (Some(e), Ok(status)) => Err(io::Error::new(e.kind(), format!("{e}; ffmpeg exited with {status}"))),
+            (Some(e), Err(_)) => Err(e),
+            (None, Ok(status)) if !status.success() => Err(io::Error::other(format!("ffmpeg exited with {status}"))),
+            (None, status) => status.map(|_| ()),
+        }
+    }
+}
+
+impl Drop for FfmpegPipe {
+    /// An export that stops early still closes the pipe and waits for ffmpeg
+    fn drop(&mut self) {
+        if self.stdin.is_some() {
+            let _ = self.close(None);
+        }
+    }
+}
+
+/// Destination of the rendered frames
+enum FrameSink {
+    /// Raw RGB24 frames written to ffmpeg
+    Ffmpeg(FfmpegPipe),
+    /// Numbered image files in a directory, with the given extension
+    Sequence(PathBuf, String),
+}
+
+impl FrameSink {
+    fn open(settings: &ExportSettings, width: usize, height: usize) -> io::Result<Self> {
+        let path = Path::new(&settings.output);
+        let is_video = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| matches!(e, "mp4" | "mkv" | "webm" | "mov"));
+
+        if !is_video {
+            fs::create_dir_all(path)?;
+            return Ok(FrameSink::Sequence(path.to_path_buf(), settings.image_format.clone()));
+        }
+
+        let mut child = Command::new("ffmpeg")
+            .args(["-y", "-loglevel", "error", "-f", "rawvideo", "-pix_fmt", "rgb24"])
+            .args(["-s", &format!("{width}x{height}")])
+            .args(["-r", &settings.fps.to_string()])
+            .args(["-i", "-", "-c:v", "libx264", "-pix_fmt", "yuv420p"])
+            .arg(path)
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|e| io::Error::new(e.kind(), format!("could not start ffmpeg ({e}); export to a directory for an image sequence instead")))?;
+
+        let stdin = child.stdin.take().expect("ffmpeg stdin is piped");
+        Ok(FrameSink::Ffmpeg(FfmpegPipe { child, stdin: Some(BufWriter::new(stdin)) }))
+    }
+
+    fn write(&mut self, index: u32, frame: &ImageBuffer) -> io::Result<()> {
+        match self {
+            FrameSink::Ffmpeg(pipe) => pipe.write(&frame.rgb()),
+            FrameSink::Sequence(dir, extension) => {
+                let path = dir.join(format!("frame_{index:05}.{extension}"));
+                frame.save(&path.to_string_lossy())
+            }
+        }
+    }
+
+    fn finish(self) -> io::Result<()> {
+        match self {
+            FrameSink::Ffmpeg(pipe) => pipe.finish(),
+            FrameSink::Sequence(..) => Ok(()),
+        }
+    }
+}
+
+/// Default scripted camera path: one slow orbit around the target over the whole video,
+/// gently bobbing in pitch
+pub fn orbit_path(camera: &mut Camera, progress: f32, start_yaw: f32, start_pitch: f32, distance: f32) {
+    let yaw = start_yaw + progress * 2.0 * PI;
+    let pitch = start_pitch + (progress * 2.0 * PI).sin() * 0.15;
+    camera.set_orbit(yaw, pitch, distance);
+}
+
+/// Renders `settings.frame_count()` frames of the scene on a fixed timestep and writes every frame
+/// `camera_path` receives the camera, the scene and the export progress in [0, 1) before each frame
+pub fn export_video(
+    renderer: &mut Renderer,
+    scene: &mut SolarSystem,
+    camera: &mut Camera,
+    settings: &ExportSettings,
+    mut camera_path: impl FnMut(&mut Camera, &SolarSystem, f32),
+) -> io::Result<()> {
+    let width = renderer.framebuffer.width as usize;
+    let height = renderer.framebuffer.height as usize;
+    let mut sink = FrameSink::open(settings, width, height)?;
+
+    let frames = settings.frame_count();
+    let dt = 1.0 / settings.fps.max(1) as f32;
+
+    for index in 0..frames {
+        let time = index as f32 * dt;
+        camera_path(camera, scene, index as f32 / frames as f32);
+
+        let frame = renderer.render_frame(scene, camera, time);
+        sink.write(index, &frame)?;
+        scene.update(dt);
+
+        if index % settings.fps.max(1) == 0 {
+            log::info!("Exported frame {}/{}", index + 1, frames);
+        }
+    }
+
+    sink.finish()?;
+    log::info!("Export finished: {}", settings.output);
+    Ok(())
+}
@@ -0,0 +1,75 @@
+// focus.rs
+// Camera follow mode: the orbit target is locked onto a body so the camera travels with
+// a moving planet or moon, easing over from the previous target when switching
+
+use raylib::prelude::*;
+use crate::camera::{Camera, CameraMode};
+use crate::solar_system::SolarSystem;
+
+/// How quickly the target settles on a newly selected body (per second); after a
+/// second less than 2% of the original gap is left
+const EASE_RATE: f32 = 4.0;
+
+/// Which body the camera follows, if any
+#[derive(Clone, Debug, Default)]
+pub struct Focus {
+    /// Id of the body (`CelestialObject::id`), so it is still followed when bodies before
+    /// it are removed
+    body: Option<u32>,
+    /// Camera target relative to the body; shrinks to zero after a switch
+    offset: Vector3,
+    /// Target set by the last update, to notice when the user pans away
+    applied: Option<Vector3>,
+}
+
+impl Focus {
+    /// Index of the followed body in `system`, None for the free camera
+    pub fn body(&self, system: &SolarSystem) -> Option<usize> {
+        system.index_of(self.body?)
+    }
+
+    /// Follows the body at index `body` (None releases the camera), starting from the
+    /// current target
+    pub fn select(&mut self, body: Option<usize>, camera: &Camera, system: &SolarSystem) {
+        let object = body.and_then(|index| system.objects.get(index));
+        self.body = object.map(|object| object.id);
+        self.offset = object.map_or(Vector3::zero(), |object| camera.target - object.position);
+        self.applied = None;
+    }
+
+    /// Steps to the next (or previous, `forward` false) body that can be followed, going
+    /// through the free camera after the last one; rings are skipped
+    pub fn cycle(&mut self, forward: bool, camera: &Camera, system: &SolarSystem) {
+        let bodies: Vec<usize> = (0..system.objects.len()).filter(|&i| system.objects[i].ring.is_none()).collect();
+        // Position in the cycle: 0 is the free camera, i + 1 the i-th body
+        let slots = bodies.len() + 1;
+        let current = self.body(system).and_then(|body| bodies.iter().position(|&b| b == body)).map_or(0, |i| i + 1);
+        let next = if forward { (current + 1) % slots } else { (current + slots - 1) % slots };
+        self.select(next.checked_sub(1).map(|i| bodies[i]), camera, system);
+    }
+
+    /// Moves the camera target onto the followed body; panning, free flight or a body that
+    /// disappeared (absorbed in a merge, scene reload) releases the camera
+    pub fn update(&mut self, camera: &mut Camera, system: &SolarSystem, delta_time: f32) {
+        if self.body.is_none() {
+            return;
+        }
+        let Some(object) = self.body(system).map(|index| &system.objects[index]) else {
+            self.body = None;
+            return;
+        };
+        if camera.mode != CameraMode::Orbit || self.applied.is_some_and(|applied| applied != camera.target) {
+            self.body = None;
+            return;
+        }
+
+        self.offset *= (-EASE_RATE * delta_time).exp();
+        camera.set_target(object.position + self.offset);
+        self.applied = Some(camera.target);
+    }
+
+    /// Name shown for the followed body
+    pub fn describe(&self, system: &SolarSystem) -> Option<String> {
+        system.display_name(self.body(system)?)
+    }
+}
@@ -7,12 +7,18 @@ use raylib::math::{Vector2, Vector3};
 pub struct Fragment {
     /// Screen-space position (x, y coordinates)
     pub position: Vector2,
-    /// Interpolated color with lighting applied
+    /// Interpolated color with lighting applied (ambient and diffuse)
     pub color: Vector3,
+    /// Specular highlight, added by the shader after its own colors
+    pub specular: Vector3,
+    /// Atmospheric glow, added over the shader's output
+    pub glow: Vector3,
     /// Depth value for depth testing
     pub depth: f32,
     /// Original position in world space (for shader calculations)
     pub world_position: Vector3,
+    /// Shading normal in world space, relief included (normals debug view)
+    pub normal: Vector3,
 }
 
 impl Fragment {
@@ -21,14 +27,60 @@ impl Fragment {
         x: f32,
         y: f32,
         color: Vector3,
+        specular: Vector3,
+        glow: Vector3,
         depth: f32,
         world_position: Vector3,
     ) -> Self {
         Fragment {
             position: Vector2::new(x, y),
             color,
+            specular,
+            glow,
             depth,
             world_position,
+            normal: Vector3::zero(),
         }
     }
-}
\ No newline at end of file
+
+    /// Same fragment with its shading normal
+    pub fn with_normal(mut self, normal: Vector3) -> Self {
+        self.normal = normal;
+        self
+    }
+}
+/// How a fragment combines with what is already in the framebuffer
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Replaces the pixel and writes depth
+    #[default]
+    Opaque,
+    /// Covers the pixel by `alpha` (rings, thin clouds)
+    Over,
+    /// Adds `alpha` times its color, so it only brightens (glows)
+    Additive,
+}
+
+impl BlendMode {
+    /// Name used in scene files
+    pub fn from_name(name: &str) -> Option<BlendMode> {
+        match name {
+            "opaque" => Some(BlendMode::Opaque),
+            "over" => Some(BlendMode::Over),
+            "additive" => Some(BlendMode::Additive),
+            _ => None,
+        }
+    }
+}
+
+/// Final color of a fragment, ready for the depth test
+#[derive(Clone, Copy, Debug)]
+pub struct ShadedFragment {
+    pub x: i32,
+    pub y: i32,
+    pub depth: f32,
+    pub color: Vector3,
+    /// Coverage, 0..1; opaque fragments ignore it
+    pub alpha: f32,
+    pub blend: BlendMode,
+}
@@ -2,36 +2,139 @@
 // Framebuffer for rendering with depth testing
 
 use raylib::prelude::*;
+use crate::fragment::BlendMode;
+use crate::noise::BlueNoise;
+
+/// Bytes per pixel of frame snapshots: R, G, B, A
+pub const BYTES_PER_PIXEL: usize = 4;
+
+/// Rounding offset of undithered pixels: rounds to the nearest 8-bit value
+const ROUND_NEAREST: f32 = 0.5;
+
+/// 8-bit opaque color of a 0..1 RGB value, each channel rounded up past `offset`
+fn quantize(color: Vector3, offset: f32) -> Color {
+    let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0 + offset).min(255.0) as u8;
+    Color::new(channel(color.x), channel(color.y), channel(color.z), 255)
+}
 
 /// Manages color and depth buffers for rendering
+///
+/// Snapshots use a stable layout: rows top to bottom, pixels left to right, 8-bit RGBA
+/// with alpha always 255 and no row padding (`stride() == width * BYTES_PER_PIXEL`)
 pub struct Framebuffer {
     pub width: i32,
     pub height: i32,
-    pub color_buffer: Image,
+    /// Color buffer as RGBA bytes, written directly by every pixel operation
+    rgba: Vec<u8>,
+    /// GPU copy of `rgba`, created on the first present and updated in place afterwards
+    texture: Option<Texture2D>,
     background_color: Color,
     depth_buffer: Vec<f32>,
+    /// Fragments submitted to each pixel since the last clear, drawn or not
+    overdraw: Vec<u16>,
+    /// Blue-noise thresholds used to dither the 8-bit quantization
+    dither: Option<BlueNoise>,
 }
 
 impl Framebuffer {
     /// Creates a new framebuffer with specified dimensions
     pub fn new(width: i32, height: i32) -> Self {
         let background_color = Color::BLACK;
-        let color_buffer = Image::gen_image_color(width, height, background_color);
         let depth_buffer = vec![f32::INFINITY; (width * height) as usize];
+        let rgba = [background_color.r, background_color.g, background_color.b, background_color.a]
+            .repeat((width * height) as usize);
         
         Framebuffer {
             width,
             height,
-            color_buffer,
+            rgba,
+            texture: None,
             background_color,
             depth_buffer,
+            overdraw: vec![0; (width * height) as usize],
+            dither: None,
         }
     }
 
+    /// Reallocates every buffer for a new size, keeping the background and dithering;
+    /// the contents are cleared
+    pub fn resize(&mut self, width: i32, height: i32) {
+        if (width, height) == (self.width, self.height) {
+            return;
+        }
+        self.width = width;
+        self.height = height;
+        let c = self.background_color;
+        self.rgba = [c.r, c.g, c.b, c.a].repeat((width * height) as usize);
+        self.depth_buffer = vec![f32::INFINITY; (width * height) as usize];
+        self.overdraw = vec![0; (width * height) as usize];
+        // Recreated at the new size on the next present
+        self.texture = None;
+    }
+
     /// Clears both color and depth buffers
     pub fn clear(&mut self) {
-        self.color_buffer.clear_background(self.background_color);
+        let c = self.background_color;
+        for pixel in self.rgba.chunks_exact_mut(BYTES_PER_PIXEL) {
+            pixel.copy_from_slice(&[c.r, c.g, c.b, c.a]);
+        }
         self.depth_buffer.fill(f32::INFINITY);
+        self.overdraw.fill(0);
+    }
+
+    /// Writes a color without depth testing; coordinates must be inside the buffer
+    fn write(&mut self, x: i32, y: i32, color: Color) {
+        let index = (y * self.width + x) as usize * BYTES_PER_PIXEL;
+        self.rgba[index..index + BYTES_PER_PIXEL].copy_from_slice(&[color.r, color.g, color.b, color.a]);
+    }
+
+    /// Depth of every pixel, row-major; infinity where nothing was drawn
+    pub fn depth_buffer(&self) -> &[f32] {
+        &self.depth_buffer
+    }
+
+    /// Fragments submitted to every pixel since the last clear, row-major, whether they
+    /// passed the depth test or not
+    pub fn overdraw(&self) -> &[u16] {
+        &self.overdraw
+    }
+
+    /// Replaces every pixel's color (0..1 RGB, row-major) without touching depth, as
+    /// post-processing does; values are clamped and dithered like shaded fragments
+    pub fn set_colors(&mut self, colors: &[Vector3]) {
+        for (i, color) in colors.iter().enumerate().take((self.width * self.height) as usize) {
+            let (x, y) = (i as i32 % self.width, i as i32 / self.width);
+            let pixel_color = quantize(*color, self.dither_offset(x, y));
+            self.write(x, y, pixel_color);
+        }
+    }
+
+    /// Bytes per row of `as_rgba_bytes`
+    pub fn stride(&self) -> usize {
+        self.width as usize * BYTES_PER_PIXEL
+    }
+
+    /// Current frame as RGBA bytes, borrowed without copying (see the layout above)
+    pub fn as_rgba_bytes(&self) -> &[u8] {
+        &self.rgba
+    }
+
+    /// Owned copy of the current frame, same layout as `as_rgba_bytes`
+    pub fn snapshot(&self) -> Vec<u8> {
+        self.rgba.clone()
+    }
+
+    /// RGBA value of a pixel
+    pub fn pixel(&self, x: i32, y: i32) -> [u8; 4] {
+        let index = (y * self.width + x) as usize * BYTES_PER_PIXEL;
+        [self.rgba[index], self.rgba[index + 1], self.rgba[index + 2], self.rgba[index + 3]]
+    }
+
+    /// Overwrites a pixel with no depth test or dithering (compositing passes)
+    pub fn set_pixel(&mut self, x: i32, y: i32, color: Color) {
+        if x >= 0 && x < self.width && y >= 0 && y < self.height {
+            self.write(x, y, color);
+        }
     }
 
     /// Sets a pixel with depth testing
@@ -39,33 +142,164 @@ impl Framebuffer {
     pub fn point(&mut self, x: i32, y: i32, depth: f32, color: Vector3) {
         if x >= 0 && x < self.width && y >= 0 && y < self.height {
             let index = (y * self.width + x) as usize;
+            self.overdraw[index] = self.overdraw[index].saturating_add(1);
 
             // Depth test: only draw if closer to camera
             if depth < self.depth_buffer[index] {
                 self.depth_buffer[index] = depth;
                 
-                let pixel_color = Color::new(
-                    (color.x.clamp(0.0, 1.0) * 255.0) as u8,
-                    (color.y.clamp(0.0, 1.0) * 255.0) as u8,
-                    (color.z.clamp(0.0, 1.0) * 255.0) as u8,
-                    255,
-                );
-                self.color_buffer.draw_pixel(x, y, pixel_color);
+                // Dithered rounding hides banding in smooth gradients
+                let pixel_color = quantize(color, self.dither_offset(x, y));
+                self.write(x, y, pixel_color);
+            }
+        }
+    }
+
+    /// Blends a translucent color over a pixel behind which nothing closer was drawn;
+    /// depth is left alone so whatever lies behind stays visible through it, which is
+    /// why translucent fragments are drawn after the opaque ones, farthest first
+    pub fn blend(&mut self, x: i32, y: i32, depth: f32, color: Vector3, alpha: f32, mode: BlendMode) {
+        if x < 0 || x >= self.width || y < 0 || y >= self.height {
+            return;
+        }
+        let index = (y * self.width + x) as usize;
+        if mode == BlendMode::Opaque {
+            self.point(x, y, depth, color);
+            return;
+        }
+        self.overdraw[index] = self.overdraw[index].saturating_add(1);
+        if depth >= self.depth_buffer[index] {
+            return;
+        }
+
+        let [r, g, b, _] = self.pixel(x, y);
+        let under = Vector3::new(r as f32, g as f32, b as f32) / 255.0;
+        let alpha = alpha.clamp(0.0, 1.0);
+        let color = match mode {
+            BlendMode::Over => under.lerp(color, alpha),
+            _ => under + color * alpha,
+        };
+        let pixel_color = quantize(color, self.dither_offset(x, y));
+        self.write(x, y, pixel_color);
+    }
+
+    /// Sets a pixel without depth testing (debug overlays)
+    pub fn overlay_point(&mut self, x: i32, y: i32, color: Vector3) {
+        if x >= 0 && x < self.width && y >= 0 && y < self.height {
+            self.write(x, y, quantize(color, ROUND_NEAREST));
+        }
+    }
+
+    /// Draws a depth-tested line between two screen-space points (z is depth), so
+    /// bodies in front hide it
+    pub fn depth_line(&mut self, from: Vector3, to: Vector3, color: Vector3) {
+        let steps = (to.x - from.x).abs().max((to.y - from.y).abs()).ceil();
+
+        // Bail out on absurdly long lines (points far off-screen)
+        if steps > (self.width.max(self.height) * 4) as f32 {
+            return;
+        }
+
+        let steps = steps.max(1.0) as i32;
+        for i in 0..=steps {
+            let p = from.lerp(to, i as f32 / steps as f32);
+            self.point(p.x.round() as i32, p.y.round() as i32, p.z, color);
+        }
+    }
+
+    /// Draws an overlay line using Bresenham's algorithm
+    pub fn overlay_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, color: Vector3) {
+        let dx = (x1 - x0).abs();
+        let dy = -(y1 - y0).abs();
+
+        // Bail out on absurdly long lines (points far off-screen)
+        if dx > self.width * 4 || -dy > self.height * 4 {
+            return;
+        }
+
+        let step_x = if x0 < x1 { 1 } else { -1 };
+        let step_y = if y0 < y1 { 1 } else { -1 };
+        let mut error = dx + dy;
+        let (mut x, mut y) = (x0, y0);
+
+        loop {
+            self.overlay_point(x, y, color);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let e2 = 2 * error;
+            if e2 >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if e2 <= dx {
+                error += dx;
+                y += step_y;
             }
         }
     }
 
+    /// Rounding offset of a shaded pixel: its blue-noise threshold when dithering,
+    /// otherwise plain rounding to the nearest value
+    #[inline]
+    fn dither_offset(&self, x: i32, y: i32) -> f32 {
+        self.dither.as_ref().map_or(ROUND_NEAREST, |d| d.sample(x, y))
+    }
+
+    /// Enables or disables blue-noise dithering of shaded pixels
+    pub fn set_dither(&mut self, dither: Option<BlueNoise>) {
+        self.dither = dither;
+    }
+
+    /// Whether blue-noise dithering is active
+    pub fn has_dither(&self) -> bool {
+        self.dither.is_some()
+    }
+
+    /// Color the buffer is cleared to
+    pub fn background_color(&self) -> Color {
+        self.background_color
+    }
+
     /// Sets the background color for clearing
     pub fn set_background_color(&mut self, color: Color) {
         self.background_color = color;
     }
 
     /// Displays the framebuffer on screen
-    pub fn swap_buffers(&self, d: &mut RaylibHandle, thread: &RaylibThread) {
-        if let Ok(texture) = d.load_texture_from_image(thread, &self.color_buffer) {
-            let mut d = d.begin_drawing(thread);
-            d.clear_background(self.background_color);
-            d.draw_texture(&texture, 0, 0, Color::WHITE);
+    pub fn swap_buffers(&mut self, d: &mut RaylibHandle, thread: &RaylibThread) {
+        self.present(d, thread, |_| {});
+    }
+
+    /// Displays the framebuffer over the whole window, then lets `overlay` draw on top with
+    /// raylib (text, HUD) in window coordinates
+    pub fn present(
+        &mut self,
+        d: &mut RaylibHandle,
+        thread: &RaylibThread,
+        overlay: impl FnOnce(&mut RaylibDrawHandle),
+    ) {
+        // Frames rendered at another size than the window are stretched over it
+        let scale = d.get_screen_width() as f32 / self.width as f32;
+        if self.texture.is_none() {
+            let image = Image::gen_image_color(self.width, self.height, self.background_color);
+            self.texture = d.load_texture_from_image(thread, &image).ok();
+            if let Some(texture) = &mut self.texture
+                && scale != 1.0
+            {
+                texture.set_texture_filter(thread, TextureFilter::TEXTURE_FILTER_BILINEAR);
+            }
         }
+        let Some(texture) = &mut self.texture else {
+            return;
+        };
+        if texture.update_texture(&self.rgba).is_err() {
+            return;
+        }
+
+        let mut d = d.begin_drawing(thread);
+        d.clear_background(self.background_color);
+        d.draw_texture_ex(&*texture, Vector2::zero(), 0.0, scale, Color::WHITE);
+        overlay(&mut d);
     }
 }
\ No newline at end of file
@@ -0,0 +1,91 @@
+// fxaa.rs
+// Fast approximate antialiasing: a post pass that finds high-contrast edges in the finished
+// frame by their luminance and blends each pixel on them with its neighbor across the edge,
+// more the closer it is to where the staircase step ends
+
+use crate::post::{PostEffect, PostFrame};
+
+/// Pixels walked along an edge in each direction looking for its end
+const SEARCH_STEPS: isize = 8;
+
+/// FXAA parameters
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fxaa {
+    /// Contrast, relative to the brightest pixel around, below which nothing is smoothed
+    pub edge_threshold: f32,
+    /// Contrast below which dark areas are left alone whatever their relative contrast
+    pub edge_threshold_min: f32,
+    /// Strength of the blending of single-pixel details (0 keeps them sharp)
+    pub subpixel: f32,
+}
+
+impl Default for Fxaa {
+    fn default() -> Self {
+        Fxaa { edge_threshold: 0.125, edge_threshold_min: 0.0312, subpixel: 0.75 }
+    }
+}
+
+impl PostEffect for Fxaa {
+    fn name(&self) -> &'static str {
+        "fxaa"
+    }
+
+    fn apply(&self, frame: &mut PostFrame) {
+        let (width, height) = (frame.width, frame.height);
+        let source = frame.colors.clone();
+        let luma: Vec<f32> = source.iter().map(|c| 0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z).collect();
+        let index = |x: isize, y: isize| y.clamp(0, height as isize - 1) as usize * width + x.clamp(0, width as isize - 1) as usize;
+        let at = |x: isize, y: isize| luma[index(x, y)];
+
+        for y in 0..height as isize {
+            for x in 0..width as isize {
+                let (m, n, s, w, e) = (at(x, y), at(x, y - 1), at(x, y + 1), at(x - 1, y), at(x + 1, y));
+                let max = m.max(n).max(s).max(w).max(e);
+                let range = max - m.min(n).min(s).min(w).min(e);
+                if range < self.edge_threshold_min.max(max * self.edge_threshold) {
+                    continue;
+                }
+                let (nw, ne, sw, se) = (at(x - 1, y - 1), at(x + 1, y - 1), at(x - 1, y + 1), at(x + 1, y + 1));
+
+                // How much the pixel stands out from its surroundings as a whole
+                let average = (2.0 * (n + s + w + e) + nw + ne + sw + se) / 12.0;
+                let t = ((average - m).abs() / range).clamp(0.0, 1.0);
+                let t = t * t * (3.0 - 2.0 * t);
+                let subpixel_blend = t * t * self.subpixel;
+
+                // A horizontal edge changes most from row to row, a vertical one from column to column
+                let horizontal = (nw - 2.0 * w + sw).abs() + 2.0 * (n - 2.0 * m + s).abs() + (ne - 2.0 * e + se).abs()
+                    >= (nw - 2.0 * n + ne).abs() + 2.0 * (w - 2.0 * m + e).abs() + (sw - 2.0 * s + se).abs();
+                let (before, after) = if horizontal { (n, s) } else { (w, e) };
+                // The pixel across the edge is on the side of the steeper change
+                let (side, other) = if (before - m).abs() >= (after - m).abs() { (-1, before) } else { (1, after) };
+                let (along, across) = if horizontal { ((1, 0), (0, side)) } else { ((0, 1), (side, 0)) };
+                let edge_luma = (m + other) * 0.5;
+                let gradient = (other - m).abs() * 0.25;
+
+                // Walk along the edge both ways until the pair of pixels straddling it no
+                // longer does, i.e. the end of this step of the staircase
+                let walk = |direction: isize| {
+                    let mut delta = 0.0;
+                    for step in 1..=SEARCH_STEPS {
+                        let (px, py) = (x + along.0 * step * direction, y + along.1 * step * direction);
+                        delta = (at(px, py) + at(px + across.0, py + across.1)) * 0.5 - edge_luma;
+                        if delta.abs() >= gradient {
+                            return (step, delta);
+                        }
+                    }
+                    (SEARCH_STEPS, delta)
+                };
+                let (backward, forward) = (walk(-1), walk(1));
+                let (distance, end_delta) = if backward.0 < forward.0 { backward } else { forward };
+                let length = (backward.0 + forward.0) as f32;
+                // Only the half of the step nearer the end that turns the other way is blended
+                let edge_blend = if (m < edge_luma) != (end_delta < 0.0) { 0.5 - distance as f32 / length } else { 0.0 };
+
+                let blend = edge_blend.max(subpixel_blend);
+                let i = index(x, y);
+                frame.colors[i] = source[i].lerp(source[index(x + across.0, y + across.1)], blend);
+            }
+        }
+    }
+}
@@ -0,0 +1,190 @@
+// generator.rs
+// Procedural systems: one or two stars, planets on widening orbits whose surfaces follow
+// how much starlight reaches them (lava close in, earth-like in the temperate zone, gas
+// giants and ice worlds further out), moons, rings and sometimes an asteroid belt
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use crate::asteroid_belt::AsteroidBelt;
+use crate::atmosphere::Atmosphere;
+use crate::aurora::Aurora;
+use crate::shader_system::ShaderType;
+use crate::solar_system::{CelestialObject, Orbit, SolarSystem, SpectralClass};
+
+/// `orbit_speed * orbit_radius^1.5` of the presets' planets (Kepler's third law)
+const KEPLER: f32 = 3.5;
+
+/// Star classes drawn for generated stars, hot ones rarer, with their size and light
+const STAR_CLASSES: [(SpectralClass, f32, f32, f32); 6] = [
+    // (class, weight, scale, luminosity)
+    (SpectralClass::B, 0.5, 3.8, 2.0),
+    (SpectralClass::A, 1.0, 3.4, 1.5),
+    (SpectralClass::F, 2.0, 3.1, 1.2),
+    (SpectralClass::G, 3.0, 3.0, 1.0),
+    (SpectralClass::K, 3.0, 2.4, 0.75),
+    (SpectralClass::M, 2.0, 1.8, 0.5),
+];
+
+/// What `SolarSystem::generate` may create
+#[derive(Clone, Debug, PartialEq)]
+pub struct GeneratorParams {
+    /// Chance of a binary: a smaller companion close to the main star
+    pub binary_chance: f32,
+    /// Fewest and most planets
+    pub planets: (usize, usize),
+    /// Most moons around one planet (gas giants get up to this many, others fewer)
+    pub max_moons: usize,
+    /// Chance of an asteroid belt in the widest gap between two planets
+    pub belt_chance: f32,
+    /// Chance of a ring around a gas giant
+    pub ring_chance: f32,
+}
+
+impl Default for GeneratorParams {
+    fn default() -> Self {
+        GeneratorParams {
+            binary_chance: 0.3,
+            planets: (3, 7),
+            max_moons: 3,
+            belt_chance: 0.6,
+            ring_chance: 0.5,
+        }
+    }
+}
+
+/// Surface of a planet from the starlight it gets (1 at the basic system's Earth) and
+/// its size; gas giants only form past the frost line
+fn planet_shader(insolation: f32, giant: bool) -> ShaderType {
+    match insolation {
+        i if i > 2.5 => ShaderType::Lava,
+        i if i > 1.6 => ShaderType::Rocky,
+        i if i > 0.55 => ShaderType::CloudPlanet,
+        _ if giant => ShaderType::GasGiant,
+        i if i > 0.25 => ShaderType::Rocky,
+        _ => ShaderType::IceWorld,
+    }
+}
+
+/// Letter astronomers give the `index`th planet of a star: b, c, d...
+fn planet_letter(index: usize) -> char {
+    (b'b' + (index % 25) as u8) as char
+}
+
+impl SolarSystem {
+    /// Creates a random but plausible system, the same one for the same seed and params
+    ///
+    /// The stars' class sets their size and light; the planets' orbits widen outward
+    /// and their speeds follow Kepler's third law, and their surfaces depend on the
+    /// starlight reaching them
+    pub fn generate(seed: u32, params: &GeneratorParams) -> SolarSystem {
+        let mut rng = StdRng::seed_from_u64(seed as u64 ^ 0x5EED_5157);
+        let mut system = SolarSystem::new();
+        system.reseed(seed);
+
+        // Main star, drawn by weight
+        let total: f32 = STAR_CLASSES.iter().map(|class| class.1).sum();
+        let mut pick = rng.random::<f32>() * total;
+        let &(class, _, scale, luminosity) = STAR_CLASSES
+            .iter()
+            .find(|class| {
+                pick -= class.1;
+                pick < 0.0
+            })
+            .unwrap_or(&STAR_CLASSES[3]);
+        let mut star = CelestialObject::star(scale).with_name("A");
+        star.spectral_class = class;
+        star.luminosity = luminosity;
+        let star_index = system.add(star);
+        let mut light = luminosity * scale / 3.0;
+
+        // A dim companion circling it closely
+        let mut inner_edge = scale * 1.8 + 3.0;
+        if rng.random::<f32>() < params.binary_chance {
+            let scale = scale * rng.random_range(0.35..0.6);
+            let mut companion = CelestialObject::star(scale).with_name("B");
+            companion.spectral_class = SpectralClass::M;
+            companion.luminosity = 0.5;
+            companion.parent_index = Some(star_index);
+            companion.orbit_radius = inner_edge;
+            companion.orbit_speed = KEPLER / inner_edge.powf(1.5);
+            system.add(companion);
+            light += 0.5 * scale / 3.0;
+            inner_edge *= 1.8;
+        }
+
+        // Planets outward, each orbit 1.3 to 1.7 times the previous one
+        let (fewest, most) = params.planets;
+        let count = rng.random_range(fewest.min(most)..=most.max(fewest));
+        let mut radius = inner_edge + rng.random_range(0.0..2.0);
+        let mut orbits = Vec::with_capacity(count);
+        for index in 0..count {
+            // Sunlight relative to the basic system's Earth (17 units from a scale 3 star)
+            let insolation = light * (17.0 / radius).powi(2);
+            let giant = insolation < 0.55 && rng.random::<f32>() < 0.6;
+            let shader = planet_shader(insolation, giant);
+            let scale = if giant { rng.random_range(1.1..1.8) } else { rng.random_range(0.35..1.0) };
+
+            let mut planet = CelestialObject::planet(star_index, radius, KEPLER / radius.powf(1.5), scale, shader)
+                .with_orbit(Orbit::new(
+                    rng.random_range(0.0..0.15),
+                    rng.random_range(-0.08..0.08),
+                    rng.random_range(0.0..std::f32::consts::TAU),
+                    rng.random_range(0.0..std::f32::consts::TAU),
+                ))
+                .with_name(&format!("A {}", planet_letter(index)));
+            if shader == ShaderType::CloudPlanet {
+                planet = planet.with_atmosphere(Atmosphere::default());
+            }
+            if matches!(shader, ShaderType::CloudPlanet | ShaderType::IceWorld) {
+                planet = planet.with_aurora(Aurora::default());
+            }
+            let planet_index = system.add(planet);
+
+            // Moons just outside the planet, more of them around giants
+            let moons = if giant { rng.random_range(1..=params.max_moons.max(1)) } else { rng.random_range(0..=params.max_moons / 2) };
+            let mut moon_radius = scale * 0.9;
+            for moon in 0..moons {
+                moon_radius += scale * rng.random_range(0.35..0.6);
+                let moon_shader = if insolation > 1.6 { ShaderType::Rocky } else if rng.random::<bool>() { ShaderType::IceWorld } else { ShaderType::Rocky };
+                let name = format!("A {} {}", planet_letter(index), ["I", "II", "III", "IV", "V", "VI"][moon % 6]);
+                system.add(
+                    CelestialObject::moon(planet_index, moon_radius, 0.4 / moon_radius.sqrt(), rng.random_range(0.1..0.2), moon_shader)
+                        .with_name(&name),
+                );
+            }
+            if giant && rng.random::<f32>() < params.ring_chance {
+                let mut ring = CelestialObject::ring(planet_index, 1.4, 2.3).with_name(&format!("A {} ring", planet_letter(index)));
+                ring.scale = scale;
+                system.add(ring);
+            }
+
+            orbits.push((radius, scale));
+            radius *= rng.random_range(1.3..1.7);
+        }
+
+        // A belt in the widest gap, clear of the planets (and their moons) on both sides
+        let widest = orbits
+            .windows(2)
+            .map(|pair| (pair[0].0 + pair[0].1 * 3.0, pair[1].0 - pair[1].1 * 3.0))
+            .filter(|(inner, outer)| outer - inner > 2.0)
+            .max_by(|a, b| (a.1 - a.0).total_cmp(&(b.1 - b.0)));
+        if let Some((inner, outer)) = widest
+            && rng.random::<f32>() < params.belt_chance
+        {
+            let middle = (inner + outer) * 0.5;
+            let half = ((outer - inner) * 0.25).min(2.0);
+            system.belts.push(AsteroidBelt::new(
+                star_index,
+                middle - half,
+                middle + half,
+                500,
+                KEPLER / middle.powf(1.5),
+                (0.03, 0.12),
+                0.06,
+                rng.random(),
+            ));
+        }
+
+        system
+    }
+}
@@ -0,0 +1,221 @@
+// gizmos.rs
+// Debug overlays for scene lights and orbits, and name labels for the bodies
+
+use raylib::prelude::*;
+use crate::framebuffer::Framebuffer;
+use crate::light::Light;
+use crate::gravity::Motion;
+use crate::matrix::{camera_position, project_to_screen};
+use crate::solar_system::{CelestialObject, CelestialType, SolarSystem};
+use std::f32::consts::PI;
+
+/// Half-size in pixels of the light marker
+const MARKER_SIZE: i32 = 5;
+
+/// Straight segments each orbit path is drawn with
+const ORBIT_SEGMENTS: usize = 128;
+
+/// Points around a body's disc checked against the depth buffer to tell how much of it
+/// is hidden; the center is checked as well
+const LABEL_SAMPLES: usize = 8;
+
+/// Pixels between the top of a body and its label
+const LABEL_GAP: f32 = 4.0;
+
+/// Pixels between the edge of a selected body and its outline
+const OUTLINE_GAP: f32 = 3.0;
+
+/// Name of a body at its place on screen
+#[derive(Clone, Debug, PartialEq)]
+pub struct BodyLabel {
+    pub text: String,
+    /// Bottom center of the label, just above the body
+    pub x: i32,
+    pub y: i32,
+    /// Share of the body left visible, 0 (behind other bodies) to 1, to fade the label
+    pub alpha: f32,
+}
+
+/// Draws a marker at every light and, optionally, a line from each light to every target
+/// Gizmos are drawn on top of the scene (no depth test) so hidden lights stay visible
+pub fn draw_light_gizmos(
+    framebuffer: &mut Framebuffer,
+    lights: &[Light],
+    targets: &[Vector3],
+    view: &Matrix,
+    projection: &Matrix,
+    viewport: &Matrix,
+    show_directions: bool,
+) {
+    for light in lights {
+        let Some(screen) = project_to_screen(light.position, view, projection, viewport) else {
+            continue;
+        };
+        let (x, y) = (screen.x as i32, screen.y as i32);
+
+        // Direction lines toward the lit bodies
+        if show_directions {
+            let line_color = light.color * 0.6;
+            for target in targets {
+                if let Some(end) = project_to_screen(*target, view, projection, viewport) {
+                    framebuffer.overlay_line(x, y, end.x as i32, end.y as i32, line_color);
+                }
+            }
+        }
+
+        // Hollow square with a cross, tinted by the light color
+        for i in -MARKER_SIZE..=MARKER_SIZE {
+            framebuffer.overlay_point(x + i, y - MARKER_SIZE, light.color);
+            framebuffer.overlay_point(x + i, y + MARKER_SIZE, light.color);
+            framebuffer.overlay_point(x - MARKER_SIZE, y + i, light.color);
+            framebuffer.overlay_point(x + MARKER_SIZE, y + i, light.color);
+            framebuffer.overlay_point(x + i, y, Vector3::one());
+            framebuffer.overlay_point(x, y + i, Vector3::one());
+        }
+    }
+}
+
+/// Draws the full path of every orbiting body around its parent's current position,
+/// from the same orbital elements the simulation uses
+/// Paths are depth tested so bodies in front of them hide them; in the gravity mode
+/// bodies leave their orbital elements behind, so nothing is drawn
+pub fn draw_orbit_paths(
+    framebuffer: &mut Framebuffer,
+    scene: &SolarSystem,
+    view: &Matrix,
+    projection: &Matrix,
+    viewport: &Matrix,
+) {
+    if scene.motion == Motion::Gravity {
+        return;
+    }
+    for object in &scene.objects {
+        let Some(parent) = object.parent_index.and_then(|index| scene.objects.get(index)) else {
+            continue;
+        };
+        if object.orbit_radius <= 0.0 {
+            continue;
+        }
+        let color = match object.object_type {
+            CelestialType::Moon => Vector3::new(0.25, 0.25, 0.3),
+            _ => Vector3::new(0.3, 0.4, 0.55),
+        };
+
+        let point = |i: usize| {
+            let anomaly = i as f32 / ORBIT_SEGMENTS as f32 * 2.0 * PI;
+            let position = parent.position + object.orbit.offset(object.orbit_radius, anomaly);
+            project_to_screen(position, view, projection, viewport)
+        };
+        let mut previous = point(0);
+        for i in 1..=ORBIT_SEGMENTS {
+            let current = point(i);
+            // Segments reaching behind the camera are skipped
+            if let (Some(from), Some(to)) = (previous, current) {
+                framebuffer.depth_line(from, to, color);
+            }
+            previous = current;
+        }
+    }
+}
+
+/// Labels for every named body in front of the camera, from its position projected
+/// through the same matrices as the frame; must run after the frame was rendered, as
+/// the depth buffer tells which bodies other ones hide (nothing does with the GPU
+/// backend, which leaves it cleared)
+/// `mesh_radius` is the model-space radius of the body mesh
+pub fn body_labels(
+    framebuffer: &Framebuffer,
+    scene: &SolarSystem,
+    view: &Matrix,
+    projection: &Matrix,
+    viewport: &Matrix,
+    mesh_radius: f32,
+) -> Vec<BodyLabel> {
+    let eye = camera_position(view);
+    let up = Vector3::new(view.m1, view.m5, view.m9);
+    let depth = framebuffer.depth_buffer();
+    let (width, height) = (framebuffer.width, framebuffer.height);
+
+    let mut labels = Vec::new();
+    for object in &scene.objects {
+        if object.name.is_empty() || object.ring.is_some() {
+            continue;
+        }
+        let radius = object.scale * mesh_radius;
+        let to_eye = eye - object.position;
+        if to_eye.length() <= radius {
+            continue;
+        }
+        let (Some(center), Some(top)) = (
+            project_to_screen(object.position, view, projection, viewport),
+            project_to_screen(object.position + up * radius, view, projection, viewport),
+        ) else {
+            continue;
+        };
+        // Anything nearer than the body's closest point lies in front of it
+        let Some(nearest) = project_to_screen(object.position + to_eye.normalized() * radius, view, projection, viewport) else {
+            continue;
+        };
+
+        let screen_radius = (top.y - center.y).abs();
+        let samples = (0..=LABEL_SAMPLES).map(|i| {
+            if i == LABEL_SAMPLES {
+                return (center.x, center.y);
+            }
+            let angle = i as f32 / LABEL_SAMPLES as f32 * 2.0 * PI;
+            (center.x + angle.cos() * screen_radius * 0.7, center.y + angle.sin() * screen_radius * 0.7)
+        });
+        let (mut inside, mut visible) = (0, 0);
+        for (x, y) in samples {
+            let (x, y) = (x as i32, y as i32);
+            if x < 0 || y < 0 || x >= width || y >= height {
+                continue;
+            }
+            inside += 1;
+            if depth[(y * width + x) as usize] >= nearest.z - 1e-3 {
+                visible += 1;
+            }
+        }
+        if inside == 0 {
+            continue;
+        }
+
+        labels.push(BodyLabel {
+            text: object.name.clone(),
+            x: center.x as i32,
+            y: (top.y.min(center.y) - LABEL_GAP) as i32,
+            alpha: visible as f32 / inside as f32,
+        });
+    }
+    labels
+}
+
+/// Circles a selected body on top of the scene, a few pixels outside its silhouette
+/// `mesh_radius` is the model-space radius of the body mesh
+pub fn draw_selection_outline(
+    framebuffer: &mut Framebuffer,
+    object: &CelestialObject,
+    view: &Matrix,
+    projection: &Matrix,
+    viewport: &Matrix,
+    mesh_radius: f32,
+) {
+    let up = Vector3::new(view.m1, view.m5, view.m9);
+    let (Some(center), Some(top)) = (
+        project_to_screen(object.position, view, projection, viewport),
+        project_to_screen(object.position + up * object.scale * mesh_radius, view, projection, viewport),
+    ) else {
+        return;
+    };
+
+    let radius = (top.y - center.y).abs() + OUTLINE_GAP;
+    // A point per pixel of circumference; huge circles (the camera next to the body) are
+    // mostly off-screen and get gaps instead of millions of points
+    let steps = ((radius * 2.0 * PI).ceil() as usize).clamp(16, 8192);
+    let color = Vector3::new(1.0, 0.85, 0.3);
+    for i in 0..steps {
+        let angle = i as f32 / steps as f32 * 2.0 * PI;
+        let (x, y) = (center.x + angle.cos() * radius, center.y + angle.sin() * radius);
+        framebuffer.overlay_point(x.round() as i32, y.round() as i32, color);
+    }
+}
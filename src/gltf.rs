@@ -0,0 +1,508 @@
+// gltf.rs
+// glTF 2.0 model loader (.gltf with external or embedded base64 buffers, and binary .glb):
+// the triangles of every mesh in the default scene, placed by their nodes' transforms and
+// colored by their primitive's material, flattened into one mesh a body can be drawn with
+
+use raylib::math::{Vector2, Vector3};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+use crate::json::{self, Json};
+use crate::material::Material;
+use crate::mesh::SPHERE_RADIUS;
+use crate::obj::Obj;
+use crate::toml::{ParseError, Value};
+use crate::vertex::Vertex;
+
+/// First bytes of a binary glTF file ("glTF")
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+/// Chunk types of a binary glTF file
+const CHUNK_JSON: u32 = 0x4E4F_534A;
+const CHUNK_BIN: u32 = 0x004E_4942;
+/// Primitive mode of triangle lists, the only one loaded
+const MODE_TRIANGLES: usize = 4;
+
+/// Why a model could not be loaded
+#[derive(Debug)]
+pub enum GltfError {
+    /// Missing model or buffer file
+    Io(String, io::Error),
+    /// Invalid JSON
+    Parse(String, ParseError),
+    /// The file is not valid glTF, or uses something the loader does not support
+    Invalid(String, String),
+}
+
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GltfError::Io(path, error) => write!(f, "could not read {path}: {error}"),
+            GltfError::Parse(path, error) => write!(f, "{path}: {error}"),
+            GltfError::Invalid(path, message) => write!(f, "{path}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for GltfError {}
+
+/// Metallic-roughness material of a primitive
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ModelMaterial {
+    /// `baseColorFactor` (linear RGB); textures are not loaded
+    pub base_color: Vector3,
+    /// `emissiveFactor`: light given off whatever the lighting
+    pub emission: Vector3,
+    pub metallic: f32,
+    pub roughness: f32,
+}
+
+impl Default for ModelMaterial {
+    /// glTF's default material: white, fully metallic and fully rough
+    fn default() -> Self {
+        ModelMaterial { base_color: Vector3::one(), emission: Vector3::zero(), metallic: 1.0, roughness: 1.0 }
+    }
+}
+
+impl ModelMaterial {
+    /// Closest Blinn-Phong highlight as (strength, shininess): smooth surfaces get small
+    /// sharp highlights, rough ones wide faint ones, and metals shine more than dielectrics
+    pub fn highlight(&self) -> (f32, f32) {
+        let smoothness = 1.0 - self.roughness.clamp(0.0, 1.0);
+        let strength = (0.04 + 0.76 * self.metallic.clamp(0.0, 1.0)) * smoothness;
+        let alpha = self.roughness.clamp(0.05, 1.0).powi(2);
+        let shininess = (2.0 / (alpha * alpha) - 2.0).clamp(1.0, 256.0);
+        (strength, shininess)
+    }
+}
+
+/// A loaded model: a glTF file, or an OBJ file through `Obj::to_model`
+#[derive(Debug)]
+pub struct GltfModel {
+    /// Triangles in model space, centered and scaled to the radius of the body sphere
+    /// (`SPHERE_RADIUS`); each vertex's `color` and `emission` come from its material
+    pub mesh: Arc<Obj>,
+    /// Material of each primitive and the range of `mesh.indices` its triangles occupy
+    pub parts: Vec<(ModelMaterial, Range<usize>)>,
+}
+
+impl GltfModel {
+    /// Loads a .gltf or .glb file; external buffers are read relative to it
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, GltfError> {
+        let path = path.as_ref();
+        let name = path.display().to_string();
+        let bytes = fs::read(path).map_err(|error| GltfError::Io(name.clone(), error))?;
+        let base = path.parent().unwrap_or(Path::new(""));
+        let model = GltfModel::from_bytes(&bytes, base).map_err(|error| match error {
+            GltfError::Invalid(_, message) => GltfError::Invalid(name.clone(), message),
+            GltfError::Parse(_, error) => GltfError::Parse(name.clone(), error),
+            error => error,
+        })?;
+        log::debug!("loaded {name}: {} triangles, {} primitives", model.mesh.indices.len() / 3, model.parts.len());
+        Ok(model)
+    }
+
+    /// Builds a model from the contents of a .gltf or .glb file; `base` is the directory
+    /// external buffers are read from
+    pub fn from_bytes(bytes: &[u8], base: &Path) -> Result<Self, GltfError> {
+        let invalid = |message: String| GltfError::Invalid("model".to_string(), message);
+        let (text, binary) = if bytes.starts_with(GLB_MAGIC) { split_glb(bytes).map_err(invalid)? } else { (bytes, None) };
+        let text = std::str::from_utf8(text).map_err(|_| invalid("the JSON is not valid UTF-8".to_string()))?;
+        let root = json::parse_tree(text).map_err(|error| GltfError::Parse("model".to_string(), error))?;
+
+        let mut buffers = Vec::new();
+        for (index, buffer) in root.get("buffers").map_or(&[][..], Json::items).iter().enumerate() {
+            let data = match buffer.get("uri").and_then(Json::as_str) {
+                Some(uri) if uri.starts_with("data:") => {
+                    let (_, data) = uri.split_once(";base64,").ok_or_else(|| invalid(format!("buffer {index}: only base64 data URIs are supported")))?;
+                    decode_base64(data).ok_or_else(|| invalid(format!("buffer {index}: invalid base64 data")))?
+                }
+                Some(uri) => {
+                    let path = base.join(uri.replace("%20", " "));
+                    fs::read(&path).map_err(|error| GltfError::Io(path.display().to_string(), error))?
+                }
+                None if index == 0 => binary.ok_or_else(|| invalid("buffer 0 has no uri and there is no binary chunk".to_string()))?.to_vec(),
+                None => return Err(invalid(format!("buffer {index} has no uri"))),
+            };
+            buffers.push(data);
+        }
+
+        let document = Document { root: &root, buffers };
+        document.model().map_err(invalid)
+    }
+
+    /// Object material of a body drawn with this model: white diffuse so the vertex colors
+    /// show unchanged, and the highlight of the material covering most of the model
+    pub fn material(&self) -> Material {
+        let main = self.parts.iter().max_by_key(|(_, range)| range.len()).map(|(material, _)| *material);
+        let (specular, shininess) = main.unwrap_or_default().highlight();
+        Material { diffuse: Vector3::one(), specular, shininess, ..Material::MATTE }
+    }
+}
+
+/// JSON and binary chunks of a .glb file
+fn split_glb(bytes: &[u8]) -> Result<(&[u8], Option<&[u8]>), String> {
+    let word = |offset: usize| bytes.get(offset..offset + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+    if word(4) != Some(2) {
+        return Err("only version 2 binary glTF is supported".to_string());
+    }
+    let length = (word(8).unwrap_or(0) as usize).min(bytes.len());
+    let (mut offset, mut text, mut binary) = (12, None, None);
+    while offset + 8 <= length {
+        let (Some(size), Some(kind)) = (word(offset), word(offset + 4)) else { break };
+        let chunk = bytes.get(offset + 8..offset + 8 + size as usize).ok_or("truncated chunk")?;
+        match kind {
+            CHUNK_JSON => text = text.or(Some(chunk)),
+            CHUNK_BIN => binary = binary.or(Some(chunk)),
+            _ => {}
+        }
+        offset += 8 + size as usize;
+    }
+    Ok((text.ok_or("missing JSON chunk")?, binary))
+}
+
+/// Decodes standard base64, padding optional
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let sextet = |c: u8| match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    };
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let (mut bits, mut count) = (0u32, 0);
+    for c in text.trim_end_matches('=').bytes() {
+        bits = bits << 6 | sextet(c)? as u32;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            bytes.push((bits >> count) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+/// Affine transform of a node as its three axes and translation
+#[derive(Clone, Copy)]
+struct Affine {
+    axes: [Vector3; 3],
+    translation: Vector3,
+}
+
+impl Affine {
+    const IDENTITY: Affine = Affine {
+        axes: [Vector3 { x: 1.0, y: 0.0, z: 0.0 }, Vector3 { x: 0.0, y: 1.0, z: 0.0 }, Vector3 { x: 0.0, y: 0.0, z: 1.0 }],
+        translation: Vector3 { x: 0.0, y: 0.0, z: 0.0 },
+    };
+
+    fn vector(&self, v: Vector3) -> Vector3 {
+        self.axes[0] * v.x + self.axes[1] * v.y + self.axes[2] * v.z
+    }
+
+    fn point(&self, p: Vector3) -> Vector3 {
+        self.vector(p) + self.translation
+    }
+
+    /// Transformed normal: the inverse transpose of the axes (their cofactors, with the sign
+    /// of the determinant so mirrored nodes keep their normals outward)
+    fn normal(&self, n: Vector3) -> Vector3 {
+        let [a, b, c] = self.axes;
+        let cofactors = [b.cross(c), c.cross(a), a.cross(b)];
+        let sign = if a.dot(cofactors[0]) < 0.0 { -1.0 } else { 1.0 };
+        ((cofactors[0] * n.x + cofactors[1] * n.y + cofactors[2] * n.z) * sign).normalized()
+    }
+
+    /// This transform applied after `child`
+    fn then(&self, child: &Affine) -> Affine {
+        Affine { axes: child.axes.map(|axis| self.vector(axis)), translation: self.point(child.translation) }
+    }
+
+    /// Local transform of a node: its column-major `matrix`, or translation, rotation
+    /// (quaternion) and scale
+    fn of_node(node: &Json) -> Result<Affine, String> {
+        let numbers = |key: &str, len: usize, default: &[f32]| -> Result<Vec<f32>, String> {
+            match node.get(key) {
+                Some(json) => json.items().iter().map(Json::as_f32).collect::<Option<Vec<f32>>>()
+                    .filter(|values| values.len() == len)
+                    .ok_or_else(|| format!("node {key} must be {len} numbers")),
+                None => Ok(default.to_vec()),
+            }
+        };
+        if node.get("matrix").is_some() {
+            let m = numbers("matrix", 16, &[])?;
+            return Ok(Affine {
+                axes: [Vector3::new(m[0], m[1], m[2]), Vector3::new(m[4], m[5], m[6]), Vector3::new(m[8], m[9], m[10])],
+                translation: Vector3::new(m[12], m[13], m[14]),
+            });
+        }
+        let t = numbers("translation", 3, &[0.0, 0.0, 0.0])?;
+        let r = numbers("rotation", 4, &[0.0, 0.0, 0.0, 1.0])?;
+        let s = numbers("scale", 3, &[1.0, 1.0, 1.0])?;
+        let (x, y, z, w) = (r[0], r[1], r[2], r[3]);
+        let rotation = [
+            Vector3::new(1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y + z * w), 2.0 * (x * z - y * w)),
+            Vector3::new(2.0 * (x * y - z * w), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z + x * w)),
+            Vector3::new(2.0 * (x * z + y * w), 2.0 * (y * z - x * w), 1.0 - 2.0 * (x * x + y * y)),
+        ];
+        Ok(Affine {
+            axes: [rotation[0] * s[0], rotation[1] * s[1], rotation[2] * s[2]],
+            translation: Vector3::new(t[0], t[1], t[2]),
+        })
+    }
+}
+
+/// A parsed glTF file with its buffers loaded
+struct Document<'a> {
+    root: &'a Json,
+    buffers: Vec<Vec<u8>>,
+}
+
+impl Document<'_> {
+    /// Entry `index` of the top-level array `key`
+    fn entry(&self, key: &str, index: usize) -> Result<&Json, String> {
+        self.root.get(key).and_then(|array| array.items().get(index)).ok_or_else(|| format!("{key} {index} does not exist"))
+    }
+
+    /// Non-negative integer member of an object, `default` when it is missing
+    fn index(json: &Json, key: &str, default: Option<usize>) -> Result<usize, String> {
+        match json.get(key) {
+            Some(value) => value.as_usize().ok_or_else(|| format!("{key} must be a non-negative integer")),
+            None => default.ok_or_else(|| format!("missing {key}")),
+        }
+    }
+
+    /// Every component of accessor `index` converted to floats (normalized integers
+    /// mapped to 0..1 or -1..1), with the number of components per element
+    fn read(&self, index: usize) -> Result<(Vec<f32>, usize), String> {
+        let accessor = self.entry("accessors", index)?;
+        let context = |message: String| format!("accessor {index}: {message}");
+        if accessor.get("sparse").is_some() {
+            return Err(context("sparse accessors are not supported".to_string()));
+        }
+        let count = Document::index(accessor, "count", None).map_err(context)?;
+        let components = match accessor.get("type").and_then(Json::as_str) {
+            Some("SCALAR") => 1,
+            Some("VEC2") => 2,
+            Some("VEC3") => 3,
+            Some("VEC4") => 4,
+            other => return Err(context(format!("unsupported type {other:?}"))),
+        };
+        let component_type = Document::index(accessor, "componentType", None).map_err(context)?;
+        let size = match component_type {
+            5120 | 5121 => 1,
+            5122 | 5123 => 2,
+            5125 | 5126 => 4,
+            other => return Err(context(format!("unknown component type {other}"))),
+        };
+        let normalized = matches!(accessor.get("normalized"), Some(Json::Value(Value::Bool(true))));
+
+        let view_index = Document::index(accessor, "bufferView", None).map_err(context)?;
+        let view = self.entry("bufferViews", view_index)?;
+        let buffer = self.buffers
+            .get(Document::index(view, "buffer", None)?)
+            .ok_or_else(|| format!("buffer view {view_index} refers to a missing buffer"))?;
+        let start = Document::index(view, "byteOffset", Some(0))? + Document::index(accessor, "byteOffset", Some(0)).map_err(context)?;
+        let stride = Document::index(view, "byteStride", Some(components * size))?;
+        let end = Document::index(view, "byteOffset", Some(0))? + Document::index(view, "byteLength", None)?;
+        let needed = if count == 0 { start } else { start + (count - 1) * stride + components * size };
+        if needed > end.min(buffer.len()) {
+            return Err(context("reads past the end of its buffer".to_string()));
+        }
+
+        let mut values = Vec::with_capacity(count * components);
+        for element in 0..count {
+            for component in 0..components {
+                let at = start + element * stride + component * size;
+                let b = &buffer[at..at + size];
+                let value = match component_type {
+                    5120 if normalized => (b[0] as i8 as f32 / 127.0).max(-1.0),
+                    5120 => b[0] as i8 as f32,
+                    5121 if normalized => b[0] as f32 / 255.0,
+                    5121 => b[0] as f32,
+                    5122 if normalized => (i16::from_le_bytes([b[0], b[1]]) as f32 / 32767.0).max(-1.0),
+                    5122 => i16::from_le_bytes([b[0], b[1]]) as f32,
+                    5123 if normalized => u16::from_le_bytes([b[0], b[1]]) as f32 / 65535.0,
+                    5123 => u16::from_le_bytes([b[0], b[1]]) as f32,
+                    5125 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32,
+                    _ => f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+                };
+                values.push(value);
+            }
+        }
+        Ok((values, components))
+    }
+
+    /// Indices of accessor `index`, which must hold unsigned integers
+    fn read_indices(&self, index: usize) -> Result<Vec<usize>, String> {
+        let accessor = self.entry("accessors", index)?;
+        if !matches!(Document::index(accessor, "componentType", None), Ok(5121 | 5123 | 5125)) {
+            return Err(format!("accessor {index}: indices must be unsigned integers"));
+        }
+        Ok(self.read(index)?.0.into_iter().map(|i| i as usize).collect())
+    }
+
+    fn material(&self, index: Option<usize>) -> Result<ModelMaterial, String> {
+        let mut material = ModelMaterial::default();
+        let Some(index) = index else { return Ok(material) };
+        let json = self.entry("materials", index)?;
+        let color = |json: Option<&Json>| -> Option<Vector3> {
+            let c: Vec<f32> = json?.items().iter().map(Json::as_f32).collect::<Option<_>>()?;
+            (c.len() >= 3).then(|| Vector3::new(c[0], c[1], c[2]))
+        };
+        let pbr = json.get("pbrMetallicRoughness");
+        if let Some(base) = pbr.and_then(|pbr| pbr.get("baseColorFactor")) {
+            material.base_color = color(Some(base)).ok_or_else(|| format!("material {index}: invalid baseColorFactor"))?;
+        }
+        if let Some(metallic) = pbr.and_then(|pbr| pbr.get("metallicFactor")) {
+            material.metallic = metallic.as_f32().ok_or_else(|| format!("material {index}: invalid metallicFactor"))?;
+        }
+        if let Some(roughness) = pbr.and_then(|pbr| pbr.get("roughnessFactor")) {
+            material.roughness = roughness.as_f32().ok_or_else(|| format!("material {index}: invalid roughnessFactor"))?;
+        }
+        if let Some(emission) = json.get("emissiveFactor") {
+            material.emission = color(Some(emission)).ok_or_else(|| format!("material {index}: invalid emissiveFactor"))?;
+        }
+        Ok(material)
+    }
+
+    /// Appends the triangles of mesh `index` placed by `transform`
+    fn add_mesh(&self, index: usize, transform: &Affine, out: &mut Obj, parts: &mut Vec<(ModelMaterial, Range<usize>)>) -> Result<(), String> {
+        let mesh = self.entry("meshes", index)?;
+        for primitive in mesh.get("primitives").map_or(&[][..], Json::items) {
+            if Document::index(primitive, "mode", Some(MODE_TRIANGLES))? != MODE_TRIANGLES {
+                log::warn!("mesh {index}: skipping a primitive that is not a triangle list");
+                continue;
+            }
+            let attributes = primitive.get("attributes").ok_or_else(|| format!("mesh {index}: primitive without attributes"))?;
+            let attribute = |name: &str| attributes.get(name).map(|json| json.as_usize().ok_or_else(|| format!("mesh {index}: invalid {name}"))).transpose();
+
+            let position_accessor = attribute("POSITION")?.ok_or_else(|| format!("mesh {index}: primitive without POSITION"))?;
+            let (positions, components) = self.read(position_accessor)?;
+            if components != 3 {
+                return Err(format!("mesh {index}: POSITION must be VEC3"));
+            }
+            let count = positions.len() / 3;
+            let normals = match attribute("NORMAL")? {
+                Some(accessor) => Some(self.read(accessor)?).filter(|(normals, components)| *components == 3 && normals.len() == positions.len()),
+                None => None,
+            };
+            let uvs = match attribute("TEXCOORD_0")? {
+                Some(accessor) => Some(self.read(accessor)?).filter(|(uvs, components)| *components == 2 && uvs.len() == count * 2),
+                None => None,
+            };
+            let indices = match Document::index(primitive, "indices", None) {
+                Ok(accessor) => self.read_indices(accessor)?,
+                Err(_) => (0..count).collect(),
+            };
+            if let Some(&bad) = indices.iter().find(|&&i| i >= count) {
+                return Err(format!("mesh {index}: vertex index {bad} out of range ({count} vertices)"));
+            }
+            let material = self.material(primitive.get("material").and_then(Json::as_usize))?;
+
+            let vertex = |i: usize, normal: Vector3| {
+                let position = transform.point(Vector3::new(positions[i * 3], positions[i * 3 + 1], positions[i * 3 + 2]));
+                let tex_coords = uvs.as_ref().map_or(Vector2::zero(), |(uvs, _)| Vector2::new(uvs[i * 2], uvs[i * 2 + 1]));
+                let mut vertex = Vertex::new(position, normal, tex_coords);
+                vertex.color = material.base_color;
+                vertex.emission = material.emission;
+                vertex
+            };
+
+            let (start, base) = (out.indices.len(), out.vertices.len() as u32);
+            let triangles = indices.as_chunks::<3>().0;
+            match &normals {
+                Some((normals, _)) => {
+                    out.vertices.extend((0..count).map(|i| vertex(i, transform.normal(Vector3::new(normals[i * 3], normals[i * 3 + 1], normals[i * 3 + 2])))));
+                    out.indices.extend(triangles.as_flattened().iter().map(|&i| base + i as u32));
+                }
+                // Without normals every triangle is shaded flat, with corners of its own
+                None => {
+                    for triangle in triangles {
+                        let corners = triangle.map(|i| vertex(i, Vector3::zero()));
+                        let flat = (corners[1].position - corners[0].position).cross(corners[2].position - corners[0].position).normalized();
+                        for mut corner in corners {
+                            corner.normal = flat;
+                            out.indices.push(out.vertices.len() as u32);
+                            out.vertices.push(corner);
+                        }
+                    }
+                }
+            }
+            if out.indices.len() > start {
+                parts.push((material, start..out.indices.len()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds node `index` and its children; `depth` guards against cycles
+    fn add_node(&self, index: usize, parent: &Affine, depth: usize, out: &mut Obj, parts: &mut Vec<(ModelMaterial, Range<usize>)>) -> Result<(), String> {
+        if depth > 64 {
+            return Err("the node hierarchy is too deep (or has a cycle)".to_string());
+        }
+        let node = self.entry("nodes", index)?;
+        let transform = parent.then(&Affine::of_node(node).map_err(|message| format!("node {index}: {message}"))?);
+        if let Some(mesh) = node.get("mesh") {
+            let mesh = mesh.as_usize().ok_or_else(|| format!("node {index}: invalid mesh"))?;
+            self.add_mesh(mesh, &transform, out, parts)?;
+        }
+        for child in node.get("children").map_or(&[][..], Json::items) {
+            let child = child.as_usize().ok_or_else(|| format!("node {index}: invalid child"))?;
+            self.add_node(child, &transform, depth + 1, out, parts)?;
+        }
+        Ok(())
+    }
+
+    /// Every mesh of the default scene (or of all root nodes without scenes), centered and
+    /// scaled to the body sphere
+    fn model(&self) -> Result<GltfModel, String> {
+        let nodes = self.root.get("nodes").map_or(&[][..], Json::items);
+        let roots: Vec<usize> = match self.root.get("scenes") {
+            Some(scenes) => {
+                let scene = Document::index(self.root, "scene", Some(0))?;
+                let scene = scenes.items().get(scene).ok_or_else(|| format!("scene {scene} does not exist"))?;
+                scene.get("nodes").map_or(&[][..], Json::items).iter().filter_map(Json::as_usize).collect()
+            }
+            None => {
+                let children: Vec<usize> = nodes
+                    .iter()
+                    .flat_map(|node| node.get("children").map_or(&[][..], Json::items))
+                    .filter_map(Json::as_usize)
+                    .collect();
+                (0..nodes.len()).filter(|index| !children.contains(index)).collect()
+            }
+        };
+
+        let mut mesh = Obj { vertices: Vec::new(), indices: Vec::new(), materials: Vec::new(), sub_meshes: Vec::new() };
+        let mut parts = Vec::new();
+        for root in roots {
+            self.add_node(root, &Affine::IDENTITY, 0, &mut mesh, &mut parts)?;
+        }
+        if mesh.indices.is_empty() {
+            return Err("the scene contains no triangles".to_string());
+        }
+        Ok(GltfModel::fitted(mesh, parts))
+    }
+}
+
+impl GltfModel {
+    /// Model of a mesh and its parts, centered on its bounding box and scaled to fit the
+    /// body sphere so `scale` sizes models like bodies
+    pub(crate) fn fitted(mut mesh: Obj, parts: Vec<(ModelMaterial, Range<usize>)>) -> Self {
+        let (min, max) = mesh.vertices.iter().fold(
+            (Vector3::one() * f32::MAX, Vector3::one() * f32::MIN),
+            |(min, max), v| (min.min(v.position), max.max(v.position)),
+        );
+        let center = (min + max) * 0.5;
+        let radius = mesh.vertices.iter().map(|v| (v.position - center).length()).fold(0.0, f32::max);
+        let fit = if radius > 0.0 { SPHERE_RADIUS / radius } else { 1.0 };
+        for vertex in &mut mesh.vertices {
+            vertex.position = (vertex.position - center) * fit;
+        }
+        GltfModel { mesh: Arc::new(mesh), parts }
+    }
+}
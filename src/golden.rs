@@ -0,0 +1,226 @@
+// golden.rs
+// Test support: renders known scenes headlessly and compares them against stored reference images
+
+use raylib::prelude::*;
+use std::env;
+use std::path::Path;
+use crate::camera::Camera;
+use crate::obj::Obj;
+use crate::renderer::{ImageBuffer, Renderer};
+use crate::solar_system::SolarSystem;
+
+/// Resolution of the golden renders; small enough to keep the tests fast
+pub const WIDTH: i32 = 160;
+pub const HEIGHT: i32 = 120;
+
+/// Environment variable that rewrites the reference images instead of comparing against them
+pub const UPDATE_VAR: &str = "UPDATE_GOLDEN";
+
+/// A scene rendered at a fixed seed, simulation time and camera
+pub struct GoldenScene {
+    /// File name of the reference image (without extension)
+    pub name: &'static str,
+    pub build: fn() -> SolarSystem,
+    pub seed: u32,
+    /// Simulated seconds before the frame is drawn; also the shader time
+    pub time: f32,
+    pub eye: Vector3,
+    pub target: Vector3,
+}
+
+impl GoldenScene {
+    /// Builds the scene with deterministic seeds and orbit phases and advances it to `time`
+    pub fn scene(&self) -> SolarSystem {
+        let mut system = (self.build)();
+        system.reseed(self.seed);
+
+        // Spread the planets by the golden angle instead of their seeded orbit angles
+        for (index, object) in system.objects.iter_mut().enumerate() {
+            if object.parent_index.is_some() {
+                object.orbit_angle = index as f32 * 2.399_963;
+            }
+        }
+        system.update(self.time);
+        system
+    }
+
+    /// Renders the scene headlessly at the golden resolution
+    pub fn render(&self, mesh: &Obj) -> ImageBuffer {
+        let mut renderer = Renderer::new(WIDTH, HEIGHT, mesh);
+        let camera = Camera::new(self.eye, self.target, Vector3::new(0.0, 1.0, 0.0));
+        renderer.render_frame(&self.scene(), &camera, self.time)
+    }
+}
+
+/// Scenes covered by the golden-image tests
+pub fn scenes() -> Vec<GoldenScene> {
+    vec![
+        GoldenScene {
+            name: "basic_overview",
+            build: SolarSystem::create_basic_system,
+            seed: 0,
+            time: 0.0,
+            eye: Vector3::new(0.0, 15.0, 30.0),
+            target: Vector3::zero(),
+        },
+        GoldenScene {
+            name: "basic_animated",
+            build: SolarSystem::create_basic_system,
+            seed: 0,
+            time: 12.5,
+            eye: Vector3::new(0.0, 15.0, 30.0),
+            target: Vector3::zero(),
+        },
+        GoldenScene {
+            name: "basic_high_angle",
+            build: SolarSystem::create_basic_system,
+            seed: 42,
+            time: 3.0,
+            eye: Vector3::new(4.0, 24.0, 8.0),
+            target: Vector3::zero(),
+        },
+        GoldenScene {
+            name: "alien_overview",
+            build: SolarSystem::create_alien_system,
+            seed: 0,
+            time: 0.0,
+            eye: Vector3::new(0.0, 15.0, 30.0),
+            target: Vector3::zero(),
+        },
+    ]
+}
+
+/// How far a render may drift from its reference before the test fails
+#[derive(Clone, Copy, Debug)]
+pub struct Tolerance {
+    /// Largest perceptual difference (0-255) for a pixel to still count as equal
+    pub pixel: f32,
+    /// Fraction of pixels allowed to exceed `pixel`
+    pub max_differing: f32,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Tolerance {
+            pixel: 6.0,
+            max_differing: 0.002,
+        }
+    }
+}
+
+/// Result of comparing two images
+#[derive(Clone, Copy, Debug)]
+pub struct Comparison {
+    /// Pixels whose difference exceeds the tolerance
+    pub differing: usize,
+    pub total: usize,
+    /// Largest perceptual difference found
+    pub max_difference: f32,
+}
+
+impl Comparison {
+    pub fn passes(&self, tolerance: &Tolerance) -> bool {
+        self.differing as f32 <= self.total as f32 * tolerance.max_differing
+    }
+}
+
+/// Perceptual distance between two colors in 0-255, using the "redmean"
+/// weighting so differences in green and in dark reds count more, like the eye does
+fn color_difference(a: &[u8], b: &[u8]) -> f32 {
+    let mean_r = (a[0] as f32 + b[0] as f32) / 2.0;
+    let dr = a[0] as f32 - b[0] as f32;
+    let dg = a[1] as f32 - b[1] as f32;
+    let db = a[2] as f32 - b[2] as f32;
+
+    let weight_r = 2.0 + mean_r / 256.0;
+    let weight_b = 2.0 + (255.0 - mean_r) / 256.0;
+    // The weights sum to 9 for equal channels, so a uniform offset of d gives d
+    ((weight_r * dr * dr + 4.0 * dg * dg + weight_b * db * db) / 9.0).sqrt()
+}
+
+/// Compares the RGB channels of two images of the same size
+pub fn compare(actual: &ImageBuffer, expected: &ImageBuffer, tolerance: &Tolerance) -> Result<Comparison, String> {
+    if (actual.width, actual.height) != (expected.width, expected.height) {
+        return Err(format!(
+            "size mismatch: rendered {}x{}, reference {}x{}",
+            actual.width, actual.height, expected.width, expected.height
+        ));
+    }
+
+    let mut comparison = Comparison {
+        differing: 0,
+        total: actual.width * actual.height,
+        max_difference: 0.0,
+    };
+    for (a, b) in actual.pixels.chunks_exact(4).zip(expected.pixels.chunks_exact(4)) {
+        let difference = color_difference(a, b);
+        comparison.max_difference = comparison.max_difference.max(difference);
+        if difference > tolerance.pixel {
+            comparison.differing += 1;
+        }
+    }
+    Ok(comparison)
+}
+
+/// Image highlighting the pixels that exceed the tolerance in red over a dimmed copy of the reference
+fn difference_image(actual: &ImageBuffer, expected: &ImageBuffer, tolerance: &Tolerance) -> ImageBuffer {
+    let pixels = actual.pixels
+        .chunks_exact(4)
+        .zip(expected.pixels.chunks_exact(4))
+        .flat_map(|(a, b)| {
+            if color_difference(a, b) > tolerance.pixel {
+                [255, 0, 0, 255]
+            } else {
+                [b[0] / 4, b[1] / 4, b[2] / 4, 255]
+            }
+        })
+        .collect();
+
+    ImageBuffer {
+        width: actual.width,
+        height: actual.height,
+        pixels,
+    }
+}
+
+/// Renders `scene` and compares it against `<dir>/<name>.ppm`
+///
+/// With `UPDATE_GOLDEN` set the reference is rewritten instead. On failure the
+/// render and a difference image are saved next to the reference as
+/// `<name>.actual.ppm` and `<name>.diff.ppm`
+pub fn check(scene: &GoldenScene, mesh: &Obj, dir: &Path, tolerance: &Tolerance) -> Result<(), String> {
+    let actual = scene.render(mesh);
+    let reference = dir.join(format!("{}.ppm", scene.name));
+
+    if env::var_os(UPDATE_VAR).is_some() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        return actual
+            .write_ppm(&reference)
+            .map_err(|e| format!("{}: could not write reference: {e}", reference.display()));
+    }
+
+    let expected = ImageBuffer::read_ppm(&reference).map_err(|e| {
+        format!("{}: {e} (run with {UPDATE_VAR}=1 to create it)", reference.display())
+    })?;
+
+    let comparison = compare(&actual, &expected, tolerance).map_err(|e| format!("{}: {e}", scene.name))?;
+    if comparison.passes(tolerance) {
+        return Ok(());
+    }
+
+    let actual_path = dir.join(format!("{}.actual.ppm", scene.name));
+    let diff_path = dir.join(format!("{}.diff.ppm", scene.name));
+    // Best effort: the comparison result matters more than the debug images
+    let _ = actual.write_ppm(&actual_path);
+    let _ = difference_image(&actual, &expected, tolerance).write_ppm(&diff_path);
+
+    Err(format!(
+        "{}: {} of {} pixels differ (max difference {:.1}); see {} and {}",
+        scene.name,
+        comparison.differing,
+        comparison.total,
+        comparison.max_difference,
+        actual_path.display(),
+        diff_path.display(),
+    ))
+}
@@ -0,0 +1,454 @@
+// gpu.rs
+// Hardware backend: the body mesh and GLSL ports of the shaders (assets/shaders) drawn by
+// raylib/OpenGL into a render texture, then read back into the renderer's framebuffer so
+// overlays, exports and the HUD work unchanged. The GLSL noise is not bit-exact with
+// noise.rs, so patterns look alike but do not match pixel for pixel
+
+use raylib::ffi;
+use raylib::prelude::*;
+use crate::atmosphere::Atmosphere;
+use crate::aurora::Aurora;
+use crate::backend::{RenderBackend, ViewSetup};
+use crate::fragment::BlendMode;
+use crate::framebuffer::Framebuffer;
+use crate::light::main_light_direction;
+use crate::matrix::camera_position;
+use crate::profiler::Stage;
+use crate::renderer::RenderStats;
+use crate::shader_system::ShaderType;
+use crate::solar_system::{CelestialObject, SolarSystem};
+use crate::storm::Vortex;
+use crate::volcano::MAX_ERUPTIONS;
+use crate::obj::Obj;
+use crate::vertex::Vertex;
+use std::sync::Arc;
+
+const VERTEX_SHADER: &str = include_str!("../assets/shaders/planet.vs");
+const FRAGMENT_SHADER: &str = include_str!("../assets/shaders/planet.fs");
+
+/// Array sizes of the light and occluder uniforms (must match planet.fs)
+const MAX_LIGHTS: usize = 4;
+const MAX_OCCLUDERS: usize = 16;
+
+/// Uniform locations of planet.fs
+struct Locations {
+    time: i32,
+    shader_type: i32,
+    emissive: i32,
+    seed_offset: i32,
+    material_diffuse: i32,
+    material_specular: i32,
+    material_shininess: i32,
+    material_two_sided: i32,
+    camera_position: i32,
+    light_direction: i32,
+    atmosphere_day: i32,
+    atmosphere_sunset: i32,
+    atmosphere_falloff: i32,
+    atmosphere_strength: i32,
+    aurora_color: i32,
+    aurora_fringe: i32,
+    aurora_latitude: i32,
+    aurora_width: i32,
+    aurora_strength: i32,
+    aurora_speed: i32,
+    translucent: i32,
+    opacity: i32,
+    light_count: i32,
+    light_position: i32,
+    light_color: i32,
+    light_intensity: i32,
+    light_radius: i32,
+    light_ambient: i32,
+    light_falloff: i32,
+    occluder_count: i32,
+    occluders: i32,
+    eruption_count: i32,
+    eruptions: i32,
+    vortex_latitude: i32,
+    vortex_longitude: i32,
+    vortex_radius: i32,
+    vortex_stretch: i32,
+    vortex_spin: i32,
+    vortex_color: i32,
+}
+
+impl Locations {
+    fn new(shader: &Shader) -> Self {
+        Locations {
+            time: shader.get_shader_location("time"),
+            shader_type: shader.get_shader_location("shaderType"),
+            emissive: shader.get_shader_location("emissive"),
+            seed_offset: shader.get_shader_location("seedOffset"),
+            material_diffuse: shader.get_shader_location("materialDiffuse"),
+            material_specular: shader.get_shader_location("materialSpecular"),
+            material_shininess: shader.get_shader_location("materialShininess"),
+            material_two_sided: shader.get_shader_location("materialTwoSided"),
+            camera_position: shader.get_shader_location("cameraPosition"),
+            light_direction: shader.get_shader_location("lightDirection"),
+            atmosphere_day: shader.get_shader_location("atmosphereDay"),
+            atmosphere_sunset: shader.get_shader_location("atmosphereSunset"),
+            atmosphere_falloff: shader.get_shader_location("atmosphereFalloff"),
+            atmosphere_strength: shader.get_shader_location("atmosphereStrength"),
+            aurora_color: shader.get_shader_location("auroraColor"),
+            aurora_fringe: shader.get_shader_location("auroraFringe"),
+            aurora_latitude: shader.get_shader_location("auroraLatitude"),
+            aurora_width: shader.get_shader_location("auroraWidth"),
+            aurora_strength: shader.get_shader_location("auroraStrength"),
+            aurora_speed: shader.get_shader_location("auroraSpeed"),
+            translucent: shader.get_shader_location("translucent"),
+            opacity: shader.get_shader_location("opacity"),
+            light_count: shader.get_shader_location("lightCount"),
+            light_position: shader.get_shader_location("lightPosition"),
+            light_color: shader.get_shader_location("lightColor"),
+            light_intensity: shader.get_shader_location("lightIntensity"),
+            light_radius: shader.get_shader_location("lightRadius"),
+            light_ambient: shader.get_shader_location("lightAmbient"),
+            light_falloff: shader.get_shader_location("lightFalloff"),
+            occluder_count: shader.get_shader_location("occluderCount"),
+            occluders: shader.get_shader_location("occluders"),
+            eruption_count: shader.get_shader_location("eruptionCount"),
+            eruptions: shader.get_shader_location("eruptions"),
+            vortex_latitude: shader.get_shader_location("vortexLatitude"),
+            vortex_longitude: shader.get_shader_location("vortexLongitude"),
+            vortex_radius: shader.get_shader_location("vortexRadius"),
+            vortex_stretch: shader.get_shader_location("vortexStretch"),
+            vortex_spin: shader.get_shader_location("vortexSpin"),
+            vortex_color: shader.get_shader_location("vortexColor"),
+        }
+    }
+}
+
+/// GPU backend; needs an open window and must be used from the thread that opened it
+pub struct GpuBackend {
+    model: Model,
+    shader: Shader,
+    locations: Locations,
+    triangles: usize,
+    /// Uploaded ring and model meshes, matched to the objects by their shared vertex list
+    meshes: Vec<(Arc<Obj>, Model)>,
+    /// Offscreen target, recreated when the framebuffer size changes
+    target: Option<RenderTexture2D>,
+}
+
+impl GpuBackend {
+    /// Uploads the body mesh and compiles the planet shaders
+    pub fn new(window: &mut RaylibHandle, thread: &RaylibThread, body_mesh: &Obj) -> Result<Self, String> {
+        let mesh = upload_mesh(&body_mesh.get_vertex_array())?;
+        // SAFETY: the model takes over the mesh's buffers and unloads them with itself;
+        // the weak handle keeps `mesh` from unloading them a second time
+        let mut model = window
+            .load_model_from_mesh(thread, unsafe { mesh.make_weak() })
+            .map_err(|error| error.to_string())?;
+
+        let shader = window.load_shader_from_memory(thread, Some(VERTEX_SHADER), Some(FRAGMENT_SHADER));
+        // raylib falls back to its default shader when compilation fails
+        let locations = Locations::new(&shader);
+        if locations.shader_type < 0 {
+            return Err("planet shaders failed to compile (see the raylib log)".to_string());
+        }
+        for material in model.materials_mut() {
+            material.as_mut().shader = *shader.as_ref();
+        }
+
+        Ok(GpuBackend {
+            model,
+            shader,
+            locations,
+            triangles: body_mesh.indices.len() / 3,
+            meshes: Vec::new(),
+            target: None,
+        })
+    }
+
+    /// Render texture matching the framebuffer size
+    fn target(&mut self, width: i32, height: i32) -> &RenderTexture2D {
+        let stale = self.target.as_ref().is_some_and(|t| t.texture.width != width || t.texture.height != height);
+        if stale {
+            self.target = None;
+        }
+        // SAFETY: called on the window's thread with its GL context current (see
+        // `GpuBackend`); the RenderTexture2D owns the new target and unloads it on drop
+        self.target.get_or_insert_with(|| unsafe { RenderTexture2D::from_raw(ffi::LoadRenderTexture(width, height)) })
+    }
+
+    /// Uploads the meshes of new rings and models and frees those no longer in the scene
+    fn sync_meshes(&mut self, scene: &SolarSystem) {
+        let meshes: Vec<&Arc<Obj>> = scene.objects.iter().filter_map(CelestialObject::mesh).collect();
+        self.meshes.retain(|(mesh, _)| meshes.iter().any(|m| Arc::ptr_eq(m, mesh)));
+        for mesh in meshes {
+            if self.meshes.iter().any(|(uploaded, _)| Arc::ptr_eq(uploaded, mesh)) {
+                continue;
+            }
+            let uploaded = match upload_mesh(&mesh.get_vertex_array()) {
+                Ok(uploaded) => uploaded,
+                Err(error) => {
+                    log::error!("could not upload a mesh: {error}");
+                    continue;
+                }
+            };
+            // SAFETY: LoadModelFromMesh moves the mesh into a new model that owns its
+            // buffers from then on; the weak handle keeps `uploaded` from freeing them too
+            let mut model = unsafe { Model::from_raw(ffi::LoadModelFromMesh(*uploaded.make_weak().as_ref())) };
+            for material in model.materials_mut() {
+                material.as_mut().shader = *self.shader.as_ref();
+            }
+            self.meshes.push((mesh.clone(), model));
+        }
+    }
+
+    /// Lights shared by every body of the view
+    fn set_lights(&mut self, setup: &ViewSetup) {
+        let lights = &setup.lights[..setup.lights.len().min(MAX_LIGHTS)];
+        let positions: Vec<Vector3> = lights.iter().map(|l| l.position).collect();
+        let colors: Vec<Vector3> = lights.iter().map(|l| l.color).collect();
+        let intensities: Vec<f32> = lights.iter().map(|l| l.intensity).collect();
+        let radii: Vec<f32> = lights.iter().map(|l| l.radius).collect();
+        let ambients: Vec<f32> = lights.iter().map(|l| l.ambient).collect();
+        let falloffs: Vec<f32> = lights.iter().map(|l| l.falloff).collect();
+
+        let (shader, loc) = (&mut self.shader, &self.locations);
+        shader.set_shader_value(loc.time, setup.time);
+        shader.set_shader_value(loc.camera_position, camera_position(&setup.view));
+        shader.set_shader_value(loc.light_count, lights.len() as i32);
+        if !lights.is_empty() {
+            shader.set_shader_value_v(loc.light_position, &positions);
+            shader.set_shader_value_v(loc.light_color, &colors);
+            shader.set_shader_value_v(loc.light_intensity, &intensities);
+            shader.set_shader_value_v(loc.light_radius, &radii);
+            shader.set_shader_value_v(loc.light_ambient, &ambients);
+            shader.set_shader_value_v(loc.light_falloff, &falloffs);
+        }
+    }
+}
+
+impl RenderBackend for GpuBackend {
+    fn name(&self) -> &'static str {
+        "gpu"
+    }
+
+    fn is_software(&self) -> bool {
+        false
+    }
+
+    /// Only the color buffer is filled; the depth buffer stays cleared, so overlays drawn
+    /// afterwards land on top of the bodies
+    fn draw_view(&mut self, setup: &ViewSetup, scene: &SolarSystem, framebuffer: &mut Framebuffer, stats: &mut RenderStats) {
+        let (width, height) = (framebuffer.width, framebuffer.height);
+        let background = framebuffer.background_color();
+        self.set_lights(setup);
+        self.sync_meshes(scene);
+
+        let image = stats.timings.measure(Stage::Rasterization, || {
+            let target = *self.target(width, height).as_ref();
+            // SAFETY: rlgl state calls on the window's thread, drawing into `target`, which
+            // `self.target` keeps alive until the matching EndTextureMode below
+            unsafe {
+                ffi::BeginTextureMode(target);
+                ffi::ClearBackground(background.into());
+                // Same camera as the software pipeline instead of raylib's Camera3D
+                ffi::rlSetMatrixProjection(setup.projection.into());
+                ffi::rlSetMatrixModelview(setup.view.into());
+                ffi::rlEnableDepthTest();
+                ffi::rlDisableBackfaceCulling();
+            }
+
+            // Opaque bodies first, then the translucent ones farthest first, as the
+            // software passes do; translucent bodies don't write depth
+            let eye = camera_position(&setup.view);
+            let (mut order, mut translucent): (Vec<usize>, Vec<usize>) =
+                (0..scene.objects.len()).partition(|&i| scene.objects[i].blend == BlendMode::Opaque);
+            let distance = |i: usize| scene.objects[i].position.distance_to(eye);
+            translucent.sort_by(|&a, &b| distance(b).total_cmp(&distance(a)));
+            order.extend(translucent);
+
+            for index in order {
+                let object = &scene.objects[index];
+                let occluders: Vec<Vector4> = setup
+                    .occluders(scene, index)
+                    .iter()
+                    .take(MAX_OCCLUDERS)
+                    .map(|o| Vector4::new(o.center.x, o.center.y, o.center.z, o.radius))
+                    .collect();
+
+                let (shader, loc) = (&mut self.shader, &self.locations);
+                shader.set_shader_value(loc.shader_type, shader_index(object.material.shader));
+                shader.set_shader_value(loc.emissive, object.material.emissive as i32);
+                shader.set_shader_value(loc.seed_offset, seed_offset(object.seed));
+                shader.set_shader_value(loc.material_diffuse, object.material.diffuse);
+                shader.set_shader_value(loc.material_specular, object.material.specular);
+                shader.set_shader_value(loc.material_shininess, object.material.shininess);
+                shader.set_shader_value(loc.material_two_sided, object.material.two_sided as i32);
+                shader.set_shader_value(loc.light_direction, main_light_direction(setup.lights, object.position));
+                let atmosphere = object.atmosphere.unwrap_or(Atmosphere { strength: 0.0, ..Atmosphere::default() });
+                shader.set_shader_value(loc.atmosphere_day, atmosphere.day);
+                shader.set_shader_value(loc.atmosphere_sunset, atmosphere.sunset);
+                shader.set_shader_value(loc.atmosphere_falloff, atmosphere.falloff);
+                shader.set_shader_value(loc.atmosphere_strength, atmosphere.strength);
+                let aurora = object.aurora.unwrap_or(Aurora { strength: 0.0, ..Aurora::default() });
+                shader.set_shader_value(loc.aurora_color, aurora.color);
+                shader.set_shader_value(loc.aurora_fringe, aurora.fringe);
+                shader.set_shader_value(loc.aurora_latitude, aurora.latitude);
+                shader.set_shader_value(loc.aurora_width, aurora.width);
+                shader.set_shader_value(loc.aurora_strength, aurora.strength);
+                shader.set_shader_value(loc.aurora_speed, aurora.speed);
+                shader.set_shader_value(loc.translucent, (object.blend != BlendMode::Opaque) as i32);
+                shader.set_shader_value(loc.opacity, object.opacity);
+                shader.set_shader_value(loc.occluder_count, occluders.len() as i32);
+                if !occluders.is_empty() {
+                    shader.set_shader_value_v(loc.occluders, &occluders);
+                }
+                let eruptions: Vec<Vector4> = object
+                    .eruptions()
+                    .iter()
+                    .take(MAX_ERUPTIONS)
+                    .map(|e| Vector4::new(e.site.x, e.site.y, e.site.z, e.heat()))
+                    .collect();
+                let vortex = object.vortex.unwrap_or(Vortex { radius: 0.0, ..Vortex::default() });
+                shader.set_shader_value(loc.vortex_latitude, vortex.latitude);
+                shader.set_shader_value(loc.vortex_longitude, vortex.longitude);
+                shader.set_shader_value(loc.vortex_radius, vortex.radius);
+                shader.set_shader_value(loc.vortex_stretch, vortex.stretch);
+                shader.set_shader_value(loc.vortex_spin, vortex.spin);
+                shader.set_shader_value(loc.vortex_color, vortex.color);
+                shader.set_shader_value(loc.eruption_count, eruptions.len() as i32);
+                if !eruptions.is_empty() {
+                    shader.set_shader_value_v(loc.eruptions, &eruptions);
+                }
+
+                let model = match object.mesh() {
+                    Some(own) => match self.meshes.iter_mut().find(|(mesh, _)| Arc::ptr_eq(mesh, own)) {
+                        Some((_, model)) => model,
+                        None => continue,
+                    },
+                    None => &mut self.model,
+                };
+                model.set_transform(&object.model_matrix());
+                // SAFETY: same thread and texture mode as above; `model` is borrowed from
+                // `self` for the whole draw
+                unsafe {
+                    match object.blend {
+                        BlendMode::Opaque => ffi::rlEnableDepthMask(),
+                        _ => ffi::rlDisableDepthMask(),
+                    }
+                    let mode = match object.blend {
+                        BlendMode::Additive => ffi::BlendMode::BLEND_ADDITIVE,
+                        _ => ffi::BlendMode::BLEND_ALPHA,
+                    };
+                    ffi::rlSetBlendMode(mode as i32);
+                    ffi::DrawModel(*model.as_ref(), Vector3::zero().into(), 1.0, Color::WHITE.into());
+                }
+            }
+
+            // SAFETY: restores the rlgl state changed above and ends the texture mode it began
+            unsafe {
+                ffi::rlEnableDepthMask();
+                ffi::rlSetBlendMode(ffi::BlendMode::BLEND_ALPHA as i32);
+                ffi::rlEnableBackfaceCulling();
+                ffi::rlDisableDepthTest();
+                ffi::EndTextureMode();
+            }
+            self.target(width, height).texture().load_image()
+        });
+
+        let image = match image {
+            Ok(image) => image,
+            Err(error) => {
+                log::error!("could not read back the GPU frame: {error}");
+                return;
+            }
+        };
+
+        // OpenGL textures are stored bottom-up; translucent bodies leave alpha below 255
+        stats.timings.measure(Stage::FragmentShading, || {
+            let colors = image.get_image_data();
+            for y in 0..height {
+                for x in 0..width {
+                    let color = colors[((height - 1 - y) * width + x) as usize];
+                    framebuffer.set_pixel(x, y, Color { a: 255, ..color });
+                }
+            }
+        });
+
+        stats.objects += scene.objects.len();
+        stats.triangles += scene.objects
+            .iter()
+            .map(|o| o.mesh().map_or(self.triangles, |mesh| mesh.indices.len() / 3))
+            .sum::<usize>();
+    }
+}
+
+/// Copies a flat triangle list into a raylib mesh (positions and normals) and uploads it;
+/// fails when the mesh is too large for raylib's counts or its buffers can't be allocated
+fn upload_mesh(vertex_array: &[Vertex]) -> Result<Mesh, String> {
+    let floats = vertex_array.len() * 3;
+    let too_large = || format!("a mesh of {} vertices is too large to upload", vertex_array.len());
+    let bytes = u32::try_from(floats * size_of::<f32>()).map_err(|_| too_large())?;
+    let vertex_count = i32::try_from(vertex_array.len()).map_err(|_| too_large())?;
+
+    // SAFETY: MemAlloc has no preconditions; it returns null or `bytes` bytes aligned for
+    // any type, which raylib frees with its own allocator when the model is unloaded
+    let (vertices, normals) = unsafe { (ffi::MemAlloc(bytes) as *mut f32, ffi::MemAlloc(bytes) as *mut f32) };
+    if vertices.is_null() || normals.is_null() {
+        // SAFETY: MemFree ignores null, and a non-null block here came from MemAlloc and was
+        // never handed to raylib
+        unsafe {
+            ffi::MemFree(vertices.cast());
+            ffi::MemFree(normals.cast());
+        }
+        return Err(format!("could not allocate {bytes} bytes for a mesh"));
+    }
+
+    for (i, vertex) in vertex_array.iter().enumerate() {
+        for (axis, (p, n)) in [
+            (vertex.position.x, vertex.normal.x),
+            (vertex.position.y, vertex.normal.y),
+            (vertex.position.z, vertex.normal.z),
+        ].into_iter().enumerate() {
+            // SAFETY: i * 3 + axis < floats, inside both blocks of `floats` f32s
+            unsafe {
+                vertices.add(i * 3 + axis).write(p);
+                normals.add(i * 3 + axis).write(n);
+            }
+        }
+    }
+
+    // SAFETY: an all-zero Mesh is raylib's empty mesh (zero counts, null buffers)
+    let mut mesh: ffi::Mesh = unsafe { std::mem::zeroed() };
+    mesh.vertexCount = vertex_count;
+    mesh.triangleCount = vertex_count / 3;
+    mesh.vertices = vertices;
+    mesh.normals = normals;
+    // SAFETY: the mesh holds `vertex_count` positions and normals in MemAlloc'd buffers,
+    // every other buffer null, as UploadMesh expects; the returned Mesh owns them
+    unsafe {
+        ffi::UploadMesh(&mut mesh, false);
+        Ok(Mesh::from_raw(mesh))
+    }
+}
+
+/// Value of the `shaderType` uniform for a shader
+fn shader_index(shader_type: ShaderType) -> i32 {
+    match shader_type {
+        ShaderType::Star => 0,
+        ShaderType::Rocky => 1,
+        ShaderType::GasGiant => 2,
+        ShaderType::Lava => 3,
+        ShaderType::IceWorld => 4,
+        ShaderType::CloudPlanet => 5,
+        ShaderType::Rings => 6,
+        // Vertex colors are not uploaded; models are drawn with their shape as rock
+        ShaderType::Model => 1,
+        // Registered shaders only exist on the CPU; the GPU draws them as rock
+        ShaderType::Custom(_) => 1,
+    }
+}
+
+/// Offset into the GLSL noise domain that stands in for a noise seed
+fn seed_offset(seed: u32) -> Vector3 {
+    let channel = |k: u32| {
+        let h = seed.wrapping_mul(0x9E37_79B9) ^ k.wrapping_mul(0x85EB_CA6B);
+        (h.wrapping_mul(0xC2B2_AE35) >> 24) as f32
+    };
+    Vector3::new(channel(1), channel(2), channel(3))
+}
@@ -0,0 +1,169 @@
+// gravity.rs
+// Optional N-body physics: every body pulls on every other with Newtonian gravity and
+// positions are integrated with velocity Verlet instead of following fixed orbits
+
+use raylib::prelude::*;
+use crate::solar_system::{CelestialObject, CelestialType, SolarSystem};
+
+/// Default gravitational constant, small enough that the presets' planets keep years
+/// close to their kinematic ones
+pub const GRAVITY: f32 = 2.5e-4;
+
+/// Mass per unit of scale³ of stars and of every other body, when none is given
+const STAR_DENSITY: f32 = 100.0;
+const BODY_DENSITY: f32 = 10.0;
+
+/// Longest integration step in simulated seconds; longer frames (fast-forwarding) are
+/// split into several steps
+const MAX_STEP: f32 = 0.25;
+
+/// Added to squared distances so close passes don't produce infinite pulls
+const SOFTENING: f32 = 0.05;
+
+/// How bodies move
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Motion {
+    /// Along their orbits, placed from the orbital elements at any time
+    #[default]
+    Kinematic,
+    /// Pulled by each other's gravity, integrated step by step
+    Gravity,
+}
+
+impl Motion {
+    /// Motion for its scene file name (`kinematic`, `gravity`)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "kinematic" => Some(Motion::Kinematic),
+            "gravity" => Some(Motion::Gravity),
+            _ => None,
+        }
+    }
+}
+
+impl CelestialObject {
+    /// Mass used by the gravity mode: the given one, or the volume times a density;
+    /// rings weigh nothing
+    pub fn mass(&self) -> f32 {
+        match (self.mass, self.object_type) {
+            (_, CelestialType::Ring) => 0.0,
+            (Some(mass), _) => mass,
+            (None, CelestialType::Star) => self.scale.powi(3) * STAR_DENSITY,
+            (None, _) => self.scale.powi(3) * BODY_DENSITY,
+        }
+    }
+}
+
+impl SolarSystem {
+    /// Switches how bodies move; turning gravity on starts every body from its current
+    /// position with its launch velocity (a circular orbit around its parent unless
+    /// given), with the system's center of mass at rest. Back to kinematic, bodies return
+    /// to their orbits
+    pub fn set_motion(&mut self, motion: Motion) {
+        if motion == self.motion {
+            return;
+        }
+        // Bodies leave from (or return to) their places on their orbits
+        self.motion = Motion::Kinematic;
+        self.update(0.0);
+        self.motion = motion;
+        if motion == Motion::Kinematic {
+            return;
+        }
+
+        for index in 0..self.objects.len() {
+            self.objects[index].velocity = self.launch_velocity(&self.objects[index]);
+        }
+        let (momentum, mass) = self.objects.iter().fold((Vector3::zero(), 0.0), |(momentum, mass), object| {
+            (momentum + object.velocity * object.mass(), mass + object.mass())
+        });
+        if mass > 0.0 {
+            let drift = momentum / mass;
+            for object in &mut self.objects {
+                object.velocity -= drift;
+            }
+        }
+    }
+
+    /// World velocity a body starts with in the gravity mode: its parent's plus the
+    /// body's `launch_velocity`, or the speed of a circular orbit at its current distance
+    pub(crate) fn launch_velocity(&self, object: &CelestialObject) -> Vector3 {
+        let Some(parent) = object.parent_index.and_then(|index| self.objects.get(index)) else {
+            return object.launch_velocity.unwrap_or(Vector3::zero());
+        };
+        if object.ring.is_some() {
+            return parent.velocity;
+        }
+        if let Some(launch) = object.launch_velocity {
+            return parent.velocity + launch;
+        }
+
+        let offset = object.position - parent.position;
+        let distance = offset.length();
+        if distance < 1e-4 {
+            return parent.velocity;
+        }
+        // Along the orbit's direction of travel, at the speed that keeps the distance
+        let ahead = object.orbit.offset(object.orbit_radius, object.orbit_angle + 1e-3)
+            - object.orbit.offset(object.orbit_radius, object.orbit_angle - 1e-3);
+        let ahead = ahead - offset * (ahead.dot(offset) / (distance * distance));
+        let direction = if ahead.length() > 1e-6 {
+            ahead.normalized() * object.orbit_speed.signum()
+        } else {
+            offset.cross(Vector3::new(0.0, 1.0, 0.0)).normalized()
+        };
+        parent.velocity + direction * (self.gravity * parent.mass() / distance).sqrt()
+    }
+
+    /// Advances positions and velocities by `delta_time` (negative runs backward, as the
+    /// integrator is time-reversible); rings stay centered on their parents
+    pub(crate) fn integrate(&mut self, delta_time: f32) {
+        let steps = (delta_time.abs() / MAX_STEP).ceil().max(1.0) as usize;
+        let step = delta_time / steps as f32;
+
+        let mut acceleration = self.accelerations();
+        for _ in 0..steps {
+            for (object, pull) in self.objects.iter_mut().zip(&acceleration) {
+                object.velocity += *pull * (step * 0.5);
+                object.position += object.velocity * step;
+            }
+            acceleration = self.accelerations();
+            for (object, pull) in self.objects.iter_mut().zip(&acceleration) {
+                object.velocity += *pull * (step * 0.5);
+            }
+        }
+
+        for index in 0..self.objects.len() {
+            if self.objects[index].ring.is_some()
+                && let Some(parent) = self.objects[index].parent_index
+            {
+                self.objects[index].position = self.objects[parent].position;
+                self.objects[index].velocity = self.objects[parent].velocity;
+            }
+        }
+    }
+
+    /// Pull of every other body on each body; rings neither pull nor are pulled
+    fn accelerations(&self) -> Vec<Vector3> {
+        let masses: Vec<f32> = self.objects.iter().map(CelestialObject::mass).collect();
+        self.objects
+            .iter()
+            .enumerate()
+            .map(|(i, object)| {
+                if object.ring.is_some() {
+                    return Vector3::zero();
+                }
+                let mut pull = Vector3::zero();
+                for (j, other) in self.objects.iter().enumerate() {
+                    if i == j || masses[j] == 0.0 {
+                        continue;
+                    }
+                    let offset = other.position - object.position;
+                    let squared = offset.dot(offset) + SOFTENING * SOFTENING;
+                    pull += offset * (self.gravity * masses[j] / (squared * squared.sqrt()));
+                }
+                pull
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,281 @@
+// hud.rs
+// On-screen performance and scene statistics, drawn with raylib text over the framebuffer,
+// and a help panel listing the controls as they are currently bound
+
+use raylib::prelude::*;
+use std::collections::VecDeque;
+use std::time::Duration;
+use crate::input::{Action, Binding, InputMap};
+use crate::profiler::{millis, Stage, StageTimings};
+use crate::renderer::RenderStats;
+use crate::solar_system::{CelestialObject, SolarSystem};
+
+/// Number of frames averaged for the frame time readout
+const FRAME_HISTORY: usize = 30;
+
+/// Bodies listed under "Slowest bodies" when objects are profiled
+pub const SLOWEST_SHOWN: usize = 5;
+
+/// Rows of the help panel: the actions they describe and what they do
+const CONTROLS: [(&[Action], &str); 33] = [
+    (&[Action::OrbitUp, Action::OrbitLeft, Action::OrbitDown, Action::OrbitRight], "Orbit the camera"),
+    (&[Action::ZoomIn, Action::ZoomOut], "Zoom in / out"),
+    (&[Action::PanLeft, Action::PanRight, Action::PanUp, Action::PanDown], "Pan"),
+    (&[Action::MouseOrbit, Action::MousePan], "Drag to orbit / pan, wheel zooms"),
+    (&[Action::Pick], "Click selects a body, double-click follows it"),
+    (&[Action::PresetBasic, Action::PresetAlien, Action::PresetGenerated], "Basic / alien / generated system"),
+    (&[Action::ReloadScene], "Reload the scene file"),
+    (&[Action::FocusNext], "Follow the next body (+modifier previous)"),
+    (&[Action::ToggleCameraMode], "Free-fly camera"),
+    (&[Action::PlayTour], "Cinematic tour"),
+    (&[Action::AddKeyframe], "Add a keyframe (+modifier plays them)"),
+    (&[Action::ToggleShip], "Fly the spacecraft (WASD, R/F, drag)"),
+    (&[Action::Autopilot], "Autopilot to the next planet"),
+    (&[Action::TogglePause, Action::StepFrame], "Pause / step one frame"),
+    (&[Action::SlowDown, Action::SpeedUp], "Simulation speed"),
+    (&[Action::ToggleGravity], "N-body gravity / fixed orbits"),
+    (&[Action::ScrubBack, Action::ScrubForward], "Scrub the timeline (hold)"),
+    (&[Action::Rewind], "Rewind"),
+    (&[Action::ShowLights], "Light gizmos (hold, +modifier directions)"),
+    (&[Action::ToggleOrbits], "Orbit paths"),
+    (&[Action::ToggleLabels], "Body names"),
+    (&[Action::ToggleBackend], "Cycle the render backend"),
+    (&[Action::ToggleAnaglyph], "Red/cyan anaglyph 3D"),
+    (&[Action::CycleDebugView], "Depth / normals / wireframe / overdraw views"),
+    (&[Action::CycleRenderScale], "Render scale 0.5x / 1x / 2x (+modifier down)"),
+    (&[Action::ToggleAntialiasing], "Edge antialiasing (FXAA)"),
+    (&[Action::ToggleNoiseCache], "Baked noise cache"),
+    (&[Action::ToggleInspector], "Noise inspector"),
+    (&[Action::TogglePanel], "Debug panel"),
+    (&[Action::ToggleConsole], "Log console"),
+    (&[Action::ToggleHud], "Statistics"),
+    (&[Action::ToggleHelp], "This help"),
+    (&[Action::Modifier], "Modifier"),
+];
+
+/// Help panel rows for a set of bindings, the keys and what they do for each row of
+/// `CONTROLS`; gamepad buttons are left out to keep the rows short
+pub fn help_lines(map: &InputMap) -> Vec<(String, &'static str)> {
+    CONTROLS
+        .iter()
+        .map(|(actions, description)| {
+            let keys: Vec<String> = actions
+                .iter()
+                .map(|&action| {
+                    let names: Vec<&str> = map
+                        .bindings(action)
+                        .filter(|binding| !matches!(binding, Binding::Gamepad(_)))
+                        .map(Binding::name)
+                        .collect();
+                    if names.is_empty() { "-".to_string() } else { names.join("/") }
+                })
+                .collect();
+            (keys.join(" "), *description)
+        })
+        .collect()
+}
+
+/// The `count` bodies of the scene that took longest to draw according to
+/// `stats.object_timings`, with their time, slowest first
+pub fn slowest_objects(scene: &SolarSystem, stats: &RenderStats, count: usize) -> Vec<(String, Duration)> {
+    let mut times: Vec<(usize, Duration)> = stats.object_timings.iter().map(StageTimings::total).enumerate().collect();
+    times.sort_by_key(|&(_, time)| std::cmp::Reverse(time));
+    times
+        .into_iter()
+        .filter(|(_, time)| !time.is_zero())
+        .take(count)
+        .filter_map(|(index, time)| Some((scene.display_name(index)?, time)))
+        .collect()
+}
+
+/// Body picked with the mouse
+pub struct Selection<'a> {
+    pub name: String,
+    pub object: &'a CelestialObject,
+    /// Distance from the camera to the body's center
+    pub distance: f32,
+}
+
+/// Per-frame values shown by the HUD
+pub struct HudInfo<'a> {
+    pub fps: u32,
+    pub stats: &'a RenderStats,
+    /// Stage timings averaged over the last frames (including presentation)
+    pub timings: StageTimings,
+    /// Bodies that took longest in the last frame and their time, slowest first; empty
+    /// unless objects are profiled
+    pub slowest: Vec<(String, Duration)>,
+    pub time_scale: f32,
+    pub paused: bool,
+    /// Simulated seconds since the scene started
+    pub sim_time: f32,
+    /// Length of a year of the scene in simulated seconds, if it has planets
+    pub year: Option<f32>,
+    pub preset: &'a str,
+    /// Size of the rendered frame and its fraction of the window
+    pub render_size: (i32, i32),
+    pub render_scale: f32,
+    /// Seed the scene's noise and starting orbits derive from
+    pub seed: u32,
+    /// Distance from the camera to its target
+    pub camera_distance: f32,
+    /// Body the camera follows
+    pub focus: Option<String>,
+    /// Body clicked last
+    pub selection: Option<Selection<'a>>,
+    /// Movement speed while the camera flies freely, None when orbiting
+    pub fly_speed: Option<f32>,
+    /// Speed of the piloted spacecraft and the planet its autopilot flies to, None
+    /// while it is parked
+    pub ship: Option<(f32, Option<String>)>,
+    /// Problem to keep visible (e.g. a missing asset)
+    pub warning: Option<&'a str>,
+}
+
+/// Toggleable statistics overlay and help panel
+pub struct Hud {
+    pub visible: bool,
+    /// Whether the controls are listed
+    pub help_visible: bool,
+    /// Rows of the help panel, see `set_controls`
+    help: Vec<(String, &'static str)>,
+    frame_times: VecDeque<f32>,
+}
+
+impl Default for Hud {
+    fn default() -> Self {
+        Hud::new()
+    }
+}
+
+impl Hud {
+    /// Creates a visible HUD with an empty frame time history; the help panel lists the
+    /// default bindings until `set_controls` is called
+    pub fn new() -> Self {
+        Hud {
+            visible: true,
+            help_visible: false,
+            help: help_lines(&InputMap::default()),
+            frame_times: VecDeque::with_capacity(FRAME_HISTORY),
+        }
+    }
+
+    /// Lists the controls of `map` in the help panel
+    pub fn set_controls(&mut self, map: &InputMap) {
+        self.help = help_lines(map);
+    }
+
+    /// Rows of the help panel: keys and description
+    pub fn help(&self) -> &[(String, &'static str)] {
+        &self.help
+    }
+
+    /// Records the duration of the last frame in seconds
+    pub fn record_frame(&mut self, delta_time: f32) {
+        if self.frame_times.len() == FRAME_HISTORY {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(delta_time);
+    }
+
+    /// Average frame time in milliseconds over the recent history
+    pub fn average_frame_ms(&self) -> f32 {
+        if self.frame_times.is_empty() {
+            return 0.0;
+        }
+        self.frame_times.iter().sum::<f32>() / self.frame_times.len() as f32 * 1000.0
+    }
+
+    /// Text lines of the overlay
+    pub fn lines(&self, info: &HudInfo) -> Vec<String> {
+        let mut lines = vec![
+            format!("FPS: {} ({:.1} ms)", info.fps, self.average_frame_ms()),
+            format!("Objects: {} ({} culled)", info.stats.objects, info.stats.culled),
+            format!("Triangles: {}", info.stats.triangles),
+            format!("Fragments: {}", info.stats.fragments),
+            format!("Resolution: {}x{} ({}x)", info.render_size.0, info.render_size.1, info.render_scale),
+            if info.paused {
+                "Paused (. = step)".to_string()
+            } else {
+                format!("Time scale: {:.2}x", info.time_scale)
+            },
+            match info.year {
+                Some(year) => format!("Time: {:.0} s ({:.2} years)", info.sim_time, info.sim_time / year),
+                None => format!("Time: {:.0} s", info.sim_time),
+            },
+            format!("Preset: {} (seed {})", info.preset, info.seed),
+            format!("Camera distance: {:.1}", info.camera_distance),
+        ];
+        if let Some(focus) = &info.focus {
+            lines.push(format!("Following: {focus}"));
+        }
+        if let Some(selection) = &info.selection {
+            let object = selection.object;
+            lines.push(format!("Selected: {}", selection.name));
+            lines.push(format!("  {:?}, {:?}", object.object_type, object.material.shader));
+            lines.push(format!("  Scale {:.2}, orbit {:.1}", object.scale, object.orbit_radius));
+            lines.push(format!("  Distance {:.1}", selection.distance));
+        }
+        if let Some(speed) = info.fly_speed {
+            lines.push(format!("Free fly: {speed:.1} u/s"));
+        }
+        if let Some((speed, autopilot)) = &info.ship {
+            lines.push(format!("Ship: {speed:.1} u/s"));
+            if let Some(target) = autopilot {
+                lines.push(format!("  Autopilot to {target}"));
+            }
+        }
+        for stage in Stage::ALL {
+            lines.push(format!("  {}: {:.2} ms", stage.name(), millis(info.timings.get(stage))));
+        }
+        if !info.slowest.is_empty() {
+            lines.push("Slowest bodies:".to_string());
+            for (name, time) in &info.slowest {
+                lines.push(format!("  {name}: {:.2} ms", millis(*time)));
+            }
+        }
+        lines
+    }
+
+    /// Draws the overlay in the top-left corner and the help panel next to it
+    pub fn draw(&self, d: &mut impl RaylibDraw, info: &HudInfo) {
+        const FONT_SIZE: i32 = 18;
+        const LINE_HEIGHT: i32 = 22;
+        const PADDING: i32 = 8;
+
+        if self.help_visible {
+            const HELP_FONT_SIZE: i32 = 16;
+            const HELP_LINE_HEIGHT: i32 = 19;
+            const KEYS_WIDTH: i32 = 230;
+            let x = if self.visible { 260 } else { 10 };
+            let height = self.help.len() as i32 * HELP_LINE_HEIGHT + PADDING * 2;
+            d.draw_rectangle(x, 10, KEYS_WIDTH + 340, height, Color::new(0, 0, 0, 180));
+            for (i, (keys, description)) in self.help.iter().enumerate() {
+                let y = 10 + PADDING + i as i32 * HELP_LINE_HEIGHT;
+                d.draw_text(keys, x + PADDING, y, HELP_FONT_SIZE, Color::new(255, 220, 140, 255));
+                d.draw_text(description, x + PADDING + KEYS_WIDTH, y, HELP_FONT_SIZE, Color::new(220, 230, 255, 255));
+            }
+        }
+        if !self.visible {
+            return;
+        }
+
+        let lines = self.lines(info);
+        let height = lines.len() as i32 * LINE_HEIGHT + PADDING * 2;
+        d.draw_rectangle(10, 10, 240, height, Color::new(0, 0, 0, 160));
+
+        for (i, line) in lines.iter().enumerate() {
+            d.draw_text(
+                line,
+                10 + PADDING,
+                10 + PADDING + i as i32 * LINE_HEIGHT,
+                FONT_SIZE,
+                Color::new(220, 230, 255, 255),
+            );
+        }
+
+        if let Some(warning) = info.warning {
+            d.draw_text(warning, 10 + PADDING, 10 + height + PADDING, FONT_SIZE, Color::new(255, 200, 80, 255));
+        }
+    }
+}
@@ -0,0 +1,595 @@
+// input.rs
+// Named actions read once per frame: an `InputMap` translates keyboard, mouse and gamepad
+// state into actions, so the same frame can come from the window or a replay and every
+// binding can be changed in config.toml
+
+use raylib::prelude::*;
+
+/// Something the user asks the application to do
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    OrbitLeft,
+    OrbitRight,
+    OrbitUp,
+    OrbitDown,
+    ZoomIn,
+    ZoomOut,
+    PanLeft,
+    PanRight,
+    PanUp,
+    PanDown,
+    PresetBasic,
+    PresetAlien,
+    PresetGenerated,
+    ShowLights,
+    /// Shows or hides every body's orbit path
+    ToggleOrbits,
+    /// Shows or hides the body name labels
+    ToggleLabels,
+    /// Held to alter other actions (light directions, faster scrubbing)
+    Modifier,
+    ToggleNoiseCache,
+    ToggleHud,
+    /// Shows or hides the panel listing the controls
+    ToggleHelp,
+    TogglePanel,
+    ToggleConsole,
+    ToggleBackend,
+    ToggleAnaglyph,
+    /// Switches the FXAA edge smoothing on or off
+    ToggleAntialiasing,
+    TogglePause,
+    StepFrame,
+    ToggleInspector,
+    SelectNext,
+    SelectPrevious,
+    ValueUp,
+    ValueDown,
+    OctavesUp,
+    OctavesDown,
+    SliceForward,
+    SliceBack,
+    ScrubBack,
+    ScrubForward,
+    Rewind,
+    /// Steps the simulation speed up (or down) through `TIME_SCALES`
+    SpeedUp,
+    SlowDown,
+    /// Switches between kinematic orbits and the N-body gravity mode
+    ToggleGravity,
+    /// Steps through the diagnostic render modes (`DebugView`)
+    CycleDebugView,
+    /// Steps the render scale up through `RENDER_SCALE_STEPS` (down with the modifier)
+    CycleRenderScale,
+    /// Reloads the scene file and its script without waiting for a save
+    ReloadScene,
+    /// Follows the next body with the camera (previous with the modifier)
+    FocusNext,
+    /// Switches between the orbit and free-fly camera
+    ToggleCameraMode,
+    /// Starts or stops the cinematic tour
+    PlayTour,
+    /// Adds the current view to the keyframe path (plays the path with the modifier)
+    AddKeyframe,
+    /// Launches the spacecraft ahead of the camera and pilots it, or parks it again
+    ToggleShip,
+    /// Sends the spacecraft's autopilot to the next planet
+    Autopilot,
+    /// Held while dragging the mouse to orbit the camera
+    MouseOrbit,
+    /// Held while dragging the mouse to pan the camera
+    MousePan,
+    /// Clicked on a body to select it, double-clicked to follow it (see `ClickDetector`)
+    Pick,
+}
+
+impl Action {
+    pub const ALL: [Action; 54] = [
+        Action::OrbitLeft,
+        Action::OrbitRight,
+        Action::OrbitUp,
+        Action::OrbitDown,
+        Action::ZoomIn,
+        Action::ZoomOut,
+        Action::PanLeft,
+        Action::PanRight,
+        Action::PanUp,
+        Action::PanDown,
+        Action::PresetBasic,
+        Action::PresetAlien,
+        Action::PresetGenerated,
+        Action::ShowLights,
+        Action::ToggleOrbits,
+        Action::ToggleLabels,
+        Action::Modifier,
+        Action::ToggleNoiseCache,
+        Action::ToggleHud,
+        Action::ToggleHelp,
+        Action::TogglePanel,
+        Action::ToggleConsole,
+        Action::ToggleBackend,
+        Action::ToggleAnaglyph,
+        Action::ToggleAntialiasing,
+        Action::TogglePause,
+        Action::StepFrame,
+        Action::ToggleInspector,
+        Action::SelectNext,
+        Action::SelectPrevious,
+        Action::ValueUp,
+        Action::ValueDown,
+        Action::OctavesUp,
+        Action::OctavesDown,
+        Action::SliceForward,
+        Action::SliceBack,
+        Action::ScrubBack,
+        Action::ScrubForward,
+        Action::Rewind,
+        Action::SpeedUp,
+        Action::SlowDown,
+        Action::ToggleGravity,
+        Action::CycleDebugView,
+        Action::CycleRenderScale,
+        Action::ReloadScene,
+        Action::FocusNext,
+        Action::ToggleCameraMode,
+        Action::PlayTour,
+        Action::AddKeyframe,
+        Action::ToggleShip,
+        Action::Autopilot,
+        Action::MouseOrbit,
+        Action::MousePan,
+        Action::Pick,
+    ];
+
+    /// Name used in config.toml and replay files
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::OrbitLeft => "orbit_left",
+            Action::OrbitRight => "orbit_right",
+            Action::OrbitUp => "orbit_up",
+            Action::OrbitDown => "orbit_down",
+            Action::ZoomIn => "zoom_in",
+            Action::ZoomOut => "zoom_out",
+            Action::PanLeft => "pan_left",
+            Action::PanRight => "pan_right",
+            Action::PanUp => "pan_up",
+            Action::PanDown => "pan_down",
+            Action::PresetBasic => "preset_basic",
+            Action::PresetAlien => "preset_alien",
+            Action::PresetGenerated => "preset_generated",
+            Action::ShowLights => "show_lights",
+            Action::ToggleOrbits => "toggle_orbits",
+            Action::ToggleLabels => "toggle_labels",
+            Action::Modifier => "modifier",
+            Action::ToggleNoiseCache => "toggle_noise_cache",
+            Action::ToggleHud => "toggle_hud",
+            Action::ToggleHelp => "toggle_help",
+            Action::TogglePanel => "toggle_panel",
+            Action::ToggleConsole => "toggle_console",
+            Action::ToggleBackend => "toggle_backend",
+            Action::ToggleAnaglyph => "toggle_anaglyph",
+            Action::ToggleAntialiasing => "toggle_antialiasing",
+            Action::TogglePause => "toggle_pause",
+            Action::StepFrame => "step_frame",
+            Action::ToggleInspector => "toggle_inspector",
+            Action::SelectNext => "select_next",
+            Action::SelectPrevious => "select_previous",
+            Action::ValueUp => "value_up",
+            Action::ValueDown => "value_down",
+            Action::OctavesUp => "octaves_up",
+            Action::OctavesDown => "octaves_down",
+            Action::SliceForward => "slice_forward",
+            Action::SliceBack => "slice_back",
+            Action::ScrubBack => "scrub_back",
+            Action::ScrubForward => "scrub_forward",
+            Action::SpeedUp => "speed_up",
+            Action::SlowDown => "slow_down",
+            Action::ToggleGravity => "toggle_gravity",
+            Action::CycleDebugView => "cycle_debug_view",
+            Action::CycleRenderScale => "cycle_render_scale",
+            Action::Rewind => "rewind",
+            Action::ReloadScene => "reload_scene",
+            Action::FocusNext => "focus_next",
+            Action::ToggleCameraMode => "toggle_camera_mode",
+            Action::PlayTour => "play_tour",
+            Action::AddKeyframe => "add_keyframe",
+            Action::ToggleShip => "toggle_ship",
+            Action::Autopilot => "autopilot",
+            Action::MouseOrbit => "mouse_orbit",
+            Action::MousePan => "mouse_pan",
+            Action::Pick => "pick",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.iter().copied().find(|action| action.name() == name)
+    }
+}
+
+/// Keys by the names accepted in config.toml
+const KEY_NAMES: [(&str, KeyboardKey); 64] = [
+    ("A", KeyboardKey::KEY_A),
+    ("B", KeyboardKey::KEY_B),
+    ("C", KeyboardKey::KEY_C),
+    ("D", KeyboardKey::KEY_D),
+    ("E", KeyboardKey::KEY_E),
+    ("F", KeyboardKey::KEY_F),
+    ("G", KeyboardKey::KEY_G),
+    ("H", KeyboardKey::KEY_H),
+    ("I", KeyboardKey::KEY_I),
+    ("J", KeyboardKey::KEY_J),
+    ("K", KeyboardKey::KEY_K),
+    ("L", KeyboardKey::KEY_L),
+    ("M", KeyboardKey::KEY_M),
+    ("N", KeyboardKey::KEY_N),
+    ("O", KeyboardKey::KEY_O),
+    ("P", KeyboardKey::KEY_P),
+    ("Q", KeyboardKey::KEY_Q),
+    ("R", KeyboardKey::KEY_R),
+    ("S", KeyboardKey::KEY_S),
+    ("T", KeyboardKey::KEY_T),
+    ("U", KeyboardKey::KEY_U),
+    ("V", KeyboardKey::KEY_V),
+    ("W", KeyboardKey::KEY_W),
+    ("X", KeyboardKey::KEY_X),
+    ("Y", KeyboardKey::KEY_Y),
+    ("Z", KeyboardKey::KEY_Z),
+    ("0", KeyboardKey::KEY_ZERO),
+    ("1", KeyboardKey::KEY_ONE),
+    ("2", KeyboardKey::KEY_TWO),
+    ("3", KeyboardKey::KEY_THREE),
+    ("4", KeyboardKey::KEY_FOUR),
+    ("5", KeyboardKey::KEY_FIVE),
+    ("6", KeyboardKey::KEY_SIX),
+    ("7", KeyboardKey::KEY_SEVEN),
+    ("8", KeyboardKey::KEY_EIGHT),
+    ("9", KeyboardKey::KEY_NINE),
+    ("F1", KeyboardKey::KEY_F1),
+    ("F2", KeyboardKey::KEY_F2),
+    ("F3", KeyboardKey::KEY_F3),
+    ("F4", KeyboardKey::KEY_F4),
+    ("F5", KeyboardKey::KEY_F5),
+    ("F6", KeyboardKey::KEY_F6),
+    ("UP", KeyboardKey::KEY_UP),
+    ("DOWN", KeyboardKey::KEY_DOWN),
+    ("LEFT", KeyboardKey::KEY_LEFT),
+    ("RIGHT", KeyboardKey::KEY_RIGHT),
+    ("SPACE", KeyboardKey::KEY_SPACE),
+    ("ENTER", KeyboardKey::KEY_ENTER),
+    ("TAB", KeyboardKey::KEY_TAB),
+    ("BACKSPACE", KeyboardKey::KEY_BACKSPACE),
+    ("LEFT_SHIFT", KeyboardKey::KEY_LEFT_SHIFT),
+    ("RIGHT_SHIFT", KeyboardKey::KEY_RIGHT_SHIFT),
+    ("LEFT_CONTROL", KeyboardKey::KEY_LEFT_CONTROL),
+    ("LEFT_ALT", KeyboardKey::KEY_LEFT_ALT),
+    ("COMMA", KeyboardKey::KEY_COMMA),
+    ("PERIOD", KeyboardKey::KEY_PERIOD),
+    ("SLASH", KeyboardKey::KEY_SLASH),
+    ("MINUS", KeyboardKey::KEY_MINUS),
+    ("EQUAL", KeyboardKey::KEY_EQUAL),
+    ("KP_ADD", KeyboardKey::KEY_KP_ADD),
+    ("KP_SUBTRACT", KeyboardKey::KEY_KP_SUBTRACT),
+    ("GRAVE", KeyboardKey::KEY_GRAVE),
+    ("LEFT_BRACKET", KeyboardKey::KEY_LEFT_BRACKET),
+    ("RIGHT_BRACKET", KeyboardKey::KEY_RIGHT_BRACKET),
+];
+
+const MOUSE_NAMES: [(&str, MouseButton); 3] = [
+    ("MOUSE_LEFT", MouseButton::MOUSE_BUTTON_LEFT),
+    ("MOUSE_RIGHT", MouseButton::MOUSE_BUTTON_RIGHT),
+    ("MOUSE_MIDDLE", MouseButton::MOUSE_BUTTON_MIDDLE),
+];
+
+const GAMEPAD_NAMES: [(&str, GamepadButton); 14] = [
+    ("PAD_UP", GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_UP),
+    ("PAD_DOWN", GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_DOWN),
+    ("PAD_LEFT", GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_LEFT),
+    ("PAD_RIGHT", GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_RIGHT),
+    ("PAD_A", GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_DOWN),
+    ("PAD_B", GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_RIGHT),
+    ("PAD_X", GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_LEFT),
+    ("PAD_Y", GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_UP),
+    ("PAD_LB", GamepadButton::GAMEPAD_BUTTON_LEFT_TRIGGER_1),
+    ("PAD_LT", GamepadButton::GAMEPAD_BUTTON_LEFT_TRIGGER_2),
+    ("PAD_RB", GamepadButton::GAMEPAD_BUTTON_RIGHT_TRIGGER_1),
+    ("PAD_RT", GamepadButton::GAMEPAD_BUTTON_RIGHT_TRIGGER_2),
+    ("PAD_SELECT", GamepadButton::GAMEPAD_BUTTON_MIDDLE_LEFT),
+    ("PAD_START", GamepadButton::GAMEPAD_BUTTON_MIDDLE_RIGHT),
+];
+
+fn lookup<T: Copy>(table: &[(&str, T)], name: &str) -> Option<T> {
+    table.iter().find(|(n, _)| *n == name).map(|&(_, value)| value)
+}
+
+fn reverse_lookup<T: PartialEq>(table: &[(&'static str, T)], value: T) -> Option<&'static str> {
+    table.iter().find(|(_, v)| *v == value).map(|&(name, _)| name)
+}
+
+/// A physical input an action can be bound to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Binding {
+    Key(KeyboardKey),
+    Mouse(MouseButton),
+    /// Button of the first gamepad
+    Gamepad(GamepadButton),
+}
+
+impl Binding {
+    /// Parses a name such as `W`, `LEFT_SHIFT`, `MOUSE_LEFT` or `PAD_A` (case-insensitive)
+    pub fn parse(name: &str) -> Option<Binding> {
+        let name = name.trim().to_ascii_uppercase();
+        lookup(&KEY_NAMES, &name).map(Binding::Key)
+            .or_else(|| lookup(&MOUSE_NAMES, &name).map(Binding::Mouse))
+            .or_else(|| lookup(&GAMEPAD_NAMES, &name).map(Binding::Gamepad))
+    }
+
+    /// Name accepted by `parse`
+    pub fn name(self) -> &'static str {
+        let name = match self {
+            Binding::Key(key) => reverse_lookup(&KEY_NAMES, key),
+            Binding::Mouse(button) => reverse_lookup(&MOUSE_NAMES, button),
+            Binding::Gamepad(button) => reverse_lookup(&GAMEPAD_NAMES, button),
+        };
+        name.unwrap_or("?")
+    }
+
+    fn is_down(self, window: &RaylibHandle) -> bool {
+        match self {
+            Binding::Key(key) => window.is_key_down(key),
+            Binding::Mouse(button) => window.is_mouse_button_down(button),
+            Binding::Gamepad(button) => window.is_gamepad_available(0) && window.is_gamepad_button_down(0, button),
+        }
+    }
+
+    fn is_pressed(self, window: &RaylibHandle) -> bool {
+        match self {
+            Binding::Key(key) => window.is_key_pressed(key),
+            Binding::Mouse(button) => window.is_mouse_button_pressed(button),
+            Binding::Gamepad(button) => window.is_gamepad_available(0) && window.is_gamepad_button_pressed(0, button),
+        }
+    }
+}
+
+/// Bindings from physical inputs to actions; an action may have several
+#[derive(Clone, Debug)]
+pub struct InputMap {
+    bindings: Vec<(Action, Binding)>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        use GamepadButton::*;
+        use KeyboardKey::*;
+        use MouseButton::*;
+
+        let keys = [
+            (Action::OrbitLeft, KEY_A),
+            (Action::OrbitRight, KEY_D),
+            (Action::OrbitUp, KEY_W),
+            (Action::OrbitDown, KEY_S),
+            (Action::ZoomIn, KEY_UP),
+            (Action::ZoomOut, KEY_DOWN),
+            (Action::PanLeft, KEY_Q),
+            (Action::PanLeft, KEY_LEFT),
+            (Action::PanRight, KEY_E),
+            (Action::PanRight, KEY_RIGHT),
+            (Action::PanUp, KEY_R),
+            (Action::PanDown, KEY_F),
+            (Action::PresetBasic, KEY_ONE),
+            (Action::PresetAlien, KEY_TWO),
+            (Action::PresetGenerated, KEY_THREE),
+            (Action::ShowLights, KEY_L),
+            (Action::ToggleOrbits, KEY_O),
+            (Action::ToggleLabels, KEY_I),
+            (Action::Modifier, KEY_LEFT_SHIFT),
+            (Action::ToggleNoiseCache, KEY_B),
+            (Action::ToggleHud, KEY_H),
+            (Action::ToggleHelp, KEY_F1),
+            (Action::TogglePanel, KEY_G),
+            (Action::ToggleConsole, KEY_GRAVE),
+            (Action::ToggleBackend, KEY_T),
+            (Action::ToggleAnaglyph, KEY_V),
+            (Action::ToggleAntialiasing, KEY_F2),
+            (Action::TogglePause, KEY_P),
+            (Action::TogglePause, KEY_SPACE),
+            (Action::StepFrame, KEY_PERIOD),
+            (Action::ToggleInspector, KEY_N),
+            (Action::SelectNext, KEY_RIGHT),
+            (Action::SelectPrevious, KEY_LEFT),
+            (Action::ValueUp, KEY_UP),
+            (Action::ValueDown, KEY_DOWN),
+            (Action::OctavesUp, KEY_X),
+            (Action::OctavesDown, KEY_Z),
+            (Action::SliceForward, KEY_PERIOD),
+            (Action::SliceBack, KEY_COMMA),
+            (Action::ScrubBack, KEY_LEFT_BRACKET),
+            (Action::ScrubForward, KEY_RIGHT_BRACKET),
+            (Action::SpeedUp, KEY_EQUAL),
+            (Action::SpeedUp, KEY_KP_ADD),
+            (Action::SlowDown, KEY_MINUS),
+            (Action::SlowDown, KEY_KP_SUBTRACT),
+            (Action::ToggleGravity, KEY_U),
+            (Action::CycleDebugView, KEY_F3),
+            (Action::CycleRenderScale, KEY_F4),
+            (Action::Rewind, KEY_BACKSPACE),
+            (Action::ReloadScene, KEY_F5),
+            (Action::FocusNext, KEY_TAB),
+            (Action::ToggleCameraMode, KEY_C),
+            (Action::PlayTour, KEY_K),
+            (Action::AddKeyframe, KEY_J),
+            (Action::ToggleShip, KEY_M),
+            (Action::Autopilot, KEY_Y),
+        ];
+        let mouse = [
+            (Action::MouseOrbit, MOUSE_BUTTON_LEFT),
+            (Action::MousePan, MOUSE_BUTTON_RIGHT),
+            (Action::MousePan, MOUSE_BUTTON_MIDDLE),
+            (Action::Pick, MOUSE_BUTTON_LEFT),
+        ];
+        let pad = [
+            (Action::OrbitLeft, GAMEPAD_BUTTON_LEFT_FACE_LEFT),
+            (Action::OrbitRight, GAMEPAD_BUTTON_LEFT_FACE_RIGHT),
+            (Action::OrbitUp, GAMEPAD_BUTTON_LEFT_FACE_UP),
+            (Action::OrbitDown, GAMEPAD_BUTTON_LEFT_FACE_DOWN),
+            (Action::ZoomIn, GAMEPAD_BUTTON_RIGHT_TRIGGER_2),
+            (Action::ZoomOut, GAMEPAD_BUTTON_LEFT_TRIGGER_2),
+            (Action::PanLeft, GAMEPAD_BUTTON_LEFT_TRIGGER_1),
+            (Action::PanRight, GAMEPAD_BUTTON_RIGHT_TRIGGER_1),
+            (Action::TogglePause, GAMEPAD_BUTTON_RIGHT_FACE_DOWN),
+            (Action::ToggleHud, GAMEPAD_BUTTON_MIDDLE_LEFT),
+            (Action::TogglePanel, GAMEPAD_BUTTON_MIDDLE_RIGHT),
+        ];
+
+        InputMap {
+            bindings: keys.into_iter()
+                .map(|(action, key)| (action, Binding::Key(key)))
+                .chain(mouse.into_iter().map(|(action, button)| (action, Binding::Mouse(button))))
+                .chain(pad.into_iter().map(|(action, button)| (action, Binding::Gamepad(button))))
+                .collect(),
+        }
+    }
+}
+
+impl InputMap {
+    /// A map with no bindings at all
+    pub fn empty() -> Self {
+        InputMap { bindings: Vec::new() }
+    }
+
+    /// Adds a binding, keeping the existing ones
+    pub fn bind(&mut self, action: Action, binding: Binding) {
+        if !self.bindings.contains(&(action, binding)) {
+            self.bindings.push((action, binding));
+        }
+    }
+
+    /// Removes every binding of an action
+    pub fn unbind(&mut self, action: Action) {
+        self.bindings.retain(|&(a, _)| a != action);
+    }
+
+    pub fn bindings(&self, action: Action) -> impl Iterator<Item = Binding> + '_ {
+        self.bindings.iter().filter(move |&&(a, _)| a == action).map(|&(_, binding)| binding)
+    }
+
+    /// Bindings of an action joined for help text, e.g. `Q/LEFT/PAD_LB`
+    pub fn describe(&self, action: Action) -> String {
+        let names: Vec<&str> = self.bindings(action).map(Binding::name).collect();
+        if names.is_empty() { "unbound".to_string() } else { names.join("/") }
+    }
+
+    /// Actions whose bindings satisfy a test, each listed once
+    fn collect(&self, mut active: impl FnMut(Binding) -> bool) -> Vec<Action> {
+        let mut actions = Vec::new();
+        for &(action, binding) in &self.bindings {
+            if !actions.contains(&action) && active(binding) {
+                actions.push(action);
+            }
+        }
+        actions
+    }
+}
+
+/// Actions active during one frame and the frame clock
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InputFrame {
+    /// Seconds since the previous frame
+    pub delta_time: f32,
+    /// Seconds since the window opened
+    pub time: f32,
+    pub down: Vec<Action>,
+    pub pressed: Vec<Action>,
+    /// Mouse movement since the previous frame, in pixels
+    pub mouse_delta: Vector2,
+    /// Cursor position in window pixels
+    pub mouse_position: Vector2,
+    /// Scroll wheel movement this frame, positive away from the user
+    pub wheel: f32,
+}
+
+impl InputFrame {
+    /// Reads the current frame from the window through the bindings
+    pub fn capture(window: &RaylibHandle, map: &InputMap) -> Self {
+        InputFrame {
+            delta_time: window.get_frame_time(),
+            time: window.get_time() as f32,
+            down: map.collect(|binding| binding.is_down(window)),
+            pressed: map.collect(|binding| binding.is_pressed(window)),
+            mouse_delta: window.get_mouse_delta(),
+            mouse_position: window.get_mouse_position(),
+            wheel: window.get_mouse_wheel_move(),
+        }
+    }
+
+    /// Drops the mouse movement and drags, when the cursor belongs to a UI element
+    pub fn release_mouse(&mut self) {
+        self.mouse_delta = Vector2::zero();
+        self.wheel = 0.0;
+        let mouse = [Action::MouseOrbit, Action::MousePan, Action::Pick];
+        self.down.retain(|action| !mouse.contains(action));
+        self.pressed.retain(|action| !mouse.contains(action));
+    }
+
+    /// Action is held this frame
+    pub fn is_down(&self, action: Action) -> bool {
+        self.down.contains(&action)
+    }
+
+    /// Action started this frame
+    pub fn is_pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+}
+
+/// Largest cursor travel in pixels between pressing and releasing `Action::Pick` for it
+/// to count as a click rather than a drag
+const CLICK_SLOP: f32 = 4.0;
+
+/// Longest time in seconds between the two clicks of a double click
+const DOUBLE_CLICK_TIME: f32 = 0.35;
+
+/// A click of `Action::Pick`, reported when the button is released
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Click {
+    /// Where the button went down, in window pixels
+    pub position: Vector2,
+    /// Second click of a double click
+    pub double: bool,
+}
+
+/// Tells clicks from drags of `Action::Pick` over consecutive frames; it only reads
+/// `InputFrame`s, so replays click the same places
+#[derive(Clone, Debug, Default)]
+pub struct ClickDetector {
+    /// Where the button went down and how far the cursor has moved since
+    press: Option<(Vector2, f32)>,
+    /// Time and position of the last click, waiting for a second one
+    last_click: Option<(f32, Vector2)>,
+}
+
+impl ClickDetector {
+    /// The click finished this frame, if any
+    pub fn update(&mut self, input: &InputFrame) -> Option<Click> {
+        if input.is_pressed(Action::Pick) {
+            self.press = Some((input.mouse_position, 0.0));
+            return None;
+        }
+        if input.is_down(Action::Pick) {
+            if let Some((_, travel)) = &mut self.press {
+                *travel += input.mouse_delta.length();
+            }
+            return None;
+        }
+
+        let (position, travel) = self.press.take()?;
+        if travel + input.mouse_delta.length() > CLICK_SLOP {
+            return None;
+        }
+        let double = self.last_click.is_some_and(|(time, at)| {
+            input.time - time <= DOUBLE_CLICK_TIME && at.distance_to(position) <= CLICK_SLOP
+        });
+        // A third click starts a new pair
+        self.last_click = if double { None } else { Some((input.time, position)) };
+        Some(Click { position, double })
+    }
+}
@@ -0,0 +1,246 @@
+// json.rs
+// Parser for JSON scene files: builds the same document as the TOML parser, so both
+// formats share the scene loader. glTF models read the raw tree instead
+
+use crate::toml::{Document, ParseError, Table, Value};
+
+/// Parsed JSON before it is mapped onto a document
+pub(crate) enum Json {
+    Value(Value),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Member `key` of an object, None for other values or missing members
+    pub(crate) fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(name, _)| name == key).map(|(_, json)| json),
+            _ => None,
+        }
+    }
+
+    /// Items of an array, empty for other values
+    pub(crate) fn items(&self) -> &[Json] {
+        match self {
+            Json::Array(items) => items,
+            _ => &[],
+        }
+    }
+
+    pub(crate) fn as_f32(&self) -> Option<f32> {
+        match self {
+            Json::Value(value) => value.as_f32(),
+            _ => None,
+        }
+    }
+
+    /// Non-negative integers (indices, counts and offsets)
+    pub(crate) fn as_usize(&self) -> Option<usize> {
+        match self {
+            Json::Value(Value::Int(int)) => usize::try_from(*int).ok(),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::Value(value) => value.as_str(),
+            _ => None,
+        }
+    }
+}
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: impl Into<String>) -> ParseError {
+        ParseError { line: self.line, message: message.into() }
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.peek().is_some_and(|c| c.is_whitespace()) {
+            self.next();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ParseError> {
+        self.skip_whitespace();
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(self.error(format!("expected `{expected}`, found `{c}`"))),
+            None => Err(self.error(format!("expected `{expected}`, found the end of the file"))),
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, ParseError> {
+        self.skip_whitespace();
+        match self.chars.peek().copied() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => Ok(Json::Value(Value::Str(self.string()?))),
+            Some(_) => self.literal(),
+            None => Err(self.error("expected a value, found the end of the file")),
+        }
+    }
+
+    /// Comma-separated items between `open` and `close`
+    fn items(&mut self, open: char, close: char, mut item: impl FnMut(&mut Self) -> Result<(), ParseError>) -> Result<(), ParseError> {
+        self.expect(open)?;
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&close) {
+            self.next();
+            return Ok(());
+        }
+        loop {
+            item(self)?;
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some(c) if c == close => return Ok(()),
+                _ => return Err(self.error(format!("expected `,` or `{close}`"))),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, ParseError> {
+        let mut members = Vec::new();
+        self.items('{', '}', |parser| {
+            parser.skip_whitespace();
+            let key = parser.string()?;
+            parser.expect(':')?;
+            members.push((key, parser.value()?));
+            Ok(())
+        })?;
+        Ok(Json::Object(members))
+    }
+
+    fn array(&mut self) -> Result<Json, ParseError> {
+        let mut items = Vec::new();
+        self.items('[', ']', |parser| {
+            items.push(parser.value()?);
+            Ok(())
+        })?;
+        Ok(Json::Array(items))
+    }
+
+    fn string(&mut self) -> Result<String, ParseError> {
+        if self.next() != Some('"') {
+            return Err(self.error("expected a string"));
+        }
+        let mut text = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(text),
+                Some('\\') => match self.next() {
+                    Some('n') => text.push('\n'),
+                    Some('t') => text.push('\t'),
+                    Some('r') => text.push('\r'),
+                    Some('u') => {
+                        let code: String = (0..4).filter_map(|_| self.next()).collect();
+                        let c = u32::from_str_radix(&code, 16).ok().and_then(char::from_u32);
+                        text.push(c.ok_or_else(|| self.error(format!("invalid escape `\\u{code}`")))?);
+                    }
+                    Some(c @ ('"' | '\\' | '/')) => text.push(c),
+                    _ => return Err(self.error("invalid escape in string")),
+                },
+                Some('\n') | None => return Err(self.error("unterminated string")),
+                Some(c) => text.push(c),
+            }
+        }
+    }
+
+    /// Numbers, `true` and `false`
+    fn literal(&mut self) -> Result<Json, ParseError> {
+        let mut word = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if !(c.is_ascii_alphanumeric() || matches!(c, '-' | '+' | '.')) {
+                break;
+            }
+            word.push(c);
+            self.next();
+        }
+        let value = match word.as_str() {
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            _ => match word.parse::<i64>() {
+                Ok(int) => Value::Int(int),
+                Err(_) => Value::Float(word.parse().map_err(|_| self.error(format!("invalid value `{word}`")))?),
+            },
+        };
+        Ok(Json::Value(value))
+    }
+}
+
+/// Scalars and arrays of scalars, the values a document can hold
+fn to_value(json: Json) -> Option<Value> {
+    match json {
+        Json::Value(value) => Some(value),
+        Json::Array(items) => items.into_iter().map(to_value).collect::<Option<_>>().map(Value::Array),
+        Json::Object(_) => None,
+    }
+}
+
+/// Adds the members of an object to a table, nested objects as "object.key"
+fn flatten(prefix: &str, members: Vec<(String, Json)>, table: &mut Table) -> Result<(), String> {
+    for (key, json) in members {
+        let key = if prefix.is_empty() { key } else { format!("{prefix}.{key}") };
+        match json {
+            Json::Object(members) => flatten(&key, members, table)?,
+            json => {
+                let value = to_value(json).ok_or_else(|| format!("`{key}` cannot hold objects inside arrays"))?;
+                table.insert(key, value);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parses any JSON text into its tree, without mapping it onto a document
+pub(crate) fn parse_tree(text: &str) -> Result<Json, ParseError> {
+    let mut parser = Parser { chars: text.chars().peekable(), line: 1 };
+    let json = parser.value()?;
+    parser.skip_whitespace();
+    if parser.chars.peek().is_some() {
+        return Err(parser.error("unexpected text after the document"));
+    }
+    Ok(json)
+}
+
+/// Parses a JSON object: nested objects become "table.key" values like TOML `[table]`
+/// sections, and arrays of objects become `[[array]]` entries
+pub fn parse(text: &str) -> Result<Document, ParseError> {
+    let Json::Object(members) = parse_tree(text)? else {
+        return Err(ParseError { line: 1, message: "the document must be a JSON object".to_string() });
+    };
+    let line = text.matches('\n').count() + 1;
+    let error = |message: String| ParseError { line, message };
+
+    let mut document = Document::default();
+    for (key, json) in members {
+        match json {
+            Json::Array(items) if !items.is_empty() && items.iter().all(|item| matches!(item, Json::Object(_))) => {
+                let entries = document.arrays.entry(key).or_default();
+                for item in items {
+                    let Json::Object(members) = item else { unreachable!("checked above") };
+                    let mut table = Table::new();
+                    flatten("", members, &mut table).map_err(error)?;
+                    entries.push(table);
+                }
+            }
+            json => flatten("", vec![(key, json)], &mut document.values).map_err(error)?,
+        }
+    }
+    Ok(document)
+}
@@ -0,0 +1,263 @@
+// lib.rs
+// Software rendering pipeline for the solar system: rasterizer, shaders, scene and noise
+
+pub mod framebuffer;
+pub mod triangle;
+pub mod obj;
+pub mod gltf;
+pub mod mesh;
+pub mod lod;
+pub mod matrix;
+pub mod fragment;
+pub mod vertex;
+pub mod camera;
+pub mod camera_path;
+pub mod focus;
+pub mod shaders;
+pub mod light;
+pub mod noise;
+pub mod noise_simd;
+pub mod shader_system;
+pub mod material;
+pub mod shader_registry;
+pub mod atmosphere;
+pub mod aurora;
+pub mod solar_system;
+pub mod collision;
+pub mod asteroid_belt;
+pub mod comet;
+pub mod volcano;
+pub mod storm;
+pub mod spacecraft;
+pub mod generator;
+pub mod corona;
+pub mod particles;
+pub mod gravity;
+pub mod shadow;
+pub mod gizmos;
+pub mod picking;
+pub mod starfield;
+pub mod bloom;
+pub mod fxaa;
+pub mod post;
+pub mod noise_cache;
+pub mod surface_bake;
+pub mod noise_inspector;
+pub mod renderer;
+pub mod export;
+pub mod hud;
+pub mod profiler;
+pub mod config;
+pub mod cli;
+pub mod app;
+pub mod toml;
+pub mod json;
+pub mod logger;
+pub mod console;
+pub mod debug_ui;
+pub mod debug_view;
+pub mod golden;
+pub mod input;
+pub mod replay;
+pub mod bench;
+pub mod simulation;
+pub mod scene_file;
+pub mod panorama;
+pub mod raytracer;
+pub mod backend;
+pub mod arena;
+pub mod clipping;
+pub mod gpu;
+pub mod script;
+
+use raylib::prelude::*;
+use triangle::{rasterize, Bump, Lighting, PixelRect};
+use framebuffer::Framebuffer;
+use obj::Obj;
+use shaders::vertex_shader;
+use light::Light;
+use shader_system::apply_shader_lanes;
+use solar_system::CelestialObject;
+use noise::Noise;
+use shadow::Occluder;
+use noise_cache::BakedNoise;
+use renderer::RenderStats;
+use profiler::Stage;
+use fragment::{BlendMode, Fragment, ShadedFragment};
+use arena::FrameArena;
+use surface_bake::CubeMap;
+use storm::Vortex;
+use volcano::Eruption;
+
+/// Uniforms for shaders
+pub struct Uniforms<'a> {
+    pub model_matrix: Matrix,
+    pub view_matrix: Matrix,
+    pub projection_matrix: Matrix,
+    pub viewport_matrix: Matrix,
+    /// World-space position of the camera, for view-dependent lighting
+    pub camera_position: Vector3,
+    /// Unit direction from the body toward its main light (see
+    /// `light::main_light_direction`), for shaders that treat the night side apart;
+    /// zero without lights
+    pub light_direction: Vector3,
+    pub time: f32,
+    pub noise: Noise,
+    /// Baked low-frequency noise layer of this object, if cached
+    pub baked: Option<&'a BakedNoise>,
+    /// Eruptions going on at the body's surface (`CelestialObject::eruptions`)
+    pub eruptions: &'a [Eruption],
+    /// Great storm of the body (`CelestialObject::vortex`)
+    pub vortex: Option<Vortex>,
+}
+
+/// Vertex stage, triangle assembly and frustum clipping of one object into
+/// `arena.vertices` and `arena.triangles` (the arena is reset first); counts the object
+/// and the triangles that survive clipping. Each vertex of the mesh is transformed once,
+/// however many triangles share it, and triangles are assembled from its index buffer
+pub fn transform_object(
+    uniforms: &Uniforms,
+    mesh: &Obj,
+    arena: &mut FrameArena,
+    stats: &mut RenderStats,
+) {
+    arena.reset();
+
+    // Transform vertices
+    stats.timings.measure(Stage::VertexShading, || {
+        arena.vertices.extend(mesh.vertices.iter().map(|v| vertex_shader(v, uniforms)));
+    });
+
+    // Assemble and clip triangles
+    stats.timings.measure(Stage::Rasterization, || {
+        let FrameArena { vertices, triangles, clipper, .. } = arena;
+        for &[a, b, c] in mesh.indices.as_chunks::<3>().0 {
+            clipper.clip([&vertices[a as usize], &vertices[b as usize], &vertices[c as usize]], &uniforms.viewport_matrix, triangles);
+        }
+    });
+
+    stats.objects += 1;
+    stats.triangles += arena.triangles.len();
+}
+
+/// Fragment stage: runs the object's shader on every fragment, appending to `shaded`;
+/// fragments go through it `LANES` at a time so batched shaders share their noise calls
+pub fn shade_fragments(
+    fragments: &[Fragment],
+    uniforms: &Uniforms,
+    object: &CelestialObject,
+    shaded: &mut Vec<ShadedFragment>,
+) {
+    for chunk in fragments.chunks(noise_simd::LANES) {
+        let colors = apply_shader_lanes(chunk, uniforms, &object.material);
+        shaded.extend(chunk.iter().zip(colors).map(|(fragment, color)| ShadedFragment {
+            x: fragment.position.x as i32,
+            y: fragment.position.y as i32,
+            depth: fragment.depth,
+            color: color + fragment.glow + object.aurora_glow(fragment, uniforms),
+            alpha: object.alpha(fragment, uniforms),
+            blend: object.blend,
+        }));
+    }
+}
+
+/// Fragment stage of a body with a baked surface: colors come from the cube map instead
+/// of its shader, with its emission glowing live over it, appending to `shaded`
+pub fn shade_baked(
+    fragments: &[Fragment],
+    surface: &CubeMap,
+    uniforms: &Uniforms,
+    object: &CelestialObject,
+    shaded: &mut Vec<ShadedFragment>,
+) {
+    shaded.extend(fragments.iter().map(|fragment| ShadedFragment {
+        x: fragment.position.x as i32,
+        y: fragment.position.y as i32,
+        depth: fragment.depth,
+        color: surface.shade(fragment) + object.material.emission(fragment, uniforms) + fragment.glow + object.aurora_glow(fragment, uniforms),
+        alpha: object.alpha(fragment, uniforms),
+        blend: object.blend,
+    }));
+}
+
+/// Runs the vertex, rasterization and fragment stages of a single celestial object
+/// and leaves its shaded fragments in `arena.shaded` without touching a framebuffer;
+/// adds its triangle and fragment counts to `stats`
+pub fn shade_object(
+    uniforms: &Uniforms,
+    mesh: &Obj,
+    lights: &[Light],
+    occluders: &[Occluder],
+    object: &CelestialObject,
+    arena: &mut FrameArena,
+    stats: &mut RenderStats,
+) {
+    transform_object(uniforms, mesh, arena, stats);
+
+    // Rasterize
+    let lighting = Lighting {
+        lights,
+        occluders,
+        material: object.material,
+        eye: uniforms.camera_position,
+        bump: object.material.relief().map(|relief| Bump { relief, uniforms }),
+        atmosphere: object.atmosphere,
+        vertex_colors: object.model.is_some(),
+    };
+    stats.timings.measure(Stage::Rasterization, || {
+        for [v1, v2, v3] in &arena.triangles {
+            rasterize(v1, v2, v3, &lighting, PixelRect::UNBOUNDED, &mut arena.fragments);
+        }
+    });
+    stats.fragments += arena.fragments.len();
+
+    // Apply shader
+    stats.timings.measure(Stage::FragmentShading, || {
+        shade_fragments(&arena.fragments, uniforms, object, &mut arena.shaded);
+    });
+}
+
+/// Writes shaded fragments to the framebuffer with depth testing
+pub fn write_fragments(framebuffer: &mut Framebuffer, fragments: &[ShadedFragment]) {
+    let mut translucent = Vec::new();
+    write_opaque(framebuffer, fragments, &mut translucent);
+    blend_translucent(framebuffer, &mut translucent);
+}
+
+/// First pass: depth-tested writes of the opaque fragments; the translucent ones are
+/// set aside in `translucent` for the second pass
+pub fn write_opaque(framebuffer: &mut Framebuffer, fragments: &[ShadedFragment], translucent: &mut Vec<ShadedFragment>) {
+    for fragment in fragments {
+        if fragment.blend == BlendMode::Opaque {
+            framebuffer.point(fragment.x, fragment.y, fragment.depth, fragment.color);
+        } else {
+            translucent.push(*fragment);
+        }
+    }
+}
+
+/// Second pass: blends translucent fragments over the finished opaque image, farthest
+/// first so nearer layers end up on top; ties keep their submission order
+pub fn blend_translucent(framebuffer: &mut Framebuffer, translucent: &mut [ShadedFragment]) {
+    translucent.sort_by(|a, b| b.depth.total_cmp(&a.depth));
+    for f in translucent.iter() {
+        framebuffer.blend(f.x, f.y, f.depth, f.color, f.alpha, f.blend);
+    }
+}
+
+/// Render a single celestial object, adding its triangle and fragment counts to `stats`
+/// Meant for one-off draws: the scratch buffers are dropped afterwards, while the
+/// rasterizer backend keeps an arena per object across frames
+pub fn render_object(
+    framebuffer: &mut Framebuffer,
+    uniforms: &Uniforms,
+    mesh: &Obj,
+    lights: &[Light],
+    occluders: &[Occluder],
+    object: &CelestialObject,
+    stats: &mut RenderStats,
+) {
+    let mut arena = FrameArena::default();
+    shade_object(uniforms, mesh, lights, occluders, object, &mut arena, stats);
+    stats.timings.measure(Stage::FragmentShading, || write_fragments(framebuffer, &arena.shaded));
+}
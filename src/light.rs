@@ -4,13 +4,44 @@
 use raylib::prelude::*;
 
 /// Represents a point light source in 3D space
+#[derive(Clone, Debug)]
 pub struct Light {
     pub position: Vector3,
+    /// Light color (RGB, 0-1)
+    pub color: Vector3,
+    /// Brightness multiplier applied to the diffuse and specular terms
+    pub intensity: f32,
+    /// Radius of the emitting body (0 for a point light), used for soft shadows
+    pub radius: f32,
+    /// Fraction of the light that reaches every surface regardless of direction or
+    /// shadows (scattered light, keeps night sides from going fully black)
+    pub ambient: f32,
+    /// Inverse-square falloff coefficient: received light is divided by
+    /// `1 + falloff * distance²` (0 keeps the light constant with distance)
+    pub falloff: f32,
 }
 
 impl Light {
-    /// Creates a new point light at the specified position
-    pub fn new(position: Vector3) -> Self {
-        Light { position }
+    /// Creates a new point light at the specified position, without ambient light or falloff
+    pub fn new(position: Vector3, color: Vector3, intensity: f32, radius: f32) -> Self {
+        Light { position, color, intensity, radius, ambient: 0.0, falloff: 0.0 }
     }
-}
\ No newline at end of file
+
+    /// Fraction of the light left at `distance` from its center
+    #[inline]
+    pub fn attenuation(&self, distance: f32) -> f32 {
+        1.0 / (1.0 + self.falloff * distance * distance)
+    }
+}
+
+/// Unit direction from `position` toward the light that reaches it most strongly (its
+/// "sun"), zero without lights
+pub fn main_light_direction(lights: &[Light], position: Vector3) -> Vector3 {
+    lights
+        .iter()
+        .map(|light| (light, light.intensity * light.attenuation(light.position.distance_to(position))))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(light, _)| light.position - position)
+        .filter(|offset| offset.length() > 0.0)
+        .map_or(Vector3::zero(), |offset| offset.normalized())
+}
@@ -0,0 +1,64 @@
+// lod.rs
+// Level of detail: bodies covering few pixels are drawn with coarser sphere meshes
+
+use raylib::prelude::*;
+use crate::matrix::multiply_matrix_vector4;
+use crate::mesh::{generate_uv_sphere, SPHERE_RADIUS};
+use crate::obj::Obj;
+use crate::vertex::Vertex;
+
+/// Tessellations below the full body mesh, finest first (latitude bands, longitude slices)
+const LEVELS: [(u32, u32); 3] = [(10, 20), (6, 12), (4, 8)];
+
+/// Smallest projected radius in pixels drawn with each level: the full mesh at 48 px
+/// and above, then the generated levels in order
+pub const LOD_THRESHOLDS: [f32; 3] = [48.0, 16.0, 6.0];
+
+/// Coarser spheres matching a body mesh of `mesh_radius`
+pub fn detail_levels(mesh_radius: f32) -> Vec<Obj> {
+    let scale = mesh_radius / SPHERE_RADIUS;
+    LEVELS
+        .iter()
+        .map(|&(lat, lon)| {
+            let mut sphere = generate_uv_sphere(lat, lon);
+            for vertex in &mut sphere.vertices {
+                *vertex = Vertex::new(vertex.position * scale, vertex.normal, vertex.tex_coords);
+            }
+            sphere
+        })
+        .collect()
+}
+
+/// Distance from the origin to the nearest face plane of a mesh: the radius of the
+/// largest sphere around the origin that fits inside the closed mesh
+pub fn inner_radius(mesh: &Obj) -> f32 {
+    mesh.triangles()
+        .filter_map(|[a, b, c]| {
+            let (a, b, c) = (a.position, b.position, c.position);
+            let normal = (b - a).cross(c - a);
+            (normal.length() > 0.0).then(|| normal.normalized().dot(a).abs())
+        })
+        .fold(f32::INFINITY, f32::min)
+}
+
+/// Level for a body of the given projected radius: 0 is the full mesh, `n` the n-th of
+/// `levels` generated ones
+pub fn select_level(screen_radius: f32, levels: usize) -> usize {
+    LOD_THRESHOLDS
+        .iter()
+        .position(|&threshold| screen_radius >= threshold)
+        .unwrap_or(LOD_THRESHOLDS.len())
+        .min(levels)
+}
+
+/// Radius in pixels of a sphere at `center` once projected; infinite when the camera is
+/// inside or right next to it
+pub fn screen_radius(center: Vector3, radius: f32, view: &Matrix, projection: &Matrix, viewport: &Matrix) -> f32 {
+    let view_position = multiply_matrix_vector4(view, &Vector4::new(center.x, center.y, center.z, 1.0));
+    let distance = Vector3::new(view_position.x, view_position.y, view_position.z).length();
+    if distance <= radius {
+        return f32::INFINITY;
+    }
+    // Vertical focal length in pixels: projection scale times half the viewport height
+    radius * projection.m5 * viewport.m5.abs() / distance
+}
@@ -0,0 +1,134 @@
+// logger.rs
+// `log` backend: per-module level filters, stderr output and a buffer of recent
+// messages for the on-screen console
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::collections::VecDeque;
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+/// Messages kept for the on-screen console
+const HISTORY: usize = 64;
+
+/// One logged message
+#[derive(Clone, Debug)]
+pub struct LogLine {
+    pub level: Level,
+    /// Module that emitted it, without the crate prefix
+    pub target: String,
+    pub message: String,
+    /// Seconds since the logger was installed
+    pub time: f32,
+}
+
+/// Level filter with per-module overrides, e.g. "info,lab5::scene_file=debug"
+#[derive(Clone, Debug, PartialEq)]
+pub struct Filter {
+    default: LevelFilter,
+    /// Module path prefixes and their level, longest prefix first
+    modules: Vec<(String, LevelFilter)>,
+}
+
+impl Filter {
+    pub fn parse(spec: &str) -> Result<Filter, String> {
+        let mut filter = Filter { default: LevelFilter::Info, modules: Vec::new() };
+
+        for directive in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            let parse_level = |text: &str| {
+                text.parse::<LevelFilter>()
+                    .map_err(|_| format!("invalid log level '{text}' (expected off, error, warn, info, debug or trace)"))
+            };
+            match directive.split_once('=') {
+                Some((module, level)) => filter.modules.push((module.trim().to_string(), parse_level(level.trim())?)),
+                None => filter.default = parse_level(directive)?,
+            }
+        }
+
+        filter.modules.sort_by_key(|(module, _)| std::cmp::Reverse(module.len()));
+        Ok(filter)
+    }
+
+    /// Level enabled for a module path
+    pub fn level(&self, target: &str) -> LevelFilter {
+        self.modules
+            .iter()
+            .find(|(module, _)| {
+                target.strip_prefix(module.as_str()).is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            .map_or(self.default, |&(_, level)| level)
+    }
+
+    /// Most verbose level of any module, for `log::set_max_level`
+    fn max_level(&self) -> LevelFilter {
+        self.modules.iter().map(|&(_, level)| level).fold(self.default, Ord::max)
+    }
+}
+
+struct Logger {
+    filter: Mutex<Filter>,
+    recent: Mutex<VecDeque<LogLine>>,
+    start: Instant,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let filter = self.filter.lock().expect("logger poisoned");
+        metadata.level() <= filter.level(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let target = record.target();
+        let target = target.strip_prefix("lab5::").unwrap_or(target).to_string();
+        let message = record.args().to_string();
+        eprintln!("[{:<5} {target}] {message}", record.level());
+
+        let mut recent = self.recent.lock().expect("logger poisoned");
+        if recent.len() == HISTORY {
+            recent.pop_front();
+        }
+        recent.push_back(LogLine {
+            level: record.level(),
+            target,
+            message,
+            time: self.start.elapsed().as_secs_f32(),
+        });
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+/// Installs the logger on first use and sets its filter
+pub fn init(filter: Filter) {
+    log::set_max_level(filter.max_level());
+    let mut installed = false;
+    let logger = LOGGER.get_or_init(|| {
+        installed = true;
+        Logger {
+            filter: Mutex::new(filter.clone()),
+            recent: Mutex::new(VecDeque::with_capacity(HISTORY)),
+            start: Instant::now(),
+        }
+    });
+
+    if installed {
+        // Fails only if another logger was installed first, which then keeps the messages
+        let _ = log::set_logger(logger);
+    } else {
+        *logger.filter.lock().expect("logger poisoned") = filter;
+    }
+}
+
+/// The most recent messages, oldest first
+pub fn recent(count: usize) -> Vec<LogLine> {
+    let Some(logger) = LOGGER.get() else {
+        return Vec::new();
+    };
+    let recent = logger.recent.lock().expect("logger poisoned");
+    recent.iter().skip(recent.len().saturating_sub(count)).cloned().collect()
+}
@@ -0,0 +1,317 @@
+// material.rs
+// Data-driven surfaces: the shader of a body, the palette and noise layer it draws with
+// and how the surface takes the light, so a new look is a new `Material` value instead of
+// a copied shader
+
+use raylib::prelude::*;
+use crate::fragment::Fragment;
+use crate::noise::Noise;
+use crate::shader_registry::ShaderRegistry;
+use crate::shader_system::{
+    cloud_planet_shader, gas_giant_shader, ice_shader, lava_emission, lava_shader, ring_shader, rocky_shader, star_shader, BakedLayer,
+    Relief, ShaderType,
+};
+use crate::Uniforms;
+
+/// Most colors a palette holds; shaders leave the slots they don't use black
+pub const PALETTE_SIZE: usize = 8;
+
+/// Noise function a layer samples
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NoiseKind {
+    /// Fractal value noise
+    Fbm,
+    /// Fractal simplex noise
+    FbmSimplex,
+    /// 4D turbulence, the time running along the fourth axis
+    Turbulence4d,
+    /// 4D domain-warped noise, `gain` being the warp strength
+    Warp4d,
+}
+
+/// Main noise layer of a look: which noise, at what frequency and how many octaves
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct NoiseLayer {
+    pub kind: NoiseKind,
+    /// Frequency along each model-space axis
+    pub scale: Vector3,
+    pub octaves: i32,
+    /// Frequency multiplier between octaves
+    pub lacunarity: f32,
+    /// Amplitude multiplier between octaves
+    pub gain: f32,
+    /// Multiplies the time the 4D kinds animate with
+    pub speed: f32,
+}
+
+impl NoiseLayer {
+    /// Value of the layer at a model-space position and time
+    pub fn sample(&self, noise: &Noise, pos: Vector3, time: f32) -> f32 {
+        let p = Vector3::new(pos.x * self.scale.x, pos.y * self.scale.y, pos.z * self.scale.z);
+        match self.kind {
+            NoiseKind::Fbm => noise.fbm(p, self.octaves, self.lacunarity, self.gain),
+            NoiseKind::FbmSimplex => noise.fbm_simplex(p, self.octaves, self.lacunarity, self.gain),
+            NoiseKind::Turbulence4d => noise.turbulence_4d(p, time * self.speed, self.octaves),
+            NoiseKind::Warp4d => noise.warp_noise_4d(p, time * self.speed, self.gain),
+        }
+    }
+}
+
+/// Surface of a body: the shader that draws it, the data it draws with and how it
+/// reflects the light
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Material {
+    /// Fragment shader; `Model` and `Custom` ignore the palette and noise layer
+    pub shader: ShaderType,
+    /// Base colors the shader mixes, in the order its built-in look lists them
+    pub palette: [Vector3; PALETTE_SIZE],
+    /// Main noise layer, the one baked and followed by the relief when the shader has them
+    pub noise: NoiseLayer,
+    /// Unlit: skips diffuse lighting and shadows
+    pub emissive: bool,
+    /// Diffuse reflectance, multiplied with the light color before the surface shader
+    /// adds its own colors
+    pub diffuse: Vector3,
+    /// Strength of the specular highlight, 0 for a matte surface
+    pub specular: f32,
+    /// Blinn-Phong exponent: higher values give smaller, sharper highlights
+    pub shininess: f32,
+    /// Thin surfaces (rings) are lit on whichever side faces each light
+    pub two_sided: bool,
+}
+
+/// Hashable identity of everything a cached bake of a material depends on
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct MaterialKey {
+    shader: ShaderType,
+    bits: [u32; PALETTE_SIZE * 3 + 8],
+}
+
+/// A palette from its first colors, the remaining slots black
+const fn palette<const N: usize>(colors: [Vector3; N]) -> [Vector3; PALETTE_SIZE] {
+    let mut out = [Vector3::new(0.0, 0.0, 0.0); PALETTE_SIZE];
+    let mut i = 0;
+    while i < N {
+        out[i] = colors[i];
+        i += 1;
+    }
+    out
+}
+
+const fn fbm(kind: NoiseKind, frequency: f32, octaves: i32) -> NoiseLayer {
+    NoiseLayer {
+        kind,
+        scale: Vector3::new(frequency, frequency, frequency),
+        octaves,
+        lacunarity: 2.0,
+        gain: 0.5,
+        speed: 0.0,
+    }
+}
+
+impl Material {
+    /// Grey surface without highlights, drawn with the colors of its vertices
+    pub const MATTE: Material = Material::plain(ShaderType::Model);
+
+    /// Sun: white core, yellow and orange surface, red flares and dark spots
+    pub const STAR: Material = Material {
+        shader: ShaderType::Star,
+        palette: palette([
+            Vector3::new(1.0, 1.0, 1.0),
+            Vector3::new(1.0, 0.95, 0.4),
+            Vector3::new(1.0, 0.6, 0.15),
+            Vector3::new(1.0, 0.4, 0.2),
+            Vector3::new(0.7, 0.2, 0.05),
+        ]),
+        noise: NoiseLayer { speed: 1.2, ..fbm(NoiseKind::Turbulence4d, 2.5, 4) },
+        emissive: true,
+        ..Material::MATTE
+    };
+
+    /// Mars-like: dark, mid and light rock
+    pub const ROCKY: Material = Material {
+        shader: ShaderType::Rocky,
+        palette: palette([
+            Vector3::new(0.25, 0.15, 0.10),
+            Vector3::new(0.55, 0.35, 0.22),
+            Vector3::new(0.85, 0.65, 0.45),
+        ]),
+        noise: fbm(NoiseKind::Fbm, 3.0, 3),
+        specular: 0.05, shininess: 8.0,
+        ..Material::MATTE
+    };
+
+    /// Jupiter-like: light, mid and dark bands, then the pale storms
+    pub const GAS_GIANT: Material = Material {
+        shader: ShaderType::GasGiant,
+        palette: palette([
+            Vector3::new(0.95, 0.85, 0.7),
+            Vector3::new(0.85, 0.55, 0.35),
+            Vector3::new(0.55, 0.35, 0.25),
+            Vector3::new(1.0, 0.9, 0.85),
+        ]),
+        noise: NoiseLayer { scale: Vector3::new(2.0, 4.0, 2.0), speed: 0.05, ..fbm(NoiseKind::Turbulence4d, 2.0, 3) },
+        specular: 0.1, shininess: 12.0,
+        ..Material::MATTE
+    };
+
+    /// Molten world: dark crust, hot lava and the bright cracks
+    pub const LAVA: Material = Material {
+        shader: ShaderType::Lava,
+        palette: palette([
+            Vector3::new(0.1, 0.05, 0.0),
+            Vector3::new(1.0, 0.3, 0.0),
+            Vector3::new(1.0, 0.8, 0.1),
+        ]),
+        noise: NoiseLayer { gain: 0.6, speed: 1.0, ..fbm(NoiseKind::Warp4d, 2.0, 1) },
+        specular: 0.25, shininess: 24.0,
+        ..Material::MATTE
+    };
+
+    /// Frozen planet: deep ice, surface ice and fresh snow
+    pub const ICE: Material = Material {
+        shader: ShaderType::IceWorld,
+        palette: palette([
+            Vector3::new(0.3, 0.6, 0.9),
+            Vector3::new(0.8, 0.9, 1.0),
+            Vector3::new(1.0, 1.0, 1.0),
+        ]),
+        noise: fbm(NoiseKind::Fbm, 8.0, 3),
+        specular: 0.7, shininess: 64.0,
+        ..Material::MATTE
+    };
+
+    /// Earth-like: ocean, clouds, polar ice, then grassland, forest, jungle and sand
+    pub const CLOUD: Material = Material {
+        shader: ShaderType::CloudPlanet,
+        palette: palette([
+            Vector3::new(0.1, 0.3, 0.6),
+            Vector3::new(1.0, 1.0, 1.0),
+            Vector3::new(0.92, 0.95, 1.0),
+            Vector3::new(0.4, 0.5, 0.3),
+            Vector3::new(0.2, 0.4, 0.2),
+            Vector3::new(0.1, 0.32, 0.12),
+            Vector3::new(0.76, 0.64, 0.42),
+        ]),
+        noise: fbm(NoiseKind::FbmSimplex, 2.0, 4),
+        specular: 0.6, shininess: 48.0,
+        ..Material::MATTE
+    };
+
+    /// Planetary ring: dust, ice and the gaps between the rings; its noise runs along the
+    /// distance to the center
+    pub const RINGS: Material = Material {
+        shader: ShaderType::Rings,
+        palette: palette([
+            Vector3::new(0.55, 0.45, 0.35),
+            Vector3::new(0.9, 0.85, 0.75),
+            Vector3::new(0.08, 0.07, 0.06),
+        ]),
+        noise: fbm(NoiseKind::Fbm, 6.0, 4),
+        specular: 0.15, shininess: 16.0, two_sided: true,
+        ..Material::MATTE
+    };
+
+    /// Grey matte surface drawn by a shader that keeps its colors elsewhere (model
+    /// materials, custom shaders)
+    pub const fn plain(shader: ShaderType) -> Material {
+        Material {
+            shader,
+            palette: palette([]),
+            noise: fbm(NoiseKind::Fbm, 1.0, 1),
+            emissive: false,
+            diffuse: Vector3::new(0.5, 0.5, 0.5),
+            specular: 0.0,
+            shininess: 1.0,
+            two_sided: false,
+        }
+    }
+
+    /// Grey surface with a highlight of the given strength and sharpness
+    pub fn glossy(specular: f32, shininess: f32) -> Self {
+        Material { specular, shininess, ..Material::MATTE }
+    }
+
+    /// Noise layer of this surface that can be baked once per body, if any
+    pub fn baked_layer(&self) -> Option<BakedLayer> {
+        match self.shader {
+            ShaderType::Rocky | ShaderType::IceWorld | ShaderType::CloudPlanet => Some(BakedLayer { noise: self.noise, refresh: None }),
+            ShaderType::GasGiant => Some(BakedLayer { noise: self.noise, refresh: Some(1.0) }),
+            // Star and lava change too quickly to cache; rings only vary with the radius
+            ShaderType::Star | ShaderType::Lava | ShaderType::Rings | ShaderType::Model | ShaderType::Custom(_) => None,
+        }
+    }
+
+    /// Height field that shapes this surface's lighting, if it has relief; gas, molten
+    /// rock and rings are smooth
+    pub fn relief(&self) -> Option<Relief> {
+        let strength = match self.shader {
+            ShaderType::Rocky => 0.02,
+            ShaderType::IceWorld => 0.01,
+            ShaderType::CloudPlanet => 0.015,
+            ShaderType::Star | ShaderType::GasGiant | ShaderType::Lava | ShaderType::Rings | ShaderType::Model | ShaderType::Custom(_) => return None,
+        };
+        Some(Relief { noise: self.noise, strength })
+    }
+
+    /// Identity of the shader, palette and noise layer, for caches of baked surfaces
+    pub fn key(&self) -> MaterialKey {
+        let mut bits = [0; PALETTE_SIZE * 3 + 8];
+        for (i, color) in self.palette.iter().enumerate() {
+            bits[i * 3..i * 3 + 3].copy_from_slice(&[color.x.to_bits(), color.y.to_bits(), color.z.to_bits()]);
+        }
+        let noise = &self.noise;
+        bits[PALETTE_SIZE * 3..].copy_from_slice(&[
+            noise.kind as u32,
+            noise.scale.x.to_bits(),
+            noise.scale.y.to_bits(),
+            noise.scale.z.to_bits(),
+            noise.octaves as u32,
+            noise.lacunarity.to_bits(),
+            noise.gain.to_bits(),
+            noise.speed.to_bits(),
+        ]);
+        MaterialKey { shader: self.shader, bits }
+    }
+
+    /// Color of a fragment in this look (`uniforms.baked` holding its baked layer): its
+    /// lit surface plus the light it gives off
+    pub fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> Vector3 {
+        self.surface(fragment, uniforms) + self.emission(fragment, uniforms)
+    }
+
+    /// Light the surface gives off by itself, added after the lighting so it glows the
+    /// same on the night side; baked surfaces add it live over the cube map
+    pub fn emission(&self, fragment: &Fragment, uniforms: &Uniforms) -> Vector3 {
+        match self.shader {
+            ShaderType::Lava => lava_emission(fragment, uniforms, self),
+            _ => Vector3::zero(),
+        }
+    }
+
+    /// Lit color of a fragment in this look, without its emission
+    pub fn surface(&self, fragment: &Fragment, uniforms: &Uniforms) -> Vector3 {
+        match self.shader {
+            ShaderType::Star => star_shader(fragment, uniforms, self),
+            ShaderType::Rocky => rocky_shader(fragment, uniforms, self),
+            ShaderType::GasGiant => gas_giant_shader(fragment, uniforms, self),
+            ShaderType::Lava => lava_shader(fragment, uniforms, self),
+            ShaderType::IceWorld => ice_shader(fragment, uniforms, self),
+            ShaderType::CloudPlanet => cloud_planet_shader(fragment, uniforms, self),
+            ShaderType::Rings => ring_shader(fragment, uniforms, self),
+            // The materials are already in the lit color (vertex colors)
+            ShaderType::Model => fragment.color + fragment.specular,
+            // A lit but unshaded surface if nothing was registered for the id
+            ShaderType::Custom(id) => match ShaderRegistry::get(id) {
+                Some(shader) => shader(fragment, uniforms),
+                None => fragment.color + fragment.specular,
+            },
+        }
+    }
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material::MATTE
+    }
+}
@@ -16,6 +16,7 @@ pub fn multiply_matrix_vector4(matrix: &Matrix, vector: &Vector4) -> Vector4 {
 
 /// Creates a 4x4 matrix from 16 values in row-major order
 /// Converts to column-major format required by Raylib
+#[allow(clippy::too_many_arguments)]
 fn new_matrix4(
     r0c0: f32, r0c1: f32, r0c2: f32, r0c3: f32,
     r1c0: f32, r1c1: f32, r1c2: f32, r1c3: f32,
@@ -31,7 +32,7 @@ fn new_matrix4(
 }
 
 /// Creates a model matrix combining translation, scale, and rotation (TRS order)
-/// Order of operations: Scale -> Translate -> Rotate (applied right to left in matrix multiplication)
+/// Order of operations: Scale -> Rotate -> Translate (raylib's `Matrix * Matrix` applies the left operand first)
 pub fn create_model_matrix(translation: Vector3, scale: f32, rotation: Vector3) -> Matrix {
     let (sin_x, cos_x) = rotation.x.sin_cos();
     let (sin_y, cos_y) = rotation.y.sin_cos();
@@ -75,7 +76,7 @@ pub fn create_model_matrix(translation: Vector3, scale: f32, rotation: Vector3)
         0.0, 0.0, 0.0, 1.0
     );
 
-    scale_matrix * translation_matrix * rotation_matrix
+    scale_matrix * rotation_matrix * translation_matrix
 }
 
 /// Creates a view matrix (lookAt matrix) for camera transformations
@@ -122,6 +123,15 @@ pub fn create_view_matrix(eye: Vector3, target: Vector3, up: Vector3) -> Matrix
     )
 }
 
+/// World-space position of the camera of a view matrix
+/// The view matrix is a rotation followed by -rotation * eye
+pub fn camera_position(view: &Matrix) -> Vector3 {
+    let right = Vector3::new(view.m0, view.m4, view.m8);
+    let up = Vector3::new(view.m1, view.m5, view.m9);
+    let back = Vector3::new(view.m2, view.m6, view.m10);
+    -(right * view.m12 + up * view.m13 + back * view.m14)
+}
+
 /// Creates a perspective projection matrix
 /// Transforms camera space to clip space
 /// 
@@ -157,4 +167,20 @@ pub fn create_viewport_matrix(x: f32, y: f32, width: f32, height: f32) -> Matrix
         0.0, 0.0, 255.0, 0.0,
         0.0, 0.0, 0.0, 1.0,
     )
+}
+
+/// Projects a world-space point to screen space (x, y in pixels, z = depth)
+/// Returns None when the point is behind the camera
+pub fn project_to_screen(point: Vector3, view: &Matrix, projection: &Matrix, viewport: &Matrix) -> Option<Vector3> {
+    let world = Vector4::new(point.x, point.y, point.z, 1.0);
+    let view_position = multiply_matrix_vector4(view, &world);
+    let clip = multiply_matrix_vector4(projection, &view_position);
+
+    if clip.w <= 0.0 {
+        return None;
+    }
+
+    let ndc = Vector4::new(clip.x / clip.w, clip.y / clip.w, clip.z / clip.w, 1.0);
+    let screen = multiply_matrix_vector4(viewport, &ndc);
+    Some(Vector3::new(screen.x, screen.y, screen.z))
 }
\ No newline at end of file
@@ -0,0 +1,127 @@
+// mesh.rs
+// Procedural meshes, so the renderer needs no model files: spheres at any level of
+// detail and the flat rings around planets
+
+use crate::obj::Obj;
+use crate::vertex::Vertex;
+use raylib::math::{Vector2, Vector3};
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+/// Radius of the generated spheres, matching `models/sphere.obj`
+pub const SPHERE_RADIUS: f32 = 0.5;
+
+/// Equirectangular texture coordinates of a point on the unit sphere
+fn sphere_uv(normal: Vector3) -> Vector2 {
+    let u = normal.z.atan2(normal.x) / (2.0 * PI);
+    let v = normal.y.clamp(-1.0, 1.0).acos() / PI;
+    Vector2::new(u.rem_euclid(1.0), v)
+}
+
+/// Generates a UV sphere with `lat_segments` latitude bands and `lon_segments` longitude slices
+pub fn generate_uv_sphere(lat_segments: u32, lon_segments: u32) -> Obj {
+    let rings = lat_segments.max(2);
+    let segments = lon_segments.max(3);
+
+    let mut vertices = Vec::new();
+    for ring in 0..=rings {
+        let theta = ring as f32 / rings as f32 * PI;
+        for segment in 0..=segments {
+            let phi = segment as f32 / segments as f32 * 2.0 * PI;
+            let normal = Vector3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin());
+            let tex_coords = Vector2::new(segment as f32 / segments as f32, ring as f32 / rings as f32);
+            vertices.push(Vertex::new(normal * SPHERE_RADIUS, normal, tex_coords));
+        }
+    }
+
+    let mut indices = Vec::new();
+    let stride = segments + 1;
+    for ring in 0..rings {
+        for segment in 0..segments {
+            let a = ring * stride + segment;
+            let b = a + stride;
+            // The pole rows would produce zero-area triangles
+            if ring != 0 {
+                indices.extend_from_slice(&[a, b, a + 1]);
+            }
+            if ring != rings - 1 {
+                indices.extend_from_slice(&[a + 1, b, b + 1]);
+            }
+        }
+    }
+
+    Obj { vertices, indices, materials: Vec::new(), sub_meshes: Vec::new() }
+}
+
+/// Generates an icosphere: an icosahedron whose faces are split in four `subdivisions`
+/// times, giving evenly sized triangles without the crowded poles of a UV sphere
+/// (20 * 4^subdivisions triangles)
+pub fn generate_icosphere(subdivisions: u32) -> Obj {
+    let t = (1.0 + 5f32.sqrt()) / 2.0;
+    let mut points: Vec<Vector3> = [
+        (-1.0, t, 0.0), (1.0, t, 0.0), (-1.0, -t, 0.0), (1.0, -t, 0.0),
+        (0.0, -1.0, t), (0.0, 1.0, t), (0.0, -1.0, -t), (0.0, 1.0, -t),
+        (t, 0.0, -1.0), (t, 0.0, 1.0), (-t, 0.0, -1.0), (-t, 0.0, 1.0),
+    ]
+    .iter()
+    .map(|&(x, y, z)| Vector3::new(x, y, z).normalized())
+    .collect();
+
+    let mut faces: Vec<[u32; 3]> = vec![
+        [0, 5, 11], [0, 1, 5], [0, 7, 1], [0, 10, 7], [0, 11, 10],
+        [1, 9, 5], [5, 4, 11], [11, 2, 10], [10, 6, 7], [7, 8, 1],
+        [3, 4, 9], [3, 2, 4], [3, 6, 2], [3, 8, 6], [3, 9, 8],
+        [4, 5, 9], [2, 11, 4], [6, 10, 2], [8, 7, 6], [9, 1, 8],
+    ];
+
+    for _ in 0..subdivisions.min(6) {
+        // Edges shared by two faces get a single midpoint
+        let mut midpoints: HashMap<(u32, u32), u32> = HashMap::new();
+        let mut midpoint = |a: u32, b: u32| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                points.push(((points[a as usize] + points[b as usize]) * 0.5).normalized());
+                points.len() as u32 - 1
+            })
+        };
+
+        faces = faces
+            .iter()
+            .flat_map(|&[a, b, c]| {
+                let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+                [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            })
+            .collect();
+    }
+
+    let vertices = points
+        .iter()
+        .map(|&normal| Vertex::new(normal * SPHERE_RADIUS, normal, sphere_uv(normal)))
+        .collect();
+    let indices = faces.into_iter().flatten().collect();
+    Obj { vertices, indices, materials: Vec::new(), sub_meshes: Vec::new() }
+}
+
+/// Generates a flat ring (annulus) in the XZ plane between `inner` and `outer` radius,
+/// facing +Y, with `segments` slices; U runs around the ring and V outwards
+pub fn generate_ring(inner: f32, outer: f32, segments: u32) -> Obj {
+    let segments = segments.max(3);
+    let normal = Vector3::new(0.0, 1.0, 0.0);
+
+    let mut vertices = Vec::new();
+    for segment in 0..=segments {
+        let u = segment as f32 / segments as f32;
+        let (sin, cos) = (u * 2.0 * PI).sin_cos();
+        for (radius, v) in [(inner, 0.0), (outer, 1.0)] {
+            let position = Vector3::new(cos * radius, 0.0, sin * radius);
+            vertices.push(Vertex::new(position, normal, Vector2::new(u, v)));
+        }
+    }
+
+    let mut indices = Vec::new();
+    for segment in 0..segments {
+        let a = segment * 2;
+        indices.extend_from_slice(&[a, a + 1, a + 2, a + 2, a + 1, a + 3]);
+    }
+
+    Obj { vertices, indices, materials: Vec::new(), sub_meshes: Vec::new() }
+}
@@ -2,253 +2,846 @@
 // Enhanced procedural noise functions with Simplex noise
 
 use raylib::math::Vector3;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+/// Gradient set for 3D simplex noise (midpoints of the cube edges)
+const GRAD3: [[f32; 3]; 12] = [
+    [1.0, 1.0, 0.0], [-1.0, 1.0, 0.0], [1.0, -1.0, 0.0], [-1.0, -1.0, 0.0],
+    [1.0, 0.0, 1.0], [-1.0, 0.0, 1.0], [1.0, 0.0, -1.0], [-1.0, 0.0, -1.0],
+    [0.0, 1.0, 1.0], [0.0, -1.0, 1.0], [0.0, 1.0, -1.0], [0.0, -1.0, -1.0],
+];
+
+/// Dot product of a corner offset with one of the simplex gradients
+#[inline]
+pub(crate) fn grad(index: usize, x: f32, y: f32, z: f32) -> f32 {
+    let g = GRAD3[index % 12];
+    g[0] * x + g[1] * y + g[2] * z
+}
 
-/// Simple hash function for pseudo-random values
+/// Maps the corner sum of 3D simplex noise onto [-1, 1]. Each of the four corners adds
+/// t⁴ (g · d) with t = 0.6 - |d|²; even with every corner hashed to the gradient that
+/// points most along its offset, the sum peaks at 0.030585 (maximized numerically over
+/// the simplex), so 1 / 0.030585 rounded down can't overshoot and no clamp is needed
+pub(crate) const SIMPLEX_SCALE: f32 = 32.69;
+
+/// Sum of the amplitudes of `octaves` fBm octaves, 1 + gain + gain² + …; dividing by it
+/// keeps the sum of octaves within the range of a single one
 #[inline]
-fn hash(p: Vector3) -> f32 {
-    let mut h = p.x * 127.1 + p.y * 311.7 + p.z * 74.7;
-    h = (h.sin() * 43758.5453).fract();
-    h
+pub(crate) fn octave_weight(octaves: i32, gain: f32) -> f32 {
+    let mut weight = 0.0;
+    let mut amplitude = 1.0;
+    for _ in 0..octaves {
+        weight += amplitude;
+        amplitude *= gain;
+    }
+    weight
 }
 
-/// 3D Value noise - simple but effective
-pub fn value_noise(p: Vector3) -> f32 {
-    let i = Vector3::new(p.x.floor(), p.y.floor(), p.z.floor());
-    let f = Vector3::new(p.x.fract(), p.y.fract(), p.z.fract());
-    
-    // Smooth interpolation
-    let u = Vector3::new(
-        f.x * f.x * (3.0 - 2.0 * f.x),
-        f.y * f.y * (3.0 - 2.0 * f.y),
-        f.z * f.z * (3.0 - 2.0 * f.z),
-    );
-
-    // Sample corners of cube
-    let a = hash(Vector3::new(i.x, i.y, i.z));
-    let b = hash(Vector3::new(i.x + 1.0, i.y, i.z));
-    let c = hash(Vector3::new(i.x, i.y + 1.0, i.z));
-    let d = hash(Vector3::new(i.x + 1.0, i.y + 1.0, i.z));
-    let e = hash(Vector3::new(i.x, i.y, i.z + 1.0));
-    let f_val = hash(Vector3::new(i.x + 1.0, i.y, i.z + 1.0));
-    let g = hash(Vector3::new(i.x, i.y + 1.0, i.z + 1.0));
-    let h_val = hash(Vector3::new(i.x + 1.0, i.y + 1.0, i.z + 1.0));
-
-    // Trilinear interpolation
-    let k0 = a;
-    let k1 = b - a;
-    let k2 = c - a;
-    let k3 = e - a;
-    let k4 = a - b - c + d;
-    let k5 = a - c - e + g;
-    let k6 = a - b - e + f_val;
-    let k7 = -a + b + c - d + e - f_val - g + h_val;
-
-    k0 + k1 * u.x + k2 * u.y + k3 * u.z + k4 * u.x * u.y 
-       + k5 * u.y * u.z + k6 * u.z * u.x + k7 * u.x * u.y * u.z
+/// Gradient set for 4D simplex noise (edges of the 4D hypercube)
+const GRAD4: [[f32; 4]; 32] = [
+    [0.0, 1.0, 1.0, 1.0], [0.0, 1.0, 1.0, -1.0], [0.0, 1.0, -1.0, 1.0], [0.0, 1.0, -1.0, -1.0],
+    [0.0, -1.0, 1.0, 1.0], [0.0, -1.0, 1.0, -1.0], [0.0, -1.0, -1.0, 1.0], [0.0, -1.0, -1.0, -1.0],
+    [1.0, 0.0, 1.0, 1.0], [1.0, 0.0, 1.0, -1.0], [1.0, 0.0, -1.0, 1.0], [1.0, 0.0, -1.0, -1.0],
+    [-1.0, 0.0, 1.0, 1.0], [-1.0, 0.0, 1.0, -1.0], [-1.0, 0.0, -1.0, 1.0], [-1.0, 0.0, -1.0, -1.0],
+    [1.0, 1.0, 0.0, 1.0], [1.0, 1.0, 0.0, -1.0], [1.0, -1.0, 0.0, 1.0], [1.0, -1.0, 0.0, -1.0],
+    [-1.0, 1.0, 0.0, 1.0], [-1.0, 1.0, 0.0, -1.0], [-1.0, -1.0, 0.0, 1.0], [-1.0, -1.0, 0.0, -1.0],
+    [1.0, 1.0, 1.0, 0.0], [1.0, 1.0, -1.0, 0.0], [1.0, -1.0, 1.0, 0.0], [1.0, -1.0, -1.0, 0.0],
+    [-1.0, 1.0, 1.0, 0.0], [-1.0, 1.0, -1.0, 0.0], [-1.0, -1.0, 1.0, 0.0], [-1.0, -1.0, -1.0, 0.0],
+];
+
+/// Dot product of a 4D corner offset with one of the simplex gradients
+#[inline]
+fn grad4(index: usize, x: f32, y: f32, z: f32, w: f32) -> f32 {
+    let g = GRAD4[index % 32];
+    g[0] * x + g[1] * y + g[2] * z + g[3] * w
 }
 
-/// Simplex noise gradient function
+/// Squared distance from `p` to the unit cell whose lowest corner is `cell`, zero inside it
 #[inline]
-fn grad(hash: i32, x: f32, y: f32, z: f32) -> f32 {
-    let h = hash & 15;
-    let u = if h < 8 { x } else { y };
-    let v = if h < 4 { y } else if h == 12 || h == 14 { x } else { z };
-    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+pub(crate) fn cell_distance_squared(p: Vector3, cell: Vector3) -> f32 {
+    let gap = |p: f32, lo: f32| (lo - p).max(p - (lo + 1.0)).max(0.0);
+    let (x, y, z) = (gap(p.x, cell.x), gap(p.y, cell.y), gap(p.z, cell.z));
+    x * x + y * y + z * z
+}
+
+/// Result of a Worley (cellular) noise lookup
+#[derive(Clone, Copy, Debug)]
+pub struct Worley {
+    /// Distance to the nearest feature point
+    pub f1: f32,
+    /// Distance to the second nearest feature point
+    pub f2: f32,
+}
+
+impl Worley {
+    /// F2 - F1: near zero along cell borders, good for cracks and crystal edges
+    #[inline]
+    pub fn edge(&self) -> f32 {
+        self.f2 - self.f1
+    }
+}
+
+/// Tileable blue-noise threshold map built with the void-and-cluster method
+/// Unlike white-noise hashes, neighbouring ranks are spread evenly, so
+/// dithering, stochastic transparency and sampling don't clump visibly
+#[derive(Clone, Debug)]
+pub struct BlueNoise {
+    size: usize,
+    /// Rank of every pixel mapped to [0, 1)
+    thresholds: Vec<f32>,
+}
+
+impl BlueNoise {
+    /// Generates a `size` x `size` map; cost is O(size^4), so keep it small (32-128)
+    pub fn new(size: usize, seed: u32) -> Self {
+        let count = size * size;
+
+        // Gaussian energy filter on the torus, indexed by wrapped offset
+        const SIGMA: f32 = 1.5;
+        let mut filter = vec![0.0; count];
+        for dy in 0..size {
+            for dx in 0..size {
+                let wx = dx.min(size - dx) as f32;
+                let wy = dy.min(size - dy) as f32;
+                filter[dy * size + dx] = (-(wx * wx + wy * wy) / (2.0 * SIGMA * SIGMA)).exp();
+            }
+        }
+
+        let toggle = |energy: &mut [f32], pattern: &mut [bool], index: usize, on: bool| {
+            pattern[index] = on;
+            let sign = if on { 1.0 } else { -1.0 };
+            let (px, py) = (index % size, index / size);
+            for (i, e) in energy.iter_mut().enumerate() {
+                let dx = (i % size + size - px) % size;
+                let dy = (i / size + size - py) % size;
+                *e += sign * filter[dy * size + dx];
+            }
+        };
+        // Tightest cluster: set pixel with the most energy; largest void: empty pixel with the least
+        let tightest = |energy: &[f32], pattern: &[bool]| {
+            (0..count).filter(|&i| pattern[i]).max_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+        };
+        let largest_void = |energy: &[f32], pattern: &[bool]| {
+            (0..count).filter(|&i| !pattern[i]).min_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+        };
+
+        // Initial random pattern with ~10% of the pixels set
+        let mut rng = StdRng::seed_from_u64(seed as u64);
+        let mut order: Vec<usize> = (0..count).collect();
+        order.shuffle(&mut rng);
+        let initial = (count / 10).max(1);
+
+        let mut pattern = vec![false; count];
+        let mut energy = vec![0.0; count];
+        for &index in &order[..initial] {
+            toggle(&mut energy, &mut pattern, index, true);
+        }
+
+        // Relax: move the tightest cluster into the largest void until stable
+        while let Some(cluster) = tightest(&energy, &pattern) {
+            toggle(&mut energy, &mut pattern, cluster, false);
+            let void = largest_void(&energy, &pattern).unwrap_or(cluster);
+            toggle(&mut energy, &mut pattern, void, true);
+            if void == cluster {
+                break;
+            }
+        }
+
+        let mut ranks = vec![0usize; count];
+
+        // Phase 1: ranks below the initial pattern, removing clusters
+        let (mut removed_energy, mut removed) = (energy.clone(), pattern.clone());
+        for rank in (0..initial).rev() {
+            if let Some(cluster) = tightest(&removed_energy, &removed) {
+                toggle(&mut removed_energy, &mut removed, cluster, false);
+                ranks[cluster] = rank;
+            }
+        }
+
+        // Phase 2: ranks above it, filling the largest voids
+        for rank in initial..count {
+            if let Some(void) = largest_void(&energy, &pattern) {
+                toggle(&mut energy, &mut pattern, void, true);
+                ranks[void] = rank;
+            }
+        }
+
+        let thresholds = ranks.iter().map(|&rank| (rank as f32 + 0.5) / count as f32).collect();
+        BlueNoise { size, thresholds }
+    }
+
+    /// Threshold in [0, 1) at a pixel, tiling every `size` pixels
+    #[inline]
+    pub fn sample(&self, x: i32, y: i32) -> f32 {
+        let size = self.size as i32;
+        let index = y.rem_euclid(size) * size + x.rem_euclid(size);
+        self.thresholds[index as usize]
+    }
+}
+
+/// Noise value together with its analytic gradient
+#[derive(Clone, Copy, Debug)]
+pub struct NoiseSample {
+    pub value: f32,
+    /// Partial derivatives of `value` along x, y and z
+    pub gradient: Vector3,
 }
 
-/// Simplex noise - more efficient than Perlin, less directional artifacts
-pub fn simplex_noise(p: Vector3) -> f32 {
-    // Skewing factors
-    const F3: f32 = 1.0 / 3.0;
-    const G3: f32 = 1.0 / 6.0;
-
-    // Skew input space
-    let s = (p.x + p.y + p.z) * F3;
-    let i = (p.x + s).floor();
-    let j = (p.y + s).floor();
-    let k = (p.z + s).floor();
-
-    let t = (i + j + k) * G3;
-    let x0 = p.x - (i - t);
-    let y0 = p.y - (j - t);
-    let z0 = p.z - (k - t);
-
-    // Determine simplex
-    let (i1, j1, k1, i2, j2, k2) = if x0 >= y0 {
-        if y0 >= z0 {
-            (1.0, 0.0, 0.0, 1.0, 1.0, 0.0)
-        } else if x0 >= z0 {
-            (1.0, 0.0, 0.0, 1.0, 0.0, 1.0)
+impl NoiseSample {
+    /// Part of the gradient tangent to a surface with the given unit normal,
+    /// i.e. the slope of the noise treated as a height field on that surface
+    #[inline]
+    pub fn surface_gradient(&self, normal: Vector3) -> Vector3 {
+        self.gradient - normal * self.gradient.dot(normal)
+    }
+
+    /// Slope magnitude on a surface with the given unit normal (0 = flat)
+    #[inline]
+    pub fn slope(&self, normal: Vector3) -> f32 {
+        self.surface_gradient(normal).length()
+    }
+
+    /// Bump-mapped normal for the noise used as a height field
+    /// `strength` scales the height, negative values invert the bumps
+    #[inline]
+    pub fn bump_normal(&self, normal: Vector3, strength: f32) -> Vector3 {
+        (normal - self.surface_gradient(normal) * strength).normalized()
+    }
+}
+
+/// Seeded procedural noise generator
+/// Every function is deterministic for a given seed, so two bodies with
+/// different seeds get different patterns and scenes are reproducible
+#[derive(Clone, Copy, Debug)]
+pub struct Noise {
+    seed: u32,
+    /// Shuffled 0..255 permutation, repeated twice to avoid index wrapping
+    perm: [u8; 512],
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Noise::new(0)
+    }
+}
+
+impl Noise {
+    /// Creates a noise generator for the given seed
+    pub fn new(seed: u32) -> Self {
+        let mut table: Vec<u8> = (0..=255).collect();
+        let mut rng = StdRng::seed_from_u64(seed as u64);
+        table.shuffle(&mut rng);
+
+        let mut perm = [0u8; 512];
+        for (i, value) in perm.iter_mut().enumerate() {
+            *value = table[i & 255];
+        }
+
+        Noise { seed, perm }
+    }
+
+    /// Seed used to build this generator
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    /// Gradient index of an integer lattice point from the permutation table
+    #[inline]
+    pub(crate) fn gradient_index(&self, i: i32, j: i32, k: i32) -> usize {
+        let perm = &self.perm;
+        let k = perm[(k & 255) as usize] as usize;
+        let j = perm[(j & 255) as usize + k] as usize;
+        perm[(i & 255) as usize + j] as usize
+    }
+
+    /// Seeded hash of an integer lattice point, returns a value in [0, 1)
+    #[inline]
+    fn hash(&self, p: Vector3) -> f32 {
+        self.hash_channel(p, 0)
+    }
+
+    /// Like `hash`, but `channel` selects an independent stream for the same point
+    #[inline]
+    pub(crate) fn hash_channel(&self, p: Vector3, channel: u32) -> f32 {
+        // Mix each coordinate with the seed
+        let mut h = self.seed.wrapping_mul(0x9E37_79B9) ^ channel.wrapping_mul(0x68E3_1DA4);
+        h ^= (p.x as i32 as u32).wrapping_mul(0x85EB_CA6B);
+        h = h.rotate_left(13) ^ (p.y as i32 as u32).wrapping_mul(0xC2B2_AE35);
+        h = h.rotate_left(13) ^ (p.z as i32 as u32).wrapping_mul(0x27D4_EB2F);
+
+        // Final avalanche (murmur3 finalizer)
+        h ^= h >> 16;
+        h = h.wrapping_mul(0x85EB_CA6B);
+        h ^= h >> 13;
+        h = h.wrapping_mul(0xC2B2_AE35);
+        h ^= h >> 16;
+
+        (h >> 8) as f32 / 16_777_216.0
+    }
+
+    /// 3D Value noise - simple but effective
+    pub fn value_noise(&self, p: Vector3) -> f32 {
+        let i = Vector3::new(p.x.floor(), p.y.floor(), p.z.floor());
+        let f = Vector3::new(p.x.fract(), p.y.fract(), p.z.fract());
+        
+        // Smooth interpolation
+        let u = Vector3::new(
+            f.x * f.x * (3.0 - 2.0 * f.x),
+            f.y * f.y * (3.0 - 2.0 * f.y),
+            f.z * f.z * (3.0 - 2.0 * f.z),
+        );
+
+        // Sample corners of cube
+        let a = self.hash(Vector3::new(i.x, i.y, i.z));
+        let b = self.hash(Vector3::new(i.x + 1.0, i.y, i.z));
+        let c = self.hash(Vector3::new(i.x, i.y + 1.0, i.z));
+        let d = self.hash(Vector3::new(i.x + 1.0, i.y + 1.0, i.z));
+        let e = self.hash(Vector3::new(i.x, i.y, i.z + 1.0));
+        let f_val = self.hash(Vector3::new(i.x + 1.0, i.y, i.z + 1.0));
+        let g = self.hash(Vector3::new(i.x, i.y + 1.0, i.z + 1.0));
+        let h_val = self.hash(Vector3::new(i.x + 1.0, i.y + 1.0, i.z + 1.0));
+
+        // Trilinear interpolation
+        let k0 = a;
+        let k1 = b - a;
+        let k2 = c - a;
+        let k3 = e - a;
+        let k4 = a - b - c + d;
+        let k5 = a - c - e + g;
+        let k6 = a - b - e + f_val;
+        let k7 = -a + b + c - d + e - f_val - g + h_val;
+
+        k0 + k1 * u.x + k2 * u.y + k3 * u.z + k4 * u.x * u.y 
+           + k5 * u.y * u.z + k6 * u.z * u.x + k7 * u.x * u.y * u.z
+    }
+
+    /// Simplex noise - more efficient than Perlin, less directional artifacts
+    /// Uses the seeded permutation table and the 12 edge gradients, output in [-1, 1]
+    pub fn simplex_noise(&self, p: Vector3) -> f32 {
+        // Skewing factors
+        const F3: f32 = 1.0 / 3.0;
+        const G3: f32 = 1.0 / 6.0;
+
+        // Skew input space
+        let s = (p.x + p.y + p.z) * F3;
+        let i = (p.x + s).floor();
+        let j = (p.y + s).floor();
+        let k = (p.z + s).floor();
+
+        let t = (i + j + k) * G3;
+        let x0 = p.x - (i - t);
+        let y0 = p.y - (j - t);
+        let z0 = p.z - (k - t);
+
+        // Determine simplex
+        let (i1, j1, k1, i2, j2, k2) = if x0 >= y0 {
+            if y0 >= z0 {
+                (1.0, 0.0, 0.0, 1.0, 1.0, 0.0)
+            } else if x0 >= z0 {
+                (1.0, 0.0, 0.0, 1.0, 0.0, 1.0)
+            } else {
+                (0.0, 0.0, 1.0, 1.0, 0.0, 1.0)
+            }
         } else {
-            (0.0, 0.0, 1.0, 1.0, 0.0, 1.0)
+            if y0 < z0 {
+                (0.0, 0.0, 1.0, 0.0, 1.0, 1.0)
+            } else if x0 < z0 {
+                (0.0, 1.0, 0.0, 0.0, 1.0, 1.0)
+            } else {
+                (0.0, 1.0, 0.0, 1.0, 1.0, 0.0)
+            }
+        };
+
+        // Offsets for corners
+        let x1 = x0 - i1 + G3;
+        let y1 = y0 - j1 + G3;
+        let z1 = z0 - k1 + G3;
+        let x2 = x0 - i2 + 2.0 * G3;
+        let y2 = y0 - j2 + 2.0 * G3;
+        let z2 = z0 - k2 + 2.0 * G3;
+        let x3 = x0 - 1.0 + 3.0 * G3;
+        let y3 = y0 - 1.0 + 3.0 * G3;
+        let z3 = z0 - 1.0 + 3.0 * G3;
+
+        // Gradient indices from the permutation table
+        let (ii, jj, kk) = (i as i32, j as i32, k as i32);
+        let gi0 = self.gradient_index(ii, jj, kk);
+        let gi1 = self.gradient_index(ii + i1 as i32, jj + j1 as i32, kk + k1 as i32);
+        let gi2 = self.gradient_index(ii + i2 as i32, jj + j2 as i32, kk + k2 as i32);
+        let gi3 = self.gradient_index(ii + 1, jj + 1, kk + 1);
+
+        // Calculate contributions
+        let mut n = 0.0;
+        let t0 = 0.6 - x0 * x0 - y0 * y0 - z0 * z0;
+        if t0 > 0.0 {
+            let t0 = t0 * t0;
+            n += t0 * t0 * grad(gi0, x0, y0, z0);
+        }
+
+        let t1 = 0.6 - x1 * x1 - y1 * y1 - z1 * z1;
+        if t1 > 0.0 {
+            let t1 = t1 * t1;
+            n += t1 * t1 * grad(gi1, x1, y1, z1);
+        }
+
+        let t2 = 0.6 - x2 * x2 - y2 * y2 - z2 * z2;
+        if t2 > 0.0 {
+            let t2 = t2 * t2;
+            n += t2 * t2 * grad(gi2, x2, y2, z2);
+        }
+
+        let t3 = 0.6 - x3 * x3 - y3 * y3 - z3 * z3;
+        if t3 > 0.0 {
+            let t3 = t3 * t3;
+            n += t3 * t3 * grad(gi3, x3, y3, z3);
         }
-    } else {
-        if y0 < z0 {
-            (0.0, 0.0, 1.0, 0.0, 1.0, 1.0)
-        } else if x0 < z0 {
-            (0.0, 1.0, 0.0, 0.0, 1.0, 1.0)
+
+        SIMPLEX_SCALE * n
+    }
+
+    /// Simplex noise with its analytic gradient, same value as `simplex_noise`
+    /// Each corner contributes t^4 * (g . d) with t = 0.6 - |d|^2, so its
+    /// derivative is t^4 * g - 8 * t^3 * (g . d) * d
+    pub fn simplex_noise_with_derivative(&self, p: Vector3) -> NoiseSample {
+        const F3: f32 = 1.0 / 3.0;
+        const G3: f32 = 1.0 / 6.0;
+
+        let s = (p.x + p.y + p.z) * F3;
+        let i = (p.x + s).floor();
+        let j = (p.y + s).floor();
+        let k = (p.z + s).floor();
+
+        let t = (i + j + k) * G3;
+        let d0 = Vector3::new(p.x - (i - t), p.y - (j - t), p.z - (k - t));
+
+        let (i1, j1, k1, i2, j2, k2) = if d0.x >= d0.y {
+            if d0.y >= d0.z {
+                (1, 0, 0, 1, 1, 0)
+            } else if d0.x >= d0.z {
+                (1, 0, 0, 1, 0, 1)
+            } else {
+                (0, 0, 1, 1, 0, 1)
+            }
+        } else if d0.y < d0.z {
+            (0, 0, 1, 0, 1, 1)
+        } else if d0.x < d0.z {
+            (0, 1, 0, 0, 1, 1)
         } else {
-            (0.0, 1.0, 0.0, 1.0, 1.0, 0.0)
+            (0, 1, 0, 1, 1, 0)
+        };
+
+        let (ii, jj, kk) = (i as i32, j as i32, k as i32);
+        let corners = [
+            (d0, self.gradient_index(ii, jj, kk)),
+            (
+                d0 - Vector3::new(i1 as f32, j1 as f32, k1 as f32) + Vector3::one() * G3,
+                self.gradient_index(ii + i1, jj + j1, kk + k1),
+            ),
+            (
+                d0 - Vector3::new(i2 as f32, j2 as f32, k2 as f32) + Vector3::one() * (2.0 * G3),
+                self.gradient_index(ii + i2, jj + j2, kk + k2),
+            ),
+            (
+                d0 - Vector3::one() + Vector3::one() * (3.0 * G3),
+                self.gradient_index(ii + 1, jj + 1, kk + 1),
+            ),
+        ];
+
+        let mut value = 0.0;
+        let mut gradient = Vector3::zero();
+        for (d, gi) in corners {
+            let t = 0.6 - d.dot(d);
+            if t > 0.0 {
+                let g = GRAD3[gi % 12];
+                let g = Vector3::new(g[0], g[1], g[2]);
+                let gd = g.dot(d);
+                let t2 = t * t;
+                let t4 = t2 * t2;
+                value += t4 * gd;
+                gradient += g * t4 - d * (8.0 * t2 * t * gd);
+            }
         }
-    };
 
-    // Offsets for corners
-    let x1 = x0 - i1 + G3;
-    let y1 = y0 - j1 + G3;
-    let z1 = z0 - k1 + G3;
-    let x2 = x0 - i2 + 2.0 * G3;
-    let y2 = y0 - j2 + 2.0 * G3;
-    let z2 = z0 - k2 + 2.0 * G3;
-    let x3 = x0 - 1.0 + 3.0 * G3;
-    let y3 = y0 - 1.0 + 3.0 * G3;
-    let z3 = z0 - 1.0 + 3.0 * G3;
+        NoiseSample { value: SIMPLEX_SCALE * value, gradient: gradient * SIMPLEX_SCALE }
+    }
 
-    // Hash coordinates
-    let gi0 = hash(Vector3::new(i, j, k)) as i32 & 255;
-    let gi1 = hash(Vector3::new(i + i1, j + j1, k + k1)) as i32 & 255;
-    let gi2 = hash(Vector3::new(i + i2, j + j2, k + k2)) as i32 & 255;
-    let gi3 = hash(Vector3::new(i + 1.0, j + 1.0, k + 1.0)) as i32 & 255;
+    /// Gradient index of a 4D integer lattice point from the permutation table
+    #[inline]
+    fn gradient_index_4d(&self, i: i32, j: i32, k: i32, l: i32) -> usize {
+        let perm = &self.perm;
+        let l = perm[(l & 255) as usize] as usize;
+        let k = perm[(k & 255) as usize + l] as usize;
+        let j = perm[(j & 255) as usize + k] as usize;
+        perm[(i & 255) as usize + j] as usize
+    }
 
-    // Calculate contributions
-    let mut n = 0.0;
-    let t0 = 0.6 - x0 * x0 - y0 * y0 - z0 * z0;
-    if t0 > 0.0 {
-        let t0 = t0 * t0;
-        n += t0 * t0 * grad(gi0, x0, y0, z0);
+    /// 4D simplex noise - the fourth coordinate is usually time, so patterns
+    /// evolve in place instead of sliding across the surface, output in [-1, 1]
+    pub fn simplex_noise_4d(&self, p: Vector3, w: f32) -> f32 {
+        // Skewing factors for 4D
+        const F4: f32 = 0.309_017; // (sqrt(5) - 1) / 4
+        const G4: f32 = 0.138_196_6; // (5 - sqrt(5)) / 20
+
+        // Skew input space
+        let s = (p.x + p.y + p.z + w) * F4;
+        let i = (p.x + s).floor();
+        let j = (p.y + s).floor();
+        let k = (p.z + s).floor();
+        let l = (w + s).floor();
+
+        let t = (i + j + k + l) * G4;
+        let x0 = p.x - (i - t);
+        let y0 = p.y - (j - t);
+        let z0 = p.z - (k - t);
+        let w0 = w - (l - t);
+
+        // Rank each coordinate to find which simplex we are in
+        let mut rank = [0; 4];
+        let c = [x0, y0, z0, w0];
+        for a in 0..4 {
+            for b in (a + 1)..4 {
+                if c[a] > c[b] {
+                    rank[a] += 1;
+                } else {
+                    rank[b] += 1;
+                }
+            }
+        }
+        let step = |threshold: i32| -> [i32; 4] {
+            [
+                (rank[0] >= threshold) as i32,
+                (rank[1] >= threshold) as i32,
+                (rank[2] >= threshold) as i32,
+                (rank[3] >= threshold) as i32,
+            ]
+        };
+        let corners = [[0; 4], step(3), step(2), step(1), [1; 4]];
+
+        let (ii, jj, kk, ll) = (i as i32, j as i32, k as i32, l as i32);
+        let mut n = 0.0;
+        for (c_index, corner) in corners.iter().enumerate() {
+            let offset = c_index as f32 * G4;
+            let x = x0 - corner[0] as f32 + offset;
+            let y = y0 - corner[1] as f32 + offset;
+            let z = z0 - corner[2] as f32 + offset;
+            let w = w0 - corner[3] as f32 + offset;
+
+            let t = 0.6 - x * x - y * y - z * z - w * w;
+            if t > 0.0 {
+                let gi = self.gradient_index_4d(
+                    ii + corner[0],
+                    jj + corner[1],
+                    kk + corner[2],
+                    ll + corner[3],
+                );
+                let t = t * t;
+                n += t * t * grad4(gi, x, y, z, w);
+            }
+        }
+
+        27.0 * n
     }
 
-    let t1 = 0.6 - x1 * x1 - y1 * y1 - z1 * z1;
-    if t1 > 0.0 {
-        let t1 = t1 * t1;
-        n += t1 * t1 * grad(gi1, x1, y1, z1);
+    /// Gradient index of a lattice point wrapped to the given period
+    /// A period of 0 leaves that axis unwrapped
+    #[inline]
+    fn periodic_gradient_index(&self, i: i32, j: i32, k: i32, period: [u32; 3]) -> usize {
+        let wrap = |v: i32, p: u32| if p > 0 { v.rem_euclid(p as i32) } else { v };
+        self.gradient_index(wrap(i, period[0]), wrap(j, period[1]), wrap(k, period[2]))
     }
 
-    let t2 = 0.6 - x2 * x2 - y2 * y2 - z2 * z2;
-    if t2 > 0.0 {
-        let t2 = t2 * t2;
-        n += t2 * t2 * grad(gi2, x2, y2, z2);
+    /// Periodic 3D gradient (Perlin) noise, output roughly [-1, 1]
+    /// Wraps seamlessly every `period` lattice units on each axis with a non-zero period,
+    /// e.g. longitude in an equirectangular bake. Periods are whole cells: a lattice can
+    /// only repeat after an integer number of them
+    pub fn periodic_noise(&self, p: Vector3, period: [u32; 3]) -> f32 {
+        let i = p.x.floor();
+        let j = p.y.floor();
+        let k = p.z.floor();
+        let (x, y, z) = (p.x - i, p.y - j, p.z - k);
+        let (i, j, k) = (i as i32, j as i32, k as i32);
+
+        // Quintic fade curves
+        let fade = |t: f32| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+
+        let corner = |di: i32, dj: i32, dk: i32| {
+            let gi = self.periodic_gradient_index(i + di, j + dj, k + dk, period);
+            grad(gi, x - di as f32, y - dj as f32, z - dk as f32)
+        };
+
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        let x00 = lerp(corner(0, 0, 0), corner(1, 0, 0), u);
+        let x10 = lerp(corner(0, 1, 0), corner(1, 1, 0), u);
+        let x01 = lerp(corner(0, 0, 1), corner(1, 0, 1), u);
+        let x11 = lerp(corner(0, 1, 1), corner(1, 1, 1), u);
+
+        lerp(lerp(x00, x10, v), lerp(x01, x11, v), w)
     }
 
-    let t3 = 0.6 - x3 * x3 - y3 * y3 - z3 * z3;
-    if t3 > 0.0 {
-        let t3 = t3 * t3;
-        n += t3 * t3 * grad(gi3, x3, y3, z3);
+    /// Periodic fBm - each octave keeps tiling because the period scales with frequency
+    /// The lacunarity is a whole number so every octave's period stays a whole number of
+    /// cells and wraps at the same place as the first
+    pub fn fbm_periodic(&self, p: Vector3, period: [u32; 3], octaves: i32, lacunarity: u32, gain: f32) -> f32 {
+        let octaves = octaves.max(1);
+        let mut value = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1u32;
+
+        for _ in 0..octaves {
+            value += amplitude * self.periodic_noise(p * frequency as f32, period.map(|axis| axis * frequency));
+            frequency *= lacunarity;
+            amplitude *= gain;
+        }
+
+        value / octave_weight(octaves, gain)
     }
 
-    32.0 * n
-}
+    /// Fractal Brownian Motion (fBm) using simplex noise
+    pub fn fbm_simplex(&self, p: Vector3, octaves: i32, lacunarity: f32, gain: f32) -> f32 {
+        let mut value = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+
+        for _ in 0..octaves {
+            value += amplitude * self.simplex_noise(Vector3::new(
+                p.x * frequency,
+                p.y * frequency,
+                p.z * frequency,
+            ));
+            frequency *= lacunarity;
+            amplitude *= gain;
+        }
 
-/// Fractal Brownian Motion (fBm) using simplex noise
-pub fn fbm_simplex(p: Vector3, octaves: i32, lacunarity: f32, gain: f32) -> f32 {
-    let mut value = 0.0;
-    let mut amplitude = 1.0;
-    let mut frequency = 1.0;
+        value
+    }
 
-    for _ in 0..octaves {
-        value += amplitude * simplex_noise(Vector3::new(
-            p.x * frequency,
-            p.y * frequency,
-            p.z * frequency,
-        ));
-        frequency *= lacunarity;
-        amplitude *= gain;
+    /// Fractal Brownian Motion (fBm) - layered noise for detail, in [-1, 1] for any gain
+    #[inline]
+    pub fn fbm(&self, p: Vector3, octaves: i32, lacunarity: f32, gain: f32) -> f32 {
+        // force a small number of octaves (max 4)
+        let oct = octaves.clamp(1, 4);
+        let mut value = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+
+        // Use simplex_noise but with fewer octaves
+        for _ in 0..oct {
+            value += amplitude * self.simplex_noise(Vector3::new(p.x * frequency, p.y * frequency, p.z * frequency));
+            frequency *= lacunarity;
+            amplitude *= gain;
+        }
+
+        value / octave_weight(oct, gain)
     }
 
-    value
-}
+    /// `fbm` with its analytic gradient (chain rule: each octave's gradient
+    /// is scaled by its amplitude and frequency)
+    pub fn fbm_with_derivative(&self, p: Vector3, octaves: i32, lacunarity: f32, gain: f32) -> NoiseSample {
+        let oct = octaves.clamp(1, 4);
+        let mut value = 0.0;
+        let mut gradient = Vector3::zero();
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+
+        for _ in 0..oct {
+            let octave = self.simplex_noise_with_derivative(p * frequency);
+            value += amplitude * octave.value;
+            gradient += octave.gradient * (amplitude * frequency);
+            frequency *= lacunarity;
+            amplitude *= gain;
+        }
 
-/// Fractal Brownian Motion (fBm) - layered noise for detail
-#[inline]
-pub fn fbm(p: Vector3, octaves: i32, lacunarity: f32, gain: f32) -> f32 {
-    // force a small number of octaves (max 4)
-    let oct = octaves.min(4).max(1);
-    let mut value = 0.0;
-    let mut amplitude = 1.0;
-    let mut frequency = 1.0;
+        let weight = octave_weight(oct, gain);
+        NoiseSample { value: value / weight, gradient: gradient / weight }
+    }
 
-    // Use simplex_noise but with fewer octaves
-    for _ in 0..oct {
-        value += amplitude * simplex_noise(Vector3::new(p.x * frequency, p.y * frequency, p.z * frequency));
-        frequency *= lacunarity;
-        amplitude *= gain;
+    /// 4D fBm - animated version of `fbm`, with `w` usually driven by time
+    pub fn fbm_4d(&self, p: Vector3, w: f32, octaves: i32, lacunarity: f32, gain: f32) -> f32 {
+        let oct = octaves.clamp(1, 4);
+        let mut value = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+
+        for _ in 0..oct {
+            value += amplitude * self.simplex_noise_4d(
+                Vector3::new(p.x * frequency, p.y * frequency, p.z * frequency),
+                w * frequency,
+            );
+            frequency *= lacunarity;
+            amplitude *= gain;
+        }
+
+        value / octave_weight(oct, gain)
     }
 
-    // small normalization (keeps values in expected range)
-    value * 0.5
-}
+    /// Turbulence - absolute values create sharp features
+    #[inline]
+    pub fn turbulence(&self, p: Vector3, octaves: i32) -> f32 {
+        self.fbm(p, octaves.clamp(1, 4), 2.0, 0.5).abs()
+    }
 
-/// Turbulence - absolute values create sharp features
-#[inline]
-pub fn turbulence(p: Vector3, octaves: i32) -> f32 {
-    fbm(p, octaves.min(4).max(1), 2.0, 0.5).abs()
-}
+    /// 4D turbulence - animated version of `turbulence`
+    #[inline]
+    pub fn turbulence_4d(&self, p: Vector3, w: f32, octaves: i32) -> f32 {
+        self.fbm_4d(p, w, octaves, 2.0, 0.5).abs()
+    }
 
-/// Voronoi/cellular noise - creates cell-like patterns
-#[inline]
-pub fn voronoi(p: Vector3, scale: f32) -> f32 {
-    // Sample a few pseudo-random points using hash at cell and three neighbors
-    let scaled = Vector3::new(p.x * scale, p.y * scale, p.z * scale);
-    let cell = Vector3::new(scaled.x.floor(), scaled.y.floor(), scaled.z.floor());
+    /// Jittered feature point of the Worley cell whose lowest corner is `cell`
+    #[inline]
+    pub(crate) fn feature_point(&self, cell: Vector3) -> Vector3 {
+        Vector3::new(
+            cell.x + self.hash_channel(cell, 0),
+            cell.y + self.hash_channel(cell, 1),
+            cell.z + self.hash_channel(cell, 2),
+        )
+    }
 
-    let center = Vector3::new(cell.x + hash(cell), cell.y + hash(Vector3::new(cell.y, cell.x, cell.z)), cell.z + hash(Vector3::new(cell.z, cell.y, cell.x)));
+    /// Worley (cellular) noise over the full 3x3x3 cell neighborhood
+    /// Each cell holds one jittered feature point; returns the two nearest distances.
+    /// The block almost always holds both, and the cells one further out are visited
+    /// when they could be nearer than F2, so neither distance jumps at cell borders
+    pub fn worley(&self, p: Vector3, scale: f32) -> Worley {
+        let scaled = Vector3::new(p.x * scale, p.y * scale, p.z * scale);
+        let cell = Vector3::new(scaled.x.floor(), scaled.y.floor(), scaled.z.floor());
+
+        let mut f1 = f32::MAX;
+        let mut f2 = f32::MAX;
+        for reach in [1i32, 2] {
+            for dz in -reach..=reach {
+                for dy in -reach..=reach {
+                    for dx in -reach..=reach {
+                        let neighbor = Vector3::new(
+                            cell.x + dx as f32,
+                            cell.y + dy as f32,
+                            cell.z + dz as f32,
+                        );
+                        // Second pass: only the outer shell, and only cells that can beat F2
+                        let shell = dx.abs().max(dy.abs()).max(dz.abs()) == 2;
+                        if reach == 2 && (!shell || cell_distance_squared(scaled, neighbor) >= f2) {
+                            continue;
+                        }
+                        let feature = self.feature_point(neighbor);
+
+                        let ox = scaled.x - feature.x;
+                        let oy = scaled.y - feature.y;
+                        let oz = scaled.z - feature.z;
+                        let d = ox * ox + oy * oy + oz * oz;
+
+                        if d < f1 {
+                            f2 = f1;
+                            f1 = d;
+                        } else if d < f2 {
+                            f2 = d;
+                        }
+                    }
+                }
+            }
+        }
 
-    let sample1 = Vector3::new(cell.x + 0.6 + hash(Vector3::new(cell.x+1.0, cell.y, cell.z)), cell.y + 0.2 + hash(Vector3::new(cell.y+1.0, cell.x, cell.z)), cell.z + 0.8 + hash(Vector3::new(cell.z+1.0, cell.y, cell.x)));
-    let sample2 = Vector3::new(cell.x - 0.4 + hash(Vector3::new(cell.x-1.0, cell.y, cell.z)), cell.y + 0.3 + hash(Vector3::new(cell.y-1.0, cell.x, cell.z)), cell.z - 0.6 + hash(Vector3::new(cell.z-1.0, cell.y, cell.x)));
+        Worley { f1: f1.sqrt(), f2: f2.sqrt() }
+    }
 
-    let dx = scaled.x - center.x;
-    let dy = scaled.y - center.y;
-    let dz = scaled.z - center.z;
-    let d0 = (dx*dx + dy*dy + dz*dz).sqrt();
+    /// Voronoi/cellular noise - distance to the nearest feature point (Worley F1)
+    #[inline]
+    pub fn voronoi(&self, p: Vector3, scale: f32) -> f32 {
+        self.worley(p, scale).f1
+    }
 
-    let dx1 = scaled.x - sample1.x;
-    let dy1 = scaled.y - sample1.y;
-    let dz1 = scaled.z - sample1.z;
-    let d1 = (dx1*dx1 + dy1*dy1 + dz1*dz1).sqrt();
+    /// Ridged noise - inverted absolute noise for mountain ridges
+    pub fn ridged_noise(&self, p: Vector3, octaves: i32) -> f32 {
+        let mut value = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+
+        for _ in 0..octaves {
+            let n = self.simplex_noise(Vector3::new(
+                p.x * frequency,
+                p.y * frequency,
+                p.z * frequency,
+            ));
+            value += (1.0 - n.abs()) * amplitude;
+            frequency *= 2.0;
+            amplitude *= 0.5;
+        }
 
-    let dx2 = scaled.x - sample2.x;
-    let dy2 = scaled.y - sample2.y;
-    let dz2 = scaled.z - sample2.z;
-    let d2 = (dx2*dx2 + dy2*dy2 + dz2*dz2).sqrt();
+        value
+    }
 
-    // Return min dist like a voronoi distance but cheaper
-    d0.min(d1.min(d2))
-}
+    /// Shared domain-warp loop: each pass displaces `p` by three decorrelated
+    /// samples of `field` taken at the previously warped point
+    #[inline]
+    fn warp_with(p: Vector3, strength: f32, iterations: u32, field: impl Fn(Vector3) -> f32) -> Vector3 {
+        // Constant offsets give every axis its own warp field
+        const AXIS_OFFSETS: [Vector3; 3] = [
+            Vector3 { x: 0.0, y: 0.0, z: 0.0 },
+            Vector3 { x: 5.2, y: 1.3, z: -2.8 },
+            Vector3 { x: -7.4, y: 3.9, z: 6.1 },
+        ];
+
+        let mut warped = p;
+        for _ in 0..iterations {
+            let offset = Vector3::new(
+                field(warped + AXIS_OFFSETS[0]),
+                field(warped + AXIS_OFFSETS[1]),
+                field(warped + AXIS_OFFSETS[2]),
+            );
+            warped = p + offset * strength;
+        }
+        warped
+    }
 
-/// Ridged noise - inverted absolute noise for mountain ridges
-pub fn ridged_noise(p: Vector3, octaves: i32) -> f32 {
-    let mut value = 0.0;
-    let mut amplitude = 1.0;
-    let mut frequency = 1.0;
+    /// Domain warp - returns `p` displaced by fBm warp fields
+    /// `iterations` = 1 is a plain warp, 2 warps the warp (second order), and so on
+    pub fn domain_warp(&self, p: Vector3, octaves: i32, strength: f32, iterations: u32) -> Vector3 {
+        Self::warp_with(p, strength, iterations, |q| self.fbm(q, octaves, 2.0, 0.5))
+    }
 
-    for _ in 0..octaves {
-        let n = simplex_noise(Vector3::new(
-            p.x * frequency,
-            p.y * frequency,
-            p.z * frequency,
-        ));
-        value += (1.0 - n.abs()) * amplitude;
-        frequency *= 2.0;
-        amplitude *= 0.5;
+    /// 4D domain warp - animated version of `domain_warp`, with `w` usually driven by time
+    pub fn domain_warp_4d(&self, p: Vector3, w: f32, octaves: i32, strength: f32, iterations: u32) -> Vector3 {
+        Self::warp_with(p, strength, iterations, |q| self.fbm_4d(q, w, octaves, 2.0, 0.5))
     }
 
-    value
-}
+    /// Warp/domain distortion - creates swirling patterns
+    #[inline]
+    pub fn warp_noise(&self, p: Vector3, amount: f32) -> f32 {
+        // use simplex_noise once on warped point (not multiple octaves)
+        self.simplex_noise(self.domain_warp(p, 3, amount, 1)) * 0.7
+    }
 
-/// Warp/domain distortion - creates swirling patterns
-#[inline]
-pub fn warp_noise(p: Vector3, amount: f32) -> f32 {
-    let offset = Vector3::new(
-        fbm(p, 3, 2.0, 0.5),
-        fbm(Vector3::new(p.y, p.z, p.x), 3, 2.0, 0.5),
-        fbm(Vector3::new(p.z, p.x, p.y), 3, 2.0, 0.5),
-    );
-
-    let warped = Vector3::new(
-        p.x + offset.x * amount,
-        p.y + offset.y * amount,
-        p.z + offset.z * amount,
-    );
-
-    // use simplex_noise once on warped point (not multiple octaves)
-    simplex_noise(warped) * 0.7
+    /// 4D warp noise - animated version of `warp_noise`
+    #[inline]
+    pub fn warp_noise_4d(&self, p: Vector3, w: f32, amount: f32) -> f32 {
+        self.simplex_noise_4d(self.domain_warp_4d(p, w, 3, amount, 1), w) * 0.7
+    }
+
+    /// Curl noise - curl of a 3-component noise potential, animated by `w`
+    /// The resulting velocity field is divergence-free, so advecting by it
+    /// produces swirls without sources or sinks
+    pub fn curl_noise(&self, p: Vector3, w: f32) -> Vector3 {
+        const EPS: f32 = 1e-3;
+
+        // Three decorrelated potential components
+        let potential = |q: Vector3| {
+            Vector3::new(
+                self.simplex_noise_4d(q, w),
+                self.simplex_noise_4d(Vector3::new(q.x + 31.4, q.y - 17.9, q.z + 5.3), w),
+                self.simplex_noise_4d(Vector3::new(q.x - 11.7, q.y + 23.1, q.z - 41.2), w),
+            )
+        };
+
+        // Forward differences of the potential along each axis
+        let base = potential(p);
+        let dx = (potential(Vector3::new(p.x + EPS, p.y, p.z)) - base) / EPS;
+        let dy = (potential(Vector3::new(p.x, p.y + EPS, p.z)) - base) / EPS;
+        let dz = (potential(Vector3::new(p.x, p.y, p.z + EPS)) - base) / EPS;
+
+        Vector3::new(
+            dy.z - dz.y,
+            dz.x - dx.z,
+            dx.y - dy.x,
+        )
+    }
 }
\ No newline at end of file
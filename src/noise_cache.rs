@@ -0,0 +1,234 @@
+// noise_cache.rs
+// Per-object baked noise textures (equirectangular maps or small 3D volumes) to avoid
+// per-fragment fBm
+
+use raylib::prelude::*;
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use crate::noise::Noise;
+use crate::material::{Material, MaterialKey};
+use crate::shader_system::BakedLayer;
+
+/// How a baked layer is laid out
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BakeGrid {
+    /// Latitude/longitude map of the sphere's surface, sampled along the direction of a
+    /// point (bilinear); exact only on the sphere
+    Surface { width: usize, height: usize },
+    /// `size`³ samples filling the cube around the sphere, sampled at the point itself
+    /// (trilinear); coarser for the same memory but valid off the surface too
+    Volume { size: usize },
+}
+
+impl Default for BakeGrid {
+    fn default() -> Self {
+        BakeGrid::Surface { width: 256, height: 128 }
+    }
+}
+
+/// Scalar noise layer baked over one body
+pub struct BakedNoise {
+    grid: BakeGrid,
+    /// Half the side of the volume's cube
+    radius: f32,
+    data: Vec<f32>,
+}
+
+/// Volume cube half-size per unit of sphere radius, so relief just off the surface still
+/// falls inside it
+const VOLUME_MARGIN: f32 = 1.1;
+
+impl BakedNoise {
+    /// Evaluates `layer` once per texel on (or around) a sphere of the given radius
+    pub fn bake(grid: BakeGrid, radius: f32, noise: &Noise, layer: &BakedLayer, time: f32) -> Self {
+        let data = match grid {
+            BakeGrid::Surface { width, height } => {
+                let mut data = Vec::with_capacity(width * height);
+                for j in 0..height {
+                    let theta = (j as f32 + 0.5) / height as f32 * PI;
+                    for i in 0..width {
+                        let lon = (i as f32 + 0.5) / width as f32 * 2.0 * PI - PI;
+                        let direction = Vector3::new(
+                            lon.cos() * theta.sin(),
+                            theta.cos(),
+                            lon.sin() * theta.sin(),
+                        );
+                        data.push(layer.noise.sample(noise, direction * radius, time));
+                    }
+                }
+                data
+            }
+            BakeGrid::Volume { size } => {
+                let half = radius * VOLUME_MARGIN;
+                let coordinate = |i: usize| (i as f32 / (size - 1).max(1) as f32 * 2.0 - 1.0) * half;
+                let mut data = Vec::with_capacity(size * size * size);
+                for k in 0..size {
+                    for j in 0..size {
+                        for i in 0..size {
+                            let point = Vector3::new(coordinate(i), coordinate(j), coordinate(k));
+                            data.push(layer.noise.sample(noise, point, time));
+                        }
+                    }
+                }
+                data
+            }
+        };
+
+        BakedNoise { grid, radius: radius * VOLUME_MARGIN, data }
+    }
+
+    /// Samples the layer at a model-space point
+    pub fn sample(&self, p: Vector3) -> f32 {
+        match self.grid {
+            BakeGrid::Surface { width, height } => self.sample_surface(p, width, height),
+            BakeGrid::Volume { size } => self.sample_volume(p, size),
+        }
+    }
+
+    /// Bilinear lookup in the direction of `p`; longitude wraps around, latitude is
+    /// clamped at the poles
+    fn sample_surface(&self, p: Vector3, width: usize, height: usize) -> f32 {
+        let length = p.length();
+        if length <= 0.0 {
+            return self.data[0];
+        }
+
+        let u = (p.z.atan2(p.x) / (2.0 * PI) + 0.5) * width as f32 - 0.5;
+        let v = (p.y / length).clamp(-1.0, 1.0).acos() / PI * height as f32 - 0.5;
+
+        let x0 = u.floor();
+        let y0 = v.floor();
+        let tx = u - x0;
+        let ty = v - y0;
+
+        let wrap_x = |x: f32| (x as i32).rem_euclid(width as i32) as usize;
+        let clamp_y = |y: f32| (y as i32).clamp(0, height as i32 - 1) as usize;
+        let (xa, xb) = (wrap_x(x0), wrap_x(x0 + 1.0));
+        let (ya, yb) = (clamp_y(y0), clamp_y(y0 + 1.0));
+
+        let texel = |x: usize, y: usize| self.data[y * width + x];
+        let top = texel(xa, ya) * (1.0 - tx) + texel(xb, ya) * tx;
+        let bottom = texel(xa, yb) * (1.0 - tx) + texel(xb, yb) * tx;
+        top * (1.0 - ty) + bottom * ty
+    }
+
+    /// Trilinear lookup at `p`, clamped to the cube
+    fn sample_volume(&self, p: Vector3, size: usize) -> f32 {
+        let last = (size - 1) as f32;
+        let grid = |c: f32| ((c / self.radius * 0.5 + 0.5) * last).clamp(0.0, last);
+        let (u, v, w) = (grid(p.x), grid(p.y), grid(p.z));
+        let (x0, y0, z0) = (u.floor() as usize, v.floor() as usize, w.floor() as usize);
+        let (x1, y1, z1) = ((x0 + 1).min(size - 1), (y0 + 1).min(size - 1), (z0 + 1).min(size - 1));
+        let (tx, ty, tz) = (u - x0 as f32, v - y0 as f32, w - z0 as f32);
+
+        let texel = |x: usize, y: usize, z: usize| self.data[(z * size + y) * size + x];
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        let plane = |z: usize| {
+            lerp(
+                lerp(texel(x0, y0, z), texel(x1, y0, z), tx),
+                lerp(texel(x0, y1, z), texel(x1, y1, z), tx),
+                ty,
+            )
+        };
+        lerp(plane(z0), plane(z1), tz)
+    }
+}
+
+/// Identifies one baked layer: the object's noise seed, its material and a coarse time
+/// step
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+struct BakeKey {
+    seed: u32,
+    material: MaterialKey,
+    time_bucket: i64,
+}
+
+/// Cache of baked noise layers, rebuilt lazily when an animated layer's time step changes
+pub struct NoiseCache {
+    maps: HashMap<BakeKey, BakedNoise>,
+    grid: BakeGrid,
+    radius: f32,
+    pub enabled: bool,
+}
+
+impl NoiseCache {
+    /// Creates an empty cache baking surface maps of the given resolution on spheres of
+    /// `radius`
+    pub fn new(width: usize, height: usize, radius: f32) -> Self {
+        NoiseCache::with_grid(BakeGrid::Surface { width, height }, radius)
+    }
+
+    /// Creates an empty cache baking every layer with `grid` on spheres of `radius`
+    pub fn with_grid(grid: BakeGrid, radius: f32) -> Self {
+        NoiseCache {
+            maps: HashMap::new(),
+            grid,
+            radius,
+            enabled: true,
+        }
+    }
+
+    pub fn grid(&self) -> BakeGrid {
+        self.grid
+    }
+
+    /// Bakes layers with another grid from now on, dropping the ones already baked
+    pub fn set_grid(&mut self, grid: BakeGrid) {
+        if grid != self.grid {
+            self.grid = grid;
+            self.maps.clear();
+        }
+    }
+
+    /// Cache key and bake time of a body's layer at `time`
+    /// None when caching is disabled or the material has no bakeable layer
+    fn key(&self, noise: &Noise, material: &Material, time: f32) -> Option<(BakeKey, BakedLayer, f32)> {
+        if !self.enabled {
+            return None;
+        }
+        let layer = material.baked_layer()?;
+
+        // Static layers always use bucket 0, animated ones re-bake every `refresh` seconds
+        let (time_bucket, bake_time) = match layer.refresh {
+            Some(step) => {
+                let bucket = (time / step).floor();
+                (bucket as i64, bucket * step)
+            }
+            None => (0, 0.0),
+        };
+        Some((BakeKey { seed: noise.seed(), material: material.key(), time_bucket }, layer, bake_time))
+    }
+
+    /// Bakes a body's layer for `time` unless it is already cached
+    pub fn prepare(&mut self, noise: &Noise, material: &Material, time: f32) {
+        let Some((key, layer, bake_time)) = self.key(noise, material, time) else {
+            return;
+        };
+
+        if !self.maps.contains_key(&key) {
+            // Drop stale time steps of the same layer
+            self.maps.retain(|k, _| k.seed != key.seed || k.material != key.material);
+            log::debug!("baking {:?} layer for seed {} at t={bake_time}", material.shader, key.seed);
+            let baked = BakedNoise::bake(self.grid, self.radius, noise, &layer, bake_time);
+            self.maps.insert(key, baked);
+        }
+    }
+
+    /// Baked layer of a body if `prepare` already baked it
+    pub fn lookup(&self, noise: &Noise, material: &Material, time: f32) -> Option<&BakedNoise> {
+        let (key, _, _) = self.key(noise, material, time)?;
+        self.maps.get(&key)
+    }
+
+    /// Returns the baked layer for a body, baking it on first use
+    /// None when caching is disabled or the material has no bakeable layer
+    pub fn get(&mut self, noise: &Noise, material: &Material, time: f32) -> Option<&BakedNoise> {
+        self.prepare(noise, material, time);
+        self.lookup(noise, material, time)
+    }
+
+    /// Drops every baked layer (e.g. when the scene changes)
+    pub fn clear(&mut self) {
+        self.maps.clear();
+    }
+}
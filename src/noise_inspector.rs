@@ -0,0 +1,171 @@
+// noise_inspector.rs
+// Debug view that draws a noise function as a 2D slice straight to the framebuffer
+
+use raylib::prelude::*;
+use crate::input::{Action, InputFrame};
+use crate::framebuffer::Framebuffer;
+use crate::noise::Noise;
+
+/// Noise functions available in the inspector
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum NoiseFunction {
+    Value,
+    Simplex,
+    Simplex4D,
+    Periodic,
+    Fbm,
+    FbmSimplex,
+    Turbulence,
+    Ridged,
+    Warp,
+    DomainWarp2,
+    WorleyF1,
+    WorleyEdge,
+    Curl,
+}
+
+impl NoiseFunction {
+    const ALL: [NoiseFunction; 13] = [
+        NoiseFunction::Value,
+        NoiseFunction::Simplex,
+        NoiseFunction::Simplex4D,
+        NoiseFunction::Periodic,
+        NoiseFunction::Fbm,
+        NoiseFunction::FbmSimplex,
+        NoiseFunction::Turbulence,
+        NoiseFunction::Ridged,
+        NoiseFunction::Warp,
+        NoiseFunction::DomainWarp2,
+        NoiseFunction::WorleyF1,
+        NoiseFunction::WorleyEdge,
+        NoiseFunction::Curl,
+    ];
+
+    /// Next (or previous) function in the list, wrapping around
+    fn cycle(self, step: i32) -> Self {
+        let index = Self::ALL.iter().position(|&f| f == self).unwrap_or(0) as i32;
+        let count = Self::ALL.len() as i32;
+        Self::ALL[(index + step).rem_euclid(count) as usize]
+    }
+
+    /// Evaluates the function and maps it to [0, 1] for display
+    fn evaluate(self, noise: &Noise, p: Vector3, octaves: i32, time: f32) -> f32 {
+        let signed = |v: f32| v * 0.5 + 0.5;
+        let value = match self {
+            NoiseFunction::Value => noise.value_noise(p),
+            NoiseFunction::Simplex => signed(noise.simplex_noise(p)),
+            NoiseFunction::Simplex4D => signed(noise.simplex_noise_4d(p, time)),
+            // Tiles every 4 units along x and y
+            NoiseFunction::Periodic => signed(noise.fbm_periodic(p, [4, 4, 0], octaves, 2, 0.5)),
+            NoiseFunction::Fbm => signed(noise.fbm(p, octaves, 2.0, 0.5)),
+            NoiseFunction::FbmSimplex => signed(noise.fbm_simplex(p, octaves, 2.0, 0.5) * 0.5),
+            NoiseFunction::Turbulence => noise.turbulence(p, octaves),
+            NoiseFunction::Ridged => noise.ridged_noise(p, octaves) * 0.5,
+            NoiseFunction::Warp => signed(noise.warp_noise(p, 0.8)),
+            NoiseFunction::DomainWarp2 => signed(noise.simplex_noise(noise.domain_warp(p, octaves, 0.8, 2))),
+            NoiseFunction::WorleyF1 => noise.worley(p, 1.0).f1,
+            NoiseFunction::WorleyEdge => noise.worley(p, 1.0).edge() * 2.0,
+            NoiseFunction::Curl => noise.curl_noise(p, time).length() * 0.25,
+        };
+        value.clamp(0.0, 1.0)
+    }
+}
+
+/// Noise inspector state and controls
+pub struct NoiseInspector {
+    pub active: bool,
+    pub function: NoiseFunction,
+    /// Noise units per screen height
+    pub frequency: f32,
+    pub octaves: i32,
+    /// Z coordinate of the displayed slice
+    pub z_slice: f32,
+    /// Size in pixels of each evaluated block (1 = every pixel)
+    pub block_size: i32,
+}
+
+impl Default for NoiseInspector {
+    fn default() -> Self {
+        NoiseInspector::new()
+    }
+}
+
+impl NoiseInspector {
+    /// Creates an inactive inspector showing simplex noise
+    pub fn new() -> Self {
+        NoiseInspector {
+            active: false,
+            function: NoiseFunction::Simplex,
+            frequency: 8.0,
+            octaves: 4,
+            z_slice: 0.0,
+            block_size: 3,
+        }
+    }
+
+    /// Processes the inspector actions, returns true when a setting changed
+    ///
+    /// Actions (default keys):
+    /// - SelectPrevious/SelectNext (Left/Right arrows): Previous/next noise function
+    /// - ValueUp/ValueDown (Up/Down arrows): Increase/decrease frequency
+    /// - OctavesDown/OctavesUp (Z/X): Fewer/more octaves
+    /// - SliceBack/SliceForward (Comma/Period): Move the z-slice
+    pub fn process_input(&mut self, input: &InputFrame) -> bool {
+        let mut changed = true;
+
+        if input.is_pressed(Action::SelectNext) {
+            self.function = self.function.cycle(1);
+        } else if input.is_pressed(Action::SelectPrevious) {
+            self.function = self.function.cycle(-1);
+        } else if input.is_pressed(Action::ValueUp) {
+            self.frequency = (self.frequency * 1.25).min(256.0);
+        } else if input.is_pressed(Action::ValueDown) {
+            self.frequency = (self.frequency / 1.25).max(0.25);
+        } else if input.is_pressed(Action::OctavesUp) {
+            self.octaves = (self.octaves + 1).min(8);
+        } else if input.is_pressed(Action::OctavesDown) {
+            self.octaves = (self.octaves - 1).max(1);
+        } else if input.is_down(Action::SliceForward) {
+            self.z_slice += 0.02;
+        } else if input.is_down(Action::SliceBack) {
+            self.z_slice -= 0.02;
+        } else {
+            changed = false;
+        }
+
+        changed
+    }
+
+    /// One-line summary of the current settings
+    pub fn describe(&self) -> String {
+        format!(
+            "Noise inspector: {:?} | frequency {:.2} | octaves {} | z {:.2}",
+            self.function, self.frequency, self.octaves, self.z_slice
+        )
+    }
+
+    /// Draws the current slice over the whole framebuffer as grayscale
+    pub fn render(&self, framebuffer: &mut Framebuffer, noise: &Noise, time: f32) {
+        let scale = self.frequency / framebuffer.height as f32;
+        let block = self.block_size.max(1);
+
+        for by in (0..framebuffer.height).step_by(block as usize) {
+            for bx in (0..framebuffer.width).step_by(block as usize) {
+                // Sample at the block center
+                let p = Vector3::new(
+                    (bx as f32 + block as f32 * 0.5) * scale,
+                    (by as f32 + block as f32 * 0.5) * scale,
+                    self.z_slice,
+                );
+                let value = self.function.evaluate(noise, p, self.octaves, time);
+                let color = Vector3::new(value, value, value);
+
+                for y in by..(by + block).min(framebuffer.height) {
+                    for x in bx..(bx + block).min(framebuffer.width) {
+                        framebuffer.overlay_point(x, y, color);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,14 +1,101 @@
 // obj.rs
-// OBJ file loader for 3D models
+// OBJ file loader for 3D models, with the colors of their MTL materials
 
+use crate::gltf::{GltfModel, ModelMaterial};
+use crate::mesh;
 use crate::vertex::Vertex;
 use raylib::math::{Vector2, Vector3};
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
 use tobj;
 
-/// Represents a loaded 3D model from an OBJ file
+/// Diffuse color of faces without a material, the MTL default
+const DEFAULT_DIFFUSE: Vector3 = Vector3 { x: 0.8, y: 0.8, z: 0.8 };
+
+/// Why a model could not be loaded
+#[derive(Debug)]
+pub enum ObjError {
+    /// Missing file or invalid OBJ syntax
+    Load(String, tobj::LoadError),
+    /// The file has no triangles
+    Empty(String),
+    /// A face refers to a vertex that does not exist
+    InvalidIndex { path: String, index: u32, vertices: usize },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjError::Load(path, error) => write!(f, "could not load {path}: {error}"),
+            ObjError::Empty(path) => write!(f, "{path} contains no triangles"),
+            ObjError::InvalidIndex { path, index, vertices } => {
+                write!(f, "{path} is corrupt: vertex index {index} out of range ({vertices} vertices)")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+/// Material of an MTL library
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjMaterial {
+    pub name: String,
+    /// `Kd`, also written into the `color` of the vertices that use the material
+    pub diffuse: Vector3,
+    /// `Ke`: light given off whatever the lighting
+    pub emission: Vector3,
+    /// `Ns`, the Phong exponent (0 when not given)
+    pub shininess: f32,
+    /// `map_Kd` image file relative to the MTL file, kept for when textures are drawn
+    pub diffuse_texture: Option<String>,
+}
+
+impl ObjMaterial {
+    fn from_mtl(material: &tobj::Material) -> Self {
+        let color = |rgb: [f32; 3]| Vector3::new(rgb[0], rgb[1], rgb[2]);
+        let emission = material.unknown_param.get("Ke").and_then(|text| {
+            let rgb: Vec<f32> = text.split_whitespace().map(str::parse).collect::<Result<_, _>>().ok()?;
+            (rgb.len() == 3).then(|| color([rgb[0], rgb[1], rgb[2]]))
+        });
+        ObjMaterial {
+            name: material.name.clone(),
+            diffuse: material.diffuse.map_or(DEFAULT_DIFFUSE, color),
+            emission: emission.unwrap_or(Vector3::zero()),
+            shininess: material.shininess.unwrap_or(0.0),
+            diffuse_texture: material.diffuse_texture.clone(),
+        }
+    }
+
+    /// Closest metallic-roughness material: a dielectric whose roughness gives the same
+    /// highlight size as `Ns`
+    fn model_material(&self) -> ModelMaterial {
+        let roughness = if self.shininess > 0.0 { (2.0 / (self.shininess + 2.0)).powf(0.25) } else { 1.0 };
+        ModelMaterial { base_color: self.diffuse, emission: self.emission, metallic: 0.0, roughness }
+    }
+}
+
+/// Object or group of an OBJ file, drawn with one material
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubMesh {
+    pub name: String,
+    /// Index into `Obj::materials`, None without a material
+    pub material: Option<usize>,
+    /// Its triangles' range of `Obj::indices`
+    pub indices: Range<usize>,
+}
+
+/// Represents a loaded 3D model from an OBJ file, or a generated mesh: each vertex once,
+/// and three indices into them per triangle
+#[derive(Clone, Debug)]
 pub struct Obj {
     pub vertices: Vec<Vertex>,
     pub indices: Vec<u32>,
+    /// Materials of the MTL library the file uses, empty without one
+    pub materials: Vec<ObjMaterial>,
+    /// Parts of the model and their materials, empty for generated meshes
+    pub sub_meshes: Vec<SubMesh>,
 }
 
 impl Obj {
@@ -18,16 +105,31 @@ impl Obj {
     /// * `path` - Path to the .obj file
     /// 
     /// # Returns
-    /// Result containing the loaded Obj or a LoadError
-    pub fn load(path: &str) -> Result<Self, tobj::LoadError> {
-        let (models, _materials) = tobj::load_obj(path, &tobj::GPU_LOAD_OPTIONS)?;
+    /// Result containing the loaded Obj or an ObjError
+    pub fn load(path: &str) -> Result<Self, ObjError> {
+        let (models, materials) = tobj::load_obj(path, &tobj::GPU_LOAD_OPTIONS)
+            .map_err(|error| ObjError::Load(path.to_string(), error))?;
+        // A missing or broken library leaves the faces gray
+        let materials: Vec<ObjMaterial> = match materials {
+            Ok(materials) => materials.iter().map(ObjMaterial::from_mtl).collect(),
+            Err(error) => {
+                log::debug!("{path}: no materials ({error})");
+                Vec::new()
+            }
+        };
 
         let mut vertices = Vec::new();
         let mut indices = Vec::new();
+        let mut sub_meshes = Vec::new();
+        let mut missing_normals = false;
 
         for model in models {
             let mesh = &model.mesh;
             let num_vertices = mesh.positions.len() / 3;
+            let base = vertices.len() as u32;
+            let material = mesh.material_id.filter(|&id| id < materials.len());
+            let color = material.map_or(Vector3::zero(), |id| materials[id].diffuse);
+            missing_normals |= mesh.normals.is_empty();
 
             // Process each vertex
             for i in 0..num_vertices {
@@ -38,11 +140,11 @@ impl Obj {
                     mesh.positions[i * 3 + 2]
                 );
 
-                // Extract normal if available
+                // Extract normal if available (flipped like the position)
                 let normal = if !mesh.normals.is_empty() {
                     Vector3::new(
                         mesh.normals[i * 3],
-                        mesh.normals[i * 3 + 1],
+                        -mesh.normals[i * 3 + 1],
                         mesh.normals[i * 3 + 2]
                     )
                 } else {
@@ -59,21 +161,142 @@ impl Obj {
                     Vector2::zero()
                 };
 
-                vertices.push(Vertex::new(position, normal, tex_coords));
+                let mut vertex = Vertex::new(position, normal, tex_coords);
+                vertex.color = color;
+                vertices.push(vertex);
+            }
+
+            // Each object's indices start at its own first vertex
+            let start = indices.len();
+            indices.extend(mesh.indices.iter().map(|&index| base + index));
+            sub_meshes.push(SubMesh { name: model.name, material, indices: start..indices.len() });
+        }
+
+        if indices.len() < 3 {
+            return Err(ObjError::Empty(path.to_string()));
+        }
+        if let Some(&index) = indices.iter().find(|&&i| i as usize >= vertices.len()) {
+            return Err(ObjError::InvalidIndex { path: path.to_string(), index, vertices: vertices.len() });
+        }
+
+        log::debug!("loaded {path}: {} vertices, {} indices, {} materials", vertices.len(), indices.len(), materials.len());
+        let mut obj = Obj { vertices, indices, materials, sub_meshes };
+        // Objects without `vn` lines get smooth normals, the others keep theirs
+        if missing_normals {
+            log::debug!("{path}: computing the missing normals");
+            let normals = obj.smooth_normals();
+            for (vertex, normal) in obj.vertices.iter_mut().zip(normals) {
+                if let Some(normal) = normal.filter(|_| vertex.normal == Vector3::zero()) {
+                    vertex.normal = normal;
+                }
             }
-            
-            indices.extend_from_slice(&mesh.indices);
         }
+        Ok(obj)
+    }
+
+    /// Loads a model, falling back to a procedural sphere when the file is missing
+    /// or corrupt; the error is returned alongside so it can be reported
+    pub fn load_or_sphere(path: &str) -> (Self, Option<ObjError>) {
+        match Obj::load(path) {
+            Ok(obj) => (obj, None),
+            Err(error) => {
+                log::warn!("{error}; using a procedural sphere instead");
+                (mesh::generate_uv_sphere(24, 48), Some(error))
+            }
+        }
+    }
+
+    /// Returns the distance from the origin to the farthest vertex
+    pub fn bounding_radius(&self) -> f32 {
+        self.vertices
+            .iter()
+            .map(|v| v.position.length())
+            .fold(0.0, f32::max)
+    }
 
-        Ok(Obj { vertices, indices })
+    /// Model a body can be drawn with (a scene's `model` key), each sub-mesh colored by
+    /// its material like the parts of a glTF model
+    pub fn to_model(&self) -> GltfModel {
+        let mut mesh = self.clone();
+        let mut parts = Vec::new();
+        for sub_mesh in &self.sub_meshes {
+            let material = match sub_mesh.material {
+                Some(id) => self.materials[id].model_material(),
+                None => ModelMaterial { base_color: DEFAULT_DIFFUSE, metallic: 0.0, ..ModelMaterial::default() },
+            };
+            // Every object of the file has vertices of its own
+            for &index in &self.indices[sub_mesh.indices.clone()] {
+                let vertex = &mut mesh.vertices[index as usize];
+                vertex.color = material.base_color;
+                vertex.emission = material.emission;
+            }
+            parts.push((material, sub_mesh.indices.clone()));
+        }
+        GltfModel::fitted(mesh, parts)
     }
 
-    /// Returns an indexed vertex array suitable for rendering
-    /// Converts indices to actual vertex data
+    /// Replaces the normals with ones computed from the faces. Smooth normals average the
+    /// faces around each position weighted by their angle at it, so vertices split along
+    /// texture seams still agree; flat normals give every triangle corners of its own
+    /// facing straight out of it. Faces are taken to wind clockwise seen from outside,
+    /// like loaded OBJ files and the generated meshes. Vertices no face uses keep theirs
+    pub fn recompute_normals(&mut self, smooth: bool) {
+        if smooth {
+            let normals = self.smooth_normals();
+            for (vertex, normal) in self.vertices.iter_mut().zip(normals) {
+                vertex.normal = normal.unwrap_or(vertex.normal);
+            }
+            return;
+        }
+        let mut vertices = Vec::with_capacity(self.indices.len());
+        for [a, b, c] in self.triangles() {
+            let normal = face_normal(a.position, b.position, c.position).normalized();
+            vertices.extend([a, b, c].map(|corner| Vertex { normal, ..corner.clone() }));
+        }
+        // Index ranges of the sub-meshes stay valid: the triangles keep their order
+        self.indices = (0..vertices.len() as u32).collect();
+        self.vertices = vertices;
+    }
+
+    /// Angle-weighted average of the face normals around the position of each vertex,
+    /// None where no face with an area touches it
+    fn smooth_normals(&self) -> Vec<Option<Vector3>> {
+        let key = |v: &Vertex| [v.position.x, v.position.y, v.position.z].map(f32::to_bits);
+        let mut sums: HashMap<[u32; 3], Vector3> = HashMap::new();
+        for corners in self.triangles() {
+            let [a, b, c] = corners.map(|v| v.position);
+            let normal = face_normal(a, b, c);
+            if normal.length() == 0.0 {
+                continue;
+            }
+            let normal = normal.normalized();
+            for (corner, (p, q, r)) in corners.iter().zip([(a, b, c), (b, c, a), (c, a, b)]) {
+                let angle = (q - p).normalized().dot((r - p).normalized()).clamp(-1.0, 1.0).acos();
+                *sums.entry(key(corner)).or_insert(Vector3::zero()) += normal * angle;
+            }
+        }
+        self.vertices
+            .iter()
+            .map(|v| sums.get(&key(v)).filter(|sum| sum.length() > 0.0).map(|sum| sum.normalized()))
+            .collect()
+    }
+
+    /// Corners of every triangle, in index order
+    pub fn triangles(&self) -> impl Iterator<Item = [&Vertex; 3]> {
+        self.indices.as_chunks::<3>().0.iter().map(|t| t.map(|i| &self.vertices[i as usize]))
+    }
+
+    /// Returns the triangles as a flat vertex list (three vertices per triangle, shared
+    /// ones repeated), for uploads that take no index buffer
     pub fn get_vertex_array(&self) -> Vec<Vertex> {
         self.indices
             .iter()
             .map(|&index| self.vertices[index as usize].clone())
             .collect()
     }
-}
\ No newline at end of file
+}
+
+/// Outward normal of a triangle wound clockwise seen from outside, as long as twice its area
+fn face_normal(a: Vector3, b: Vector3, c: Vector3) -> Vector3 {
+    (c - a).cross(b - a)
+}
@@ -0,0 +1,116 @@
+// panorama.rs
+// 360° equirectangular panoramas: six 90° cube faces rendered from one point and
+// resampled into a 2:1 longitude/latitude image
+
+use raylib::prelude::*;
+use std::f32::consts::PI;
+use crate::matrix::{create_projection_matrix, create_view_matrix};
+use crate::renderer::{ImageBuffer, Renderer, NEAR_PLANE};
+use crate::solar_system::SolarSystem;
+
+/// One face of the cube: looking along `forward` with `up` pointing to the top of the image
+struct Face {
+    forward: Vector3,
+    up: Vector3,
+    right: Vector3,
+    image: ImageBuffer,
+}
+
+/// Directions and up vectors of the six cube faces
+const FACES: [([f32; 3], [f32; 3]); 6] = [
+    ([1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+    ([-1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+    ([0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+    ([0.0, 0.0, -1.0], [0.0, 1.0, 0.0]),
+    ([0.0, 1.0, 0.0], [0.0, 0.0, -1.0]),
+    ([0.0, -1.0, 0.0], [0.0, 0.0, 1.0]),
+];
+
+/// Bilinearly filtered RGB at continuous pixel coordinates, clamped to the image
+fn sample(image: &ImageBuffer, x: f32, y: f32) -> [f32; 3] {
+    let x = (x - 0.5).clamp(0.0, (image.width - 1) as f32);
+    let y = (y - 0.5).clamp(0.0, (image.height - 1) as f32);
+    let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+    let (x1, y1) = ((x0 + 1).min(image.width - 1), (y0 + 1).min(image.height - 1));
+    let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+
+    let mut rgb = [0.0; 3];
+    for (px, py, weight) in [
+        (x0, y0, (1.0 - fx) * (1.0 - fy)),
+        (x1, y0, fx * (1.0 - fy)),
+        (x0, y1, (1.0 - fx) * fy),
+        (x1, y1, fx * fy),
+    ] {
+        let pixel = image.pixel(px, py);
+        for (channel, value) in rgb.iter_mut().zip(pixel) {
+            *channel += value as f32 * weight;
+        }
+    }
+    rgb
+}
+
+/// Renders the scene in every direction around `eye` into a `width` x `width / 2`
+/// equirectangular image; the center of the image faces `heading` (projected on the
+/// horizontal plane), the top row is straight up
+pub fn render_panorama(
+    renderer: &Renderer,
+    scene: &SolarSystem,
+    eye: Vector3,
+    heading: Vector3,
+    time: f32,
+    width: usize,
+) -> ImageBuffer {
+    let width = width.max(4);
+    let height = width / 2;
+
+    // A quarter of the width per face keeps roughly one face pixel per output pixel at the equator
+    let face_size = width.div_ceil(4) as i32;
+    let mut face_renderer = renderer.resized(face_size, face_size);
+    face_renderer.anaglyph = None;
+    face_renderer.projection = create_projection_matrix(PI / 2.0, 1.0, NEAR_PLANE, 100.0);
+
+    let faces: Vec<Face> = FACES
+        .iter()
+        .map(|&(forward, up)| {
+            let forward = Vector3::new(forward[0], forward[1], forward[2]);
+            let up = Vector3::new(up[0], up[1], up[2]);
+            let view = create_view_matrix(eye, eye + forward, up);
+            Face {
+                forward,
+                up,
+                right: forward.cross(up),
+                image: face_renderer.render_view(scene, view, time),
+            }
+        })
+        .collect();
+
+    let start = heading.x.atan2(-heading.z);
+    let mut pixels = Vec::with_capacity(width * height * 4);
+    for y in 0..height {
+        let latitude = PI / 2.0 - (y as f32 + 0.5) / height as f32 * PI;
+        for x in 0..width {
+            let longitude = start + (x as f32 + 0.5) / width as f32 * 2.0 * PI - PI;
+            let direction = Vector3::new(
+                latitude.cos() * longitude.sin(),
+                latitude.sin(),
+                -latitude.cos() * longitude.cos(),
+            );
+
+            // The face whose axis is closest to the direction contains it
+            let face = faces
+                .iter()
+                .max_by(|a, b| direction.dot(a.forward).total_cmp(&direction.dot(b.forward)))
+                .expect("six faces");
+            let depth = direction.dot(face.forward);
+            let u = direction.dot(face.right) / depth;
+            let v = direction.dot(face.up) / depth;
+
+            let size = face_size as f32;
+            let rgb = sample(&face.image, (u + 1.0) * 0.5 * size, (1.0 - v) * 0.5 * size);
+            pixels.extend(rgb.map(|c| c.round().min(255.0) as u8));
+            pixels.push(255);
+        }
+    }
+
+    ImageBuffer { width, height, pixels }
+}
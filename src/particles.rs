@@ -0,0 +1,255 @@
+// particles.rs
+// Particle systems: points that an emitter releases, the CPU moves every frame and that
+// change color and fade over their life, drawn over the framebuffer as depth-tested
+// points or small camera-facing quads (comet tails, impact sparks)
+
+use raylib::prelude::*;
+use crate::fragment::BlendMode;
+use crate::framebuffer::Framebuffer;
+use crate::lod::screen_radius;
+use crate::matrix::project_to_screen;
+
+/// Largest quad half-size in pixels, so particles right in front of the camera don't
+/// cover the screen
+const MAX_QUAD: f32 = 4.0;
+
+/// One emitted point
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Particle {
+    pub position: Vector3,
+    pub velocity: Vector3,
+    /// Seconds since it was emitted; it disappears at `lifetime`
+    pub age: f32,
+    pub lifetime: f32,
+}
+
+impl Particle {
+    /// Fraction of its life already gone, 0..1
+    pub fn life(&self) -> f32 {
+        (self.age / self.lifetime).clamp(0.0, 1.0)
+    }
+
+    /// Opacity left: 1 when emitted, fading to 0 at the end of its life
+    pub fn alpha(&self) -> f32 {
+        1.0 - self.life()
+    }
+}
+
+/// Where and how particles are released
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Emitter {
+    pub position: Vector3,
+    /// Velocity of a new particle, give or take `spread` along every axis
+    pub velocity: Vector3,
+    pub spread: f32,
+    /// Particles per second released by `ParticleSystem::emit`
+    pub rate: f32,
+    /// Seconds each particle lasts
+    pub lifetime: f32,
+    /// Color of a new particle and of one about to disappear, blended over its life
+    pub color_start: Vector3,
+    pub color_end: Vector3,
+    /// World-space half-size of the quads
+    pub size: f32,
+}
+
+impl Default for Emitter {
+    fn default() -> Self {
+        Emitter {
+            position: Vector3::zero(),
+            velocity: Vector3::zero(),
+            spread: 0.0,
+            rate: 0.0,
+            lifetime: 1.0,
+            color_start: Vector3::one(),
+            color_end: Vector3::one(),
+            size: 0.05,
+        }
+    }
+}
+
+/// What each particle is drawn as
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ParticleShape {
+    /// A single pixel
+    #[default]
+    Point,
+    /// A square facing the camera, `Emitter::size` wide in the world
+    Quad {
+        /// Fades toward the edges instead of a hard square
+        soft: bool,
+    },
+}
+
+/// Particles of one emitter
+#[derive(Clone, Debug)]
+pub struct ParticleSystem {
+    pub particles: Vec<Particle>,
+    pub emitter: Emitter,
+    pub shape: ParticleShape,
+    /// Opaque particles write depth; translucent ones fade out with `Particle::alpha`
+    pub blend: BlendMode,
+    /// Oldest particles are dropped past this count
+    pub max_particles: usize,
+    /// How fast particles take on the velocity `update` pushes them to, per second;
+    /// 0 leaves them coasting
+    pub drag: f32,
+    /// Fraction of a particle still owed by the emission rate
+    pending: f32,
+    /// Particles emitted so far, to scatter each one differently but the same every run
+    emitted: u32,
+}
+
+/// Hash of a particle number and a coordinate, spread over -1..1
+fn jitter(particle: u32, axis: u32) -> f32 {
+    let mut h = particle.wrapping_mul(0x9E37_79B9) ^ axis.wrapping_mul(0x85EB_CA6B);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2C1B_3C6D);
+    h ^= h >> 12;
+    (h & 0xFFFF) as f32 / 32767.5 - 1.0
+}
+
+impl ParticleSystem {
+    /// Empty system of additive single-pixel particles
+    pub fn new(emitter: Emitter, max_particles: usize) -> Self {
+        ParticleSystem {
+            particles: Vec::new(),
+            emitter,
+            shape: ParticleShape::Point,
+            blend: BlendMode::Additive,
+            max_particles,
+            drag: 0.0,
+            pending: 0.0,
+            emitted: 0,
+        }
+    }
+
+    /// Draws particles as quads, soft-edged or not
+    pub fn with_shape(mut self, shape: ParticleShape) -> Self {
+        self.shape = shape;
+        self
+    }
+
+    /// Blends particles another way (opaque ones write depth)
+    pub fn with_blend(mut self, blend: BlendMode) -> Self {
+        self.blend = blend;
+        self
+    }
+
+    /// Ages and moves every particle by `delta_time`, bending its velocity toward `wind`
+    /// by `drag`, and drops the ones that lived out their lifetime
+    pub fn update(&mut self, delta_time: f32, wind: Vector3) {
+        let response = 1.0 - (-self.drag * delta_time).exp();
+        for particle in &mut self.particles {
+            particle.age += delta_time;
+            particle.velocity += (wind - particle.velocity) * response;
+            particle.position += particle.velocity * delta_time;
+        }
+        self.particles.retain(|particle| particle.age < particle.lifetime);
+    }
+
+    /// Releases the emitter's `rate` particles per second for the last `delta_time`
+    /// seconds; particles of a long step are spread over it as if they had been emitted
+    /// all along
+    pub fn emit(&mut self, delta_time: f32) {
+        self.pending += self.emitter.rate * delta_time.max(0.0);
+        let count = self.pending.floor();
+        self.pending -= count;
+        // Older ones would already be gone
+        let count = (count as usize).min(self.max_particles);
+        for i in (0..count).rev() {
+            self.spawn(delta_time * (i as f32 + 0.5) / count as f32);
+        }
+        self.cap();
+    }
+
+    /// Releases `count` particles at once, e.g. sparks of an impact
+    pub fn burst(&mut self, count: usize) {
+        for _ in 0..count.min(self.max_particles) {
+            self.spawn(0.0);
+        }
+        self.cap();
+    }
+
+    /// One new particle from the emitter, `age` seconds old
+    fn spawn(&mut self, age: f32) {
+        let age = age.min(self.emitter.lifetime);
+        let id = self.emitted;
+        self.emitted = self.emitted.wrapping_add(1);
+        let scatter = Vector3::new(jitter(id, 0), jitter(id, 1), jitter(id, 2)) * self.emitter.spread;
+        let velocity = self.emitter.velocity + scatter;
+        self.particles.push(Particle {
+            position: self.emitter.position + velocity * age,
+            velocity,
+            age,
+            lifetime: self.emitter.lifetime,
+        });
+    }
+
+    /// Keeps the youngest `max_particles`
+    fn cap(&mut self) {
+        if self.particles.len() > self.max_particles {
+            self.particles.sort_by(|a, b| a.age.total_cmp(&b.age));
+            self.particles.truncate(self.max_particles);
+        }
+    }
+
+    /// Color of a particle at its point of life
+    pub fn color(&self, particle: &Particle) -> Vector3 {
+        self.emitter.color_start.lerp(self.emitter.color_end, particle.life())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+        self.pending = 0.0;
+    }
+}
+
+/// Draws every particle of the system, depth tested against what is already in the
+/// framebuffer so they pass behind bodies; translucent particles fade with age (and
+/// toward the edges of soft quads), farthest first when blended over
+pub fn draw_particles(framebuffer: &mut Framebuffer, system: &ParticleSystem, view: &Matrix, projection: &Matrix, viewport: &Matrix) {
+    let mut projected: Vec<(Vector3, &Particle)> = system.particles
+        .iter()
+        .filter_map(|particle| Some((project_to_screen(particle.position, view, projection, viewport)?, particle)))
+        .collect();
+    if system.blend == BlendMode::Over {
+        projected.sort_by(|a, b| b.0.z.total_cmp(&a.0.z));
+    }
+
+    for (center, particle) in projected {
+        let color = system.color(particle);
+        let mut plot = |x: i32, y: i32, coverage: f32| match system.blend {
+            BlendMode::Opaque => framebuffer.point(x, y, center.z, color),
+            blend => framebuffer.blend(x, y, center.z, color, particle.alpha() * coverage, blend),
+        };
+
+        let soft = match system.shape {
+            ParticleShape::Point => {
+                plot(center.x.floor() as i32, center.y.floor() as i32, 1.0);
+                continue;
+            }
+            ParticleShape::Quad { soft } => soft,
+        };
+        let half = screen_radius(particle.position, system.emitter.size, view, projection, viewport).clamp(0.5, MAX_QUAD);
+        let reach = half.ceil() as i32;
+        let (cx, cy) = (center.x.floor() as i32, center.y.floor() as i32);
+        for y in cy - reach..=cy + reach {
+            for x in cx - reach..=cx + reach {
+                let dx = (x as f32 + 0.5 - center.x).abs();
+                let dy = (y as f32 + 0.5 - center.y).abs();
+                if dx.max(dy) > half + 0.5 {
+                    continue;
+                }
+                let coverage = if soft { 1.0 - (dx * dx + dy * dy).sqrt() / (half + 0.5) } else { 1.0 };
+                if coverage > 0.0 {
+                    plot(x, y, coverage);
+                }
+            }
+        }
+    }
+}
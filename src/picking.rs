@@ -0,0 +1,50 @@
+// picking.rs
+// Mouse picking: the body under a point of the window, found by casting a ray from the
+// camera through it against every body's sphere
+
+use raylib::prelude::*;
+use crate::matrix::camera_position;
+use crate::solar_system::SolarSystem;
+
+/// Index of the nearest body under `position`, in pixels of a `width` x `height` window
+/// showing the scene through `view` and `projection`; rings are left out, since their
+/// bounding sphere would cover the planet they surround
+/// `mesh_radius` is the model-space radius of the body mesh
+pub fn pick_body(
+    scene: &SolarSystem,
+    view: &Matrix,
+    projection: &Matrix,
+    mesh_radius: f32,
+    position: Vector2,
+    width: f32,
+    height: f32,
+) -> Option<usize> {
+    let eye = camera_position(view);
+    let right = Vector3::new(view.m0, view.m4, view.m8);
+    let up = Vector3::new(view.m1, view.m5, view.m9);
+    let back = Vector3::new(view.m2, view.m6, view.m10);
+
+    // Window pixel to NDC, then to a view-space slope through the inverse projection scale
+    let ndc_x = position.x / width * 2.0 - 1.0;
+    let ndc_y = 1.0 - position.y / height * 2.0;
+    let direction = (right * (ndc_x / projection.m0) + up * (ndc_y / projection.m5) - back).normalized();
+
+    scene.objects
+        .iter()
+        .enumerate()
+        .filter(|(_, object)| object.ring.is_none())
+        .filter_map(|(index, object)| {
+            let radius = object.scale * mesh_radius;
+            let offset = eye - object.position;
+            let b = offset.dot(direction);
+            let c = offset.dot(offset) - radius * radius;
+            let discriminant = b * b - c;
+            if discriminant < 0.0 {
+                return None;
+            }
+            let t = -b - discriminant.sqrt();
+            (t > 0.0).then_some((index, t))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(index, _)| index)
+}
@@ -0,0 +1,243 @@
+// post.rs
+// Post-processing: a chain of effects run over the finished frame (color and depth)
+// before it is presented, each of which can be switched on and off at runtime
+
+use raylib::prelude::*;
+use std::sync::Arc;
+use crate::bloom::Bloom;
+use crate::framebuffer::Framebuffer;
+
+/// Names of the built-in effects, in the order the chain runs them
+pub const POST_EFFECT_NAMES: [&str; 5] = ["bloom", "tone_mapping", "chromatic_aberration", "vignette", "film_grain"];
+
+/// A finished frame as effects see it
+pub struct PostFrame<'a> {
+    pub width: usize,
+    pub height: usize,
+    /// RGB in 0..1 per pixel, row-major; values may leave that range between effects and
+    /// are clamped when written back
+    pub colors: Vec<Vector3>,
+    /// Depth per pixel, infinity where no body was drawn
+    pub depth: &'a [f32],
+    /// Animation time in seconds, for effects that change every frame
+    pub time: f32,
+}
+
+impl PostFrame<'_> {
+    /// Color at a pixel, with coordinates clamped to the frame
+    pub fn color(&self, x: usize, y: usize) -> Vector3 {
+        self.colors[y.min(self.height - 1) * self.width + x.min(self.width - 1)]
+    }
+}
+
+/// An image-space effect
+pub trait PostEffect {
+    /// Name used to toggle the effect, unique within a chain
+    fn name(&self) -> &'static str;
+
+    /// Modifies the frame in place
+    fn apply(&self, frame: &mut PostFrame);
+}
+
+/// Filmic tone curve (Narkowicz's ACES fit) after an exposure multiplier: more contrast
+/// and saturation in the midtones, highlights rolled off instead of clipped
+#[derive(Clone, Debug, PartialEq)]
+pub struct ToneMapping {
+    pub exposure: f32,
+}
+
+impl PostEffect for ToneMapping {
+    fn name(&self) -> &'static str {
+        "tone_mapping"
+    }
+
+    fn apply(&self, frame: &mut PostFrame) {
+        let aces = |c: f32| {
+            let c = c * self.exposure;
+            ((c * (2.51 * c + 0.03)) / (c * (2.43 * c + 0.59) + 0.14)).clamp(0.0, 1.0)
+        };
+        for color in &mut frame.colors {
+            *color = Vector3::new(aces(color.x), aces(color.y), aces(color.z));
+        }
+    }
+}
+
+/// Lens fringing: red and blue are sampled slightly outward and inward of green,
+/// growing toward the edges of the frame
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChromaticAberration {
+    /// Channel offset in pixels at the corners
+    pub strength: f32,
+}
+
+impl PostEffect for ChromaticAberration {
+    fn name(&self) -> &'static str {
+        "chromatic_aberration"
+    }
+
+    fn apply(&self, frame: &mut PostFrame) {
+        let source = frame.colors.clone();
+        let (width, height) = (frame.width, frame.height);
+        let center = Vector2::new(width as f32 / 2.0, height as f32 / 2.0);
+        let corner = center.length().max(1.0);
+        let sample = |p: Vector2| {
+            let x = (p.x.round() as isize).clamp(0, width as isize - 1) as usize;
+            let y = (p.y.round() as isize).clamp(0, height as isize - 1) as usize;
+            source[y * width + x]
+        };
+
+        for y in 0..height {
+            for x in 0..width {
+                let p = Vector2::new(x as f32, y as f32);
+                let offset = (p - center) / corner * self.strength;
+                let color = &mut frame.colors[y * width + x];
+                color.x = sample(p + offset).x;
+                color.z = sample(p - offset).z;
+            }
+        }
+    }
+}
+
+/// Darkens the frame toward its corners
+#[derive(Clone, Debug, PartialEq)]
+pub struct Vignette {
+    /// Darkening at the corners, 0..1
+    pub strength: f32,
+    /// Distance from the center (1 = corner) where the darkening starts
+    pub radius: f32,
+}
+
+impl PostEffect for Vignette {
+    fn name(&self) -> &'static str {
+        "vignette"
+    }
+
+    fn apply(&self, frame: &mut PostFrame) {
+        let (width, height) = (frame.width, frame.height);
+        let center = Vector2::new(width as f32 / 2.0, height as f32 / 2.0);
+        let corner = center.length().max(1.0);
+        for y in 0..height {
+            for x in 0..width {
+                let distance = (Vector2::new(x as f32 + 0.5, y as f32 + 0.5) - center).length() / corner;
+                let t = ((distance - self.radius) / (1.0 - self.radius).max(1e-3)).clamp(0.0, 1.0);
+                // Smoothstep keeps the start of the falloff invisible
+                let shade = 1.0 - self.strength * t * t * (3.0 - 2.0 * t);
+                frame.colors[y * width + x] *= shade;
+            }
+        }
+    }
+}
+
+/// Animated monochrome noise, stronger in the midtones as on film
+#[derive(Clone, Debug, PartialEq)]
+pub struct FilmGrain {
+    /// Largest change of a channel, 0..1
+    pub amount: f32,
+}
+
+impl PostEffect for FilmGrain {
+    fn name(&self) -> &'static str {
+        "film_grain"
+    }
+
+    fn apply(&self, frame: &mut PostFrame) {
+        // A new grain pattern 24 times per second
+        let frame_seed = (frame.time * 24.0) as u32;
+        for (i, color) in frame.colors.iter_mut().enumerate() {
+            let mut h = (i as u32).wrapping_mul(0x9E37_79B9) ^ frame_seed.wrapping_mul(0x85EB_CA6B);
+            h ^= h >> 15;
+            h = h.wrapping_mul(0x2C1B_3C6D);
+            h ^= h >> 12;
+            let noise = (h & 0xFFFF) as f32 / 65535.0 - 0.5;
+
+            let luma = 0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z;
+            let midtones = 4.0 * luma.clamp(0.0, 1.0) * (1.0 - luma.clamp(0.0, 1.0));
+            *color += Vector3::one() * (noise * self.amount * (0.3 + 0.7 * midtones));
+        }
+    }
+}
+
+/// Ordered list of effects with an on/off switch each
+#[derive(Clone)]
+pub struct PostChain {
+    effects: Vec<(Arc<dyn PostEffect>, bool)>,
+}
+
+impl Default for PostChain {
+    fn default() -> Self {
+        PostChain::new(Bloom::default())
+    }
+}
+
+impl PostChain {
+    /// The built-in effects (`POST_EFFECT_NAMES`) with default settings, all off
+    pub fn new(bloom: Bloom) -> Self {
+        let effects: [Arc<dyn PostEffect>; 5] = [
+            Arc::new(bloom),
+            Arc::new(ToneMapping { exposure: 1.2 }),
+            Arc::new(ChromaticAberration { strength: 2.0 }),
+            Arc::new(Vignette { strength: 0.6, radius: 0.4 }),
+            Arc::new(FilmGrain { amount: 0.06 }),
+        ];
+        PostChain { effects: effects.into_iter().map(|effect| (effect, false)).collect() }
+    }
+
+    /// Adds an effect at the end of the chain, replacing one with the same name in place
+    pub fn add(&mut self, effect: Arc<dyn PostEffect>, enabled: bool) {
+        match self.effects.iter().position(|(e, _)| e.name() == effect.name()) {
+            Some(index) => self.effects[index] = (effect, enabled),
+            None => self.effects.push((effect, enabled)),
+        }
+    }
+
+    /// Names of the effects, in the order they run
+    pub fn names(&self) -> Vec<&'static str> {
+        self.effects.iter().map(|(effect, _)| effect.name()).collect()
+    }
+
+    /// Switches an effect on or off; false if the chain has no effect with this name
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        match self.effects.iter_mut().find(|(effect, _)| effect.name() == name) {
+            Some((_, on)) => {
+                *on = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether the effect with this name exists and is on
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.effects.iter().any(|(effect, on)| *on && effect.name() == name)
+    }
+
+    /// Runs the enabled effects over the framebuffer in order; untouched if none is on
+    pub fn apply(&self, framebuffer: &mut Framebuffer, time: f32) {
+        if !self.effects.iter().any(|(_, on)| *on) {
+            return;
+        }
+        run_effects(framebuffer, self.effects.iter().filter(|(_, on)| *on).map(|(effect, _)| effect.as_ref()), time);
+    }
+}
+
+/// Runs effects over the framebuffer in order, whether or not they belong to a chain
+pub fn run_effects<'a>(framebuffer: &mut Framebuffer, effects: impl IntoIterator<Item = &'a dyn PostEffect>, time: f32) {
+    let colors = framebuffer
+        .as_rgba_bytes()
+        .chunks_exact(4)
+        .map(|p| Vector3::new(p[0] as f32, p[1] as f32, p[2] as f32) / 255.0)
+        .collect();
+    let mut frame = PostFrame {
+        width: framebuffer.width as usize,
+        height: framebuffer.height as usize,
+        colors,
+        depth: framebuffer.depth_buffer(),
+        time,
+    };
+    for effect in effects {
+        effect.apply(&mut frame);
+    }
+
+    let colors = frame.colors;
+    framebuffer.set_colors(&colors);
+}
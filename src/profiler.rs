@@ -0,0 +1,234 @@
+// profiler.rs
+// Per-stage frame timings with optional CSV and Chrome trace dumps
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::time::{Duration, Instant};
+
+/// Pipeline stages measured every frame
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Stage {
+    VertexShading,
+    Rasterization,
+    FragmentShading,
+    Presentation,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 4] = [
+        Stage::VertexShading,
+        Stage::Rasterization,
+        Stage::FragmentShading,
+        Stage::Presentation,
+    ];
+
+    /// Short label used in the HUD and the dumps
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::VertexShading => "vertex",
+            Stage::Rasterization => "raster",
+            Stage::FragmentShading => "fragment",
+            Stage::Presentation => "present",
+        }
+    }
+}
+
+/// Accumulated time of every stage during one frame
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StageTimings {
+    totals: [Duration; Stage::ALL.len()],
+}
+
+impl StageTimings {
+    /// Adds time spent in a stage
+    #[inline]
+    pub fn add(&mut self, stage: Stage, duration: Duration) {
+        self.totals[stage as usize] += duration;
+    }
+
+    /// Runs `f` and charges its duration to `stage`
+    #[inline]
+    pub fn measure<T>(&mut self, stage: Stage, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.add(stage, start.elapsed());
+        result
+    }
+
+    /// Time spent in a stage
+    pub fn get(&self, stage: Stage) -> Duration {
+        self.totals[stage as usize]
+    }
+
+    /// Time spent in all stages
+    pub fn total(&self) -> Duration {
+        self.totals.iter().sum()
+    }
+
+    /// Adds the time of every stage of `other`
+    pub fn merge(&mut self, other: &StageTimings) {
+        for stage in Stage::ALL {
+            self.add(stage, other.get(stage));
+        }
+    }
+}
+
+/// Frames the rolling averages cover
+pub const AVERAGE_FRAMES: usize = 60;
+
+/// Milliseconds of a duration, for display
+#[inline]
+pub fn millis(duration: Duration) -> f32 {
+    duration.as_secs_f32() * 1000.0
+}
+
+/// Collects finished frames and optionally dumps them to CSV and Chrome trace files
+pub struct Profiler {
+    last: StageTimings,
+    /// The last `AVERAGE_FRAMES` frames, oldest first
+    history: VecDeque<StageTimings>,
+    frame_index: u64,
+    origin: Instant,
+    frame_start: Instant,
+    csv: Option<BufWriter<File>>,
+    trace: Option<BufWriter<File>>,
+    trace_events: usize,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Profiler::new()
+    }
+}
+
+impl Profiler {
+    /// Creates a profiler with no dump files
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Profiler {
+            last: StageTimings::default(),
+            history: VecDeque::with_capacity(AVERAGE_FRAMES),
+            frame_index: 0,
+            origin: now,
+            frame_start: now,
+            csv: None,
+            trace: None,
+            trace_events: 0,
+        }
+    }
+
+    /// Writes one CSV row per frame (times in milliseconds)
+    pub fn write_csv(&mut self, path: &str) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        write!(out, "frame")?;
+        for stage in Stage::ALL {
+            write!(out, ",{}_ms", stage.name())?;
+        }
+        writeln!(out, ",total_ms")?;
+        self.csv = Some(out);
+        Ok(())
+    }
+
+    /// Writes a Chrome trace (chrome://tracing, Perfetto) with one span per stage and frame
+    pub fn write_trace(&mut self, path: &str) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, "[")?;
+        self.trace = Some(out);
+        Ok(())
+    }
+
+    /// Marks the start of a frame
+    pub fn begin_frame(&mut self) {
+        self.frame_start = Instant::now();
+    }
+
+    /// Adds one span per object to the Chrome trace, on a track of its own; call before
+    /// `end_frame` with the frame's per-object timings
+    pub fn record_objects(&mut self, objects: &[(String, StageTimings)]) -> io::Result<()> {
+        let Some(trace) = &mut self.trace else {
+            return Ok(());
+        };
+        let mut offset = self.frame_start.duration_since(self.origin).as_secs_f64() * 1e6;
+        for (name, timings) in objects {
+            let duration = timings.total().as_secs_f64() * 1e6;
+            if self.trace_events > 0 {
+                writeln!(trace, ",")?;
+            }
+            write!(
+                trace,
+                "{{\"name\":\"{}\",\"cat\":\"frame {}\",\"ph\":\"X\",\"ts\":{:.1},\"dur\":{:.1},\"pid\":1,\"tid\":2}}",
+                name.replace(['"', '\\'], ""), self.frame_index, offset, duration
+            )?;
+            self.trace_events += 1;
+            offset += duration;
+        }
+        Ok(())
+    }
+
+    /// Stores the timings of the finished frame and appends them to the dumps
+    pub fn end_frame(&mut self, timings: StageTimings) -> io::Result<()> {
+        self.last = timings;
+        if self.history.len() == AVERAGE_FRAMES {
+            self.history.pop_front();
+        }
+        self.history.push_back(timings);
+
+        if let Some(csv) = &mut self.csv {
+            write!(csv, "{}", self.frame_index)?;
+            for stage in Stage::ALL {
+                write!(csv, ",{:.3}", millis(timings.get(stage)))?;
+            }
+            writeln!(csv, ",{:.3}", millis(timings.total()))?;
+        }
+
+        if let Some(trace) = &mut self.trace {
+            // Stages are laid out back to back from the frame start; their
+            // durations are exact, their offsets only approximate the interleaving
+            let mut offset = self.frame_start.duration_since(self.origin).as_secs_f64() * 1e6;
+            for stage in Stage::ALL {
+                let duration = timings.get(stage).as_secs_f64() * 1e6;
+                if self.trace_events > 0 {
+                    writeln!(trace, ",")?;
+                }
+                write!(
+                    trace,
+                    "{{\"name\":\"{}\",\"cat\":\"frame {}\",\"ph\":\"X\",\"ts\":{:.1},\"dur\":{:.1},\"pid\":1,\"tid\":1}}",
+                    stage.name(), self.frame_index, offset, duration
+                )?;
+                self.trace_events += 1;
+                offset += duration;
+            }
+        }
+
+        self.frame_index += 1;
+        Ok(())
+    }
+
+    /// Timings of the last finished frame
+    pub fn last_frame(&self) -> &StageTimings {
+        &self.last
+    }
+
+    /// Timings averaged over the last `AVERAGE_FRAMES` frames
+    pub fn average(&self) -> StageTimings {
+        let mut average = StageTimings::default();
+        let frames = self.history.len().max(1) as u32;
+        for stage in Stage::ALL {
+            average.add(stage, self.history.iter().map(|timings| timings.get(stage)).sum::<Duration>() / frames);
+        }
+        average
+    }
+
+    /// Flushes and closes the dump files
+    pub fn finish(&mut self) -> io::Result<()> {
+        if let Some(mut csv) = self.csv.take() {
+            csv.flush()?;
+        }
+        if let Some(mut trace) = self.trace.take() {
+            writeln!(trace, "\n]")?;
+            trace.flush()?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,242 @@
+// raytracer.rs
+// Alternative backend: one ray per pixel against the analytic body spheres and ring
+// discs, shaded with the same lights and shaders as the rasterizer. Exact silhouettes
+// make it a reference for the triangle pipeline
+
+use raylib::prelude::*;
+use rayon::prelude::*;
+use crate::backend::{merge, RenderBackend, ViewSetup};
+use crate::debug_view::{normal_color, DebugView};
+use crate::fragment::{BlendMode, Fragment, ShadedFragment};
+use crate::light::main_light_direction;
+use crate::framebuffer::Framebuffer;
+use crate::matrix::{camera_position, project_to_screen};
+use crate::noise::Noise;
+use crate::profiler::Stage;
+use crate::renderer::RenderStats;
+use crate::shader_system::apply_shader;
+use crate::shadow::Occluder;
+use crate::solar_system::{CelestialObject, SolarSystem};
+use crate::triangle::{atmosphere_glow, surface_lighting, Lighting};
+use crate::Uniforms;
+
+/// Rows traced by each job
+const BAND_HEIGHT: usize = 16;
+
+/// A body prepared for tracing: a sphere, or a flat disc with a hole for rings
+struct Body<'a> {
+    object: &'a CelestialObject,
+    /// Sphere radius, unused by rings
+    radius: f32,
+    /// World-space images of the model axes, to map hits back to model space
+    axes: [Vector3; 3],
+    uniforms: Uniforms<'a>,
+    occluders: Vec<Occluder>,
+}
+
+impl Body<'_> {
+    /// Distance along the ray to the front surface, if hit in front of the eye
+    fn intersect(&self, origin: Vector3, direction: Vector3) -> Option<f32> {
+        if let Some(ring) = &self.object.ring {
+            // Ring plane through the center, then the annulus between both radii
+            let normal = self.axes[1];
+            let facing = direction.dot(normal);
+            if facing.abs() < 1e-6 {
+                return None;
+            }
+            let t = (self.object.position - origin).dot(normal) / facing;
+            let distance = (origin + direction * t - self.object.position).length() / self.object.scale;
+            return (t > 0.0 && (ring.inner..=ring.outer).contains(&distance)).then_some(t);
+        }
+
+        let offset = origin - self.object.position;
+        let b = offset.dot(direction);
+        let c = offset.dot(offset) - self.radius * self.radius;
+        let discriminant = b * b - c;
+        if discriminant < 0.0 {
+            return None;
+        }
+        let t = -b - discriminant.sqrt();
+        (t > 0.0).then_some(t)
+    }
+
+    /// World-space surface normal at a point of the body
+    fn normal(&self, point: Vector3) -> Vector3 {
+        match self.object.ring {
+            Some(_) => self.axes[1],
+            None => (point - self.object.position) / self.radius,
+        }
+    }
+
+    /// Point of the body mesh (model space) under a world-space surface point
+    fn model_position(&self, point: Vector3) -> Vector3 {
+        let local = (point - self.object.position) / self.object.scale;
+        Vector3::new(local.dot(self.axes[0]), local.dot(self.axes[1]), local.dot(self.axes[2]))
+    }
+}
+
+/// Camera placement recovered from a view matrix
+struct Eye {
+    position: Vector3,
+    right: Vector3,
+    up: Vector3,
+    back: Vector3,
+}
+
+impl Eye {
+    fn from_view(view: &Matrix) -> Self {
+        let right = Vector3::new(view.m0, view.m4, view.m8);
+        let up = Vector3::new(view.m1, view.m5, view.m9);
+        let back = Vector3::new(view.m2, view.m6, view.m10);
+        Eye { position: camera_position(view), right, up, back }
+    }
+}
+
+/// Ray-traced backend
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RayTracer;
+
+impl RenderBackend for RayTracer {
+    fn name(&self) -> &'static str {
+        "raytracer"
+    }
+
+    fn draw_view(&mut self, setup: &ViewSetup, scene: &SolarSystem, framebuffer: &mut Framebuffer, stats: &mut RenderStats) {
+        let (width, height) = (framebuffer.width as usize, framebuffer.height as usize);
+        let results = trace_scene(setup, scene, width, height);
+        merge(results.iter().map(|(fragments, stats)| (fragments.as_slice(), stats)), framebuffer, stats);
+    }
+}
+
+/// Traces the whole image in bands of rows and returns each band's shaded fragments
+/// with its counters; fragment depths match the rasterizer's so overlays still depth test
+pub fn trace_scene(setup: &ViewSetup, scene: &SolarSystem, width: usize, height: usize) -> Vec<(Vec<ShadedFragment>, RenderStats)> {
+    let eye = Eye::from_view(&setup.view);
+    let bodies: Vec<Body> = scene.objects
+        .iter()
+        .enumerate()
+        .map(|(index, object)| {
+            let model = object.model_matrix();
+            let noise = Noise::new(object.seed);
+            let baked = setup.noise_cache.lookup(&noise, &object.material, setup.time);
+            Body {
+                object,
+                radius: object.scale * setup.mesh_radius,
+                axes: [
+                    Vector3::new(model.m0, model.m1, model.m2) / object.scale,
+                    Vector3::new(model.m4, model.m5, model.m6) / object.scale,
+                    Vector3::new(model.m8, model.m9, model.m10) / object.scale,
+                ],
+                uniforms: Uniforms {
+                    model_matrix: model,
+                    view_matrix: setup.view,
+                    projection_matrix: setup.projection,
+                    viewport_matrix: setup.viewport,
+                    camera_position: eye.position,
+                    light_direction: main_light_direction(setup.lights, object.position),
+                    time: setup.time,
+                    noise,
+                    baked,
+                    eruptions: object.eruptions(),
+                    vortex: object.vortex,
+                },
+                occluders: setup.occluders(scene, index),
+            }
+        })
+        .collect();
+
+    // Inverse of the projection scale: NDC offsets to view-space slopes
+    let (slope_x, slope_y) = (1.0 / setup.projection.m0, 1.0 / setup.projection.m5);
+
+    let trace_band = |band: usize| {
+        let mut stats = RenderStats::default();
+        if band == 0 {
+            stats.objects = bodies.len();
+        }
+        let rows = band * BAND_HEIGHT..((band + 1) * BAND_HEIGHT).min(height);
+
+        // Nearest opaque body under every pixel center, and the translucent ones in front of it
+        let hits: Vec<(usize, usize, usize, f32, Vector3)> = stats.timings.measure(Stage::Rasterization, || {
+            let mut hits = Vec::new();
+            for y in rows {
+                let ndc_y = 1.0 - (y as f32 + 0.5) / height as f32 * 2.0;
+                for x in 0..width {
+                    let ndc_x = (x as f32 + 0.5) / width as f32 * 2.0 - 1.0;
+                    let direction = (eye.right * (ndc_x * slope_x) + eye.up * (ndc_y * slope_y) - eye.back).normalized();
+
+                    let crossed = || {
+                        bodies
+                            .iter()
+                            .enumerate()
+                            .filter_map(|(i, body)| body.intersect(eye.position, direction).map(|t| (i, t)))
+                    };
+                    let translucent = |i: usize| bodies[i].object.blend != BlendMode::Opaque;
+                    let nearest = crossed().filter(|&(i, _)| !translucent(i)).min_by(|a, b| a.1.total_cmp(&b.1));
+                    let limit = nearest.map_or(f32::INFINITY, |(_, t)| t);
+                    for (index, t) in nearest.into_iter().chain(crossed().filter(|&(i, t)| translucent(i) && t < limit)) {
+                        hits.push((x, y, index, t, direction));
+                    }
+                }
+            }
+            hits
+        });
+
+        stats.fragments = hits.len();
+        let fragments = stats.timings.measure(Stage::FragmentShading, || {
+            hits.iter()
+                .filter_map(|&(x, y, index, t, direction)| {
+                    let body = &bodies[index];
+                    let point = eye.position + direction * t;
+                    let normal = body.normal(point);
+                    let depth = project_to_screen(point, &setup.view, &setup.projection, &setup.viewport)?.z;
+
+                    let lighting = Lighting {
+                        lights: setup.lights,
+                        occluders: &body.occluders,
+                        material: body.object.material,
+                        eye: eye.position,
+                        bump: setup.bump(body.object, &body.uniforms),
+                        atmosphere: body.object.atmosphere,
+                        vertex_colors: false,
+                    };
+                    let glow = atmosphere_glow(normal, point, &lighting);
+                    let model_position = body.model_position(point);
+                    let normal = match lighting.bump {
+                        Some(bump) if !lighting.material.emissive => bump.normal(normal, model_position),
+                        _ => normal,
+                    };
+                    let (lit, specular) = surface_lighting(normal, point, &lighting);
+                    let fragment = Fragment::new_with_world_pos(
+                        x as f32 + 0.5,
+                        y as f32 + 0.5,
+                        lit,
+                        specular,
+                        glow,
+                        depth,
+                        model_position,
+                    )
+                    .with_normal(normal);
+                    // No triangles to outline: the wireframe view shades as usual
+                    let color = match setup.debug_view {
+                        DebugView::Normals => normal_color(normal),
+                        _ => {
+                            apply_shader(&fragment, &body.uniforms, &body.object.material)
+                                + fragment.glow
+                                + body.object.aurora_glow(&fragment, &body.uniforms)
+                        }
+                    };
+                    Some(ShadedFragment {
+                        x: x as i32,
+                        y: y as i32,
+                        depth,
+                        color,
+                        alpha: body.object.alpha(&fragment, &body.uniforms),
+                        blend: body.object.blend,
+                    })
+                })
+                .collect()
+        });
+        (fragments, stats)
+    };
+    setup.install(|| (0..height.div_ceil(BAND_HEIGHT)).into_par_iter().map(trace_band).collect())
+}
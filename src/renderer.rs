@@ -0,0 +1,510 @@
+// renderer.rs
+// Window-independent scene renderer: draws a solar system into its own framebuffer
+
+use raylib::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::f32::consts::PI;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+use crate::backend::{Rasterizer, RenderBackend, ViewSetup};
+use crate::camera::Camera;
+use crate::framebuffer::Framebuffer;
+use crate::asteroid_belt::{draw_belts, rock_mesh};
+use crate::corona::draw_coronas;
+use crate::debug_view::DebugView;
+use crate::lod::{detail_levels, inner_radius};
+use crate::particles::draw_particles;
+use crate::matrix::{create_projection_matrix, create_viewport_matrix};
+use crate::noise::Noise;
+use crate::noise_cache::{BakeGrid, NoiseCache};
+use crate::surface_bake::{SurfaceCache, CUBE_SIZE};
+use crate::obj::Obj;
+use crate::fxaa::Fxaa;
+use crate::post::{run_effects, PostChain, PostEffect};
+use crate::profiler::StageTimings;
+use crate::raytracer::RayTracer;
+use crate::solar_system::SolarSystem;
+use crate::starfield::Starfield;
+
+/// Plain RGBA8 copy of a rendered frame, independent of raylib
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageBuffer {
+    pub width: usize,
+    pub height: usize,
+    /// Row-major RGBA bytes, 4 per pixel
+    pub pixels: Vec<u8>,
+}
+
+impl ImageBuffer {
+    /// Copies the color buffer of a framebuffer
+    pub fn from_framebuffer(framebuffer: &Framebuffer) -> Self {
+        ImageBuffer {
+            width: framebuffer.width as usize,
+            height: framebuffer.height as usize,
+            pixels: framebuffer.snapshot(),
+        }
+    }
+
+    /// RGBA value of a pixel
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 4] {
+        let i = (y * self.width + x) * 4;
+        [self.pixels[i], self.pixels[i + 1], self.pixels[i + 2], self.pixels[i + 3]]
+    }
+
+    /// Writes the RGB channels as a binary PPM (P6) file, dropping alpha
+    pub fn write_ppm(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        write!(out, "P6\n{} {}\n255\n", self.width, self.height)?;
+        out.write_all(&self.rgb())?;
+        out.flush()
+    }
+
+    /// Saves the image: `.ppm` directly, any other format raylib can export (png, jpg, ...)
+    /// through a raylib image
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        if path.ends_with(".ppm") {
+            return self.write_ppm(path);
+        }
+
+        let mut image = Image::gen_image_color(self.width as i32, self.height as i32, Color::BLACK);
+        for (i, p) in self.pixels.chunks_exact(4).enumerate() {
+            let (x, y) = ((i % self.width) as i32, (i / self.width) as i32);
+            image.draw_pixel(x, y, Color::new(p[0], p[1], p[2], p[3]));
+        }
+        image.export_image(path);
+        Ok(())
+    }
+
+    /// Reads a binary PPM (P6) file with 8-bit channels; alpha is set to 255
+    pub fn read_ppm(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut input = BufReader::new(File::open(path)?);
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+        // Header: magic, width, height and max value separated by whitespace, `#` comments allowed
+        let mut fields = Vec::new();
+        while fields.len() < 4 {
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                return Err(invalid("truncated PPM header"));
+            }
+            let line = line.split('#').next().unwrap_or("");
+            fields.extend(line.split_whitespace().map(str::to_string));
+        }
+        if fields[0] != "P6" || fields[3] != "255" {
+            return Err(invalid("only 8-bit binary PPM (P6) is supported"));
+        }
+        let width: usize = fields[1].parse().map_err(|_| invalid("invalid PPM width"))?;
+        let height: usize = fields[2].parse().map_err(|_| invalid("invalid PPM height"))?;
+
+        let mut rgb = vec![0; width * height * 3];
+        input.read_exact(&mut rgb)?;
+
+        Ok(ImageBuffer {
+            width,
+            height,
+            pixels: rgb.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        })
+    }
+
+    /// RGB bytes of the frame, 3 per pixel
+    pub fn rgb(&self) -> Vec<u8> {
+        self.pixels
+            .chunks_exact(4)
+            .flat_map(|p| [p[0], p[1], p[2]])
+            .collect()
+    }
+}
+
+/// Distance of the near plane of the projection
+pub const NEAR_PLANE: f32 = 0.1;
+
+/// Render scales stepped through at runtime: half resolution for speed, twice it for
+/// supersampling (the frame is filtered down when presented)
+pub const RENDER_SCALE_STEPS: [f32; 3] = [0.5, 1.0, 2.0];
+
+/// Step of `RENDER_SCALE_STEPS` after `scale`, or before it when `down`, wrapping around;
+/// a scale between steps goes to the nearest one in that direction
+pub fn step_render_scale(scale: f32, down: bool) -> f32 {
+    let steps = RENDER_SCALE_STEPS;
+    if down {
+        steps.iter().rev().copied().find(|&step| step < scale).unwrap_or(steps[steps.len() - 1])
+    } else {
+        steps.iter().copied().find(|&step| step > scale).unwrap_or(steps[0])
+    }
+}
+
+/// Work counters of the last drawn frame
+/// With several render threads the stage timings add up the time of every thread
+#[derive(Clone, Debug, Default)]
+pub struct RenderStats {
+    pub objects: usize,
+    /// Objects skipped because they are outside the view or hidden behind another
+    pub culled: usize,
+    pub triangles: usize,
+    pub fragments: usize,
+    /// Time spent in each pipeline stage
+    pub timings: StageTimings,
+    /// Time spent on each object, in scene order; empty unless `Renderer::profile_objects`
+    /// is on and the rasterizer draws
+    pub object_timings: Vec<StageTimings>,
+}
+
+impl RenderStats {
+    /// Charges time to the object at `index`
+    pub fn add_object(&mut self, index: usize, timings: &StageTimings) {
+        if self.object_timings.len() <= index {
+            self.object_timings.resize(index + 1, StageTimings::default());
+        }
+        self.object_timings[index].merge(timings);
+    }
+
+    /// Adds the counters of another job or view
+    pub fn merge(&mut self, other: &RenderStats) {
+        self.objects += other.objects;
+        self.culled += other.culled;
+        self.triangles += other.triangles;
+        self.fragments += other.fragments;
+        self.timings.merge(&other.timings);
+        for (index, timings) in other.object_timings.iter().enumerate() {
+            self.add_object(index, timings);
+        }
+    }
+}
+
+/// Owns everything needed to draw a scene: framebuffer, sphere mesh,
+/// projection/viewport matrices and the baked noise cache
+pub struct Renderer {
+    pub framebuffer: Framebuffer,
+    pub noise_cache: NoiseCache,
+    /// Shader surfaces baked per object, off unless enabled
+    pub surface_cache: SurfaceCache,
+    pub projection: Matrix,
+    pub viewport: Matrix,
+    /// Counters of the last `draw` call
+    pub stats: RenderStats,
+    /// Multiplier on every light's intensity
+    pub light_intensity: f32,
+    /// Whether bodies cast shadows on each other
+    pub shadows: bool,
+    /// Whether rocky, icy and earth-like surfaces are lit along their noise relief
+    pub normal_mapping: bool,
+    /// Whether small bodies are drawn with coarser meshes
+    pub lod: bool,
+    /// Whether asteroids smaller than a pixel or so are drawn as points instead of meshes
+    pub asteroid_sprites: bool,
+    /// Whether stars are surrounded by an animated corona glow
+    pub coronas: bool,
+    /// Diagnostic mode of the software backends; anything but `Shaded` leaves out the
+    /// belts, coronas, particles and image effects
+    pub debug_view: DebugView,
+    /// Sky drawn behind the bodies, None for the flat background color
+    pub starfield: Option<Starfield>,
+    /// Image effects run over every view (bloom, vignette, ...), all off by default
+    pub post: PostChain,
+    /// Edge antialiasing run over every view before the image effects, None for hard edges
+    pub antialiasing: Option<Fxaa>,
+    /// Thread pool that transforms, rasterizes and shades in parallel, None for rayon's
+    /// global pool (one thread per core); see `set_threads`
+    pool: Option<Arc<ThreadPool>>,
+    /// Eye separation of the red/cyan stereo mode, None for a normal image
+    pub anaglyph: Option<f32>,
+    /// Whether the rasterizer times every object into `stats.object_timings`
+    pub profile_objects: bool,
+    /// Available backends; `active` indexes the one that draws
+    backends: Vec<Box<dyn RenderBackend>>,
+    active: usize,
+    mesh: Obj,
+    /// Coarser versions of the body mesh, see `lod::detail_levels`
+    detail_levels: Vec<Obj>,
+    /// Mesh shared by every asteroid, see `asteroid_belt::rock_mesh`
+    rock_mesh: Obj,
+    mesh_radius: f32,
+    /// Radius of the largest sphere inside the body mesh and each detail level
+    inner_radii: Vec<f32>,
+    /// Vertical field of view of `projection` in radians
+    fov: f32,
+}
+
+impl Renderer {
+    /// Creates a renderer of the given size that draws every body with `mesh`
+    pub fn new(width: i32, height: i32, mesh: &Obj) -> Self {
+        let mesh_radius = mesh.bounding_radius();
+        let detail_levels = detail_levels(mesh_radius);
+        let inner_radii = std::iter::once(mesh).chain(&detail_levels).map(inner_radius).collect();
+
+        let mut framebuffer = Framebuffer::new(width, height);
+        framebuffer.set_background_color(Color::new(5, 5, 15, 255));
+
+        Renderer {
+            framebuffer,
+            // Baked noise layers per object (equirectangular, 256x128)
+            noise_cache: NoiseCache::with_grid(BakeGrid::default(), mesh_radius),
+            surface_cache: SurfaceCache::new(CUBE_SIZE, mesh_radius),
+            projection: create_projection_matrix(PI / 3.0, width as f32 / height as f32, NEAR_PLANE, 100.0),
+            viewport: create_viewport_matrix(0.0, 0.0, width as f32, height as f32),
+            stats: RenderStats::default(),
+            light_intensity: 1.0,
+            shadows: true,
+            normal_mapping: true,
+            lod: true,
+            asteroid_sprites: true,
+            coronas: true,
+            debug_view: DebugView::Shaded,
+            starfield: None,
+            post: PostChain::default(),
+            antialiasing: None,
+            pool: None,
+            anaglyph: None,
+            profile_objects: false,
+            backends: vec![Box::new(Rasterizer::default()), Box::new(RayTracer)],
+            active: 0,
+            mesh: mesh.clone(),
+            detail_levels,
+            rock_mesh: rock_mesh(),
+            mesh_radius,
+            inner_radii,
+            fov: PI / 3.0,
+        }
+    }
+
+    /// Renderer of another size with the same mesh and settings (the noise cache starts empty)
+    /// Only the software backends are carried over; a window-bound active backend falls
+    /// back to the rasterizer
+    pub fn resized(&self, width: i32, height: i32) -> Renderer {
+        let mut framebuffer = Framebuffer::new(width, height);
+        framebuffer.set_background_color(self.framebuffer.background_color());
+
+        let mut renderer = Renderer {
+            framebuffer,
+            noise_cache: NoiseCache::with_grid(self.noise_cache.grid(), self.mesh_radius),
+            surface_cache: SurfaceCache::new(CUBE_SIZE, self.mesh_radius),
+            projection: create_projection_matrix(self.fov, width as f32 / height as f32, NEAR_PLANE, 100.0),
+            viewport: create_viewport_matrix(0.0, 0.0, width as f32, height as f32),
+            stats: RenderStats::default(),
+            light_intensity: self.light_intensity,
+            shadows: self.shadows,
+            normal_mapping: self.normal_mapping,
+            lod: self.lod,
+            asteroid_sprites: self.asteroid_sprites,
+            coronas: self.coronas,
+            debug_view: self.debug_view,
+            starfield: self.starfield.clone(),
+            post: self.post.clone(),
+            antialiasing: self.antialiasing.clone(),
+            pool: self.pool.clone(),
+            anaglyph: self.anaglyph,
+            profile_objects: self.profile_objects,
+            backends: vec![Box::new(Rasterizer::default()), Box::new(RayTracer)],
+            active: 0,
+            mesh: self.mesh.clone(),
+            detail_levels: self.detail_levels.clone(),
+            rock_mesh: self.rock_mesh.clone(),
+            mesh_radius: self.mesh_radius,
+            inner_radii: self.inner_radii.clone(),
+            fov: self.fov,
+        };
+        renderer.surface_cache.enabled = self.surface_cache.enabled;
+        renderer.select_backend(self.backend());
+        renderer
+    }
+
+    /// Name of the backend that draws
+    pub fn backend(&self) -> &'static str {
+        self.backends[self.active].name()
+    }
+
+    /// Names of the available backends
+    pub fn backend_names(&self) -> Vec<&'static str> {
+        self.backends.iter().map(|backend| backend.name()).collect()
+    }
+
+    /// Makes another backend available, replacing one with the same name
+    pub fn add_backend(&mut self, backend: Box<dyn RenderBackend>) {
+        match self.backends.iter().position(|b| b.name() == backend.name()) {
+            Some(index) => self.backends[index] = backend,
+            None => self.backends.push(backend),
+        }
+    }
+
+    /// Switches to the backend with this name; false (and no change) if there is none
+    pub fn select_backend(&mut self, name: &str) -> bool {
+        match self.backends.iter().position(|backend| backend.name() == name) {
+            Some(index) => {
+                self.active = index;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Switches to the next available backend and returns its name
+    pub fn next_backend(&mut self) -> &'static str {
+        self.active = (self.active + 1) % self.backends.len();
+        self.backend()
+    }
+
+    /// The body mesh, for backends that upload it elsewhere
+    pub fn mesh(&self) -> &Obj {
+        &self.mesh
+    }
+
+    /// Radius of the body mesh in model space
+    pub fn mesh_radius(&self) -> f32 {
+        self.mesh_radius
+    }
+
+    /// Vertical field of view in radians
+    pub fn fov(&self) -> f32 {
+        self.fov
+    }
+
+    /// Changes the vertical field of view (radians) of the projection
+    pub fn set_fov(&mut self, fov: f32) {
+        self.fov = fov;
+        let aspect = self.framebuffer.width as f32 / self.framebuffer.height as f32;
+        self.projection = create_projection_matrix(fov, aspect, NEAR_PLANE, 100.0);
+    }
+
+    /// Number of threads the frame is rendered on
+    pub fn threads(&self) -> usize {
+        self.pool.as_ref().map_or_else(rayon::current_num_threads, |pool| pool.current_num_threads())
+    }
+
+    /// Renders on `threads` threads from now on; 0 goes back to one per core
+    pub fn set_threads(&mut self, threads: usize) {
+        self.pool = (threads > 0).then(|| {
+            let pool = ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .thread_name(|index| format!("render-{index}"))
+                .build()
+                .expect("failed to start the render threads");
+            Arc::new(pool)
+        });
+    }
+
+    /// Renders at a new size from now on: reallocates the framebuffer and rebuilds the
+    /// projection and viewport; unlike `resized`, every setting, backend and cache stays
+    pub fn resize(&mut self, width: i32, height: i32) {
+        self.framebuffer.resize(width, height);
+        self.viewport = create_viewport_matrix(0.0, 0.0, width as f32, height as f32);
+        self.set_fov(self.fov);
+    }
+
+    /// Clears the framebuffer and draws the scene as seen by `camera`
+    /// (twice, merged into a red/cyan image, in anaglyph mode)
+    pub fn draw(&mut self, scene: &SolarSystem, camera: &Camera, time: f32) {
+        self.framebuffer.clear();
+        self.stats = RenderStats::default();
+
+        match self.anaglyph {
+            None => self.draw_view(scene, camera.get_view_matrix(), time),
+            Some(separation) => {
+                let (left, right) = camera.stereo_view_matrices(separation);
+                self.draw_view(scene, left, time);
+                let left_eye = ImageBuffer::from_framebuffer(&self.framebuffer);
+
+                self.framebuffer.clear();
+                self.draw_view(scene, right, time);
+                self.compose_anaglyph(&left_eye);
+            }
+        }
+    }
+
+    /// Replaces the red channel of the framebuffer (right eye) with the left eye's
+    /// luminance; using luminance instead of plain red keeps red bodies (stars, lava)
+    /// visible to both eyes
+    fn compose_anaglyph(&mut self, left_eye: &ImageBuffer) {
+        let width = self.framebuffer.width as usize;
+
+        for (i, left) in left_eye.pixels.chunks_exact(4).enumerate() {
+            let (x, y) = ((i % width) as i32, (i / width) as i32);
+            let right = self.framebuffer.pixel(x, y);
+            let luma = 0.299 * left[0] as f32 + 0.587 * left[1] as f32 + 0.114 * left[2] as f32;
+            let color = Color::new(luma.round().min(255.0) as u8, right[1], right[2], 255);
+            self.framebuffer.set_pixel(x, y, color);
+        }
+    }
+
+    /// Draws every object of the scene with the given view matrix, adding to the stats
+    fn draw_view(&mut self, scene: &SolarSystem, view: Matrix, time: f32) {
+        // Stars are the light sources
+        let mut lights = scene.lights(self.mesh_radius);
+        for light in &mut lights {
+            light.intensity *= self.light_intensity;
+        }
+
+        // Bake missing noise layers first; the jobs only read the cache
+        for object in &scene.objects {
+            self.noise_cache.prepare(&Noise::new(object.seed), &object.material, time);
+            self.surface_cache.prepare(object.seed, &object.material, time, &self.noise_cache);
+        }
+
+        // The sky first, so every body covers it
+        if let Some(starfield) = &self.starfield {
+            starfield.draw(&mut self.framebuffer, &view, &self.projection, &self.viewport, time);
+        }
+
+        let setup = ViewSetup {
+            view,
+            projection: self.projection,
+            viewport: self.viewport,
+            time,
+            mesh_radius: self.mesh_radius,
+            inner_radii: &self.inner_radii,
+            body_mesh: &self.mesh,
+            detail_levels: if self.lod { &self.detail_levels } else { &[] },
+            lights: &lights,
+            shadows: self.shadows,
+            normal_mapping: self.normal_mapping,
+            noise_cache: &self.noise_cache,
+            surfaces: &self.surface_cache,
+            pool: self.pool.as_deref(),
+            debug_view: self.debug_view,
+            profile_objects: self.profile_objects,
+        };
+        self.backends[self.active].draw_view(&setup, scene, &mut self.framebuffer, &mut self.stats);
+        if self.debug_view != DebugView::Shaded && self.backends[self.active].is_software() {
+            self.debug_view.apply(&mut self.framebuffer);
+            return;
+        }
+        // Coronas behind the belts and particles, but over the bodies behind the stars
+        if self.coronas && self.backends[self.active].is_software() {
+            draw_coronas(&mut self.framebuffer, &setup, scene);
+        }
+        // Belts are drawn against the depth the software backends leave behind
+        if !scene.belts.is_empty() && self.backends[self.active].is_software() {
+            let occluders = setup.occluders(scene, usize::MAX);
+            draw_belts(&mut self.framebuffer, &setup, &scene.belts, &occluders, &self.rock_mesh, self.asteroid_sprites, &mut self.stats);
+        }
+        // Particles last, so translucent ones blend over everything behind them
+        if self.backends[self.active].is_software() {
+            let tails = scene.objects.iter().filter_map(|object| object.tail.as_ref());
+            let plumes = scene.objects.iter().filter_map(|object| object.volcanoes.as_ref().map(|volcanoes| &volcanoes.plume));
+            for particles in tails.chain(plumes).chain(&scene.effects) {
+                draw_particles(&mut self.framebuffer, particles, &view, &self.projection, &self.viewport);
+            }
+        }
+
+        if let Some(fxaa) = &self.antialiasing {
+            run_effects(&mut self.framebuffer, [fxaa as &dyn PostEffect], time);
+        }
+        self.post.apply(&mut self.framebuffer, time);
+    }
+
+    /// Draws the scene with an explicit view matrix (e.g. one face of a cube map)
+    /// and returns a copy of the frame
+    pub fn render_view(&mut self, scene: &SolarSystem, view: Matrix, time: f32) -> ImageBuffer {
+        self.framebuffer.clear();
+        self.stats = RenderStats::default();
+        self.draw_view(scene, view, time);
+        ImageBuffer::from_framebuffer(&self.framebuffer)
+    }
+
+    /// Draws the scene and returns a copy of the frame; needs no window
+    pub fn render_frame(&mut self, scene: &SolarSystem, camera: &Camera, time: f32) -> ImageBuffer {
+        self.draw(scene, camera, time);
+        ImageBuffer::from_framebuffer(&self.framebuffer)
+    }
+}
@@ -0,0 +1,380 @@
+// replay.rs
+// Session recording: per-frame input, panel settings and scene loads written to a text file
+// and played back to reproduce the exact same frames
+
+use raylib::prelude::*;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use crate::camera::{Camera, FLY_SPEED, MOUSE_SENSITIVITY, SCROLL_SENSITIVITY};
+use crate::debug_ui::DebugSettings;
+use crate::input::{Action, InputFrame};
+use crate::solar_system::SolarSystem;
+
+/// Version 2 stores action names instead of key codes, so replays survive rebinding
+const MAGIC: &str = "lab5-replay 2";
+
+/// Initial camera placement and sensitivity
+#[derive(Clone, Debug, PartialEq)]
+pub struct CameraSetup {
+    pub eye: Vector3,
+    pub target: Vector3,
+    pub rotation_speed: f32,
+    pub zoom_speed: f32,
+    pub pan_speed: f32,
+    pub mouse_sensitivity: f32,
+    pub scroll_sensitivity: f32,
+    pub invert_x: bool,
+    pub invert_y: bool,
+    pub fly_speed: f32,
+}
+
+impl CameraSetup {
+    pub fn capture(camera: &Camera) -> Self {
+        CameraSetup {
+            eye: camera.eye,
+            target: camera.target,
+            rotation_speed: camera.rotation_speed,
+            zoom_speed: camera.zoom_speed,
+            pan_speed: camera.pan_speed,
+            mouse_sensitivity: camera.mouse_sensitivity,
+            scroll_sensitivity: camera.scroll_sensitivity,
+            invert_x: camera.invert_x,
+            invert_y: camera.invert_y,
+            fly_speed: camera.fly_speed,
+        }
+    }
+
+    /// Creates the camera described by this setup
+    pub fn build(&self) -> Camera {
+        let mut camera = Camera::new(self.eye, self.target, Vector3::new(0.0, 1.0, 0.0));
+        camera.rotation_speed = self.rotation_speed;
+        camera.zoom_speed = self.zoom_speed;
+        camera.pan_speed = self.pan_speed;
+        camera.mouse_sensitivity = self.mouse_sensitivity;
+        camera.scroll_sensitivity = self.scroll_sensitivity;
+        camera.invert_x = self.invert_x;
+        camera.invert_y = self.invert_y;
+        camera.fly_speed = self.fly_speed;
+        camera
+    }
+}
+
+/// A scene (re)load; planets start at random orbit angles, so those are stored too
+#[derive(Clone, Debug, PartialEq)]
+pub struct SceneLoad {
+    /// Preset name as accepted by `--preset`
+    pub preset: String,
+    pub seed: u32,
+    pub orbit_angles: Vec<f32>,
+}
+
+impl SceneLoad {
+    pub fn capture(preset: &str, system: &SolarSystem) -> Self {
+        SceneLoad {
+            preset: preset.to_string(),
+            seed: system.seed,
+            orbit_angles: system.objects.iter().map(|o| o.orbit_angle).collect(),
+        }
+    }
+
+    /// Restores the recorded seed and orbit angles on a freshly built preset
+    pub fn apply(&self, system: &mut SolarSystem) {
+        system.reseed(self.seed);
+        for (object, &angle) in system.objects.iter_mut().zip(&self.orbit_angles) {
+            object.orbit_angle = angle;
+        }
+    }
+}
+
+/// Everything that influenced one recorded frame
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplayFrame {
+    pub input: InputFrame,
+    /// Debug panel settings in effect during the frame
+    pub settings: DebugSettings,
+    /// Scene loaded during the frame, if any
+    pub scene: Option<SceneLoad>,
+}
+
+/// A recorded session
+#[derive(Clone, Debug)]
+pub struct Recording {
+    pub width: i32,
+    pub height: i32,
+    pub camera: CameraSetup,
+    pub scene: SceneLoad,
+    pub frames: Vec<ReplayFrame>,
+}
+
+fn invalid(line: usize, message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("line {line}: {}", message.into()))
+}
+
+fn parse<T: std::str::FromStr>(line: usize, text: &str) -> io::Result<T> {
+    text.parse().map_err(|_| invalid(line, format!("invalid number '{text}'")))
+}
+
+fn write_actions(out: &mut impl Write, name: &str, actions: &[Action]) -> io::Result<()> {
+    let names: Vec<&str> = actions.iter().map(|a| a.name()).collect();
+    write!(out, " {name}={}", names.join(","))
+}
+
+fn parse_actions(line: usize, text: &str) -> io::Result<Vec<Action>> {
+    text.split(',')
+        .filter(|name| !name.is_empty())
+        .map(|name| Action::from_name(name).ok_or_else(|| invalid(line, format!("unknown action '{name}'"))))
+        .collect()
+}
+
+fn write_scene(out: &mut impl Write, scene: &SceneLoad) -> io::Result<()> {
+    write!(out, "scene {} {}", scene.preset, scene.seed)?;
+    for angle in &scene.orbit_angles {
+        write!(out, " {angle}")?;
+    }
+    writeln!(out)
+}
+
+fn parse_scene(line: usize, fields: &[&str]) -> io::Result<SceneLoad> {
+    let [preset, seed, angles @ ..] = fields else {
+        return Err(invalid(line, "expected `scene <preset> <seed> <angles...>`"));
+    };
+    Ok(SceneLoad {
+        preset: preset.to_string(),
+        seed: parse(line, seed)?,
+        orbit_angles: angles.iter().map(|a| parse(line, a)).collect::<io::Result<_>>()?,
+    })
+}
+
+fn parse_frame(line: usize, fields: &[&str]) -> io::Result<ReplayFrame> {
+    let [delta_time, time, rest @ ..] = fields else {
+        return Err(invalid(line, "expected `frame <dt> <time> ...`"));
+    };
+    let mut frame = ReplayFrame {
+        input: InputFrame {
+            delta_time: parse(line, delta_time)?,
+            time: parse(line, time)?,
+            ..InputFrame::default()
+        },
+        settings: DebugSettings::default(),
+        scene: None,
+    };
+
+    for field in rest {
+        let (key, value) = field.split_once('=').ok_or_else(|| invalid(line, format!("invalid field '{field}'")))?;
+        match key {
+            "down" => frame.input.down = parse_actions(line, value)?,
+            "pressed" => frame.input.pressed = parse_actions(line, value)?,
+            "mouse" => {
+                let values: Vec<f32> = value.split(',').map(|v| parse(line, v)).collect::<io::Result<_>>()?;
+                let [dx, dy, wheel] = values[..] else {
+                    return Err(invalid(line, "expected 3 mouse values"));
+                };
+                frame.input.mouse_delta = Vector2::new(dx, dy);
+                frame.input.wheel = wheel;
+            }
+            "cursor" => {
+                let values: Vec<f32> = value.split(',').map(|v| parse(line, v)).collect::<io::Result<_>>()?;
+                let [x, y] = values[..] else {
+                    return Err(invalid(line, "expected 2 cursor values"));
+                };
+                frame.input.mouse_position = Vector2::new(x, y);
+            }
+            "settings" => {
+                let values: Vec<&str> = value.split(',').collect();
+                // Recordings made before the shadow toggle have 5 settings, and before the
+                // post-processing toggles 6; those keep the default effects
+                let (time_scale, light, animation, dither, cache, shadows, post) = match values[..] {
+                    [t, l, a, d, c] => (t, l, a, d, c, "1", None),
+                    [t, l, a, d, c, s] => (t, l, a, d, c, s, None),
+                    [t, l, a, d, c, s, p] => (t, l, a, d, c, s, Some(p)),
+                    _ => return Err(invalid(line, "expected 7 settings")),
+                };
+                let mut post_effects = DebugSettings::default().post_effects;
+                if let Some(bits) = post {
+                    if bits.len() != post_effects.len() || bits.chars().any(|c| c != '0' && c != '1') {
+                        return Err(invalid(line, format!("invalid post effects '{bits}'")));
+                    }
+                    for (enabled, bit) in post_effects.iter_mut().zip(bits.chars()) {
+                        *enabled = bit == '1';
+                    }
+                }
+                frame.settings = DebugSettings {
+                    time_scale: parse(line, time_scale)?,
+                    light_intensity: parse(line, light)?,
+                    animation_speed: parse(line, animation)?,
+                    dither: dither == "1",
+                    noise_cache: cache == "1",
+                    shadows: shadows == "1",
+                    post_effects,
+                };
+            }
+            _ => return Err(invalid(line, format!("unknown field '{key}'"))),
+        }
+    }
+    Ok(frame)
+}
+
+impl Recording {
+    /// Reads a session written by `Recorder`
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Recording> {
+        let input = BufReader::new(File::open(path)?);
+        let mut size = None;
+        let mut camera = None;
+        let mut scene = None;
+        let mut frames: Vec<ReplayFrame> = Vec::new();
+
+        for (index, text) in input.lines().enumerate() {
+            let text = text?;
+            let line = index + 1;
+            if line == 1 {
+                if text.trim() == "lab5-replay 1" {
+                    return Err(invalid(line, "recorded with key codes by an older version, record it again"));
+                }
+                if text.trim() != MAGIC {
+                    return Err(invalid(line, "not a replay file"));
+                }
+                continue;
+            }
+
+            let fields: Vec<&str> = text.split_whitespace().collect();
+            match fields.as_slice() {
+                [] => {}
+                ["window", width, height] => size = Some((parse(line, width)?, parse(line, height)?)),
+                // Recordings made before mouse controls have 9 values and before free flight 13;
+                // they get the defaults of what is missing
+                ["camera", values @ ..] if [9, 13, 14].contains(&values.len()) => {
+                    let mut v: Vec<f32> = values.iter().map(|v| parse(line, v)).collect::<io::Result<_>>()?;
+                    if v.len() == 9 {
+                        v.extend([MOUSE_SENSITIVITY, SCROLL_SENSITIVITY, 0.0, 0.0]);
+                    }
+                    if v.len() == 13 {
+                        v.push(FLY_SPEED);
+                    }
+                    camera = Some(CameraSetup {
+                        eye: Vector3::new(v[0], v[1], v[2]),
+                        target: Vector3::new(v[3], v[4], v[5]),
+                        rotation_speed: v[6],
+                        zoom_speed: v[7],
+                        pan_speed: v[8],
+                        mouse_sensitivity: v[9],
+                        scroll_sensitivity: v[10],
+                        invert_x: v[11] != 0.0,
+                        invert_y: v[12] != 0.0,
+                        fly_speed: v[13],
+                    });
+                }
+                ["scene", rest @ ..] => {
+                    let load = parse_scene(line, rest)?;
+                    // The first scene is the initial one, later ones belong to the frame before them
+                    match frames.last_mut() {
+                        Some(frame) => frame.scene = Some(load),
+                        None => scene = Some(load),
+                    }
+                }
+                ["frame", rest @ ..] => frames.push(parse_frame(line, rest)?),
+                _ => return Err(invalid(line, format!("unexpected '{text}'"))),
+            }
+        }
+
+        let missing = |what: &str| io::Error::new(io::ErrorKind::InvalidData, format!("missing {what} line"));
+        let (width, height) = size.ok_or_else(|| missing("window"))?;
+        Ok(Recording {
+            width,
+            height,
+            camera: camera.ok_or_else(|| missing("camera"))?,
+            scene: scene.ok_or_else(|| missing("scene"))?,
+            frames,
+        })
+    }
+}
+
+/// Writes a session as it is played; frames are appended as they happen so an
+/// interrupted session still leaves a usable file
+pub struct Recorder {
+    out: BufWriter<File>,
+}
+
+impl Recorder {
+    /// Creates the file and writes the session header
+    pub fn create(path: impl AsRef<Path>, width: i32, height: i32, camera: &CameraSetup, scene: &SceneLoad) -> io::Result<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, "{MAGIC}")?;
+        writeln!(out, "window {width} {height}")?;
+        writeln!(
+            out,
+            "camera {} {} {} {} {} {} {} {} {} {} {} {} {} {}",
+            camera.eye.x, camera.eye.y, camera.eye.z,
+            camera.target.x, camera.target.y, camera.target.z,
+            camera.rotation_speed, camera.zoom_speed, camera.pan_speed,
+            camera.mouse_sensitivity, camera.scroll_sensitivity,
+            camera.invert_x as u8, camera.invert_y as u8,
+            camera.fly_speed,
+        )?;
+        write_scene(&mut out, scene)?;
+        Ok(Recorder { out })
+    }
+
+    /// Appends a frame's input and the panel settings in effect
+    pub fn record_frame(&mut self, input: &InputFrame, settings: &DebugSettings) -> io::Result<()> {
+        write!(self.out, "frame {} {}", input.delta_time, input.time)?;
+        write_actions(&mut self.out, "down", &input.down)?;
+        write_actions(&mut self.out, "pressed", &input.pressed)?;
+        // Only frames where the mouse moved carry it, which keeps keyboard sessions short
+        if input.mouse_delta != Vector2::zero() || input.wheel != 0.0 {
+            write!(self.out, " mouse={},{},{}", input.mouse_delta.x, input.mouse_delta.y, input.wheel)?;
+        }
+        // The cursor only matters where a click starts
+        if input.is_pressed(Action::Pick) {
+            write!(self.out, " cursor={},{}", input.mouse_position.x, input.mouse_position.y)?;
+        }
+        writeln!(
+            self.out,
+            " settings={},{},{},{},{},{},{}",
+            settings.time_scale,
+            settings.light_intensity,
+            settings.animation_speed,
+            settings.dither as u8,
+            settings.noise_cache as u8,
+            settings.shadows as u8,
+            settings.post_effects.iter().map(|&on| if on { '1' } else { '0' }).collect::<String>(),
+        )
+    }
+
+    /// Records a scene loaded during the last recorded frame
+    pub fn record_scene(&mut self, scene: &SceneLoad) -> io::Result<()> {
+        write_scene(&mut self.out, scene)
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Steps through a recording one frame at a time
+pub struct Player {
+    recording: Recording,
+    next: usize,
+}
+
+impl Player {
+    pub fn new(recording: Recording) -> Self {
+        Player { recording, next: 0 }
+    }
+
+    pub fn recording(&self) -> &Recording {
+        &self.recording
+    }
+
+    /// Next recorded frame, None once the recording is over
+    pub fn next_frame(&mut self) -> Option<&ReplayFrame> {
+        let frame = self.recording.frames.get(self.next)?;
+        self.next += 1;
+        Some(frame)
+    }
+
+    /// Scene loaded during the frame last returned by `next_frame`
+    pub fn current_scene(&self) -> Option<&SceneLoad> {
+        self.next.checked_sub(1).and_then(|i| self.recording.frames[i].scene.as_ref())
+    }
+}
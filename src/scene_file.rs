@@ -0,0 +1,534 @@
+// scene_file.rs
+// Solar systems described in TOML or JSON scene files, plus change detection for hot-reload
+
+use raylib::prelude::*;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use crate::asteroid_belt::AsteroidBelt;
+use crate::atmosphere::Atmosphere;
+use crate::aurora::Aurora;
+use crate::storm::Vortex;
+use crate::volcano::Volcanoes;
+use crate::collision::CollisionResponse;
+use crate::fragment::BlendMode;
+use crate::gltf::GltfModel;
+use crate::gravity::Motion;
+use crate::script::ScriptError;
+use crate::shader_registry::ShaderRegistry;
+use crate::shader_system::ShaderType;
+use crate::solar_system::{CelestialObject, CelestialType, Orbit, SolarSystem, SpectralClass};
+use crate::json;
+use crate::material::Material;
+use crate::obj::Obj;
+use crate::toml::{self, Document, ParseError, Table, Value};
+
+/// Error while reading or interpreting a scene file
+#[derive(Debug)]
+pub enum SceneError {
+    Io(io::Error),
+    Parse(ParseError),
+    Script(ScriptError),
+    /// Body (name or position) and description
+    Invalid(String, String),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io(error) => write!(f, "{error}"),
+            SceneError::Parse(error) => write!(f, "{error}"),
+            SceneError::Script(error) => write!(f, "script {error}"),
+            SceneError::Invalid(body, message) => write!(f, "body '{body}': {message}"),
+        }
+    }
+}
+
+impl From<io::Error> for SceneError {
+    fn from(error: io::Error) -> Self {
+        SceneError::Io(error)
+    }
+}
+
+impl From<ParseError> for SceneError {
+    fn from(error: ParseError) -> Self {
+        SceneError::Parse(error)
+    }
+}
+
+pub(crate) fn shader_from_name(name: &str) -> Option<ShaderType> {
+    match name {
+        "star" => Some(ShaderType::Star),
+        "rocky" => Some(ShaderType::Rocky),
+        "gas_giant" => Some(ShaderType::GasGiant),
+        "lava" => Some(ShaderType::Lava),
+        "ice" => Some(ShaderType::IceWorld),
+        "cloud" => Some(ShaderType::CloudPlanet),
+        "rings" => Some(ShaderType::Rings),
+        "model" => Some(ShaderType::Model),
+        _ => ShaderRegistry::lookup(name),
+    }
+}
+
+/// Scene file name of a shader, the inverse of `shader_from_name`
+pub(crate) fn shader_name(shader: ShaderType) -> String {
+    match shader {
+        ShaderType::Star => "star".to_string(),
+        ShaderType::Rocky => "rocky".to_string(),
+        ShaderType::GasGiant => "gas_giant".to_string(),
+        ShaderType::Lava => "lava".to_string(),
+        ShaderType::IceWorld => "ice".to_string(),
+        ShaderType::CloudPlanet => "cloud".to_string(),
+        ShaderType::Rings => "rings".to_string(),
+        ShaderType::Model => "model".to_string(),
+        ShaderType::Custom(id) => ShaderRegistry::name(id).unwrap_or_else(|| format!("custom #{id}")),
+    }
+}
+
+fn spectral_class_from_name(name: &str) -> Option<SpectralClass> {
+    match name {
+        "O" => Some(SpectralClass::O),
+        "B" => Some(SpectralClass::B),
+        "A" => Some(SpectralClass::A),
+        "F" => Some(SpectralClass::F),
+        "G" => Some(SpectralClass::G),
+        "K" => Some(SpectralClass::K),
+        "M" => Some(SpectralClass::M),
+        _ => None,
+    }
+}
+
+/// Reads the model of a body: OBJ (with its MTL colors) when its extension is `.obj`,
+/// glTF otherwise
+fn load_model(path: &Path) -> Result<GltfModel, String> {
+    let is_obj = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("obj"));
+    if is_obj {
+        Obj::load(&path.to_string_lossy()).map(|obj| obj.to_model()).map_err(|error| error.to_string())
+    } else {
+        GltfModel::load(path).map_err(|error| error.to_string())
+    }
+}
+
+/// Builds one `[[body]]` entry; parents must be declared before their children and
+/// models are read relative to `base`
+fn parse_body(index: usize, table: &Table, system: &SolarSystem, base: &Path) -> Result<CelestialObject, SceneError> {
+    let name = match table.get("name") {
+        Some(value) => value.as_str().unwrap_or_default().to_string(),
+        None => format!("#{}", index + 1),
+    };
+    let invalid = |message: String| SceneError::Invalid(name.clone(), message);
+
+    let number = |key: &str, default: f32| match table.get(key) {
+        Some(value) => value.as_f32().ok_or_else(|| invalid(format!("{key} must be a number"))),
+        None => Ok(default),
+    };
+    let vector = |key: &str, what: &str| match table.get(key) {
+        Some(value) => match value {
+            Value::Array(items) if items.len() == 3 => {
+                let c: Option<Vec<f32>> = items.iter().map(Value::as_f32).collect();
+                c.map(|c| Some(Vector3::new(c[0], c[1], c[2])))
+            }
+            _ => None,
+        }
+        .ok_or_else(|| invalid(format!("{key} must be {what}"))),
+        None => Ok(None),
+    };
+    let text = |key: &str| match table.get(key) {
+        Some(value) => value.as_str().map(Some).ok_or_else(|| invalid(format!("{key} must be a string"))),
+        None => Ok(None),
+    };
+
+    let kind = match text("type")?.unwrap_or("planet") {
+        "star" => CelestialType::Star,
+        "planet" => CelestialType::Planet,
+        "moon" => CelestialType::Moon,
+        "ring" => CelestialType::Ring,
+        "comet" => CelestialType::Comet,
+        other => return Err(invalid(format!("unknown type '{other}' (expected star, planet, moon, ring or comet)"))),
+    };
+
+    let parent = match text("parent")? {
+        Some(parent) => Some(
+            system.objects
+                .iter()
+                .position(|o| o.name == parent)
+                .ok_or_else(|| invalid(format!("parent '{parent}' must be declared before it")))?,
+        ),
+        None => None,
+    };
+
+    let shader = match text("shader")? {
+        Some(shader) => shader_from_name(shader)
+            .ok_or_else(|| invalid(format!("unknown shader '{shader}' (expected star, rocky, gas_giant, lava, ice, cloud or a registered shader)")))?,
+        None if table.contains_key("model") => ShaderType::Model,
+        None if kind == CelestialType::Star => ShaderType::Star,
+        None if kind == CelestialType::Ring => ShaderType::Rings,
+        None if kind == CelestialType::Comet => ShaderType::IceWorld,
+        None => ShaderType::Rocky,
+    };
+
+    let scale = number("scale", 1.0)?;
+    let orbit_radius = number("orbit_radius", 0.0)?;
+    let orbit_speed = number("orbit_speed", 0.0)?;
+
+    let mut object = match (kind, parent) {
+        (CelestialType::Star, parent) => {
+            let mut star = CelestialObject::star(scale);
+            star.parent_index = parent;
+            star.orbit_radius = orbit_radius;
+            star.orbit_speed = orbit_speed;
+            star
+        }
+        (CelestialType::Planet, Some(parent)) => {
+            CelestialObject::planet(parent, orbit_radius, orbit_speed, scale, shader)
+        }
+        (CelestialType::Moon, Some(parent)) => {
+            CelestialObject::moon(parent, orbit_radius, orbit_speed, scale, shader)
+        }
+        (CelestialType::Comet, Some(parent)) => {
+            CelestialObject::comet(parent, orbit_radius, orbit_speed, scale)
+        }
+        (CelestialType::Ring, Some(parent)) => {
+            let inner = number("inner_radius", 1.5)?.max(0.0);
+            let outer = number("outer_radius", inner + 1.0)?;
+            if outer <= inner {
+                return Err(invalid("outer_radius must be larger than inner_radius".to_string()));
+            }
+            let mut ring = CelestialObject::ring(parent, inner, outer);
+            ring.scale = scale;
+            ring.rotation.x = number("tilt", ring.rotation.x)?;
+            ring
+        }
+        (_, None) => return Err(invalid("planets, moons, rings and comets need a parent".to_string())),
+    };
+    object.name = name.clone();
+    object.material = Material { emissive: object.material.emissive, ..shader.material() };
+    if let Some(file) = text("model")? {
+        if kind == CelestialType::Ring {
+            return Err(invalid("rings cannot have a model".to_string()));
+        }
+        let model = load_model(&base.join(file)).map_err(invalid)?;
+        object = object.with_model(Arc::new(model));
+        // The model's materials light the body, the shader still draws its surface
+        let surface = shader.material();
+        object.material = Material { shader, palette: surface.palette, noise: surface.noise, ..object.material };
+    }
+    object.material.specular = number("specular", object.material.specular)?.max(0.0);
+    object.material.shininess = number("shininess", object.material.shininess)?.max(1.0);
+    if let Some(tint) = vector("color", "[r, g, b]")? {
+        object.material.diffuse *= tint;
+    }
+
+    if let Some(class) = text("spectral_class")? {
+        object.spectral_class = spectral_class_from_name(class)
+            .ok_or_else(|| invalid(format!("unknown spectral class '{class}'")))?;
+    }
+    object.luminosity = number("luminosity", object.luminosity)?.max(0.0);
+    object.falloff = number("falloff", object.falloff)?.max(0.0);
+    if let Some(value) = table.get("storm") {
+        match value {
+            Value::Bool(storm) => object.vortex = storm.then(Vortex::default),
+            _ => return Err(invalid("storm must be true or false".to_string())),
+        }
+    }
+    if let Some(value) = table.get("eruptions") {
+        match value {
+            Value::Bool(eruptions) => object.volcanoes = eruptions.then(Volcanoes::default),
+            _ => return Err(invalid("eruptions must be true or false".to_string())),
+        }
+    }
+    if let Some(value) = table.get("emissive") {
+        match value {
+            Value::Bool(emissive) => object.material.emissive = *emissive,
+            _ => return Err(invalid("emissive must be true or false".to_string())),
+        }
+    }
+    if table.contains_key("orbit_angle") {
+        object.orbit_angle = number("orbit_angle", 0.0)?;
+        object.seeded_angle = false;
+    }
+    // Comets start out on a long orbit of their own
+    object.orbit = Orbit::new(
+        number("eccentricity", object.orbit.eccentricity)?,
+        number("inclination", object.orbit.inclination)?,
+        number("periapsis", object.orbit.periapsis)?,
+        number("ascending_node", object.orbit.ascending_node)?,
+    );
+    if let Some(speed) = vector("rotation_speed", "[x, y, z]")? {
+        object.rotation_speed = speed;
+    }
+    object.axial_tilt = number("axial_tilt", object.axial_tilt)?;
+    object.atmosphere = match table.get("atmosphere") {
+        None | Some(Value::Bool(false)) => None,
+        Some(Value::Bool(true)) => Some(Atmosphere::default()),
+        Some(_) => vector("atmosphere", "true, false or [r, g, b]")?.map(Atmosphere::tinted),
+    };
+    if let Some(atmosphere) = &mut object.atmosphere {
+        atmosphere.strength = number("atmosphere_strength", atmosphere.strength)?.max(0.0);
+    }
+    object.aurora = match table.get("aurora") {
+        None | Some(Value::Bool(false)) => None,
+        Some(Value::Bool(true)) => Some(Aurora::default()),
+        Some(_) => vector("aurora", "true, false or [r, g, b]")?.map(Aurora::tinted),
+    };
+    if let Some(aurora) = &mut object.aurora {
+        aurora.strength = number("aurora_strength", aurora.strength)?.max(0.0);
+    }
+    object.opacity = number("opacity", object.opacity)?.clamp(0.0, 1.0);
+    object.blend = match text("blend")? {
+        Some(blend) => BlendMode::from_name(blend)
+            .ok_or_else(|| invalid(format!("unknown blend '{blend}' (expected opaque, over or additive)")))?,
+        // A partly transparent body without a blend mode is blended over
+        None if object.opacity < 1.0 && object.blend == BlendMode::Opaque => BlendMode::Over,
+        None => object.blend,
+    };
+    if table.contains_key("mass") {
+        object.mass = Some(number("mass", 0.0)?.max(0.0));
+    }
+    object.launch_velocity = vector("velocity", "[x, y, z]")?;
+
+    for key in table.keys() {
+        if !matches!(
+            key.as_str(),
+            "name" | "type" | "parent" | "shader" | "scale" | "orbit_radius" | "orbit_speed"
+                | "orbit_angle" | "spectral_class" | "emissive" | "rotation_speed" | "axial_tilt" | "specular"
+                | "shininess" | "luminosity" | "falloff" | "inner_radius" | "outer_radius" | "tilt"
+                | "color" | "eccentricity" | "inclination" | "periapsis" | "ascending_node"
+                | "atmosphere" | "atmosphere_strength" | "aurora" | "aurora_strength" | "eruptions" | "storm" | "opacity" | "blend" | "mass" | "velocity"
+                | "model"
+        ) {
+            log::warn!("unknown key '{key}' in body '{name}'");
+        }
+    }
+
+    Ok(object)
+}
+
+/// Builds an asteroid belt from a `[[belt]]` table; its rocks are laid out from `seed`
+fn parse_belt(index: usize, table: &Table, system: &SolarSystem, seed: u32) -> Result<AsteroidBelt, SceneError> {
+    let invalid = |message: String| SceneError::Invalid(format!("belt #{}", index + 1), message);
+    let number = |key: &str, default: f32| match table.get(key) {
+        Some(value) => value.as_f32().ok_or_else(|| invalid(format!("{key} must be a number"))),
+        None => Ok(default),
+    };
+
+    let parent = match table.get("parent") {
+        Some(Value::Str(parent)) => system.objects
+            .iter()
+            .position(|o| &o.name == parent)
+            .ok_or_else(|| invalid(format!("parent '{parent}' must be declared as a body")))?,
+        _ => return Err(invalid("belts need a parent body name".to_string())),
+    };
+    let inner = number("inner_radius", 1.0)?.max(0.0);
+    let outer = number("outer_radius", inner + 2.0)?;
+    if outer <= inner {
+        return Err(invalid("outer_radius must be larger than inner_radius".to_string()));
+    }
+    let count = match table.get("count") {
+        Some(Value::Int(count)) if *count >= 0 => *count as usize,
+        Some(_) => return Err(invalid("count must be a positive integer".to_string())),
+        None => 300,
+    };
+    let min_scale = number("min_scale", 0.03)?.max(0.0);
+    let max_scale = number("max_scale", 0.1)?.max(min_scale);
+    let mut belt = AsteroidBelt::new(
+        parent,
+        inner,
+        outer,
+        count,
+        number("orbit_speed", 0.04)?,
+        (min_scale, max_scale),
+        number("thickness", 0.05)?.abs(),
+        seed,
+    );
+    if let Some(value) = table.get("shader") {
+        let name = value.as_str().ok_or_else(|| invalid("shader must be a string".to_string()))?;
+        belt.material = shader_from_name(name).ok_or_else(|| invalid(format!("unknown shader '{name}'")))?.material();
+    }
+
+    for key in table.keys() {
+        if !matches!(
+            key.as_str(),
+            "parent" | "inner_radius" | "outer_radius" | "count" | "orbit_speed" | "min_scale" | "max_scale"
+                | "thickness" | "shader"
+        ) {
+            log::warn!("unknown key '{key}' in belt #{}", index + 1);
+        }
+    }
+
+    Ok(belt)
+}
+
+impl SolarSystem {
+    /// Builds a system from scene file text; models are read relative to the working directory
+    ///
+    /// ```toml
+    /// seed = 3
+    /// collisions = "merge"
+    /// motion = "gravity"
+    ///
+    /// [[body]]
+    /// name = "sun"
+    /// type = "star"
+    /// scale = 3.0
+    ///
+    /// [[body]]
+    /// name = "earth"
+    /// parent = "sun"
+    /// orbit_radius = 17.0
+    /// orbit_speed = 0.05
+    /// shader = "cloud"
+    /// ```
+    pub fn parse_scene(text: &str) -> Result<SolarSystem, SceneError> {
+        SolarSystem::from_document(&toml::parse(text)?, Path::new(""))
+    }
+
+    /// Builds a system from JSON scene text, with the same keys as the TOML format
+    ///
+    /// ```json
+    /// {
+    ///     "seed": 3,
+    ///     "body": [
+    ///         { "name": "sun", "type": "star", "scale": 3.0 },
+    ///         { "name": "earth", "parent": "sun", "orbit_radius": 17.0, "shader": "cloud" }
+    ///     ]
+    /// }
+    /// ```
+    pub fn parse_json_scene(text: &str) -> Result<SolarSystem, SceneError> {
+        SolarSystem::from_document(&json::parse(text)?, Path::new(""))
+    }
+
+    /// Builds a system from a parsed scene; `base` is the directory models are read from
+    fn from_document(document: &Document, base: &Path) -> Result<SolarSystem, SceneError> {
+        let mut system = SolarSystem::new();
+
+        for (index, table) in document.arrays.get("body").into_iter().flatten().enumerate() {
+            let object = parse_body(index, table, &system, base)?;
+            if !object.name.is_empty() && system.objects.iter().any(|o| o.name == object.name) {
+                return Err(SceneError::Invalid(object.name, "duplicate name".to_string()));
+            }
+            system.add(object);
+        }
+        if system.objects.is_empty() {
+            return Err(SceneError::Invalid("-".to_string(), "the scene has no [[body]] entries".to_string()));
+        }
+
+        if let Some(value) = document.values.get("seed") {
+            match value {
+                Value::Int(seed) => system.reseed(*seed as u32),
+                _ => return Err(SceneError::Invalid("-".to_string(), "seed must be an integer".to_string())),
+            }
+        }
+        if let Some(value) = document.values.get("collisions") {
+            system.collisions = match value {
+                Value::Str(name) => CollisionResponse::from_name(name),
+                _ => None,
+            }
+            .ok_or_else(|| SceneError::Invalid("-".to_string(), "collisions must be log, merge or debris".to_string()))?;
+        }
+        for (index, table) in document.arrays.get("belt").into_iter().flatten().enumerate() {
+            let belt = parse_belt(index, table, &system, system.seed.wrapping_add(index as u32))?;
+            system.belts.push(belt);
+        }
+        if let Some(value) = document.values.get("gravity") {
+            system.gravity = value
+                .as_f32()
+                .ok_or_else(|| SceneError::Invalid("-".to_string(), "gravity must be a number".to_string()))?;
+        }
+        if let Some(value) = document.values.get("motion") {
+            let motion = match value {
+                Value::Str(name) => Motion::from_name(name),
+                _ => None,
+            }
+            .ok_or_else(|| SceneError::Invalid("-".to_string(), "motion must be kinematic or gravity".to_string()))?;
+            system.set_motion(motion);
+        }
+        log::debug!("parsed scene with {} bodies", system.objects.len());
+        Ok(system)
+    }
+
+    /// Reads and parses a scene file, JSON when its extension is `.json` and TOML otherwise
+    pub fn from_file(path: impl AsRef<Path>) -> Result<SolarSystem, SceneError> {
+        let path = path.as_ref();
+        SolarSystem::from_document(&read_document(path)?, path.parent().unwrap_or(Path::new("")))
+    }
+
+    /// Keeps the orbital phase and spin of bodies that also exist in `previous`
+    /// (matched by name), so a reloaded scene continues where the old one was
+    pub fn carry_state_from(&mut self, previous: &SolarSystem) {
+        self.time = previous.time;
+        for object in &mut self.objects {
+            if let Some(old) = previous.objects.iter().find(|o| !o.name.is_empty() && o.name == object.name) {
+                object.orbit_angle = old.orbit_angle;
+                object.rotation = old.rotation;
+                object.position = old.position;
+                object.velocity = old.velocity;
+            }
+        }
+    }
+}
+
+/// Parses a scene file with the parser its extension calls for
+fn read_document(path: &Path) -> Result<Document, SceneError> {
+    let text = fs::read_to_string(path)?;
+    let is_json = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+    Ok(if is_json { json::parse(&text)? } else { toml::parse(&text)? })
+}
+
+/// Script attached to a scene file with a top-level `script = "tour.script"` key,
+/// resolved relative to the scene file
+pub fn scene_script(path: impl AsRef<Path>) -> Result<Option<PathBuf>, SceneError> {
+    let path = path.as_ref();
+    let document = read_document(path)?;
+    match document.values.get("script") {
+        Some(Value::Str(script)) => Ok(Some(path.parent().unwrap_or(Path::new("")).join(script))),
+        Some(_) => Err(SceneError::Invalid("-".to_string(), "script must be a file name".to_string())),
+        None => Ok(None),
+    }
+}
+
+/// Polls a file's modification time to detect edits
+pub struct FileWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    last_check: Instant,
+}
+
+impl FileWatcher {
+    /// Minimum time between two checks of the file system
+    const INTERVAL: Duration = Duration::from_millis(250);
+
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        FileWatcher {
+            modified: fs::metadata(&path).and_then(|m| m.modified()).ok(),
+            path,
+            last_check: Instant::now(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// True once after every change to the file
+    pub fn changed(&mut self) -> bool {
+        if self.last_check.elapsed() < Self::INTERVAL {
+            return false;
+        }
+        self.last_check = Instant::now();
+
+        // A missing file (editors often delete and rewrite) is not a change yet
+        let Ok(modified) = fs::metadata(&self.path).and_then(|m| m.modified()) else {
+            return false;
+        };
+        if self.modified == Some(modified) {
+            return false;
+        }
+        self.modified = Some(modified);
+        true
+    }
+}
@@ -0,0 +1,855 @@
+// script.rs
+// Small embedded scripting language for scene behaviors: timers that spawn bodies,
+// camera tours and custom orbital logic, reloaded without recompiling
+//
+//     # globals keep their value between frames
+//     let comets = 0
+//
+//     on start { focus("sun") }
+//
+//     on update {
+//         camera.yaw = camera.yaw + dt * 0.1
+//         body("terra").orbit_speed = 0.05 + sin(time) * 0.02
+//     }
+//
+//     every 5 {
+//         comets = comets + 1
+//         spawn("comet" + comets, "sun", 25, 0.3, 0.1, "ice")
+//     }
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use crate::camera::Camera;
+use crate::debug_ui::DebugSettings;
+use crate::material::Material;
+use crate::scene_file::{shader_from_name, shader_name, SceneError};
+use crate::solar_system::{CelestialObject, Orbit, SolarSystem};
+
+/// Parse or runtime error with its 1-based line number
+#[derive(Debug)]
+pub struct ScriptError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Runtime value
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Nil,
+    Num(f32),
+    Bool(bool),
+    Str(String),
+    /// Id of a body (`CelestialObject::id`), looked up on every use so a handle kept
+    /// between frames stays on its body when others are removed
+    Body(u32),
+    Camera,
+}
+
+impl Value {
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::Nil => "nil",
+            Value::Num(_) => "number",
+            Value::Bool(_) => "bool",
+            Value::Str(_) => "string",
+            Value::Body(_) => "body",
+            Value::Camera => "camera",
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Nil => write!(f, "nil"),
+            Value::Num(number) => write!(f, "{number}"),
+            Value::Bool(flag) => write!(f, "{flag}"),
+            Value::Str(text) => write!(f, "{text}"),
+            Value::Body(id) => write!(f, "body #{id}"),
+            Value::Camera => write!(f, "camera"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Num(f32),
+    Str(String),
+    Ident(String),
+    /// Operators and punctuation
+    Sym(&'static str),
+}
+
+const SYMBOLS: [&str; 20] = [
+    "==", "!=", "<=", ">=", "&&", "||", "+", "-", "*", "/", "%", "<", ">", "!", "=", "(", ")", "{", "}", ",",
+];
+
+/// Splits the source into tokens tagged with their line; `#` starts a comment
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>, ScriptError> {
+    let mut tokens = Vec::new();
+    for (index, raw) in text.lines().enumerate() {
+        let line = index + 1;
+        let error = |message: String| ScriptError { line, message };
+        let chars: Vec<char> = raw.chars().collect();
+        let mut i = 0;
+
+        while i < chars.len() {
+            let c = chars[i];
+            if c == '#' {
+                break;
+            }
+            if c.is_whitespace() || c == ';' {
+                i += 1;
+            } else if c == '"' {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|&c| c == '"')
+                    .ok_or_else(|| error("unterminated string".to_string()))?;
+                tokens.push((Token::Str(chars[i + 1..i + 1 + end].iter().collect()), line));
+                i += end + 2;
+            } else if c.is_ascii_digit() {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                let number = text.parse().map_err(|_| error(format!("invalid number '{text}'")))?;
+                tokens.push((Token::Num(number), line));
+            } else if c.is_alphabetic() || c == '_' {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                tokens.push((Token::Ident(chars[start..i].iter().collect()), line));
+            } else if c == '.' {
+                tokens.push((Token::Sym("."), line));
+                i += 1;
+            } else {
+                let rest: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+                let symbol = SYMBOLS
+                    .iter()
+                    .find(|s| rest.starts_with(**s))
+                    .ok_or_else(|| error(format!("unexpected character '{c}'")))?;
+                tokens.push((Token::Sym(symbol), line));
+                i += symbol.len();
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug)]
+enum Expr {
+    Literal(Value),
+    Var(String),
+    Field(Box<Expr>, String),
+    Call(String, Vec<Expr>),
+    Unary(&'static str, Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug)]
+enum Stmt {
+    Let(String, Expr),
+    Assign(Expr, Expr),
+    If(Expr, Vec<Stmt>, Vec<Stmt>),
+    Expr(Expr),
+}
+
+/// A statement and the line it starts on
+type Block = Vec<(Stmt, usize)>;
+
+/// Binary operators from the loosest to the tightest binding
+const PRECEDENCE: [&[&str]; 5] = [&["||"], &["&&"], &["==", "!="], &["<", ">", "<=", ">="], &["+", "-"]];
+const FACTOR: [&str; 3] = ["*", "/", "%"];
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+}
+
+impl Parser {
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .or(self.tokens.last())
+            .map_or(1, |(_, line)| *line)
+    }
+
+    fn error(&self, message: String) -> ScriptError {
+        ScriptError { line: self.line(), message }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).map(|(token, _)| token.clone());
+        self.position += 1;
+        token
+    }
+
+    fn eat(&mut self, symbol: &'static str) -> bool {
+        if self.peek() == Some(&Token::Sym(symbol)) {
+            self.position += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, symbol: &'static str) -> Result<(), ScriptError> {
+        if self.eat(symbol) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected '{symbol}'")))
+        }
+    }
+
+    fn keyword(&mut self, word: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Ident(ident)) if ident == word) {
+            self.position += 1;
+            return true;
+        }
+        false
+    }
+
+    fn ident(&mut self) -> Result<String, ScriptError> {
+        match self.next() {
+            Some(Token::Ident(name)) => Ok(name),
+            _ => {
+                self.position -= 1;
+                Err(self.error("expected a name".to_string()))
+            }
+        }
+    }
+
+    fn block(&mut self) -> Result<Block, ScriptError> {
+        self.expect("{")?;
+        let mut statements = Vec::new();
+        while !self.eat("}") {
+            if self.peek().is_none() {
+                return Err(self.error("missing '}'".to_string()));
+            }
+            let line = self.line();
+            statements.push((self.statement()?, line));
+        }
+        Ok(statements)
+    }
+
+    fn statement(&mut self) -> Result<Stmt, ScriptError> {
+        if self.keyword("let") {
+            let name = self.ident()?;
+            self.expect("=")?;
+            return Ok(Stmt::Let(name, self.expression(0)?));
+        }
+        if self.keyword("if") {
+            let condition = self.expression(0)?;
+            let then = self.block()?.into_iter().map(|(s, _)| s).collect();
+            let otherwise = if self.keyword("else") {
+                if matches!(self.peek(), Some(Token::Ident(word)) if word == "if") {
+                    vec![self.statement()?]
+                } else {
+                    self.block()?.into_iter().map(|(s, _)| s).collect()
+                }
+            } else {
+                Vec::new()
+            };
+            return Ok(Stmt::If(condition, then, otherwise));
+        }
+
+        let target = self.expression(0)?;
+        if self.eat("=") {
+            if !matches!(target, Expr::Var(_) | Expr::Field(..)) {
+                return Err(self.error("only variables and fields can be assigned".to_string()));
+            }
+            return Ok(Stmt::Assign(target, self.expression(0)?));
+        }
+        Ok(Stmt::Expr(target))
+    }
+
+    /// Precedence climbing over `PRECEDENCE`, then factors and unary operators
+    fn expression(&mut self, level: usize) -> Result<Expr, ScriptError> {
+        if level == PRECEDENCE.len() {
+            return self.factor();
+        }
+        let mut left = self.expression(level + 1)?;
+        while let Some(&Token::Sym(symbol)) = self.peek() {
+            if !PRECEDENCE[level].contains(&symbol) {
+                break;
+            }
+            self.position += 1;
+            let right = self.expression(level + 1)?;
+            left = Expr::Binary(symbol, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn factor(&mut self) -> Result<Expr, ScriptError> {
+        let mut left = self.unary()?;
+        while let Some(&Token::Sym(symbol)) = self.peek() {
+            if !FACTOR.contains(&symbol) {
+                break;
+            }
+            self.position += 1;
+            let right = self.unary()?;
+            left = Expr::Binary(symbol, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ScriptError> {
+        for symbol in ["-", "!"] {
+            if self.eat(symbol) {
+                return Ok(Expr::Unary(symbol, Box::new(self.unary()?)));
+            }
+        }
+        let mut expr = self.primary()?;
+        while self.eat(".") {
+            expr = Expr::Field(Box::new(expr), self.ident()?);
+        }
+        Ok(expr)
+    }
+
+    fn primary(&mut self) -> Result<Expr, ScriptError> {
+        match self.next() {
+            Some(Token::Num(number)) => Ok(Expr::Literal(Value::Num(number))),
+            Some(Token::Str(text)) => Ok(Expr::Literal(Value::Str(text))),
+            Some(Token::Ident(name)) => match name.as_str() {
+                "true" => Ok(Expr::Literal(Value::Bool(true))),
+                "false" => Ok(Expr::Literal(Value::Bool(false))),
+                "nil" => Ok(Expr::Literal(Value::Nil)),
+                _ if self.eat("(") => {
+                    let mut args = Vec::new();
+                    if !self.eat(")") {
+                        loop {
+                            args.push(self.expression(0)?);
+                            if self.eat(")") {
+                                break;
+                            }
+                            self.expect(",")?;
+                        }
+                    }
+                    Ok(Expr::Call(name, args))
+                }
+                _ => Ok(Expr::Var(name)),
+            },
+            Some(Token::Sym("(")) => {
+                let expr = self.expression(0)?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            _ => {
+                self.position -= 1;
+                Err(self.error("expected a value".to_string()))
+            }
+        }
+    }
+}
+
+/// Shortest `every` interval a script may ask for, in simulated seconds
+const MIN_TIMER_INTERVAL: f32 = 0.01;
+
+/// Most times one timer runs in a single `Script::run` to catch up after a long frame;
+/// the runs past it are dropped
+const MAX_TIMER_RUNS: u64 = 8;
+
+/// Block run every `interval` simulated seconds
+struct Timer {
+    interval: f64,
+    next: f64,
+    body: Block,
+}
+
+/// World a script reads and changes while it runs
+pub struct ScriptContext<'a> {
+    pub system: &'a mut SolarSystem,
+    pub camera: &'a mut Camera,
+    /// Time scale, light intensity and animation speed
+    pub settings: &'a mut DebugSettings,
+    /// Simulated seconds since the previous run (0 while paused)
+    pub dt: f32,
+}
+
+/// Parsed script and its state: globals, timers and elapsed simulated time
+pub struct Script {
+    /// Top-level `let`s, evaluated before `on start`
+    globals_init: Block,
+    start: Block,
+    update: Block,
+    timers: Vec<Timer>,
+    globals: HashMap<String, Value>,
+    /// Simulated seconds since the script started; f64 so timers keep their spacing in
+    /// long sessions
+    time: f64,
+    started: bool,
+    /// Draws of `random()`, seeded from the scene's seed when the script starts, so a
+    /// rerun or a replay of the same scene draws the same numbers
+    rng: StdRng,
+}
+
+impl Script {
+    pub fn parse(text: &str) -> Result<Script, ScriptError> {
+        let mut parser = Parser { tokens: tokenize(text)?, position: 0 };
+        let mut script = Script {
+            globals_init: Vec::new(),
+            start: Vec::new(),
+            update: Vec::new(),
+            timers: Vec::new(),
+            globals: HashMap::new(),
+            time: 0.0,
+            started: false,
+            rng: StdRng::seed_from_u64(0),
+        };
+
+        while parser.peek().is_some() {
+            let line = parser.line();
+            if parser.keyword("let") {
+                let name = parser.ident()?;
+                parser.expect("=")?;
+                script.globals_init.push((Stmt::Let(name, parser.expression(0)?), line));
+            } else if parser.keyword("on") {
+                match parser.ident()?.as_str() {
+                    "start" => script.start.extend(parser.block()?),
+                    "update" => script.update.extend(parser.block()?),
+                    other => return Err(ScriptError { line, message: format!("unknown event '{other}' (expected start or update)") }),
+                }
+            } else if parser.keyword("every") {
+                let interval = match parser.next() {
+                    Some(Token::Num(seconds)) if seconds >= MIN_TIMER_INTERVAL => seconds as f64,
+                    _ => {
+                        let message = format!("every needs at least {MIN_TIMER_INTERVAL} seconds");
+                        return Err(ScriptError { line, message });
+                    }
+                };
+                script.timers.push(Timer { interval, next: interval, body: parser.block()? });
+            } else {
+                return Err(parser.error("expected let, on or every".to_string()));
+            }
+        }
+        Ok(script)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Script, SceneError> {
+        Script::parse(&std::fs::read_to_string(path)?).map_err(SceneError::Script)
+    }
+
+    /// Runs `on start` the first time, then `on update` and every due timer; `random()`
+    /// is seeded from the system's seed on that first run (a reloaded script starts over)
+    pub fn run(&mut self, context: &mut ScriptContext) -> Result<(), ScriptError> {
+        if !self.started {
+            self.started = true;
+            self.rng = StdRng::seed_from_u64(context.system.seed as u64);
+            let init = std::mem::take(&mut self.globals_init);
+            let start = std::mem::take(&mut self.start);
+            let result = self.execute(&init, context, true).and_then(|_| self.execute(&start, context, false));
+            self.globals_init = init;
+            self.start = start;
+            result?;
+        }
+
+        self.time += context.dt as f64;
+        let update = std::mem::take(&mut self.update);
+        let result = self.execute(&update, context, false);
+        self.update = update;
+        result?;
+
+        for index in 0..self.timers.len() {
+            let timer = &mut self.timers[index];
+            if self.time < timer.next {
+                continue;
+            }
+            let due = ((self.time - timer.next) / timer.interval).floor() as u64 + 1;
+            timer.next += due as f64 * timer.interval;
+            if due > MAX_TIMER_RUNS {
+                log::debug!("timer {index} dropped {} runs to catch up", due - MAX_TIMER_RUNS);
+            }
+
+            let body = std::mem::take(&mut timer.body);
+            let mut result = Ok(());
+            for _ in 0..due.min(MAX_TIMER_RUNS) {
+                result = self.execute(&body, context, false);
+                if result.is_err() {
+                    break;
+                }
+            }
+            self.timers[index].body = body;
+            result?;
+        }
+        Ok(())
+    }
+
+    /// Runs a block; with `global` its `let`s define globals instead of locals
+    fn execute(&mut self, block: &Block, context: &mut ScriptContext, global: bool) -> Result<(), ScriptError> {
+        let mut locals = HashMap::new();
+        for (statement, line) in block {
+            let mut run = Run { script: self, context, locals: &mut locals, global };
+            run.statement(statement).map_err(|message| ScriptError { line: *line, message })?;
+        }
+        Ok(())
+    }
+}
+
+/// State of one block being executed
+struct Run<'s, 'c, 'w> {
+    script: &'s mut Script,
+    context: &'c mut ScriptContext<'w>,
+    locals: &'s mut HashMap<String, Value>,
+    /// Top-level `let`s become globals
+    global: bool,
+}
+
+fn number(value: &Value) -> Result<f32, String> {
+    match value {
+        Value::Num(number) => Ok(*number),
+        other => Err(format!("expected a number, got {}", other.type_name())),
+    }
+}
+
+fn flag(value: &Value) -> Result<bool, String> {
+    match value {
+        Value::Bool(flag) => Ok(*flag),
+        other => Err(format!("expected true or false, got {}", other.type_name())),
+    }
+}
+
+fn text(value: &Value) -> Result<&str, String> {
+    match value {
+        Value::Str(text) => Ok(text),
+        other => Err(format!("expected a string, got {}", other.type_name())),
+    }
+}
+
+/// Index of a named body
+fn find_body(system: &SolarSystem, name: &str) -> Result<usize, String> {
+    system.objects
+        .iter()
+        .position(|o| o.name == name)
+        .ok_or_else(|| format!("no body named '{name}'"))
+}
+
+/// Current index of the body behind a handle; an error once it was removed or absorbed
+fn body_index(system: &SolarSystem, id: u32) -> Result<usize, String> {
+    system.index_of(id).ok_or_else(|| format!("body #{id} no longer exists"))
+}
+
+impl Run<'_, '_, '_> {
+    fn statement(&mut self, statement: &Stmt) -> Result<(), String> {
+        match statement {
+            Stmt::Let(name, expr) => {
+                let value = self.eval(expr)?;
+                if self.global {
+                    self.script.globals.insert(name.clone(), value);
+                } else {
+                    self.locals.insert(name.clone(), value);
+                }
+            }
+            Stmt::Assign(target, expr) => {
+                let value = self.eval(expr)?;
+                self.assign(target, value)?;
+            }
+            Stmt::If(condition, then, otherwise) => {
+                let branch = if flag(&self.eval(condition)?)? { then } else { otherwise };
+                let global = std::mem::replace(&mut self.global, false);
+                for statement in branch {
+                    self.statement(statement)?;
+                }
+                self.global = global;
+            }
+            Stmt::Expr(expr) => {
+                self.eval(expr)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn variable(&self, name: &str) -> Result<Value, String> {
+        if let Some(value) = self.locals.get(name).or_else(|| self.script.globals.get(name)) {
+            return Ok(value.clone());
+        }
+        let settings = &self.context.settings;
+        Ok(match name {
+            "time" => Value::Num(self.script.time as f32),
+            "dt" => Value::Num(self.context.dt),
+            "camera" => Value::Camera,
+            "light_intensity" => Value::Num(settings.light_intensity),
+            "time_scale" => Value::Num(settings.time_scale),
+            "animation_speed" => Value::Num(settings.animation_speed),
+            _ => return Err(format!("unknown variable '{name}'")),
+        })
+    }
+
+    fn assign(&mut self, target: &Expr, value: Value) -> Result<(), String> {
+        match target {
+            Expr::Var(name) => {
+                let settings = &mut self.context.settings;
+                match name.as_str() {
+                    "light_intensity" => settings.light_intensity = number(&value)?.max(0.0),
+                    "time_scale" => settings.time_scale = number(&value)?,
+                    "animation_speed" => settings.animation_speed = number(&value)?,
+                    "time" | "dt" | "camera" => return Err(format!("'{name}' is read-only")),
+                    _ if self.locals.contains_key(name) => {
+                        self.locals.insert(name.clone(), value);
+                    }
+                    _ if self.script.globals.contains_key(name) => {
+                        self.script.globals.insert(name.clone(), value);
+                    }
+                    _ => return Err(format!("assignment to undeclared variable '{name}' (use let)")),
+                }
+            }
+            Expr::Field(owner, field) => match self.eval(owner)? {
+                Value::Camera => {
+                    let camera = &mut self.context.camera;
+                    let (mut yaw, mut pitch, mut distance) = (camera.yaw, camera.pitch, camera.distance);
+                    match field.as_str() {
+                        "yaw" => yaw = number(&value)?,
+                        "pitch" => pitch = number(&value)?,
+                        "distance" => distance = number(&value)?,
+                        _ => return Err(format!("camera has no writable field '{field}'")),
+                    }
+                    camera.set_orbit(yaw, pitch, distance);
+                }
+                Value::Body(id) => {
+                    let index = body_index(self.context.system, id)?;
+                    let object = &mut self.context.system.objects[index];
+                    match field.as_str() {
+                        "scale" => object.scale = number(&value)?.max(0.0),
+                        "orbit_radius" => object.orbit_radius = number(&value)?.max(0.0),
+                        "orbit_speed" => object.orbit_speed = number(&value)?,
+                        "orbit_angle" => {
+                            object.orbit_angle = number(&value)?;
+                            object.seeded_angle = false;
+                        }
+                        "eccentricity" => {
+                            object.orbit.eccentricity = number(&value)?.clamp(0.0, Orbit::MAX_ECCENTRICITY)
+                        }
+                        "inclination" => object.orbit.inclination = number(&value)?,
+                        "emissive" => object.material.emissive = flag(&value)?,
+                        "luminosity" => object.luminosity = number(&value)?.max(0.0),
+                        "falloff" => object.falloff = number(&value)?.max(0.0),
+                        "shader" => {
+                            let name = text(&value)?;
+                            let shader = shader_from_name(name)
+                                .ok_or_else(|| format!("unknown shader '{name}'"))?;
+                            object.material = Material { emissive: object.material.emissive, ..shader.material() };
+                        }
+                        _ => return Err(format!("body has no writable field '{field}'")),
+                    }
+                }
+                other => return Err(format!("{} has no fields", other.type_name())),
+            },
+            _ => return Err("only variables and fields can be assigned".to_string()),
+        }
+        Ok(())
+    }
+
+    fn eval(&mut self, expr: &Expr) -> Result<Value, String> {
+        Ok(match expr {
+            Expr::Literal(value) => value.clone(),
+            Expr::Var(name) => self.variable(name)?,
+            Expr::Field(owner, field) => {
+                let owner = self.eval(owner)?;
+                self.field(&owner, field)?
+            }
+            Expr::Call(name, args) => {
+                let args = args.iter().map(|arg| self.eval(arg)).collect::<Result<Vec<_>, _>>()?;
+                self.call(name, &args)?
+            }
+            Expr::Unary(op, operand) => {
+                let value = self.eval(operand)?;
+                match *op {
+                    "-" => Value::Num(-number(&value)?),
+                    _ => Value::Bool(!flag(&value)?),
+                }
+            }
+            Expr::Binary(op, left, right) => {
+                let left = self.eval(left)?;
+                // Short-circuit the logical operators
+                match (*op, &left) {
+                    ("&&", Value::Bool(false)) => return Ok(Value::Bool(false)),
+                    ("||", Value::Bool(true)) => return Ok(Value::Bool(true)),
+                    _ => {}
+                }
+                let right = self.eval(right)?;
+                binary(op, &left, &right)?
+            }
+        })
+    }
+
+    fn field(&self, owner: &Value, field: &str) -> Result<Value, String> {
+        match owner {
+            Value::Camera => {
+                let camera = &self.context.camera;
+                Ok(Value::Num(match field {
+                    "yaw" => camera.yaw,
+                    "pitch" => camera.pitch,
+                    "distance" => camera.distance,
+                    "x" => camera.eye.x,
+                    "y" => camera.eye.y,
+                    "z" => camera.eye.z,
+                    _ => return Err(format!("camera has no field '{field}'")),
+                }))
+            }
+            Value::Body(id) => {
+                let object = &self.context.system.objects[body_index(self.context.system, *id)?];
+                Ok(match field {
+                    "name" => Value::Str(object.name.clone()),
+                    "x" => Value::Num(object.position.x),
+                    "y" => Value::Num(object.position.y),
+                    "z" => Value::Num(object.position.z),
+                    "scale" => Value::Num(object.scale),
+                    "orbit_radius" => Value::Num(object.orbit_radius),
+                    "orbit_speed" => Value::Num(object.orbit_speed),
+                    "orbit_angle" => Value::Num(object.orbit_angle),
+                    "eccentricity" => Value::Num(object.orbit.eccentricity),
+                    "inclination" => Value::Num(object.orbit.inclination),
+                    "emissive" => Value::Bool(object.material.emissive),
+                    "luminosity" => Value::Num(object.luminosity),
+                    "falloff" => Value::Num(object.falloff),
+                    "shader" => Value::Str(shader_name(object.material.shader)),
+                    _ => return Err(format!("body has no field '{field}'")),
+                })
+            }
+            other => Err(format!("{} has no fields", other.type_name())),
+        }
+    }
+
+    fn call(&mut self, name: &str, args: &[Value]) -> Result<Value, String> {
+        let arity = |count: usize| {
+            if args.len() == count {
+                Ok(())
+            } else {
+                Err(format!("{name} takes {count} arguments, got {}", args.len()))
+            }
+        };
+        let math = |f: fn(f32) -> f32| -> Result<Value, String> {
+            arity(1)?;
+            Ok(Value::Num(f(number(&args[0])?)))
+        };
+
+        match name {
+            "sin" => math(f32::sin),
+            "cos" => math(f32::cos),
+            "abs" => math(f32::abs),
+            "sqrt" => math(f32::sqrt),
+            "floor" => math(f32::floor),
+            "min" | "max" => {
+                arity(2)?;
+                let (a, b) = (number(&args[0])?, number(&args[1])?);
+                Ok(Value::Num(if name == "min" { a.min(b) } else { a.max(b) }))
+            }
+            "clamp" => {
+                arity(3)?;
+                Ok(Value::Num(number(&args[0])?.clamp(number(&args[1])?, number(&args[2])?)))
+            }
+            "random" => {
+                arity(0)?;
+                Ok(Value::Num(self.script.rng.random::<f32>()))
+            }
+            "log" => {
+                let line: Vec<String> = args.iter().map(Value::to_string).collect();
+                log::info!("{}", line.join(" "));
+                Ok(Value::Nil)
+            }
+            "body" => {
+                arity(1)?;
+                let index = find_body(self.context.system, text(&args[0])?)?;
+                Ok(Value::Body(self.context.system.objects[index].id))
+            }
+            "exists" => {
+                arity(1)?;
+                Ok(Value::Bool(find_body(self.context.system, text(&args[0])?).is_ok()))
+            }
+            "focus" => {
+                arity(1)?;
+                let index = find_body(self.context.system, text(&args[0])?)?;
+                let camera = &mut self.context.camera;
+                camera.target = self.context.system.objects[index].position;
+                camera.set_orbit(camera.yaw, camera.pitch, camera.distance);
+                Ok(Value::Nil)
+            }
+            "spawn" => {
+                // spawn(name, parent, orbit_radius, orbit_speed, scale, shader)
+                arity(6)?;
+                let system = &mut *self.context.system;
+                let name = text(&args[0])?;
+                if find_body(system, name).is_ok() {
+                    return Err(format!("a body named '{name}' already exists"));
+                }
+                let parent = find_body(system, text(&args[1])?)?;
+                let shader_text = text(&args[5])?;
+                let shader = shader_from_name(shader_text).ok_or_else(|| format!("unknown shader '{shader_text}'"))?;
+                let mut object = CelestialObject::planet(
+                    parent,
+                    number(&args[2])?,
+                    number(&args[3])?,
+                    number(&args[4])?,
+                    shader,
+                );
+                object.name = name.to_string();
+                let index = system.add(object);
+                Ok(Value::Body(system.objects[index].id))
+            }
+            "remove" => {
+                arity(1)?;
+                remove_body(self.context.system, text(&args[0])?)?;
+                Ok(Value::Nil)
+            }
+            _ => Err(format!("unknown function '{name}'")),
+        }
+    }
+}
+
+fn binary(op: &str, left: &Value, right: &Value) -> Result<Value, String> {
+    Ok(match (op, left, right) {
+        ("==", a, b) => Value::Bool(a == b),
+        ("!=", a, b) => Value::Bool(a != b),
+        ("&&" | "||", a, b) => {
+            let (a, b) = (flag(a)?, flag(b)?);
+            Value::Bool(if op == "&&" { a && b } else { a || b })
+        }
+        // Concatenation turns numbers into text, for names like "comet" + n
+        ("+", Value::Str(a), b) => Value::Str(format!("{a}{b}")),
+        (_, a, b) => {
+            let (a, b) = (number(a)?, number(b)?);
+            match op {
+                "+" => Value::Num(a + b),
+                "-" => Value::Num(a - b),
+                "*" => Value::Num(a * b),
+                "/" => Value::Num(a / b),
+                "%" => Value::Num(a.rem_euclid(b)),
+                "<" => Value::Bool(a < b),
+                ">" => Value::Bool(a > b),
+                "<=" => Value::Bool(a <= b),
+                ">=" => Value::Bool(a >= b),
+                _ => return Err(format!("unknown operator '{op}'")),
+            }
+        }
+    })
+}
+
+/// Removes a body without satellites and shifts the parent indices after it
+fn remove_body(system: &mut SolarSystem, name: &str) -> Result<(), String> {
+    let index = find_body(system, name)?;
+    if system.objects.iter().any(|o| o.parent_index == Some(index)) {
+        return Err(format!("'{name}' still has satellites"));
+    }
+    system.objects.remove(index);
+    for object in &mut system.objects {
+        if let Some(parent) = &mut object.parent_index
+            && *parent > index
+        {
+            *parent -= 1;
+        }
+    }
+    Ok(())
+}
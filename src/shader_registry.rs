@@ -0,0 +1,74 @@
+// shader_registry.rs
+// Custom fragment shaders registered by name at startup, so new planet looks can be
+// referenced from scene files and scripts without adding `ShaderType` variants
+
+use raylib::prelude::*;
+use std::sync::{Arc, PoisonError, RwLock};
+use crate::fragment::Fragment;
+use crate::scene_file::shader_from_name;
+use crate::shader_system::ShaderType;
+use crate::Uniforms;
+
+/// A registered fragment shader: same inputs as the built-in ones, returns the final color
+pub type CustomShader = dyn Fn(&Fragment, &Uniforms) -> Vector3 + Send + Sync;
+
+/// Named custom shaders; `ShaderType::Custom(id)` indexes into it
+pub struct ShaderRegistry {
+    shaders: Vec<(String, Arc<CustomShader>)>,
+}
+
+/// Shaders of the whole process: scene files are parsed without any context, so the
+/// names they use have to be visible from everywhere
+static REGISTRY: RwLock<ShaderRegistry> = RwLock::new(ShaderRegistry { shaders: Vec::new() });
+
+impl ShaderRegistry {
+    /// Registers `shader` under `name` and returns the type that selects it; registering
+    /// a name again replaces its shader (bodies using it switch over). None for the names
+    /// of built-in shaders, which can't be replaced
+    pub fn register(
+        name: &str,
+        shader: impl Fn(&Fragment, &Uniforms) -> Vector3 + Send + Sync + 'static,
+    ) -> Option<ShaderType> {
+        if shader_from_name(name).is_some_and(|found| !matches!(found, ShaderType::Custom(_))) {
+            return None;
+        }
+        let mut registry = REGISTRY.write().unwrap_or_else(PoisonError::into_inner);
+        let shader: Arc<CustomShader> = Arc::new(shader);
+        let id = match registry.shaders.iter().position(|(existing, _)| existing == name) {
+            Some(id) => {
+                registry.shaders[id].1 = shader;
+                id
+            }
+            None => {
+                registry.shaders.push((name.to_string(), shader));
+                registry.shaders.len() - 1
+            }
+        };
+        Some(ShaderType::Custom(id as u32))
+    }
+
+    /// Type of the custom shader registered under `name`
+    pub fn lookup(name: &str) -> Option<ShaderType> {
+        let registry = REGISTRY.read().unwrap_or_else(PoisonError::into_inner);
+        let id = registry.shaders.iter().position(|(existing, _)| existing == name)?;
+        Some(ShaderType::Custom(id as u32))
+    }
+
+    /// Name a custom shader was registered under
+    pub fn name(id: u32) -> Option<String> {
+        let registry = REGISTRY.read().unwrap_or_else(PoisonError::into_inner);
+        registry.shaders.get(id as usize).map(|(name, _)| name.clone())
+    }
+
+    /// Names of every registered shader, in registration order
+    pub fn names() -> Vec<String> {
+        let registry = REGISTRY.read().unwrap_or_else(PoisonError::into_inner);
+        registry.shaders.iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    /// The shader with this id, None if nothing was registered for it
+    pub fn get(id: u32) -> Option<Arc<CustomShader>> {
+        let registry = REGISTRY.read().unwrap_or_else(PoisonError::into_inner);
+        registry.shaders.get(id as usize).map(|(_, shader)| Arc::clone(shader))
+    }
+}
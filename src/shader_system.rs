@@ -3,11 +3,13 @@
 
 use raylib::prelude::*;
 use crate::fragment::Fragment;
+use crate::material::{Material, NoiseKind, NoiseLayer};
+use crate::noise::Noise;
+use crate::noise_simd::LANES;
 use crate::Uniforms;
-use crate::noise::*;
 
 /// Available shader types
-#[derive(Clone, Copy, PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum ShaderType {
     Star,          // Animated sun shader
     Rocky,         // Rocky planet
@@ -15,6 +17,83 @@ pub enum ShaderType {
     Lava,          // Lava planet
     IceWorld,      // Frozen planet
     CloudPlanet,   // Earth-like planet
+    Rings,         // Planetary ring of ice and dust
+    Model,         // Lit colors of a loaded model's materials
+    Custom(u32),   // Shader registered in the ShaderRegistry, by id
+}
+
+/// Low-frequency noise layer of a shader that can be baked once per object
+pub struct BakedLayer {
+    /// The shader's main noise layer, evaluated at a model-space position and time
+    pub noise: NoiseLayer,
+    /// Seconds between re-bakes for animated layers, None for static ones
+    pub refresh: Option<f32>,
+}
+
+/// Height field a shader's lighting follows: normals are tilted along its slopes, so
+/// mountains and crater rims catch the light instead of the surface looking painted on
+#[derive(Clone, Copy)]
+pub struct Relief {
+    /// Height at a model-space position and time, the same noise the shader colors with
+    pub noise: NoiseLayer,
+    /// How far a slope tilts the normal
+    pub strength: f32,
+}
+
+/// Step of the finite differences, in model units
+const RELIEF_STEP: f32 = 0.005;
+
+impl Relief {
+    /// Tilts a world-space `normal` along the slope of the height field at a model-space
+    /// `position`; `model` is the body's model matrix
+    pub fn perturb(&self, normal: Vector3, position: Vector3, noise: &Noise, time: f32, model: &Matrix) -> Vector3 {
+        let height = |p: Vector3| self.noise.sample(noise, p, time);
+        let center = height(position);
+        let gradient = Vector3::new(
+            height(position + Vector3::new(RELIEF_STEP, 0.0, 0.0)) - center,
+            height(position + Vector3::new(0.0, RELIEF_STEP, 0.0)) - center,
+            height(position + Vector3::new(0.0, 0.0, RELIEF_STEP)) - center,
+        ) / RELIEF_STEP;
+
+        // Only the part of the slope along the surface tilts the normal
+        let up = position.normalized();
+        let slope = gradient - up * gradient.dot(up);
+
+        // Into world space with the body's rotation (the model matrix scales uniformly)
+        let rotate = |v: Vector3| Vector3::new(
+            model.m0 * v.x + model.m4 * v.y + model.m8 * v.z,
+            model.m1 * v.x + model.m5 * v.y + model.m9 * v.z,
+            model.m2 * v.x + model.m6 * v.y + model.m10 * v.z,
+        );
+        let scale = rotate(Vector3::new(1.0, 0.0, 0.0)).length().max(1e-6);
+        (normal - rotate(slope) * (self.strength / scale)).normalized()
+    }
+}
+
+impl ShaderType {
+    /// Built-in surface of bodies drawn with this shader: its palette and noise layer, and
+    /// a highlight that makes ice and oceans glossy and leaves rock and gas nearly matte
+    pub const fn material(self) -> Material {
+        match self {
+            ShaderType::Star => Material::STAR,
+            ShaderType::Rocky => Material::ROCKY,
+            ShaderType::GasGiant => Material::GAS_GIANT,
+            ShaderType::Lava => Material::LAVA,
+            ShaderType::IceWorld => Material::ICE,
+            ShaderType::CloudPlanet => Material::CLOUD,
+            ShaderType::Rings => Material::RINGS,
+            ShaderType::Model | ShaderType::Custom(_) => Material::plain(self),
+        }
+    }
+}
+
+/// Samples the baked layer when available, otherwise evaluates it live
+#[inline]
+fn layer(uniforms: &Uniforms, pos: Vector3, noise: &NoiseLayer) -> f32 {
+    match uniforms.baked {
+        Some(baked) => baked.sample(pos),
+        None => noise.sample(&uniforms.noise, pos, uniforms.time),
+    }
 }
 
 /// Helper functions for color mixing
@@ -35,33 +114,31 @@ fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
 }
 
 /// Star shader - Animated sun with corona and solar flares
-pub fn star_shader(fragment: &Fragment, uniforms: &Uniforms) -> Vector3 {
+pub fn star_shader(fragment: &Fragment, uniforms: &Uniforms, material: &Material) -> Vector3 {
     let pos = fragment.world_position;
+    let noise = &uniforms.noise;
     let time = uniforms.time * 0.8; // Aumentado de 0.3 para movimiento más rápido
 
     // Múltiples capas de ruido para más movimiento
-    let surface = turbulence(Vector3::new(pos.x * 2.5, pos.y * 2.5 + time * 1.2, pos.z * 2.5), 4);
-    let flares = warp_noise(Vector3::new(pos.x * 4.0 + time * 1.5, pos.y * 4.0 + time, pos.z * 4.0), 0.8);
+    // Ruido 4D: el tiempo es la cuarta dimensión, así la superficie hierve en su sitio
+    let surface = material.noise.sample(noise, pos, time);
+    let flares = noise.warp_noise_4d(Vector3::new(pos.x * 4.0, pos.y * 4.0, pos.z * 4.0), time * 1.5, 0.8);
     
     // Segundo nivel de flares para más dinamismo
-    let secondary_flares = warp_noise(Vector3::new(pos.x * 2.0 + time * 0.7, pos.y * 2.0 - time * 0.5, pos.z * 2.0), 0.5);
+    let secondary_flares = noise.warp_noise_4d(Vector3::new(pos.x * 2.0, pos.y * 2.0, pos.z * 2.0), time * 0.7, 0.5);
 
     // Pulsos más intensos y diversos
-    let pulse1 = (simplex_noise(Vector3::new(pos.x * 1.5, pos.y * 1.5, pos.z * 1.5 + time * 3.0)) * 0.5 + 0.5) * 0.8;
+    let pulse1 = (noise.simplex_noise(Vector3::new(pos.x * 1.5, pos.y * 1.5, pos.z * 1.5 + time * 3.0)) * 0.5 + 0.5) * 0.8;
     let pulse2 = ((time * 1.5).sin() * 0.5 + 0.5) * 0.6; // Pulso adicional sinusoidal
     let combined_pulse = (pulse1 + pulse2) * 0.5;
 
     // Manchas solares que se mueven más rápido
-    let spots1 = voronoi(Vector3::new(pos.x + time * 0.3, pos.y + time * 0.2, pos.z), 3.5);
-    let spots2 = voronoi(Vector3::new(pos.x - time * 0.4, pos.y, pos.z + time * 0.25), 4.5);
+    let spots1 = noise.voronoi(Vector3::new(pos.x + time * 0.3, pos.y + time * 0.2, pos.z), 3.5);
+    let spots2 = noise.voronoi(Vector3::new(pos.x - time * 0.4, pos.y, pos.z + time * 0.25), 4.5);
     let spot_mask = smoothstep(0.15, 0.3, spots1) * smoothstep(0.2, 0.35, spots2);
 
     // Colores más vibrantes y variados
-    let core_white = Vector3::new(1.0, 1.0, 1.0);
-    let bright_yellow = Vector3::new(1.0, 0.95, 0.4);
-    let deep_orange = Vector3::new(1.0, 0.6, 0.15);
-    let hot_red = Vector3::new(1.0, 0.4, 0.2);
-    let dark_spot = Vector3::new(0.7, 0.2, 0.05);
+    let [core_white, bright_yellow, deep_orange, hot_red, dark_spot, ..] = material.palette;
 
     // Mezcla de colores más dinámica
     let mut color = mix_color(bright_yellow, deep_orange, surface);
@@ -78,41 +155,45 @@ pub fn star_shader(fragment: &Fragment, uniforms: &Uniforms) -> Vector3 {
     
     // Brillo e iluminación intensificada
     let brightness = 1.6 + combined_pulse * 0.3; // Más brillante y con más variación
-    color = color * brightness + Vector3::new(0.25, 0.15, 0.02) * combined_pulse;
-
-    // Aplicar iluminación base
-    let lit_color = color * (fragment.color * 0.4 + Vector3::new(0.6, 0.6, 0.6));
-    lit_color
+    color * brightness + Vector3::new(0.25, 0.15, 0.02) * combined_pulse
 }
 
 
 /// Rocky planet shader - Mars-like with craters
-pub fn rocky_shader(fragment: &Fragment, uniforms: &Uniforms) -> Vector3 {
+pub fn rocky_shader(fragment: &Fragment, uniforms: &Uniforms, material: &Material) -> Vector3 {
     let pos = fragment.world_position;
-    let base_color = fragment.color;
 
-    // Use fast_fbm with only 3 octaves
-    let terrain = fbm(Vector3::new(pos.x * 3.0, pos.y * 3.0, pos.z * 3.0), 3, 2.0, 0.5);
+    // Use fast_fbm with only 3 octaves (baked when the noise cache is on)
+    let terrain = layer(uniforms, pos, &material.noise);
 
     // Cheaper crater pattern
-    let craters = voronoi(pos, 3.5);
+    let craters = uniforms.noise.voronoi(pos, 3.5);
+    rocky_surface(fragment, terrain, craters, material)
+}
+
+/// Rocky colors from the terrain and crater noise of a fragment
+fn rocky_surface(fragment: &Fragment, terrain: f32, craters: f32, material: &Material) -> Vector3 {
+    let base_color = fragment.color;
     let crater_mask = smoothstep(0.28, 0.48, craters);
 
-    let dark_rock = Vector3::new(0.25, 0.15, 0.10);
-    let mid_rock = Vector3::new(0.55, 0.35, 0.22);
-    let light_rock = Vector3::new(0.85, 0.65, 0.45);
+    let [dark_rock, mid_rock, light_rock, ..] = material.palette;
 
-    let mut color = mix_color(dark_rock, mid_rock, (terrain * 0.5 + 0.5));
+    let mut color = mix_color(dark_rock, mid_rock, terrain * 0.5 + 0.5);
     // avoid powf on terrain; replace with cheaper abs or multiply
-    color = mix_color(color, light_rock, (terrain.abs() * 0.8 + 0.2));
+    color = mix_color(color, light_rock, terrain.abs() * 0.8 + 0.2);
     color = mix_color(color, dark_rock * 0.9, 1.0 - crater_mask);
 
-    apply_lighting(color, base_color)
+    with_specular(apply_lighting(color, base_color), fragment, 1.0)
 }
 
 /// Gas giant shader - Jupiter-like with turbulent bands
-pub fn gas_giant_shader(fragment: &Fragment, uniforms: &Uniforms) -> Vector3 {
-    let pos = fragment.world_position;
+pub fn gas_giant_shader(fragment: &Fragment, uniforms: &Uniforms, material: &Material) -> Vector3 {
+    // The great storm winds everything sampled below around its center
+    let (pos, storm) = match uniforms.vortex {
+        Some(vortex) => vortex.swirl(fragment.world_position),
+        None => (fragment.world_position, 0.0),
+    };
+    let noise = &uniforms.noise;
     let base_color = fragment.color;
     let time = uniforms.time * 0.05;
 
@@ -120,14 +201,19 @@ pub fn gas_giant_shader(fragment: &Fragment, uniforms: &Uniforms) -> Vector3 {
     let band_pattern= (pos.y * 10.0 + time).sin() * 0.5 + 0.5;
 
     // cheap turbulence with fewer octaves
-    let turb = turbulence(Vector3::new(pos.x * 2.0 + time, pos.y * 4.0, pos.z * 2.0), 3);
-
-    let swirl = warp_noise(Vector3::new(pos.x + time * 0.5, pos.y * 2.0, pos.z), 0.5);
+    // 4D turbulence evolves in place instead of sliding with the bands (re-baked every second)
+    let turb = layer(uniforms, pos, &material.noise);
+
+    // Storms follow a divergence-free curl flow, so they swirl instead of smearing
+    let flow = noise.curl_noise(Vector3::new(pos.x * 1.5, pos.y * 3.0, pos.z * 1.5), time);
+    let advected = Vector3::new(
+        pos.x + flow.x * 0.15,
+        pos.y * 2.0 + flow.y * 0.15,
+        pos.z + flow.z * 0.15,
+    );
+    let swirl = noise.simplex_noise(advected) * 0.7;
 
-    let light_band = Vector3::new(0.95, 0.85, 0.7);
-    let mid_band = Vector3::new(0.85, 0.55, 0.35);
-    let dark_band = Vector3::new(0.55, 0.35, 0.25);
-    let storm_color = Vector3::new(1.0, 0.9, 0.85);
+    let [light_band, mid_band, dark_band, storm_color, ..] = material.palette;
 
     let mut color = mix_color(light_band, mid_band, band_pattern);
     color = mix_color(color, dark_band, turb * 0.35);
@@ -135,97 +221,238 @@ pub fn gas_giant_shader(fragment: &Fragment, uniforms: &Uniforms) -> Vector3 {
     let storm_mask = smoothstep(0.68, 0.82, swirl);
     color = mix_color(color, storm_color, storm_mask * 0.6);
 
-    apply_lighting(color, base_color)
+    if let Some(vortex) = uniforms.vortex {
+        // Pale collar around a red core streaked by the wound-up noise
+        let collar = smoothstep(0.0, 0.15, storm) * (1.0 - smoothstep(0.15, 0.35, storm));
+        color = mix_color(color, light_band, collar * 0.5);
+        let core = vortex.color * (0.85 + 0.3 * swirl);
+        color = mix_color(color, core, smoothstep(0.25, 0.6, storm) * 0.85);
+    }
+
+    with_specular(apply_lighting(color, base_color), fragment, 1.0)
 }
 
-/// Lava planet shader - Molten world
-pub fn lava_shader(fragment: &Fragment, uniforms: &Uniforms) -> Vector3 {
+/// Lava planet shader - Molten world: the lit crust, its glowing cracks coming from
+/// `lava_emission`
+pub fn lava_shader(fragment: &Fragment, uniforms: &Uniforms, material: &Material) -> Vector3 {
     let pos = fragment.world_position;
+    let noise = &uniforms.noise;
     let base_color = fragment.color;
     let time = uniforms.time * 0.3;
 
-    let lava_flow = warp_noise(Vector3::new(pos.x * 2.0, pos.y * 2.0 + time, pos.z * 2.0), 0.6);
+    let lava_flow = material.noise.sample(noise, pos, time);
 
-    // cracks: use ridged_noise but fewer octaves (2 or 3)
-    let cracks = ridged_noise(Vector3::new(pos.x * 5.0, pos.y * 5.0, pos.z * 5.0), 2);
+    let [dark_crust, hot_lava, ..] = material.palette;
 
-    let pulse = ((time * 2.0).sin() * 0.5 + 0.5) * 0.28;
+    let mut surface = mix_color(dark_crust, hot_lava, lava_flow * 0.5 + 0.5);
 
-    let dark_crust = Vector3::new(0.1, 0.05, 0.0);
-    let hot_lava = Vector3::new(1.0, 0.3, 0.0);
-    let bright_lava = Vector3::new(1.0, 0.8, 0.1);
+    // Lava pools in flat, low-lying valleys of the crust
+    let crust = noise.fbm_with_derivative(Vector3::new(pos.x * 1.5, pos.y * 1.5, pos.z * 1.5), 2, 2.0, 0.5);
+    let valley = 1.0 - smoothstep(-0.25, 0.05, crust.value);
+    let flat = 1.0 - smoothstep(0.3, 0.9, crust.slope(pos.normalized()));
+    surface = mix_color(surface, hot_lava, valley * flat * 0.8);
 
-    let mut color = mix_color(dark_crust, hot_lava, lava_flow * 0.5 + 0.5);
+    with_specular(apply_lighting(surface, base_color), fragment, 1.0)
+}
 
+/// Light a lava world gives off by itself, never touched by the lighting: the molten
+/// cracks, slowly pulsing, and the eruptions going on
+pub fn lava_emission(fragment: &Fragment, uniforms: &Uniforms, material: &Material) -> Vector3 {
+    let pos = fragment.world_position;
+    let time = uniforms.time * 0.3;
+
+    // cracks: use ridged_noise but fewer octaves (2 or 3)
+    let cracks = uniforms.noise.ridged_noise(Vector3::new(pos.x * 5.0, pos.y * 5.0, pos.z * 5.0), 2);
     let crack_mask = smoothstep(0.62, 0.72, cracks);
-    color = mix_color(color, bright_lava, crack_mask);
-    color = color + Vector3::new(pulse, pulse * 0.25, 0.0);
+    let pulse = 0.85 + 0.15 * (time * 2.0).sin();
 
-    color * (base_color * 0.5 + Vector3::new(0.5, 0.5, 0.5))
+    material.palette[2] * (crack_mask * pulse) + eruption_glow(fragment, uniforms)
+}
+
+/// Light of the eruptions going on around a fragment (`Uniforms::eruptions`): a white-hot
+/// vent inside a wider red glow, both fading as the eruption cools; zero elsewhere
+pub fn eruption_glow(fragment: &Fragment, uniforms: &Uniforms) -> Vector3 {
+    let dir = fragment.world_position.normalized();
+    let mut glow = Vector3::zero();
+    for eruption in uniforms.eruptions {
+        // 1 - cos of the angle to the vent, about half its square
+        let distance = 1.0 - dir.dot(eruption.site);
+        if distance > 0.05 {
+            continue;
+        }
+        let heat = eruption.heat();
+        let vent = 1.0 - smoothstep(0.0, 0.012, distance);
+        let halo = 1.0 - smoothstep(0.0, 0.05, distance);
+        glow += Vector3::new(1.0, 0.85, 0.4) * (vent * heat * 1.5) + Vector3::new(1.0, 0.3, 0.0) * (halo * heat * 0.6);
+    }
+    glow
 }
 
 /// Ice world shader - Frozen planet
-pub fn ice_shader(fragment: &Fragment, uniforms: &Uniforms) -> Vector3 {
+pub fn ice_shader(fragment: &Fragment, uniforms: &Uniforms, material: &Material) -> Vector3 {
     let pos = fragment.world_position;
+    let noise = &uniforms.noise;
     let base_color = fragment.color;
     let time = uniforms.time * 0.02;
 
-    let crystals = voronoi(pos, 4.0);
-    let snow = fbm(Vector3::new(pos.x * 8.0, pos.y * 8.0, pos.z * 8.0), 3, 2.0, 0.5);
-    let frost = simplex_noise(Vector3::new(pos.x * 12.0 + time, pos.y * 12.0, pos.z * 12.0));
+    let crystals = noise.worley(pos, 4.0);
+    let snow = layer(uniforms, pos, &material.noise);
+    let frost = noise.simplex_noise(Vector3::new(pos.x * 12.0 + time, pos.y * 12.0, pos.z * 12.0));
 
-    let deep_ice = Vector3::new(0.3, 0.6, 0.9);
-    let surface_ice = Vector3::new(0.8, 0.9, 1.0);
-    let bright_snow = Vector3::new(1.0, 1.0, 1.0);
+    let [deep_ice, surface_ice, bright_snow, ..] = material.palette;
 
     let mut color = mix_color(deep_ice, surface_ice, snow * 0.45 + 0.55);
-    let sparkle_mask = smoothstep(0.78, 0.88, crystals);
+
+    // Fresh snow settles on flat ground and slides off steep slopes
+    let relief = noise.simplex_noise_with_derivative(Vector3::new(pos.x * 3.0, pos.y * 3.0, pos.z * 3.0));
+    let flatness = 1.0 - smoothstep(0.6, 1.6, relief.slope(pos.normalized()));
+    color = mix_color(color, bright_snow, flatness * 0.35);
+    // Crystal facets sparkle along the cell borders (F2 - F1 close to zero)
+    let sparkle_mask = 1.0 - smoothstep(0.02, 0.1, crystals.edge());
     color = mix_color(color, bright_snow, sparkle_mask * (frost * 0.5 + 0.5) * 1.1);
 
     let lit_color = color * (base_color + Vector3::new(0.18, 0.22, 0.28));
-    normalize_intensity(lit_color, base_color, 0.18)
+    with_specular(normalize_intensity(lit_color, base_color, 0.18), fragment, 1.0)
 }
 
 /// Cloud planet shader - Earth-like
-pub fn cloud_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Vector3 {
+pub fn cloud_planet_shader(fragment: &Fragment, uniforms: &Uniforms, material: &Material) -> Vector3 {
     let pos = fragment.world_position;
+    let noise = &uniforms.noise;
     let base_color = fragment.color;
     let time = uniforms.time * 0.1;
     
-    // Continents vs oceans
-    let land_mask = fbm_simplex(
-        Vector3::new(pos.x * 2.0, pos.y * 2.0, pos.z * 2.0),
-        6, 2.0, 0.5
-    );
+    // Continents vs oceans: baked low octaves plus the two finest ones evaluated live
+    let land_mask = layer(uniforms, pos, &material.noise)
+        + noise.fbm_simplex(
+            Vector3::new(pos.x * 32.0, pos.y * 32.0, pos.z * 32.0),
+            2, 2.0, 0.5
+        ) * 0.0625;
     
     // Cloud layer
-    let clouds = fbm_simplex(
+    let clouds = noise.fbm_simplex(
         Vector3::new(pos.x * 4.0 + time, pos.y * 4.0, pos.z * 4.0),
         4, 2.0, 0.6
     );
     
     // Vegetation detail
-    let vegetation = simplex_noise(
+    let vegetation = noise.simplex_noise(
         Vector3::new(pos.x * 10.0, pos.y * 10.0, pos.z * 10.0)
     );
     
     // Colors
-    let ocean = Vector3::new(0.1, 0.3, 0.6);
-    let land = Vector3::new(0.4, 0.5, 0.3);
-    let forest = Vector3::new(0.2, 0.4, 0.2);
-    let cloud_color = Vector3::new(1.0, 1.0, 1.0);
+    let [ocean, cloud_color, polar_ice, ..] = material.palette;
     
     let surface_threshold = 0.0;
-    let mut color = if land_mask > surface_threshold {
-        mix_color(land, forest, vegetation * 0.5 + 0.5)
+    let is_land = land_mask > surface_threshold;
+    let climate = latitude_climate(noise, pos);
+    let mut color = if is_land {
+        land_biome(&climate, vegetation, material)
     } else {
         ocean
     };
+    // Ice caps spread further over land than over the sea
+    let ice = if is_land { climate.land_ice } else { climate.sea_ice };
+    color = mix_color(color, polar_ice, ice);
     
     let cloud_mask = smoothstep(0.3, 0.5, clouds);
     color = mix_color(color, cloud_color, cloud_mask * 0.7);
     
-    apply_lighting(color, base_color)
+    // Oceans mirror the sun, land barely does and clouds hide both
+    let gloss = if is_land { 0.15 } else { 1.0 - ice * 0.6 } * (1.0 - cloud_mask * 0.7);
+    let lit = with_specular(apply_lighting(color, base_color), fragment, gloss);
+    lit + city_lights(fragment, uniforms, land_mask) * ((1.0 - cloud_mask * 0.8) * (1.0 - ice))
+}
+
+/// Climate bands of an earth-like planet, from the latitude of the model-space position
+/// (the body's `axial_tilt` leans them with its spin axis)
+struct Climate {
+    /// Polar ice over land and over the sea, 0..1
+    land_ice: f32,
+    sea_ice: f32,
+    /// Dry subtropical belt, 0..1
+    desert: f32,
+    /// Lush equatorial belt, 0..1
+    tropics: f32,
+}
+
+fn latitude_climate(noise: &Noise, pos: Vector3) -> Climate {
+    // Sine of the latitude, its band edges wobbled by noise so they don't look ruled
+    let wobble = noise.simplex_noise(Vector3::new(pos.x * 3.0 + 11.0, pos.y * 3.0, pos.z * 3.0)) * 0.08;
+    let latitude = pos.normalized().y.abs() + wobble;
+    Climate {
+        land_ice: smoothstep(0.78, 0.84, latitude),
+        sea_ice: smoothstep(0.88, 0.93, latitude),
+        desert: smoothstep(0.2, 0.3, latitude) * (1.0 - smoothstep(0.42, 0.55, latitude)),
+        tropics: 1.0 - smoothstep(0.12, 0.25, latitude),
+    }
+}
+
+/// Land color of the band: forests in the tropics, sand in the desert belt and mixed
+/// grassland and forest in the temperate zones
+fn land_biome(climate: &Climate, vegetation: f32, material: &Material) -> Vector3 {
+    let [_, _, _, land, forest, jungle, sand, ..] = material.palette;
+
+    let mut color = mix_color(land, forest, vegetation * 0.5 + 0.5);
+    color = mix_color(color, jungle, climate.tropics * (vegetation * 0.3 + 0.7));
+    mix_color(color, sand, climate.desert * (0.85 - vegetation * 0.15))
+}
+
+/// Warm speckles of city light on the land of the night side; nothing without a light
+/// direction to tell day from night (baked surfaces)
+fn city_lights(fragment: &Fragment, uniforms: &Uniforms, land_mask: f32) -> Vector3 {
+    if uniforms.light_direction == Vector3::zero() || land_mask <= 0.0 {
+        return Vector3::zero();
+    }
+    // Fades in past the terminator so dusk keeps a few lights on
+    let night = smoothstep(0.05, -0.2, fragment.normal.normalized().dot(uniforms.light_direction));
+    if night <= 0.0 {
+        return Vector3::zero();
+    }
+    let pos = fragment.world_position;
+    // Thresholded high-frequency noise, thinned out towards the coasts
+    let speckle = uniforms.noise.simplex_noise(Vector3::new(pos.x * 60.0, pos.y * 60.0, pos.z * 60.0));
+    let cities = smoothstep(0.55, 0.75, speckle) * smoothstep(0.0, 0.08, land_mask);
+    Vector3::new(1.0, 0.75, 0.4) * (cities * night * 0.9)
+}
+
+/// Ring shader - concentric bands of ice and dust with darker gaps
+pub fn ring_shader(fragment: &Fragment, uniforms: &Uniforms, material: &Material) -> Vector3 {
+    let pos = fragment.world_position;
+    let noise = &uniforms.noise;
+    let base_color = fragment.color;
+
+    let radius = (pos.x * pos.x + pos.z * pos.z).sqrt();
+    let bands = ring_bands(noise, pos, material);
+    let grain = noise.simplex_noise(Vector3::new(radius * 40.0, 0.0, 0.0));
+
+    let [dust, ice, gap, ..] = material.palette;
+
+    let mut color = mix_color(dust, ice, bands * 0.5 + 0.5 + grain * 0.1);
+    // Sparse bands read as divisions between the rings
+    let density = smoothstep(-0.45, -0.25, bands);
+    color = mix_color(gap, color, density);
+
+    with_specular(apply_lighting(color, base_color), fragment, density)
+}
+
+/// Band noise of the rings; everything depends on the distance to the center, so the
+/// bands stay circular
+fn ring_bands(noise: &Noise, pos: Vector3, material: &Material) -> f32 {
+    let radius = (pos.x * pos.x + pos.z * pos.z).sqrt();
+    material.noise.sample(noise, Vector3::new(radius, 0.0, 0.0), 0.0)
+}
+
+/// Coverage a surface gives its fragments when the body is drawn translucent: ring
+/// divisions let most of the light behind them through, other shaders cover fully
+pub fn shader_alpha(fragment: &Fragment, uniforms: &Uniforms, material: &Material) -> f32 {
+    match material.shader {
+        ShaderType::Rings => {
+            let density = smoothstep(-0.45, -0.25, ring_bands(&uniforms.noise, fragment.world_position, material));
+            0.15 + 0.8 * density
+        }
+        _ => 1.0,
+    }
 }
 
 /// Apply standard lighting
@@ -238,6 +465,12 @@ fn apply_lighting(color: Vector3, base_color: Vector3) -> Vector3 {
     normalize_intensity(lit_color, base_color, 0.0)
 }
 
+/// Adds the fragment's specular highlight, scaled by how glossy this spot is
+#[inline]
+fn with_specular(color: Vector3, fragment: &Fragment, gloss: f32) -> Vector3 {
+    color + fragment.specular * gloss
+}
+
 /// Normalize intensity to preserve brightness
 fn normalize_intensity(lit_color: Vector3, base_color: Vector3, boost: f32) -> Vector3 {
     let original_intensity = (base_color.x + base_color.y + base_color.z) / 3.0 + boost;
@@ -250,18 +483,38 @@ fn normalize_intensity(lit_color: Vector3, base_color: Vector3, boost: f32) -> V
     }
 }
 
-/// Main shader dispatcher
+/// Main shader dispatcher: the material's shader, drawing with its palette and noise
 pub fn apply_shader(
     fragment: &Fragment,
     uniforms: &Uniforms,
-    shader_type: ShaderType,
+    material: &Material,
 ) -> Vector3 {
-    match shader_type {
-        ShaderType::Star => star_shader(fragment, uniforms),
-        ShaderType::Rocky => rocky_shader(fragment, uniforms),
-        ShaderType::GasGiant => gas_giant_shader(fragment, uniforms),
-        ShaderType::Lava => lava_shader(fragment, uniforms),
-        ShaderType::IceWorld => ice_shader(fragment, uniforms),
-        ShaderType::CloudPlanet => cloud_planet_shader(fragment, uniforms),
+    material.shade(fragment, uniforms)
+}
+
+/// `apply_shader` over a chunk of at most `LANES` fragments of one object: the rocky
+/// shader evaluates its fBm terrain for the whole chunk at once, the others shade
+/// fragment by fragment; lanes past the chunk's length are left black
+pub fn apply_shader_lanes(fragments: &[Fragment], uniforms: &Uniforms, material: &Material) -> [Vector3; LANES] {
+    let mut colors = [Vector3::zero(); LANES];
+    let batched = material.shader == ShaderType::Rocky && material.noise.kind == NoiseKind::Fbm;
+    if !batched || fragments.len() < 2 {
+        for (color, fragment) in colors.iter_mut().zip(fragments) {
+            *color = apply_shader(fragment, uniforms, material);
+        }
+        return colors;
+    }
+
+    // Short chunks repeat their last fragment
+    let NoiseLayer { scale, octaves, lacunarity, gain, .. } = material.noise;
+    let positions: [Vector3; LANES] = std::array::from_fn(|l| fragments[l.min(fragments.len() - 1)].world_position);
+    let terrain = match uniforms.baked {
+        Some(baked) => positions.map(|pos| baked.sample(pos)),
+        None => uniforms.noise.fbm_lanes(&positions.map(|pos| Vector3::new(pos.x * scale.x, pos.y * scale.y, pos.z * scale.z)), octaves, lacunarity, gain),
+    };
+    let craters = uniforms.noise.voronoi_lanes(&positions, 3.5);
+    for (l, fragment) in fragments.iter().enumerate() {
+        colors[l] = rocky_surface(fragment, terrain[l], craters[l], material);
     }
+    colors
 }
\ No newline at end of file
@@ -1,5 +1,6 @@
 // shaders.rs
-// Basic vertex shader (planet shaders are in planet_shaders.rs)
+// Basic vertex shader (the planet fragment shaders are in shader_system.rs, the looks they
+// draw in material.rs)
 
 use raylib::prelude::*;
 use crate::vertex::Vertex;
@@ -69,7 +70,10 @@ pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
         normal: vertex.normal,
         tex_coords: vertex.tex_coords,
         color: vertex.color,
+        emission: vertex.emission,
         transformed_position,
+        world_position: Vector3::new(world_position.x, world_position.y, world_position.z),
         transformed_normal: transform_normal(&vertex.normal, &uniforms.model_matrix),
+        clip_position,
     }
 }
\ No newline at end of file
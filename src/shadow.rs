@@ -0,0 +1,91 @@
+// shadow.rs
+// Analytic sphere-occlusion shadows between celestial bodies
+
+use raylib::prelude::*;
+use crate::light::Light;
+
+/// Bounding sphere of a body that can block light
+#[derive(Clone, Copy, Debug)]
+pub struct Occluder {
+    pub center: Vector3,
+    pub radius: f32,
+}
+
+impl Occluder {
+    /// Creates an occluder sphere in world space
+    pub fn new(center: Vector3, radius: f32) -> Self {
+        Occluder { center, radius }
+    }
+}
+
+#[inline]
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Fraction of the light disc hidden by an occluder disc, as seen from the point
+/// Angles are angular radii of both discs and the angular distance between their centers
+fn disc_coverage(light_angle: f32, occluder_angle: f32, separation: f32) -> f32 {
+    let outer = light_angle + occluder_angle;
+    if separation >= outer {
+        return 0.0;
+    }
+
+    // Full overlap: total eclipse, or an annular one when the occluder looks smaller
+    let full = if occluder_angle >= light_angle {
+        1.0
+    } else {
+        (occluder_angle / light_angle) * (occluder_angle / light_angle)
+    };
+
+    let inner = (light_angle - occluder_angle).abs();
+    if separation <= inner {
+        return full;
+    }
+
+    // Penumbra: fade between partial contact and full overlap
+    full * (1.0 - smoothstep(inner, outer, separation))
+}
+
+/// Returns how much of a light reaches a world-space point (0 = fully shadowed, 1 = fully lit)
+/// Occluders should not include the body the point belongs to
+pub fn light_visibility(point: Vector3, light: &Light, occluders: &[Occluder]) -> f32 {
+    let to_light = light.position - point;
+    let light_distance = to_light.length();
+    if light_distance < 1e-6 {
+        return 1.0;
+    }
+    let light_dir = to_light / light_distance;
+    let light_angle = (light.radius / light_distance).min(1.0).asin();
+
+    let mut visibility = 1.0;
+    for occluder in occluders {
+        // Skip the body emitting this light
+        if occluder.center.distance_to(light.position) < occluder.radius {
+            continue;
+        }
+
+        // Occluder must lie between the point and the light
+        let to_occluder = occluder.center - point;
+        let along = to_occluder.dot(light_dir);
+        if along <= 0.0 || along - occluder.radius >= light_distance {
+            continue;
+        }
+
+        let occluder_distance = to_occluder.length();
+        if occluder_distance <= occluder.radius {
+            continue;
+        }
+
+        let occluder_angle = (occluder.radius / occluder_distance).asin();
+        let separation = (along / occluder_distance).clamp(-1.0, 1.0).acos();
+        visibility *= 1.0 - disc_coverage(light_angle, occluder_angle, separation);
+
+        if visibility <= 0.0 {
+            return 0.0;
+        }
+    }
+
+    visibility
+}
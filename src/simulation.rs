@@ -0,0 +1,271 @@
+// simulation.rs
+// Orbital simulation that runs either inline with the render loop or on its own thread
+// at a fixed rate, publishing double-buffered snapshots of the solar system
+
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use crate::collision::Collision;
+use crate::solar_system::SolarSystem;
+
+/// Ticks the worker may fall behind before it drops them instead of catching up
+const MAX_LAG_TICKS: u32 = 5;
+
+/// Simulation speeds the speed keys step through, in simulated seconds per second
+pub const TIME_SCALES: [f32; 13] = [0.1, 0.25, 0.5, 1.0, 2.0, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0];
+
+/// The step of `TIME_SCALES` after `current`, or before it when not `faster`; a speed
+/// between steps (set on the panel) goes to the nearest one in that direction, and the
+/// ends of the range are kept
+pub fn next_time_scale(current: f32, faster: bool) -> f32 {
+    if faster {
+        TIME_SCALES.into_iter().find(|&scale| scale > current * 1.001).unwrap_or(TIME_SCALES[TIME_SCALES.len() - 1])
+    } else {
+        TIME_SCALES.into_iter().rev().find(|&scale| scale < current * 0.999).unwrap_or(TIME_SCALES[0])
+    }
+}
+
+/// Messages from the render loop to the simulation thread
+enum Command {
+    /// Replaces the simulated system; the id tags the snapshots of the new scene
+    Load(SolarSystem, u64),
+    TimeScale(f32),
+    Paused(bool),
+    /// Advances by the given (unscaled) time even while paused
+    Step(f32),
+    /// Jumps to an absolute simulated time
+    SetTime(f32),
+    Stop,
+}
+
+/// Latest finished state of the simulation thread
+struct Snapshot {
+    system: SolarSystem,
+    scene_id: u64,
+    generation: u64,
+}
+
+struct Worker {
+    commands: Sender<Command>,
+    front: Arc<Mutex<Snapshot>>,
+    handle: Option<JoinHandle<()>>,
+    /// Generation of the snapshot currently copied into the view
+    generation: u64,
+}
+
+/// Owner of the simulated solar system
+///
+/// Inline simulations advance only when `advance` or `step` is called, which keeps
+/// them deterministic for recording and replays. Threaded ones tick on their own
+/// at a fixed rate; the render loop reads the latest snapshot with `scene`
+pub struct Simulation {
+    /// Copy of the system the render loop draws
+    view: SolarSystem,
+    time_scale: f32,
+    paused: bool,
+    scene_id: u64,
+    worker: Option<Worker>,
+}
+
+impl Simulation {
+    /// Simulation advanced by the caller, on the caller's thread
+    pub fn inline(system: SolarSystem) -> Self {
+        Simulation {
+            view: system,
+            time_scale: 1.0,
+            paused: false,
+            scene_id: 0,
+            worker: None,
+        }
+    }
+
+    /// Simulation running on its own thread at `rate` ticks per second
+    pub fn threaded(system: SolarSystem, rate: f32) -> Self {
+        let (commands, receiver) = mpsc::channel();
+        let front = Arc::new(Mutex::new(Snapshot {
+            system: system.clone(),
+            scene_id: 0,
+            generation: 0,
+        }));
+
+        let shared = Arc::clone(&front);
+        let back = system.clone();
+        let handle = thread::Builder::new()
+            .name("simulation".to_string())
+            .spawn(move || run(back, receiver, shared, 1.0 / rate))
+            .expect("failed to spawn the simulation thread");
+
+        Simulation {
+            view: system,
+            time_scale: 1.0,
+            paused: false,
+            scene_id: 0,
+            worker: Some(Worker {
+                commands,
+                front,
+                handle: Some(handle),
+                generation: 0,
+            }),
+        }
+    }
+
+    pub fn is_threaded(&self) -> bool {
+        self.worker.is_some()
+    }
+
+    /// Replaces the simulated system
+    pub fn load(&mut self, system: SolarSystem) {
+        self.scene_id += 1;
+        if let Some(worker) = &self.worker {
+            worker.send(Command::Load(system.clone(), self.scene_id));
+        }
+        self.view = system;
+    }
+
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        if time_scale != self.time_scale {
+            self.time_scale = time_scale;
+            if let Some(worker) = &self.worker {
+                worker.send(Command::TimeScale(time_scale));
+            }
+        }
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        if let Some(worker) = &self.worker {
+            worker.send(Command::Paused(paused));
+        }
+    }
+
+    /// Advances by `dt` seconds (scaled by the time scale) even while paused
+    pub fn step(&mut self, dt: f32) {
+        match &self.worker {
+            Some(worker) => worker.send(Command::Step(dt)),
+            None => report(self.view.advance(dt * self.time_scale)),
+        }
+    }
+
+    /// Moves the system to an absolute simulated time (scrubbing the timeline)
+    pub fn set_time(&mut self, time: f32) {
+        match &self.worker {
+            Some(worker) => worker.send(Command::SetTime(time)),
+            None => self.view.set_time(time),
+        }
+    }
+
+    /// Called once per rendered frame; an inline simulation advances by the frame
+    /// time unless paused, a threaded one keeps its own clock and ignores it
+    pub fn advance(&mut self, frame_time: f32) {
+        if self.worker.is_none() && !self.paused {
+            report(self.view.advance(frame_time * self.time_scale));
+        }
+    }
+
+    /// The simulated system, for scripts to change between frames; only an inline
+    /// simulation owns it on this thread, a threaded one returns None
+    pub fn scene_mut(&mut self) -> Option<&mut SolarSystem> {
+        match self.worker {
+            Some(_) => None,
+            None => Some(&mut self.view),
+        }
+    }
+
+    /// Latest state of the simulated system
+    pub fn scene(&mut self) -> &SolarSystem {
+        if let Some(worker) = &mut self.worker {
+            let front = worker.front.lock().expect("simulation thread panicked");
+            // Snapshots of a scene replaced since are stale
+            if front.generation != worker.generation && front.scene_id == self.scene_id {
+                self.view.clone_from(&front.system);
+                worker.generation = front.generation;
+            }
+        }
+        &self.view
+    }
+}
+
+impl Worker {
+    fn send(&self, command: Command) {
+        // Only fails if the thread is gone, which `scene` reports on the next frame
+        let _ = self.commands.send(command);
+    }
+}
+
+impl Drop for Simulation {
+    fn drop(&mut self) {
+        if let Some(worker) = &mut self.worker {
+            worker.send(Command::Stop);
+            if let Some(handle) = worker.handle.take() {
+                let _ = handle.join();
+            }
+        }
+    }
+}
+
+/// Logs the collisions of a simulation step
+fn report(collisions: Vec<Collision>) {
+    for collision in collisions {
+        log::info!("{collision}");
+    }
+}
+
+/// Copies the back buffer into the shared front buffer
+fn publish(back: &SolarSystem, scene_id: u64, front: &Mutex<Snapshot>) {
+    let mut front = front.lock().expect("render thread panicked");
+    front.system.clone_from(back);
+    front.scene_id = scene_id;
+    front.generation += 1;
+}
+
+/// Body of the simulation thread
+fn run(mut back: SolarSystem, commands: Receiver<Command>, front: Arc<Mutex<Snapshot>>, tick: f32) {
+    let tick_duration = Duration::from_secs_f32(tick);
+    let mut scene_id = 0;
+    let mut time_scale = 1.0;
+    let mut paused = false;
+    let mut next_tick = Instant::now();
+    log::debug!("simulation thread running at {:.0} Hz", 1.0 / tick);
+
+    loop {
+        loop {
+            match commands.try_recv() {
+                Ok(Command::Load(system, id)) => {
+                    back = system;
+                    scene_id = id;
+                    publish(&back, scene_id, &front);
+                }
+                Ok(Command::TimeScale(scale)) => time_scale = scale,
+                Ok(Command::Paused(state)) => paused = state,
+                Ok(Command::Step(dt)) => {
+                    report(back.advance(dt * time_scale));
+                    publish(&back, scene_id, &front);
+                }
+                Ok(Command::SetTime(time)) => {
+                    back.set_time(time);
+                    publish(&back, scene_id, &front);
+                }
+                Ok(Command::Stop) | Err(TryRecvError::Disconnected) => return,
+                Err(TryRecvError::Empty) => break,
+            }
+        }
+
+        if !paused {
+            report(back.advance(tick * time_scale));
+            publish(&back, scene_id, &front);
+        }
+
+        next_tick += tick_duration;
+        let now = Instant::now();
+        if next_tick > now {
+            thread::sleep(next_tick - now);
+        } else if now - next_tick > tick_duration * MAX_LAG_TICKS {
+            next_tick = now;
+        }
+    }
+}
@@ -2,8 +2,26 @@
 // Solar system scene with multiple celestial objects
 
 use raylib::prelude::*;
-use crate::shader_system::ShaderType;
+use crate::asteroid_belt::AsteroidBelt;
+use crate::atmosphere::Atmosphere;
+use crate::aurora::Aurora;
+use crate::collision::{Collision, CollisionResponse};
+use crate::gravity::{Motion, GRAVITY};
+use crate::fragment::{BlendMode, Fragment};
+use crate::gltf::GltfModel;
+use crate::shader_system::{shader_alpha, ShaderType};
+use crate::light::Light;
+use crate::material::Material;
+use crate::matrix::create_model_matrix;
+use crate::mesh::generate_ring;
+use crate::obj::Obj;
+use crate::particles::ParticleSystem;
+use crate::shadow::Occluder;
+use crate::storm::Vortex;
+use crate::volcano::{Eruption, Volcanoes};
 use std::f32::consts::PI;
+use std::sync::Arc;
+use crate::Uniforms;
 
 /// Celestial object types
 #[derive(Clone, Copy, PartialEq, Debug)]
@@ -11,37 +29,194 @@ pub enum CelestialType {
     Star,
     Planet,
     Moon,
+    Ring,
+    /// Small body whose particle tail streams away from its star
+    Comet,
+}
+
+/// Slices of the generated ring meshes
+const RING_SEGMENTS: u32 = 128;
+
+/// Flat ring around a body, drawn with its own mesh instead of the shared sphere
+#[derive(Clone, Debug)]
+pub struct Ring {
+    /// Radii in model units (the ring's scale applies on top)
+    pub inner: f32,
+    pub outer: f32,
+    /// Annulus mesh, shared by clones of the object
+    pub mesh: Arc<Obj>,
+}
+
+impl Ring {
+    /// Generates the mesh of a ring between two radii
+    pub fn new(inner: f32, outer: f32) -> Self {
+        Ring { inner, outer, mesh: Arc::new(generate_ring(inner, outer, RING_SEGMENTS)) }
+    }
+}
+
+/// Shape and orientation of an orbit; its size is the object's `orbit_radius` (the
+/// semi-major axis) and the object's `orbit_angle` is the mean anomaly, which grows
+/// linearly with time. All zero is a circle in the parent's XZ plane
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Orbit {
+    /// 0 for a circle, up to 0.95 for a long ellipse
+    pub eccentricity: f32,
+    /// Tilt of the orbital plane from XZ, in radians
+    pub inclination: f32,
+    /// Angle from the ascending node to the periapsis (closest point), in radians
+    pub periapsis: f32,
+    /// Longitude of the ascending node, where the orbit rises through XZ, in radians
+    pub ascending_node: f32,
+}
+
+impl Orbit {
+    /// Largest eccentricity kept by `new`; closer to 1 the ellipse degenerates into a line
+    pub const MAX_ECCENTRICITY: f32 = 0.95;
+
+    pub fn new(eccentricity: f32, inclination: f32, periapsis: f32, ascending_node: f32) -> Self {
+        Orbit {
+            eccentricity: eccentricity.clamp(0.0, Self::MAX_ECCENTRICITY),
+            inclination,
+            periapsis,
+            ascending_node,
+        }
+    }
+
+    /// Offset from the parent of a body on this orbit with semi-major axis `radius` at
+    /// mean anomaly `mean_anomaly`
+    pub fn offset(&self, radius: f32, mean_anomaly: f32) -> Vector3 {
+        let e = self.eccentricity.clamp(0.0, Self::MAX_ECCENTRICITY);
+
+        // Kepler's equation M = E - e sin E, solved for the eccentric anomaly by Newton's method
+        let mut anomaly = mean_anomaly;
+        if e > 0.0 {
+            for _ in 0..8 {
+                anomaly -= (anomaly - e * anomaly.sin() - mean_anomaly) / (1.0 - e * anomaly.cos());
+            }
+        }
+
+        // In the orbital plane, periapsis along +X
+        let x = radius * (anomaly.cos() - e);
+        let z = radius * (1.0 - e * e).sqrt() * anomaly.sin();
+
+        // Turn the periapsis within the plane, tilt the plane about the node line, then
+        // turn the node line around the parent's Y axis
+        let (x, z) = rotate_xz(x, z, self.periapsis);
+        let (y, z) = (z * self.inclination.sin(), z * self.inclination.cos());
+        let (x, z) = rotate_xz(x, z, self.ascending_node);
+        Vector3::new(x, y, z)
+    }
+}
+
+/// Rotates a point of the XZ plane by `angle`, in the direction orbits advance
+fn rotate_xz(x: f32, z: f32, angle: f32) -> (f32, f32) {
+    let (sin, cos) = angle.sin_cos();
+    (x * cos - z * sin, x * sin + z * cos)
+}
+
+/// Stellar spectral classes (hottest to coolest)
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SpectralClass {
+    O,
+    B,
+    A,
+    F,
+    G,
+    K,
+    M,
+}
+
+impl SpectralClass {
+    /// Approximate emitted light color for this class
+    pub fn color(&self) -> Vector3 {
+        match self {
+            SpectralClass::O => Vector3::new(0.61, 0.69, 1.0),
+            SpectralClass::B => Vector3::new(0.67, 0.75, 1.0),
+            SpectralClass::A => Vector3::new(0.79, 0.84, 1.0),
+            SpectralClass::F => Vector3::new(0.97, 0.97, 1.0),
+            SpectralClass::G => Vector3::new(1.0, 0.96, 0.92),
+            SpectralClass::K => Vector3::new(1.0, 0.82, 0.63),
+            SpectralClass::M => Vector3::new(1.0, 0.8, 0.44),
+        }
+    }
 }
 
 /// Represents a celestial object
 #[derive(Clone, Debug)]
 pub struct CelestialObject {
+    /// Name shown in labels and used by scene files to refer to the body; may be empty
+    pub name: String,
+    /// Identity of the body in its system, kept while other bodies are removed and their
+    /// indices shift (set by `SolarSystem::add`, 0 for a body not added to one)
+    pub id: u32,
     pub object_type: CelestialType,
-    pub shader_type: ShaderType,
     pub position: Vector3,
     pub scale: f32,
     pub rotation: Vector3,
     pub rotation_speed: Vector3,
+    pub axial_tilt: f32,                // Lean of the spin axis in radians, tilting the climate bands
     pub orbit_radius: f32,
     pub orbit_speed: f32,
     pub orbit_angle: f32,
+    pub seeded_angle: bool,             // Starting orbit angle derived from the system seed
+    pub orbit: Orbit,                   // Eccentricity and orientation of the orbit
     pub parent_index: Option<usize>,
+    pub spectral_class: SpectralClass, // Only meaningful for stars
+    pub luminosity: f32,                // Light output of a star, scaled by its size
+    pub falloff: f32,                   // Distance falloff of a star's light (0: none)
+    pub material: Material,             // Shader, palette, noise layer and lighting of the surface
+    pub seed: u32,                      // Noise seed for this body's surface
+    pub ring: Option<Ring>,             // Shape of ring objects, None for spheres
+    pub model: Option<Arc<GltfModel>>,  // Loaded model drawn instead of the sphere
+    pub atmosphere: Option<Atmosphere>, // Glowing rim of air over the surface
+    pub aurora: Option<Aurora>,         // Polar lights over the surface
+    pub blend: BlendMode,               // Opaque, or how the translucent body is blended
+    pub opacity: f32,                   // Coverage of translucent bodies, 0..1
+    pub debris: bool,                   // Fragment of a collision, passes through bodies
+    pub mass: Option<f32>,              // Gravity mode mass, None to derive it from the size
+    pub velocity: Vector3,              // World velocity in the gravity mode
+    pub launch_velocity: Option<Vector3>, // Starting velocity relative to the parent, None for a circular orbit
+    pub tail: Option<ParticleSystem>,   // Particles streaming away from the star (comets)
+    pub volcanoes: Option<Volcanoes>,   // Eruptions bursting out of the surface (lava planets)
+    pub vortex: Option<Vortex>,         // Great storm drifting around a band (gas giants)
 }
 
 impl CelestialObject {
     /// Create a new star
     pub fn star(scale: f32) -> Self {
         CelestialObject {
+            name: String::new(),
+            id: 0,
             object_type: CelestialType::Star,
-            shader_type: ShaderType::Star,
             position: Vector3::zero(),
             scale,
             rotation: Vector3::zero(),
             rotation_speed: Vector3::new(0.0, 0.02, 0.0),
+            axial_tilt: 0.0,
             orbit_radius: 0.0,
             orbit_speed: 0.0,
             orbit_angle: 0.0,
+            seeded_angle: false,
+            orbit: Orbit::default(),
             parent_index: None,
+            spectral_class: SpectralClass::G,
+            luminosity: 1.0,
+            falloff: 0.0,
+            material: ShaderType::Star.material(),
+            seed: 0,
+            ring: None,
+            model: None,
+            atmosphere: None,
+            aurora: None,
+            blend: BlendMode::Opaque,
+            opacity: 1.0,
+            debris: false,
+            mass: None,
+            velocity: Vector3::zero(),
+            launch_velocity: None,
+            tail: None,
+            volcanoes: None,
+            vortex: None,
         }
     }
     
@@ -54,16 +229,38 @@ impl CelestialObject {
         shader: ShaderType,
     ) -> Self {
         CelestialObject {
+            name: String::new(),
+            id: 0,
             object_type: CelestialType::Planet,
-            shader_type: shader,
             position: Vector3::new(orbit_radius, 0.0, 0.0),
             scale,
             rotation: Vector3::zero(),
             rotation_speed: Vector3::new(0.0, 0.03, 0.0),
+            axial_tilt: 0.0,
             orbit_radius,
             orbit_speed,
-            orbit_angle: (rand::random::<f32>() * PI * 2.0), // Ángulo inicial aleatorio para desincronizar
+            orbit_angle: 0.0,
+            seeded_angle: true, // Ángulo inicial según la semilla para desincronizar
+            orbit: Orbit::default(),
             parent_index: Some(parent_idx),
+            spectral_class: SpectralClass::G,
+            luminosity: 1.0,
+            falloff: 0.0,
+            material: shader.material(),
+            seed: 0,
+            ring: None,
+            model: None,
+            atmosphere: None,
+            aurora: None,
+            blend: BlendMode::Opaque,
+            opacity: 1.0,
+            debris: false,
+            mass: None,
+            velocity: Vector3::zero(),
+            launch_velocity: None,
+            tail: None,
+            volcanoes: (shader == ShaderType::Lava).then(Volcanoes::default),
+            vortex: (shader == ShaderType::GasGiant).then(Vortex::default),
         }
     }
     
@@ -79,10 +276,117 @@ impl CelestialObject {
         moon.object_type = CelestialType::Moon;
         moon.rotation_speed = Vector3::new(0.0, 0.05, 0.0);
         moon.orbit_angle = 0.0;
+        moon.seeded_angle = false;
         moon.orbit_speed *= 2.0; // Las lunas orbitan más rápido que los planetas
         moon
     }
     
+    /// Makes the orbit elliptical and tilted, e.g.
+    /// `CelestialObject::planet(sun, 17.0, 0.05, 1.0, shader).with_orbit(Orbit::new(0.2, 0.1, 0.0, 0.0))`
+    pub fn with_orbit(mut self, orbit: Orbit) -> Self {
+        self.orbit = orbit;
+        self
+    }
+
+    /// Leans the spin axis by `tilt` radians; polar caps and climate bands lean with it
+    pub fn with_axial_tilt(mut self, tilt: f32) -> Self {
+        self.axial_tilt = tilt;
+        self
+    }
+
+    /// Names the body, e.g. `CelestialObject::star(3.0).with_name("Sun")`
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    /// Wraps the body in air that glows along its limb, whatever its shader
+    pub fn with_atmosphere(mut self, atmosphere: Atmosphere) -> Self {
+        self.atmosphere = Some(atmosphere);
+        self
+    }
+
+    /// Rings the poles with animated curtains of light, whatever its shader
+    pub fn with_aurora(mut self, aurora: Aurora) -> Self {
+        self.aurora = Some(aurora);
+        self
+    }
+
+    /// Draws the body with a loaded model instead of the sphere, lit with the model's
+    /// materials (the `Model` shader), e.g. a spacecraft or station orbiting a planet
+    pub fn with_model(mut self, model: Arc<GltfModel>) -> Self {
+        self.material = Material { emissive: self.material.emissive, ..model.material() };
+        self.volcanoes = None;
+        self.vortex = None;
+        self.model = Some(model);
+        self
+    }
+
+    /// Triangles the body is drawn with when it is not the shared sphere: a ring's
+    /// annulus or a loaded model
+    pub fn mesh(&self) -> Option<&Arc<Obj>> {
+        match (&self.ring, &self.model) {
+            (Some(ring), _) => Some(&ring.mesh),
+            (None, Some(model)) => Some(&model.mesh),
+            (None, None) => None,
+        }
+    }
+
+    /// Model matrix of the body: its spin about the axis leaned by `axial_tilt`, which
+    /// `create_model_matrix` applies last (about world X)
+    pub fn model_matrix(&self) -> Matrix {
+        let rotation = Vector3::new(self.rotation.x + self.axial_tilt, self.rotation.y, self.rotation.z);
+        create_model_matrix(self.position, self.scale, rotation)
+    }
+
+    /// Offset from the parent at the current orbit angle
+    pub fn orbit_offset(&self) -> Vector3 {
+        self.orbit.offset(self.orbit_radius, self.orbit_angle)
+    }
+
+    /// Create a ring around a body, between `inner` and `outer` radius (world units)
+    /// It stays centered on its parent, tilted and slowly spinning in its own plane
+    pub fn ring(parent_idx: usize, inner: f32, outer: f32) -> Self {
+        let mut ring = Self::planet(parent_idx, 0.0, 0.0, 1.0, ShaderType::Rings);
+        ring.object_type = CelestialType::Ring;
+        ring.orbit_angle = 0.0;
+        ring.seeded_angle = false;
+        ring.rotation = Vector3::new(0.45, 0.0, 0.0);
+        ring.rotation_speed = Vector3::new(0.0, 0.02, 0.0);
+        ring.ring = Some(Ring::new(inner, outer));
+        ring.blend = BlendMode::Over;
+        ring
+    }
+
+    /// Draws the body translucent, covering what lies behind it by `opacity`
+    pub fn with_opacity(mut self, opacity: f32, blend: BlendMode) -> Self {
+        self.opacity = opacity;
+        self.blend = blend;
+        self
+    }
+
+    /// Coverage of one of the body's fragments: its opacity times what the shader
+    /// leaves (ring divisions); always 1 for opaque bodies
+    pub fn alpha(&self, fragment: &Fragment, uniforms: &Uniforms) -> f32 {
+        match self.blend {
+            BlendMode::Opaque => 1.0,
+            _ => self.opacity * shader_alpha(fragment, uniforms, &self.material),
+        }
+    }
+    
+    /// Eruptions going on at the body's surface, for its shader
+    pub fn eruptions(&self) -> &[Eruption] {
+        self.volcanoes.as_ref().map_or(&[], |volcanoes| &volcanoes.eruptions)
+    }
+
+    /// Light the body's aurora adds over one of its fragments, zero without one
+    pub fn aurora_glow(&self, fragment: &Fragment, uniforms: &Uniforms) -> Vector3 {
+        match self.aurora {
+            Some(aurora) if !self.material.emissive => aurora.glow(fragment, uniforms),
+            _ => Vector3::zero(),
+        }
+    }
+    
     /// Update object state
     pub fn update(&mut self, delta_time: f32, parent_pos: Option<Vector3>) {
         // Update rotation
@@ -93,16 +397,38 @@ impl CelestialObject {
         // Update orbital position if has parent
         if let Some(center) = parent_pos {
             self.orbit_angle += self.orbit_speed * delta_time;
-            self.position.x = center.x + self.orbit_radius * self.orbit_angle.cos();
-            self.position.z = center.z + self.orbit_radius * self.orbit_angle.sin();
-            self.position.y = center.y;
+            self.position = center + self.orbit_offset();
         }
     }
 }
 
 /// Solar system container
+#[derive(Clone)]
 pub struct SolarSystem {
     pub objects: Vec<CelestialObject>,
+    /// Rock belts around some of the bodies
+    pub belts: Vec<AsteroidBelt>,
+    /// Particle systems not tied to a body (impact sparks); dropped once they die out
+    pub effects: Vec<ParticleSystem>,
+    pub seed: u32,
+    /// Simulated seconds since the system was created
+    pub time: f32,
+    /// What `advance` does with bodies that touch
+    pub collisions: CollisionResponse,
+    /// Pairs of bodies touching at the last collision check, to report each contact once
+    pub(crate) contacts: Vec<(usize, usize)>,
+    /// Id the next added body gets
+    next_id: u32,
+    /// How bodies move; change it with `set_motion`
+    pub motion: Motion,
+    /// Gravitational constant of the gravity mode
+    pub gravity: f32,
+}
+
+impl Default for SolarSystem {
+    fn default() -> Self {
+        SolarSystem::new()
+    }
 }
 
 impl SolarSystem {
@@ -110,17 +436,92 @@ impl SolarSystem {
     pub fn new() -> Self {
         SolarSystem {
             objects: Vec::new(),
+            belts: Vec::new(),
+            effects: Vec::new(),
+            seed: 0,
+            time: 0.0,
+            collisions: CollisionResponse::default(),
+            contacts: Vec::new(),
+            next_id: 1,
+            motion: Motion::default(),
+            gravity: GRAVITY,
         }
     }
     
     /// Add an object and return its index
-    pub fn add(&mut self, object: CelestialObject) -> usize {
+    /// The object gets a new id; its noise seed (and the starting orbit angle of planets)
+    /// is derived from the system seed and its index; in the gravity mode it starts on its
+    /// orbit with its launch velocity
+    pub fn add(&mut self, mut object: CelestialObject) -> usize {
+        let index = self.objects.len();
+        object.id = self.next_id;
+        self.next_id += 1;
+        object.seed = self.object_seed(index);
+        if object.seeded_angle {
+            object.orbit_angle = self.start_angle(index);
+        }
+        if self.motion == Motion::Gravity {
+            if let Some(parent) = object.parent_index.and_then(|parent| self.objects.get(parent)) {
+                object.position = parent.position + object.orbit_offset();
+            }
+            object.velocity = self.launch_velocity(&object);
+        }
         self.objects.push(object);
-        self.objects.len() - 1
+        index
     }
     
+    /// Noise seed of the object at `index` for the current system seed
+    fn object_seed(&self, index: usize) -> u32 {
+        self.seed
+            .wrapping_mul(0x9E37_79B9)
+            .wrapping_add((index as u32).wrapping_mul(0x85EB_CA6B))
+    }
+    
+    /// Starting orbit angle of the object at `index` for the current system seed
+    fn start_angle(&self, index: usize) -> f32 {
+        let mut h = self.object_seed(index) ^ 0x68E3_1DA4;
+        h ^= h >> 16;
+        h = h.wrapping_mul(0x27D4_EB2F);
+        h ^= h >> 15;
+        (h >> 8) as f32 / 16_777_216.0 * PI * 2.0
+    }
+
+    /// Change the system seed and re-derive every object's noise seed and seeded orbit
+    /// angle (meant for freshly built systems: in the gravity mode the bodies are put
+    /// back on their orbits)
+    pub fn reseed(&mut self, seed: u32) {
+        self.seed = seed;
+        for index in 0..self.objects.len() {
+            self.objects[index].seed = self.object_seed(index);
+            if self.objects[index].seeded_angle {
+                self.objects[index].orbit_angle = self.start_angle(index);
+                if self.motion == Motion::Gravity {
+                    let object = &self.objects[index];
+                    if let Some(parent) = object.parent_index.and_then(|parent| self.objects.get(parent)) {
+                        let (position, velocity) = (parent.position + object.orbit_offset(), self.launch_velocity(object));
+                        self.objects[index].position = position;
+                        self.objects[index].velocity = velocity;
+                    }
+                }
+            }
+        }
+    }
+    
+    /// Current index of the body with the given id (`CelestialObject::id`), None once it
+    /// is gone
+    pub fn index_of(&self, id: u32) -> Option<usize> {
+        self.objects.iter().position(|object| object.id == id)
+    }
+
+    /// Name shown for the object at `index`: its own, or its index and type when unnamed
+    pub fn display_name(&self, index: usize) -> Option<String> {
+        let object = self.objects.get(index)?;
+        Some(if object.name.is_empty() { format!("{index}: {:?}", object.object_type) } else { object.name.clone() })
+    }
+
     /// Update all objects
     pub fn update(&mut self, delta_time: f32) {
+        self.time += delta_time;
         let len = self.objects.len();
         // Update rotations
         for i in 0..len {
@@ -133,6 +534,15 @@ impl SolarSystem {
             obj.rotation.y += obj.rotation_speed.y * delta_time;
             obj.rotation.z += obj.rotation_speed.z * delta_time;
         }
+        if self.motion == Motion::Gravity {
+            self.integrate(delta_time);
+            self.update_belts(delta_time);
+            self.update_tails(delta_time);
+            self.update_eruptions(delta_time);
+            self.update_storms(delta_time);
+            self.update_effects(delta_time);
+            return;
+        }
         // Update orbital positions (hierarchical)
         for i in 0..len {
             if let Some(parent_idx) = self.objects[i].parent_index {
@@ -140,15 +550,109 @@ impl SolarSystem {
                 let obj = &mut self.objects[i];
                 
                 // Aplicar ley de Kepler: velocidad más lenta mientras más lejos
-                // v = orbit_speed / sqrt(orbit_radius)
-                let adjusted_speed = obj.orbit_speed / obj.orbit_radius.sqrt();
+                // v = orbit_speed / sqrt(orbit_radius); rings sit at radius 0 and don't orbit
+                let adjusted_speed = if obj.orbit_radius > 0.0 {
+                    obj.orbit_speed / obj.orbit_radius.sqrt()
+                } else {
+                    0.0
+                };
                 
                 obj.orbit_angle += adjusted_speed * 1.0 * delta_time;
-                obj.position.x = parent_pos.x + obj.orbit_radius * obj.orbit_angle.cos();
-                obj.position.z = parent_pos.z + obj.orbit_radius * obj.orbit_angle.sin();
-                obj.position.y = parent_pos.y;
+                obj.position = parent_pos + obj.orbit_offset();
             }
         }
+        self.update_belts(delta_time);
+        self.update_tails(delta_time);
+        self.update_eruptions(delta_time);
+        self.update_storms(delta_time);
+        self.update_effects(delta_time);
+    }
+
+    /// Runs the free particle systems, dropping the ones with nothing left to show;
+    /// going back in time drops them all
+    fn update_effects(&mut self, delta_time: f32) {
+        if delta_time < 0.0 {
+            self.effects.clear();
+        }
+        for effect in &mut self.effects {
+            effect.update(delta_time, Vector3::zero());
+            effect.emit(delta_time);
+        }
+        self.effects.retain(|effect| !effect.is_empty() || effect.emitter.rate > 0.0);
+    }
+
+    /// Moves the rocks of every belt around their body
+    fn update_belts(&mut self, delta_time: f32) {
+        for belt in &mut self.belts {
+            if let Some(parent) = self.objects.get(belt.parent) {
+                belt.update(delta_time, parent.position);
+            }
+        }
+    }
+    
+    /// Update all objects, then resolve the collisions that happened on the way
+    /// Jumps in time (`set_time`) skip the collision checks, as merged bodies can't be
+    /// taken apart when scrubbing back
+    pub fn advance(&mut self, delta_time: f32) -> Vec<Collision> {
+        self.update(delta_time);
+        self.resolve_collisions()
+    }
+
+    /// Moves the whole system to an absolute simulated time, forward or backward
+    /// Spins and orbital angles grow linearly with time and positions follow from the
+    /// angles, so any time is reached exactly by one update over the difference; in the
+    /// gravity mode the jump is integrated in steps, so scrubbing back only retraces the
+    /// path approximately
+    pub fn set_time(&mut self, time: f32) {
+        self.update(time - self.time);
+        self.time = time;
+    }
+    
+    /// Length of a "year" in simulated seconds: the orbital period of the first
+    /// Earth-like planet, or of the first planet if there is none
+    pub fn year_length(&self) -> Option<f32> {
+        let planets = || self.objects.iter().filter(|o| o.object_type == CelestialType::Planet);
+        let planet = planets()
+            .find(|o| o.material.shader == ShaderType::CloudPlanet)
+            .or_else(|| planets().next())?;
+        let angular_speed = planet.orbit_speed / planet.orbit_radius.sqrt();
+        (angular_speed.abs() > f32::EPSILON).then(|| 2.0 * PI / angular_speed.abs())
+    }
+    
+    /// Build the scene lights from the current star positions
+    /// Intensity is the star's luminosity times its size relative to the largest star,
+    /// so with the default luminosity the largest star shines at 1.0
+    /// `mesh_radius` is the model-space radius of the body mesh
+    pub fn lights(&self, mesh_radius: f32) -> Vec<Light> {
+        // Starlight scattered around the system, a faint glow on the night sides
+        const AMBIENT: f32 = 0.04;
+
+        let stars = self.objects.iter().filter(|o| o.object_type == CelestialType::Star);
+        let max_scale = stars.clone().map(|o| o.scale).fold(0.0, f32::max);
+
+        stars
+            .map(|star| Light {
+                ambient: AMBIENT,
+                falloff: star.falloff,
+                ..Light::new(
+                    star.position,
+                    star.spectral_class.color(),
+                    star.luminosity * star.scale / max_scale,
+                    star.scale * mesh_radius,
+                )
+            })
+            .collect()
+    }
+    
+    /// Bounding spheres of every object except `skip_index`, for shadow tests
+    /// Rings and models are too far from a sphere to be approximated by one and cast no shadows
+    pub fn occluders(&self, mesh_radius: f32, skip_index: usize) -> Vec<Occluder> {
+        self.objects
+            .iter()
+            .enumerate()
+            .filter(|(i, o)| *i != skip_index && o.mesh().is_none())
+            .map(|(_, o)| Occluder::new(o.position, o.scale * mesh_radius))
+            .collect()
     }
     
     /// Create a basic solar system preset
@@ -158,16 +662,16 @@ impl SolarSystem {
         system.add(CelestialObject::star(1.0));
         
         // Central star (Sun)
-        let sun_idx = system.add(CelestialObject::star(3.0));
+        let sun_idx = system.add(CelestialObject::star(3.0).with_name("Sun"));
         
-        // Inner rocky planet (Mercury-like)
+        // Inner rocky planet (Mercury-like), on an eccentric and slightly tilted orbit
         system.add(CelestialObject::planet(
             sun_idx,
             8.0,
             0.08,
             0.4,
             ShaderType::Rocky
-        ));
+        ).with_orbit(Orbit::new(0.2, 0.12, 0.5, 0.8)).with_name("Mercury"));
         
         // Second planet (Venus-like - lava world)
         system.add(CelestialObject::planet(
@@ -176,7 +680,7 @@ impl SolarSystem {
             0.06,
             0.65,
             ShaderType::Lava
-        ));
+        ).with_name("Venus"));
         
         // Earth-like planet with moon
         let earth_idx = system.add(CelestialObject::planet(
@@ -185,7 +689,7 @@ impl SolarSystem {
             0.05,
             1.0,
             ShaderType::CloudPlanet
-        ));
+        ).with_axial_tilt(0.41).with_atmosphere(Atmosphere::default()).with_aurora(Aurora::default()).with_name("Earth"));
         
         // Moon orbiting Earth (planeta pequeño orbitando otro planeta)
         // Solo necesitas crear un planeta con radio de órbita pequeño y agregarlo como hijo de Earth
@@ -195,7 +699,7 @@ impl SolarSystem {
             0.15,     // velocidad de órbita más rápida que los planetas grandes
             0.12,     // escala pequeña (es una luna)
             ShaderType::Rocky
-        ));
+        ).with_name("Moon"));
         
         // Gas giant (Jupiter-like)
         let jupiter_idx = system.add(CelestialObject::planet(
@@ -204,7 +708,7 @@ impl SolarSystem {
             0.03,
             1.5,
             ShaderType::GasGiant
-        ));
+        ).with_name("Jupiter"));
         
         // Moons of gas giant
         system.add(CelestialObject::moon(
@@ -213,7 +717,7 @@ impl SolarSystem {
             0.12,
             0.15,
             ShaderType::IceWorld
-        ));
+        ).with_name("Europa"));
         
         system.add(CelestialObject::moon(
             jupiter_idx,
@@ -221,7 +725,7 @@ impl SolarSystem {
             0.09,
             0.18,
             ShaderType::Rocky
-        ));
+        ).with_name("Callisto"));
         
         // Outer ice world
         system.add(CelestialObject::planet(
@@ -230,7 +734,13 @@ impl SolarSystem {
             0.02,
             0.8,
             ShaderType::IceWorld
-        ));
+        ).with_orbit(Orbit::new(0.1, 0.06, 2.0, 0.0)).with_name("Frost"));
+        
+        // Comet on a long orbit that dives inside Mercury's
+        system.add(CelestialObject::comet(sun_idx, 20.0, 0.07, 0.15).with_name("Halley"));
+        
+        // Asteroid belt between Earth and Jupiter
+        system.belts.push(AsteroidBelt::new(sun_idx, 19.5, 22.0, 600, 0.04, (0.03, 0.12), 0.05, 1));
         
         system
     }
@@ -240,24 +750,25 @@ impl SolarSystem {
         let mut system = SolarSystem::new();
         
         // Binary star system (two stars)
-        let star1_idx = system.add(CelestialObject::star(1.2));
+        let star1_idx = system.add(CelestialObject::star(1.2).with_name("Alpha"));
         
         let mut star2 = CelestialObject::star(0.8);
-        star2.shader_type = ShaderType::Lava; // Orange dwarf
+        star2.material = Material { emissive: true, ..ShaderType::Lava.material() }; // Orange dwarf
+        star2.spectral_class = SpectralClass::K;
         star2.scale = 0.8;
         star2.parent_index = Some(star1_idx);
         star2.orbit_radius = 3.0;
         star2.orbit_speed = 0.1;
-        system.add(star2);
+        system.add(star2.with_name("Beta"));
         
-        // Lava world close to stars
+        // Lava world close to stars, on a stretched orbit
         system.add(CelestialObject::planet(
             star1_idx,
             6.0,
             0.12,
             0.5,
             ShaderType::Lava
-        ));
+        ).with_orbit(Orbit::new(0.3, 0.0, 1.0, 0.0)).with_name("Ember"));
         
         // Large gas giant
         let giant_idx = system.add(CelestialObject::planet(
@@ -266,7 +777,7 @@ impl SolarSystem {
             0.04,
             1.2,
             ShaderType::GasGiant
-        ));
+        ).with_name("Colossus"));
         
         // Multiple moons around giant
         system.add(CelestialObject::moon(
@@ -275,7 +786,7 @@ impl SolarSystem {
             0.15,
             0.2,
             ShaderType::IceWorld
-        ));
+        ).with_name("Rime"));
         
         system.add(CelestialObject::moon(
             giant_idx,
@@ -283,7 +794,7 @@ impl SolarSystem {
             0.11,
             0.25,
             ShaderType::Lava
-        ));
+        ).with_name("Cinder"));
         
         system.add(CelestialObject::moon(
             giant_idx,
@@ -291,7 +802,7 @@ impl SolarSystem {
             0.08,
             0.18,
             ShaderType::CloudPlanet
-        ));
+        ).with_atmosphere(Atmosphere::tinted(Vector3::new(0.45, 1.0, 0.7))).with_name("Verdant"));
         
         // Distant frozen world
         system.add(CelestialObject::planet(
@@ -300,7 +811,10 @@ impl SolarSystem {
             0.02,
             0.6,
             ShaderType::IceWorld
-        ));
+        ).with_aurora(Aurora::tinted(Vector3::new(0.85, 0.3, 0.75))).with_name("Far Frost"));
+        
+        // Wide, thick belt past the giant's moons
+        system.belts.push(AsteroidBelt::new(star1_idx, 13.4, 15.0, 400, 0.03, (0.03, 0.1), 0.1, 2));
         
         system
     }
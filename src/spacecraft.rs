@@ -0,0 +1,331 @@
+// spacecraft.rs
+// A ship flown through the scene apart from the simulated bodies: piloted with the
+// keyboard and mouse or by an autopilot hopping between the planets, followed by a chase
+// camera and trailing the glow of its engine. It is drawn by adding it to a copy of the
+// frame's scene, so every backend shows it like any other body with a model
+
+use raylib::prelude::*;
+use std::f32::consts::PI;
+use std::sync::Arc;
+use crate::camera::Camera;
+use crate::gltf::GltfModel;
+use crate::input::{Action, InputFrame};
+use crate::mesh::SPHERE_RADIUS;
+use crate::obj::{Obj, ObjError};
+use crate::particles::{Emitter, ParticleShape, ParticleSystem};
+use crate::shader_system::ShaderType;
+use crate::solar_system::{CelestialObject, CelestialType, SolarSystem};
+
+/// Model the window's ship is loaded from
+pub const SHIP_MODEL: &str = "./models/ship.obj";
+
+/// Size of the ship: the model is fitted to the body sphere, so its length is about
+/// twice this
+const SHIP_SCALE: f32 = 0.15;
+
+/// Acceleration at full throttle in units per second squared, and the speed it tops out at
+const THRUST: f32 = 6.0;
+const MAX_SPEED: f32 = 12.0;
+
+/// Throttle and top speed multiplier while the modifier is held
+const BOOST: f32 = 3.0;
+
+/// Radians per second the ship turns at with the keys, and per pixel of mouse drag
+const TURN_RATE: f32 = 1.6;
+const MOUSE_TURN: f32 = 0.004;
+
+/// How quickly the ship sheds its speed per second while coasting and while braking
+const DRAG: f32 = 0.3;
+const BRAKE: f32 = 2.5;
+
+/// Highest pitch, short of straight up or down where the heading is undefined
+const MAX_PITCH: f32 = PI / 2.0 - 0.1;
+
+/// The autopilot stops this many body radii from the planet's center, plus one unit
+const STANDOFF: f32 = 3.0;
+
+/// Distance from its stopping point at which the autopilot reports the arrival
+const ARRIVAL: f32 = 0.5;
+
+/// Chase camera: distance behind and height above the ship in ship lengths, how far
+/// ahead of it the camera looks and how quickly it settles (per second)
+const CHASE_DISTANCE: f32 = 3.0;
+const CHASE_HEIGHT: f32 = 0.8;
+const CHASE_LOOK_AHEAD: f32 = 2.0;
+const CHASE_RATE: f32 = 5.0;
+
+/// Engine glow: particles per second at full throttle (a few idle), their speed out of
+/// the nozzle and how long they last
+const ENGINE_RATE: f32 = 240.0;
+const ENGINE_IDLE: f32 = 0.1;
+const EXHAUST_SPEED: f32 = 3.0;
+const EXHAUST_LIFETIME: f32 = 0.5;
+const ENGINE_PARTICLES: usize = 256;
+
+/// A ship with its own position, heading and velocity, drawn with a loaded model
+#[derive(Clone, Debug)]
+pub struct Spacecraft {
+    /// Label shown over the ship
+    pub name: String,
+    pub position: Vector3,
+    pub velocity: Vector3,
+    /// Heading: turn around the Y axis (0 faces +Z) and climb above the XZ plane, in radians
+    pub yaw: f32,
+    pub pitch: f32,
+    pub scale: f32,
+    pub model: Arc<GltfModel>,
+    /// Id of the planet the autopilot flies to (`CelestialObject::id`, see
+    /// `autopilot_target`), None while piloted by hand
+    pub autopilot: Option<u32>,
+    /// Fraction of full thrust of the last update, 0..1 (more while boosting)
+    pub throttle: f32,
+    /// Exhaust released behind the ship
+    pub engine: ParticleSystem,
+    /// Turn and thrust asked for by `steer`, used up by the next `update`
+    controls: Controls,
+    /// Distance from the center of the model to its back, where the exhaust leaves
+    engine_offset: f32,
+    /// Whether the autopilot already reported reaching its planet
+    arrived: bool,
+}
+
+/// Piloting input of one frame
+#[derive(Clone, Copy, Debug, Default)]
+struct Controls {
+    /// Radians added to the yaw (turning left) and the pitch (climbing)
+    turn: Vector2,
+    /// -1 brakes, 0 coasts, 1 accelerates
+    thrust: f32,
+    boost: bool,
+}
+
+impl Spacecraft {
+    /// Loads the ship model from an OBJ file; its faces are shaded flat for hard edges
+    pub fn load(path: &str) -> Result<Self, ObjError> {
+        let mut obj = Obj::load(path)?;
+        obj.recompute_normals(false);
+        Ok(Spacecraft::new(Arc::new(obj.to_model())))
+    }
+
+    /// Ship at the origin, stopped and facing +Z, drawn with `model` (nose toward +Z)
+    pub fn new(model: Arc<GltfModel>) -> Self {
+        let engine_offset = -model.mesh.vertices.iter().map(|v| v.position.z).fold(0.0, f32::min);
+        let emitter = Emitter {
+            spread: EXHAUST_SPEED * 0.15,
+            lifetime: EXHAUST_LIFETIME,
+            color_start: Vector3::new(1.0, 0.85, 0.5),
+            color_end: Vector3::new(0.9, 0.2, 0.05),
+            size: SHIP_SCALE * 0.25,
+            ..Emitter::default()
+        };
+        Spacecraft {
+            name: "Ship".to_string(),
+            position: Vector3::zero(),
+            velocity: Vector3::zero(),
+            yaw: 0.0,
+            pitch: 0.0,
+            scale: SHIP_SCALE,
+            model,
+            autopilot: None,
+            throttle: 0.0,
+            engine: ParticleSystem::new(emitter, ENGINE_PARTICLES).with_shape(ParticleShape::Quad { soft: true }),
+            controls: Controls::default(),
+            engine_offset,
+            arrived: false,
+        }
+    }
+
+    /// Places the ship just ahead of the camera, stopped and heading where it looks
+    pub fn launch(&mut self, camera: &Camera) {
+        let view = (camera.target - camera.eye).normalized();
+        self.position = camera.eye + view * (self.length() * CHASE_DISTANCE);
+        (self.yaw, self.pitch) = heading(view);
+        self.velocity = Vector3::zero();
+        self.autopilot = None;
+        self.engine.clear();
+    }
+
+    /// Unit vector the nose points along
+    pub fn forward(&self) -> Vector3 {
+        Vector3::new(self.pitch.cos() * self.yaw.sin(), self.pitch.sin(), self.pitch.cos() * self.yaw.cos())
+    }
+
+    /// Rotation for `create_model_matrix` that turns the model's +Z nose to `forward`
+    /// with the wings level: the matrix turns around Z, then Y, then X, so the last two
+    /// aim the nose and the first rolls the ship upright
+    pub fn rotation(&self) -> Vector3 {
+        let forward = self.forward();
+        let x = (-forward.y).atan2(forward.z);
+        let y = forward.x.clamp(-1.0, 1.0).asin();
+        let z = (-x.sin() * y.sin()).atan2(x.cos());
+        Vector3::new(x, y, z)
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.velocity.length()
+    }
+
+    /// Length of the ship from nose to tail in world units
+    pub fn length(&self) -> f32 {
+        SPHERE_RADIUS * self.scale * 2.0
+    }
+
+    /// Reads the piloting actions for the next `update`:
+    /// - OrbitUp/OrbitDown (W/S): Accelerate / brake
+    /// - OrbitLeft/OrbitRight (A/D): Turn
+    /// - PanUp/PanDown (R/F): Climb / dive
+    /// - MouseOrbit (left drag): Turn and climb
+    /// - Modifier (Shift): Boost
+    ///
+    /// Any of them takes the ship off the autopilot
+    pub fn steer(&mut self, input: &InputFrame) {
+        let axis = |positive: Action, negative: Action| {
+            input.is_down(positive) as i32 as f32 - input.is_down(negative) as i32 as f32
+        };
+        let mut turn = Vector2::new(axis(Action::OrbitLeft, Action::OrbitRight), axis(Action::PanUp, Action::PanDown))
+            * (TURN_RATE * input.delta_time);
+        // Dragging right turns right, dragging down dives
+        if input.is_down(Action::MouseOrbit) {
+            turn -= input.mouse_delta * MOUSE_TURN;
+        }
+        let thrust = axis(Action::OrbitUp, Action::OrbitDown);
+        if (turn != Vector2::zero() || thrust != 0.0) && self.autopilot.take().is_some() {
+            log::info!("Autopilot off");
+        }
+        self.controls = Controls { turn, thrust, boost: input.is_down(Action::Modifier) };
+    }
+
+    /// Hands the ship to the autopilot, flying to the planet after its current target
+    /// (the first one when off); after the last planet it goes back to manual piloting.
+    /// Returns the new target
+    pub fn cycle_autopilot(&mut self, system: &SolarSystem) -> Option<usize> {
+        let planets: Vec<usize> = (0..system.objects.len())
+            .filter(|&i| system.objects[i].object_type == CelestialType::Planet)
+            .collect();
+        let current = self.autopilot_target(system);
+        let next = match current.and_then(|target| planets.iter().position(|&p| p == target)) {
+            Some(i) => planets.get(i + 1).copied(),
+            None => planets.first().copied(),
+        };
+        self.autopilot = next.map(|index| system.objects[index].id);
+        self.arrived = false;
+        next
+    }
+
+    /// Index in `system` of the planet the autopilot flies to, None while flown by hand
+    /// or once the planet is gone
+    pub fn autopilot_target(&self, system: &SolarSystem) -> Option<usize> {
+        system.index_of(self.autopilot?)
+    }
+
+    /// Moves the ship by `delta_time` seconds of its controls or its autopilot and lets
+    /// the exhaust out; true on the frame the autopilot reaches its planet. A target that
+    /// disappeared (scene change) turns the autopilot off
+    pub fn update(&mut self, system: &SolarSystem, delta_time: f32) -> bool {
+        let controls = std::mem::take(&mut self.controls);
+        let forward = self.forward();
+        let mut arrived = false;
+
+        match self.autopilot.map(|id| system.index_of(id).map(|index| &system.objects[index])) {
+            Some(Some(target)) => {
+                // Stop on the near side of the planet, wherever it has moved to
+                let radius = target.scale * SPHERE_RADIUS;
+                let away = self.position - target.position;
+                let away = if away.length() > 0.0 { away.normalized() } else { -forward };
+                let goal = target.position + away * (radius * STANDOFF + 1.0);
+                let to_goal = goal - self.position;
+                let distance = to_goal.length();
+
+                // Face the planet once there, the goal while on the way
+                let facing = if distance > ARRIVAL { to_goal } else { target.position - self.position };
+                let (yaw, pitch) = heading(facing.normalized());
+                let step = TURN_RATE * delta_time;
+                self.yaw += wrap_angle(yaw - self.yaw).clamp(-step, step);
+                self.pitch += (pitch - self.pitch).clamp(-step, step);
+
+                // Slow down on the approach
+                let desired = if distance > 1e-3 { to_goal * (distance.min(MAX_SPEED) / distance) } else { Vector3::zero() };
+                let change = (desired - self.velocity) * (1.0 - (-2.0 * delta_time).exp());
+                self.velocity += change;
+                self.throttle = (change.dot(forward) / (THRUST * delta_time).max(1e-6)).clamp(0.0, 1.0);
+
+                if distance < ARRIVAL && !self.arrived {
+                    self.arrived = true;
+                    arrived = true;
+                }
+            }
+            Some(None) => {
+                self.autopilot = None;
+                self.throttle = 0.0;
+            }
+            None => {
+                self.yaw = wrap_angle(self.yaw + controls.turn.x);
+                self.pitch += controls.turn.y;
+                let boost = if controls.boost { BOOST } else { 1.0 };
+                self.throttle = controls.thrust.max(0.0) * boost;
+                self.velocity += self.forward() * (THRUST * self.throttle * delta_time);
+                let drag = if controls.thrust < 0.0 { BRAKE } else { DRAG };
+                self.velocity *= (-drag * delta_time).exp();
+                let top = MAX_SPEED * boost;
+                if self.speed() > top {
+                    self.velocity = self.velocity.normalized() * top;
+                }
+            }
+        }
+        self.pitch = self.pitch.clamp(-MAX_PITCH, MAX_PITCH);
+        self.position += self.velocity * delta_time;
+
+        // Exhaust out of the back, carried along with the ship
+        let forward = self.forward();
+        let emitter = &mut self.engine.emitter;
+        emitter.position = self.position - forward * (self.engine_offset * self.scale);
+        emitter.velocity = self.velocity - forward * (EXHAUST_SPEED * (0.5 + self.throttle));
+        emitter.rate = ENGINE_RATE * self.throttle.max(ENGINE_IDLE);
+        self.engine.update(delta_time, Vector3::zero());
+        self.engine.emit(delta_time);
+        arrived
+    }
+
+    /// Moves the camera toward its spot behind and above the ship, looking past its nose
+    pub fn chase(&self, camera: &mut Camera, delta_time: f32) {
+        let forward = self.forward();
+        let right = forward.cross(camera.up).normalized();
+        let up = right.cross(forward);
+        let length = self.length();
+        let eye = self.position - forward * (length * CHASE_DISTANCE) + up * (length * CHASE_HEIGHT);
+        let target = self.position + forward * (length * CHASE_LOOK_AHEAD);
+        let follow = 1.0 - (-CHASE_RATE * delta_time).exp();
+        camera.set_view(camera.eye.lerp(eye, follow), target);
+    }
+
+    /// Body drawing the ship where it is, turned along its heading
+    pub fn body(&self) -> CelestialObject {
+        let mut body = CelestialObject::planet(0, 0.0, 0.0, self.scale, ShaderType::Model).with_model(Arc::clone(&self.model));
+        body.name = self.name.clone();
+        body.parent_index = None;
+        body.position = self.position;
+        body.rotation = self.rotation();
+        body.rotation_speed = Vector3::zero();
+        body.velocity = self.velocity;
+        body
+    }
+
+    /// Copy of `scene` with the ship and its exhaust added after everything else, so
+    /// the indices of the scene's bodies still hold
+    pub fn scene_with(&self, scene: &SolarSystem) -> SolarSystem {
+        let mut scene = scene.clone();
+        scene.objects.push(self.body());
+        scene.effects.push(self.engine.clone());
+        scene
+    }
+}
+
+/// Yaw and pitch of a unit direction, see `Spacecraft::forward`
+fn heading(direction: Vector3) -> (f32, f32) {
+    (direction.x.atan2(direction.z), direction.y.clamp(-1.0, 1.0).asin())
+}
+
+/// Angle brought into -PI..PI
+fn wrap_angle(angle: f32) -> f32 {
+    (angle + PI).rem_euclid(2.0 * PI) - PI
+}
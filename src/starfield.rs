@@ -0,0 +1,210 @@
+// starfield.rs
+// Procedural sky behind the bodies: blue-noise placed star points and an optional fBm nebula,
+// fixed in world directions so it turns with the camera but never moves with it. The nebula
+// slowly swirls along a curl-noise flow
+
+use raylib::prelude::*;
+use std::f32::consts::PI;
+use crate::framebuffer::Framebuffer;
+use crate::matrix::project_to_screen;
+use crate::noise::{BlueNoise, Noise};
+
+/// Stars scattered over the whole sky
+const STAR_COUNT: usize = 6000;
+
+/// Stars are placed on an equal-area grid of longitude by height made of this many
+/// blue-noise tiles side by side, at most one star per cell, so they keep apart instead
+/// of clumping like hashed positions
+const SKY_TILES: usize = 3;
+const SKY_TILE_SIZE: usize = 64;
+
+/// Size of the baked equirectangular nebula map; the nebula is smooth enough that a
+/// coarse map sampled bilinearly looks the same as evaluating the noise per pixel
+const NEBULA_WIDTH: usize = 256;
+const NEBULA_HEIGHT: usize = 128;
+
+/// Nebula texels dimmer than this are skipped when drawing
+const NEBULA_CUTOFF: f32 = 0.004;
+
+/// Coarse equirectangular grid the nebula flow is evaluated on each frame and sampled
+/// bilinearly per pixel, like the nebula map itself
+const FLOW_WIDTH: usize = 64;
+const FLOW_HEIGHT: usize = 32;
+
+/// Nebula flow: frequency of the curl field over the sky, how fast it evolves (noise
+/// units per second), how far back the sample positions are traced (seconds) and in how
+/// many steps, and the speed of the gas in radians per second
+const FLOW_SCALE: f32 = 1.5;
+const FLOW_EVOLUTION: f32 = 0.02;
+const FLOW_WINDOW: f32 = 40.0;
+const FLOW_STEPS: usize = 2;
+const FLOW_SPEED: f32 = 0.0015;
+
+/// Seeded hash of an index, in [0, 1); `channel` selects an independent stream
+fn hash(seed: u32, index: u32, channel: u32) -> f32 {
+    let mut h = seed.wrapping_mul(0x9E37_79B9) ^ channel.wrapping_mul(0x68E3_1DA4);
+    h = h.rotate_left(13) ^ index.wrapping_mul(0x85EB_CA6B);
+    // murmur3 finalizer
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85EB_CA6B);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xC2B2_AE35);
+    h ^= h >> 16;
+    (h >> 8) as f32 / 16_777_216.0
+}
+
+/// Unit direction of an equirectangular coordinate (u, v in [0, 1], v = 0 straight up)
+fn direction(u: f32, v: f32) -> Vector3 {
+    let (phi, theta) = (u * 2.0 * PI, v * PI);
+    Vector3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin())
+}
+
+/// A star of the background
+#[derive(Clone, Debug)]
+struct Star {
+    direction: Vector3,
+    color: Vector3,
+}
+
+/// Procedural background, generated once per seed and drawn before the bodies
+#[derive(Clone, Debug)]
+pub struct Starfield {
+    stars: Vec<Star>,
+    /// Equirectangular nebula colors, None without a nebula
+    nebula: Option<Vec<Vector3>>,
+    /// Potential of the curl field the nebula drifts along
+    flow: Noise,
+}
+
+impl Starfield {
+    /// Generates the stars and, if `nebula` is set, bakes the nebula map for `seed`
+    pub fn new(seed: u32, nebula: bool) -> Self {
+        let blue_noise = BlueNoise::new(SKY_TILE_SIZE, seed);
+        let size = SKY_TILE_SIZE as i32;
+        // Cells of a tile ranked below this hold a star; tiles read the map shifted so the
+        // sky doesn't repeat every tile
+        let density = (STAR_COUNT / SKY_TILES) as f32 / (SKY_TILE_SIZE * SKY_TILE_SIZE) as f32;
+        let stars = (0..SKY_TILES as i32)
+            .flat_map(|tile| (0..size).flat_map(move |y| (0..size).map(move |x| (tile, x, y))))
+            .filter(|&(tile, x, y)| blue_noise.sample(x + tile * 23, y + tile * 41) < density)
+            .enumerate()
+            .map(|(i, (tile, x, y))| {
+                let i = i as u32;
+                // Uniform over the sphere: uniform height and angle, jittered within the cell
+                let y = (y as f32 + hash(seed, i, 0)) / size as f32 * 2.0 - 1.0;
+                let angle = ((tile * size + x) as f32 + hash(seed, i, 1)) / (SKY_TILES * SKY_TILE_SIZE) as f32 * 2.0 * PI;
+                let ring = (1.0 - y * y).sqrt();
+                let direction = Vector3::new(ring * angle.cos(), y, ring * angle.sin());
+
+                // Mostly faint stars, a few bright ones, from bluish to orange
+                let brightness = 0.15 + 0.85 * hash(seed, i, 2).powi(6);
+                let warmth = hash(seed, i, 3);
+                let tint = Vector3::new(0.8 + 0.2 * warmth, 0.85 + 0.1 * warmth, 1.0 - 0.25 * warmth);
+                Star { direction, color: tint * brightness }
+            })
+            .collect();
+
+        Starfield { stars, nebula: nebula.then(|| Self::bake_nebula(seed)), flow: Noise::new(seed ^ 0xF10E_5EED) }
+    }
+
+    /// Two fBm layers: one shapes the clouds, the other shifts their hue
+    fn bake_nebula(seed: u32) -> Vec<Vector3> {
+        let noise = Noise::new(seed ^ 0x5EED_0F5B);
+        let purple = Vector3::new(0.09, 0.03, 0.12);
+        let teal = Vector3::new(0.02, 0.07, 0.09);
+
+        let mut texels = Vec::with_capacity(NEBULA_WIDTH * NEBULA_HEIGHT);
+        for y in 0..NEBULA_HEIGHT {
+            for x in 0..NEBULA_WIDTH {
+                let d = direction((x as f32 + 0.5) / NEBULA_WIDTH as f32, (y as f32 + 0.5) / NEBULA_HEIGHT as f32);
+                let density = (noise.fbm(d * 2.0, 4, 2.0, 0.5) + 0.1).max(0.0) * 2.5;
+                let hue = (noise.fbm(d * 1.5 + Vector3::new(7.3, 1.1, 4.7), 2, 2.0, 0.5) + 0.5).clamp(0.0, 1.0);
+                texels.push(purple.lerp(teal, hue) * density * density);
+            }
+        }
+        texels
+    }
+
+    /// Offset from each flow texel's direction to where the gas there was `FLOW_WINDOW`
+    /// seconds ago, traced back through the curl field as it evolved. The baked map stands
+    /// for the gas at that moment, so the clouds swirl within a bounded distance of it
+    /// instead of smearing out, and the field is divergence-free so they don't thin out or
+    /// bunch up either
+    fn bake_flow(&self, time: f32) -> Vec<Vector3> {
+        let step = FLOW_WINDOW / FLOW_STEPS as f32;
+        let mut offsets = Vec::with_capacity(FLOW_WIDTH * FLOW_HEIGHT);
+        for y in 0..FLOW_HEIGHT {
+            for x in 0..FLOW_WIDTH {
+                let d = direction((x as f32 + 0.5) / FLOW_WIDTH as f32, (y as f32 + 0.5) / FLOW_HEIGHT as f32);
+                let mut p = d;
+                for i in 0..FLOW_STEPS {
+                    let w = (time - i as f32 * step) * FLOW_EVOLUTION;
+                    p = (p - self.flow.curl_noise(p * FLOW_SCALE, w) * FLOW_SPEED * step).normalized();
+                }
+                offsets.push(p - d);
+            }
+        }
+        offsets
+    }
+
+    /// Bilinearly filtered value of an equirectangular map in a world direction
+    fn sample(map: &[Vector3], width: usize, height: usize, d: Vector3) -> Vector3 {
+        let u = (d.z.atan2(d.x) / (2.0 * PI)).rem_euclid(1.0) * width as f32 - 0.5;
+        let v = (d.y.clamp(-1.0, 1.0).acos() / PI * height as f32 - 0.5).clamp(0.0, (height - 1) as f32);
+        let (x0, y0) = (u.floor(), v.floor());
+        let (fx, fy) = (u - x0, v - y0);
+        // Longitude wraps around, latitude is clamped
+        let column = |x: f32| (x as i32).rem_euclid(width as i32) as usize;
+        let (x0, x1) = (column(x0), column(x0 + 1.0));
+        let (y0, y1) = (y0 as usize, (y0 as usize + 1).min(height - 1));
+        let texel = |x: usize, y: usize| map[y * width + x];
+        texel(x0, y0).lerp(texel(x1, y0), fx).lerp(texel(x0, y1).lerp(texel(x1, y1), fx), fy)
+    }
+
+    /// Draws the sky over a cleared framebuffer, seen with the rotation of `view`
+    /// (its translation is ignored: the sky is infinitely far away) and the nebula as it
+    /// has drifted by `time`
+    pub fn draw(&self, framebuffer: &mut Framebuffer, view: &Matrix, projection: &Matrix, viewport: &Matrix, time: f32) {
+        let background = framebuffer.background_color();
+        let base = Vector3::new(background.r as f32, background.g as f32, background.b as f32) / 255.0;
+
+        if let Some(nebula) = &self.nebula {
+            let flow = self.bake_flow(time);
+            let (width, height) = (framebuffer.width, framebuffer.height);
+            for y in 0..height {
+                for x in 0..width {
+                    // View-space ray through the pixel, turned back into world space
+                    let ndc_x = (x as f32 + 0.5) / width as f32 * 2.0 - 1.0;
+                    let ndc_y = 1.0 - (y as f32 + 0.5) / height as f32 * 2.0;
+                    let (rx, ry) = (ndc_x / projection.m0, ndc_y / projection.m5);
+                    let d = Vector3::new(
+                        view.m0 * rx + view.m1 * ry - view.m2,
+                        view.m4 * rx + view.m5 * ry - view.m6,
+                        view.m8 * rx + view.m9 * ry - view.m10,
+                    )
+                    .normalized();
+
+                    let advected = (d + Self::sample(&flow, FLOW_WIDTH, FLOW_HEIGHT, d)).normalized();
+                    let color = Self::sample(nebula, NEBULA_WIDTH, NEBULA_HEIGHT, advected);
+                    if color.x + color.y + color.z > NEBULA_CUTOFF {
+                        framebuffer.overlay_point(x, y, base + color);
+                    }
+                }
+            }
+        }
+
+        let rotation = Matrix { m12: 0.0, m13: 0.0, m14: 0.0, ..*view };
+        for star in &self.stars {
+            let Some(screen) = project_to_screen(star.direction * 10.0, &rotation, projection, viewport) else {
+                continue;
+            };
+            let (x, y) = (screen.x as i32, screen.y as i32);
+            if x < 0 || y < 0 || x >= framebuffer.width || y >= framebuffer.height {
+                continue;
+            }
+            let [r, g, b, _] = framebuffer.pixel(x, y);
+            let under = Vector3::new(r as f32, g as f32, b as f32) / 255.0;
+            framebuffer.overlay_point(x, y, under + star.color);
+        }
+    }
+}
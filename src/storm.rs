@@ -0,0 +1,100 @@
+// storm.rs
+// Great storm of a gas giant: a long-lived vortex whose center is kept on the body and
+// drifts around its latitude band, drawn by winding the bands around it
+
+use raylib::prelude::*;
+use crate::solar_system::SolarSystem;
+
+/// A persistent oval storm on a gas giant, like Jupiter's great red spot
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Vortex {
+    /// Latitude of the center in radians, negative to the south
+    pub latitude: f32,
+    /// Longitude of the center in radians, advanced by `drift`
+    pub longitude: f32,
+    /// Radians per second the storm drifts around its band
+    pub drift: f32,
+    /// Angular radius in radians from north to south
+    pub radius: f32,
+    /// East-west size over north-south size
+    pub stretch: f32,
+    /// Radians the flow is wound by at the center, fading to none at the edge
+    pub spin: f32,
+    /// Color of the storm's core
+    pub color: Vector3,
+}
+
+impl Default for Vortex {
+    /// Brick red storm in the southern tropics
+    fn default() -> Self {
+        Vortex {
+            latitude: -0.38,
+            longitude: 0.0,
+            drift: 0.05,
+            radius: 0.2,
+            stretch: 1.6,
+            spin: 5.0,
+            color: Vector3::new(0.78, 0.35, 0.22),
+        }
+    }
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+impl Vortex {
+    /// Unit direction of the center in model space
+    pub fn center(&self) -> Vector3 {
+        let (lat, lon) = (self.latitude, self.longitude);
+        Vector3::new(lat.cos() * lon.cos(), lat.sin(), lat.cos() * lon.sin())
+    }
+
+    /// Moves the center along its band; going back in time moves it back
+    pub fn update(&mut self, delta_time: f32) {
+        self.longitude = (self.longitude + self.drift * delta_time).rem_euclid(std::f32::consts::TAU);
+    }
+
+    /// How far inside the oval a model-space position lies: 1 at the center, 0 at the
+    /// edge and beyond
+    pub fn coverage(&self, pos: Vector3) -> f32 {
+        let center = self.center();
+        let dir = pos.normalized();
+        if dir.dot(center) <= 0.0 {
+            return 0.0;
+        }
+        // Offsets along the surface toward the east and the north of the center
+        let east = Vector3::new(-self.longitude.sin(), 0.0, self.longitude.cos());
+        let north = east.cross(center);
+        let (x, y) = (dir.dot(east) / self.stretch, dir.dot(north));
+        1.0 - smoothstep(0.0, self.radius, (x * x + y * y).sqrt())
+    }
+
+    /// Rotational domain warp: `pos` turned about the center by the spin, most at the
+    /// center and not at all past the edge, so whatever is sampled at the result swirls
+    /// around the storm. Also returns `coverage`
+    pub fn swirl(&self, pos: Vector3) -> (Vector3, f32) {
+        let inside = self.coverage(pos);
+        if inside <= 0.0 {
+            return (pos, 0.0);
+        }
+        let axis = self.center();
+        let angle = self.spin * inside * inside;
+        let (sin, cos) = angle.sin_cos();
+        // Rodrigues' rotation about the center's axis keeps the point on the sphere
+        let turned = pos * cos + axis.cross(pos) * sin + axis * (axis.dot(pos) * (1.0 - cos));
+        (turned, inside)
+    }
+}
+
+impl SolarSystem {
+    /// Drifts the great storm of every gas giant
+    pub(crate) fn update_storms(&mut self, delta_time: f32) {
+        for object in &mut self.objects {
+            if let Some(vortex) = &mut object.vortex {
+                vortex.update(delta_time);
+            }
+        }
+    }
+}
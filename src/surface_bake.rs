@@ -0,0 +1,205 @@
+// surface_bake.rs
+// Baked planet surfaces: a body's procedural shader evaluated once into a cube map under
+// white light, then looked up per fragment and tinted by the fragment's lighting instead
+// of running the shader again; animated shaders are baked again every few seconds
+
+use raylib::prelude::*;
+use std::collections::HashMap;
+use crate::fragment::Fragment;
+use crate::matrix::create_model_matrix;
+use crate::noise::Noise;
+use crate::noise_cache::NoiseCache;
+use crate::material::{Material, MaterialKey};
+use crate::shader_system::ShaderType;
+use crate::Uniforms;
+
+/// Texels along each side of a cube face
+pub const CUBE_SIZE: usize = 128;
+
+/// When a shader's baked surface is replaced
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Refresh {
+    /// Static surface, baked once
+    Never,
+    /// Animated surface, baked again every this many seconds of shader time
+    Every(f32),
+}
+
+impl ShaderType {
+    /// How a shader's surface is baked, None for the ones always shaded live: stars are
+    /// too bright and fast to bake, rings and models aren't spheres and custom shaders are unknown
+    pub fn surface_refresh(self) -> Option<Refresh> {
+        match self {
+            ShaderType::Rocky | ShaderType::IceWorld => Some(Refresh::Never),
+            ShaderType::GasGiant | ShaderType::CloudPlanet => Some(Refresh::Every(2.0)),
+            ShaderType::Lava => Some(Refresh::Every(0.5)),
+            ShaderType::Star | ShaderType::Rings | ShaderType::Model | ShaderType::Custom(_) => None,
+        }
+    }
+}
+
+/// Unlit shader colors over the six faces of a cube around the body, +x, -x, +y, -y,
+/// +z, -z
+pub struct CubeMap {
+    size: usize,
+    texels: Vec<Vector3>,
+}
+
+/// Unit direction through a point of a face, both face coordinates in -1..1
+fn face_direction(face: usize, u: f32, v: f32) -> Vector3 {
+    match face {
+        0 => Vector3::new(1.0, v, -u),
+        1 => Vector3::new(-1.0, v, u),
+        2 => Vector3::new(u, 1.0, -v),
+        3 => Vector3::new(u, -1.0, v),
+        4 => Vector3::new(u, v, 1.0),
+        _ => Vector3::new(-u, v, -1.0),
+    }
+    .normalized()
+}
+
+/// Face and face coordinates (-1..1) a direction passes through
+fn face_coordinates(d: Vector3) -> (usize, f32, f32) {
+    let (ax, ay, az) = (d.x.abs(), d.y.abs(), d.z.abs());
+    if ax >= ay && ax >= az {
+        if d.x > 0.0 { (0, -d.z / ax, d.y / ax) } else { (1, d.z / ax, d.y / ax) }
+    } else if ay >= az {
+        if d.y > 0.0 { (2, d.x / ay, -d.z / ay) } else { (3, d.x / ay, d.z / ay) }
+    } else if d.z > 0.0 {
+        (4, d.x / az, d.y / az)
+    } else {
+        (5, -d.x / az, d.y / az)
+    }
+}
+
+impl CubeMap {
+    /// Runs the material's shader once per texel on a sphere of `radius`, lit by full
+    /// white light with no highlight and leaving out its emission
+    pub fn bake(size: usize, radius: f32, material: &Material, uniforms: &Uniforms) -> Self {
+        let mut texels = Vec::with_capacity(6 * size * size);
+        for face in 0..6 {
+            for j in 0..size {
+                for i in 0..size {
+                    let u = (i as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+                    let v = (j as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+                    let direction = face_direction(face, u, v);
+                    let fragment = Fragment::new_with_world_pos(
+                        0.0, 0.0, Vector3::one(), Vector3::zero(), Vector3::zero(), 0.0, direction * radius,
+                    )
+                    .with_normal(direction);
+                    texels.push(material.surface(&fragment, uniforms));
+                }
+            }
+        }
+        CubeMap { size, texels }
+    }
+
+    /// Unlit color in the direction of a model-space point (bilinear within a face)
+    pub fn sample(&self, p: Vector3) -> Vector3 {
+        let (face, u, v) = face_coordinates(p);
+        let last = (self.size - 1) as f32;
+        let x = ((u * 0.5 + 0.5) * self.size as f32 - 0.5).clamp(0.0, last);
+        let y = ((v * 0.5 + 0.5) * self.size as f32 - 0.5).clamp(0.0, last);
+        let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+        let (x1, y1) = ((x0 + 1).min(self.size - 1), (y0 + 1).min(self.size - 1));
+        let (tx, ty) = (x - x0 as f32, y - y0 as f32);
+
+        let texel = |x: usize, y: usize| self.texels[(face * self.size + y) * self.size + x];
+        let top = texel(x0, y0).lerp(texel(x1, y0), tx);
+        let bottom = texel(x0, y1).lerp(texel(x1, y1), tx);
+        top.lerp(bottom, ty)
+    }
+
+    /// Color of a fragment from the baked surface: the unlit color tinted by the
+    /// fragment's light, plus its highlight and glow
+    pub fn shade(&self, fragment: &Fragment) -> Vector3 {
+        let albedo = self.sample(fragment.world_position);
+        let light = fragment.color;
+        Vector3::new(albedo.x * light.x, albedo.y * light.y, albedo.z * light.z) + fragment.specular
+    }
+}
+
+/// Identifies one baked surface: the body's noise seed, its material and a coarse time
+/// step
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+struct SurfaceKey {
+    seed: u32,
+    material: MaterialKey,
+    time_bucket: i64,
+}
+
+/// Baked surfaces of every body, rebuilt lazily when an animated one's time step changes;
+/// off by default, as lighting is applied as a tint after the fact
+pub struct SurfaceCache {
+    maps: HashMap<SurfaceKey, CubeMap>,
+    size: usize,
+    radius: f32,
+    pub enabled: bool,
+}
+
+impl SurfaceCache {
+    /// Creates an empty, disabled cache of `size`² texel faces on spheres of `radius`
+    pub fn new(size: usize, radius: f32) -> Self {
+        SurfaceCache { maps: HashMap::new(), size, radius, enabled: false }
+    }
+
+    /// Cache key and bake time of a body's surface at `time`
+    fn key(&self, seed: u32, material: &Material, time: f32) -> Option<(SurfaceKey, f32)> {
+        if !self.enabled {
+            return None;
+        }
+        let (time_bucket, bake_time) = match material.shader.surface_refresh()? {
+            Refresh::Never => (0, 0.0),
+            Refresh::Every(step) => {
+                let bucket = (time / step).floor();
+                (bucket as i64, bucket * step)
+            }
+        };
+        Some((SurfaceKey { seed, material: material.key(), time_bucket }, bake_time))
+    }
+
+    /// Bakes a body's surface for `time` unless it is already cached; the shader reads
+    /// the noise layers already baked in `noise_cache`
+    pub fn prepare(&mut self, seed: u32, material: &Material, time: f32, noise_cache: &NoiseCache) {
+        let Some((key, bake_time)) = self.key(seed, material, time) else {
+            return;
+        };
+        if self.maps.contains_key(&key) {
+            return;
+        }
+        // Drop stale time steps of the same surface
+        self.maps.retain(|k, _| k.seed != key.seed || k.material != key.material);
+        log::debug!("baking {:?} surface for seed {} at t={bake_time}", material.shader, seed);
+
+        let noise = Noise::new(seed);
+        let baked = noise_cache.lookup(&noise, material, bake_time);
+        let identity = create_model_matrix(Vector3::zero(), 1.0, Vector3::zero());
+        let uniforms = Uniforms {
+            model_matrix: identity,
+            view_matrix: identity,
+            projection_matrix: identity,
+            viewport_matrix: identity,
+            camera_position: Vector3::zero(),
+            // Baked without a sun: what changes between day and night is left out
+            light_direction: Vector3::zero(),
+            time: bake_time,
+            noise,
+            baked,
+            // The emission, eruptions included, is added live over the baked surface
+            eruptions: &[],
+            vortex: None,
+        };
+        self.maps.insert(key, CubeMap::bake(self.size, self.radius, material, &uniforms));
+    }
+
+    /// Baked surface of a body if `prepare` already baked it
+    pub fn lookup(&self, seed: u32, material: &Material, time: f32) -> Option<&CubeMap> {
+        let (key, _) = self.key(seed, material, time)?;
+        self.maps.get(&key)
+    }
+
+    /// Drops every baked surface (e.g. when the scene changes)
+    pub fn clear(&mut self) {
+        self.maps.clear();
+    }
+}
@@ -0,0 +1,236 @@
+// toml.rs
+// Parser for the TOML subset used by config.toml and scene files; the rest of TOML is
+// rejected with the line it is on rather than misread
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// TOML value (only the types used by the config and scene files)
+#[derive(Clone, Debug)]
+pub enum Value {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    pub fn as_f32(&self) -> Option<f32> {
+        match *self {
+            Value::Int(int) => Some(int as f32),
+            Value::Float(float) => Some(float as f32),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(text) => Some(text),
+            _ => None,
+        }
+    }
+}
+
+/// Syntax error with its 1-based line number
+#[derive(Debug)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Key/value pairs of one table
+pub type Table = HashMap<String, Value>;
+
+/// Parsed document
+#[derive(Debug, Default)]
+pub struct Document {
+    /// Top-level and `[table]` keys, as "table.key"
+    pub values: Table,
+    /// Entries of every `[[array]]` table, in file order
+    pub arrays: HashMap<String, Vec<Table>>,
+}
+
+/// Number token: digits with an optional sign, `_` separators, fraction and exponent
+fn parse_number(token: &str) -> Option<Value> {
+    if !token.chars().all(|c| c.is_ascii_digit() || matches!(c, '+' | '-' | '_' | '.' | 'e' | 'E')) {
+        return None;
+    }
+    let number = token.replace('_', "");
+    if let Ok(int) = number.parse::<i64>() {
+        return Some(Value::Int(int));
+    }
+    number.parse::<f64>().ok().map(Value::Float)
+}
+
+/// Body of a `"..."` string after its opening quote; returns the text after the closing one
+fn parse_string(text: &str) -> Result<(String, &str), String> {
+    let mut string = String::new();
+    let mut chars = text.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((string, &text[i + 1..])),
+            '\\' => string.push(match chars.next().map(|(_, c)| c) {
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some('r') => '\r',
+                Some('"') => '"',
+                Some('\\') => '\\',
+                Some(other) => return Err(format!("unsupported escape `\\{other}`")),
+                None => break,
+            }),
+            _ => string.push(c),
+        }
+    }
+    Err("unterminated string".to_string())
+}
+
+/// Parses the value at the start of `text`: a boolean, number, string or single-line
+/// array of those. Returns it with the text after it
+fn parse_value(text: &str) -> Result<(Value, &str), String> {
+    let text = text.trim_start();
+    if text.starts_with("\"\"\"") || text.starts_with("'''") {
+        return Err("multi-line strings are not supported".to_string());
+    }
+    if let Some(rest) = text.strip_prefix('"') {
+        let (string, rest) = parse_string(rest)?;
+        return Ok((Value::Str(string), rest));
+    }
+    if let Some(rest) = text.strip_prefix('\'') {
+        let end = rest.find('\'').ok_or("unterminated string")?;
+        return Ok((Value::Str(rest[..end].to_string()), &rest[end + 1..]));
+    }
+    if let Some(mut rest) = text.strip_prefix('[') {
+        let mut items = Vec::new();
+        loop {
+            rest = rest.trim_start();
+            if let Some(after) = rest.strip_prefix(']') {
+                return Ok((Value::Array(items), after));
+            }
+            if rest.is_empty() || rest.starts_with('#') {
+                return Err("arrays must close on the line they open".to_string());
+            }
+            let (item, after) = parse_value(rest)?;
+            items.push(item);
+            rest = after.trim_start();
+            if let Some(after) = rest.strip_prefix(',') {
+                rest = after;
+            } else if !rest.starts_with(']') {
+                return Err(format!("expected `,` or `]` in array, found `{rest}`"));
+            }
+        }
+    }
+    if text.starts_with('{') {
+        return Err("inline tables are not supported".to_string());
+    }
+
+    // Bare token up to whitespace, a separator or a comment
+    let end = text.find(|c: char| c.is_whitespace() || matches!(c, ',' | ']' | '#')).unwrap_or(text.len());
+    let (token, rest) = text.split_at(end);
+    let value = match token {
+        "" => return Err("missing value".to_string()),
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => parse_number(token).ok_or_else(|| format!("invalid value `{token}`"))?,
+    };
+    Ok((value, rest))
+}
+
+/// Checks a bare key: letters, digits, `_` and `-`
+fn check_key(key: &str) -> Result<(), String> {
+    if key.starts_with(['"', '\'']) {
+        Err(format!("quoted keys are not supported: `{key}`"))
+    } else if key.contains('.') {
+        Err(format!("dotted keys are not supported: `{key}`, use a [table] instead"))
+    } else if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-')) {
+        Err(format!("invalid key `{key}`"))
+    } else {
+        Ok(())
+    }
+}
+
+/// Anything after a value or header must be a comment
+fn check_end(rest: &str) -> Result<(), String> {
+    let rest = rest.trim_start();
+    if rest.is_empty() || rest.starts_with('#') {
+        Ok(())
+    } else {
+        Err(format!("unexpected `{rest}` after the value"))
+    }
+}
+
+/// Parses `[table]` and `[[array]]` headers and `key = value` lines with booleans,
+/// numbers, strings and single-line arrays. Anything else, and keys or tables defined
+/// twice, is an error naming its line
+pub fn parse(text: &str) -> Result<Document, ParseError> {
+    let mut document = Document::default();
+    let mut tables = HashSet::new();
+    let mut table = String::new();
+    // Name of the `[[array]]` whose last entry receives the keys
+    let mut array: Option<String> = None;
+
+    for (index, raw) in text.lines().enumerate() {
+        let error = |message: String| ParseError { line: index + 1, message };
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix('[') {
+            let is_array = header.starts_with('[');
+            let close = if is_array { "]]" } else { "]" };
+            let header = if is_array { &header[1..] } else { header };
+            let (name, rest) = header.split_once(close).ok_or_else(|| error(format!("expected `{close}` to close `{line}`")))?;
+            check_end(rest).map_err(error)?;
+            let name = name.trim();
+            for part in name.split('.') {
+                check_key(part.trim()).map_err(|message| error(format!("{message} in `[{name}]`")))?;
+            }
+
+            if is_array {
+                if tables.contains(name) {
+                    return Err(error(format!("`[[{name}]]` is already a table")));
+                }
+                document.arrays.entry(name.to_string()).or_default().push(Table::new());
+                array = Some(name.to_string());
+            } else {
+                if document.arrays.contains_key(name) {
+                    return Err(error(format!("`[{name}]` is already an array of tables")));
+                }
+                if !tables.insert(name.to_string()) {
+                    return Err(error(format!("table `[{name}]` is defined twice")));
+                }
+                table = name.to_string();
+                array = None;
+            }
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| error(format!("expected `key = value`, found `{line}`")))?;
+        let key = key.trim();
+        check_key(key).map_err(error)?;
+        let (value, rest) = parse_value(value).map_err(error)?;
+        check_end(rest).map_err(error)?;
+
+        let (entries, full_key) = match &array {
+            Some(name) => {
+                let entries = document.arrays.get_mut(name).expect("array entry was pushed");
+                (entries.last_mut().expect("array entry was pushed"), key.to_string())
+            }
+            None => (&mut document.values, if table.is_empty() { key.to_string() } else { format!("{table}.{key}") }),
+        };
+        if entries.insert(full_key, value).is_some() {
+            return Err(error(format!("duplicate key `{key}`")));
+        }
+    }
+
+    Ok(document)
+}
@@ -2,9 +2,14 @@
 // Triangle rasterization using barycentric coordinates
 
 use crate::Vector3;
+use crate::atmosphere::Atmosphere;
 use crate::fragment::Fragment;
 use crate::light::Light;
+use crate::material::Material;
+use crate::shader_system::Relief;
+use crate::shadow::{light_visibility, Occluder};
 use crate::vertex::Vertex;
+use crate::Uniforms;
 
 /// Computes barycentric coordinates for a point in a triangle
 /// Returns (w1, w2, w3) where w1 + w2 + w3 = 1 for points inside the triangle
@@ -49,31 +54,195 @@ fn normalize_vector3(v: &mut Vector3) {
     }
 }
 
+/// Accumulates the Blinn-Phong contribution of every light at a world-space point:
+/// returns the ambient plus diffuse light and, separately, the specular highlight
+#[inline]
+fn blinn_phong(normal: Vector3, position: Vector3, lighting: &Lighting) -> (Vector3, Vector3) {
+    let Material { diffuse: base_color, specular: strength, shininess, two_sided, .. } = lighting.material;
+
+    // Direction from the surface to the camera, shared by every light
+    let mut view_dir = Vector3::new(
+        lighting.eye.x - position.x,
+        lighting.eye.y - position.y,
+        lighting.eye.z - position.z,
+    );
+    normalize_vector3(&mut view_dir);
+
+    let mut shaded_color = Vector3::zero();
+    let mut specular = Vector3::zero();
+    for light in lighting.lights {
+        // Calculate light direction (from surface to light)
+        let mut light_dir = Vector3::new(
+            light.position.x - position.x,
+            light.position.y - position.y,
+            light.position.z - position.z,
+        );
+        let distance = (light_dir.x * light_dir.x + light_dir.y * light_dir.y + light_dir.z * light_dir.z).sqrt();
+        normalize_vector3(&mut light_dir);
+        let radiance = light.intensity * light.attenuation(distance);
+
+        // Two-sided surfaces turn their normal toward the light
+        let facing_light = normal.x * light_dir.x + normal.y * light_dir.y + normal.z * light_dir.z >= 0.0;
+        let normal = if two_sided && !facing_light { -normal } else { normal };
+
+        // Lambertian shading: intensity = max(0, normal · light_dir)
+        let intensity = (normal.x * light_dir.x
+            + normal.y * light_dir.y
+            + normal.z * light_dir.z)
+            .max(0.0)
+            * radiance;
+
+        // Skip the shadow test on faces turned away from the light
+        let visibility = if intensity > 0.0 {
+            light_visibility(position, light, lighting.occluders)
+        } else {
+            0.0
+        };
+
+        // Ambient light reaches every side, diffuse only the lit one
+        let received = light.ambient * light.intensity + intensity * visibility;
+        shaded_color.x += base_color.x * light.color.x * received;
+        shaded_color.y += base_color.y * light.color.y * received;
+        shaded_color.z += base_color.z * light.color.z * received;
+
+        // Specular highlight around the half vector between light and view directions
+        if strength > 0.0 && visibility > 0.0 {
+            let mut half = Vector3::new(
+                light_dir.x + view_dir.x,
+                light_dir.y + view_dir.y,
+                light_dir.z + view_dir.z,
+            );
+            normalize_vector3(&mut half);
+            let facing = (normal.x * half.x + normal.y * half.y + normal.z * half.z).max(0.0);
+            let highlight = facing.powf(shininess) * strength * radiance * visibility;
+            specular.x += light.color.x * highlight;
+            specular.y += light.color.y * highlight;
+            specular.z += light.color.z * highlight;
+        }
+    }
+    (shaded_color, specular)
+}
+
+/// Lit color of a surface point before its shader runs, and its specular highlight;
+/// emissive surfaces skip lighting and receive full white without highlights. Shared
+/// with the ray tracer so both backends light alike
+#[inline]
+pub fn surface_lighting(normal: Vector3, position: Vector3, lighting: &Lighting) -> (Vector3, Vector3) {
+    if lighting.material.emissive {
+        return (Vector3::one(), Vector3::zero());
+    }
+    blinn_phong(normal, position, lighting)
+}
+
+/// Rim of scattered light the surface's atmosphere adds over its shader, zero without
+/// one; `normal` is the smooth normal, the relief doesn't reach the air above it
+#[inline]
+pub fn atmosphere_glow(normal: Vector3, position: Vector3, lighting: &Lighting) -> Vector3 {
+    match lighting.atmosphere {
+        Some(atmosphere) if !lighting.material.emissive => atmosphere.glow(normal, position, lighting.eye, lighting.lights),
+        _ => Vector3::zero(),
+    }
+}
+
+/// Pixel rectangle `[x0, x1) x [y0, y1)` that rasterization is limited to (a screen tile)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PixelRect {
+    pub x0: i32,
+    pub y0: i32,
+    pub x1: i32,
+    pub y1: i32,
+}
+
+impl PixelRect {
+    /// No limit at all
+    pub const UNBOUNDED: PixelRect = PixelRect { x0: i32::MIN, y0: i32::MIN, x1: i32::MAX, y1: i32::MAX };
+
+    pub fn new(x0: i32, y0: i32, x1: i32, y1: i32) -> Self {
+        PixelRect { x0, y0, x1, y1 }
+    }
+}
+
+/// How the fragments of one surface are lit
+#[derive(Clone, Copy)]
+pub struct Lighting<'a> {
+    pub lights: &'a [Light],
+    /// Bodies that can shadow this surface (not the surface's own body)
+    pub occluders: &'a [Occluder],
+    /// Reflectance of the surface; emissive ones skip lighting entirely and receive full
+    /// white
+    pub material: Material,
+    /// World-space camera position, for the specular highlights
+    pub eye: Vector3,
+    /// Surface relief tilting the normals (normal mapping), None for smooth shading
+    pub bump: Option<Bump<'a>>,
+    /// Air around the body, glowing along its limb
+    pub atmosphere: Option<Atmosphere>,
+    /// The vertices' colors tint the diffuse reflectance and their emission is added to
+    /// the lit color (loaded models with materials)
+    pub vertex_colors: bool,
+}
+
+/// Relief of one body together with the uniforms its height field is evaluated with
+#[derive(Clone, Copy)]
+pub struct Bump<'a> {
+    pub relief: Relief,
+    pub uniforms: &'a Uniforms<'a>,
+}
+
+impl Bump<'_> {
+    /// Perturbed world-space normal at a model-space position
+    #[inline]
+    pub fn normal(&self, normal: Vector3, position: Vector3) -> Vector3 {
+        let uniforms = self.uniforms;
+        self.relief.perturb(normal, position, &uniforms.noise, uniforms.time, &uniforms.model_matrix)
+    }
+}
+
+/// Pixel bounding box of a screen-space triangle, inclusive on both ends
+#[inline]
+pub fn pixel_bounds(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> (i32, i32, i32, i32) {
+    let (a, b, c) = (v1.transformed_position, v2.transformed_position, v3.transformed_position);
+    (
+        a.x.min(b.x).min(c.x).floor() as i32,
+        a.y.min(b.y).min(c.y).floor() as i32,
+        a.x.max(b.x).max(c.x).ceil() as i32,
+        a.y.max(b.y).max(c.y).ceil() as i32,
+    )
+}
+
 /// Rasterizes a triangle and generates fragments with per-pixel lighting
 /// Uses barycentric coordinates for interpolation of vertex attributes
-pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex, light: &Light) -> Vec<Fragment> {
+/// Lights blocked by any of the `occluders` are attenuated (soft sphere shadows)
+/// Emissive surfaces skip lighting entirely and receive full white; other surfaces are
+/// lit as a matte material
+pub fn triangle(
+    v1: &Vertex,
+    v2: &Vertex,
+    v3: &Vertex,
+    lights: &[Light],
+    occluders: &[Occluder],
+    emissive: bool,
+) -> Vec<Fragment> {
     let mut fragments = Vec::new();
+    let lighting = Lighting { lights, occluders, material: Material { emissive, ..Material::MATTE }, eye: Vector3::zero(), bump: None, atmosphere: None, vertex_colors: false };
+    rasterize(v1, v2, v3, &lighting, PixelRect::UNBOUNDED, &mut fragments);
+    fragments
+}
 
-    // Base color for the surface
-    let base_color = Vector3::new(0.5, 0.5, 0.5);
-
-    // Calculate bounding box for the triangle
-    let min_x = v1.transformed_position.x
-        .min(v2.transformed_position.x)
-        .min(v3.transformed_position.x)
-        .floor() as i32;
-    let max_x = v1.transformed_position.x
-        .max(v2.transformed_position.x)
-        .max(v3.transformed_position.x)
-        .ceil() as i32;
-    let min_y = v1.transformed_position.y
-        .min(v2.transformed_position.y)
-        .min(v3.transformed_position.y)
-        .floor() as i32;
-    let max_y = v1.transformed_position.y
-        .max(v2.transformed_position.y)
-        .max(v3.transformed_position.y)
-        .ceil() as i32;
+/// `triangle` limited to the pixels inside `bounds`, appending to an existing buffer so
+/// the caller can reuse its allocation
+pub fn rasterize(
+    v1: &Vertex,
+    v2: &Vertex,
+    v3: &Vertex,
+    lighting: &Lighting,
+    bounds: PixelRect,
+    fragments: &mut Vec<Fragment>,
+) {
+    // Bounding box of the triangle, clipped to the allowed rectangle
+    let (min_x, min_y, max_x, max_y) = pixel_bounds(v1, v2, v3);
+    let (min_x, max_x) = (min_x.max(bounds.x0), max_x.min(bounds.x1.saturating_sub(1)));
+    let (min_y, max_y) = (min_y.max(bounds.y0), max_y.min(bounds.y1.saturating_sub(1)));
 
     // Iterate over bounding box and test each pixel
     for y in min_y..=max_y {
@@ -100,27 +269,28 @@ pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex, light: &Light) -> Vec<Fra
                     w1 * v1.position.z + w2 * v2.position.z + w3 * v3.position.z,
                 );
 
-                // Calculate light direction (from surface to light)
-                let mut light_dir = Vector3::new(
-                    light.position.x - world_pos.x,
-                    light.position.y - world_pos.y,
-                    light.position.z - world_pos.z,
-                );
-                normalize_vector3(&mut light_dir);
-
-                // Lambertian shading: intensity = max(0, normal · light_dir)
-                let intensity = (interpolated_normal.x * light_dir.x
-                    + interpolated_normal.y * light_dir.y
-                    + interpolated_normal.z * light_dir.z)
-                    .max(0.0);
-
-                // Apply lighting to base color
-                let shaded_color = Vector3::new(
-                    base_color.x * intensity,
-                    base_color.y * intensity,
-                    base_color.z * intensity,
+                // Interpolate the lit position in world space
+                let lit_pos = Vector3::new(
+                    w1 * v1.world_position.x + w2 * v2.world_position.x + w3 * v3.world_position.x,
+                    w1 * v1.world_position.y + w2 * v2.world_position.y + w3 * v3.world_position.y,
+                    w1 * v1.world_position.z + w2 * v2.world_position.z + w3 * v3.world_position.z,
                 );
 
+                let normal = match lighting.bump {
+                    Some(bump) if !lighting.material.emissive => bump.normal(interpolated_normal, world_pos),
+                    _ => interpolated_normal,
+                };
+                let (shaded_color, specular) = if lighting.vertex_colors && !lighting.material.emissive {
+                    let base_color = v1.color * w1 + v2.color * w2 + v3.color * w3;
+                    let emission = v1.emission * w1 + v2.emission * w2 + v3.emission * w3;
+                    let material = Material { diffuse: lighting.material.diffuse * base_color, ..lighting.material };
+                    let (color, specular) = surface_lighting(normal, lit_pos, &Lighting { material, ..*lighting });
+                    (color + emission, specular)
+                } else {
+                    surface_lighting(normal, lit_pos, lighting)
+                };
+                let glow = atmosphere_glow(interpolated_normal, lit_pos, lighting);
+
                 // Interpolate depth for depth testing
                 let depth = w1 * v1.transformed_position.z
                     + w2 * v2.transformed_position.z
@@ -130,12 +300,52 @@ pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex, light: &Light) -> Vec<Fra
                     p_x,
                     p_y,
                     shaded_color,
+                    specular,
+                    glow,
                     depth,
                     world_pos,
-                ));
+                ).with_normal(normal));
             }
         }
     }
+}
 
-    fragments
-}
\ No newline at end of file
+/// Edges of a triangle for the wireframe debug view: white fragments on the pixels inside
+/// `bounds` within about half a pixel of one of its sides, carrying the interpolated
+/// normal and depth so edges hide behind nearer ones
+pub fn rasterize_edges(v1: &Vertex, v2: &Vertex, v3: &Vertex, bounds: PixelRect, fragments: &mut Vec<Fragment>) {
+    let (a, b, c) = (v1.transformed_position, v2.transformed_position, v3.transformed_position);
+    let area = ((b.x - a.x) * (c.y - a.y) - (c.x - a.x) * (b.y - a.y)).abs();
+    if area < 1e-10 {
+        return;
+    }
+    let length = |p: Vector3, q: Vector3| (p.x - q.x).hypot(p.y - q.y).max(1e-6);
+    // Pixel distance to the side opposite each vertex per unit of its weight
+    let heights = (area / length(b, c), area / length(c, a), area / length(a, b));
+
+    let (min_x, min_y, max_x, max_y) = pixel_bounds(v1, v2, v3);
+    let (min_x, max_x) = (min_x.max(bounds.x0), max_x.min(bounds.x1.saturating_sub(1)));
+    let (min_y, max_y) = (min_y.max(bounds.y0), max_y.min(bounds.y1.saturating_sub(1)));
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            let (p_x, p_y) = (x as f32 + 0.5, y as f32 + 0.5);
+            let (w1, w2, w3) = barycentric_coordinates(p_x, p_y, v1, v2, v3);
+            if w1 < 0.0 || w2 < 0.0 || w3 < 0.0 {
+                continue;
+            }
+            let edge = (w1 * heights.0).min(w2 * heights.1).min(w3 * heights.2);
+            if edge > 0.5 {
+                continue;
+            }
+
+            let mut normal = v1.transformed_normal * w1 + v2.transformed_normal * w2 + v3.transformed_normal * w3;
+            normalize_vector3(&mut normal);
+            let depth = w1 * a.z + w2 * b.z + w3 * c.z;
+            let world_pos = v1.position * w1 + v2.position * w2 + v3.position * w3;
+            fragments.push(
+                Fragment::new_with_world_pos(p_x, p_y, Vector3::one(), Vector3::zero(), Vector3::zero(), depth, world_pos)
+                    .with_normal(normal),
+            );
+        }
+    }
+}
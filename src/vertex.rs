@@ -1,7 +1,7 @@
 // vertex.rs
 // Vertex data structure for 3D rendering
 
-use raylib::math::{Vector2, Vector3};
+use raylib::math::{Vector2, Vector3, Vector4};
 
 /// Represents a vertex with all necessary attributes for rendering
 #[derive(Clone, Debug)]
@@ -14,10 +14,16 @@ pub struct Vertex {
     pub tex_coords: Vector2,
     /// Vertex color (if using vertex colors)
     pub color: Vector3,
+    /// Light the surface gives off at this vertex whatever the lighting (with vertex colors)
+    pub emission: Vector3,
     /// Position after transformation to screen space
     pub transformed_position: Vector3,
+    /// Position after transformation to world space (for lighting)
+    pub world_position: Vector3,
     /// Normal after transformation to world space
     pub transformed_normal: Vector3,
+    /// Homogeneous position before the perspective divide, used for clipping
+    pub clip_position: Vector4,
 }
 
 impl Vertex {
@@ -28,8 +34,11 @@ impl Vertex {
             normal,
             tex_coords,
             color: Vector3::new(0.0, 0.0, 0.0),
+            emission: Vector3::zero(),
             transformed_position: position,
+            world_position: position,
             transformed_normal: normal,
+            clip_position: Vector4::new(position.x, position.y, position.z, 1.0),
         }
     }
 }
@@ -41,8 +50,11 @@ impl Default for Vertex {
             normal: Vector3::new(0.0, 1.0, 0.0),
             tex_coords: Vector2::zero(),
             color: Vector3::zero(),
+            emission: Vector3::zero(),
             transformed_position: Vector3::zero(),
+            world_position: Vector3::zero(),
             transformed_normal: Vector3::new(0.0, 1.0, 0.0),
+            clip_position: Vector4::new(0.0, 0.0, 0.0, 1.0),
         }
     }
 }
\ No newline at end of file
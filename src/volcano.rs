@@ -0,0 +1,178 @@
+// volcano.rs
+// Volcanic eruptions on lava planets: every few seconds a point of the surface bursts,
+// glowing in the lava shader and throwing up a plume of particles before it cools down
+
+use raylib::prelude::*;
+use crate::matrix::multiply_matrix_vector4;
+use crate::mesh::SPHERE_RADIUS;
+use crate::particles::{Emitter, ParticleShape, ParticleSystem};
+use crate::solar_system::SolarSystem;
+
+/// Average seconds between two eruptions of a body
+const ERUPTION_INTERVAL: f32 = 4.0;
+
+/// Seconds an eruption lasts, and how much of that it takes to flare up
+const ERUPTION_DURATION: f32 = 5.0;
+const ERUPTION_RISE: f32 = 0.4;
+
+/// Most eruptions a body keeps going at once (and the GPU shader knows about)
+pub const MAX_ERUPTIONS: usize = 8;
+
+/// Particles a plume emits per second at the height of its eruption, their speed away
+/// from the surface (times the body's scale) and how long they last
+const PLUME_RATE: f32 = 60.0;
+const PLUME_SPEED: f32 = 1.2;
+const PLUME_LIFETIME: f32 = 1.6;
+
+/// Particles kept per body
+const PLUME_PARTICLES: usize = 400;
+
+/// Yellow-hot ash at the vent, dark red smoke higher up
+const PLUME_HOT: Vector3 = Vector3 { x: 1.0, y: 0.75, z: 0.3 };
+const PLUME_COOL: Vector3 = Vector3 { x: 0.45, y: 0.1, z: 0.05 };
+
+/// How quickly the plume slows down as it rises, per second
+const PLUME_DRAG: f32 = 1.5;
+
+/// One eruption at a point of the surface
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Eruption {
+    /// Unit direction of the vent in model space, where the shaders sample the surface
+    pub site: Vector3,
+    /// Seconds since it started
+    pub age: f32,
+    /// Seconds it lasts
+    pub duration: f32,
+}
+
+impl Eruption {
+    /// How hot the eruption burns, 0..1: flares up quickly, then cools down until it ends
+    pub fn heat(&self) -> f32 {
+        if self.age < 0.0 || self.age >= self.duration {
+            return 0.0;
+        }
+        let rise = (self.age / ERUPTION_RISE).min(1.0);
+        let fade = 1.0 - (self.age - ERUPTION_RISE).max(0.0) / (self.duration - ERUPTION_RISE);
+        rise * fade * fade
+    }
+}
+
+/// Eruption state of a body, updated by `SolarSystem::update` and read by its shader
+#[derive(Clone, Debug)]
+pub struct Volcanoes {
+    /// Eruptions still going, oldest first
+    pub eruptions: Vec<Eruption>,
+    /// Average seconds between two eruptions
+    pub interval: f32,
+    /// Ash thrown up by the eruptions, in world space
+    pub plume: ParticleSystem,
+    /// Seconds until the next eruption
+    countdown: f32,
+    /// Eruptions started so far, numbering the hashed sites and intervals
+    started: u32,
+}
+
+/// Hash of an eruption number, a coordinate and the body's seed, spread over 0..1
+fn hash(eruption: u32, axis: u32, seed: u32) -> f32 {
+    let mut h = eruption.wrapping_mul(0x9E37_79B9) ^ axis.wrapping_mul(0x85EB_CA6B) ^ seed.wrapping_mul(0xC2B2_AE35);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2C1B_3C6D);
+    h ^= h >> 12;
+    (h & 0xFFFF) as f32 / 65535.0
+}
+
+impl Default for Volcanoes {
+    fn default() -> Self {
+        let emitter = Emitter {
+            lifetime: PLUME_LIFETIME,
+            color_start: PLUME_HOT,
+            color_end: PLUME_COOL,
+            ..Emitter::default()
+        };
+        let mut plume = ParticleSystem::new(emitter, PLUME_PARTICLES).with_shape(ParticleShape::Quad { soft: true });
+        plume.drag = PLUME_DRAG;
+        Volcanoes {
+            eruptions: Vec::new(),
+            interval: ERUPTION_INTERVAL,
+            plume,
+            countdown: ERUPTION_INTERVAL * 0.5,
+            started: 0,
+        }
+    }
+}
+
+impl Volcanoes {
+    /// Starts an eruption at a point picked from the body's `seed` and the eruption count;
+    /// the oldest one is dropped past `MAX_ERUPTIONS`
+    pub fn erupt(&mut self, seed: u32) {
+        let id = self.started;
+        self.started = self.started.wrapping_add(1);
+        // Uniform over the sphere: height and longitude
+        let y = hash(id, 0, seed) * 2.0 - 1.0;
+        let longitude = hash(id, 1, seed) * std::f32::consts::TAU;
+        let around = (1.0 - y * y).sqrt();
+        self.eruptions.push(Eruption {
+            site: Vector3::new(longitude.cos() * around, y, longitude.sin() * around),
+            age: 0.0,
+            duration: ERUPTION_DURATION * (0.7 + hash(id, 2, seed) * 0.6),
+        });
+        if self.eruptions.len() > MAX_ERUPTIONS {
+            self.eruptions.remove(0);
+        }
+    }
+
+    /// Ages the eruptions, starts new ones every `interval` seconds or so and lets the
+    /// burning ones throw up ash from the body drawn with `model` (its model matrix,
+    /// `scale` times the mesh radius across); going back in time puts them all out
+    pub fn update(&mut self, delta_time: f32, seed: u32, model: &Matrix, scale: f32) {
+        if delta_time < 0.0 {
+            self.eruptions.clear();
+            self.plume.clear();
+            return;
+        }
+        for eruption in &mut self.eruptions {
+            eruption.age += delta_time;
+        }
+        self.eruptions.retain(|eruption| eruption.age < eruption.duration);
+        if self.interval > 0.0 {
+            self.countdown -= delta_time;
+            while self.countdown <= 0.0 {
+                self.erupt(seed);
+                self.countdown += self.interval * (0.5 + hash(self.started, 3, seed));
+            }
+        }
+
+        self.plume.update(delta_time, Vector3::zero());
+        self.plume.emitter.size = scale * 0.05;
+        for eruption in &self.eruptions {
+            let vent = multiply_matrix_vector4(model, &Vector4::new(
+                eruption.site.x * SPHERE_RADIUS,
+                eruption.site.y * SPHERE_RADIUS,
+                eruption.site.z * SPHERE_RADIUS,
+                1.0,
+            ));
+            let origin = multiply_matrix_vector4(model, &Vector4::new(0.0, 0.0, 0.0, 1.0));
+            let (vent, origin) = (Vector3::new(vent.x, vent.y, vent.z), Vector3::new(origin.x, origin.y, origin.z));
+            let up = (vent - origin).normalized();
+            let heat = eruption.heat();
+            self.plume.emitter.position = vent;
+            self.plume.emitter.velocity = up * (PLUME_SPEED * scale * (0.5 + heat));
+            self.plume.emitter.spread = PLUME_SPEED * scale * 0.3;
+            self.plume.emitter.rate = PLUME_RATE * heat;
+            self.plume.emit(delta_time);
+        }
+    }
+}
+
+impl SolarSystem {
+    /// Runs the eruptions of every volcanic body
+    pub(crate) fn update_eruptions(&mut self, delta_time: f32) {
+        for object in &mut self.objects {
+            let model = object.model_matrix();
+            let (seed, scale) = (object.seed, object.scale);
+            if let Some(volcanoes) = &mut object.volcanoes {
+                volcanoes.update(delta_time, seed, &model, scale);
+            }
+        }
+    }
+}
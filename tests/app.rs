@@ -0,0 +1,336 @@
+// app.rs
+// Integration tests for configuration, command-line options, startup, input bindings, logging and the HUD
+
+mod common;
+
+use common::sphere;
+use lab5::app::Startup;
+use lab5::camera::Camera;
+use lab5::cli::{Options, Preset};
+use lab5::config::{Config, Quality};
+use lab5::gizmos::{body_labels, draw_orbit_paths};
+use lab5::hud::{help_lines, slowest_objects};
+use lab5::input::{Action, Binding};
+use lab5::logger::Filter;
+use lab5::matrix::project_to_screen;
+use lab5::profiler::{Profiler, Stage, StageTimings, AVERAGE_FRAMES};
+use lab5::renderer::{ImageBuffer, Renderer};
+use lab5::simulation::{next_time_scale, TIME_SCALES};
+use lab5::solar_system::SolarSystem;
+use lab5::toml::{self, Value};
+use raylib::consts::{GamepadButton, KeyboardKey};
+use raylib::math::Vector3;
+use std::f32::consts::PI;
+use std::time::Duration;
+
+#[test]
+fn log_filter_applies_most_specific_module() {
+    let filter = Filter::parse("warn, lab5::scene_file=debug, lab5=info").unwrap();
+
+    assert_eq!(filter.level("lab5::scene_file"), log::LevelFilter::Debug);
+    assert_eq!(filter.level("lab5::renderer"), log::LevelFilter::Info);
+    assert_eq!(filter.level("lab5x"), log::LevelFilter::Warn);
+    assert!(Filter::parse("loud").is_err());
+}
+
+#[test]
+fn speed_keys_step_through_the_time_scales() {
+    assert_eq!(next_time_scale(1.0, true), 2.0);
+    assert_eq!(next_time_scale(1.0, false), 0.5);
+    // Speeds set on the panel go to the nearest step in the direction asked
+    assert_eq!(next_time_scale(3.0, true), 5.0);
+    assert_eq!(next_time_scale(3.0, false), 2.0);
+    // The ends of the range hold
+    assert_eq!(next_time_scale(1000.0, true), 1000.0);
+    assert_eq!(next_time_scale(0.1, false), 0.1);
+    assert_eq!(next_time_scale(0.0, true), TIME_SCALES[0]);
+
+    let mut scale = 0.1;
+    for _ in 0..TIME_SCALES.len() - 1 {
+        scale = next_time_scale(scale, true);
+    }
+    assert_eq!(scale, 1000.0);
+}
+
+#[test]
+fn config_bindings_replace_the_defaults() {
+    let config = Config::parse("[bindings]\ntoggle_pause = [\"space\", \"PAD_START\"]\nzoom_in = []\n").unwrap();
+    let pause: Vec<Binding> = config.bindings.bindings(Action::TogglePause).collect();
+    assert_eq!(pause, [
+        Binding::Key(KeyboardKey::KEY_SPACE),
+        Binding::Gamepad(GamepadButton::GAMEPAD_BUTTON_MIDDLE_RIGHT),
+    ]);
+    assert_eq!(config.bindings.describe(Action::ZoomIn), "unbound");
+    assert_eq!(config.bindings.describe(Action::PanLeft), "Q/LEFT/PAD_LB");
+
+    assert!(Config::parse("[bindings]\nwarp = \"W\"\n").is_err());
+    assert!(Config::parse("[bindings]\ntoggle_hud = \"KEY_NOPE\"\n").is_err());
+}
+
+#[test]
+fn help_panel_lists_the_current_bindings() {
+    let config = Config::parse("[bindings]\ntoggle_pause = [\"SPACE\", \"PAD_START\"]\nzoom_in = []\n").unwrap();
+    let help = help_lines(&config.bindings);
+    let row = |description: &str| help.iter().find(|(_, d)| *d == description).map(|(keys, _)| keys.clone()).unwrap();
+    // Gamepad buttons are left out, unbound actions show as a dash
+    assert_eq!(row("Pause / step one frame"), "SPACE PERIOD");
+    assert_eq!(row("Zoom in / out"), "- DOWN");
+    assert_eq!(row("This help"), "F1");
+}
+
+#[test]
+fn body_labels_fade_when_another_body_hides_them() {
+    let mesh = sphere();
+    let mut renderer = Renderer::new(160, 120, &mesh);
+    let mut system = SolarSystem::parse_scene(
+        "[[body]]\nname = \"sun\"\ntype = \"star\"\nscale = 2.0\n\n\
+         [[body]]\nname = \"pebble\"\nparent = \"sun\"\norbit_radius = 10.0\norbit_angle = 0.0\nscale = 0.3\n\n\
+         [[body]]\nparent = \"sun\"\norbit_radius = 10.0\norbit_angle = 1.5708\nscale = 0.3\n",
+    )
+    .expect("Failed to parse scene");
+    system.objects[2].name.clear();
+    system.update(0.0);
+    let mut view_from = |eye: Vector3| {
+        let camera = Camera::new(eye, Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+        renderer.render_frame(&system, &camera, 0.0);
+        let view = camera.get_view_matrix();
+        body_labels(&renderer.framebuffer, &system, &view, &renderer.projection, &renderer.viewport, renderer.mesh_radius())
+    };
+
+    // Unnamed bodies get no label; from above every body shows fully
+    let labels = view_from(Vector3::new(0.0, 30.0, 0.1));
+    let names: Vec<&str> = labels.iter().map(|label| label.text.as_str()).collect();
+    assert_eq!(names, ["sun", "pebble"]);
+    assert!(labels.iter().all(|label| label.alpha == 1.0));
+    let (sun, pebble) = (&labels[0], &labels[1]);
+    assert!((sun.x - 80).abs() <= 1 && sun.y < 60, "the label sits above the body");
+    assert!(pebble.x > sun.x && pebble.y < 60 && pebble.y > sun.y, "the smaller body's label sits lower");
+
+    // From the far side the sun hides the pebble
+    let labels = view_from(Vector3::new(-30.0, 0.0, 0.0));
+    let pebble = labels.iter().find(|label| label.text == "pebble").unwrap();
+    assert_eq!(pebble.alpha, 0.0);
+    let sun = labels.iter().find(|label| label.text == "sun").unwrap();
+    assert_eq!(sun.alpha, 1.0);
+}
+
+#[test]
+fn orbit_paths_trace_the_elliptical_orbits() {
+    let mesh = sphere();
+    let mut renderer = Renderer::new(160, 120, &mesh);
+    let mut system = SolarSystem::parse_scene(
+        "[[body]]\nname = \"sun\"\ntype = \"star\"\n\n\
+         [[body]]\nname = \"rock\"\nparent = \"sun\"\norbit_radius = 10.0\norbit_angle = 0.0\neccentricity = 0.4\n",
+    )
+    .expect("Failed to parse scene");
+    system.update(0.0);
+    let camera = Camera::new(Vector3::new(0.0, 30.0, 0.1), Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+    let view = camera.get_view_matrix();
+    let frame = renderer.render_frame(&system, &camera, 0.0);
+
+    draw_orbit_paths(&mut renderer.framebuffer, &system, &view, &renderer.projection, &renderer.viewport);
+    // The apoapsis, opposite the planet, is only drawn by the overlay
+    let apoapsis = system.objects[0].position + system.objects[1].orbit.offset(10.0, PI);
+    let screen = project_to_screen(apoapsis, &view, &renderer.projection, &renderer.viewport).expect("apoapsis behind the camera");
+    let (x, y) = (screen.x.round() as i32, screen.y.round() as i32);
+    let near = |x: i32, y: i32| (-1..=1).any(|dy| (-1..=1).any(|dx| renderer.framebuffer.pixel(x + dx, y + dy) != frame.pixel((x + dx) as usize, (y + dy) as usize)));
+    assert!(near(x, y), "no path at the apoapsis");
+    // The star at the focus is not on the path
+    assert_eq!(renderer.framebuffer.pixel(80, 60), frame.pixel(80, 60));
+}
+
+#[test]
+fn quality_presets_set_render_options_that_single_keys_override() {
+    let config = Config::parse("[render]\nquality = \"low\"\nshadows = true\nfov = 75.0\n\n[window]\nvsync = true\n").unwrap();
+    assert_eq!(config.render.scale, 0.5);
+    assert!(!config.render.bloom && !config.render.normal_mapping);
+    assert!(config.render.shadows, "explicit key lost to the quality preset");
+    assert_eq!(config.render.fov, 75.0);
+    assert!(config.window.vsync);
+    assert!(Config::parse("[render]\nquality = \"extreme\"\n").is_err());
+
+    // High is the defaults
+    let mut render = Config::default().render;
+    Quality::High.apply(&mut render);
+    let defaults = Config::default().render;
+    assert_eq!((render.scale, render.shadows, render.lod, render.noise_cache), (defaults.scale, defaults.shadows, defaults.lod, defaults.noise_cache));
+
+    // The field of view carries over to resized renderers
+    let mesh = sphere();
+    let mut renderer = Renderer::new(64, 48, &mesh);
+    let narrow = renderer.projection;
+    renderer.set_fov(90f32.to_radians());
+    assert_ne!(renderer.projection, narrow);
+    assert_eq!(renderer.resized(64, 48).projection, renderer.projection);
+}
+
+#[test]
+fn toml_parser_reads_the_supported_subset() {
+    let document = toml::parse(
+        "# comment\n\
+         title = \"a # not a comment, \\\"quoted\\\"\"  # comment\n\
+         path = 'C:\\\\raw'\n\
+         count = 1_000\n\
+         ratio = -2.5e-1\n\
+         on = true\n\n\
+         [render.post]\n\
+         colors = [[1, 0.5], [], \"x, y\"] # nested\n\n\
+         [[body]]\n\
+         name = \"sun\"\n\
+         [[body]]\n\
+         name = \"moon\"\n",
+    )
+    .expect("valid document");
+
+    let value = |key: &str| document.values.get(key).unwrap_or_else(|| panic!("missing {key}"));
+    assert_eq!(value("title").as_str(), Some("a # not a comment, \"quoted\""));
+    assert_eq!(value("path").as_str(), Some("C:\\\\raw"));
+    assert!(matches!(value("count"), Value::Int(1000)));
+    assert_eq!(value("ratio").as_f32(), Some(-0.25));
+    assert!(matches!(value("on"), Value::Bool(true)));
+    let Value::Array(colors) = value("render.post.colors") else { panic!("not an array") };
+    assert!(matches!(&colors[..], [Value::Array(pair), Value::Array(empty), Value::Str(text)] if pair.len() == 2 && empty.is_empty() && text == "x, y"));
+    let names: Vec<_> = document.arrays["body"].iter().map(|entry| entry["name"].as_str().unwrap()).collect();
+    assert_eq!(names, ["sun", "moon"]);
+}
+
+#[test]
+fn toml_parser_rejects_the_rest_with_its_line() {
+    let line = |text: &str| toml::parse(text).map(|_| ()).unwrap_err().line;
+
+    // Unsupported TOML is refused instead of misread
+    assert_eq!(line("a = 1\nb = { x = 1 }\n"), 2);
+    assert_eq!(line("a = 1\n\nb = [1,\n  2]\n"), 3);
+    assert_eq!(line("a.b = 1\n"), 1);
+    assert_eq!(line("\"a\" = 1\n"), 1);
+    assert_eq!(line("a = \"\"\"\ntext\n\"\"\"\n"), 1);
+    assert_eq!(line("a = 1979-05-27\n"), 1);
+    assert_eq!(line("a = \"\\u00e9\"\n"), 1);
+    assert_eq!(line("a = \"open\n"), 1);
+    // So is anything left after a value, or a header that doesn't close
+    assert_eq!(line("a = 1 2\n"), 1);
+    assert_eq!(line("a = [1 2]\n"), 1);
+    assert_eq!(line("[table\nkey = 1\n"), 1);
+    assert_eq!(line("[[body]\n"), 1);
+    assert_eq!(line("a = \n"), 1);
+    assert_eq!(line("just text\n"), 1);
+
+    // Keys and tables defined twice are errors, in a table or an array entry
+    assert_eq!(line("a = 1\na = 2\n"), 2);
+    assert_eq!(line("[render]\nbloom = true\n\n[window]\nvsync = true\n[render]\nfov = 60\n"), 6);
+    assert_eq!(line("[render]\nbloom = true\nbloom = false\n"), 3);
+    assert_eq!(line("[[body]]\nname = \"a\"\nname = \"b\"\n"), 3);
+    assert_eq!(line("[body]\n[[body]]\n"), 2);
+    // while each array entry has its own keys
+    assert!(toml::parse("[[body]]\nname = \"a\"\n[[body]]\nname = \"b\"\n").is_ok());
+
+    // The scene and config loaders pass the line on
+    assert!(Config::parse("[window]\nwidth = 800\nwidth = 1024\n").unwrap_err().to_string().contains("line 3"));
+    assert!(SolarSystem::parse_scene("[[body]]\nname = \"sun\"\ntype = \"star\"\nscale = { x = 1 }\n").is_err());
+}
+
+#[test]
+fn command_line_flags_override_the_config() {
+    let parse = |args: &[&str], defaults: Options| Options::parse(args.iter().map(|arg| arg.to_string()), defaults);
+    let config = Config::parse("[render]\nfov = 50.0\nthreads = 3\n\n[scene]\npreset = \"generated\"\n").unwrap();
+
+    // Both "--flag value" and "--flag=value" work, and unset flags keep the config's value
+    let options = parse(
+        &["--resolution", "800x600", "--seed=9", "--preset", "alien", "--threads", "2", "--raytrace", "--headless"],
+        Options::from_config(&config),
+    )
+    .unwrap()
+    .expect("not a help request");
+    assert_eq!((options.width, options.height), (800, 600));
+    assert_eq!(options.seed, 9);
+    assert_eq!(options.preset, Preset::Alien);
+    assert_eq!(options.threads, 2);
+    assert_eq!(options.backend, "raytracer");
+    assert!(options.headless);
+    assert_eq!(options.fov, 50.0);
+    assert_eq!(Options::from_config(&config).preset, Preset::Generated);
+    assert!(parse(&["--fov", "75", "--help"], Options::default()).unwrap().is_none());
+
+    let error = |args: &[&str]| parse(args, Options::default()).map(|_| ()).unwrap_err();
+    // Unknown flags and names
+    assert_eq!(error(&["--warp", "9"]), "unknown option '--warp'");
+    assert_eq!(error(&["--seed=1", "-x"]), "unknown option '-x'");
+    assert!(error(&["--preset", "nowhere"]).starts_with("unknown preset 'nowhere'"));
+    assert!(error(&["--backend", "vulkan"]).starts_with("unknown backend 'vulkan'"));
+    // Flags missing their value
+    assert_eq!(error(&["--seed"]), "missing value for --seed");
+    assert_eq!(error(&["--headless", "--scene"]), "missing value for --scene");
+    // Values that don't parse or are out of range
+    assert_eq!(error(&["--seed", "many"]), "invalid value 'many' for --seed");
+    assert!(error(&["--resolution", "800"]).starts_with("invalid resolution '800'"));
+    assert_eq!(error(&["--width", "0"]), "resolution must be positive");
+    assert!(error(&["--fov=200"]).contains("between 10 and 150"));
+    assert!(error(&["--record", "a", "--replay", "b"]).contains("cannot be combined"));
+}
+
+#[test]
+fn startup_runs_the_windowless_modes_and_reports_missing_files() {
+    let output = std::env::temp_dir().join(format!("lab5_headless_{}.ppm", std::process::id()));
+    let options = Options {
+        width: 64,
+        height: 48,
+        render_scale: 0.5,
+        headless: true,
+        output: output.display().to_string(),
+        ..Options::default()
+    };
+    let mut startup = Startup::new(options, Config::default()).unwrap();
+    assert_eq!(startup.window_size(), (64, 48));
+    startup.run_windowless().expect("a windowless run").unwrap();
+    // Images keep the requested resolution whatever the render scale
+    let image = ImageBuffer::read_ppm(&output).unwrap();
+    assert_eq!((image.width, image.height), (64, 48));
+    std::fs::remove_file(&output).unwrap();
+
+    // Interactive runs are left to the window
+    let interactive = Options { width: 64, height: 48, ..Options::default() };
+    assert!(Startup::new(interactive, Config::default()).unwrap().run_windowless().is_none());
+
+    let missing = Options { script: Some("missing.script".to_string()), ..Options::default() };
+    let error = Startup::new(missing, Config::default()).err().expect("a missing script");
+    assert!(error.starts_with("could not load script missing.script"), "{error}");
+}
+
+#[test]
+fn profiler_averages_recent_frames_and_times_objects_on_request() {
+    let mut profiler = Profiler::new();
+    let frame = |ms: u64| {
+        let mut timings = StageTimings::default();
+        timings.add(Stage::Rasterization, Duration::from_millis(ms));
+        timings
+    };
+    profiler.end_frame(frame(100)).unwrap();
+    for _ in 0..AVERAGE_FRAMES {
+        profiler.end_frame(frame(2)).unwrap();
+    }
+    profiler.end_frame(frame(8)).unwrap();
+    assert_eq!(profiler.last_frame().get(Stage::Rasterization), Duration::from_millis(8));
+    let expected = Duration::from_millis(2 * (AVERAGE_FRAMES as u64 - 1) + 8) / AVERAGE_FRAMES as u32;
+    assert_eq!(profiler.average().get(Stage::Rasterization), expected, "older frames drop out");
+
+    let mesh = sphere();
+    let mut renderer = Renderer::new(160, 120, &mesh);
+    let system = SolarSystem::create_basic_system();
+    let camera = Camera::new(Vector3::new(0.0, 20.0, 40.0), Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+    let plain = renderer.render_frame(&system, &camera, 0.0);
+    assert!(renderer.stats.object_timings.is_empty());
+
+    renderer.profile_objects = true;
+    let profiled = renderer.render_frame(&system, &camera, 0.0);
+    assert!(profiled.pixels == plain.pixels);
+    let stats = &renderer.stats;
+    assert!(stats.object_timings.len() <= system.objects.len());
+    let timed = stats.object_timings.iter().filter(|timings| !timings.total().is_zero()).count();
+    assert_eq!(timed, stats.objects, "every drawn body has a time");
+    let objects_total: Duration = stats.object_timings.iter().map(StageTimings::total).sum();
+    assert!(objects_total <= stats.timings.total());
+
+    let slowest = slowest_objects(&system, stats, 3);
+    assert_eq!(slowest.len(), 3);
+    assert!(slowest.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+}
@@ -0,0 +1,243 @@
+// camera.rs
+// Integration tests for the camera, its controls and body picking
+
+mod common;
+
+use common::sphere;
+use lab5::camera::{Camera, CameraMode};
+use lab5::camera_path::{CameraAnimation, CameraKey, Easing, Playback};
+use lab5::focus::Focus;
+use lab5::input::{Action, ClickDetector, InputFrame};
+use lab5::picking::pick_body;
+use lab5::renderer::Renderer;
+use lab5::solar_system::SolarSystem;
+use raylib::math::{Vector2, Vector3};
+
+#[test]
+fn clicks_pick_the_nearest_body_under_the_cursor() {
+    let mut system = SolarSystem::parse_scene(
+        "[[body]]\nname = \"sun\"\ntype = \"star\"\nscale = 6.0\n\n\
+         [[body]]\nname = \"pebble\"\nparent = \"sun\"\norbit_radius = 10.0\norbit_angle = 0.0\nscale = 0.5\n",
+    )
+    .expect("Failed to parse scene");
+    system.update(0.0);
+    let renderer = Renderer::new(160, 120, &sphere());
+    let pick = |eye: Vector3, x: f32, y: f32| {
+        let camera = Camera::new(eye, Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+        let view = camera.get_view_matrix();
+        pick_body(&system, &view, &renderer.projection, renderer.mesh_radius(), Vector2::new(x, y), 160.0, 120.0)
+    };
+
+    // The pebble sits in front of the sun's center, the sun's edge shows around it
+    assert_eq!(pick(Vector3::new(30.0, 0.0, 0.0), 80.0, 60.0), Some(1));
+    assert_eq!(pick(Vector3::new(-30.0, 0.0, 0.0), 80.0, 60.0), Some(0));
+    assert_eq!(pick(Vector3::new(30.0, 0.0, 0.0), 80.0, 56.0), Some(0));
+    assert_eq!(pick(Vector3::new(30.0, 0.0, 0.0), 5.0, 5.0), None);
+
+    // Press and release in place is a click, a drag is not; two quick clicks make a double
+    let frame = |time: f32, down: bool, pressed: bool, moved: f32| InputFrame {
+        time,
+        down: if down { vec![Action::Pick] } else { Vec::new() },
+        pressed: if pressed { vec![Action::Pick] } else { Vec::new() },
+        mouse_delta: Vector2::new(moved, 0.0),
+        mouse_position: Vector2::new(40.0, 30.0),
+        ..InputFrame::default()
+    };
+    let mut clicks = ClickDetector::default();
+    assert_eq!(clicks.update(&frame(0.0, true, true, 0.0)), None);
+    let click = clicks.update(&frame(0.05, false, false, 1.0)).expect("released in place");
+    assert_eq!((click.position, click.double), (Vector2::new(40.0, 30.0), false));
+    clicks.update(&frame(0.2, true, true, 0.0));
+    assert!(clicks.update(&frame(0.25, false, false, 0.0)).is_some_and(|click| click.double));
+
+    clicks.update(&frame(2.0, true, true, 0.0));
+    clicks.update(&frame(2.05, true, false, 30.0));
+    assert_eq!(clicks.update(&frame(2.1, false, false, 0.0)), None, "dragging orbits instead");
+}
+
+#[test]
+fn mouse_drags_orbit_pan_and_zoom_the_camera() {
+    let up = Vector3::new(0.0, 1.0, 0.0);
+    let drag = |action: Action, dx: f32, dy: f32| InputFrame {
+        down: vec![action],
+        mouse_delta: Vector2::new(dx, dy),
+        ..InputFrame::default()
+    };
+
+    // Moving the mouse without a button held does nothing
+    let mut camera = Camera::new(Vector3::new(0.0, 0.0, 20.0), Vector3::zero(), up);
+    let start = camera.eye;
+    camera.process_input(&InputFrame { mouse_delta: Vector2::new(40.0, 0.0), ..InputFrame::default() });
+    assert_eq!(camera.eye, start);
+
+    // Orbiting keeps the distance; inverting reverses the direction
+    camera.process_input(&drag(Action::MouseOrbit, 40.0, 20.0));
+    let yaw = camera.yaw;
+    assert!((camera.eye.length() - 20.0).abs() < 1e-3);
+    assert!(camera.eye.y > 0.0, "dragging down raises the camera");
+    camera.invert_x = true;
+    camera.process_input(&drag(Action::MouseOrbit, 40.0, 0.0));
+    assert!(camera.yaw < yaw);
+
+    // Panning moves eye and target together
+    let before = (camera.eye, camera.target);
+    camera.process_input(&drag(Action::MousePan, 30.0, 0.0));
+    let moved = camera.target - before.1;
+    assert!(moved.length() > 0.0);
+    assert!((camera.eye - before.0 - moved).length() < 1e-3);
+
+    // Each wheel notch covers a fixed fraction of the distance
+    let distance = camera.distance;
+    camera.process_input(&InputFrame { wheel: 2.0, ..InputFrame::default() });
+    assert!((camera.distance - distance * 0.81).abs() < 1e-3);
+}
+
+#[test]
+fn free_fly_moves_the_eye_along_the_view_direction() {
+    let mut camera = Camera::new(Vector3::new(0.0, 0.0, 20.0), Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+    camera.toggle_mode();
+    assert_eq!(camera.mode, CameraMode::FreeFly);
+    let frame = |down: Vec<Action>| InputFrame { delta_time: 0.5, down, ..InputFrame::default() };
+
+    // Switching keeps the view; W flies toward the old target at the fly speed
+    camera.process_input(&InputFrame::default());
+    assert_eq!(camera.eye, Vector3::new(0.0, 0.0, 20.0));
+    assert!(camera.target.length() < 1e-3);
+    camera.process_input(&frame(vec![Action::OrbitUp]));
+    assert!(camera.eye.distance_to(Vector3::new(0.0, 0.0, 15.0)) < 1e-3);
+    camera.process_input(&frame(vec![Action::OrbitRight, Action::Modifier]));
+    assert!(camera.eye.distance_to(Vector3::new(20.0, 0.0, 15.0)) < 1e-3, "D strafes, Shift is 4x faster");
+
+    // Looking turns the view around the eye, which stays put
+    let eye = camera.eye;
+    camera.process_input(&InputFrame { down: vec![Action::MouseOrbit], mouse_delta: Vector2::new(0.0, 50.0), ..frame(vec![]) });
+    assert_eq!(camera.eye, eye);
+    assert!(camera.target.y < eye.y, "dragging down looks down");
+    assert!((camera.target.distance_to(eye) - camera.distance).abs() < 1e-3);
+
+    // The wheel changes the speed, and orbiting resumes around the current target
+    camera.process_input(&InputFrame { wheel: 1.0, ..InputFrame::default() });
+    assert!((camera.fly_speed - 12.5).abs() < 1e-3);
+    camera.toggle_mode();
+    let target = camera.target;
+    camera.process_input(&frame(vec![Action::OrbitLeft]));
+    assert_eq!(camera.target, target);
+}
+
+#[test]
+fn focus_follows_a_moving_body_and_eases_between_bodies() {
+    let mut system = SolarSystem::create_basic_system();
+    let mut camera = Camera::new(Vector3::new(0.0, 15.0, 30.0), Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+    let mut focus = Focus::default();
+
+    // Cycling starts from the first body
+    focus.cycle(true, &camera, &system);
+    assert_eq!(focus.body(&system), Some(0));
+    focus.cycle(true, &camera, &system);
+    assert_eq!(focus.body(&system), Some(1));
+    let planet = 2;
+    focus.select(Some(planet), &camera, &system);
+
+    // The target eases over instead of jumping, then sticks to the body as it orbits
+    system.update(0.1);
+    focus.update(&mut camera, &system, 0.1);
+    let position = system.objects[planet].position;
+    assert!(camera.target.distance_to(position) > 0.1);
+    assert!(camera.target.distance_to(position) < position.length());
+    for _ in 0..60 {
+        system.update(0.1);
+        focus.update(&mut camera, &system, 0.1);
+    }
+    assert!(camera.target.distance_to(system.objects[planet].position) < 1e-3);
+    assert!((camera.eye.distance_to(camera.target) - camera.distance).abs() < 1e-3);
+
+    // Stepping back past the first body frees the camera; panning releases it too
+    focus.cycle(false, &camera, &system);
+    focus.cycle(false, &camera, &system);
+    focus.cycle(false, &camera, &system);
+    assert_eq!(focus.body(&system), None);
+    focus.select(Some(planet), &camera, &system);
+    focus.update(&mut camera, &system, 0.1);
+    camera.process_input(&InputFrame { down: vec![Action::PanUp], ..InputFrame::default() });
+    focus.update(&mut camera, &system, 0.1);
+    assert_eq!(focus.body(&system), None);
+}
+
+#[test]
+fn focus_stays_on_its_body_when_a_merge_shifts_the_indices() {
+    let mut system = SolarSystem::parse_scene(
+        "collisions = \"merge\"\n\n\
+         [[body]]\nname = \"sun\"\ntype = \"star\"\n\n\
+         [[body]]\nname = \"big\"\nparent = \"sun\"\norbit_radius = 10.0\norbit_angle = 0.0\nscale = 1.0\n\n\
+         [[body]]\nname = \"small\"\nparent = \"sun\"\norbit_radius = 10.0\norbit_angle = 0.05\nscale = 0.5\n\n\
+         [[body]]\nname = \"far\"\nparent = \"sun\"\norbit_radius = 30.0\nscale = 0.8\n",
+    )
+    .expect("Failed to parse scene");
+    system.update(0.0);
+    let mut camera = Camera::new(Vector3::new(0.0, 15.0, 30.0), Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+    let mut focus = Focus::default();
+    let mut absorbed = Focus::default();
+    focus.select(Some(3), &camera, &system);
+    absorbed.select(Some(2), &camera, &system);
+    let far = system.objects[3].id;
+
+    // "small" is absorbed into "big", so "far" moves down to index 2 and is still followed
+    system.advance(0.0);
+    assert_eq!(system.objects.len(), 3);
+    assert_eq!(system.index_of(far), Some(2));
+    assert_eq!(focus.body(&system), Some(2));
+    assert_eq!(focus.describe(&system).as_deref(), Some("far"));
+    for _ in 0..60 {
+        system.update(0.1);
+        focus.update(&mut camera, &system, 0.1);
+    }
+    assert!(camera.target.distance_to(system.objects[2].position) < 1e-3);
+
+    // Following the absorbed body releases the camera instead of jumping to another one
+    assert_eq!(absorbed.body(&system), None);
+    absorbed.update(&mut camera, &system, 0.1);
+    assert_eq!(absorbed.describe(&system), None);
+}
+
+#[test]
+fn camera_animations_pass_through_their_keyframes() {
+    let mut system = SolarSystem::create_basic_system();
+    system.update(1.0);
+    let origin = Vector3::zero();
+    let keys = vec![
+        CameraKey::new(4.0, Vector3::new(0.0, 5.0, 20.0), origin),
+        CameraKey::new(0.0, Vector3::new(20.0, 5.0, 0.0), origin),
+        CameraKey::new(8.0, Vector3::new(-20.0, 5.0, 0.0), Vector3::new(1.0, 0.0, 0.0)),
+    ];
+    for easing in [Easing::Linear, Easing::Smooth, Easing::Cubic] {
+        assert_eq!((easing.apply(0.0), easing.apply(1.0)), (0.0, 1.0));
+        let animation = CameraAnimation::new(keys.clone(), easing, true);
+        for key in animation.keys() {
+            let (eye, target) = animation.sample(key.time, &system).unwrap();
+            assert!(eye.distance_to(key.eye) < 1e-4 && target.distance_to(key.target) < 1e-4);
+        }
+    }
+    // Eased segments start slower than linear ones
+    let sample = |easing| CameraAnimation::new(keys.clone(), easing, false).sample(0.5, &system).unwrap().0;
+    assert!(sample(Easing::Smooth).distance_to(keys[1].eye) < sample(Easing::Linear).distance_to(keys[1].eye));
+
+    // The tour looks straight at each planet as it passes and ends where it started
+    let camera = Camera::new(Vector3::new(0.0, 15.0, 30.0), origin, Vector3::new(0.0, 1.0, 0.0));
+    let tour = CameraAnimation::tour(&camera, &system);
+    let visited: Vec<usize> = tour.keys().iter().filter_map(|key| key.body).collect();
+    let planets = system.objects.iter().filter(|o| !o.material.emissive && o.ring.is_none()).count();
+    assert_eq!(visited.len(), planets * 2);
+    for key in tour.keys().iter().filter(|key| key.body.is_some()) {
+        let (_, target) = tour.sample(key.time, &system).unwrap();
+        assert!(target.distance_to(system.objects[key.body.unwrap()].position) < 1e-3);
+    }
+
+    let mut playing = camera.clone();
+    let mut playback = Playback::new(tour.clone());
+    let mut steps = 0;
+    while playback.advance(&mut playing, &system, 0.5) {
+        steps += 1;
+    }
+    assert_eq!(steps as f32, (tour.duration() / 0.5).ceil() - 1.0);
+    assert!(playing.eye.distance_to(camera.eye) < 1e-3);
+}
@@ -0,0 +1,47 @@
+// common/mod.rs
+// Fixtures shared by the integration tests; each test crate uses only some of them
+#![allow(dead_code)]
+
+use lab5::fragment::Fragment;
+use lab5::matrix::{create_model_matrix, create_projection_matrix, create_view_matrix, create_viewport_matrix};
+use lab5::noise::Noise;
+use lab5::obj::Obj;
+use lab5::vertex::Vertex;
+use lab5::Uniforms;
+use raylib::math::{Vector2, Vector3};
+use std::f32::consts::PI;
+
+pub fn screen_vertex(x: f32, y: f32, depth: f32) -> Vertex {
+    let position = Vector3::new(x, y, depth);
+    let mut vertex = Vertex::new(position, Vector3::new(0.0, 0.0, 1.0), Vector2::zero());
+    vertex.transformed_normal = Vector3::new(0.0, 0.0, 1.0);
+    vertex
+}
+
+/// Uniforms for calling a shader directly: unit model at the origin, seen from +Z on a
+/// 64x64 viewport, no light direction, no baked layer and no effects
+pub fn shading_uniforms(noise: Noise) -> Uniforms<'static> {
+    Uniforms {
+        model_matrix: create_model_matrix(Vector3::zero(), 1.0, Vector3::zero()),
+        view_matrix: create_view_matrix(Vector3::new(0.0, 0.0, 5.0), Vector3::zero(), Vector3::new(0.0, 1.0, 0.0)),
+        projection_matrix: create_projection_matrix(PI / 3.0, 1.0, 0.1, 100.0),
+        viewport_matrix: create_viewport_matrix(0.0, 0.0, 64.0, 64.0),
+        camera_position: Vector3::new(0.0, 0.0, 5.0),
+        light_direction: Vector3::zero(),
+        time: 0.0,
+        noise,
+        baked: None,
+        eruptions: &[],
+        vortex: None,
+    }
+}
+
+/// White fragment at a model-space position, as the shaders sample the surface
+pub fn surface_fragment(pos: Vector3) -> Fragment {
+    Fragment::new_with_world_pos(0.0, 0.0, Vector3::one(), Vector3::zero(), Vector3::zero(), 0.5, pos)
+}
+
+/// The unit sphere model most tests render
+pub fn sphere() -> Obj {
+    Obj::load("./models/sphere.obj").expect("Failed to load sphere.obj")
+}
@@ -0,0 +1,251 @@
+// geometry.rs
+// Integration tests for model transforms and mesh loading
+
+mod common;
+
+use common::{screen_vertex, sphere};
+use lab5::camera::Camera;
+use lab5::gltf::GltfModel;
+use lab5::light::Light;
+use lab5::matrix::{create_model_matrix, multiply_matrix_vector4};
+use lab5::mesh::{generate_icosphere, generate_uv_sphere, SPHERE_RADIUS};
+use lab5::material::Material;
+use lab5::obj::Obj;
+use lab5::renderer::Renderer;
+use lab5::shader_system::ShaderType;
+use lab5::solar_system::SolarSystem;
+use lab5::triangle::{rasterize, Lighting, PixelRect};
+use lab5::vertex::Vertex;
+use raylib::math::{Vector3, Vector4};
+use std::f32::consts::PI;
+
+#[test]
+fn spinning_bodies_turn_in_place() {
+    let translation = Vector3::new(4.0, -1.5, 2.0);
+    let origin = Vector4::new(0.0, 0.0, 0.0, 1.0);
+
+    for step in 0..8 {
+        let angle = step as f32 * 0.7;
+        let model = create_model_matrix(translation, 2.0, Vector3::new(angle * 0.3, angle, angle * 0.5));
+        let center = multiply_matrix_vector4(&model, &origin);
+        assert!((center.x - translation.x).abs() < 1e-4, "x {} at angle {angle}", center.x);
+        assert!((center.y - translation.y).abs() < 1e-4, "y {} at angle {angle}", center.y);
+        assert!((center.z - translation.z).abs() < 1e-4, "z {} at angle {angle}", center.z);
+    }
+}
+
+#[test]
+fn sphere_normals_point_outward() {
+    let mesh = sphere();
+
+    for vertex in &mesh.vertices {
+        let outward = vertex.position.normalized().dot(vertex.normal);
+        assert!(outward > 0.9, "normal {:?} at {:?}", vertex.normal, vertex.position);
+    }
+}
+
+#[test]
+fn missing_model_falls_back_to_a_procedural_sphere() {
+    let (mesh, error) = Obj::load_or_sphere("./models/does_not_exist.obj");
+
+    assert!(error.is_some());
+    assert!(mesh.indices.len() >= 3 && mesh.indices.len() % 3 == 0);
+    assert!(mesh.indices.iter().all(|&i| (i as usize) < mesh.vertices.len()));
+    assert!((mesh.bounding_radius() - 0.5).abs() < 1e-4);
+
+    let mut renderer = Renderer::new(160, 120, &mesh);
+    let camera = Camera::new(
+        Vector3::new(0.0, 15.0, 30.0),
+        Vector3::zero(),
+        Vector3::new(0.0, 1.0, 0.0),
+    );
+    let frame = renderer.render_frame(&SolarSystem::create_basic_system(), &camera, 0.0);
+    assert_ne!(frame.pixel(80, 60), frame.pixel(0, 0));
+}
+
+#[test]
+fn procedural_spheres_are_closed_and_face_outwards() {
+    let ico = generate_icosphere(2);
+    assert_eq!(ico.indices.len() / 3, 20 * 16);
+    // Midpoints are shared between neighbouring faces
+    assert_eq!(ico.vertices.len(), 10 * 16 + 2);
+
+    let mut orientations = Vec::new();
+    for mesh in [generate_uv_sphere(12, 24), ico] {
+        assert!(mesh.vertices.iter().all(|v| (v.position.length() - SPHERE_RADIUS).abs() < 1e-4));
+        assert!(mesh.vertices.iter().all(|v| (0.0..=1.0).contains(&v.tex_coords.x) && (0.0..=1.0).contains(&v.tex_coords.y)));
+        let windings: Vec<f32> = mesh
+            .indices
+            .chunks_exact(3)
+            .map(|t| {
+                let [a, b, c] = [0, 1, 2].map(|i| mesh.vertices[t[i] as usize].position);
+                (b - a).cross(c - a).dot(a + b + c)
+            })
+            .collect();
+        assert!(windings.iter().all(|&w| w < 0.0) || windings.iter().all(|&w| w > 0.0), "mixed triangle winding");
+        orientations.push(windings[0] > 0.0);
+    }
+    assert_eq!(orientations[0], orientations[1], "both spheres wind the same way");
+}
+
+#[test]
+fn gltf_models_load_with_their_materials_and_render() {
+    let model = GltfModel::load("models/station.gltf").expect("Failed to load station.gltf");
+    // Hull, two solar panels and a beacon, twelve triangles each over 24 shared vertices
+    assert_eq!(model.parts.len(), 4);
+    assert_eq!((model.mesh.vertices.len(), model.mesh.indices.len()), (4 * 24, 4 * 36));
+    let radius = model.mesh.vertices.iter().map(|v| v.position.length()).fold(0.0, f32::max);
+    assert!((radius - SPHERE_RADIUS).abs() < 1e-4, "fitted to radius {radius}");
+    let (panel, range) = &model.parts[1];
+    assert_eq!(panel.base_color, Vector3::new(0.1, 0.2, 0.6));
+    let panel_vertices = model.mesh.indices[range.clone()].iter().map(|&i| &model.mesh.vertices[i as usize]);
+    assert!(panel_vertices.clone().count() == 36 && panel_vertices.clone().all(|v| v.color == panel.base_color && (v.normal.length() - 1.0).abs() < 1e-4));
+    assert_eq!(model.parts[3].0.emission, Vector3::new(1.0, 0.25, 0.1));
+    assert!(model.material().specular > 0.0);
+
+    // A binary file with one triangle, no normals and the default material
+    let json = br#"{"asset":{"version":"2.0"},"buffers":[{"byteLength":36}],"bufferViews":[{"buffer":0,"byteLength":36}],
+        "accessors":[{"bufferView":0,"componentType":5126,"count":3,"type":"VEC3"}],
+        "meshes":[{"primitives":[{"attributes":{"POSITION":0}}]}],"nodes":[{"mesh":0,"translation":[5,0,0]}]}  "#;
+    let positions: Vec<u8> = [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0].iter().flat_map(|f| f.to_le_bytes()).collect();
+    let mut glb = b"glTF".to_vec();
+    glb.extend(2u32.to_le_bytes());
+    glb.extend(((12 + 8 + json.len() + 8 + positions.len()) as u32).to_le_bytes());
+    glb.extend((json.len() as u32).to_le_bytes());
+    glb.extend(b"JSON");
+    glb.extend(json);
+    glb.extend((positions.len() as u32).to_le_bytes());
+    glb.extend(b"BIN\0");
+    glb.extend(&positions);
+    let triangle = GltfModel::from_bytes(&glb, std::path::Path::new("")).expect("Failed to parse the GLB");
+    assert_eq!(triangle.mesh.indices, [0, 1, 2]);
+    assert!(triangle.mesh.vertices.iter().all(|v| v.normal == Vector3::new(0.0, 0.0, 1.0) && v.color == Vector3::one()));
+    assert!(GltfModel::from_bytes(b"{\"meshes\": []}", std::path::Path::new("")).is_err());
+
+    // Lit with their vertex colors, plus the emission
+    let mut v1 = screen_vertex(10.0, 10.0, 0.5);
+    let mut v2 = screen_vertex(60.0, 12.0, 0.5);
+    let mut v3 = screen_vertex(30.0, 50.0, 0.5);
+    for vertex in [&mut v1, &mut v2, &mut v3] {
+        vertex.color = Vector3::new(1.0, 0.0, 0.0);
+        vertex.emission = Vector3::new(0.0, 0.0, 0.5);
+    }
+    let eye = Vector3::new(35.0, 25.0, 200.0);
+    let lights = [Light::new(eye, Vector3::one(), 1.0, 0.0)];
+    let lighting = Lighting { lights: &lights, occluders: &[], material: Material::MATTE, eye, bump: None, atmosphere: None, vertex_colors: true };
+    let mut fragments = Vec::new();
+    rasterize(&v1, &v2, &v3, &lighting, PixelRect::UNBOUNDED, &mut fragments);
+    assert!(fragments.iter().all(|f| f.color.x > 0.0 && f.color.y == 0.0 && (f.color.z - 0.5).abs() < 1e-4));
+
+    // Placed in a scene like any body, with the model's colors on screen
+    let mut system = SolarSystem::parse_scene(
+        "[[body]]\nname = \"sun\"\ntype = \"star\"\n\n\
+         [[body]]\nname = \"station\"\nparent = \"sun\"\norbit_radius = 10.0\norbit_angle = 0.0\nscale = 4.0\nmodel = \"models/station.gltf\"\n",
+    )
+    .expect("Failed to parse scene");
+    let station = &mut system.objects[1];
+    assert_eq!(station.material.shader, ShaderType::Model);
+    assert_eq!(station.mesh().map(|mesh| mesh.indices.len()), Some(4 * 36));
+    station.rotation = Vector3::new(0.0, 0.0, PI / 2.0);
+    let target = station.position;
+    let mesh = sphere();
+    let mut renderer = Renderer::new(160, 120, &mesh);
+    let camera = Camera::new(target - Vector3::new(5.0, 0.0, 0.0), target, Vector3::new(0.0, 1.0, 0.0));
+    let frame = renderer.render_frame(&system, &camera, 0.0);
+
+    // Turned a quarter so the panels face the sun: grey hull in the middle, blue panels
+    // above and below it
+    let [r, g, b, _] = frame.pixel(80, 60);
+    assert!(r > 150 && r.abs_diff(b) < 30, "hull is {r} {g} {b}");
+    let [r, g, b, _] = frame.pixel(80, 30);
+    assert!(b > 100 && b > 2 * g && g > r, "panel is {r} {g} {b}");
+}
+
+#[test]
+fn obj_materials_color_their_sub_meshes() {
+    let dir = std::env::temp_dir().join(format!("lab5_mtl_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("craft.mtl"),
+        "newmtl hull\nKd 0.6 0.6 0.7\nNs 64\nmap_Kd hull.png\n\nnewmtl light\nKd 1.0 0.2 0.1\nKe 1.0 0.5 0.0\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("craft.obj"),
+        "mtllib craft.mtl\no body\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl hull\nf 1 2 3\n\
+         o lamp\nv 0 0 1\nv 1 0 1\nv 0 1 1\nusemtl light\nf 4 5 6\n",
+    )
+    .unwrap();
+
+    let obj = Obj::load(dir.join("craft.obj").to_str().unwrap()).expect("Failed to load craft.obj");
+    assert_eq!(obj.materials.len(), 2);
+    assert_eq!(obj.materials[0].diffuse_texture.as_deref(), Some("hull.png"));
+    assert_eq!(obj.materials[1].emission, Vector3::new(1.0, 0.5, 0.0));
+    let names: Vec<(&str, Option<usize>)> = obj.sub_meshes.iter().map(|s| (s.name.as_str(), s.material)).collect();
+    assert_eq!(names, [("body", Some(0)), ("lamp", Some(1))]);
+    // The second object's indices point at its own vertices
+    let lamp = obj.indices[obj.sub_meshes[1].indices.clone()].to_vec();
+    assert_eq!(lamp, [3, 4, 5]);
+    assert!(obj.get_vertex_array()[3..].iter().all(|v| v.color == Vector3::new(1.0, 0.2, 0.1) && v.position.z == 1.0));
+
+    let model = obj.to_model();
+    assert_eq!(model.mesh.indices.len(), 6);
+    assert_eq!(model.parts[0].0.base_color, Vector3::new(0.6, 0.6, 0.7));
+    assert!(model.parts[0].0.highlight().1 > 32.0, "Ns 64 keeps a sharp highlight");
+    assert_eq!(model.mesh.vertices[4].emission, Vector3::new(1.0, 0.5, 0.0));
+
+    // Scene bodies take OBJ models too
+    let scene = format!(
+        "[[body]]\nname = \"sun\"\ntype = \"star\"\n\n[[body]]\nname = \"craft\"\nparent = \"sun\"\norbit_radius = 5.0\nmodel = {:?}\n",
+        dir.join("craft.obj").to_str().unwrap(),
+    );
+    let system = SolarSystem::parse_scene(&scene).expect("Failed to parse scene");
+    assert_eq!(system.objects[1].mesh().map(|mesh| mesh.indices.len()), Some(6));
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn missing_normals_are_computed_from_the_faces() {
+    // The sample sphere without its `vn` lines
+    let source = std::fs::read_to_string("./models/sphere.obj").unwrap();
+    let stripped: String = source
+        .lines()
+        .filter(|line| !line.starts_with("vn "))
+        .map(|line| match line.strip_prefix("f ") {
+            Some(corners) => {
+                let corners: Vec<&str> = corners.split_whitespace().map(|c| c.rsplit_once('/').map_or(c, |(vt, _)| vt)).collect();
+                format!("f {}\n", corners.join(" "))
+            }
+            None => format!("{line}\n"),
+        })
+        .collect();
+    let path = std::env::temp_dir().join(format!("lab5_normals_{}.obj", std::process::id()));
+    std::fs::write(&path, stripped).unwrap();
+    let obj = Obj::load(path.to_str().unwrap()).expect("Failed to load the sphere without normals");
+    std::fs::remove_file(&path).ok();
+
+    let outward = |v: &Vertex| v.normal.dot(v.position.normalized());
+    assert!(obj.vertices.iter().all(|v| (v.normal.length() - 1.0).abs() < 1e-4 && outward(v) > 0.99));
+    // Vertices split along the texture seam share their normal
+    for a in &obj.vertices {
+        for b in obj.vertices.iter().filter(|b| b.position == a.position) {
+            assert!((a.normal - b.normal).length() < 1e-5);
+        }
+    }
+
+    // Generated meshes get back the normals they were built with
+    let sphere = generate_uv_sphere(12, 24);
+    let mut smooth = sphere.clone();
+    smooth.recompute_normals(true);
+    for (before, after) in sphere.vertices.iter().zip(&smooth.vertices) {
+        assert!(before.normal.normalized().dot(after.normal) > 0.98, "{:?} vs {:?}", before.normal, after.normal);
+    }
+
+    let mut flat = sphere.clone();
+    flat.recompute_normals(false);
+    assert_eq!((flat.vertices.len(), flat.indices.len()), (sphere.indices.len(), sphere.indices.len()));
+    for [a, b, c] in flat.triangles() {
+        assert!(a.normal == b.normal && b.normal == c.normal);
+        assert!(a.normal.dot(a.position + b.position + c.position) > 0.0, "flat normals face outwards");
+    }
+}
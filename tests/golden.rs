@@ -0,0 +1,38 @@
+// golden.rs
+// Golden-image regression tests: known scenes must keep rendering the same
+// Regenerate the references after an intended visual change with
+// `UPDATE_GOLDEN=1 cargo test --test golden`
+
+mod common;
+
+use common::sphere;
+use lab5::golden::{self, Tolerance};
+use lab5::renderer::ImageBuffer;
+use std::path::Path;
+
+#[test]
+fn scenes_match_reference_images() {
+    let mesh = sphere();
+    let dir = Path::new("tests/golden");
+    let tolerance = Tolerance::default();
+
+    let failures: Vec<String> = golden::scenes()
+        .iter()
+        .filter_map(|scene| golden::check(scene, &mesh, dir, &tolerance).err())
+        .collect();
+
+    assert!(failures.is_empty(), "golden images differ:\n{}", failures.join("\n"));
+}
+
+#[test]
+fn comparison_tolerates_small_differences() {
+    let image = |value: u8| ImageBuffer {
+        width: 10,
+        height: 10,
+        pixels: vec![value; 10 * 10 * 4],
+    };
+    let tolerance = Tolerance::default();
+
+    assert!(golden::compare(&image(100), &image(102), &tolerance).unwrap().passes(&tolerance));
+    assert!(!golden::compare(&image(100), &image(140), &tolerance).unwrap().passes(&tolerance));
+}
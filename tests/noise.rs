@@ -0,0 +1,295 @@
+// noise.rs
+// Integration tests for noise functions and the baked noise caches
+
+mod common;
+
+use common::shading_uniforms;
+use lab5::fragment::Fragment;
+use lab5::material::Material;
+use lab5::noise::{BlueNoise, Noise};
+use lab5::noise_cache::{BakeGrid, BakedNoise, NoiseCache};
+use lab5::noise_simd::LANES;
+use lab5::shader_system::{apply_shader, apply_shader_lanes};
+use raylib::math::Vector3;
+use std::f32::consts::PI;
+
+#[test]
+fn noise_is_deterministic_per_seed() {
+    let p = Vector3::new(0.3, -1.7, 2.4);
+
+    assert_eq!(Noise::new(7).fbm(p, 4, 2.0, 0.5), Noise::new(7).fbm(p, 4, 2.0, 0.5));
+    assert_ne!(Noise::new(7).simplex_noise(p), Noise::new(8).simplex_noise(p));
+}
+
+#[test]
+fn simplex_and_fbm_stay_in_documented_range() {
+    let noise = Noise::new(3);
+    let samples: Vec<Vector3> = (0..20_000)
+        .map(|i| Vector3::new((i % 37) as f32 * 0.173, (i / 37 % 23) as f32 * 0.219, (i / 851) as f32 * 0.311))
+        .collect();
+    let range = |f: &dyn Fn(Vector3) -> f32| {
+        samples.iter().fold((f32::MAX, f32::MIN), |(lo, hi), &p| (lo.min(f(p)), hi.max(f(p))))
+    };
+
+    // Simplex within [-1, 1] and using most of it
+    let (lo, hi) = range(&|p| noise.simplex_noise(p));
+    assert!((-1.0..=-0.7).contains(&lo) && (0.7..=1.0).contains(&hi), "simplex range {lo}..{hi}");
+    // fBm too, whatever the gain
+    for gain in [0.5, 0.8] {
+        let (lo, hi) = range(&|p| noise.fbm(p, 4, 2.0, gain));
+        assert!(lo >= -1.0 && hi <= 1.0, "fbm range {lo}..{hi} at gain {gain}");
+    }
+
+    // 4D simplex and fBm too, over positions and times alike
+    let (lo, hi) = range(&|p| noise.simplex_noise_4d(p, p.x * 0.7 - p.z * 1.3));
+    assert!((-1.0..=-0.7).contains(&lo) && (0.7..=1.0).contains(&hi), "4D simplex range {lo}..{hi}");
+    for gain in [0.5, 0.8] {
+        let (lo, hi) = range(&|p| noise.fbm_4d(p, p.y * 1.1 + 0.4, 4, 2.0, gain));
+        assert!(lo >= -1.0 && hi <= 1.0, "4D fbm range {lo}..{hi} at gain {gain}");
+    }
+    // Animating w changes the value smoothly, without jumps where w crosses into the
+    // next simplex
+    for &p in &samples {
+        for w in [0.0, 0.999_5, 2.37, -4.0005] {
+            let slope = (noise.simplex_noise_4d(p, w + 1e-3) - noise.simplex_noise_4d(p, w)).abs() / 1e-3;
+            assert!(slope < 10.0, "4D simplex changes by {slope} per unit of w at {p:?}, {w}");
+        }
+    }
+
+    // The derivative variants return the same values
+    for &p in &samples {
+        assert!((noise.simplex_noise_with_derivative(p).value - noise.simplex_noise(p)).abs() < 1e-5, "value at {p:?}");
+        assert!((noise.fbm_with_derivative(p, 3, 2.0, 0.5).value - noise.fbm(p, 3, 2.0, 0.5)).abs() < 1e-5, "fbm at {p:?}");
+    }
+}
+
+#[test]
+fn batched_noise_matches_the_scalar_functions() {
+    let noise = Noise::new(21);
+    for batch in 0..64 {
+        let points: [Vector3; LANES] = std::array::from_fn(|l| {
+            let i = (batch * LANES + l) as f32;
+            Vector3::new((i * 0.731).sin() * 4.0, (i * 0.377).cos() * 4.0 - 1.3, i * 0.093 - 9.0)
+        });
+        assert_eq!(noise.simplex_noise_lanes(&points), points.map(|p| noise.simplex_noise(p)));
+        assert_eq!(noise.fbm_lanes(&points, 3, 2.0, 0.5), points.map(|p| noise.fbm(p, 3, 2.0, 0.5)));
+        assert_eq!(noise.fbm_simplex_lanes(&points, 5, 2.0, 0.5), points.map(|p| noise.fbm_simplex(p, 5, 2.0, 0.5)));
+        assert_eq!(noise.voronoi_lanes(&points, 3.5), points.map(|p| noise.voronoi(p, 3.5)));
+    }
+
+    // The batched rocky shader gives every fragment the color it gets on its own
+    let uniforms = shading_uniforms(noise);
+    let fragments: Vec<Fragment> = (0..5)
+        .map(|i| {
+            let position = Vector3::new(i as f32 * 0.2 - 0.4, 0.3, 0.9).normalized();
+            Fragment::new_with_world_pos(i as f32, 0.0, Vector3::new(0.8, 0.7, 0.6), Vector3::zero(), Vector3::zero(), 0.5, position)
+        })
+        .collect();
+    let colors = apply_shader_lanes(&fragments, &uniforms, &Material::ROCKY);
+    for (fragment, color) in fragments.iter().zip(colors) {
+        assert_eq!(color, apply_shader(fragment, &uniforms, &Material::ROCKY));
+    }
+}
+
+#[test]
+fn volume_noise_cache_follows_the_layer_on_and_off_the_surface() {
+    let noise = Noise::new(8);
+    let layer = Material::ROCKY.baked_layer().expect("rocky has a baked layer");
+    let volume = BakedNoise::bake(BakeGrid::Volume { size: 48 }, 1.0, &noise, &layer, 0.0);
+    let surface = BakedNoise::bake(BakeGrid::default(), 1.0, &noise, &layer, 0.0);
+
+    // Trilinear samples stay close to the live layer on the sphere and inside it, where
+    // the surface map only knows the direction
+    let point = |i: usize, radius: f32| {
+        let (theta, phi) = (i as f32 * 0.37, i as f32 * 0.71);
+        Vector3::new(theta.cos() * phi.sin(), phi.cos(), theta.sin() * phi.sin()) * radius
+    };
+    let error = |baked: &BakedNoise, radius: f32| {
+        (0..200)
+            .map(|i| (baked.sample(point(i, radius)) - layer.noise.sample(&noise, point(i, radius), 0.0)).abs())
+            .sum::<f32>()
+            / 200.0
+    };
+    // Measured against the layer's own spread, so the bound doesn't depend on how the
+    // noise is scaled
+    let live: Vec<f32> = (0..200).map(|i| layer.noise.sample(&noise, point(i, 1.0), 0.0)).collect();
+    let mean = live.iter().sum::<f32>() / live.len() as f32;
+    let spread = (live.iter().map(|v| (v - mean) * (v - mean)).sum::<f32>() / live.len() as f32).sqrt();
+    assert!(error(&volume, 1.0) < 0.25 * spread, "volume error {} for a spread of {spread}", error(&volume, 1.0));
+    assert!(error(&volume, 0.6) < error(&surface, 0.6));
+
+    // Switching the grid drops what was baked with the old one
+    let mut cache = NoiseCache::new(64, 32, 1.0);
+    cache.prepare(&noise, &Material::ROCKY, 0.0);
+    assert!(cache.lookup(&noise, &Material::ROCKY, 0.0).is_some());
+    cache.set_grid(BakeGrid::Volume { size: 16 });
+    assert!(cache.lookup(&noise, &Material::ROCKY, 0.0).is_none());
+    assert!(cache.get(&noise, &Material::ROCKY, 0.0).is_some());
+}
+
+#[test]
+fn surface_noise_cache_has_no_seam_where_longitude_wraps() {
+    let noise = Noise::new(8);
+    let layer = Material::ROCKY.baked_layer().expect("rocky has a baked layer");
+    let surface = BakedNoise::bake(BakeGrid::default(), 1.0, &noise, &layer, 0.0);
+
+    // The map is sampled along 3D directions, so the texels on both sides of the wrap
+    // (the -x axis) hold neighbouring values of the same continuous layer: the step
+    // across it follows the live layer's own step there instead of jumping
+    for latitude in [-1.2f32, -0.5, 0.0, 0.4, 1.1] {
+        let at = |longitude: f32| Vector3::new(longitude.cos() * latitude.cos(), latitude.sin(), longitude.sin() * latitude.cos());
+        let (before, after) = (at(PI - 0.002), at(-PI + 0.002));
+        let baked = surface.sample(before) - surface.sample(after);
+        let live = layer.noise.sample(&noise, before, 0.0) - layer.noise.sample(&noise, after, 0.0);
+        assert!((baked - live).abs() < 0.02, "seam of {baked} against {live} live at latitude {latitude}");
+        assert!((surface.sample(before) - layer.noise.sample(&noise, at(PI), 0.0)).abs() < 0.1);
+    }
+}
+
+#[test]
+fn periodic_noise_wraps_at_whole_cell_periods() {
+    let noise = Noise::new(5);
+    let period = [4, 6, 0];
+    let points = (0..200).map(|i| Vector3::new(i as f32 * 0.137 - 9.0, (i as f32 * 0.61).sin() * 5.0, i as f32 * 0.071));
+
+    for p in points {
+        // Shifting by whole periods along the wrapped axes gives back the same value, on
+        // either side of zero
+        for shift in [Vector3::new(4.0, 0.0, 0.0), Vector3::new(0.0, -6.0, 0.0), Vector3::new(-8.0, 12.0, 0.0)] {
+            assert!((noise.periodic_noise(p + shift, period) - noise.periodic_noise(p, period)).abs() < 1e-4, "noise at {p:?}");
+            let fbm = |q: Vector3| noise.fbm_periodic(q, period, 4, 2, 0.5);
+            assert!((fbm(p + shift) - fbm(p)).abs() < 1e-4, "fbm at {p:?}");
+        }
+        assert!(noise.periodic_noise(p, period).abs() <= 1.0);
+    }
+
+    // The axis with a zero period doesn't repeat, and the wrapped ones match the unwrapped
+    // noise inside the first period
+    let (p, shift) = (Vector3::new(0.3, 0.7, 0.45), Vector3::new(0.0, 0.0, 4.0));
+    assert_ne!(noise.periodic_noise(p + shift, period), noise.periodic_noise(p, period));
+    assert_eq!(noise.periodic_noise(p, period), noise.periodic_noise(p, [0, 0, 0]));
+}
+
+#[test]
+fn curl_noise_is_divergence_free() {
+    let noise = Noise::new(12);
+    const H: f32 = 5e-3;
+    let (mut divergence, mut shear) = (0.0, 0.0);
+    for i in 0..200 {
+        let p = Vector3::new((i as f32 * 0.37).sin() * 2.0, i as f32 * 0.02 - 2.0, (i as f32 * 0.91).cos() * 2.0);
+        let w = (i as f32 * 0.13).sin();
+        // Central differences of each component along its own axis, which cancel out, and
+        // across the others, which don't
+        let along = |axis: Vector3| (noise.curl_noise(p + axis * H, w) - noise.curl_noise(p - axis * H, w)) / (2.0 * H);
+        let (dx, dy, dz) = (along(Vector3::new(1.0, 0.0, 0.0)), along(Vector3::new(0.0, 1.0, 0.0)), along(Vector3::new(0.0, 0.0, 1.0)));
+        divergence += (dx.x + dy.y + dz.z).abs() / 200.0;
+        shear += (dx.y.abs() + dy.z.abs() + dz.x.abs()) / 600.0;
+    }
+    assert!(divergence < 0.02 * shear, "mean divergence {divergence} against derivatives of {shear}");
+}
+
+#[test]
+fn worley_distances_are_ordered_and_continuous() {
+    let noise = Noise::new(17);
+    let scale = 1.3;
+    let points: Vec<Vector3> = (0..300)
+        .map(|i| Vector3::new((i as f32 * 0.613).sin() * 6.0, i as f32 * 0.047 - 7.0, (i as f32 * 0.281).cos() * 6.0))
+        .collect();
+
+    for &p in &points {
+        let cell = noise.worley(p, scale);
+        assert!(0.0 <= cell.f1 && cell.f1 <= cell.f2, "F1 {} F2 {} at {p:?}", cell.f1, cell.f2);
+        assert!(cell.edge() >= 0.0);
+
+        // F1 is the distance to one feature point, so three nearby samples locate it, and
+        // F1 vanishes there
+        const H: f32 = 1e-3;
+        let squared = |q: Vector3| noise.worley(q, scale).f1.powi(2);
+        let (at, s) = (squared(p), p * scale);
+        // |s + H e - f|^2 - |s - f|^2 = 2 H (s - f).e + H^2, in the scaled space
+        let axis = |e: Vector3, coordinate: f32| coordinate + H / 2.0 - (squared(p + e * (H / scale)) - at) / (2.0 * H);
+        let feature = Vector3::new(
+            axis(Vector3::new(1.0, 0.0, 0.0), s.x),
+            axis(Vector3::new(0.0, 1.0, 0.0), s.y),
+            axis(Vector3::new(0.0, 0.0, 1.0), s.z),
+        );
+        let f1 = noise.worley(feature / scale, scale).f1;
+        assert!(f1 < 0.01, "F1 of {f1} at the feature point near {p:?}");
+    }
+
+    // Both distances are 1-Lipschitz, so they don't jump where the lookup crosses into
+    // the next cell (scaled coordinates hitting whole numbers)
+    for &p in &points {
+        for axis in [Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0), Vector3::new(0.0, 0.0, 1.0)] {
+            let s = p * scale;
+            let along = s.dot(axis);
+            let border = (s + axis * (along.round() - along)) / scale;
+            let step = 1e-4;
+            let (before, after) = (noise.worley(border - axis * step, scale), noise.worley(border + axis * step, scale));
+            assert!((after.f1 - before.f1).abs() <= 2.0 * step * scale + 1e-5, "F1 jumps at {border:?}");
+            assert!((after.f2 - before.f2).abs() <= 2.0 * step * scale + 1e-5, "F2 jumps at {border:?}");
+        }
+    }
+}
+
+#[test]
+fn blue_noise_ranks_every_pixel_once_and_spreads_them_out() {
+    let size = 32;
+    let blue_noise = BlueNoise::new(size, 2);
+    let pixels: Vec<(i32, i32)> = (0..size as i32).flat_map(|y| (0..size as i32).map(move |x| (x, y))).collect();
+
+    // The thresholds are the ranks 0..size^2 mapped to [0, 1), each used once, and tile
+    let mut ranks: Vec<usize> = pixels.iter().map(|&(x, y)| (blue_noise.sample(x, y) * (size * size) as f32) as usize).collect();
+    ranks.sort_unstable();
+    assert_eq!(ranks, (0..size * size).collect::<Vec<_>>());
+    assert_eq!(blue_noise.sample(3, 5), blue_noise.sample(3 - size as i32, 5 + 2 * size as i32));
+
+    // Every prefix of the ranks is evenly spread: the nearest neighbours of the pixels
+    // under a threshold sit close to the spacing of a regular grid of that density,
+    // where white noise averages half of it and puts many of them side by side
+    let distance = |a: (i32, i32), b: (i32, i32)| {
+        let wrap = |d: i32| d.rem_euclid(size as i32).min((-d).rem_euclid(size as i32)) as f32;
+        wrap(a.0 - b.0).hypot(wrap(a.1 - b.1))
+    };
+    let nearest = |threshold: f32| -> Vec<f32> {
+        let lit: Vec<(i32, i32)> = pixels.iter().copied().filter(|&(x, y)| blue_noise.sample(x, y) < threshold).collect();
+        lit.iter()
+            .map(|&a| lit.iter().filter(|&&b| b != a).map(|&b| distance(a, b)).fold(f32::MAX, f32::min))
+            .collect()
+    };
+    for threshold in [0.1f32, 0.25, 0.5] {
+        let spacing = 1.0 / threshold.sqrt();
+        let mean = nearest(threshold).iter().sum::<f32>() / nearest(threshold).len() as f32;
+        assert!(mean > 0.65 * spacing, "mean spacing {mean} under {threshold}, {spacing} on a grid");
+    }
+    let min = nearest(0.1).into_iter().fold(f32::MAX, f32::min);
+    assert!(min >= 2.0, "pixels {min} apart under 0.1");
+}
+
+#[test]
+fn domain_warp_is_deterministic_and_vanishes_at_zero_strength() {
+    let (noise, other) = (Noise::new(4), Noise::new(5));
+    for i in 0..100 {
+        let p = Vector3::new(i as f32 * 0.29 - 14.0, (i as f32 * 0.5).cos() * 3.0, i as f32 * 0.07);
+        let w = i as f32 * 0.1;
+
+        // Same seed and arguments, same point; another seed warps elsewhere
+        assert_eq!(noise.domain_warp(p, 4, 0.8, 2), Noise::new(4).domain_warp(p, 4, 0.8, 2));
+        assert_eq!(noise.domain_warp_4d(p, w, 3, 0.8, 2), Noise::new(4).domain_warp_4d(p, w, 3, 0.8, 2));
+        assert_ne!(noise.domain_warp(p, 4, 0.8, 2), other.domain_warp(p, 4, 0.8, 2));
+
+        // Without strength (or passes) the warped field is the field itself
+        for iterations in [0, 1, 3] {
+            assert_eq!(noise.domain_warp(p, 4, 0.0, iterations), p);
+            assert_eq!(noise.domain_warp_4d(p, w, 3, 0.0, iterations), p);
+        }
+        assert_eq!(noise.domain_warp(p, 4, 0.8, 0), p);
+        assert_eq!(noise.fbm(noise.domain_warp(p, 4, 0.0, 2), 4, 2.0, 0.5), noise.fbm(p, 4, 2.0, 0.5));
+
+        // A single pass moves the point in proportion to the strength, by at most the
+        // strength along each axis since fBm stays within [-1, 1]
+        let offset = |strength: f32| noise.domain_warp(p, 4, strength, 1).distance_to(p);
+        assert!(offset(0.01) <= 0.01 * 3f32.sqrt() + 1e-6);
+        assert!((offset(0.5) - 50.0 * offset(0.01)).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,175 @@
+// post.rs
+// Integration tests for post-processing, debug views and frame export
+
+mod common;
+
+use common::sphere;
+use lab5::bloom::Bloom;
+use lab5::camera::Camera;
+use lab5::debug_view::DebugView;
+use lab5::export::{export_video, ExportSettings};
+use lab5::fxaa::Fxaa;
+use lab5::post::{PostChain, PostEffect, PostFrame, POST_EFFECT_NAMES};
+use lab5::renderer::{ImageBuffer, Renderer};
+use lab5::solar_system::{CelestialObject, SolarSystem};
+use raylib::math::Vector3;
+
+#[test]
+fn export_writes_a_numbered_frame_sequence() {
+    let mesh = sphere();
+    let mut renderer = Renderer::new(64, 48, &mesh);
+    let mut system = SolarSystem::create_basic_system();
+    let mut camera = Camera::new(Vector3::new(0.0, 15.0, 30.0), Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+    let dir = std::env::temp_dir().join(format!("lab5_export_{}", std::process::id()));
+    let settings = ExportSettings {
+        output: dir.to_string_lossy().into_owned(),
+        image_format: "ppm".to_string(),
+        fps: 10,
+        duration: 60.0,
+        frames: Some(3),
+    };
+    assert_eq!(settings.frame_count(), 3);
+
+    let mut progress = Vec::new();
+    export_video(&mut renderer, &mut system, &mut camera, &settings, |_, _, p| progress.push(p)).expect("export failed");
+
+    assert_eq!(progress, [0.0, 1.0 / 3.0, 2.0 / 3.0]);
+    let frames: Vec<_> = (0..3).map(|i| ImageBuffer::read_ppm(dir.join(format!("frame_{i:05}.ppm"))).expect("missing frame")).collect();
+    assert!(!dir.join("frame_00003.ppm").exists());
+    assert!(frames.iter().all(|f| (f.width, f.height) == (64, 48)));
+    // The simulation advances between frames
+    assert_ne!(frames[0].pixels, frames[2].pixels);
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn bloom_makes_stars_glow_past_their_edge() {
+    let mesh = sphere();
+    let mut renderer = Renderer::new(160, 120, &mesh);
+    let mut system = SolarSystem::new();
+    system.add(CelestialObject::star(3.0));
+    let camera = Camera::new(Vector3::new(0.0, 0.0, 30.0), Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+    let plain = renderer.render_frame(&system, &camera, 0.0);
+
+    assert!(renderer.post.set_enabled("bloom", true));
+    let glowing = renderer.render_frame(&system, &camera, 0.0);
+    let brightness = |p: [u8; 4]| p[0] as u32 + p[1] as u32 + p[2] as u32;
+    // Find the star's right edge on the middle row, then look just past it
+    let edge = (80..160).find(|&x| plain.pixel(x, 60) == plain.pixel(159, 60)).expect("star fills the row");
+    assert!(brightness(glowing.pixel(edge + 2, 60)) > brightness(plain.pixel(edge + 2, 60)) + 10);
+    assert_eq!(glowing.pixel(0, 0), plain.pixel(0, 0), "the glow stays around the star");
+
+    // Nothing is brighter than a threshold of 1
+    renderer.post = PostChain::new(Bloom { threshold: 1.0, ..Bloom::default() });
+    renderer.post.set_enabled("bloom", true);
+    assert!(renderer.render_frame(&system, &camera, 0.0).pixels == plain.pixels);
+}
+
+#[test]
+fn post_effects_run_in_order_and_toggle_at_runtime() {
+    let mesh = sphere();
+    let mut renderer = Renderer::new(160, 120, &mesh);
+    let mut system = SolarSystem::new();
+    system.add(CelestialObject::star(6.0));
+    let camera = Camera::new(Vector3::new(0.0, 0.0, 30.0), Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+    assert_eq!(renderer.post.names(), POST_EFFECT_NAMES);
+    let plain = renderer.render_frame(&system, &camera, 0.0);
+
+    let brightness = |p: [u8; 4]| p[0] as u32 + p[1] as u32 + p[2] as u32;
+    assert!(renderer.post.set_enabled("vignette", true));
+    assert!(renderer.post.is_enabled("vignette"));
+    let vignetted = renderer.render_frame(&system, &camera, 0.0);
+    assert_eq!(vignetted.pixel(80, 60), plain.pixel(80, 60), "the center is untouched");
+    assert!(brightness(vignetted.pixel(0, 0)) <= brightness(plain.pixel(0, 0)));
+    assert!(brightness(vignetted.pixel(80, 60)) > 0);
+
+    // Effects stack, and switching them all off restores the plain frame
+    renderer.post.set_enabled("film_grain", true);
+    assert!(renderer.render_frame(&system, &camera, 0.0).pixels != vignetted.pixels);
+    renderer.post.set_enabled("vignette", false);
+    renderer.post.set_enabled("film_grain", false);
+    assert!(renderer.render_frame(&system, &camera, 0.0).pixels == plain.pixels);
+    assert!(!renderer.post.set_enabled("lens_flare", true), "unknown effects are reported");
+}
+
+#[test]
+fn debug_views_show_depth_normals_edges_and_overdraw() {
+    let mesh = sphere();
+    let mut renderer = Renderer::new(120, 90, &mesh);
+    renderer.coronas = false;
+    let system = SolarSystem::parse_scene("[[body]]\nname = \"rock\"\ntype = \"star\"\nshader = \"rocky\"\n").expect("Failed to parse scene");
+    let camera = Camera::new(Vector3::new(0.0, 0.0, 6.0), Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+    let pixel = |frame: &ImageBuffer, x: usize, y: usize| -> [u8; 3] {
+        let index = (y * 120 + x) * 4;
+        [frame.pixels[index], frame.pixels[index + 1], frame.pixels[index + 2]]
+    };
+
+    assert_eq!(DebugView::ALL.iter().fold(DebugView::Shaded, |view, _| view.next()), DebugView::Shaded);
+
+    // Depth: the nearest point of the sphere is the hot end, the sky black
+    renderer.debug_view = DebugView::Depth;
+    let depth = renderer.render_frame(&system, &camera, 0.0);
+    assert_eq!(pixel(&depth, 0, 0), [0, 0, 0]);
+    let center = pixel(&depth, 60, 45);
+    assert!(center[0] > center[2], "center is not hot: {center:?}");
+
+    // Normals: the sphere faces the camera (+z) at its center and +x on its right
+    renderer.debug_view = DebugView::Normals;
+    let normals = renderer.render_frame(&system, &camera, 0.0);
+    assert!(pixel(&normals, 60, 45)[2] > 200);
+    let right = (60..120).rev().find(|&x| pixel(&normals, x, 45) != pixel(&normals, 0, 0)).expect("sphere not drawn");
+    assert!(pixel(&normals, right, 45)[0] > 200);
+
+    // Wireframe: fewer pixels than the filled sphere, all of them white
+    renderer.debug_view = DebugView::Wireframe;
+    let wireframe = renderer.render_frame(&system, &camera, 0.0);
+    let background = pixel(&wireframe, 0, 0);
+    let drawn: Vec<[u8; 3]> = (0..120 * 90).map(|i| pixel(&wireframe, i % 120, i / 120)).filter(|&p| p != background).collect();
+    let filled = (0..120 * 90).filter(|&i| pixel(&normals, i % 120, i / 120) != pixel(&normals, 0, 0)).count();
+    assert!(!drawn.is_empty() && drawn.len() < filled);
+    assert!(drawn.iter().all(|&p| p == [255, 255, 255]));
+
+    // Overdraw: the sky gets no fragments, the sphere its front and back faces
+    renderer.debug_view = DebugView::Overdraw;
+    renderer.render_frame(&system, &camera, 0.0);
+    let overdraw = renderer.framebuffer.overdraw();
+    assert_eq!(overdraw[0], 0);
+    assert!(overdraw[45 * 120 + 60] >= 1);
+    assert_eq!(renderer.framebuffer.pixel(0, 0)[..3], [0, 0, 0]);
+}
+
+#[test]
+fn fxaa_softens_staircase_edges_and_leaves_flat_areas_alone() {
+    // A white half-plane with a shallow slope, so its border is a staircase of long steps
+    let (width, height) = (48, 24);
+    let colors: Vec<Vector3> = (0..width * height)
+        .map(|i| if (i / width) as f32 > 6.0 + (i % width) as f32 * 0.25 { Vector3::one() } else { Vector3::zero() })
+        .collect();
+    let depth = vec![f32::INFINITY; colors.len()];
+    let mut frame = PostFrame { width, height, colors: colors.clone(), depth: &depth, time: 0.0 };
+    Fxaa::default().apply(&mut frame);
+
+    let gray = |c: Vector3| c.x > 0.02 && c.x < 0.98;
+    assert!(!colors.iter().any(|&c| gray(c)));
+    assert!(frame.colors.iter().filter(|&&c| gray(c)).count() >= width / 2, "the steps are blended");
+    for (i, (before, after)) in colors.iter().zip(&frame.colors).enumerate() {
+        assert!(after.x >= 0.0 && after.x <= 1.0 && after.x == after.y && after.y == after.z);
+        let (x, y) = ((i % width) as f32, (i / width) as f32);
+        if (y - 6.0 - x * 0.25).abs() > 2.0 {
+            assert_eq!(before, after, "pixels away from the edge keep their color");
+        }
+    }
+
+    // The renderer runs it over whole views when switched on
+    let mesh = sphere();
+    let mut renderer = Renderer::new(160, 120, &mesh);
+    let mut system = SolarSystem::new();
+    system.add(CelestialObject::star(6.0));
+    let camera = Camera::new(Vector3::new(0.0, 0.0, 30.0), Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+    let hard = renderer.render_frame(&system, &camera, 0.0);
+    renderer.antialiasing = Some(Fxaa::default());
+    let smooth = renderer.render_frame(&system, &camera, 0.0);
+    assert!(smooth.pixels != hard.pixels);
+    assert_eq!(smooth.pixel(0, 0), hard.pixel(0, 0), "the empty sky is untouched");
+    assert!(renderer.resized(80, 60).antialiasing.is_some(), "resizing keeps the setting");
+}
@@ -0,0 +1,587 @@
+// raster.rs
+// Integration tests for rasterization, the framebuffer and the render backends
+
+mod common;
+
+use common::{screen_vertex, sphere};
+use lab5::arena::FrameArena;
+use lab5::backend::{Rasterizer, RenderBackend, ViewSetup};
+use lab5::camera::Camera;
+use lab5::clipping::{sphere_in_frustum, sphere_occluded, Clipper};
+use lab5::debug_view::DebugView;
+use lab5::fragment::{BlendMode, ShadedFragment};
+use lab5::framebuffer::{Framebuffer, BYTES_PER_PIXEL};
+use lab5::light::{main_light_direction, Light};
+use lab5::lod::{inner_radius, select_level, LOD_THRESHOLDS};
+use lab5::matrix::{camera_position, create_model_matrix, create_projection_matrix, create_view_matrix, create_viewport_matrix, project_to_screen};
+use lab5::noise::{BlueNoise, Noise};
+use lab5::obj::Obj;
+use lab5::panorama::render_panorama;
+use lab5::renderer::{step_render_scale, RenderStats, Renderer, RENDER_SCALE_STEPS};
+use lab5::shader_system::ShaderType;
+use lab5::solar_system::{CelestialObject, SolarSystem};
+use lab5::starfield::Starfield;
+use lab5::triangle::triangle;
+use lab5::vertex::Vertex;
+use lab5::{render_object, transform_object, write_fragments, Uniforms};
+use raylib::math::{Vector3, Vector4};
+use std::f32::consts::PI;
+
+#[test]
+fn triangle_fragments_stay_inside_its_bounds() {
+    let v1 = screen_vertex(10.0, 10.0, 0.5);
+    let v2 = screen_vertex(60.0, 12.0, 0.5);
+    let v3 = screen_vertex(30.0, 50.0, 0.5);
+
+    let fragments = triangle(&v1, &v2, &v3, &[], &[], true);
+
+    assert!(!fragments.is_empty());
+    for fragment in &fragments {
+        assert!((10.0..=60.0).contains(&fragment.position.x));
+        assert!((10.0..=50.0).contains(&fragment.position.y));
+    }
+}
+
+#[test]
+fn headless_render_frame_draws_the_scene() {
+    let mesh = sphere();
+    let mut renderer = Renderer::new(160, 120, &mesh);
+    let camera = Camera::new(
+        Vector3::new(0.0, 15.0, 30.0),
+        Vector3::zero(),
+        Vector3::new(0.0, 1.0, 0.0),
+    );
+
+    let frame = renderer.render_frame(&SolarSystem::create_basic_system(), &camera, 0.0);
+
+    assert_eq!((frame.width, frame.height), (160, 120));
+    assert_eq!(frame.pixels.len(), 160 * 120 * 4);
+    // The central star covers the middle of the screen
+    assert_ne!(frame.pixel(80, 60), frame.pixel(0, 0));
+}
+
+#[test]
+fn parallel_rendering_matches_single_threaded() {
+    let mesh = sphere();
+    let camera = Camera::new(
+        Vector3::new(0.0, 15.0, 30.0),
+        Vector3::zero(),
+        Vector3::new(0.0, 1.0, 0.0),
+    );
+    let system = SolarSystem::create_basic_system();
+
+    let frames = [1, 4].map(|threads| {
+        let mut renderer = Renderer::new(160, 120, &mesh);
+        renderer.set_threads(threads);
+        assert_eq!(renderer.threads(), threads);
+        renderer.render_frame(&system, &camera, 2.0)
+    });
+
+    // Compared without assert_eq to keep the pixel dump out of the failure message
+    assert!(frames[0] == frames[1]);
+}
+
+#[test]
+fn framebuffer_snapshot_is_row_major_rgba() {
+    let mut framebuffer = Framebuffer::new(4, 3);
+    framebuffer.point(2, 1, 0.5, Vector3::new(1.0, 0.0, 0.0));
+    framebuffer.overlay_point(0, 2, Vector3::new(0.0, 0.0, 1.0));
+
+    let bytes = framebuffer.as_rgba_bytes();
+    assert_eq!(framebuffer.stride(), 4 * BYTES_PER_PIXEL);
+    assert_eq!(bytes.len(), framebuffer.stride() * 3);
+    let at = |x: usize, y: usize| &bytes[y * framebuffer.stride() + x * BYTES_PER_PIXEL..][..BYTES_PER_PIXEL];
+    assert_eq!(at(2, 1), [255, 0, 0, 255]);
+    assert_eq!(at(0, 2), [0, 0, 255, 255]);
+    assert_eq!(at(3, 0), [0, 0, 0, 255]);
+    assert_eq!(framebuffer.snapshot(), bytes);
+
+    framebuffer.clear();
+    assert!(framebuffer.as_rgba_bytes().chunks_exact(4).all(|p| p == [0, 0, 0, 255]));
+}
+
+#[test]
+fn renderer_switches_backends_by_name() {
+    let mesh = sphere();
+    let mut renderer = Renderer::new(32, 24, &mesh);
+    assert_eq!(renderer.backend(), "rasterizer");
+    assert_eq!(renderer.backend_names(), ["rasterizer", "raytracer"]);
+
+    // The GPU backend is only added once a window exists
+    assert!(!renderer.select_backend("gpu"));
+    assert_eq!(renderer.backend(), "rasterizer");
+
+    assert_eq!(renderer.next_backend(), "raytracer");
+    assert_eq!(renderer.resized(16, 12).backend(), "raytracer");
+    assert_eq!(renderer.next_backend(), "rasterizer");
+}
+
+#[test]
+fn rasterizer_reuses_its_arenas_across_frames() {
+    let mesh = sphere();
+    let renderer = Renderer::new(160, 120, &mesh);
+    let camera = Camera::new(
+        Vector3::new(0.0, 15.0, 30.0),
+        Vector3::zero(),
+        Vector3::new(0.0, 1.0, 0.0),
+    );
+    let system = SolarSystem::create_basic_system();
+    let lights = system.lights(renderer.mesh_radius());
+    let setup = ViewSetup {
+        view: camera.get_view_matrix(),
+        projection: renderer.projection,
+        viewport: renderer.viewport,
+        time: 0.0,
+        mesh_radius: renderer.mesh_radius(),
+        inner_radii: &[],
+        body_mesh: renderer.mesh(),
+        detail_levels: &[],
+        lights: &lights,
+        shadows: true,
+        normal_mapping: true,
+        noise_cache: &renderer.noise_cache,
+        pool: None,
+        surfaces: &renderer.surface_cache,
+        debug_view: DebugView::Shaded,
+        profile_objects: false,
+    };
+
+    let mut rasterizer = Rasterizer::default();
+    let draw = |rasterizer: &mut Rasterizer| {
+        let mut framebuffer = Framebuffer::new(160, 120);
+        let mut stats = RenderStats::default();
+        rasterizer.draw_view(&setup, &system, &mut framebuffer, &mut stats);
+        (framebuffer.snapshot(), stats.fragments)
+    };
+
+    let first = draw(&mut rasterizer);
+    let reserved = rasterizer.arena_bytes();
+    assert!(first.1 > 0 && reserved > 0);
+
+    // Same frame again: same image, no buffer had to grow
+    assert_eq!(draw(&mut rasterizer), first);
+    assert_eq!(rasterizer.arena_bytes(), reserved);
+}
+
+#[test]
+fn tiled_rasterizer_matches_drawing_whole_objects() {
+    let mesh = sphere();
+    let mut renderer = Renderer::new(150, 100, &mesh);
+    renderer.noise_cache.enabled = false;
+    renderer.lod = false;
+    let camera = Camera::new(
+        Vector3::new(0.0, 15.0, 30.0),
+        Vector3::zero(),
+        Vector3::new(0.0, 1.0, 0.0),
+    );
+    // Only the bodies: belts and coronas are drawn over the backend's image by the renderer
+    let mut system = SolarSystem::create_basic_system();
+    system.belts.clear();
+    renderer.coronas = false;
+    let tiled = renderer.render_frame(&system, &camera, 1.0);
+
+    // Reference: every object rasterized on its own, straight into the framebuffer
+    let lights = system.lights(renderer.mesh_radius());
+    let mut framebuffer = Framebuffer::new(150, 100);
+    framebuffer.set_background_color(renderer.framebuffer.background_color());
+    framebuffer.clear();
+    for (index, object) in system.objects.iter().enumerate() {
+        let uniforms = Uniforms {
+            model_matrix: object.model_matrix(),
+            view_matrix: camera.get_view_matrix(),
+            projection_matrix: renderer.projection,
+            viewport_matrix: renderer.viewport,
+            camera_position: camera_position(&camera.get_view_matrix()),
+            light_direction: main_light_direction(&lights, object.position),
+            time: 1.0,
+            noise: Noise::new(object.seed),
+            baked: None,
+            eruptions: &[],
+            vortex: None,
+        };
+        let occluders = system.occluders(renderer.mesh_radius(), index);
+        let mut stats = RenderStats::default();
+        render_object(&mut framebuffer, &uniforms, renderer.mesh(), &lights, &occluders, object, &mut stats);
+    }
+
+    assert!(tiled.pixels == framebuffer.snapshot());
+}
+
+#[test]
+fn indexed_meshes_shade_each_vertex_once() {
+    let mesh = sphere();
+    let expanded = mesh.get_vertex_array();
+    let unrolled = Obj {
+        indices: (0..expanded.len() as u32).collect(),
+        vertices: expanded,
+        materials: Vec::new(),
+        sub_meshes: Vec::new(),
+    };
+    assert!(mesh.vertices.len() < unrolled.vertices.len(), "the sphere shares its vertices");
+
+    let camera = Camera::new(Vector3::new(0.0, 0.0, 6.0), Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+    let object = &SolarSystem::create_basic_system().objects[0];
+    let uniforms = Uniforms {
+        model_matrix: create_model_matrix(Vector3::zero(), 1.0, Vector3::zero()),
+        view_matrix: camera.get_view_matrix(),
+        projection_matrix: create_projection_matrix(PI / 3.0, 160.0 / 120.0, 0.1, 100.0),
+        viewport_matrix: create_viewport_matrix(0.0, 0.0, 160.0, 120.0),
+        camera_position: camera.eye,
+        light_direction: Vector3::zero(),
+        time: 0.0,
+        noise: Noise::new(object.seed),
+        baked: None,
+        eruptions: &[],
+        vortex: None,
+    };
+
+    let mut arena = FrameArena::default();
+    let mut stats = RenderStats::default();
+    transform_object(&uniforms, &mesh, &mut arena, &mut stats);
+    assert_eq!(arena.vertices.len(), mesh.vertices.len());
+    let triangles = arena.triangles.len();
+    assert!(triangles > 0);
+
+    // Same image as shading every corner of every triangle
+    let lights = [Light::new(Vector3::new(0.0, 0.0, 20.0), Vector3::one(), 1.0, 100.0)];
+    let image = |mesh: &Obj| {
+        let mut framebuffer = Framebuffer::new(160, 120);
+        framebuffer.clear();
+        let mut stats = RenderStats::default();
+        render_object(&mut framebuffer, &uniforms, mesh, &lights, &[], object, &mut stats);
+        (framebuffer.snapshot(), stats.triangles)
+    };
+    assert!(image(&mesh) == image(&unrolled));
+    assert_eq!(image(&mesh).1, triangles);
+}
+
+#[test]
+fn clipper_cuts_triangles_at_the_near_plane() {
+    let viewport = create_viewport_matrix(0.0, 0.0, 160.0, 120.0);
+    let clip_vertex = |x: f32, y: f32, z: f32, w: f32| Vertex { clip_position: Vector4::new(x, y, z, w), ..Vertex::default() };
+    // One corner behind the camera, where the perspective divide would flip it
+    let (a, b, c) = (clip_vertex(-0.5, -0.5, 0.5, 1.0), clip_vertex(0.5, -0.5, 0.5, 1.0), clip_vertex(0.0, 0.5, -2.0, -1.0));
+
+    let mut clipper = Clipper::default();
+    let mut triangles = Vec::new();
+    clipper.clip([&a, &b, &c], &viewport, &mut triangles);
+
+    assert_eq!(triangles.len(), 2, "a quad left after cutting one corner");
+    for vertex in triangles.iter().flatten() {
+        let p = vertex.clip_position;
+        assert!(p.z >= -p.w - 1e-4 && p.w > 0.0);
+        let s = vertex.transformed_position;
+        assert!((0.0..=160.0).contains(&s.x) && (0.0..=120.0).contains(&s.y), "off screen at {s:?}");
+    }
+
+    // Fully behind the camera
+    triangles.clear();
+    clipper.clip([&clip_vertex(0.0, 0.0, -2.0, -1.0), &clip_vertex(1.0, 0.0, -2.0, -1.0), &c], &viewport, &mut triangles);
+    assert!(triangles.is_empty());
+}
+
+#[test]
+fn camera_inside_a_body_draws_no_smeared_triangles() {
+    let mesh = sphere();
+    let mut renderer = Renderer::new(160, 120, &mesh);
+    let system = SolarSystem::create_basic_system();
+    let star = &system.objects[0];
+
+    // Just inside the star's surface, looking out: part of the mesh is behind the eye
+    let eye = star.position + Vector3::new(0.0, 0.0, star.scale * renderer.mesh_radius() * 0.9);
+    let camera = Camera::new(eye, eye + Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.0, 1.0, 0.0));
+    let frame = renderer.render_frame(&system, &camera, 0.0);
+
+    let [r, g, _, _] = frame.pixel(80, 60);
+    assert!(r > 150 && g > 150, "the star's far side should fill the view, got {r} {g}");
+    let fragments = renderer.stats.fragments;
+    assert!(fragments <= 160 * 120 * 4, "{fragments} fragments for a 160x120 view");
+}
+
+#[test]
+fn rings_surround_their_planet_in_both_software_backends() {
+    let mesh = sphere();
+    let mut renderer = Renderer::new(160, 120, &mesh);
+    let mut system = SolarSystem::parse_scene(
+        "[[body]]\nname = \"sun\"\ntype = \"star\"\n\n\
+         [[body]]\nname = \"giant\"\nparent = \"sun\"\nshader = \"gas_giant\"\norbit_radius = 20.0\norbit_angle = 1.5707964\n\n\
+         [[body]]\nname = \"rings\"\ntype = \"ring\"\nparent = \"giant\"\ninner_radius = 1.0\nouter_radius = 2.0\n",
+    )
+    .expect("Failed to parse scene");
+    assert!(system.occluders(renderer.mesh_radius(), 0).iter().all(|o| o.radius < 1.0), "rings cast no sphere shadows");
+    system.update(0.0);
+    assert!((system.objects[1].position - Vector3::new(0.0, 0.0, 20.0)).length() < 1e-3);
+    assert_eq!(system.objects[2].position, system.objects[1].position);
+
+    // From the star's side, above the tilted ring, which is lit on its underside
+    let eye = Vector3::new(0.0, 8.0, 12.0);
+    let camera = Camera::new(eye, system.objects[1].position, Vector3::new(0.0, 1.0, 0.0));
+    for backend in ["rasterizer", "raytracer"] {
+        renderer.select_backend(backend);
+        let frame = renderer.render_frame(&system, &camera, 0.0);
+        let background = frame.pixel(0, 0);
+        let (planet, ring) = (frame.pixel(80, 60), frame.pixel(80 + 14, 60));
+        assert_ne!(planet, background, "{backend}: planet missing");
+        assert_ne!(ring, background, "{backend}: ring missing");
+        assert_ne!(ring, planet, "{backend}: ring drawn over the planet");
+    }
+}
+
+#[test]
+fn ray_tracer_covers_the_same_bodies_as_the_rasterizer() {
+    let mesh = sphere();
+    let camera = Camera::new(
+        Vector3::new(0.0, 15.0, 30.0),
+        Vector3::zero(),
+        Vector3::new(0.0, 1.0, 0.0),
+    );
+    let system = SolarSystem::create_basic_system();
+
+    let covered = ["rasterizer", "raytracer"].map(|backend| {
+        let mut renderer = Renderer::new(160, 120, &mesh);
+        assert!(renderer.select_backend(backend));
+        let frame = renderer.render_frame(&system, &camera, 2.0);
+        let background = frame.pixel(0, 0);
+        (0..frame.height)
+            .flat_map(|y| (0..frame.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| frame.pixel(x, y) != background)
+            .count()
+    });
+
+    // The mesh is inscribed in the traced sphere, so its silhouettes are a little smaller
+    assert!(covered[0] > 0);
+    assert!(covered[1] >= covered[0], "{covered:?}");
+    assert!((covered[1] as f32) < covered[0] as f32 * 1.15, "{covered:?}");
+}
+
+#[test]
+fn panorama_is_centered_on_the_heading() {
+    let mesh = sphere();
+    let renderer = Renderer::new(160, 120, &mesh);
+    let system = SolarSystem::create_basic_system();
+
+    // Looking at the star from inside the orbits, which a single view could not render
+    let eye = Vector3::new(0.0, 0.0, 12.0);
+    let panorama = render_panorama(&renderer, &system, eye, -eye, 0.0, 256);
+
+    assert_eq!((panorama.width, panorama.height), (256, 128));
+    let [r, g, _, _] = panorama.pixel(128, 64);
+    assert!(r > 150 && g > 150, "the star should fill the center, got {r} {g}");
+    let [r, g, b, _] = panorama.pixel(0, 64);
+    assert!(r < 40 && g < 40 && b < 40, "behind the camera should be empty space");
+}
+
+#[test]
+fn distant_bodies_are_drawn_with_coarser_meshes() {
+    assert_eq!(select_level(f32::INFINITY, 3), 0);
+    assert_eq!(select_level(LOD_THRESHOLDS[1], 3), 1);
+    assert_eq!(select_level(0.5, 3), 3);
+    assert_eq!(select_level(0.5, 1), 1);
+
+    let mesh = sphere();
+    let mut renderer = Renderer::new(160, 120, &mesh);
+    let system = SolarSystem::create_basic_system();
+    let camera = Camera::new(Vector3::new(0.0, 15.0, 30.0), Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+    let mut draw = |lod: bool| {
+        renderer.lod = lod;
+        let frame = renderer.render_frame(&system, &camera, 0.0);
+        (frame, renderer.stats.triangles)
+    };
+
+    let (full, full_triangles) = draw(false);
+    let (coarse, coarse_triangles) = draw(true);
+    assert!(coarse_triangles * 2 < full_triangles, "{coarse_triangles} vs {full_triangles} triangles");
+    // Only the silhouettes of small bodies move
+    let differing = full.pixels.chunks_exact(4).zip(coarse.pixels.chunks_exact(4)).filter(|(a, b)| a != b).count();
+    assert!(differing < 160 * 120 / 50, "{differing} pixels changed");
+}
+
+#[test]
+fn starfield_turns_with_the_camera_but_ignores_its_position() {
+    let mesh = sphere();
+    let mut renderer = Renderer::new(160, 120, &mesh);
+    renderer.starfield = Some(Starfield::new(3, true));
+    let empty = SolarSystem::new();
+    let up = Vector3::new(0.0, 1.0, 0.0);
+    let mut draw = |eye: Vector3, target: Vector3| renderer.render_frame(&empty, &Camera::new(eye, target, up), 0.0).pixels;
+
+    let sky = draw(Vector3::new(0.0, 0.0, 10.0), Vector3::zero());
+    let background = [5, 5, 15, 255];
+    assert!(sky.chunks_exact(4).filter(|p| *p != background).count() > 100, "no stars or nebula");
+    assert_eq!(draw(Vector3::new(40.0, -7.0, 10.0), Vector3::new(40.0, -7.0, 0.0)), sky, "moved with the camera");
+    assert_ne!(draw(Vector3::new(0.0, 0.0, 10.0), Vector3::new(10.0, 0.0, 10.0)), sky, "did not turn with the camera");
+
+    // The nebula swirls over time, the stars stay where they are
+    let later = renderer.render_frame(&empty, &Camera::new(Vector3::new(0.0, 0.0, 10.0), Vector3::zero(), up), 120.0).pixels;
+    let drifted = sky.chunks_exact(4).zip(later.chunks_exact(4)).filter(|(a, b)| a != b).count();
+    assert!(drifted > 100, "only {drifted} pixels drifted");
+    renderer.starfield = Some(Starfield::new(3, false));
+    let mut stars = |time: f32| renderer.render_frame(&empty, &Camera::new(Vector3::new(0.0, 0.0, 10.0), Vector3::zero(), up), time).pixels;
+    assert_eq!(stars(0.0), stars(120.0));
+    renderer.starfield = Some(Starfield::new(3, true));
+
+    // Bodies are drawn over the sky
+    let system = SolarSystem::create_basic_system();
+    let camera = Camera::new(Vector3::new(0.0, 15.0, 30.0), Vector3::zero(), up);
+    let with_sky = renderer.render_frame(&system, &camera, 0.0);
+    renderer.starfield = None;
+    assert_eq!(with_sky.pixel(80, 60), renderer.render_frame(&system, &camera, 0.0).pixel(80, 60));
+}
+
+#[test]
+fn framebuffer_writes_round_colors_the_same_way() {
+    let gray = Vector3::new(0.5, 0.25, 0.1);
+    let mut framebuffer = Framebuffer::new(64, 64);
+
+    // Without dithering every write rounds to the nearest value
+    framebuffer.set_colors(&[Vector3::zero(), Vector3::zero(), Vector3::zero(), gray]);
+    framebuffer.point(0, 0, 0.5, gray);
+    framebuffer.blend(1, 0, 0.5, gray, 1.0, BlendMode::Over);
+    framebuffer.overlay_point(2, 0, gray);
+    for x in 0..4 {
+        assert_eq!(framebuffer.pixel(x, 0), [128, 64, 26, 255]);
+    }
+
+    // Dithered, a flat color averages out to itself over a tile of the blue noise
+    framebuffer.set_dither(Some(BlueNoise::new(64, 1)));
+    let level = Vector3::new(0.3, 0.3, 0.3);
+    framebuffer.set_colors(&vec![level; 64 * 64]);
+    let mean = framebuffer.as_rgba_bytes().chunks_exact(BYTES_PER_PIXEL).map(|p| p[0] as f32).sum::<f32>() / (64.0 * 64.0);
+    assert!((mean - 0.3 * 255.0).abs() < 0.1, "mean {mean}");
+}
+
+#[test]
+fn translucent_fragments_blend_back_to_front_after_the_opaque_pass() {
+    let fragment = |depth: f32, color: Vector3, alpha: f32, blend: BlendMode| ShadedFragment { x: 1, y: 1, depth, color, alpha, blend };
+    let red = fragment(0.5, Vector3::new(1.0, 0.0, 0.0), 0.5, BlendMode::Over);
+    let blue = fragment(0.3, Vector3::new(0.0, 0.0, 1.0), 0.5, BlendMode::Over);
+    let wall = fragment(0.4, Vector3::new(0.0, 1.0, 0.0), 1.0, BlendMode::Opaque);
+
+    // Submission order doesn't matter: the nearer layer ends up on top
+    let draw = |fragments: &[ShadedFragment]| {
+        let mut framebuffer = Framebuffer::new(4, 4);
+        write_fragments(&mut framebuffer, fragments);
+        framebuffer.pixel(1, 1)
+    };
+    assert_eq!(draw(&[red, blue]), draw(&[blue, red]));
+    assert_eq!(draw(&[red, blue]), [64, 0, 128, 255]);
+
+    // Opaque surfaces hide what is behind them whatever the order, translucent ones
+    // in front of them are blended over them
+    assert_eq!(draw(&[red, wall]), [0, 255, 0, 255]);
+    assert_eq!(draw(&[blue, wall]), draw(&[wall, blue]));
+    assert_eq!(draw(&[blue, wall]), [0, 128, 128, 255]);
+    let glow = fragment(0.3, Vector3::new(1.0, 0.0, 0.0), 0.5, BlendMode::Additive);
+    assert_eq!(draw(&[wall, glow]), [128, 255, 0, 255]);
+
+    // Rings are translucent, their divisions more than their bands
+    let ring = CelestialObject::ring(0, 1.5, 2.5);
+    assert_eq!(ring.blend, BlendMode::Over);
+    let scene = SolarSystem::parse_scene("[[body]]\nname = \"sun\"\ntype = \"star\"\n\n[[body]]\nparent = \"sun\"\nopacity = 0.4\n")
+        .expect("Failed to parse scene");
+    assert_eq!((scene.objects[1].blend, scene.objects[1].opacity), (BlendMode::Over, 0.4));
+}
+
+#[test]
+fn bodies_outside_the_frustum_are_culled_whole() {
+    let view = create_view_matrix(Vector3::new(0.0, 0.0, 30.0), Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+    let projection = create_projection_matrix(PI / 3.0, 4.0 / 3.0, 0.1, 100.0);
+    assert!(sphere_in_frustum(Vector3::zero(), 1.0, &view, &projection));
+    assert!(!sphere_in_frustum(Vector3::new(0.0, 0.0, 40.0), 1.0, &view, &projection), "behind the camera");
+    assert!(!sphere_in_frustum(Vector3::new(60.0, 0.0, 0.0), 1.0, &view, &projection), "off to the side");
+    assert!(sphere_in_frustum(Vector3::new(60.0, 0.0, 0.0), 40.0, &view, &projection), "big enough to reach in");
+    assert!(sphere_in_frustum(Vector3::new(0.0, 0.0, 30.0), 0.5, &view, &projection), "around the camera");
+    assert!(!sphere_in_frustum(Vector3::new(0.0, 0.0, -200.0), 1.0, &view, &projection), "past the far plane");
+
+    // Culled bodies are counted but change nothing on screen
+    let mesh = sphere();
+    let mut renderer = Renderer::new(160, 120, &mesh);
+    let camera = Camera::new(Vector3::new(0.0, 0.0, 30.0), Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+    let mut system = SolarSystem::new();
+    let star = system.add(CelestialObject::star(3.0));
+    let planet = system.add(CelestialObject::planet(star, 10.0, 0.0, 1.0, ShaderType::Rocky));
+    system.objects[planet].position = Vector3::new(8.0, 0.0, 0.0);
+    let visible = renderer.render_frame(&system, &camera, 0.0);
+    assert_eq!((renderer.stats.objects, renderer.stats.culled), (2, 0));
+
+    for position in [Vector3::new(0.0, 0.0, 40.0), Vector3::new(60.0, 0.0, 0.0)] {
+        let hidden = system.add(CelestialObject::planet(star, 10.0, 0.0, 1.0, ShaderType::Rocky));
+        system.objects[hidden].position = position;
+    }
+    let frame = renderer.render_frame(&system, &camera, 0.0);
+    assert_eq!((renderer.stats.objects, renderer.stats.culled), (2, 2));
+    assert!(frame.pixels == visible.pixels);
+}
+
+#[test]
+fn bodies_hidden_behind_a_nearer_one_are_skipped() {
+    let eye = Vector3::new(0.0, 0.0, 30.0);
+    assert!(sphere_occluded(eye, Vector3::new(0.0, 0.0, -10.0), 1.0, Vector3::zero(), 5.0));
+    assert!(!sphere_occluded(eye, Vector3::new(0.0, 0.0, -10.0), 8.0, Vector3::zero(), 5.0), "too big to hide");
+    assert!(!sphere_occluded(eye, Vector3::new(6.0, 0.0, -10.0), 1.0, Vector3::zero(), 5.0), "peeking out");
+    assert!(!sphere_occluded(eye, Vector3::new(0.0, 0.0, 10.0), 1.0, Vector3::zero(), 5.0), "in front");
+    assert!(!sphere_occluded(Vector3::new(0.0, 0.0, 2.0), Vector3::new(0.0, 0.0, -10.0), 1.0, Vector3::zero(), 5.0), "eye inside");
+
+    let mesh = sphere();
+    let ratio = inner_radius(&mesh) / mesh.bounding_radius();
+    assert!(ratio > 0.8 && ratio < 1.0, "the faces cut inside the vertices' sphere: {ratio}");
+
+    // A planet right behind the star is counted as culled and the frame does not change;
+    // one peeking out beside it is still drawn
+    let mut renderer = Renderer::new(160, 120, &mesh);
+    let camera = Camera::new(eye, Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+    let mut system = SolarSystem::new();
+    let star = system.add(CelestialObject::star(6.0));
+    let alone = renderer.render_frame(&system, &camera, 0.0);
+    let hidden = system.add(CelestialObject::planet(star, 15.0, 0.0, 1.0, ShaderType::Rocky));
+    system.objects[hidden].position = Vector3::new(0.0, 0.0, -15.0);
+    let frame = renderer.render_frame(&system, &camera, 0.0);
+    assert_eq!((renderer.stats.objects, renderer.stats.culled), (1, 1));
+    assert!(frame.pixels == alone.pixels);
+
+    system.objects[hidden].position = Vector3::new(10.0, 0.0, -15.0);
+    let _ = renderer.render_frame(&system, &camera, 0.0);
+    assert_eq!((renderer.stats.objects, renderer.stats.culled), (2, 0));
+}
+
+#[test]
+fn resizing_rebuilds_the_buffers_and_matrices_in_place() {
+    let mesh = sphere();
+    let mut renderer = Renderer::new(120, 90, &mesh);
+    renderer.noise_cache.enabled = false;
+    renderer.set_fov(50f32.to_radians());
+    let system = SolarSystem::parse_scene("[[body]]\nname = \"sun\"\ntype = \"star\"\n").expect("Failed to parse scene");
+    let camera = Camera::new(Vector3::new(0.0, 0.0, 6.0), Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+    renderer.render_frame(&system, &camera, 0.0);
+
+    // A wider window: matching buffers, the aspect ratio of the new size, settings kept
+    renderer.resize(200, 90);
+    assert_eq!((renderer.framebuffer.width, renderer.framebuffer.height), (200, 90));
+    assert_eq!(renderer.framebuffer.depth_buffer().len(), 200 * 90);
+    assert_eq!(renderer.projection, renderer.resized(200, 90).projection);
+    assert_eq!(renderer.viewport, create_viewport_matrix(0.0, 0.0, 200.0, 90.0));
+    assert!(!renderer.noise_cache.enabled);
+
+    // The star stays round: as tall as it is wide in pixels
+    let frame = renderer.render_frame(&system, &camera, 0.0);
+    assert_eq!(frame.pixels.len(), 200 * 90 * 4);
+    let center = project_to_screen(Vector3::zero(), &camera.get_view_matrix(), &renderer.projection, &renderer.viewport).expect("star in view");
+    let lit = |x: i32, y: i32| renderer.framebuffer.depth_buffer()[(y * 200 + x) as usize].is_finite();
+    let (cx, cy) = (center.x.round() as i32, center.y.round() as i32);
+    let across = (0..200).filter(|&x| lit(x, cy)).count() as i32;
+    let down = (0..90).filter(|&y| lit(cx, y)).count() as i32;
+    assert!(across > 10 && (across - down).abs() <= 2, "star is {across}x{down} pixels");
+}
+
+#[test]
+fn render_scale_steps_wrap_and_snap_to_the_next_step() {
+    assert_eq!(step_render_scale(0.5, false), 1.0);
+    assert_eq!(step_render_scale(1.0, false), 2.0);
+    assert_eq!(step_render_scale(2.0, false), 0.5);
+    assert_eq!(step_render_scale(0.5, true), 2.0);
+    assert_eq!(step_render_scale(2.0, true), 1.0);
+    // From a configured scale between steps
+    assert_eq!(step_render_scale(0.75, false), 1.0);
+    assert_eq!(step_render_scale(0.75, true), 0.5);
+    assert!(RENDER_SCALE_STEPS.windows(2).all(|pair| pair[0] < pair[1]));
+}
@@ -0,0 +1,675 @@
+// scene.rs
+// Integration tests for scene files, scripts and the simulation of the bodies
+
+mod common;
+
+use common::sphere;
+use lab5::asteroid_belt::AsteroidBelt;
+use lab5::camera::Camera;
+use lab5::collision::CollisionResponse;
+use lab5::debug_ui::DebugSettings;
+use lab5::fragment::BlendMode;
+use lab5::framebuffer::Framebuffer;
+use lab5::generator::GeneratorParams;
+use lab5::gravity::Motion;
+use lab5::input::{Action, InputFrame};
+use lab5::matrix::{create_model_matrix, multiply_matrix_vector4, create_projection_matrix, create_view_matrix, create_viewport_matrix, project_to_screen};
+use lab5::mesh::SPHERE_RADIUS;
+use lab5::particles::{draw_particles, Emitter, Particle, ParticleShape, ParticleSystem};
+use lab5::renderer::{ImageBuffer, Renderer};
+use lab5::replay::{CameraSetup, Recorder, Recording, SceneLoad};
+use lab5::scene_file::scene_script;
+use lab5::shader_system::ShaderType;
+use lab5::script::{Script, ScriptContext};
+use lab5::simulation::Simulation;
+use lab5::solar_system::{CelestialObject, CelestialType, Orbit, SolarSystem};
+use lab5::spacecraft::{Spacecraft, SHIP_MODEL};
+use raylib::math::{Vector2, Vector3, Vector4};
+use std::f32::consts::PI;
+
+#[test]
+fn presets_have_lights_and_distinct_seeds() {
+    for system in [SolarSystem::create_basic_system(), SolarSystem::create_alien_system()] {
+        assert!(!system.lights(1.0).is_empty());
+
+        let mut seeds: Vec<u32> = system.objects.iter().map(|o| o.seed).collect();
+        seeds.sort_unstable();
+        seeds.dedup();
+        assert_eq!(seeds.len(), system.objects.len());
+    }
+}
+
+#[test]
+fn recorded_session_loads_back_unchanged() {
+    let path = std::env::temp_dir().join(format!("lab5_replay_{}.txt", std::process::id()));
+    let camera = CameraSetup::capture(&Camera::new(
+        Vector3::new(0.0, 15.0, 30.0),
+        Vector3::zero(),
+        Vector3::new(0.0, 1.0, 0.0),
+    ));
+    let scene = SceneLoad::capture("basic", &SolarSystem::create_basic_system());
+    let input = InputFrame {
+        delta_time: 1.0 / 60.0,
+        time: 0.25,
+        down: vec![Action::OrbitUp, Action::Modifier],
+        pressed: vec![Action::PresetAlien, Action::Pick],
+        mouse_delta: Vector2::new(12.0, -3.5),
+        mouse_position: Vector2::new(100.0, 80.5),
+        wheel: 1.0,
+    };
+    let settings = DebugSettings {
+        time_scale: 2.5,
+        dither: false,
+        post_effects: [false, true, false, true, false],
+        ..DebugSettings::default()
+    };
+    let alien = SceneLoad::capture("alien", &SolarSystem::create_alien_system());
+
+    let mut recorder = Recorder::create(&path, 320, 240, &camera, &scene).unwrap();
+    recorder.record_frame(&input, &settings).unwrap();
+    recorder.record_scene(&alien).unwrap();
+    recorder.record_frame(&InputFrame::default(), &DebugSettings::default()).unwrap();
+    recorder.finish().unwrap();
+
+    let recording = Recording::load(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!((recording.width, recording.height), (320, 240));
+    assert_eq!(recording.camera, camera);
+    assert_eq!(recording.scene, scene);
+    assert_eq!(recording.frames.len(), 2);
+    assert_eq!(recording.frames[0].input, input);
+    assert_eq!(recording.frames[0].settings, settings);
+    assert_eq!(recording.frames[0].scene.as_ref(), Some(&alien));
+    assert_eq!(recording.frames[1].scene, None);
+}
+
+#[test]
+fn threaded_simulation_advances_on_its_own() {
+    let system = SolarSystem::create_basic_system();
+    let start: Vec<f32> = system.objects.iter().map(|o| o.orbit_angle).collect();
+    let mut simulation = Simulation::threaded(system, 240.0);
+
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    let moved: Vec<f32> = simulation.scene().objects.iter().map(|o| o.orbit_angle).collect();
+    assert_ne!(moved, start);
+
+    // Replacing the scene discards snapshots of the old one
+    simulation.set_paused(true);
+    simulation.load(SolarSystem::create_alien_system());
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    assert_eq!(simulation.scene().objects.len(), SolarSystem::create_alien_system().objects.len());
+}
+
+#[test]
+fn example_scene_file_loads() {
+    let system = SolarSystem::from_file("scenes/example.toml").expect("Failed to load example scene");
+
+    assert_eq!(system.objects.len(), 9);
+    assert_eq!(system.seed, 7);
+    let luna = system.objects.iter().find(|o| o.name == "luna").unwrap();
+    let terra = system.objects.iter().position(|o| o.name == "terra");
+    assert_eq!(luna.parent_index, terra);
+
+    assert!(SolarSystem::parse_scene("[[body]]\nname = \"moon\"\ntype = \"moon\"").is_err());
+}
+
+#[test]
+fn json_scenes_match_their_toml_equivalent() {
+    let toml = SolarSystem::parse_scene(
+        "seed = 4\n[[body]]\nname = \"sun\"\ntype = \"star\"\n\n\
+         [[body]]\nname = \"rock\"\nparent = \"sun\"\norbit_radius = 5.0\norbit_angle = 1.0\ncolor = [1.0, 0.5, 0.5]\n",
+    )
+    .expect("Failed to parse TOML scene");
+    let json = SolarSystem::parse_json_scene(
+        r#"{ "seed": 4, "body": [
+            { "name": "sun", "type": "star" },
+            { "name": "rock", "parent": "sun", "orbit_radius": 5.0, "orbit_angle": 1.0, "color": [1.0, 0.5, 0.5] }
+        ] }"#,
+    )
+    .expect("Failed to parse JSON scene");
+
+    assert_eq!(json.seed, toml.seed);
+    for (a, b) in json.objects.iter().zip(&toml.objects) {
+        assert_eq!((&a.name, a.parent_index, a.orbit_radius, a.orbit_angle), (&b.name, b.parent_index, b.orbit_radius, b.orbit_angle));
+        assert_eq!(a.material, b.material);
+    }
+    assert_eq!(json.objects[1].material.diffuse, Vector3::new(0.5, 0.25, 0.25));
+
+    let example = SolarSystem::from_file("scenes/example.json").expect("Failed to load example scene");
+    assert!(example.objects.iter().any(|o| o.ring.is_some()));
+    let error = SolarSystem::parse_json_scene("{ \"body\": [ { \"name\": \"sun\" \"type\": \"star\" } ] }");
+    assert!(error.is_err());
+}
+
+#[test]
+fn script_random_follows_the_scene_seed() {
+    let source = "every 1 {\n  body(\"sun\").scale = 1 + random()\n}";
+    let scales = |seed: u32| -> Vec<f32> {
+        let mut system = SolarSystem::parse_scene("[[body]]\nname = \"sun\"\ntype = \"star\"\n").expect("Failed to parse scene");
+        system.seed = seed;
+        let mut script = Script::parse(source).unwrap();
+        let (mut camera, mut settings) = (Camera::new(Vector3::new(0.0, 0.0, 10.0), Vector3::zero(), Vector3::new(0.0, 1.0, 0.0)), DebugSettings::default());
+        (0..5)
+            .map(|_| {
+                script.run(&mut ScriptContext { system: &mut system, camera: &mut camera, settings: &mut settings, dt: 1.0 }).unwrap();
+                system.objects[0].scale
+            })
+            .collect()
+    };
+    assert_eq!(scales(7), scales(7), "reruns of the same scene draw the same numbers");
+    assert_ne!(scales(7), scales(8));
+    assert!(scales(7).iter().all(|scale| (1.0..2.0).contains(scale)));
+}
+
+#[test]
+fn script_timers_catch_up_a_bounded_number_of_runs() {
+    let source = "on start { body(\"sun\").scale = 0 }\nevery 0.25 {\n  body(\"sun\").scale = body(\"sun\").scale + 1\n}";
+    let mut system = SolarSystem::parse_scene("[[body]]\nname = \"sun\"\ntype = \"star\"\n").expect("Failed to parse scene");
+    let mut script = Script::parse(source).unwrap();
+    let (mut camera, mut settings) = (Camera::new(Vector3::new(0.0, 0.0, 10.0), Vector3::zero(), Vector3::new(0.0, 1.0, 0.0)), DebugSettings::default());
+    let mut run = |dt: f32| {
+        script.run(&mut ScriptContext { system: &mut system, camera: &mut camera, settings: &mut settings, dt }).unwrap();
+        system.objects[0].scale
+    };
+
+    // Every interval that passed runs once, then a long frame runs only a few of its many
+    assert_eq!(run(0.1), 0.0);
+    assert_eq!(run(0.5), 2.0);
+    let caught_up = run(1000.0);
+    assert!(caught_up > 2.0 && caught_up <= 10.0, "{caught_up} runs");
+    // The dropped runs are skipped, not owed to the next frames
+    assert_eq!(run(0.25), caught_up + 1.0);
+
+    // Intervals too short to ever keep up with are refused
+    assert!(Script::parse("every 0 { }").is_err());
+    assert!(Script::parse("every 0.0001 { }").is_err());
+    assert!(Script::parse("every 0.01 { }").is_ok());
+}
+
+#[test]
+fn example_script_spawns_comets_on_its_timer() {
+    let mut system = SolarSystem::from_file("scenes/example.toml").expect("Failed to load example scene");
+    let script_path = scene_script("scenes/example.toml").unwrap().expect("example scene has a script");
+    let mut script = Script::from_file(script_path).expect("Failed to load example script");
+    let mut camera = Camera::new(Vector3::new(0.0, 15.0, 30.0), Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+    let mut settings = DebugSettings::default();
+    let start_yaw = camera.yaw;
+    let luna = system.objects.iter().find(|o| o.name == "luna").unwrap().orbit_radius;
+
+    // 20 simulated seconds: comets at 8 and 16. Halfway through "ember" goes away, moving
+    // every later body down one index under the handles the script keeps in its globals
+    for step in 0..200 {
+        if step == 100 {
+            let mut context = ScriptContext { system: &mut system, camera: &mut camera, settings: &mut settings, dt: 0.0 };
+            Script::parse("on start { remove(\"ember\") }").unwrap().run(&mut context).unwrap();
+        }
+        let mut context = ScriptContext { system: &mut system, camera: &mut camera, settings: &mut settings, dt: 0.1 };
+        script.run(&mut context).unwrap();
+        system.update(0.1);
+    }
+
+    let comets: Vec<&CelestialObject> = system.objects.iter().filter(|o| o.name.starts_with("comet")).collect();
+    let names: Vec<&str> = comets.iter().map(|o| o.name.as_str()).collect();
+    assert_eq!(names, ["comet1", "comet2"]);
+    assert!(camera.yaw > start_yaw);
+    // The comet spawned before the removal finished growing in, the newest is on its way
+    assert!((comets[0].scale - 0.12).abs() < 1e-5, "comet1 at {}", comets[0].scale);
+    assert!(comets[1].scale > 0.05 && comets[1].scale < 0.12, "comet2 at {}", comets[1].scale);
+    // Terra's orbit still breathes and its moon, now at Terra's old index, is left alone
+    let terra = system.objects.iter().find(|o| o.name == "terra").unwrap();
+    assert!((terra.orbit_radius - (13.0 + (20.0f32 * 0.2).sin() * 1.5)).abs() < 1e-3);
+    assert_eq!(system.objects.iter().find(|o| o.name == "luna").unwrap().orbit_radius, luna);
+
+    let error = Script::parse("on update {\n  body(\"nobody\").scale = 2\n}").unwrap().run(&mut ScriptContext {
+        system: &mut system,
+        camera: &mut camera,
+        settings: &mut settings,
+        dt: 0.1,
+    });
+    assert_eq!(error.unwrap_err().line, 2);
+    assert!(Script::parse("every { }").is_err());
+}
+
+#[test]
+fn script_globals_stay_on_their_body_when_a_merge_shifts_the_indices() {
+    let mut system = SolarSystem::parse_scene(
+        "collisions = \"merge\"\n\n\
+         [[body]]\nname = \"sun\"\ntype = \"star\"\n\n\
+         [[body]]\nname = \"big\"\nparent = \"sun\"\norbit_radius = 10.0\norbit_angle = 0.0\nscale = 1.0\n\n\
+         [[body]]\nname = \"small\"\nparent = \"sun\"\norbit_radius = 10.0\norbit_angle = 0.05\nscale = 0.5\n\n\
+         [[body]]\nname = \"far\"\nparent = \"sun\"\norbit_radius = 30.0\nscale = 0.8\n",
+    )
+    .expect("Failed to parse scene");
+    system.update(0.0);
+    let (mut camera, mut settings) = (Camera::new(Vector3::new(0.0, 15.0, 30.0), Vector3::zero(), Vector3::new(0.0, 1.0, 0.0)), DebugSettings::default());
+    let grow = |name: &str| Script::parse(&format!("let target = body(\"{name}\")\non update {{\n  target.luminosity = target.luminosity + 1\n}}")).unwrap();
+    let (mut far, mut small) = (grow("far"), grow("small"));
+    let mut run = |script: &mut Script, system: &mut SolarSystem| {
+        script.run(&mut ScriptContext { system, camera: &mut camera, settings: &mut settings, dt: 0.1 })
+    };
+    run(&mut far, &mut system).unwrap();
+    run(&mut small, &mut system).unwrap();
+    assert_eq!(system.objects[3].luminosity, 2.0);
+
+    // "small" is absorbed into "big", so "far" moves down to index 2 and the handle kept in
+    // the global still reaches it
+    system.advance(0.0);
+    assert_eq!(system.objects.len(), 3);
+    let big = system.objects[1].luminosity;
+    run(&mut far, &mut system).unwrap();
+    assert_eq!((system.objects[2].name.as_str(), system.objects[2].luminosity), ("far", 3.0));
+    assert_eq!(system.objects[1].luminosity, big);
+
+    // The absorbed body is an error instead of whichever body took its index
+    let error = run(&mut small, &mut system).unwrap_err();
+    assert_eq!(error.line, 3);
+    assert!(error.message.contains("no longer exists"), "{}", error.message);
+    assert_eq!(system.objects[2].luminosity, 3.0);
+}
+
+#[test]
+fn timeline_scrubs_to_absolute_times() {
+    let mut stepped = SolarSystem::create_basic_system();
+    // Places the bodies on their starting angles
+    stepped.update(0.0);
+    let mut scrubbed = stepped.clone();
+    let start: Vec<Vector3> = stepped.objects.iter().map(|o| o.position).collect();
+
+    for _ in 0..600 {
+        stepped.update(0.05);
+    }
+    scrubbed.set_time(30.0);
+    // Stepping accumulates rounding over the 600 updates, the jump does not
+    for (a, b) in stepped.objects.iter().zip(&scrubbed.objects) {
+        assert!(a.position.distance_to(b.position) < 1e-2, "{:?} vs {:?}", a.position, b.position);
+    }
+
+    // Back to the start
+    scrubbed.set_time(0.0);
+    for (object, position) in scrubbed.objects.iter().zip(&start) {
+        assert!(object.position.distance_to(*position) < 1e-3);
+    }
+    assert!(scrubbed.year_length().is_some_and(|year| year > 0.0));
+}
+
+#[test]
+fn elliptical_orbits_follow_keplers_equation() {
+    // The default orbit is the old circle in the XZ plane
+    let circle = Orbit::default().offset(5.0, 1.0);
+    assert!((circle - Vector3::new(5.0 * 1f32.cos(), 0.0, 5.0 * 1f32.sin())).length() < 1e-5);
+
+    let ellipse = Orbit::new(0.5, 0.0, 0.0, 0.0);
+    assert!((ellipse.offset(10.0, 0.0).length() - 5.0).abs() < 1e-4, "periapsis at a(1 - e)");
+    assert!((ellipse.offset(10.0, PI).length() - 15.0).abs() < 1e-4, "apoapsis at a(1 + e)");
+    // Equal time steps cover more of the orbit near the periapsis
+    let step = |m: f32| (ellipse.offset(10.0, m + 0.1) - ellipse.offset(10.0, m)).length();
+    assert!(step(0.0) > 2.5 * step(PI - 0.05));
+
+    let tilted = Orbit::new(0.0, 0.5, 0.0, 0.0);
+    assert!((tilted.offset(4.0, PI / 2.0).y - 4.0 * 0.5f32.sin()).abs() < 1e-5);
+    assert!(tilted.offset(4.0, 0.0).y.abs() < 1e-5, "the node lies on the XZ plane");
+
+    let mut system = SolarSystem::parse_scene(
+        "[[body]]\nname = \"sun\"\ntype = \"star\"\n\n\
+         [[body]]\nname = \"rock\"\nparent = \"sun\"\norbit_radius = 8.0\norbit_angle = 0.0\n\
+         eccentricity = 0.25\ninclination = 0.3\nperiapsis = 1.0\nascending_node = 2.0\n",
+    )
+    .expect("Failed to parse scene");
+    assert_eq!(system.objects[1].orbit, Orbit::new(0.25, 0.3, 1.0, 2.0));
+    system.update(0.0);
+    assert!((system.objects[1].position.length() - 6.0).abs() < 1e-4);
+}
+
+#[test]
+fn touching_bodies_log_merge_or_shatter() {
+    let scene = |response: &str| {
+        let mut system = SolarSystem::parse_scene(&format!(
+            "collisions = \"{response}\"\n\n\
+             [[body]]\nname = \"sun\"\ntype = \"star\"\n\n\
+             [[body]]\nname = \"big\"\nparent = \"sun\"\norbit_radius = 10.0\norbit_angle = 0.0\nscale = 1.0\n\n\
+             [[body]]\nname = \"small\"\nparent = \"sun\"\norbit_radius = 10.0\norbit_angle = 0.05\nscale = 0.5\n\n\
+             [[body]]\nname = \"pebble\"\ntype = \"moon\"\nparent = \"small\"\norbit_radius = 2.0\nscale = 0.1\n",
+        ))
+        .expect("Failed to parse scene");
+        system.update(0.0);
+        system
+    };
+
+    // Contacts are reported once, however long the bodies keep touching
+    let mut logged = scene("log");
+    let collisions = logged.advance(0.0);
+    assert_eq!(collisions.len(), 1);
+    assert_eq!((collisions[0].larger.as_str(), collisions[0].smaller.as_str()), ("big", "small"));
+    assert!(logged.advance(0.0).is_empty());
+    assert_eq!(logged.objects.len(), 4);
+
+    // Merging keeps the volume and hands the moons over, throwing sparks
+    let mut merged = scene("merge");
+    assert_eq!(merged.advance(0.0)[0].response, CollisionResponse::Merge);
+    assert_eq!(merged.effects.len(), 1);
+    assert!(logged.effects.is_empty());
+    let names: Vec<&str> = merged.objects.iter().map(|o| o.name.as_str()).collect();
+    assert_eq!(names, ["sun", "big", "pebble"]);
+    assert!((merged.objects[1].scale - 1.125f32.cbrt()).abs() < 1e-5);
+    assert_eq!(merged.objects[2].parent_index, Some(1));
+
+    // Shattering leaves fragments around the survivor that pass through everything
+    let mut shattered = scene("debris");
+    shattered.advance(0.0);
+    let debris: Vec<&CelestialObject> = shattered.objects.iter().filter(|o| o.debris).collect();
+    assert_eq!(debris.len(), 8);
+    assert!(debris.iter().all(|o| o.parent_index == Some(1) && o.name.starts_with("small debris")));
+    for _ in 0..200 {
+        assert!(shattered.advance(0.1).iter().all(|c| !c.smaller.contains("debris")));
+    }
+}
+
+#[test]
+fn particle_emitters_release_age_and_draw_depth_tested() {
+    let emitter = Emitter {
+        velocity: Vector3::new(1.0, 0.0, 0.0),
+        rate: 10.0,
+        lifetime: 2.0,
+        color_start: Vector3::new(1.0, 0.0, 0.0),
+        color_end: Vector3::new(0.0, 0.0, 1.0),
+        ..Emitter::default()
+    };
+    let mut system = ParticleSystem::new(emitter, 100).with_blend(BlendMode::Opaque);
+    system.emit(1.0);
+    assert_eq!(system.particles.len(), 10);
+    // Emitted along the whole second, so they are spread out and colored by age
+    let (young, old) = (system.particles[9], system.particles[0]);
+    assert!(old.position.x > young.position.x);
+    assert!(system.color(&young).x > system.color(&old).x);
+    system.update(1.5, Vector3::zero());
+    assert!(system.particles.len() < 10 && !system.is_empty());
+    system.burst(500);
+    assert_eq!(system.particles.len(), 100);
+    system.update(2.0, Vector3::zero());
+    assert!(system.is_empty());
+
+    // A point behind something already drawn is hidden, one in front covers it; quads
+    // cover a few pixels
+    let view = create_view_matrix(Vector3::new(0.0, 0.0, 10.0), Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+    let projection = create_projection_matrix(PI / 3.0, 1.0, 0.1, 100.0);
+    let viewport = create_viewport_matrix(0.0, 0.0, 64.0, 64.0);
+    let draw = |system: &ParticleSystem, z: f32| {
+        let mut framebuffer = Framebuffer::new(64, 64);
+        framebuffer.clear();
+        let wall = project_to_screen(Vector3::zero(), &view, &projection, &viewport).unwrap();
+        framebuffer.point(32, 32, wall.z, Vector3::new(0.0, 1.0, 0.0));
+        let mut system = system.clone();
+        system.particles = vec![Particle { position: Vector3::new(0.0, 0.0, z), velocity: Vector3::zero(), age: 0.0, lifetime: 1.0 }];
+        draw_particles(&mut framebuffer, &system, &view, &projection, &viewport);
+        let lit = (0..64).flat_map(|y| (0..64).map(move |x| (x, y))).filter(|&(x, y)| framebuffer.pixel(x, y)[0] > 0).count();
+        (framebuffer.pixel(32, 32), lit)
+    };
+    assert_eq!(draw(&system, -1.0).0[..3], [0, 255, 0]);
+    assert_eq!(draw(&system, 1.0), ([255, 0, 0, 255], 1));
+    system.emitter.size = 0.2;
+    let quad = system.clone().with_shape(ParticleShape::Quad { soft: false });
+    assert!(draw(&quad, 1.0).1 > 4);
+}
+
+#[test]
+fn comet_tails_stream_away_from_the_star_and_fade() {
+    let mut system = SolarSystem::parse_scene(
+        "[[body]]\nname = \"sun\"\ntype = \"star\"\n\n\
+         [[body]]\nname = \"comet\"\ntype = \"comet\"\nparent = \"sun\"\norbit_radius = 12.0\norbit_angle = 0.0\nscale = 0.3\n",
+    )
+    .expect("Failed to parse scene");
+    assert_eq!(system.objects[1].object_type, CelestialType::Comet);
+    assert!(system.objects[1].orbit.eccentricity > 0.5);
+    for _ in 0..120 {
+        system.update(1.0 / 30.0);
+    }
+
+    let comet = &system.objects[1];
+    let tail = comet.tail.as_ref().expect("comets have a tail");
+    assert!(tail.particles.len() > 20);
+    let away = comet.position.normalized();
+    // The oldest particles are the farthest down the tail and the faintest
+    let oldest = tail.particles.iter().max_by(|a, b| a.age.total_cmp(&b.age)).unwrap();
+    let newest = tail.particles.iter().min_by(|a, b| a.age.total_cmp(&b.age)).unwrap();
+    assert!((oldest.position - comet.position).dot(away) > 1.0);
+    assert!(oldest.alpha() < newest.alpha());
+    assert!(tail.particles.iter().all(|p| p.alpha() > 0.0 && p.alpha() <= 1.0));
+
+    // The tail glows over the background where it points
+    let mesh = sphere();
+    let mut renderer = Renderer::new(160, 120, &mesh);
+    let camera = Camera::new(Vector3::new(0.0, 40.0, 0.1), Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+    let frame = renderer.render_frame(&system, &camera, 0.0);
+    let mut bare = system.clone();
+    bare.objects[1].tail.as_mut().unwrap().clear();
+    let without = renderer.render_frame(&bare, &camera, 0.0);
+    let tip = comet.position + away * 1.5;
+    let screen = project_to_screen(tip, &camera.get_view_matrix(), &renderer.projection, &renderer.viewport).unwrap();
+    let brightness = |image: &ImageBuffer| {
+        let [r, g, b, _] = image.pixel(screen.x as usize, screen.y as usize);
+        r as u32 + g as u32 + b as u32
+    };
+    assert!(brightness(&frame) > brightness(&without));
+
+    // Going back in time starts the tail over
+    system.update(-1.0);
+    assert!(system.objects[1].tail.as_ref().unwrap().particles.is_empty());
+}
+
+#[test]
+fn asteroid_belts_orbit_in_their_annulus_and_fall_back_to_sprites() {
+    let mut system = SolarSystem::parse_scene(
+        "[[body]]\nname = \"sun\"\ntype = \"star\"\n\n\
+         [[belt]]\nparent = \"sun\"\ninner_radius = 8.0\nouter_radius = 10.0\ncount = 200\nthickness = 0.0\n",
+    )
+    .expect("Failed to parse scene");
+    assert_eq!(system.belts[0].rocks.len(), 200);
+    // The same seed lays out the same belt
+    let again = AsteroidBelt::new(0, 8.0, 10.0, 200, 0.04, (0.03, 0.1), 0.0, system.seed);
+    assert_eq!(again.rocks[17].position, system.belts[0].rocks[17].position);
+
+    let angles: Vec<f32> = system.belts[0].rocks.iter().map(|rock| rock.orbit_angle).collect();
+    system.update(25.0);
+    for (rock, angle) in system.belts[0].rocks.iter().zip(angles) {
+        let distance = rock.position.length();
+        assert!((8.0..=10.0).contains(&distance), "rock left the belt at {distance}");
+        assert!(rock.orbit_angle > angle);
+    }
+
+    // Far away the rocks are points and add no triangles; close up they are meshes
+    let mesh = sphere();
+    let mut renderer = Renderer::new(160, 120, &mesh);
+    let triangles = |renderer: &mut Renderer, system: &SolarSystem, eye: Vector3| {
+        let camera = Camera::new(eye, Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+        renderer.render_frame(system, &camera, 0.0);
+        renderer.stats.triangles
+    };
+    let mut bare = system.clone();
+    bare.belts.clear();
+    let far = Vector3::new(0.0, 40.0, 60.0);
+    assert_eq!(triangles(&mut renderer, &system, far), triangles(&mut renderer, &bare, far));
+    renderer.asteroid_sprites = false;
+    assert!(triangles(&mut renderer, &system, far) > triangles(&mut renderer, &bare, far));
+    renderer.asteroid_sprites = true;
+    let rock = system.belts[0].rocks[0].position;
+    let near = rock + (rock - Vector3::zero()).normalized() * 1.0 + Vector3::new(0.0, 0.3, 0.0);
+    let close_up = Camera::new(near, rock, Vector3::new(0.0, 1.0, 0.0));
+    renderer.render_frame(&system, &close_up, 0.0);
+    let with_rocks = renderer.stats.triangles;
+    renderer.render_frame(&bare, &close_up, 0.0);
+    assert!(with_rocks > renderer.stats.triangles);
+}
+
+#[test]
+fn gravity_mode_keeps_circular_orbits_and_momentum() {
+    let scene = |motion: &str| {
+        SolarSystem::parse_scene(&format!(
+            "motion = \"{motion}\"\n\n\
+             [[body]]\nname = \"sun\"\ntype = \"star\"\nscale = 3.0\n\n\
+             [[body]]\nname = \"rock\"\nparent = \"sun\"\norbit_radius = 12.0\norbit_angle = 0.0\n\n\
+             [[body]]\nname = \"comet\"\nparent = \"sun\"\norbit_radius = 20.0\norbit_angle = 2.0\nmass = 5.0\nvelocity = [0.0, 0.05, 0.0]\n",
+        ))
+        .expect("Failed to parse scene")
+    };
+    let momentum = |system: &SolarSystem| {
+        system.objects.iter().fold(Vector3::zero(), |sum, o| sum + o.velocity * o.mass())
+    };
+
+    // Kinematic stays the default and ignores masses
+    let mut kinematic = scene("kinematic");
+    assert_eq!(SolarSystem::new().motion, Motion::Kinematic);
+    kinematic.update(10.0);
+    assert_eq!(kinematic.objects[0].position, Vector3::zero());
+
+    let mut system = scene("gravity");
+    assert_eq!(system.motion, Motion::Gravity);
+    assert_eq!(system.objects[2].mass(), 5.0);
+    assert!(momentum(&system).length() < 1e-3);
+    let distance = |system: &SolarSystem| system.objects[1].position.distance_to(system.objects[0].position);
+    for _ in 0..400 {
+        system.update(1.0);
+        // Without a launch velocity the rock orbits in a circle
+        assert!((distance(&system) - 12.0).abs() < 0.6, "rock drifted to {}", distance(&system));
+    }
+    assert!(system.objects[1].velocity.length() > 0.1);
+    assert!(momentum(&system).length() < 1e-2);
+    // The comet was launched off the plane
+    assert!(system.objects[2].position.y.abs() > 1.0);
+
+    // Back to kinematic, bodies return to their orbits
+    system.set_motion(Motion::Kinematic);
+    assert!((distance(&system) - 12.0).abs() < 1e-3);
+}
+
+#[test]
+fn scenes_are_reproducible_from_their_seed() {
+    let angles = |system: &SolarSystem| system.objects.iter().map(|object| object.orbit_angle).collect::<Vec<f32>>();
+
+    // Same seed, same starting orbits; another seed moves the planets but not the moons
+    let mut a = SolarSystem::create_basic_system();
+    let mut b = SolarSystem::create_basic_system();
+    a.reseed(5);
+    b.reseed(5);
+    assert_eq!(angles(&a), angles(&b));
+    b.reseed(6);
+    let planets = a.objects.iter().zip(&b.objects).filter(|(object, _)| object.object_type == CelestialType::Planet);
+    assert!(planets.clone().count() > 1 && planets.clone().all(|(a, b)| a.orbit_angle != b.orbit_angle));
+    assert!(a.objects.iter().zip(&b.objects).filter(|(object, _)| object.object_type == CelestialType::Moon).all(|(a, b)| a.orbit_angle == b.orbit_angle));
+
+    // An angle given by the scene file is kept whatever the seed
+    let scene = "seed = 9\n[[body]]\nname = \"sun\"\ntype = \"star\"\n\n[[body]]\nname = \"a\"\nparent = \"sun\"\norbit_radius = 5.0\norbit_angle = 1.25\n\n[[body]]\nname = \"b\"\nparent = \"sun\"\norbit_radius = 8.0\n";
+    let mut system = SolarSystem::parse_scene(scene).expect("Failed to parse scene");
+    let seeded = system.objects[2].orbit_angle;
+    assert_eq!(SolarSystem::parse_scene(scene).expect("Failed to parse scene").objects[2].orbit_angle, seeded);
+    system.reseed(10);
+    assert_eq!(system.objects[1].orbit_angle, 1.25);
+    assert_ne!(system.objects[2].orbit_angle, seeded);
+}
+
+#[test]
+fn generated_systems_are_reproducible_and_ordered_by_starlight() {
+    let params = GeneratorParams::default();
+    for seed in 0..20 {
+        let system = SolarSystem::generate(seed, &params);
+        let again = SolarSystem::generate(seed, &params);
+        let describe = |system: &SolarSystem| system.objects.iter().map(|o| (o.name.clone(), o.orbit_radius, o.orbit_angle, o.material.shader)).collect::<Vec<_>>();
+        assert_eq!(describe(&system), describe(&again));
+        assert_eq!(system.seed, seed);
+
+        // Planets of the main star on widening orbits, slower further out
+        let planets: Vec<&CelestialObject> = system.objects.iter().filter(|o| o.object_type == CelestialType::Planet).collect();
+        assert!((params.planets.0..=params.planets.1).contains(&planets.len()), "seed {seed}: {} planets", planets.len());
+        assert!(planets.windows(2).all(|pair| pair[1].orbit_radius > pair[0].orbit_radius && pair[1].orbit_speed < pair[0].orbit_speed));
+
+        // Lava worlds only inside the temperate zone, gas giants only outside it
+        let outermost = |shader| planets.iter().filter(|p| p.material.shader == shader).map(|p| p.orbit_radius).fold(0.0, f32::max);
+        let innermost = |shader| planets.iter().filter(|p| p.material.shader == shader).map(|p| p.orbit_radius).fold(f32::MAX, f32::min);
+        assert!(outermost(ShaderType::Lava) < innermost(ShaderType::CloudPlanet));
+        assert!(outermost(ShaderType::CloudPlanet) < innermost(ShaderType::GasGiant));
+
+        // Moons and rings belong to planets
+        for object in system.objects.iter().filter(|o| matches!(o.object_type, CelestialType::Moon | CelestialType::Ring)) {
+            let parent = object.parent_index.expect("moons have a parent");
+            assert_eq!(system.objects[parent].object_type, CelestialType::Planet);
+        }
+    }
+    let sizes: Vec<usize> = (0..20).map(|seed| SolarSystem::generate(seed, &params).objects.len()).collect();
+    assert!(sizes.iter().any(|&size| size != sizes[0]), "every seed made the same system");
+}
+
+#[test]
+fn spacecraft_flies_by_hand_and_by_autopilot() {
+    let mut ship = Spacecraft::load(SHIP_MODEL).expect("Failed to load the ship");
+    // Hull, wings, fin, canopy and a glowing engine, shaded flat
+    assert_eq!(ship.model.parts.len(), 5);
+    assert!(ship.model.parts.iter().any(|(material, _)| material.emission.x > 0.5));
+    assert_eq!(ship.model.mesh.vertices.len(), ship.model.mesh.indices.len());
+
+    // The model's nose turns with the heading, its wings stay level
+    (ship.yaw, ship.pitch) = (2.2, -0.4);
+    let model = create_model_matrix(Vector3::zero(), 1.0, ship.rotation());
+    let turn = |x: f32, y: f32, z: f32| {
+        let v = multiply_matrix_vector4(&model, &Vector4::new(x, y, z, 0.0));
+        Vector3::new(v.x, v.y, v.z)
+    };
+    assert!((turn(0.0, 0.0, 1.0) - ship.forward()).length() < 1e-4);
+    assert!(turn(1.0, 0.0, 0.0).y.abs() < 1e-4 && turn(0.0, 1.0, 0.0).y > 0.0);
+
+    let camera = Camera::new(Vector3::new(0.0, 5.0, 20.0), Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+    let system = SolarSystem::create_basic_system();
+    ship.launch(&camera);
+    let heading = (camera.target - camera.eye).normalized();
+    assert!(ship.forward().dot(heading) > 0.999 && ship.speed() == 0.0);
+
+    let frame = |down: Vec<Action>| InputFrame { delta_time: 1.0 / 30.0, down, ..InputFrame::default() };
+    let start = ship.position;
+    for _ in 0..30 {
+        ship.steer(&frame(vec![Action::OrbitUp]));
+        ship.update(&system, 1.0 / 30.0);
+    }
+    assert!(ship.speed() > 3.0 && (ship.position - start).normalized().dot(heading) > 0.999);
+    // Exhaust trails behind the ship
+    assert!(!ship.engine.is_empty());
+    assert!(ship.engine.particles.iter().all(|p| (p.position - ship.position).dot(ship.forward()) < 0.0));
+    // A left turn swings the nose to the left of the heading
+    let yaw = ship.yaw;
+    ship.steer(&frame(vec![Action::OrbitLeft]));
+    ship.update(&system, 1.0 / 30.0);
+    assert!(ship.yaw > yaw);
+    let left = Vector3::new(0.0, 1.0, 0.0).cross(heading);
+    assert!(ship.forward().dot(left) > 0.0);
+
+    // The chase camera settles behind and above the ship
+    let mut chase = camera.clone();
+    for _ in 0..120 {
+        ship.chase(&mut chase, 1.0 / 30.0);
+    }
+    let behind = chase.eye - ship.position;
+    assert!(behind.dot(ship.forward()) < 0.0 && behind.y > 0.0);
+
+    // The autopilot stops short of the first planet, facing it, and reports it once
+    let target = ship.cycle_autopilot(&system).expect("the basic system has planets");
+    assert_eq!(system.objects[target].object_type, CelestialType::Planet);
+    let arrivals = (0..900).filter(|_| ship.update(&system, 1.0 / 30.0)).count();
+    assert_eq!(arrivals, 1);
+    let planet = &system.objects[target];
+    let gap = ship.position.distance_to(planet.position);
+    assert!((gap - (planet.scale * SPHERE_RADIUS * 3.0 + 1.0)).abs() < 0.6, "stopped {gap} from the planet");
+    assert!(ship.forward().dot((planet.position - ship.position).normalized()) > 0.95);
+    // Piloting by hand takes over again
+    ship.steer(&frame(vec![Action::OrbitDown]));
+    assert_eq!(ship.autopilot, None);
+
+    // Drawn as one more body of a copy of the scene
+    ship.launch(&camera);
+    let scene = ship.scene_with(&system);
+    assert_eq!(scene.objects.len(), system.objects.len() + 1);
+    assert_eq!(scene.objects.last().unwrap().name, "Ship");
+    let mesh = sphere();
+    let mut renderer = Renderer::new(160, 120, &mesh);
+    let without = renderer.render_frame(&system, &camera, 0.0);
+    let with = renderer.render_frame(&scene, &camera, 0.0);
+    let changed = without.pixels.chunks(4).zip(with.pixels.chunks(4)).filter(|(a, b)| a != b).count();
+    assert!(changed > 20, "{changed} pixels show the ship");
+}
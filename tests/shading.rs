@@ -0,0 +1,504 @@
+// shading.rs
+// Integration tests for planet shaders, materials and lighting
+
+mod common;
+
+use common::{screen_vertex, shading_uniforms, surface_fragment, sphere};
+use lab5::atmosphere::Atmosphere;
+use lab5::aurora::Aurora;
+use lab5::camera::Camera;
+use lab5::fragment::Fragment;
+use lab5::light::{main_light_direction, Light};
+use lab5::matrix::{camera_position, create_model_matrix, multiply_matrix_vector4, create_view_matrix};
+use lab5::material::{Material, NoiseKind};
+use lab5::noise::Noise;
+use lab5::noise_cache::NoiseCache;
+use lab5::renderer::{ImageBuffer, Renderer};
+use lab5::shader_registry::ShaderRegistry;
+use lab5::surface_bake::{SurfaceCache, CUBE_SIZE};
+use lab5::shader_system::{apply_shader, eruption_glow, ShaderType};
+use lab5::solar_system::SolarSystem;
+use lab5::triangle::{rasterize, Lighting, PixelRect};
+use lab5::volcano::{Eruption, MAX_ERUPTIONS};
+use lab5::{shade_fragments, Uniforms};
+use raylib::math::{Vector3, Vector4};
+use std::f32::consts::PI;
+
+#[test]
+fn glossy_materials_reflect_a_highlight_toward_the_camera() {
+    let eye = Vector3::new(35.0, 25.0, 200.0);
+    let view = create_view_matrix(eye, Vector3::new(35.0, 25.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+    let recovered = camera_position(&view);
+    assert!((recovered - eye).length() < 1e-3, "camera at {recovered:?}");
+
+    let (v1, v2, v3) = (screen_vertex(10.0, 10.0, 0.5), screen_vertex(60.0, 12.0, 0.5), screen_vertex(30.0, 50.0, 0.5));
+    let lights = [Light::new(eye, Vector3::one(), 1.0, 0.0)];
+    let highlight = |material: Material| {
+        let lighting = Lighting { lights: &lights, occluders: &[], material, eye, bump: None, atmosphere: None, vertex_colors: false };
+        let mut fragments = Vec::new();
+        rasterize(&v1, &v2, &v3, &lighting, PixelRect::UNBOUNDED, &mut fragments);
+        fragments.iter().map(|f| f.specular.x).fold(0.0, f32::max)
+    };
+
+    assert_eq!(highlight(Material::MATTE), 0.0);
+    assert!(highlight(Material::glossy(0.8, 32.0)) > 0.5);
+}
+
+#[test]
+fn new_planet_looks_are_materials_over_the_same_shader() {
+    let uniforms = shading_uniforms(Noise::new(2));
+    let fragments: Vec<Fragment> = (0..200)
+        .map(|i| surface_fragment(Vector3::new((i as f32 * 0.7).cos(), (i as f32 * 0.013) - 1.3, (i as f32 * 0.7).sin()).normalized() * 0.5))
+        .collect();
+
+    // The dispatcher draws each shader's built-in look, and only the star glows
+    for shader in [ShaderType::Star, ShaderType::Rocky, ShaderType::GasGiant, ShaderType::Lava, ShaderType::IceWorld, ShaderType::CloudPlanet, ShaderType::Rings] {
+        let surface = shader.material();
+        assert_eq!(surface.shader, shader);
+        assert_eq!(surface.emissive, shader == ShaderType::Star);
+        for fragment in &fragments {
+            assert_eq!(surface.shade(fragment, &uniforms), apply_shader(fragment, &uniforms, &surface));
+        }
+    }
+    // The baked layer and the relief follow the material's noise
+    assert_eq!(Material::ROCKY.baked_layer().map(|layer| layer.noise), Some(Material::ROCKY.noise));
+    assert_eq!(Material::ICE.relief().map(|relief| relief.noise), Some(Material::ICE.noise));
+
+    // A blue-grey moon: the rocky shader with another palette and coarser terrain
+    let mut moon = Material::ROCKY;
+    moon.palette[..3].copy_from_slice(&[Vector3::new(0.2, 0.22, 0.26), Vector3::new(0.45, 0.48, 0.55), Vector3::new(0.75, 0.78, 0.85)]);
+    moon.noise.scale = Vector3::new(1.5, 1.5, 1.5);
+    moon.noise.kind = NoiseKind::FbmSimplex;
+    let (mut bluer, mut changed) = (0, 0);
+    for fragment in &fragments {
+        let (rock, grey) = (Material::ROCKY.shade(fragment, &uniforms), moon.shade(fragment, &uniforms));
+        bluer += (grey.z > grey.x && rock.z < rock.x) as usize;
+        changed += (grey != rock) as usize;
+    }
+    assert_eq!(changed, fragments.len());
+    assert!(bluer > fragments.len() * 9 / 10, "{bluer} bluer fragments");
+
+    // Its terrain is baked from its own noise, apart from the rocky planets'
+    assert_eq!(moon.baked_layer().map(|layer| layer.noise), Some(moon.noise));
+    assert_ne!(moon.key(), Material::ROCKY.key());
+    assert_eq!(moon.key(), moon.key());
+}
+
+#[test]
+fn star_luminosity_and_falloff_shape_the_lights() {
+    let mut system = SolarSystem::parse_scene(
+        "[[body]]\nname = \"sun\"\ntype = \"star\"\nluminosity = 2.0\nfalloff = 0.01\n",
+    )
+    .expect("Failed to parse scene");
+    system.objects[0].position = Vector3::new(4.0, 0.0, 0.0);
+
+    let lights = system.lights(1.0);
+    assert_eq!(lights.len(), 1);
+    assert_eq!(lights[0].position, Vector3::new(4.0, 0.0, 0.0));
+    assert_eq!(lights[0].intensity, 2.0);
+    assert!(lights[0].attenuation(20.0) < lights[0].attenuation(5.0));
+    assert_eq!(lights[0].attenuation(0.0), 1.0);
+}
+
+#[test]
+fn moons_eclipse_their_planet_unless_shadows_are_off() {
+    let mesh = sphere();
+    let mut renderer = Renderer::new(160, 120, &mesh);
+    let mut system = SolarSystem::parse_scene(
+        "[[body]]\nname = \"sun\"\ntype = \"star\"\n\n\
+         [[body]]\nname = \"planet\"\nparent = \"sun\"\n\n\
+         [[body]]\nname = \"moon\"\ntype = \"moon\"\nparent = \"planet\"\nscale = 0.6\n",
+    )
+    .expect("Failed to parse scene");
+    // Moon right between the star and the planet's lit side
+    system.objects[1].position = Vector3::new(12.0, 0.0, 0.0);
+    system.objects[2].position = Vector3::new(8.0, 0.0, 0.0);
+    let camera = Camera::new(Vector3::new(7.0, 0.0, 4.0), Vector3::new(12.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+
+    let mut brightness = |shadows: bool| {
+        renderer.shadows = shadows;
+        let frame = renderer.render_frame(&system, &camera, 0.0);
+        frame.rgb().iter().map(|&c| c as u64).sum::<u64>()
+    };
+
+    assert!(brightness(true) < brightness(false));
+}
+
+#[test]
+fn normal_mapping_tilts_normals_along_the_terrain() {
+    let noise = Noise::new(7);
+    let relief = Material::ROCKY.relief().expect("rocky planets have relief");
+    assert!(Material::GAS_GIANT.relief().is_none() && Material::STAR.relief().is_none());
+
+    // Perturbed normals stay unit length and close to the surface normal, and follow the
+    // body's rotation
+    let position = Vector3::new(0.3, 0.8, 0.5).normalized();
+    let identity = create_model_matrix(Vector3::zero(), 1.0, Vector3::zero());
+    let bumped = relief.perturb(position, position, &noise, 0.0, &identity);
+    assert!((bumped.length() - 1.0).abs() < 1e-4);
+    assert!(bumped.dot(position) > 0.8 && bumped.dot(position) < 0.99999, "the terrain tilts the normal");
+    let turned = create_model_matrix(Vector3::new(5.0, 0.0, 0.0), 2.0, Vector3::new(0.0, PI / 2.0, 0.0));
+    let rotate = |v: Vector3| Vector3::new(turned.m0 * v.x + turned.m8 * v.z, 2.0 * v.y, turned.m2 * v.x + turned.m10 * v.z) / 2.0;
+    let rotated = relief.perturb(rotate(position), position, &noise, 0.0, &turned);
+    assert!(rotated.distance_to(rotate(bumped)) < 1e-4);
+
+    // Switching it off gives back the smooth shading
+    let mesh = sphere();
+    let system = SolarSystem::create_basic_system();
+    let camera = Camera::new(Vector3::new(6.5, 0.5, 1.0), Vector3::new(8.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+    let mut renderer = Renderer::new(120, 90, &mesh);
+    let bumped = renderer.render_frame(&system, &camera, 0.0);
+    renderer.normal_mapping = false;
+    let smooth = renderer.render_frame(&system, &camera, 0.0);
+    assert!(bumped.pixels != smooth.pixels);
+}
+
+#[test]
+fn atmosphere_glows_along_the_limb_with_the_sun_angle() {
+    let atmosphere = Atmosphere::default();
+    let sun = [Light::new(Vector3::new(100.0, 0.0, 0.0), Vector3::one(), 1.0, 0.0)];
+    let eye = Vector3::new(0.0, 0.0, 10.0);
+
+    // Nothing where the eye looks straight down, most at the limb
+    let facing = Vector3::new(0.0, 0.0, 1.0);
+    assert_eq!(atmosphere.glow(facing, facing, eye, &sun), Vector3::zero());
+    let noon = Vector3::new(1.0, 0.0, 0.0);
+    let day = atmosphere.glow(noon, noon, eye, &sun);
+    assert!(day.z > day.x, "the day side scatters blue");
+
+    // Orange at the terminator, dark on the night side
+    let dusk = Vector3::new(0.05, 1.0, 0.0).normalized();
+    let sunset = atmosphere.glow(dusk, dusk, eye, &sun);
+    assert!(sunset.x > sunset.z && sunset.x > 0.0, "the terminator glows orange");
+    let night = Vector3::new(-1.0, 0.0, 0.0);
+    assert_eq!(atmosphere.glow(night, night, eye, &sun), Vector3::zero());
+
+    // Any shader can take one; bodies without air are untouched
+    let mesh = sphere();
+    let mut system = SolarSystem::create_basic_system();
+    assert!(system.objects.iter().any(|o| o.atmosphere.is_some()));
+    let camera = Camera::new(Vector3::new(6.5, 0.5, 1.0), Vector3::new(8.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+    let mut renderer = Renderer::new(120, 90, &mesh);
+    let bare = renderer.render_frame(&system, &camera, 0.0);
+    system.objects[2].atmosphere = Some(Atmosphere::tinted(Vector3::new(1.0, 0.3, 0.3)));
+    assert!(renderer.render_frame(&system, &camera, 0.0).pixels != bare.pixels);
+
+    let scene = SolarSystem::parse_scene("[[body]]\nname = \"sun\"\ntype = \"star\"\n\n[[body]]\nparent = \"sun\"\natmosphere = [0.5, 1.0, 0.5]\n")
+        .expect("Failed to parse scene");
+    assert_eq!(scene.objects[1].atmosphere.map(|a| a.day), Some(Vector3::new(0.5, 1.0, 0.5)));
+}
+
+#[test]
+fn registered_shaders_can_be_used_from_scene_files() {
+    let magenta = Vector3::new(1.0, 0.0, 1.0);
+    let shader = ShaderRegistry::register("test_flat_magenta", move |_fragment: &Fragment, _uniforms: &Uniforms| magenta)
+        .expect("custom names can be registered");
+    assert_eq!(ShaderRegistry::lookup("test_flat_magenta"), Some(shader));
+    assert!(ShaderRegistry::names().iter().any(|name| name == "test_flat_magenta"));
+    assert!(ShaderRegistry::register("rocky", |fragment: &Fragment, _: &Uniforms| fragment.color).is_none(), "built-ins stay");
+
+    let scene = SolarSystem::parse_scene(
+        "[[body]]\nname = \"sun\"\ntype = \"star\"\n\n[[body]]\nname = \"odd\"\nparent = \"sun\"\nshader = \"test_flat_magenta\"\norbit_radius = 5.0\norbit_angle = 0.0\n",
+    )
+    .expect("Failed to parse scene");
+    assert_eq!(scene.objects[1].material.shader, shader);
+    assert!(SolarSystem::parse_scene("[[body]]\nname = \"sun\"\ntype = \"star\"\n\n[[body]]\nparent = \"sun\"\nshader = \"test_unregistered\"\n").is_err());
+
+    // The body is drawn with the registered function
+    let mesh = sphere();
+    let camera = Camera::new(Vector3::new(5.0, 0.0, 3.0), Vector3::new(5.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+    let mut renderer = Renderer::new(80, 60, &mesh);
+    assert_eq!(renderer.render_frame(&scene, &camera, 0.0).pixel(40, 30), [255, 0, 255, 255]);
+}
+
+#[test]
+fn star_coronas_glow_past_the_silhouette_behind_closer_bodies() {
+    let system = SolarSystem::parse_scene("[[body]]\nname = \"sun\"\ntype = \"star\"\nscale = 1.0\n").expect("Failed to parse scene");
+    let mesh = sphere();
+    let mut renderer = Renderer::new(160, 120, &mesh);
+    let camera = Camera::new(Vector3::new(0.0, 0.0, 12.0), Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+    let brightness = |frame: &ImageBuffer, x: i32| -> u32 {
+        frame.pixels[(60 * 160 + x) as usize * 4..][..3].iter().map(|&c| c as u32).sum()
+    };
+
+    let glowing = renderer.render_frame(&system, &camera, 0.0);
+    renderer.coronas = false;
+    let bare = renderer.render_frame(&system, &camera, 0.0);
+    // The disk itself is untouched; just past its edge the glow brightens the sky, and
+    // it has faded out well before the frame's edge
+    let edge = (80..160).find(|&x| brightness(&bare, x) == brightness(&bare, 159)).expect("star fills the frame");
+    assert_eq!(brightness(&glowing, 80), brightness(&bare, 80));
+    assert!(brightness(&glowing, edge + 1) > brightness(&bare, edge + 1));
+    assert_eq!(brightness(&glowing, 159), brightness(&bare, 159));
+}
+
+#[test]
+fn cloud_planets_light_their_cities_on_the_night_side() {
+    let sun = [Light::new(Vector3::new(50.0, 0.0, 0.0), Vector3::one(), 1.0, 200.0)];
+    let direction = main_light_direction(&sun, Vector3::zero());
+    assert!((direction - Vector3::new(1.0, 0.0, 0.0)).length() < 1e-6);
+    assert_eq!(main_light_direction(&[], Vector3::zero()), Vector3::zero());
+
+    let mut uniforms = shading_uniforms(Noise::new(3));
+    let normals: Vec<Vector3> = (0..4000)
+        .map(|i| {
+            let (theta, phi) = (i as f32 * 0.37, i as f32 * 0.71);
+            Vector3::new(theta.cos() * phi.sin(), phi.cos(), theta.sin() * phi.sin())
+        })
+        .collect();
+    let shade = |uniforms: &Uniforms| -> Vec<Vector3> {
+        normals
+            .iter()
+            .map(|&normal| {
+                let fragment = Fragment::new_with_world_pos(0.0, 0.0, Vector3::new(0.3, 0.3, 0.3), Vector3::zero(), Vector3::zero(), 0.5, normal)
+                    .with_normal(normal);
+                apply_shader(&fragment, uniforms, &Material::CLOUD)
+            })
+            .collect()
+    };
+    // Without a light direction (baked surfaces) there is no night side
+    let unlit = shade(&uniforms);
+    uniforms.light_direction = direction;
+    let lit = shade(&uniforms);
+
+    let mut cities = 0;
+    for ((normal, before), after) in normals.iter().zip(&unlit).zip(&lit) {
+        if normal.dot(direction) > 0.1 {
+            assert_eq!(before, after, "the day side keeps its colors");
+        } else if normal.dot(direction) < -0.2 {
+            assert!(after.x >= before.x && after.y >= before.y && after.z >= before.z);
+            if after.x > before.x + 0.05 {
+                cities += 1;
+            }
+        }
+    }
+    assert!(cities > 0 && cities < normals.len() / 4, "{cities} city fragments");
+}
+
+#[test]
+fn cloud_planets_have_polar_caps_and_climate_bands_tilted_with_the_axis() {
+    let uniforms = shading_uniforms(Noise::new(5));
+    // Share of a band of latitude (sine of it) whose color passes `test`
+    let share = |latitude: f32, test: &dyn Fn(Vector3) -> bool| {
+        let hits = (0..400)
+            .filter(|&i| {
+                let longitude = i as f32 * 0.0157;
+                let ring = (1.0 - latitude * latitude).sqrt();
+                let pos = Vector3::new(longitude.cos() * ring, latitude, longitude.sin() * ring);
+                let fragment = surface_fragment(pos);
+                test(apply_shader(&fragment, &uniforms, &Material::CLOUD))
+            })
+            .count();
+        hits as f32 / 400.0
+    };
+    let white = |c: Vector3| c.x.min(c.y).min(c.z) > c.x.max(c.y).max(c.z) * 0.8;
+    let sandy = |c: Vector3| c.x > c.y;
+    assert!(share(0.97, &white) > 0.9, "ice caps at the poles");
+    assert!(share(-0.97, &white) > 0.9);
+    assert!(share(0.0, &white) < share(0.97, &white) - 0.4);
+    assert!(share(0.36, &sandy) > share(0.0, &sandy) + 0.1, "a desert belt off the equator");
+
+    // The tilt leans the spin axis (and the bands with it) away from world Y
+    let system = SolarSystem::create_basic_system();
+    let earth = system.objects.iter().find(|o| o.name == "Earth").expect("the basic system has an Earth");
+    let axis = multiply_matrix_vector4(&earth.model_matrix(), &Vector4::new(0.0, 1.0, 0.0, 0.0));
+    let axis = Vector3::new(axis.x, axis.y, axis.z).normalized();
+    assert!((axis.dot(Vector3::new(0.0, 1.0, 0.0)).acos() - earth.axial_tilt).abs() < 1e-3);
+    assert!(earth.axial_tilt > 0.3);
+
+    let scene = SolarSystem::parse_scene("[[body]]\nname = \"sun\"\ntype = \"star\"\n\n[[body]]\nname = \"terra\"\nparent = \"sun\"\nshader = \"cloud\"\naxial_tilt = 0.5\n").expect("Failed to parse scene");
+    assert_eq!(scene.objects[1].axial_tilt, 0.5);
+}
+
+#[test]
+fn auroras_circle_the_poles_and_drift_over_any_shader() {
+    let mut uniforms = shading_uniforms(Noise::new(9));
+    let aurora = Aurora::default();
+    // A ring of fragments at one latitude (sine of it), facing straight out
+    let ring = |latitude: f32| -> Vec<Fragment> {
+        (0..200)
+            .map(|i| {
+                let longitude = i as f32 * 0.0314;
+                let around = (1.0 - latitude * latitude).sqrt();
+                let pos = Vector3::new(longitude.cos() * around, latitude, longitude.sin() * around);
+                surface_fragment(pos).with_normal(pos)
+            })
+            .collect()
+    };
+    let total = |fragments: &[Fragment], uniforms: &Uniforms| -> f32 {
+        fragments.iter().map(|f| { let g = aurora.glow(f, uniforms); g.x + g.y + g.z }).sum()
+    };
+    let oval = ring(aurora.latitude);
+    assert_eq!(total(&ring(0.0), &uniforms), 0.0, "no aurora at the equator");
+    assert!(total(&oval, &uniforms) > 0.0);
+    assert!(total(&ring(-aurora.latitude), &uniforms) > 0.0, "both poles");
+    let glows: Vec<Vector3> = oval.iter().map(|f| aurora.glow(f, &uniforms)).collect();
+    uniforms.time = 5.0;
+    assert!(oval.iter().zip(&glows).any(|(f, &g)| aurora.glow(f, &uniforms) != g), "the curtains drift with time");
+
+    // Faint on the day side of a lit body
+    let dark = total(&oval, &uniforms);
+    uniforms.light_direction = Vector3::new(1.0, 0.0, 0.0);
+    let mean = |sunward: f32| {
+        let side: Vec<&Fragment> = oval.iter().filter(|f| f.normal.x * sunward > 0.3).collect();
+        side.iter().map(|f| { let g = aurora.glow(f, &uniforms); g.x + g.y + g.z }).sum::<f32>() / side.len() as f32
+    };
+    assert!(mean(1.0) * 2.0 < mean(-1.0), "day {} night {}", mean(1.0), mean(-1.0));
+    assert!(total(&oval, &uniforms) < dark);
+
+    // Added over the body's own shader in the fragment stage
+    let system = SolarSystem::create_basic_system();
+    let earth = system.objects.iter().find(|o| o.name == "Earth").expect("the basic system has an Earth");
+    assert!(earth.aurora.is_some());
+    let mut plain = earth.clone();
+    plain.aurora = None;
+    let (mut with, mut without) = (Vec::new(), Vec::new());
+    shade_fragments(&oval, &uniforms, earth, &mut with);
+    shade_fragments(&oval, &uniforms, &plain, &mut without);
+    for ((a, b), fragment) in with.iter().zip(&without).zip(&oval) {
+        assert_eq!(a.color, b.color + aurora.glow(fragment, &uniforms));
+    }
+
+    let scene = SolarSystem::parse_scene("[[body]]\nname = \"sun\"\ntype = \"star\"\n\n[[body]]\nname = \"frost\"\nparent = \"sun\"\nshader = \"ice\"\naurora = [1.0, 0.2, 0.6]\n").expect("Failed to parse scene");
+    assert_eq!(scene.objects[1].aurora.map(|a| a.color), Some(Vector3::new(1.0, 0.2, 0.6)));
+}
+
+#[test]
+fn lava_planets_erupt_glow_and_cool_down() {
+    let scene = "[[body]]\nname = \"sun\"\ntype = \"star\"\n\n[[body]]\nname = \"hell\"\nparent = \"sun\"\nshader = \"lava\"\norbit_radius = 8.0\n\n[[body]]\nname = \"rock\"\nparent = \"sun\"\nshader = \"rocky\"\norbit_radius = 12.0\n";
+    let mut system = SolarSystem::parse_scene(scene).expect("Failed to parse scene");
+    let mut twin = SolarSystem::parse_scene(scene).expect("Failed to parse scene");
+    assert!(system.objects[1].volcanoes.is_some());
+    assert!(system.objects[2].volcanoes.is_none(), "only lava planets erupt");
+
+    let (mut erupted, mut plume) = (false, false);
+    for _ in 0..150 {
+        system.update(0.1);
+        twin.update(0.1);
+        let eruptions = system.objects[1].eruptions();
+        assert!(eruptions.len() <= MAX_ERUPTIONS);
+        assert!(eruptions.iter().all(|e| (0.0..=1.0).contains(&e.heat()) && (e.site.length() - 1.0).abs() < 1e-4));
+        assert_eq!(eruptions, twin.objects[1].eruptions(), "eruptions follow the body's seed");
+        erupted |= !eruptions.is_empty();
+        plume |= !system.objects[1].volcanoes.as_ref().unwrap().plume.is_empty();
+    }
+    assert!(erupted && plume);
+
+    // Flares up, then cools down until it ends
+    let eruption = |age: f32| Eruption { site: Vector3::new(0.0, 1.0, 0.0), age, duration: 5.0 };
+    assert_eq!(eruption(0.0).heat(), 0.0);
+    assert!(eruption(0.5).heat() > 0.9);
+    assert!(eruption(3.0).heat() < eruption(1.0).heat());
+    assert_eq!(eruption(5.0).heat(), 0.0);
+
+    // The lava shader glows around a burning vent, also over a baked surface
+    let active = [eruption(0.5)];
+    let mut uniforms = shading_uniforms(Noise::new(4));
+    let (vent, far) = (surface_fragment(Vector3::new(0.0, 1.0, 0.0)), surface_fragment(Vector3::new(1.0, 0.0, 0.0)));
+    let quiet = (apply_shader(&vent, &uniforms, &Material::LAVA), apply_shader(&far, &uniforms, &Material::LAVA));
+    uniforms.eruptions = &active;
+    let vent_color = apply_shader(&vent, &uniforms, &Material::LAVA);
+    assert!(vent_color.x + vent_color.y > quiet.0.x + quiet.0.y + 1.0);
+    assert_eq!(apply_shader(&far, &uniforms, &Material::LAVA), quiet.1);
+    assert!(eruption_glow(&vent, &uniforms).y > 0.5);
+
+    // Going back in time puts them out
+    system.update(-0.1);
+    assert!(system.objects[1].eruptions().is_empty());
+    let calm = SolarSystem::parse_scene(&scene.replace("shader = \"lava\"", "shader = \"lava\"\neruptions = false")).expect("Failed to parse scene");
+    assert!(calm.objects[1].volcanoes.is_none());
+}
+
+#[test]
+fn lava_cracks_glow_through_the_night_side() {
+    let uniforms = shading_uniforms(Noise::new(4));
+    let points: Vec<Vector3> = (0..400).map(|i| Vector3::new((i as f32 * 0.37).cos(), (i as f32 * 0.011) - 2.2, (i as f32 * 0.37).sin()).normalized()).collect();
+    let mut glowing = 0;
+    for &pos in &points {
+        let day = surface_fragment(pos);
+        let night = Fragment::new_with_world_pos(0.0, 0.0, Vector3::zero(), Vector3::zero(), Vector3::zero(), 0.5, pos);
+
+        // The emission ignores the light, and is all that is left of the surface at night
+        let emission = Material::LAVA.emission(&day, &uniforms);
+        assert_eq!(Material::LAVA.emission(&night, &uniforms), emission);
+        assert_eq!(Material::LAVA.shade(&night, &uniforms), emission);
+        assert_eq!(Material::LAVA.shade(&day, &uniforms), Material::LAVA.surface(&day, &uniforms) + emission);
+        glowing += (emission.x > 0.5) as usize;
+    }
+    assert!(glowing > 0 && glowing < points.len());
+    // Only lava gives off light of its own
+    assert_eq!(Material::ROCKY.emission(&surface_fragment(points[0]), &uniforms), Vector3::zero());
+}
+
+#[test]
+fn gas_giants_keep_a_great_storm_drifting_around_its_band() {
+    let scene = "[[body]]\nname = \"sun\"\ntype = \"star\"\n\n[[body]]\nname = \"giant\"\nparent = \"sun\"\nshader = \"gas_giant\"\norbit_radius = 8.0\n\n[[body]]\nname = \"rock\"\nparent = \"sun\"\nshader = \"rocky\"\norbit_radius = 12.0\n";
+    let mut system = SolarSystem::parse_scene(scene).expect("Failed to parse scene");
+    assert!(system.objects[2].vortex.is_none());
+    let start = system.objects[1].vortex.expect("gas giants have a great storm");
+
+    // The center drifts around its band and comes back when time runs backwards
+    system.update(10.0);
+    let moved = system.objects[1].vortex.unwrap();
+    assert!((moved.longitude - start.longitude - start.drift * 10.0).abs() < 1e-4);
+    assert_eq!(moved.latitude, start.latitude);
+    system.update(-10.0);
+    assert!((system.objects[1].vortex.unwrap().longitude - start.longitude).abs() < 1e-4);
+
+    // The swirl turns points about the center, most near it and not at all outside
+    let center = moved.center();
+    assert!((moved.coverage(center) - 1.0).abs() < 1e-5);
+    let far = -center;
+    assert_eq!(moved.swirl(far), (far, 0.0));
+    let near = (center + Vector3::new(0.0, 0.05, 0.0)).normalized() * 0.5;
+    let (turned, inside) = moved.swirl(near);
+    assert!(inside > 0.5 && (turned.length() - 0.5).abs() < 1e-5);
+    assert!((turned.dot(center) - near.dot(center)).abs() < 1e-5, "turned about the center");
+    assert!(turned.distance_to(near) > 0.01);
+
+    // Drawn at its current center: a red core there, the bands untouched far away
+    let mut uniforms = shading_uniforms(Noise::new(6));
+    let at = |pos: Vector3| surface_fragment(pos * 0.5);
+    let calm = (apply_shader(&at(center), &uniforms, &Material::GAS_GIANT), apply_shader(&at(far), &uniforms, &Material::GAS_GIANT));
+    uniforms.vortex = Some(moved);
+    let core = apply_shader(&at(center), &uniforms, &Material::GAS_GIANT);
+    assert!(core.x > core.y * 1.4 && core != calm.0, "core {core:?}");
+    assert_eq!(apply_shader(&at(far), &uniforms, &Material::GAS_GIANT), calm.1);
+
+    let clear = SolarSystem::parse_scene(&scene.replace("shader = \"gas_giant\"", "shader = \"gas_giant\"\nstorm = false")).expect("Failed to parse scene");
+    assert!(clear.objects[1].vortex.is_none());
+}
+
+#[test]
+fn baked_surfaces_match_the_live_shader_and_rebake_animated_ones() {
+    let mesh = sphere();
+    let mut renderer = Renderer::new(120, 90, &mesh);
+    renderer.coronas = false;
+    let system = SolarSystem::parse_scene("[[body]]\nname = \"rock\"\ntype = \"star\"\nshader = \"rocky\"\n").expect("Failed to parse scene");
+    let camera = Camera::new(Vector3::new(0.0, 0.0, 6.0), Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+
+    // Lit from the cube map the planet looks like its shader, a texel's blur apart
+    let live = renderer.render_frame(&system, &camera, 0.0);
+    renderer.surface_cache.enabled = true;
+    let baked = renderer.render_frame(&system, &camera, 0.0);
+    let difference = live.pixels.iter().zip(&baked.pixels).map(|(&a, &b)| (a as f32 - b as f32).abs()).sum::<f32>() / live.pixels.len() as f32;
+    assert!(difference < 4.0, "mean difference {difference}");
+    assert!(renderer.surface_cache.lookup(system.objects[0].seed, &Material::ROCKY, 0.0).is_some());
+    assert_ne!(live.pixels, baked.pixels, "the shader ran instead of the cube map");
+
+    // Static surfaces are kept forever, animated ones only for their time step
+    let noise_cache = NoiseCache::new(64, 32, 1.0);
+    let mut cache = SurfaceCache::new(CUBE_SIZE / 8, 1.0);
+    cache.prepare(3, &Material::ROCKY, 0.0, &noise_cache);
+    assert!(cache.lookup(3, &Material::ROCKY, 0.0).is_none(), "baked while disabled");
+    cache.enabled = true;
+    for shader in [ShaderType::Rocky, ShaderType::Lava, ShaderType::Star] {
+        cache.prepare(3, &shader.material(), 0.0, &noise_cache);
+    }
+    assert!(cache.lookup(3, &Material::ROCKY, 100.0).is_some());
+    assert!(cache.lookup(3, &Material::LAVA, 0.2).is_some());
+    assert!(cache.lookup(3, &Material::LAVA, 0.7).is_none());
+    assert!(cache.lookup(3, &Material::STAR, 0.0).is_none());
+    cache.clear();
+    assert!(cache.lookup(3, &Material::ROCKY, 0.0).is_none());
+}